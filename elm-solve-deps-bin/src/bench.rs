@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `bench-registry` command, solving every package version of the registry
+//! and comparing the results with a baseline run.
+
+use anyhow::Context;
+use std::path::Path;
+
+use elm_solve_deps::bench::{self, BenchComparison, BenchRecord, BenchSummary};
+use elm_solve_deps::pkg_version::PkgVersion;
+use elm_solve_deps::project_config::Pkg;
+
+/// Solve every package version, print the records in CSV or JSON,
+/// and a summary, or a comparison with the baseline records, to stderr.
+pub fn run(
+    format: &str,
+    authors: &[&str],
+    baseline: Option<&str>,
+    offline: bool,
+) -> anyhow::Result<()> {
+    if format != "csv" && format != "json" {
+        anyhow::bail!("Unknown bench format: {}, expected csv or json", format);
+    }
+    let baseline = match baseline {
+        Some(path) => Some(
+            load_records(Path::new(path))
+                .context(format!("Failed to load the baseline records in {}", path))?,
+        ),
+        None => None,
+    };
+    let versions_cache = crate::load_versions_cache(offline)?;
+    let select = |pkg: &Pkg| authors.is_empty() || authors.contains(&pkg.author.as_str());
+    let load_config = |pkg_version: &PkgVersion| {
+        if offline {
+            crate::load_local_config(pkg_version).ok()
+        } else {
+            crate::load_package_config(pkg_version).ok()
+        }
+    };
+    let records = bench::bench_registry(&versions_cache, select, load_config);
+    if format == "csv" {
+        let mut writer = csv::Writer::from_writer(std::io::stdout());
+        for record in &records {
+            writer.serialize(record)?;
+        }
+        writer.flush()?;
+    } else {
+        println!("{}", serde_json::to_string_pretty(&records)?);
+    }
+    match baseline {
+        Some(baseline) => eprintln!("{}", BenchComparison::between(&baseline, &records)),
+        None => eprintln!("{}", BenchSummary::of(&records)),
+    }
+    Ok(())
+}
+
+/// Load records previously printed by this command,
+/// in CSV if the file has a `.csv` extension, and in JSON otherwise.
+fn load_records(path: &Path) -> anyhow::Result<Vec<BenchRecord>> {
+    if path.extension().is_some_and(|ext| ext == "csv") {
+        let mut reader = csv::Reader::from_path(path)?;
+        let records: Result<Vec<BenchRecord>, _> = reader.deserialize().collect();
+        Ok(records?)
+    } else {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
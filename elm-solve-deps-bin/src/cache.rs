@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `cache` command, importing and exporting the versions cache, saving it as a `registry.dat`,
+//! priming the caches from a directory of package configs, forgetting package versions known to be missing on the package server,
+//! removing memoized solutions, trusting the current identity of the package server,
+//! compressing the cached package configs and versions caches,
+//! and packing the cached package configs into a single file.
+
+use anyhow::Context;
+use std::path::Path;
+use std::str::FromStr;
+
+use elm_solve_deps::compression::{self, Compression};
+use elm_solve_deps::interop::{self, CacheFormat};
+use elm_solve_deps::missing::MissingCache;
+use elm_solve_deps::nix;
+use elm_solve_deps::packed;
+use elm_solve_deps::pkg_version::Cache;
+use elm_solve_deps::project_config::Pkg;
+use elm_solve_deps::registry_pin::{PinnedRegistries, RegistryIdentity};
+use elm_solve_deps::solve_memo::SolveMemo;
+
+/// Run `cache import FILE`, `cache export FILE`, `cache snapshot FILE`, `cache prime DIR`
+/// `cache forget-missing [PACKAGE]`, `cache clear-solutions`, `cache trust-registry`
+/// `cache compact [gzip|none]` or `cache pack`.
+/// Only `cache trust-registry` makes requests to the package server, unless `offline`.
+pub fn run(positional: &[&str], format: CacheFormat, offline: bool) -> anyhow::Result<()> {
+    let elm_home = crate::elm_home();
+    match positional {
+        ["import", path] => {
+            let imported = interop::import(path, format)
+                .context(format!("Failed to import {} as {}", path, format))?;
+            let mut versions_cache = Cache::load(&elm_home).unwrap_or_default();
+            let added = interop::merge(&mut versions_cache, imported);
+            versions_cache.save(&elm_home)?;
+            eprintln!("Imported {} new package versions from {}", added, path);
+        }
+        ["export", path] => {
+            let versions_cache =
+                Cache::load(&elm_home).context("Failed to load the versions cache")?;
+            interop::export(&versions_cache, path, format)
+                .context(format!("Failed to export the versions cache into {}", path))?;
+            eprintln!("Exported the versions cache into {}", path);
+        }
+        ["snapshot", path] => {
+            let versions_cache =
+                Cache::load(&elm_home).context("Failed to load the versions cache")?;
+            std::fs::write(path, nix::registry_dat(&versions_cache))
+                .context(format!("Failed to write {}", path))?;
+            eprintln!(
+                "Saved the {} package versions of the versions cache into {}",
+                versions_cache.versions_count(),
+                path
+            );
+        }
+        ["prime", dir] => {
+            let report = Cache::prime_from_dir(dir, &elm_home, |done, total| {
+                eprint!("\rPriming caches: {}/{}", done, total)
+            })
+            .context(format!("Failed to prime the caches from {}", dir))?;
+            eprintln!();
+            let (imported, skipped) = (report.imported.len(), report.rejected.len());
+            for rejected in report.rejected {
+                eprintln!("Skipped: {:#}", anyhow::Error::from(rejected));
+            }
+            eprintln!(
+                "Imported {} package configs from {}, skipped {}",
+                imported, dir, skipped
+            );
+        }
+        ["forget-missing"] => {
+            let mut missing = MissingCache::load(&elm_home).unwrap_or_default();
+            let count = missing.len();
+            missing.clear();
+            missing.save(&elm_home)?;
+            eprintln!("Forgot {} missing packages and versions", count);
+        }
+        ["forget-missing", pkg] => {
+            let pkg = Pkg::from_str(pkg)?;
+            let mut missing = MissingCache::load(&elm_home).unwrap_or_default();
+            if missing.invalidate(&pkg) {
+                missing.save(&elm_home)?;
+                eprintln!("Forgot that {} is missing", pkg);
+            } else {
+                eprintln!("{} was not known to be missing", pkg);
+            }
+        }
+        ["clear-solutions"] => {
+            let memo = SolveMemo::new(&elm_home);
+            let count =
+                (memo.clear()).context(format!("Failed to clear {}", memo.dir().display()))?;
+            eprintln!("Removed {} memoized solutions", count);
+        }
+        ["trust-registry"] => {
+            // The identity is trusted below instead of checked against the pinned one.
+            crate::REGISTRY_CHECK.call_once(|| {});
+            let versions_cache = crate::load_versions_cache(offline)?;
+            if versions_cache.cache.is_empty() {
+                anyhow::bail!(
+                    "No package version known to identify the package server, run online"
+                );
+            }
+            let identity = RegistryIdentity::of_versions(&versions_cache, crate::clock());
+            let mut pinned = PinnedRegistries::load(&elm_home).unwrap_or_default();
+            pinned.trust(crate::remote(), identity);
+            pinned.save(&elm_home)?;
+            eprintln!("Trusted the package server at {}", crate::remote());
+        }
+        ["compact"] => compact(&elm_home, Compression::Gzip)?,
+        ["compact", compression] => compact(&elm_home, compression.parse()?)?,
+        ["pack"] => {
+            let report = packed::pack(&elm_home).context("Failed to pack the package configs")?;
+            eprintln!(
+                "Packed {} package configs ({} bytes), skipped {} corrupted ones",
+                report.packed, report.bytes, report.skipped
+            );
+        }
+        _ => anyhow::bail!(
+            "cache expects import FILE, export FILE, snapshot FILE, prime DIR, forget-missing [PACKAGE], \
+            clear-solutions, trust-registry, compact [gzip|none] or pack"
+        ),
+    }
+    Ok(())
+}
+
+/// Migrate the cached package configs and versions caches to a compression.
+fn compact(elm_home: &Path, compression: Compression) -> anyhow::Result<()> {
+    let report = compression::compact(elm_home, compression).context(format!(
+        "Failed to compress the caches with {}",
+        compression
+    ))?;
+    eprintln!(
+        "Migrated {} cache files to {} compression, from {} to {} bytes",
+        report.files, compression, report.bytes_before, report.bytes_after
+    );
+    Ok(())
+}
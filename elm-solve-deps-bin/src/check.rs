@@ -0,0 +1,78 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `check` command, checking versions against constraints without solving.
+
+use std::str::FromStr;
+
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::phased::PhasedSolution;
+use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+use elm_solve_deps::report;
+use elm_solve_deps::semver::SemVer;
+
+/// Check one constraint against a version,
+/// or all constraints against the versions recorded in the elm.json of the current directory.
+/// Fail if any constraint is not satisfied.
+pub fn run(positional: &[&str]) -> anyhow::Result<()> {
+    let single = match positional {
+        [constraint, version] => SemVer::from_str(version)
+            .ok()
+            .map(|version| (*constraint, version)),
+        _ => None,
+    };
+    if let Some((constraint, version)) = single {
+        let (pkg, constraint) = crate::parse_package_constraint(constraint)?;
+        if !constraint.allows(&version) {
+            anyhow::bail!("{} {} does not satisfy {}", pkg, version, constraint);
+        }
+        eprintln!("{} {} satisfies {}", pkg, version, constraint);
+        return Ok(());
+    }
+
+    // Batch mode over the versions recorded in elm.json.
+    if positional.is_empty() {
+        anyhow::bail!("check expects at least one constraint");
+    }
+    let constraints: Vec<(Pkg, Constraint)> = (positional.iter())
+        .map(|s| crate::parse_package_constraint(s))
+        .collect::<anyhow::Result<_>>()?;
+    let app_config = match crate::load_project_config(None)? {
+        ProjectConfig::Application(app_config) => app_config,
+        ProjectConfig::Package(_) => anyhow::bail!("check needs the elm.json of an application"),
+    };
+    let solution = PhasedSolution {
+        dependencies: app_config.dependencies,
+        test_dependencies: app_config.test_dependencies,
+    };
+    let checks = report::check_constraints(&solution.combined(), &constraints);
+    let mut unsatisfied = 0;
+    for check in &checks {
+        match check.version {
+            Some(version) if check.satisfied() => {
+                println!(
+                    "ok   {} {} satisfies {}",
+                    check.pkg, version, check.constraint
+                )
+            }
+            Some(version) => {
+                unsatisfied += 1;
+                println!(
+                    "FAIL {} {} does not satisfy {}",
+                    check.pkg, version, check.constraint
+                )
+            }
+            None => {
+                unsatisfied += 1;
+                println!("FAIL {} is not in the solution", check.pkg)
+            }
+        }
+    }
+    if unsatisfied > 0 {
+        anyhow::bail!(
+            "{} of {} constraints are not satisfied",
+            unsatisfied,
+            checks.len()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `compare` command, reporting the exposed modules changes between two package versions.
+
+use elm_solve_deps::api_diff::{ApiDiff, Bump};
+
+/// Print the differences of exposed modules on stdout.
+pub fn print_diff(diff: &ApiDiff) {
+    println!(
+        "{} {} -> {} ({})",
+        diff.pkg,
+        diff.from,
+        diff.to,
+        display_bump(diff.bump)
+    );
+    for module in &diff.added {
+        println!("  + {}", module);
+    }
+    for module in &diff.removed {
+        println!("  - {}", module);
+    }
+    if diff.added.is_empty() && diff.removed.is_empty() {
+        println!("  no change in exposed modules");
+    }
+    if diff.is_suspicious() {
+        println!(
+            "Suspicious release: these changes require a bigger version bump than {}",
+            display_bump(diff.bump)
+        );
+    }
+}
+
+fn display_bump(bump: Bump) -> &'static str {
+    match bump {
+        Bump::Major => "major",
+        Bump::Minor => "minor",
+        Bump::Patch => "patch",
+        Bump::Same => "same version",
+        Bump::Downgrade => "downgrade",
+    }
+}
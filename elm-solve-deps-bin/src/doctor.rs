@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `doctor` command, checking the integrity of `ELM_HOME`.
+
+use elm_solve_deps::doctor;
+
+/// Print the problems found in `ELM_HOME` with suggested fixes,
+/// or fix them when possible if `fix` is true.
+/// Fail if problems remain.
+pub fn run(fix: bool) -> anyhow::Result<()> {
+    let elm_home = crate::elm_home();
+    // Problems of the shared caches are found again for every version of elm.
+    let mut problems: Vec<doctor::Problem> = Vec::new();
+    for elm_version in crate::elm_versions() {
+        for problem in doctor::diagnose(&elm_home, elm_version) {
+            if !problems.contains(&problem) {
+                problems.push(problem);
+            }
+        }
+    }
+    let mut remaining = 0;
+    for problem in &problems {
+        eprintln!("Problem: {}", problem);
+        if !fix {
+            eprintln!("    Suggestion: {}", problem.suggestion());
+            remaining += 1;
+            continue;
+        }
+        match problem.fix(&elm_home) {
+            Ok(true) => eprintln!("    Fixed"),
+            Ok(false) => {
+                eprintln!("    Cannot fix it automatically: {}", problem.suggestion());
+                remaining += 1;
+            }
+            Err(err) => {
+                eprintln!("    Failed to fix it: {}", err);
+                remaining += 1;
+            }
+        }
+    }
+    match remaining {
+        0 if problems.is_empty() => eprintln!("No problem found in {}", elm_home.display()),
+        0 => eprintln!("All problems fixed"),
+        n if fix => anyhow::bail!("{} problems remain in {}", n, elm_home.display()),
+        n => anyhow::bail!("{} problems found, run with --fix to fix them", n),
+    }
+    Ok(())
+}
@@ -0,0 +1,521 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `--interactive` mode, browsing a solution in a terminal user interface.
+//!
+//! The packages of the solution are listed on the left,
+//! with details about the selected one on the right:
+//! why it is needed, its dependencies, and the other versions that could be picked
+//! without changing the rest of the solution.
+//! The result can then be written into the `elm.json` after confirmation.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::io;
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint as Size, Direction, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::pkg_version::{Cache, PkgVersion};
+use elm_solve_deps::project_config::{AppDependencies, PackageConfig, Pkg, ProjectConfig};
+use elm_solve_deps::semver::SemVer;
+
+/// Write the normal and test dependencies into elm.json.
+pub type WriteSolution<'a> =
+    &'a dyn Fn(&AppDependencies, Option<&AppDependencies>) -> anyhow::Result<()>;
+
+/// Everything the interactive mode needs from the rest of the program.
+pub struct Session<'a> {
+    /// The project being solved.
+    pub project: &'a ProjectConfig,
+    /// Known versions of all packages.
+    pub versions_cache: &'a Cache,
+    /// Whether the initial solution includes test dependencies.
+    pub use_test: bool,
+    /// Solve the project, with or without test dependencies.
+    pub solve: &'a dyn Fn(bool) -> anyhow::Result<AppDependencies>,
+    /// Load the elm.json of a package version.
+    pub load_config: &'a dyn Fn(&PkgVersion) -> Option<PackageConfig>,
+    /// Write the normal and test dependencies into elm.json, if allowed for this project.
+    pub write: Option<WriteSolution<'a>>,
+}
+
+/// Browse the solution until the user quits.
+pub fn run(session: Session, solution: AppDependencies) -> anyhow::Result<()> {
+    let mut app = App::new(session, solution);
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    let result = event_loop(&mut terminal, &mut app);
+    // Restore the terminal even if the event loop failed.
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+fn event_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut App,
+) -> anyhow::Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, app))?;
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        if app.confirm_write {
+            app.confirm_write = false;
+            if key.code == KeyCode::Char('y') {
+                app.write();
+                // Writing may print to stderr, redraw everything.
+                terminal.clear()?;
+            }
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Up | KeyCode::Char('k') => app.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => app.move_selection(1),
+            KeyCode::Left | KeyCode::Char('h') => app.change_version(-1),
+            KeyCode::Right | KeyCode::Char('l') => app.change_version(1),
+            KeyCode::Enter => app.expanded = !app.expanded,
+            KeyCode::Char('t') => {
+                app.toggle_test();
+                // Solving may print to stderr, redraw everything.
+                terminal.clear()?;
+            }
+            KeyCode::Char('w') => app.confirm_write = true,
+            _ => {}
+        }
+    }
+}
+
+struct App<'a> {
+    session: Session<'a>,
+    use_test: bool,
+    solution: AppDependencies,
+    /// Configs already loaded, or `None` if they could not be loaded.
+    configs: RefCell<BTreeMap<(Pkg, SemVer), Option<PackageConfig>>>,
+    /// Alternative versions of packages, reset every time the solution changes.
+    alternatives: RefCell<BTreeMap<Pkg, Vec<SemVer>>>,
+    selected: ListState,
+    expanded: bool,
+    confirm_write: bool,
+    message: String,
+}
+
+impl<'a> App<'a> {
+    fn new(session: Session<'a>, solution: AppDependencies) -> Self {
+        let mut selected = ListState::default();
+        selected.select(Some(0));
+        Self {
+            use_test: session.use_test,
+            session,
+            solution,
+            configs: RefCell::new(BTreeMap::new()),
+            alternatives: RefCell::new(BTreeMap::new()),
+            selected,
+            expanded: false,
+            confirm_write: false,
+            message: String::new(),
+        }
+    }
+
+    // Queries #################################################################
+
+    /// Packages of the solution, direct dependencies first.
+    fn rows(&self) -> Vec<(Pkg, SemVer, bool)> {
+        let direct = (self.solution.direct.iter()).map(|(p, v)| (p.clone(), *v, true));
+        let indirect = (self.solution.indirect.iter()).map(|(p, v)| (p.clone(), *v, false));
+        direct.chain(indirect).collect()
+    }
+
+    fn selected_pkg(&self) -> Option<Pkg> {
+        let rows = self.rows();
+        let (pkg, _, _) = rows.get(self.selected.selected()?)?;
+        Some(pkg.clone())
+    }
+
+    fn version_of(&self, pkg: &Pkg) -> Option<SemVer> {
+        (self.solution.direct.get(pkg))
+            .or_else(|| self.solution.indirect.get(pkg))
+            .copied()
+    }
+
+    fn config(&self, pkg: &Pkg, version: SemVer) -> Option<PackageConfig> {
+        let key = (pkg.clone(), version);
+        if let Some(config) = self.configs.borrow().get(&key) {
+            return config.clone();
+        }
+        let config = (self.session.load_config)(&PkgVersion {
+            author_pkg: pkg.clone(),
+            version,
+        });
+        self.configs.borrow_mut().insert(key, config.clone());
+        config
+    }
+
+    /// Dependencies of a package, at the version of the solution.
+    fn dependencies_of(&self, pkg: &Pkg) -> Vec<(Pkg, Constraint)> {
+        let config = self.version_of(pkg).and_then(|v| self.config(pkg, v));
+        config.map_or_else(Vec::new, |c| c.dependencies.into_iter().collect())
+    }
+
+    /// Packages of the solution directly depending on the given one.
+    fn dependents_of(&self, pkg: &Pkg) -> Vec<(Pkg, Constraint)> {
+        (self.rows().into_iter())
+            .filter_map(|(dependent, _, _)| {
+                let (_, constraint) =
+                    (self.dependencies_of(&dependent).into_iter()).find(|(dep, _)| dep == pkg)?;
+                Some((dependent, constraint))
+            })
+            .collect()
+    }
+
+    /// Direct dependencies of the project, with their constraint for packages.
+    fn root_dependencies(&self, use_test: bool) -> Vec<(Pkg, Option<Constraint>)> {
+        match self.session.project {
+            ProjectConfig::Application(app) => {
+                let test = app.test_dependencies.direct.keys().filter(|_| use_test);
+                (app.dependencies.direct.keys())
+                    .chain(test)
+                    .map(|p| (p.clone(), None))
+                    .collect()
+            }
+            ProjectConfig::Package(config) => {
+                let test = config.test_dependencies.iter().filter(|_| use_test);
+                (config.dependencies.iter())
+                    .chain(test)
+                    .map(|(p, c)| (p.clone(), Some(c.clone())))
+                    .collect()
+            }
+        }
+    }
+
+    /// Parent of every package reachable from the given roots, in breadth first order.
+    fn reachable(&self, roots: Vec<Pkg>) -> BTreeMap<Pkg, Option<Pkg>> {
+        let mut parents: BTreeMap<Pkg, Option<Pkg>> = BTreeMap::new();
+        let mut queue: VecDeque<Pkg> = VecDeque::new();
+        for root in roots {
+            if parents.insert(root.clone(), None).is_none() {
+                queue.push_back(root);
+            }
+        }
+        while let Some(pkg) = queue.pop_front() {
+            for (dep, _) in self.dependencies_of(&pkg) {
+                if !parents.contains_key(&dep) && self.version_of(&dep).is_some() {
+                    parents.insert(dep.clone(), Some(pkg.clone()));
+                    queue.push_back(dep);
+                }
+            }
+        }
+        parents
+    }
+
+    /// Shortest chain of dependencies from the project to the given package.
+    fn chain_to(&self, pkg: &Pkg) -> Vec<Pkg> {
+        let roots = self.root_dependencies(self.use_test);
+        let parents = self.reachable(roots.into_iter().map(|(p, _)| p).collect());
+        let mut chain = vec![pkg.clone()];
+        while let Some(Some(parent)) = parents.get(chain.last().unwrap()) {
+            chain.push(parent.clone());
+        }
+        chain.reverse();
+        chain
+    }
+
+    /// Versions of a package compatible with the rest of the solution, oldest first.
+    fn alternatives_of(&self, pkg: &Pkg) -> Vec<SemVer> {
+        if let Some(alternatives) = self.alternatives.borrow().get(pkg) {
+            return alternatives.clone();
+        }
+        let current = match self.version_of(pkg) {
+            Some(version) => version,
+            None => return Vec::new(),
+        };
+        let mut required: Vec<Constraint> = (self.dependents_of(pkg).into_iter())
+            .map(|(_, c)| c)
+            .collect();
+        (self.root_dependencies(self.use_test).into_iter())
+            .filter(|(p, _)| p == pkg)
+            .for_each(|(_, c)| required.extend(c));
+        let mut candidates: BTreeSet<SemVer> = (self.session.versions_cache.cache.get(pkg))
+            .into_iter()
+            .flatten()
+            .copied()
+            .collect();
+        candidates.insert(current);
+        let alternatives: Vec<SemVer> = (candidates.into_iter())
+            .filter(|v| required.iter().all(|c| c.0.contains(v)))
+            .filter(|v| *v == current || self.fits_solution(pkg, *v))
+            .collect();
+        (self.alternatives.borrow_mut()).insert(pkg.clone(), alternatives.clone());
+        alternatives
+    }
+
+    /// Check that the dependencies of a package version are satisfied by the solution.
+    fn fits_solution(&self, pkg: &Pkg, version: SemVer) -> bool {
+        let config = match self.config(pkg, version) {
+            Some(config) => config,
+            None => return false,
+        };
+        config.elm_version.0.contains(&SemVer::new(0, 19, 1))
+            && (config.dependencies.iter()).all(|(dep, c)| match self.version_of(dep) {
+                Some(v) => c.0.contains(&v),
+                None => false,
+            })
+    }
+
+    // Actions #################################################################
+
+    fn move_selection(&mut self, step: isize) {
+        let len = self.rows().len();
+        if len == 0 {
+            return;
+        }
+        let current = self.selected.selected().unwrap_or(0);
+        let next = (current as isize + step).clamp(0, len as isize - 1);
+        self.selected.select(Some(next as usize));
+    }
+
+    fn change_version(&mut self, step: isize) {
+        let pkg = match self.selected_pkg() {
+            Some(pkg) => pkg,
+            None => return,
+        };
+        let alternatives = self.alternatives_of(&pkg);
+        let current = self.version_of(&pkg);
+        let index = alternatives.iter().position(|v| Some(*v) == current);
+        let next = index.and_then(|i| alternatives.get((i as isize + step).max(0) as usize));
+        match next {
+            Some(version) if Some(*version) != current => {
+                for versions in [&mut self.solution.direct, &mut self.solution.indirect] {
+                    if let Some(v) = versions.get_mut(&pkg) {
+                        *v = *version;
+                    }
+                }
+                self.prune();
+                self.alternatives.borrow_mut().clear();
+                self.message = format!("Picked {}@{}", pkg, version);
+                // Keep the same package selected, even if pruning moved it.
+                let rows = self.rows();
+                let index = rows.iter().position(|(p, _, _)| p == &pkg);
+                self.selected.select(index.or(Some(0)));
+            }
+            _ => self.message = format!("No other compatible version of {}", pkg),
+        }
+    }
+
+    /// Remove indirect dependencies that are not needed anymore.
+    fn prune(&mut self) {
+        let roots = self.root_dependencies(self.use_test);
+        let needed = self.reachable(roots.into_iter().map(|(p, _)| p).collect());
+        self.solution.indirect.retain(|p, _| needed.contains_key(p));
+    }
+
+    fn toggle_test(&mut self) {
+        let use_test = !self.use_test;
+        match (self.session.solve)(use_test) {
+            Ok(solution) => {
+                self.use_test = use_test;
+                self.solution = solution;
+                self.alternatives.borrow_mut().clear();
+                self.selected.select(Some(0));
+                self.message = if use_test {
+                    "Solved with test dependencies".to_string()
+                } else {
+                    "Solved without test dependencies".to_string()
+                };
+            }
+            Err(err) => self.message = format!("Solving failed: {}", err),
+        }
+    }
+
+    fn write(&mut self) {
+        let write = match self.session.write {
+            Some(write) => write,
+            None => {
+                self.message =
+                    "Only the application in the current directory can be written".to_string();
+                return;
+            }
+        };
+        let result = if self.use_test {
+            let (normal, test) = self.split_test();
+            write(&normal, Some(&test))
+        } else {
+            write(&self.solution, None)
+        };
+        self.message = match result {
+            Ok(()) => "Updated elm.json".to_string(),
+            Err(err) => format!("{:#}", err),
+        };
+    }
+
+    /// Split a solution with test dependencies into the normal dependencies,
+    /// and the additional ones only needed by tests.
+    fn split_test(&self) -> (AppDependencies, AppDependencies) {
+        let normal_roots = self.root_dependencies(false);
+        let normal_pkgs = self.reachable(normal_roots.into_iter().map(|(p, _)| p).collect());
+        let mut normal = AppDependencies::default();
+        let mut test = AppDependencies::default();
+        for (pkg, version) in &self.solution.direct {
+            match normal_pkgs.get(pkg) {
+                Some(None) => normal.direct.insert(pkg.clone(), *version),
+                Some(Some(_)) => normal.indirect.insert(pkg.clone(), *version),
+                None => test.direct.insert(pkg.clone(), *version),
+            };
+        }
+        for (pkg, version) in &self.solution.indirect {
+            if normal_pkgs.contains_key(pkg) {
+                normal.indirect.insert(pkg.clone(), *version);
+            } else {
+                test.indirect.insert(pkg.clone(), *version);
+            }
+        }
+        (normal, test)
+    }
+
+    // Display #################################################################
+
+    fn details(&self, pkg: &Pkg) -> Vec<Line<'static>> {
+        let version = match self.version_of(pkg) {
+            Some(version) => version,
+            None => return Vec::new(),
+        };
+        let mut lines = vec![Line::from(format!("{}@{}", pkg, version))];
+        match self.config(pkg, version) {
+            Some(config) => lines.push(Line::from(config.summary)),
+            None => lines.push(Line::from("(elm.json not available)")),
+        }
+        lines.push(Line::from(""));
+
+        let chain: Vec<String> = self.chain_to(pkg).iter().map(|p| p.to_string()).collect();
+        lines.push(Line::from(format!(
+            "Needed by: project -> {}",
+            chain.join(" -> ")
+        )));
+        let dependents: Vec<String> = (self.dependents_of(pkg).into_iter())
+            .map(|(p, c)| format!("{} ({})", p, c))
+            .collect();
+        if !dependents.is_empty() {
+            lines.push(Line::from(format!(
+                "Required by: {}",
+                dependents.join(", ")
+            )));
+        }
+        lines.push(Line::from(""));
+
+        let alternatives: Vec<String> = (self.alternatives_of(pkg).into_iter())
+            .map(|v| {
+                if v == version {
+                    format!("[{}]", v)
+                } else {
+                    v.to_string()
+                }
+            })
+            .collect();
+        if alternatives.len() > 1 {
+            lines.push(Line::from(format!("Versions: {}", alternatives.join(" "))));
+        } else {
+            lines.push(Line::from("No other version compatible with the solution"));
+        }
+        lines.push(Line::from(""));
+
+        lines.push(Line::from("Dependencies:"));
+        if self.expanded {
+            self.tree_lines(pkg, 1, &mut BTreeSet::new(), &mut lines);
+        } else {
+            for (dep, constraint) in self.dependencies_of(pkg) {
+                let picked = self.version_of(&dep).map(|v| v.to_string());
+                lines.push(Line::from(format!(
+                    "  {} {} -> {}",
+                    dep,
+                    constraint,
+                    picked.unwrap_or_else(|| "---".to_string())
+                )));
+            }
+        }
+        lines
+    }
+
+    /// Transitive dependencies of a package, each one only expanded once.
+    fn tree_lines(
+        &self,
+        pkg: &Pkg,
+        depth: usize,
+        seen: &mut BTreeSet<Pkg>,
+        lines: &mut Vec<Line<'static>>,
+    ) {
+        for (dep, _) in self.dependencies_of(pkg) {
+            let picked = self.version_of(&dep).map(|v| v.to_string());
+            let first_time = seen.insert(dep.clone());
+            lines.push(Line::from(format!(
+                "{}{} {}{}",
+                "  ".repeat(depth),
+                dep,
+                picked.unwrap_or_else(|| "---".to_string()),
+                if first_time { "" } else { " (see above)" }
+            )));
+            if first_time {
+                self.tree_lines(&dep, depth + 1, seen, lines);
+            }
+        }
+    }
+
+    fn help(&self) -> String {
+        let test = if self.use_test { "on" } else { "off" };
+        format!(
+            "up/down: select  left/right: change version  enter: expand  t: test deps ({})  w: write  q: quit",
+            test
+        )
+    }
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Size::Min(1), Size::Length(2)])
+        .split(frame.size());
+    let horizontal = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Size::Percentage(40), Size::Percentage(60)])
+        .split(vertical[0]);
+
+    let items: Vec<ListItem> = (app.rows().into_iter())
+        .map(|(pkg, version, direct)| {
+            let kind = if direct { "" } else { "  (indirect)" };
+            ListItem::new(format!("{} {}{}", pkg, version, kind))
+        })
+        .collect();
+    let details = match app.selected_pkg() {
+        Some(pkg) => app.details(&pkg),
+        None => Vec::new(),
+    };
+    let status = if app.confirm_write {
+        vec![Line::from("Write the solution into elm.json? (y/n)")]
+    } else {
+        vec![Line::from(app.help()), Line::from(app.message.clone())]
+    };
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Solution"))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, horizontal[0], &mut app.selected);
+    let details = Paragraph::new(details)
+        .block(Block::default().borders(Borders::ALL).title("Details"))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(details, horizontal[1]);
+    frame.render_widget(Paragraph::new(status), vertical[1]);
+}
@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `lint` command, checking the dependency constraints of a package elm.json,
+//! and the modules its sources import from test dependencies.
+
+use anyhow::Context;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use elm_solve_deps::imports;
+use elm_solve_deps::lint::{self, LintReport, TestDependencyLeak};
+use elm_solve_deps::pkg_version::{Cache, PkgVersion};
+use elm_solve_deps::project_config::{PackageConfig, Pkg};
+use elm_solve_deps::semver::SemVer;
+
+/// Lint the package elm.json at the given path, and print the problems found in text or JSON.
+pub fn run(path: &str, format: &str, offline: bool) -> anyhow::Result<()> {
+    let elm_json_str = std::fs::read_to_string(path).context(format!("Failed to read {}", path))?;
+    let config: PackageConfig = serde_json::from_str(&elm_json_str)
+        .context(format!("{} is not the elm.json of a package", path))?;
+    let report = LintReport {
+        lints: lint::lint_package(&config),
+        test_dependency_leaks: test_dependency_leaks(path, &config, offline)?,
+    };
+    let (lints, leaks) = (&report.lints, &report.test_dependency_leaks);
+    match format {
+        "text" => {
+            for lint in lints {
+                println!("{}", lint);
+            }
+            for leak in leaks {
+                println!("{}", leak);
+            }
+        }
+        "json" => println!("{}", serde_json::to_string_pretty(&report)?),
+        _ => anyhow::bail!("Unknown lint format: {}, expected text or json", format),
+    }
+    if !lints.is_empty() || !leaks.is_empty() {
+        anyhow::bail!(
+            "Found {} problems in the dependencies of {}",
+            lints.len() + leaks.len(),
+            path
+        );
+    }
+    Ok(())
+}
+
+/// Scan the `src/` directory next to the elm.json for imports of test dependencies.
+fn test_dependency_leaks(
+    path: &str,
+    config: &PackageConfig,
+    offline: bool,
+) -> anyhow::Result<Vec<TestDependencyLeak>> {
+    let src_dir = Path::new(path).with_file_name("src");
+    let has_test_only =
+        (config.test_dependencies.keys()).any(|pkg| !config.dependencies.contains_key(pkg));
+    if !has_test_only || !src_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let sources = imports::scan_dir(&src_dir).context(format!(
+        "Failed to scan the imports of {}",
+        src_dir.display()
+    ))?;
+    let versions_cache = crate::load_versions_cache(offline)?;
+    let mut dependency_configs = BTreeMap::new();
+    for (pkg, constraint) in config.dependencies.iter().chain(&config.test_dependencies) {
+        if dependency_configs.contains_key(pkg) {
+            continue;
+        }
+        match load_dependency_config(&versions_cache, pkg, constraint, offline) {
+            Some(dep_config) => {
+                dependency_configs.insert(pkg.clone(), dep_config);
+            }
+            None => eprintln!(
+                "Warning: no elm.json found for {} \"{}\", its modules are not checked",
+                pkg, constraint
+            ),
+        }
+    }
+    Ok(lint::test_dependency_leaks(
+        config,
+        &sources,
+        &dependency_configs,
+    ))
+}
+
+/// Load the config of the newest known version of a dependency within its constraint.
+fn load_dependency_config(
+    versions_cache: &Cache,
+    pkg: &Pkg,
+    constraint: &elm_solve_deps::constraint::Constraint,
+    offline: bool,
+) -> Option<PackageConfig> {
+    let mut versions: BTreeSet<SemVer> = (crate::elm_versions().iter())
+        .flat_map(|elm_version| {
+            Cache::list_installed_versions(crate::elm_home(), elm_version, pkg).unwrap_or_default()
+        })
+        .collect();
+    versions.extend(versions_cache.cache.get(pkg).into_iter().flatten().copied());
+    let version = versions
+        .into_iter()
+        .rev()
+        .find(|v| constraint.0.contains(v))?;
+    let pkg_version = PkgVersion {
+        author_pkg: pkg.clone(),
+        version,
+    };
+    if offline {
+        crate::load_local_config(&pkg_version).ok()
+    } else {
+        crate::load_package_config(&pkg_version).ok()
+    }
+}
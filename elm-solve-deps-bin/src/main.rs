@@ -1,18 +1,89 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+use std::process::exit;
 use std::str::FromStr;
-use std::{error::Error, process::exit};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Once, OnceLock};
+use std::time::Duration;
 
 use anyhow::Context;
-use pubgrub::error::PubGrubError;
-use pubgrub::report::{DefaultStringReporter, Reporter};
-use pubgrub::version::SemanticVersion as SemVer;
 
+use elm_solve_deps::api_diff::ApiDiff;
+use elm_solve_deps::audit::TrustedAuthors;
+use elm_solve_deps::budget::{Interrupted, Limits};
+use elm_solve_deps::cached_solution::CachedSolution;
+use elm_solve_deps::clock::{self, Clock, SystemClock};
 use elm_solve_deps::constraint::Constraint;
-use elm_solve_deps::pkg_version::PkgVersion;
-use elm_solve_deps::project_config::{AppDependencies, Pkg, ProjectConfig};
-use elm_solve_deps::solver::{self, VersionStrategy};
+use elm_solve_deps::corpus::Recorder;
+use elm_solve_deps::diagnostics::Diagnostic;
+use elm_solve_deps::ecosystem::Ecosystem;
+use elm_solve_deps::error_code::{Coded, CodedError, ErrorCode};
+use elm_solve_deps::federation::{Federation, Remote, MAIN_REMOTE};
+use elm_solve_deps::fetch::{FetchError, Frozen, HttpFetch, UreqFetcher};
+use elm_solve_deps::footprint::{Footprint, FootprintLimits};
+use elm_solve_deps::graph::{DependencyGraph, PathLimits, TreeOptions};
+use elm_solve_deps::health::{self, HealthThresholds, PackageHealth};
+use elm_solve_deps::infer::{self, InferPolicy};
+use elm_solve_deps::interop::CacheFormat;
+use elm_solve_deps::known_good::KnownGoodSet;
+#[cfg(feature = "max-memory")]
+use elm_solve_deps::memory::CountingAllocator;
+use elm_solve_deps::missing::MissingCache;
+use elm_solve_deps::optional::OptionalExtra;
+use elm_solve_deps::overrides::{OverrideSource, PackageOverride};
+use elm_solve_deps::packed::PackedStore;
+use elm_solve_deps::phased::PhaseError;
+use elm_solve_deps::pkg_version::{
+    Cache, CacheError, CacheUpdate, PkgVersion, PkgVersionError, GITHUB_FALLBACKS,
+};
+use elm_solve_deps::policy::Policies;
+use elm_solve_deps::progress::Progress;
+use elm_solve_deps::project_config::{
+    AppDependencies, ApplicationConfig, PackageConfig, Pkg, ProjectConfig,
+};
+use elm_solve_deps::registry_pin::{PinCheck, PinnedRegistries, RegistryIdentity};
+use elm_solve_deps::registry_view::RegistryView;
+use elm_solve_deps::release_age::ReleaseTimes;
+use elm_solve_deps::renames::{Rename, Renames};
+use elm_solve_deps::report::ModesComparison;
+use elm_solve_deps::review::ReviewError;
+use elm_solve_deps::sandbox::Sandbox;
+use elm_solve_deps::scoring::Objective;
+use elm_solve_deps::search_trace::TraceRecorder;
+use elm_solve_deps::semver::SemVer;
+use elm_solve_deps::shared_cache::SharedCache;
+use elm_solve_deps::solve_memo::SolveMemo;
+use elm_solve_deps::solver::{
+    self, DefaultStringReporter, ExtraMode, IndirectPins, PubGrubError, Reporter, RootDependencies,
+    RootPinning, TieBreak, VersionStrategy,
+};
+use elm_solve_deps::source_span::ElmJsonSource;
+use elm_solve_deps::{
+    bazel, conflict, graph, nix, optional, phased, report, review, scoring, source_span,
+};
+
+mod bench;
+mod cache;
+mod check;
+mod compare;
+mod doctor;
+#[cfg(feature = "interactive")]
+mod interactive;
+mod lint;
+mod outdated;
+mod race;
+mod repair;
+mod search;
+#[cfg(feature = "serve")]
+mod serve;
+mod stale_bounds;
+mod stats;
+mod watch;
+mod watchlist;
 
 const HELP: &str = r#"
 elm-solve-deps
@@ -22,35 +93,510 @@ By default, try in offline mode first
 and switch to online mode if that fails.
 
 USAGE:
-    elm-solve-deps [FLAGS...] [author/package@version]
+    elm-solve-deps [COMMAND] [FLAGS...] [author/package@version]
     For example:
         elm-solve-deps
         elm-solve-deps --help
         elm-solve-deps --offline
+        elm-solve-deps --frozen --test
+        elm-solve-deps --remote https://elm-package-mirror.example.com
+        elm-solve-deps --federate corp=https://elm.corp.example.com
+        elm-solve-deps --sandbox --online-newest
+        elm-solve-deps --watch --review
+        elm-solve-deps --why elm/bytes
+        elm-solve-deps --offline --error-format json
+        elm-solve-deps --provenance provenance.json
+        elm-solve-deps --root-pinning major --online-newest
+        elm-solve-deps --objective freshest --candidates 20
         elm-solve-deps ianmackenzie/elm-3d-scene@1.0.1
         elm-solve-deps --offline jxxcarlson/elm-tar@4.0.0
         elm-solve-deps --online-newest w0rm/elm-physics@5.1.1
         elm-solve-deps --online-oldest lucamug/style-framework@1.1.0
+        elm-solve-deps --online-prefer-installed --test
         elm-solve-deps --test
+        elm-solve-deps --review
         elm-solve-deps --extra "elm/json: 1.1.3 <= v < 2.0.0"
+        elm-solve-deps --extra-override --extra "elm/json: 1.1.2 <= v < 2.0.0"
+        elm-solve-deps --optional-extra "jfmengels/elm-review: 2.0.0 <= v < 3.0.0"
+        elm-solve-deps --test --write --backup
+        elm-solve-deps --offline --minimize-conflict
+        elm-solve-deps --compare-modes --test
+        elm-solve-deps --export nix > elm-srcs.nix
+        elm-solve-deps --export bazel > elm_dependencies.bzl
+        elm-solve-deps --race
+        elm-solve-deps outdated --test
+        elm-solve-deps outdated --health
+        elm-solve-deps as-app --offline --source-dir . --source-dir ../src > examples/elm.json
+        elm-solve-deps tree --depth 2
+        elm-solve-deps tree --invert elm/bytes
+        elm-solve-deps compare elm/core@1.0.2 elm/core@1.0.5
+        elm-solve-deps check "elm/json: 1.1.2 <= v < 2.0.0" 1.1.3
+        elm-solve-deps check "elm/json: 1.1.2 <= v < 2.0.0" "elm/core: 1.0.5 <= v < 2.0.0"
+        elm-solve-deps stats --format json --author elm
+        elm-solve-deps stats --released-since 2023-01-01
+        elm-solve-deps stale-bounds --offline --author elm-community
+        elm-solve-deps bench-registry --offline --baseline bench.csv
+        elm-solve-deps search markdown
+        elm-solve-deps doctor --fix
+        elm-solve-deps cache export index.json --cache-format list
+        elm-solve-deps cache import ~/ci-cache/elm --cache-format elm-tooling
+        elm-solve-deps cache prime /opt/elm-packages
+        elm-solve-deps repair --write
+        elm-solve-deps infer examples/elm.json --constraint-policy full-major
+        elm-solve-deps lint --format json
+        elm-solve-deps serve --port 8080
+        elm-solve-deps watchlist add elm/http elm-community/list-extra
+        elm-solve-deps notify
+
+COMMANDS:
+    outdated               Report how outdated the solution is,
+                           compared to the newest versions available
+    as-app                 Print an application elm.json to build the examples
+                           or tests of a package, with its dependencies
+                           and test dependencies pinned to the solution
+    tree                   Print the tree of the dependencies of the solution,
+                           marking with (*) the packages whose dependencies
+                           were already printed
+    compare FROM TO        Compare the exposed modules of two versions
+                           of a package, and flag suspicious releases
+    check CONSTRAINT VERSION
+                           Check if a version satisfies a constraint
+                           of the shape "author/package: constraint"
+    check CONSTRAINTS...   Check if the versions recorded in elm.json
+                           satisfy all the given constraints
+    stats                  Print statistics about the latest version
+                           of every package of the registry
+    stale-bounds           Report the constraints of the latest version of every
+                           package of the registry excluding the latest version
+                           of a dependency, starting with the packages
+                           with the most dependents
+    bench-registry         Solve every version of every package of the registry,
+                           print the outcome, time and decisions of each solve,
+                           and a summary, or a comparison with --baseline
+    search WORDS...        Search packages by name and summary: one word must
+                           match the package name, and the others its name
+                           or summary, for locally available packages
+    cache import FILE      Add the package versions of an index exported by another tool
+                           to the versions cache
+    cache export FILE      Save the versions cache as an index for other tools
+    cache snapshot FILE    Save the versions cache as the registry.dat of the elm
+                           compiler, like elm2nix snapshot, to build with Nix
+    cache prime DIR        Import a directory of pre-downloaded package configs,
+                           organized as author/package/version/elm.json,
+                           into the package configs cache
+    cache forget-missing [PACKAGE]
+                           Forget the packages and versions remembered as missing
+                           on the package server, or only those of PACKAGE
+    cache clear-solutions  Remove the memoized solutions
+    cache trust-registry   Trust the current registry at the url of the package server,
+                           after a warning that its identity changed, identified
+                           by the package versions it lists in the versions cache,
+                           updated first unless --offline
+    cache compact [gzip|none]
+                           Compress the cached package configs and versions caches
+                           with gzip (default), or decompress them with none,
+                           and keep writing them with that compression
+    cache pack             Move the cached package configs into a single packed file,
+                           faster to read on slow filesystems, such as on CI
+    doctor                 Check ELM_HOME for problems breaking dependency solving,
+                           such as partially extracted packages or corrupted caches,
+                           and suggest fixes
+    repair                 Check the versions pinned in elm.json, and print
+                           the indirect dependencies changes making them
+                           consistent, preferring the pinned versions
+    infer [ELM_JSON]       Infer the dependency constraints of a package
+                           from the exact versions of an application elm.json
+                           used to develop it (default: elm.json)
+    lint [ELM_JSON]        Check the dependency constraints of a package elm.json
+                           for exact versions, constraints spanning several
+                           major versions, and test dependencies looser than
+                           regular ones, and suggest replacements.
+                           Also report modules of test dependencies imported
+                           by the sources in src/, which would break consumers.
+                           Exit with an error if any is found (default: elm.json)
+    serve                  Answer solving requests over HTTP, reusing warm caches:
+                           POST /solve with a JSON body {"elm-json": {...},
+                           "test", "extra", "extra-mode", "strategy", "offline"}
+                           GET /versions/author/package
+                           (requires the "serve" feature)
+    watchlist              List the packages watched for new versions
+    watchlist add PACKAGES...
+                           Watch packages for new versions
+    watchlist remove PACKAGES...
+                           Stop watching packages for new versions
+    notify                 Print the new versions of watched packages
+                           published since the last run
 
 FLAGS:
     --help                 Print this message and exit
     --offline              No network request, use only installed packages
+    --sandbox              Work in a temporary copy of ELM_HOME, removed when exiting,
+                           such that downloads and caches leave the real one untouched
+    --frozen               Like --offline, but also fail with the offending url
+                           on any attempt to reach the network, for hermetic builds
+    --remote URL           Use a mirror of the package server
+                           (default: https://package.elm-lang.org)
+                           Its identity is pinned on first use, with a warning
+                           when a later run sees another registry at that url
+    --elm-version VERSION  Use the packages installed for this version of elm
+                           (default: 0.19.1), repeat to merge the packages
+                           of several versions, in order of preference
+    --federate NAME=URL    Also use the packages of another package server,
+                           such as a corporate one, named NAME (repeatable)
+                           Versions published on several package servers come
+                           from the main one, then the federated ones in order
+    --prefer-remote PKG=NAME
+                           Take the versions of the package PKG from the package
+                           server NAME first, "main" being the main one (repeatable)
     --online-newest        Use the newest compatible version
     --online-oldest        Use the oldest compatible version
+    --online-prefer-installed
+                           Use installed versions first, then the newest compatible
+                           version, to reduce downloads
     --test                 Solve with both normal and test dependencies
+    --review               Also solve the elm-review configuration in review/
+                           with versions compatible with the project ones
+    --no-solve-cache       Always solve, instead of reusing the memoized solution
+                           of identical inputs: same elm.json, extras and options,
+                           and same number of package versions known
+                           Solutions are memoized in $ELM_HOME/pubgrub/solve_memo/
+    --prefer-cached-solution
+                           Reuse the previous solution if the inputs are unchanged
     --extra "author/package: constraint"
                            Additional package version constraint
                            Need one --extra per additional constraint
-                           MUST be placed before an eventual package to solve
+    --extra-tighten        Intersect extra constraints with declared ones (default)
+    --extra-override       Replace declared constraints by the extra ones
+    --extra-add            Fail if an extra package already is a direct dependency
+    --optional-extra "author/package: constraint"
+                           Additional constraint only included if it can be
+                           satisfied, silently skipped otherwise
+                           Need one --optional-extra per optional constraint
+    --policies FILE        Version policies applied to all packages
+                           (default: $ELM_HOME/pubgrub/policies.json if it exists)
+    --renames FILE         Packages renamed over time, as a JSON object mapping
+                           obsolete names to their successors, in addition to
+                           the well-known ones, such as elm-lang/core to elm/core
+                           (default: $ELM_HOME/pubgrub/renames.json if it exists)
+    --follow-renames       Replace obsolete dependencies by their successors,
+                           instead of only warning about them
+    --ecosystem elm|lamdera
+                           Ecosystem of the project, defining the packages it
+                           must depend on, such as elm/core and elm/json for
+                           applications, and their missing ones are reported
+                           (default: elm)
+    --inject-mandatory     Add the missing mandatory packages of the ecosystem
+                           to the resolution, instead of only warning about them
+    --hold author/package  Keep a package at the version recorded in elm.json,
+                           such that only other packages are upgraded
+                           Need one --hold per package
+    --known-good FILE|URL  Restrict packages to the versions of a known good set,
+                           a JSON file or url listing package versions known
+                           to work together, failing if the solution has others
+    --escape author/package
+                           Release a package from the known good set, allowing
+                           any of its versions (repeatable)
+    --override author/package=git+REPOSITORY#REF|author/package=PATH
+                           Replace a package by an unreleased version, from a
+                           git repository fetched at a ref, or from a local
+                           clone, resolving its dependencies (repeatable)
+    --trusted-author AUTHOR
+                           Trust this author to publish kernel packages,
+                           in addition to elm and elm-explorations
+    --format csv|json      Output format of the stats, stale-bounds
+                           and bench-registry commands (default: csv)
+                           or text|json for the lint command (default: text)
+    --author AUTHOR        Only print stats, stale bounds or bench packages
+                           of this author. Need one --author per author
+    --released-since DATE  Only print stats of packages whose latest version
+                           was released on or after DATE, as YYYY-MM-DD in UTC,
+                           according to the package server
+    --shared-cache DIR     Share downloaded package configs between ELM_HOMEs,
+                           storing them once in DIR, addressed by their sha256
+    --github-fallback      Fetch elm.json files from the GitHub repositories of packages
+                           when the package server fails to provide them,
+                           marked as fallbacks in the --provenance
+    --root-pinning exact|major|any
+                           How strictly the direct dependencies of an application
+                           are kept at the versions of its elm.json: exactly,
+                           or allowing upgrades within the same major version,
+                           or allowing any version (default: exact)
+    --indirect-pins prefer|ignore
+                           Whether the indirect dependencies recorded
+                           in the elm.json of an application are tried first,
+                           to keep them when possible (default: prefer)
+    --tie-break alphabetical|reverse-alphabetical
+                           Which package to decide first among the ones
+                           with the same number of candidate versions
+                           (default: alphabetical)
+    --objective freshest|conservative
+                           Compare several solutions and keep the best one:
+                           the freshest overall, or the most conservative,
+                           avoiding versions before 1.1.0, then newer versions
+    --candidates N         Number of solutions compared with --objective
+                           (default: 10)
+    --max-decisions N      Give up after deciding N package versions,
+                           and report the partial solution reached
+    --min-release-age DAYS Only use versions released at least DAYS days ago,
+                           according to the package server, solving online
+    --max-memory MB        Give up when the process allocates more than MB mebibytes,
+                           and report the partial solution reached,
+                           instead of exhausting the memory of the host
+                           (requires the "max-memory" feature, which counts
+                           every allocation of the process)
+    --max-packages N       Fail if the solution has more than N packages,
+                           naming the direct dependencies bringing the most
+    --max-depth D          Fail if the solution has a chain of dependencies
+                           longer than D, direct dependencies being at depth 1,
+                           naming the longest chain
+    --record-test-case DIR Save the project, the inputs, and all package configs
+                           and versions consulted while solving into DIR,
+                           to replay the resolution later
+    --provenance FILE      Write into FILE, as JSON, where the config of each package
+                           of the solution was loaded from: installed packages,
+                           caches, or the package server, with the url and time
+    --compare-modes        Solve both offline and online, and print the differences
+                           between both solutions as JSON, telling if solving offline
+                           is sufficient, without downloading anything
+    --race                 Solve with the newest, oldest and prefer-installed strategies
+                           in parallel, and print the first solution found
+    --race-all             Same as --race, but wait for all strategies
+                           and report the outcome and duration of each one
+    --why author/package   Print the chains of dependencies leading to a package
+                           of the solution, instead of the solution
+    --export nix|bazel|bazel-json
+                           Print the solution for another build system, instead of
+                           the solution. nix: the elm-srcs.nix of elm2nix, pinning
+                           the GitHub tarball of every package with the sha256 of
+                           its content, downloading it unless hashed before,
+                           see also cache snapshot FILE.
+                           bazel: a .bzl file declaring an http_archive per package,
+                           bazel-json: the same archives as JSON, downloading them
+                           to compute their sha256
+    --depth N              Only print the tree down to N levels of dependencies
+    --invert author/package
+                           Print the tree of the packages depending on a package
+                           of the solution, instead of its dependencies
+    --prune author/package Remove a package and its dependencies from the tree
+                           Need one --prune per package
+    --no-dedupe            Print the dependencies of repeated packages in the tree
+    --source-dir DIR       Source directory of the application printed by as-app,
+                           src by default. Need one --source-dir per directory
+    --health               Add the number of releases, the last release date,
+                           and the number of dependents of each package to outdated,
+                           flagging abandoned packages. Release dates are cached
+                           in $ELM_HOME/pubgrub, like for --min-release-age,
+                           and fetched again when new versions are published
+    --error-format text|json
+                           Print errors as text, or as a JSON object with the code,
+                           the message, and the spans of the elm.json entries
+                           the error is attributable to, for editors (default: text)
+    --progress ndjson      Stream progress events on stderr, one JSON object per line,
+                           with the phase, and each package chosen, config loaded,
+                           and version decided or unavailable, with counts of decisions
+                           and undetermined packages, for editors and GUIs.
+                           Other lines of stderr are not JSON objects
+    --schema NAME          Print the JSON schema of a machine-readable output
+                           and exit, one of: solution, error, progress,
+                           provenance, modes-comparison, stats, stale-bounds,
+                           bench, lint, health
+    --debug-dump FILE      Write into FILE, as JSON, the timestamped trace of how
+                           the solver explored the search space, with the packages
+                           chosen, loaded, decided again after backtracking,
+                           or without compatible version, and print a summary
+                           of the packages it struggled with
+    --minimize-conflict    When solving fails, search for a minimal set
+                           of direct dependencies conflicting together
+    --watch                Solve again every time the elm.json changes,
+                           and the review configuration with --review,
+                           printing what changed in the solution
+                           (polled, or watched with filesystem notifications
+                           with the "fs-events" feature)
+    --isolate-test         Solve test dependencies on top of the normal ones,
+                           to tell which of them fail (implies --test)
+    --write                Update the dependencies of the elm.json in place
+                           instead of printing the solution, or the repair
+    --backup               Copy elm.json to elm.json.bak before writing it
+    --interactive          Browse the solution in the terminal, change versions
+                           and write the result into elm.json on confirmation
+                           (requires the "interactive" feature)
+    --constraint-policy caret|full-major
+                           Constraints inferred by the infer command,
+                           from the used version or its whole major version,
+                           up to the next major version (default: caret)
+    --cache-format all-packages|list|elm-json|elm-tooling
+                           Format of the cache command index files, a JSON object
+                           mapping packages to versions, like the /all-packages
+                           endpoint, a JSON array of "author/package@version",
+                           the registry.dat of the elm compiler read by elm-json,
+                           or an ELM_HOME of elm-tooling, with its registry.dat
+                           and installed packages (default: all-packages)
+    --fix                  Fix the problems found by the doctor command
+    --port N               Port of the serve command (default: 8080)
+    --host ADDRESS         Address the serve command listens on, such as 0.0.0.0
+                           to accept requests from other machines
+                           (default: 127.0.0.1, only local requests)
+    --baseline FILE        Records of a previous bench-registry run to compare with,
+                           in CSV if FILE ends with .csv, and in JSON otherwise
+
+ENVIRONMENT:
+    ELM_HOME               Location of the elm packages and of the solver caches
+                           (default: ~/.elm)
+    SOURCE_DATE_EPOCH      Current time, in seconds since the unix epoch,
+                           for reproducible outputs and release ages
+
+ERRORS:
+    Errors are printed with a stable code, such as E-no-solution
+    or E-fetch-timeout, for scripts to rely on instead of the messages
 "#;
 
-fn main() -> anyhow::Result<()> {
-    let args: Vec<String> = std::env::args().skip(1).collect();
-    let is_option = |s: &&str| s.starts_with("--");
-    let (options, positional): (Vec<&str>, Vec<&str>) =
-        args.iter().map(|s| s.as_str()).partition(is_option);
+/// Options expecting a value as the next argument.
+const OPTIONS_WITH_VALUE: &[&str] = &[
+    "--extra",
+    "--optional-extra",
+    "--why",
+    "--export",
+    "--depth",
+    "--invert",
+    "--prune",
+    "--source-dir",
+    "--policies",
+    "--renames",
+    "--ecosystem",
+    "--max-decisions",
+    "--max-memory",
+    "--max-packages",
+    "--max-depth",
+    "--min-release-age",
+    "--trusted-author",
+    "--format",
+    "--author",
+    "--released-since",
+    "--hold",
+    "--known-good",
+    "--escape",
+    "--override",
+    "--record-test-case",
+    "--shared-cache",
+    "--port",
+    "--host",
+    "--constraint-policy",
+    "--baseline",
+    "--cache-format",
+    "--provenance",
+    "--root-pinning",
+    "--tie-break",
+    "--indirect-pins",
+    "--objective",
+    "--candidates",
+    "--error-format",
+    "--schema",
+    "--progress",
+    "--debug-dump",
+    "--remote",
+    "--elm-version",
+    "--federate",
+    "--prefer-remote",
+];
+
+fn main() {
+    if let Err(err) = try_main() {
+        if JSON_ERRORS.load(Ordering::Relaxed) {
+            eprintln!("{}", structured_error(&err));
+        } else {
+            eprintln!("Error [{}]: {:?}", error_code(&err), err);
+        }
+        exit(1);
+    }
+}
+
+/// The structured JSON of an error, with the locations in elm.json it is attributable to.
+fn structured_error(err: &anyhow::Error) -> serde_json::Value {
+    let locations = (err.chain())
+        .find_map(|e| e.downcast_ref::<CodedError>())
+        .map(|coded| coded.locations.clone())
+        .unwrap_or_default();
+    let structured = CodedError {
+        code: error_code(err),
+        message: format!("{:#}", err),
+        locations,
+    };
+    serde_json::to_value(structured).unwrap_or_default()
+}
+
+/// Code of an error of the library, even when wrapping the errors of the solving functions.
+fn error_code(err: &anyhow::Error) -> ErrorCode {
+    if let Some(err) = err.downcast_ref::<PhaseError<anyhow::Error>>() {
+        err.code_with(error_code)
+    } else if let Some(err) = err.downcast_ref::<ReviewError<anyhow::Error>>() {
+        err.code_with(error_code)
+    } else {
+        ErrorCode::of(err.as_ref())
+    }
+}
+
+fn try_main() -> anyhow::Result<()> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    // Fix the current time for reproducible outputs
+    let _ = CLOCK.set(clock::from_env()?);
+
+    // Check for a command
+    let command = match args.first().map(|s| s.as_str()) {
+        Some("outdated") => Command::Outdated,
+        Some("as-app") => Command::AsApp,
+        Some("tree") => Command::Tree,
+        Some("compare") => Command::Compare,
+        Some("check") => Command::Check,
+        Some("stats") => Command::Stats,
+        Some("stale-bounds") => Command::StaleBounds,
+        Some("bench-registry") => Command::BenchRegistry,
+        Some("search") => Command::Search,
+        Some("doctor") => Command::Doctor,
+        Some("cache") => Command::Cache,
+        Some("repair") => Command::Repair,
+        Some("infer") => Command::Infer,
+        Some("lint") => Command::Lint,
+        Some("serve") => Command::Serve,
+        Some("watchlist") => Command::Watchlist,
+        Some("notify") => Command::Notify,
+        _ => Command::Solve,
+    };
+    if command != Command::Solve {
+        args.remove(0);
+    }
+
+    // Split flags, options with a value, and positional arguments.
+    let mut options: Vec<&str> = Vec::new();
+    let mut option_values: Vec<(&str, &str)> = Vec::new();
+    let mut positional: Vec<&str> = Vec::new();
+    let mut args_iter = args.iter().map(|s| s.as_str());
+    while let Some(arg) = args_iter.next() {
+        if OPTIONS_WITH_VALUE.contains(&arg) {
+            let value = args_iter
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("Missing value for the option {}", arg))?;
+            option_values.push((arg, value));
+        } else if arg.starts_with("--") {
+            options.push(arg);
+        } else {
+            positional.push(arg);
+        }
+    }
+    let values_of = |name: &str| -> Vec<&str> {
+        option_values
+            .iter()
+            .filter(|(o, _)| *o == name)
+            .map(|(_, v)| *v)
+            .collect()
+    };
+
+    // Print errors as JSON
+    match values_of("--error-format").last() {
+        None | Some(&"text") => {}
+        Some(&"json") => JSON_ERRORS.store(true, Ordering::Relaxed),
+        Some(format) => anyhow::bail!("Unknown error format {}, expected text or json", format),
+    }
 
     // Check for the --help option
     if options.contains(&"--help") {
@@ -58,8 +604,165 @@ fn main() -> anyhow::Result<()> {
         exit(0);
     }
 
-    // Check if solving with test dependencies
-    let use_test = options.contains(&"--test");
+    // Print the JSON schema of a machine-readable output
+    if let Some(name) = values_of("--schema").last() {
+        let schema = elm_solve_deps::schema::schema(name)?;
+        println!("{}", serde_json::to_string_pretty(&schema)?);
+        exit(0);
+    }
+
+    // Check for the versions of elm whose installed packages are used
+    let mut versions: Vec<String> = Vec::new();
+    for elm_version in values_of("--elm-version") {
+        SemVer::from_str(elm_version)
+            .context(format!("Invalid version of elm: {}", elm_version))?;
+        if !versions.iter().any(|v| v == elm_version) {
+            versions.push(elm_version.to_string());
+        }
+    }
+    if !versions.is_empty() {
+        let _ = ELM_VERSIONS.set(versions);
+    }
+
+    // Work in a temporary copy of ELM_HOME, removed when exiting
+    let _sandbox = if options.contains(&"--sandbox") {
+        let sandbox = Sandbox::seeded_from(elm_home(), &elm_versions()[0])
+            .context("Failed to copy ELM_HOME into a sandbox")?;
+        for elm_version in &elm_versions()[1..] {
+            (sandbox.seed_elm_version(elm_home(), elm_version))
+                .context("Failed to copy ELM_HOME into a sandbox")?;
+        }
+        std::env::set_var("ELM_HOME", sandbox.elm_home());
+        Some(sandbox)
+    } else {
+        None
+    };
+
+    // Use a mirror of the package server
+    if let Some(url) = values_of("--remote").last() {
+        let _ = REMOTE.set(url.trim_end_matches('/').to_string());
+    }
+
+    // Forbid any network request, which also implies offline mode
+    let frozen = options.contains(&"--frozen");
+    if frozen {
+        FROZEN.store(true, Ordering::Relaxed);
+        options.push("--offline");
+    }
+
+    // Compare the exposed modules of two package versions
+    if command == Command::Compare {
+        let (from, to) = match positional.as_slice() {
+            [from, to] => (PkgVersion::from_str(from)?, PkgVersion::from_str(to)?),
+            _ => anyhow::bail!("compare expects two package versions"),
+        };
+        if from.author_pkg != to.author_pkg {
+            anyhow::bail!("compare expects two versions of the same package");
+        }
+        let diff = ApiDiff::between(&load_package_config(&from)?, &load_package_config(&to)?);
+        compare::print_diff(&diff);
+        return Ok(());
+    }
+
+    // Check constraints without solving
+    if command == Command::Check {
+        return check::run(&positional);
+    }
+
+    // Compute statistics about all packages of the registry
+    if command == Command::Stats {
+        let format = values_of("--format").last().copied().unwrap_or("csv");
+        let authors = values_of("--author");
+        let offline = options.contains(&"--offline");
+        let released_since = match values_of("--released-since").last() {
+            Some(date) => Some(stats::parse_date(date)?),
+            None => None,
+        };
+        return stats::run(format, &authors, released_since, offline);
+    }
+
+    // Find stale upper bounds across the registry
+    if command == Command::StaleBounds {
+        let format = values_of("--format").last().copied().unwrap_or("csv");
+        let authors = values_of("--author");
+        let offline = options.contains(&"--offline");
+        return stale_bounds::run(format, &authors, offline);
+    }
+
+    // Benchmark the solver on all package versions of the registry
+    if command == Command::BenchRegistry {
+        let format = values_of("--format").last().copied().unwrap_or("csv");
+        let authors = values_of("--author");
+        let baseline = values_of("--baseline").last().copied();
+        let offline = options.contains(&"--offline");
+        return bench::run(format, &authors, baseline, offline);
+    }
+
+    // Check ELM_HOME for problems
+    if command == Command::Doctor {
+        return doctor::run(options.contains(&"--fix"));
+    }
+
+    // Import or export the versions cache
+    if command == Command::Cache {
+        let format = match values_of("--cache-format").last() {
+            Some(f) => CacheFormat::from_str(f)?,
+            None => CacheFormat::default(),
+        };
+        return cache::run(&positional, format, options.contains(&"--offline"));
+    }
+
+    // Infer the dependency constraints of a package
+    if command == Command::Infer {
+        let path = match positional.as_slice() {
+            [] => "elm.json",
+            [path] => path,
+            _ => anyhow::bail!("infer expects at most one elm.json path"),
+        };
+        let policy = match values_of("--constraint-policy").last() {
+            Some(p) => InferPolicy::from_str(p)?,
+            None => InferPolicy::default(),
+        };
+        let elm_json_str =
+            std::fs::read_to_string(path).context(format!("Failed to read {}", path))?;
+        let dev_config: ApplicationConfig = serde_json::from_str(&elm_json_str)
+            .context(format!("{} is not the elm.json of an application", path))?;
+        let inferred = infer::infer_dependencies(&dev_config, policy);
+        println!("{}", serde_json::to_string_pretty(&inferred)?);
+        return Ok(());
+    }
+
+    // Lint the dependency constraints of a package
+    if command == Command::Lint {
+        let path = match positional.as_slice() {
+            [] => "elm.json",
+            [path] => path,
+            _ => anyhow::bail!("lint expects at most one elm.json path"),
+        };
+        let format = values_of("--format").last().copied().unwrap_or("text");
+        let offline = options.contains(&"--offline");
+        return lint::run(path, format, offline);
+    }
+
+    // Watch packages for new versions
+    if command == Command::Watchlist {
+        return watchlist::run(&positional);
+    }
+    if command == Command::Notify {
+        return watchlist::notify(options.contains(&"--offline"));
+    }
+
+    // Search packages by name and summary
+    if command == Command::Search {
+        if positional.is_empty() {
+            anyhow::bail!("search expects at least one word to look for");
+        }
+        let offline = options.contains(&"--offline");
+        return search::run(&positional.join(" "), offline);
+    }
+
+    // Check if solving with test dependencies, always included in applications built from packages
+    let use_test = options.contains(&"--test") || command == Command::AsApp;
 
     // Check for connectivity and strategy
     let offline = options.contains(&"--offline");
@@ -68,108 +771,1817 @@ fn main() -> anyhow::Result<()> {
         online_strat = Some(VersionStrategy::Newest);
     } else if options.contains(&"--online-oldest") {
         online_strat = Some(VersionStrategy::Oldest);
+    } else if options.contains(&"--online-prefer-installed") {
+        online_strat = Some(VersionStrategy::PreferInstalled);
+    }
+    if frozen && online_strat.is_some() {
+        anyhow::bail!("--frozen is incompatible with online strategies");
     }
 
+    // Check for the mode of extra additional constraints
+    let extra_modes: Vec<ExtraMode> = options
+        .iter()
+        .filter_map(|&o| match o {
+            "--extra-tighten" => Some(ExtraMode::Tighten),
+            "--extra-override" => Some(ExtraMode::Override),
+            "--extra-add" => Some(ExtraMode::Add),
+            _ => None,
+        })
+        .collect();
+    let extra_mode = match extra_modes.as_slice() {
+        [] => ExtraMode::default(),
+        [mode] => *mode,
+        _ => anyhow::bail!(
+            "Only one of --extra-tighten, --extra-override and --extra-add is allowed"
+        ),
+    };
+
     // Check for extra additional constraints
-    let extra_count = options.iter().filter(|&o| o == &"--extra").count();
-    let (extras_args, pkg) = positional.split_at(extra_count);
-    let parse_package_constraint = |s: &&str| {
-        let (pkg_str, range_str) = s.split_once(':').ok_or_else(|| {
-            anyhow::anyhow!(
-                "Did not find the separator ':' in the extra argument {}",
-                s.to_string()
-            )
+    let extras: anyhow::Result<Vec<(Pkg, Constraint)>> = values_of("--extra")
+        .iter()
+        .map(|s| parse_package_constraint(s))
+        .collect();
+
+    // Check for optional additional constraints
+    let optional_extras: anyhow::Result<Vec<OptionalExtra>> = values_of("--optional-extra")
+        .iter()
+        .map(|s| {
+            let (pkg, constraint) = parse_package_constraint(s)?;
+            Ok(OptionalExtra { pkg, constraint })
+        })
+        .collect();
+    let optional_extras = optional_extras?;
+
+    // Check for version policies
+    let mut policies = match values_of("--policies").last() {
+        Some(path) => {
+            Policies::load(path).context(format!("Failed to load the policies in {}", path))?
+        }
+        None => Policies::load(Policies::default_path(elm_home())).unwrap_or_default(),
+    };
+
+    // Check for packages renamed over time
+    let mut renames = Renames::builtin();
+    match values_of("--renames").last() {
+        Some(path) => renames.extend(
+            Renames::load(path).context(format!("Failed to load the renames in {}", path))?,
+        ),
+        None => {
+            let path = Renames::default_path(elm_home());
+            if path.exists() {
+                renames.extend(
+                    Renames::load(&path)
+                        .context(format!("Failed to load the renames in {}", path.display()))?,
+                );
+            }
+        }
+    }
+    let follow_renames = options.contains(&"--follow-renames");
+
+    // Check for packages mandatory in the ecosystem of the project
+    let ecosystem: Ecosystem = match values_of("--ecosystem").last() {
+        Some(ecosystem) => ecosystem.parse()?,
+        None => Ecosystem::default(),
+    };
+    let inject_mandatory = options.contains(&"--inject-mandatory");
+
+    let maybe_pkg_version = match positional.first() {
+        Some(p_str) => Some(
+            PkgVersion::from_str(p_str)
+                .context(format!("Failed to parse the package to solve: {}", p_str))?,
+        ),
+        None => None,
+    };
+
+    // Check for a known good set restricting packages to curated versions
+    let escaped: BTreeSet<Pkg> = values_of("--escape")
+        .iter()
+        .map(|p| Pkg::from_str(p))
+        .collect::<Result<_, _>>()?;
+    let known_good = match values_of("--known-good").last() {
+        Some(source) if source.starts_with("http://") || source.starts_with("https://") => {
+            let set = KnownGoodSet::fetch(source, &http_fetch);
+            Some(set.context(format!("Failed to fetch the known good set at {}", source))?)
+        }
+        Some(path) => {
+            let set = KnownGoodSet::load(path);
+            Some(set.context(format!("Failed to load the known good set in {}", path))?)
+        }
+        None => None,
+    };
+    match &known_good {
+        Some(set) => set.restrict(&mut policies, &escaped),
+        None if !escaped.is_empty() => anyhow::bail!("--escape can only be used with --known-good"),
+        None => {}
+    }
+
+    // Check for packages overridden by unreleased versions
+    let overrides: Vec<PackageOverride> = values_of("--override")
+        .iter()
+        .map(|o| PackageOverride::from_str(o))
+        .collect::<Result<_, _>>()?;
+    let fetches_git = |o: &PackageOverride| matches!(o.source, OverrideSource::Git { .. });
+    if frozen && overrides.iter().any(fetches_git) {
+        anyhow::bail!("--frozen is incompatible with overrides fetched from git repositories");
+    }
+
+    // Check for packages held at their recorded version
+    let held: Vec<Pkg> = values_of("--hold")
+        .iter()
+        .map(|p| Pkg::from_str(p))
+        .collect::<Result<_, _>>()?;
+    if !held.is_empty() {
+        let hold_error = "--hold can only be used for the application in the current directory";
+        if maybe_pkg_version.is_some() {
+            anyhow::bail!(hold_error);
+        }
+        let app_config = match load_project_config(None)? {
+            ProjectConfig::Application(app_config) => app_config,
+            ProjectConfig::Package(_) => anyhow::bail!(hold_error),
+        };
+        policies.hold_recorded(&app_config.recorded_versions(), &held)?;
+    }
+
+    // Check if also solving the elm-review configuration
+    let review = options.contains(&"--review");
+    if review && maybe_pkg_version.is_some() {
+        anyhow::bail!("--review can only be used for the project in the current directory");
+    }
+    if review && command != Command::Solve {
+        anyhow::bail!("--review can only be used to solve dependencies");
+    }
+
+    // Check for additional package servers
+    let mut federation = Federation::new();
+    for remote in values_of("--federate") {
+        federation = federation.with_remote(Remote::from_str(remote)?);
+    }
+    for preference in values_of("--prefer-remote") {
+        let (pkg, name) = preference.split_once('=').ok_or_else(|| {
+            anyhow::anyhow!("Invalid --prefer-remote {}, expected PKG=NAME", preference)
         })?;
-        Ok((
-            Pkg::from_str(pkg_str.trim())?,
-            Constraint::from_str(range_str.trim())?,
-        ))
-    };
-    let extras: anyhow::Result<Vec<(Pkg, Constraint)>> =
-        extras_args.iter().map(parse_package_constraint).collect();
-
-    let maybe_pkg_version = match pkg.get(0) {
-        Some(p_str) => Some(PkgVersion::from_str(p_str).context(format!(
-            "Failed to parse the package to solve: {}",
-            p_str.to_string(),
-        ))?),
+        if name != MAIN_REMOTE && federation.url(name).is_none() {
+            anyhow::bail!("--prefer-remote names an unknown package server {}", name);
+        }
+        federation = federation.with_precedence(Pkg::from_str(pkg)?, name);
+    }
+
+    // Check for additional trusted authors
+    let mut trusted_authors = TrustedAuthors::default();
+    (trusted_authors.authors).extend(values_of("--trusted-author").iter().map(|a| a.to_string()));
+
+    // Check for a budget of decisions
+    let max_decisions = match values_of("--max-decisions").last() {
+        Some(n) => Some(
+            n.parse()
+                .context(format!("Invalid number of decisions: {}", n))?,
+        ),
+        None => None,
+    };
+
+    // Check for a minimum age of the versions used
+    let min_release_age = match values_of("--min-release-age").last() {
+        Some(days) => Some(Duration::from_secs(
+            days.parse::<u64>()
+                .context(format!("Invalid number of days: {}", days))?
+                .saturating_mul(24 * 60 * 60),
+        )),
+        None => None,
+    };
+
+    // Check for a memory ceiling
+    if !values_of("--max-memory").is_empty() && !cfg!(feature = "max-memory") {
+        anyhow::bail!("--max-memory requires elm-solve-deps built with the max-memory feature");
+    }
+    let max_memory = match values_of("--max-memory").last() {
+        Some(mb) => Some(
+            mb.parse::<usize>()
+                .context(format!("Invalid number of mebibytes: {}", mb))?
+                .saturating_mul(1 << 20),
+        ),
+        None => None,
+    };
+
+    // Check for a maximum footprint of the solution
+    let footprint_limits = FootprintLimits {
+        max_packages: match values_of("--max-packages").last() {
+            Some(n) => Some(
+                n.parse()
+                    .context(format!("Invalid number of packages: {}", n))?,
+            ),
+            None => None,
+        },
+        max_depth: match values_of("--max-depth").last() {
+            Some(d) => Some(d.parse().context(format!("Invalid depth: {}", d))?),
+            None => None,
+        },
+    };
+
+    // Check how strictly direct dependencies are pinned
+    let root_pinning = match values_of("--root-pinning").last() {
+        Some(p) => RootPinning::from_str(p)?,
+        None => RootPinning::default(),
+    };
+    let prefer_cached_solution = options.contains(&"--prefer-cached-solution");
+    let tie_break = match values_of("--tie-break").last() {
+        Some(t) => TieBreak::from_str(t)?,
+        None => TieBreak::default(),
+    };
+    let indirect_pins = match values_of("--indirect-pins").last() {
+        Some(p) => IndirectPins::from_str(p)?,
+        None => IndirectPins::default(),
+    };
+    let progress = match values_of("--progress").last() {
+        None => false,
+        Some(&"ndjson") => true,
+        Some(format) => anyhow::bail!("Unknown progress format {}, expected ndjson", format),
+    };
+    if prefer_cached_solution && root_pinning != RootPinning::Exact {
+        anyhow::bail!("--prefer-cached-solution can only be used with exact root pinning");
+    }
+
+    // Check if solving test dependencies separately
+    let isolate_test = options.contains(&"--isolate-test");
+    if isolate_test && (review || command != Command::Solve) {
+        anyhow::bail!("--isolate-test can only be used to solve dependencies");
+    }
+
+    // Check if writing the solution into elm.json
+    let write = options.contains(&"--write");
+    let backup = options.contains(&"--backup");
+    let writable = command == Command::Solve || command == Command::Repair;
+    if write && (maybe_pkg_version.is_some() || review || !writable) {
+        anyhow::bail!("--write can only be used to solve the application in the current directory");
+    }
+    if command == Command::Repair && (maybe_pkg_version.is_some() || review) {
+        anyhow::bail!("repair only works for the application in the current directory");
+    }
+    // Check if browsing the solution interactively
+    let interactive = options.contains(&"--interactive");
+    if interactive && !cfg!(feature = "interactive") {
+        anyhow::bail!("--interactive requires elm-solve-deps built with the interactive feature");
+    }
+    if interactive && (review || isolate_test || command != Command::Solve) {
+        anyhow::bail!("--interactive can only be used to solve dependencies");
+    }
+    if backup && !(write || interactive) {
+        anyhow::bail!("--backup can only be used with --write or --interactive");
+    }
+    if write && interactive {
+        anyhow::bail!("--interactive already writes the elm.json on confirmation");
+    }
+
+    // Check if comparing the offline and online solutions
+    let compare_modes = options.contains(&"--compare-modes");
+    if compare_modes && (offline || review || isolate_test || interactive || write) {
+        anyhow::bail!(
+            "--compare-modes is incompatible with --offline, --review, --isolate-test, --interactive and --write"
+        );
+    }
+    if compare_modes && command != Command::Solve {
+        anyhow::bail!("--compare-modes can only be used to solve dependencies");
+    }
+    if min_release_age.is_some() && (offline || frozen || compare_modes) {
+        anyhow::bail!(
+            "--min-release-age is incompatible with --offline, --frozen and --compare-modes"
+        );
+    }
+
+    // Check if racing several strategies in parallel
+    let race = options.contains(&"--race");
+    let race_all = options.contains(&"--race-all");
+    if (race || race_all) && (offline || online_strat.is_some() || compare_modes) {
+        anyhow::bail!(
+            "--race and --race-all are incompatible with --offline, online strategies and --compare-modes"
+        );
+    }
+    if (race || race_all) && (review || isolate_test || interactive || write) {
+        anyhow::bail!(
+            "--race and --race-all are incompatible with --review, --isolate-test, --interactive and --write"
+        );
+    }
+    if (race || race_all) && command != Command::Solve {
+        anyhow::bail!("--race and --race-all can only be used to solve dependencies");
+    }
+    if race && race_all {
+        anyhow::bail!("Only one of --race and --race-all is allowed");
+    }
+
+    // Check if including optional extras
+    if !optional_extras.is_empty() {
+        if command != Command::Solve {
+            anyhow::bail!("--optional-extra can only be used to solve dependencies");
+        }
+        if review || isolate_test || interactive || compare_modes || race || race_all {
+            anyhow::bail!(
+                "--optional-extra is incompatible with --review, --isolate-test, --interactive, --compare-modes, --race and --race-all"
+            );
+        }
+        if prefer_cached_solution || (write && use_test) {
+            anyhow::bail!(
+                "--optional-extra is incompatible with --prefer-cached-solution and with --write --test"
+            );
+        }
+    }
+
+    // Check if writing the provenance of the solution
+    let provenance = values_of("--provenance").last().map(PathBuf::from);
+    if provenance.is_some() && (compare_modes || race || race_all || interactive) {
+        anyhow::bail!(
+            "--provenance is incompatible with --compare-modes, --race, --race-all and --interactive"
+        );
+    }
+    if provenance.is_some() && command != Command::Solve {
+        anyhow::bail!("--provenance can only be used to solve dependencies");
+    }
+
+    // Check if dumping the exploration of the search space
+    let debug_dump = values_of("--debug-dump").last().map(PathBuf::from);
+    if debug_dump.is_some() && command != Command::Solve {
+        anyhow::bail!("--debug-dump can only be used to solve dependencies");
+    }
+    let single_solve = !(review || isolate_test || compare_modes || race || race_all);
+    if debug_dump.is_some() && !(single_solve && optional_extras.is_empty()) {
+        anyhow::bail!(
+            "--debug-dump is incompatible with --review, --isolate-test, --optional-extra, --compare-modes, --race and --race-all"
+        );
+    }
+
+    // Check if solving again on every change of elm.json
+    let watch = options.contains(&"--watch");
+    if watch && (maybe_pkg_version.is_some() || command != Command::Solve) {
+        anyhow::bail!("--watch can only be used to solve the project in the current directory");
+    }
+    if watch && (write || interactive || isolate_test || compare_modes || race || race_all) {
+        anyhow::bail!(
+            "--watch is incompatible with --write, --interactive, --isolate-test, --compare-modes, --race and --race-all"
+        );
+    }
+    if watch && (!optional_extras.is_empty() || prefer_cached_solution || provenance.is_some()) {
+        anyhow::bail!(
+            "--watch is incompatible with --optional-extra, --prefer-cached-solution and --provenance"
+        );
+    }
+    if watch && debug_dump.is_some() {
+        anyhow::bail!("--watch is incompatible with --debug-dump");
+    }
+
+    // Check if exporting the solution in another format
+    let export = match values_of("--export").last() {
+        Some(&"nix") => Some(Export::Nix),
+        Some(&"bazel") => Some(Export::Bazel),
+        Some(&"bazel-json") => Some(Export::BazelJson),
+        Some(format) => anyhow::bail!(
+            "Unknown export format {}, expected nix, bazel or bazel-json",
+            format
+        ),
+        None => None,
+    };
+    if export.is_some() && (command != Command::Solve || write || interactive || isolate_test) {
+        anyhow::bail!("--export can only be used to print the solution");
+    }
+    if export.is_some() && (review || compare_modes || race || race_all || watch) {
+        anyhow::bail!(
+            "--export is incompatible with --review, --compare-modes, --race, --race-all and --watch"
+        );
+    }
+
+    // Check if explaining why a package is in the solution
+    let why = match values_of("--why").last() {
+        Some(pkg) => Some(Pkg::from_str(pkg)?),
+        None => None,
+    };
+    if why.is_some() && (command != Command::Solve || write || interactive || isolate_test) {
+        anyhow::bail!("--why can only be used to print the solution");
+    }
+    if why.is_some() && export.is_some() {
+        anyhow::bail!("--why is incompatible with --export");
+    }
+    if why.is_some() && (review || compare_modes || race || race_all || watch) {
+        anyhow::bail!(
+            "--why is incompatible with --review, --compare-modes, --race, --race-all and --watch"
+        );
+    }
+    if why.is_some() && !optional_extras.is_empty() {
+        anyhow::bail!("--why is incompatible with --optional-extra");
+    }
+
+    // Check how the dependency tree is printed
+    let max_depth = match values_of("--depth").last() {
+        Some(n) => Some(n.parse().context(format!("Invalid depth: {}", n))?),
+        None => None,
+    };
+    let invert = match values_of("--invert").last() {
+        Some(pkg) => Some(Pkg::from_str(pkg)?),
+        None => None,
+    };
+    let prune: BTreeSet<Pkg> = values_of("--prune")
+        .iter()
+        .map(|p| Pkg::from_str(p))
+        .collect::<Result<_, _>>()?;
+    let dedupe = !options.contains(&"--no-dedupe");
+    let tree_option = max_depth.is_some() || invert.is_some() || !prune.is_empty() || !dedupe;
+    if tree_option && command != Command::Tree {
+        anyhow::bail!("--depth, --invert, --prune and --no-dedupe can only be used with tree");
+    }
+    if command == Command::Tree
+        && (write || interactive || isolate_test || review || compare_modes || race || race_all)
+    {
+        anyhow::bail!(
+            "tree is incompatible with --write, --interactive, --isolate-test, --review, \
+            --compare-modes, --race and --race-all"
+        );
+    }
+    if command == Command::Tree && (watch || !optional_extras.is_empty()) {
+        anyhow::bail!("tree is incompatible with --watch and --optional-extra");
+    }
+    // Check if enriching the outdated report with the health of packages
+    let health = options.contains(&"--health");
+    if health && command != Command::Outdated {
+        anyhow::bail!("--health can only be used with outdated");
+    }
+    let tree_options = TreeOptions {
+        max_depth,
+        prune,
+        dedupe,
+    };
+    // Check the source directories of as-app
+    let mut source_dirs: Vec<String> = values_of("--source-dir")
+        .iter()
+        .map(|dir| dir.to_string())
+        .collect();
+    if !source_dirs.is_empty() && command != Command::AsApp {
+        anyhow::bail!("--source-dir can only be used with as-app");
+    }
+    if source_dirs.is_empty() {
+        source_dirs.push("src".to_string());
+    }
+
+    // Check if picking the best of several solutions
+    let objective = match values_of("--objective").last() {
+        Some(o) => Some(Objective::from_str(o)?),
         None => None,
     };
-    run(maybe_pkg_version, offline, online_strat, use_test, &extras?)
+    let candidates = match values_of("--candidates").last() {
+        Some(n) if objective.is_none() => {
+            anyhow::bail!("--candidates {} can only be used with --objective", n)
+        }
+        Some(n) => n
+            .parse()
+            .context(format!("Invalid number of candidates: {}", n))?,
+        None => 10,
+    };
+    if objective.is_some() && (command != Command::Solve || review || isolate_test || watch) {
+        anyhow::bail!("--objective can only be used to solve dependencies, without --review, --isolate-test and --watch");
+    }
+    if objective.is_some() && (compare_modes || race || race_all || !optional_extras.is_empty()) {
+        anyhow::bail!(
+            "--objective is incompatible with --compare-modes, --race, --race-all and --optional-extra"
+        );
+    }
+    if objective.is_some() && prefer_cached_solution {
+        anyhow::bail!("--objective is incompatible with --prefer-cached-solution");
+    }
+
+    // Check for the port of the serve command
+    if command == Command::Serve && !cfg!(feature = "serve") {
+        anyhow::bail!("serve requires elm-solve-deps built with the serve feature");
+    }
+    if command == Command::Serve && maybe_pkg_version.is_some() {
+        anyhow::bail!("serve does not expect a package to solve");
+    }
+    let other_solve = review || isolate_test || !optional_extras.is_empty() || watch;
+    if !footprint_limits.is_unbounded() && (other_solve || compare_modes || race || race_all) {
+        anyhow::bail!(
+            "--max-packages and --max-depth are incompatible with --review, --isolate-test, \
+            --optional-extra, --watch, --compare-modes, --race and --race-all"
+        );
+    }
+    let port = match values_of("--port").last() {
+        Some(p) => p.parse().context(format!("Invalid port: {}", p))?,
+        None => 8080,
+    };
+    let host = match values_of("--host").last() {
+        Some(h) => h.parse().context(format!("Invalid address: {}", h))?,
+        None => IpAddr::V4(Ipv4Addr::LOCALHOST),
+    };
+
+    run(Args {
+        command,
+        maybe_pkg_version,
+        offline,
+        online_strat,
+        use_test,
+        extras: extras?,
+        optional_extras,
+        extra_mode,
+        review,
+        prefer_cached_solution,
+        policies,
+        renames,
+        follow_renames,
+        ecosystem,
+        inject_mandatory,
+        known_good,
+        escaped,
+        overrides,
+        max_decisions,
+        max_memory,
+        footprint_limits,
+        min_release_age,
+        root_pinning,
+        tie_break,
+        indirect_pins,
+        no_solve_cache: options.contains(&"--no-solve-cache"),
+        progress,
+        trusted_authors,
+        isolate_test,
+        compare_modes,
+        race,
+        race_all,
+        minimize_conflict: options.contains(&"--minimize-conflict"),
+        watch,
+        why,
+        export,
+        health,
+        tree_options,
+        source_dirs,
+        invert,
+        objective,
+        candidates,
+        record_test_case: values_of("--record-test-case").last().map(PathBuf::from),
+        debug_dump,
+        shared_cache: values_of("--shared-cache").last().map(PathBuf::from),
+        github_fallback: options.contains(&"--github-fallback"),
+        federation,
+        provenance,
+        interactive,
+        write,
+        backup,
+        port,
+        host,
+    })
+}
+
+/// Formats of the --export option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Export {
+    Nix,
+    Bazel,
+    BazelJson,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Solve,
+    Outdated,
+    AsApp,
+    Tree,
+    Compare,
+    Check,
+    Stats,
+    StaleBounds,
+    BenchRegistry,
+    Search,
+    Doctor,
+    Cache,
+    Repair,
+    Infer,
+    Lint,
+    Serve,
+    Watchlist,
+    Notify,
 }
 
-fn run(
+struct Args {
+    command: Command,
     maybe_pkg_version: Option<PkgVersion>,
     offline: bool,
     online_strat: Option<VersionStrategy>,
     use_test: bool,
-    extras: &[(Pkg, Constraint)],
-) -> anyhow::Result<()> {
-    let elm_version = "0.19.1";
+    extras: Vec<(Pkg, Constraint)>,
+    optional_extras: Vec<OptionalExtra>,
+    extra_mode: ExtraMode,
+    review: bool,
+    prefer_cached_solution: bool,
+    policies: Policies,
+    renames: Renames,
+    follow_renames: bool,
+    ecosystem: Ecosystem,
+    inject_mandatory: bool,
+    known_good: Option<KnownGoodSet>,
+    escaped: BTreeSet<Pkg>,
+    overrides: Vec<PackageOverride>,
+    max_decisions: Option<usize>,
+    max_memory: Option<usize>,
+    footprint_limits: FootprintLimits,
+    min_release_age: Option<Duration>,
+    root_pinning: RootPinning,
+    tie_break: TieBreak,
+    indirect_pins: IndirectPins,
+    no_solve_cache: bool,
+    progress: bool,
+    trusted_authors: TrustedAuthors,
+    isolate_test: bool,
+    compare_modes: bool,
+    race: bool,
+    race_all: bool,
+    minimize_conflict: bool,
+    watch: bool,
+    why: Option<Pkg>,
+    export: Option<Export>,
+    health: bool,
+    tree_options: TreeOptions,
+    invert: Option<Pkg>,
+    source_dirs: Vec<String>,
+    objective: Option<Objective>,
+    candidates: usize,
+    record_test_case: Option<PathBuf>,
+    debug_dump: Option<PathBuf>,
+    shared_cache: Option<PathBuf>,
+    github_fallback: bool,
+    federation: Federation,
+    provenance: Option<PathBuf>,
+    #[cfg_attr(not(feature = "interactive"), allow(dead_code))]
+    interactive: bool,
+    write: bool,
+    backup: bool,
+    #[cfg_attr(not(feature = "serve"), allow(dead_code))]
+    port: u16,
+    #[cfg_attr(not(feature = "serve"), allow(dead_code))]
+    host: IpAddr,
+}
+
+fn run(args: Args) -> anyhow::Result<()> {
+    // Define an offline solver.
+    let limits = Limits {
+        max_decisions: args.max_decisions,
+        max_memory: args.max_memory,
+        cancel: None,
+    };
+    let recorder = Recorder::new();
+    let mut offline_solver = solver::Offline::new(elm_home(), &elm_versions()[0])
+        .with_policies(args.policies.clone())
+        .with_limits(limits)
+        .with_root_pinning(args.root_pinning)
+        .with_tie_break(args.tie_break)
+        .with_indirect_pins(args.indirect_pins)
+        .with_clock(clock());
+    if elm_versions().len() > 1 {
+        offline_solver = offline_solver.with_elm_versions(elm_versions());
+    }
+    if !args.overrides.is_empty() {
+        offline_solver = (offline_solver.with_overrides(&args.overrides))
+            .context("Failed to load the overridden packages")?;
+    }
+    if args.record_test_case.is_some() {
+        offline_solver = offline_solver.with_recorder(recorder.clone());
+    }
+    let tracer = TraceRecorder::new();
+    let mut progress = args.debug_dump.as_ref().map(|_| tracer.progress());
+    if args.progress {
+        let ndjson = Progress::new(|event| {
+            eprintln!("{}", serde_json::to_string(event).unwrap_or_default())
+        });
+        progress = Some(match progress {
+            Some(progress) => progress.and(ndjson),
+            None => ndjson,
+        });
+    }
+    if let Some(progress) = progress {
+        offline_solver = offline_solver.with_progress(progress);
+    }
+    let solve = |project_elm_json: &ProjectConfig,
+                 use_test: bool,
+                 extras: &[(Pkg, Constraint)],
+                 extra_mode: ExtraMode| {
+        solve_deps(
+            &args,
+            &offline_solver,
+            project_elm_json,
+            use_test,
+            extras,
+            extra_mode,
+        )
+    };
+
+    // Solve again on every change of elm.json.
+    if args.watch {
+        return watch::run(&args, &offline_solver);
+    }
+
+    // Repair the versions pinned in elm.json.
+    if args.command == Command::Repair {
+        return repair::run(&args, &offline_solver);
+    }
+
+    // Answer solving requests over HTTP.
+    #[cfg(feature = "serve")]
+    if args.command == Command::Serve {
+        return serve::run(&args, &offline_solver);
+    }
+
+    // Solve both the project and its elm-review configuration.
+    if args.review {
+        let solution =
+            review::solve_with_review(".", args.use_test, &args.extras, args.extra_mode, solve)?;
+        report_diagnostics(&offline_solver);
+        print!("{}", solution.to_canonical_json());
+        return Ok(());
+    }
 
     // Load the elm.json of the package given as argument or of the current folder.
-    let project_elm_json: ProjectConfig = match maybe_pkg_version {
-        Some(pkg_version) => {
-            let pkg_config = pkg_version
-                .load_config(elm_home(), elm_version)
-                .or_else(|_| pkg_version.load_from_cache(elm_home()))
-                .or_else(|_| {
-                    pkg_version.fetch_config(elm_home(), "https://package.elm-lang.org", http_fetch)
-                })
-                .context("Failed to load the elm.json config of the package to solve")?;
-            ProjectConfig::Package(pkg_config)
+    let project_elm_json = load_project_config(args.maybe_pkg_version.as_ref())?;
+
+    // Solve both offline and online, and compare the solutions.
+    if args.compare_modes {
+        let offline = offline_solver
+            .solve_deps(
+                &project_elm_json,
+                args.use_test,
+                &args.extras,
+                args.extra_mode,
+            )
+            .map_err(|err| format!("{:#}", handle_pubgrub_error(err)));
+        let strat = args.online_strat.unwrap_or(VersionStrategy::Newest);
+        let online = online_solver(&args, &offline_solver, strat)
+            .map_err(|err| format!("Failed to initialize the online solver: {:#}", err))
+            .and_then(|online_solver| {
+                online_solver
+                    .solve_deps(
+                        &project_elm_json,
+                        args.use_test,
+                        &args.extras,
+                        args.extra_mode,
+                    )
+                    .map_err(|err| format!("{:#}", handle_pubgrub_error(err)))
+            });
+        report_diagnostics(&offline_solver);
+        let comparison = ModesComparison::new(offline, online);
+        println!("{}", serde_json::to_string_pretty(&comparison)?);
+        match (comparison.offline_sufficient, comparison.identical()) {
+            (true, true) => eprintln!("Solving offline is sufficient, with the same solution"),
+            (true, false) => eprintln!("Solving offline is sufficient, with a different solution"),
+            (false, _) => eprintln!("Solving offline is not sufficient"),
+        }
+        return Ok(());
+    }
+
+    // Race several strategies in parallel.
+    if args.race || args.race_all {
+        return race::run(&args, &offline_solver, &project_elm_json);
+    }
+
+    // Solve normal dependencies first, then test dependencies on top of them.
+    if args.isolate_test {
+        let solution =
+            phased::solve_in_phases(&project_elm_json, &args.extras, args.extra_mode, solve)?;
+        report_diagnostics(&offline_solver);
+        write_provenance(&args, &offline_solver, &solution.combined())?;
+        if args.write {
+            write_solution(
+                &args,
+                &solution.dependencies,
+                Some(&solution.test_dependencies),
+            )?;
+        } else {
+            print!("{}", solution.to_canonical_json());
+        }
+        return Ok(());
+    }
+
+    // Solve with the optional extras that can be satisfied.
+    if !args.optional_extras.is_empty() {
+        let outcome = optional::solve_with_optional_extras(
+            &project_elm_json,
+            args.use_test,
+            &args.extras,
+            args.extra_mode,
+            &args.optional_extras,
+            solve,
+            error_code,
+        )?;
+        report_diagnostics(&offline_solver);
+        for extra in &outcome.included {
+            eprintln!("Included the optional extra {}", extra);
+        }
+        for extra in &outcome.skipped {
+            eprintln!("Skipped the optional extra {}, no version fits", extra);
+        }
+        write_provenance(&args, &offline_solver, &outcome.solution)?;
+        if args.write {
+            write_solution(&args, &outcome.solution, None)?;
+        } else {
+            print!("{}", outcome.solution.to_canonical_json());
+        }
+        return Ok(());
+    }
+
+    // Reuse the previous solution if it was solved with the same inputs.
+    let cached_solution = if args.prefer_cached_solution {
+        CachedSolution::load(elm_home()).ok().and_then(|cached| {
+            cached
+                .verify(
+                    &project_elm_json,
+                    args.use_test,
+                    &args.extras,
+                    args.extra_mode,
+                )
+                .filter(|solution| allowed_by_policies(solution, &args.policies))
+                .cloned()
+        })
+    } else {
+        None
+    };
+
+    let solution = match cached_solution {
+        Some(solution) => {
+            eprintln!("Reusing the previous solution");
+            solution
+        }
+        None => {
+            let result = solve_memoized(
+                &args,
+                &offline_solver,
+                &project_elm_json,
+                args.use_test,
+                &args.extras,
+                args.extra_mode,
+                |project_elm_json, extras| match args.objective {
+                    Some(objective) => {
+                        solve_best(&args, &offline_solver, project_elm_json, extras, objective)
+                    }
+                    None => solve(project_elm_json, args.use_test, extras, args.extra_mode),
+                },
+            );
+            if let Some(dir) = &args.record_test_case {
+                let test_case = recorder.test_case(
+                    &project_elm_json,
+                    args.use_test,
+                    &args.extras,
+                    args.extra_mode,
+                    result.as_ref().ok(),
+                );
+                test_case
+                    .save(dir)
+                    .context(format!("Failed to save the test case in {}", dir.display()))?;
+                eprintln!("Saved the test case in {}", dir.display());
+            }
+            if let Some(path) = &args.debug_dump {
+                write_debug_dump(path, &tracer)?;
+            }
+            let solution = match result {
+                Err(_) if args.minimize_conflict => {
+                    return Err(minimize_conflict(&args, &offline_solver, &project_elm_json));
+                }
+                result => result?,
+            };
+            save_cached_solution(&project_elm_json, &args, &solution);
+            solution
+        }
+    };
+    report_diagnostics(&offline_solver);
+    write_provenance(&args, &offline_solver, &solution)?;
+    report_policies(&solution, &args.policies);
+    report_overrides(&solution, &args.overrides);
+    if let Some(set) = &args.known_good {
+        let unlisted = set.unlisted(&solution, &args.escaped);
+        if !unlisted.is_empty() {
+            let unlisted: Vec<String> = unlisted.iter().map(|pkg| pkg.to_string()).collect();
+            anyhow::bail!(
+                "The solution has packages outside of the known good set, \
+                add them to the set or release them with --escape: {}",
+                unlisted.join(", ")
+            );
+        }
+    }
+    for warning in args.trusted_authors.audit(&solution) {
+        eprintln!("Warning: {}", warning);
+    }
+    check_footprint(&args, &project_elm_json, &solution)?;
+
+    #[cfg(feature = "interactive")]
+    if args.interactive {
+        return run_interactive(&args, &project_elm_json, solution, solve);
+    }
+
+    match args.command {
+        Command::Solve if args.write && args.use_test => {
+            // The solution mixes normal and test dependencies, split them.
+            let (_, graph) = solution_graph(&args, &project_elm_json, &solution)?;
+            let phased = phased::split(&project_elm_json, &solution, &graph);
+            write_solution(&args, &phased.dependencies, Some(&phased.test_dependencies))?;
+        }
+        Command::Solve if args.write => {
+            write_solution(&args, &solution, None)?;
+        }
+        Command::Solve if args.why.is_some() => {
+            let pkg = args.why.as_ref().unwrap();
+            print_why(&args, &project_elm_json, &solution, pkg)?;
+        }
+        Command::Solve if args.export.is_some() => {
+            export_solution(&args, &solution)?;
+        }
+        Command::Solve => {
+            // Write solution to stdout.
+            print!("{}", solution.to_canonical_json());
+        }
+        Command::Outdated => {
+            let versions_cache = load_versions_cache(args.offline)?;
+            let rows = report::outdated(&solution, &versions_cache);
+            if args.health {
+                let health = solution_health(&args, &solution, &versions_cache)?;
+                outdated::print_table(&rows, Some(&health));
+            } else {
+                outdated::print_table(&rows, None);
+            }
+        }
+        Command::Tree => {
+            print_tree(&args, &project_elm_json, &solution)?;
+        }
+        Command::AsApp => {
+            let package = match &project_elm_json {
+                ProjectConfig::Package(package) => package,
+                ProjectConfig::Application(_) => anyhow::bail!("as-app expects a package"),
+            };
+            let source_dirs = args.source_dirs.clone();
+            let application =
+                ProjectConfig::package_to_application(package, &solution, source_dirs)?;
+            print!("{}", ProjectConfig::Application(application).to_elm_json());
+        }
+        Command::Compare
+        | Command::Check
+        | Command::Stats
+        | Command::StaleBounds
+        | Command::BenchRegistry
+        | Command::Search
+        | Command::Doctor
+        | Command::Cache
+        | Command::Repair
+        | Command::Infer
+        | Command::Lint
+        | Command::Serve
+        | Command::Watchlist
+        | Command::Notify => {
+            unreachable!("handled before solving")
+        }
+    }
+    Ok(())
+}
+
+/// Browse the solution in a terminal user interface.
+#[cfg(feature = "interactive")]
+fn run_interactive<Solve>(
+    args: &Args,
+    project_elm_json: &ProjectConfig,
+    solution: AppDependencies,
+    solve: Solve,
+) -> anyhow::Result<()>
+where
+    Solve: Fn(
+        &ProjectConfig,
+        bool,
+        &[(Pkg, Constraint)],
+        ExtraMode,
+    ) -> anyhow::Result<AppDependencies>,
+{
+    let versions_cache = load_versions_cache(args.offline)?;
+    let solve_project = |use_test| solve(project_elm_json, use_test, &args.extras, args.extra_mode);
+    let load_config = |pkg_version: &PkgVersion| {
+        if args.offline {
+            load_local_config(pkg_version).ok()
+        } else {
+            load_package_config(pkg_version).ok()
+        }
+    };
+    let write = |solution: &AppDependencies, test_solution: Option<&AppDependencies>| {
+        write_solution(args, solution, test_solution)
+    };
+    let writable = args.maybe_pkg_version.is_none()
+        && matches!(project_elm_json, ProjectConfig::Application(_));
+    let session = interactive::Session {
+        project: project_elm_json,
+        versions_cache: &versions_cache,
+        use_test: args.use_test,
+        solve: &solve_project,
+        load_config: &load_config,
+        write: if writable { Some(&write) } else { None },
+    };
+    interactive::run(session, solution)
+}
+
+/// Print the solution in the format of another build system.
+fn export_solution(args: &Args, solution: &AppDependencies) -> anyhow::Result<()> {
+    match args.export {
+        Some(Export::Nix) => {
+            // Tarballs are hashed once, then their sha256 is read from the cache.
+            let sources = if args.offline {
+                nix::fetch_sources(solution, elm_home(), &Frozen)
+            } else {
+                HTTP_CLIENT.with(|client| nix::fetch_sources(solution, elm_home(), client))
+            };
+            let sources = sources.context("Failed to hash the tarballs of packages")?;
+            print!("{}", nix::elm_srcs(&sources));
+        }
+        Some(export) => {
+            if args.offline {
+                anyhow::bail!(
+                    "--export bazel downloads the archives of packages, it cannot be offline"
+                );
+            }
+            let archives = HTTP_CLIENT
+                .with(|client| bazel::fetch_archives(solution, client))
+                .context("Failed to download the archives of packages")?;
+            if export == Export::Bazel {
+                print!("{}", bazel::to_bzl(&archives));
+            } else {
+                println!("{}", serde_json::to_string_pretty(&archives)?);
+            }
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+/// Print the dependency paths from the root of the project to a package of the solution.
+fn print_why(
+    args: &Args,
+    project_elm_json: &ProjectConfig,
+    solution: &AppDependencies,
+    pkg: &Pkg,
+) -> anyhow::Result<()> {
+    let (root, graph) = solution_graph(args, project_elm_json, solution)?;
+    let paths = graph::paths_between(&root.pkg, pkg, &graph, PathLimits::default());
+    if paths.is_empty() {
+        anyhow::bail!("{} is not part of the solution", pkg);
+    }
+    for path in &paths {
+        println!("{}", path);
+    }
+    Ok(())
+}
+
+/// Print the dependency tree of the solution,
+/// or of the packages depending on a package with --invert.
+fn print_tree(
+    args: &Args,
+    project_elm_json: &ProjectConfig,
+    solution: &AppDependencies,
+) -> anyhow::Result<()> {
+    let (root, graph) = solution_graph(args, project_elm_json, solution)?;
+    let tree = match &args.invert {
+        Some(pkg) if graph.version_of(pkg).is_none() => {
+            anyhow::bail!("{} is not part of the solution", pkg)
+        }
+        Some(pkg) => graph::render_tree(&graph.inverted(), pkg, &args.tree_options),
+        None => graph::render_tree(&graph, &root.pkg, &args.tree_options),
+    };
+    print!("{}", tree);
+    Ok(())
+}
+
+/// Check the footprint of the solution against --max-packages and --max-depth.
+fn check_footprint(
+    args: &Args,
+    project_elm_json: &ProjectConfig,
+    solution: &AppDependencies,
+) -> anyhow::Result<()> {
+    if args.footprint_limits.is_unbounded() {
+        return Ok(());
+    }
+    let (root, graph) = solution_graph(args, project_elm_json, solution)?;
+    let footprint = Footprint::measure(&graph, &root.pkg);
+    args.footprint_limits.check(&footprint)?;
+    Ok(())
+}
+
+/// Dependency graph of a solution, with the root of the project.
+fn solution_graph(
+    args: &Args,
+    project_elm_json: &ProjectConfig,
+    solution: &AppDependencies,
+) -> anyhow::Result<(RootDependencies, DependencyGraph)> {
+    let root = RootDependencies::new(
+        project_elm_json,
+        args.use_test,
+        &args.extras,
+        args.extra_mode,
+    )
+    .map_err(handle_pubgrub_error)?;
+    let load_config = |pkg: &Pkg, version| {
+        load_package_config(&PkgVersion {
+            author_pkg: pkg.clone(),
+            version,
+        })
+    };
+    let graph = DependencyGraph::of_solution(&root, solution, load_config)?;
+    Ok((root, graph))
+}
+
+/// Search for a minimal set of direct dependencies that cannot be solved together,
+/// and return the error explaining their conflict.
+#[allow(clippy::result_large_err)]
+fn minimize_conflict(
+    args: &Args,
+    offline_solver: &solver::Offline,
+    project_elm_json: &ProjectConfig,
+) -> anyhow::Error {
+    // Initialize the online solver only once, instead of for every attempt.
+    let online_solver = if args.offline {
+        None
+    } else {
+        let strat = args.online_strat.unwrap_or(VersionStrategy::Newest);
+        match online_solver(args, offline_solver, strat) {
+            Ok(online_solver) => Some(online_solver),
+            Err(err) => {
+                return anyhow::Error::from(err).context("Failed to initialize the online solver")
+            }
+        }
+    };
+    let solve = |project_elm_json: &ProjectConfig,
+                 use_test: bool,
+                 extras: &[(Pkg, Constraint)],
+                 extra_mode: ExtraMode| match &online_solver {
+        Some(online_solver) => {
+            online_solver.solve_deps(project_elm_json, use_test, extras, extra_mode)
+        }
+        None => offline_solver.solve_deps(project_elm_json, use_test, extras, extra_mode),
+    };
+    eprintln!("Searching for a minimal set of conflicting direct dependencies");
+    let conflict = conflict::minimize_conflict(
+        project_elm_json,
+        args.use_test,
+        &args.extras,
+        args.extra_mode,
+        solve,
+    );
+    match conflict {
+        Some(conflict) => {
+            let pkgs: Vec<String> = conflict.packages.iter().map(|p| p.to_string()).collect();
+            eprintln!(
+                "Found after {} attempts, these direct dependencies cannot be solved together: {}",
+                conflict.attempts,
+                pkgs.join(", ")
+            );
+            handle_pubgrub_error(conflict.error)
+        }
+        None => anyhow::anyhow!("Solving succeeded when searching for a minimal conflict"),
+    }
+}
+
+/// Write the solution into the elm.json of the current directory.
+///
+/// Test dependencies are only updated when a solution for them is provided.
+fn write_solution(
+    args: &Args,
+    solution: &AppDependencies,
+    test_solution: Option<&AppDependencies>,
+) -> anyhow::Result<()> {
+    if args.backup {
+        std::fs::copy("elm.json", "elm.json.bak").context("Failed to backup elm.json")?;
+    }
+    ApplicationConfig::apply_and_write("elm.json", solution, test_solution)
+        .context("Failed to write the solution into elm.json")?;
+    eprintln!("Updated elm.json");
+    Ok(())
+}
+
+/// Record the obsolete dependencies of the project as diagnostics.
+fn report_renames(offline_solver: &solver::Offline, renames: &[Rename], replaced: bool) {
+    for rename in renames {
+        offline_solver.diagnostics().push(Diagnostic::Renamed {
+            from: rename.from.clone(),
+            to: rename.to.clone(),
+            replaced,
+        });
+    }
+}
+
+/// Print to stderr the warnings recorded while solving.
+fn report_diagnostics(offline_solver: &solver::Offline) {
+    for diagnostic in offline_solver.diagnostics().take() {
+        eprintln!("Warning: {}", diagnostic);
+    }
+}
+
+/// Write where the config of each package of the solution was loaded from,
+/// if requested with --provenance.
+fn write_provenance(
+    args: &Args,
+    offline_solver: &solver::Offline,
+    solution: &AppDependencies,
+) -> anyhow::Result<()> {
+    if let Some(path) = &args.provenance {
+        let report = offline_solver.provenance().report(solution);
+        std::fs::write(path, serde_json::to_string_pretty(&report)?).context(format!(
+            "Failed to write the provenance into {}",
+            path.display()
+        ))?;
+    }
+    Ok(())
+}
+
+/// Check that a solution only contains versions allowed by the policies.
+fn allowed_by_policies(solution: &AppDependencies, policies: &Policies) -> bool {
+    (solution.direct.iter())
+        .chain(solution.indirect.iter())
+        .all(|(pkg, version)| policies.allows(pkg, version))
+}
+
+/// Print to stderr the policies which excluded versions of packages in the solution.
+fn report_policies(solution: &AppDependencies, policies: &Policies) {
+    let versions_cache = Cache::load(elm_home()).unwrap_or_default();
+    let list_available_versions = |pkg: &Pkg| {
+        let versions = versions_cache.cache.get(pkg);
+        versions.into_iter().flatten().copied()
+    };
+    for effect in policies.effects(solution, list_available_versions) {
+        let excluded: Vec<String> = effect.excluded.iter().map(|v| v.to_string()).collect();
+        eprint!(
+            "Policy on {} (selected {}) excluded versions {}",
+            effect.pkg,
+            effect.selected,
+            excluded.join(", ")
+        );
+        match effect.reason {
+            Some(reason) => eprintln!(": {}", reason),
+            None => eprintln!(),
+        }
+    }
+}
+
+fn report_overrides(solution: &AppDependencies, overrides: &[PackageOverride]) {
+    for package_override in overrides {
+        let pkg = &package_override.pkg;
+        let version = (solution.direct.get(pkg)).or_else(|| solution.indirect.get(pkg));
+        if let Some(version) = version {
+            eprintln!(
+                "Override of {} {} from {}",
+                pkg, version, package_override.source
+            );
+        }
+    }
+}
+
+/// Record the solution, to be reused by the next invocation with --prefer-cached-solution.
+fn save_cached_solution(project_elm_json: &ProjectConfig, args: &Args, solution: &AppDependencies) {
+    let saved = record_solution(
+        project_elm_json,
+        args.use_test,
+        &args.extras,
+        args.extra_mode,
+        solution,
+    )
+    .and_then(|cached| Ok(cached.save(elm_home())?));
+    if let Err(err) = saved {
+        eprintln!("Failed to record the solution for later reuse: {:#}", err);
+    }
+}
+
+/// Record a solution with the dependencies of its packages, to verify it when reusing it.
+fn record_solution(
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    extras: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+    solution: &AppDependencies,
+) -> anyhow::Result<CachedSolution> {
+    let fetch_elm_json = |pkg: &Pkg, version| {
+        let pkg_version = PkgVersion {
+            author_pkg: pkg.clone(),
+            version,
+        };
+        load_local_config(&pkg_version)
+    };
+    let cached = CachedSolution::record(
+        project_elm_json,
+        use_test,
+        extras,
+        extra_mode,
+        solution.clone(),
+        fetch_elm_json,
+    )?;
+    Ok(cached)
+}
+
+/// Reuse the memoized solution of identical inputs, or solve and memoize the solution,
+/// unless disabled with --no-solve-cache.
+///
+/// Memoized solutions are keyed and verified with the effective inputs,
+/// after renames and mandatory packages are applied, which are also given to `solve`.
+fn solve_memoized<F>(
+    args: &Args,
+    offline_solver: &solver::Offline,
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    extras: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+    solve: F,
+) -> anyhow::Result<AppDependencies>
+where
+    F: FnOnce(&ProjectConfig, &[(Pkg, Constraint)]) -> anyhow::Result<AppDependencies>,
+{
+    let (project_elm_json, extras) =
+        effective_inputs(args, offline_solver, project_elm_json, use_test, extras);
+    let (project_elm_json, extras) = (project_elm_json.as_ref(), extras.as_ref());
+    let key = match solve_memo_key(args, project_elm_json, use_test, extras, extra_mode) {
+        Some(key) => key,
+        None => return solve(project_elm_json, extras),
+    };
+    let memo = SolveMemo::new(elm_home());
+    let memoized = memo.get(&key).and_then(|cached| {
+        cached
+            .verify(project_elm_json, use_test, extras, extra_mode)
+            .filter(|solution| allowed_by_policies(solution, &args.policies))
+            .cloned()
+    });
+    if let Some(solution) = memoized {
+        eprintln!("Reusing the memoized solution of identical inputs");
+        return Ok(solution);
+    }
+    let solution = solve(project_elm_json, extras)?;
+    let memoized = record_solution(project_elm_json, use_test, extras, extra_mode, &solution)
+        .and_then(|cached| Ok(memo.insert(&key, &cached)?));
+    if let Err(err) = memoized {
+        eprintln!("Failed to memoize the solution: {:#}", err);
+    }
+    Ok(solution)
+}
+
+/// Write the trace of the resolutions into a file, and print its hot spots.
+fn write_debug_dump(path: &Path, tracer: &TraceRecorder) -> anyhow::Result<()> {
+    let trace = tracer.trace();
+    std::fs::write(path, serde_json::to_string_pretty(&trace)?).context(format!(
+        "Failed to write the debug dump into {}",
+        path.display()
+    ))?;
+    let summary = trace.summarize();
+    eprintln!(
+        "Saved the debug dump in {}: {} decisions, {} backtracks, {} conflicts in {} ms",
+        path.display(),
+        summary.decisions,
+        summary.backtracks,
+        summary.conflicts,
+        summary.duration_us / 1000
+    );
+    for spot in summary.hot_spots.iter().take(5) {
+        eprintln!(
+            "    {}: {} decisions, {} backtracks, {} conflicts, {} ms loading",
+            spot.pkg,
+            spot.decisions,
+            spot.backtracks,
+            spot.conflicts,
+            spot.load_us / 1000
+        );
+    }
+    Ok(())
+}
+
+/// Key of the memoized solution of a resolution, covering every option changing the solution,
+/// the package server, the packages installed in `ELM_HOME`,
+/// and the number of package versions known, such that publications invalidate it.
+/// There is none with --no-solve-cache, or when the resolution itself must be observed,
+/// to record a test case or the provenance of package configs,
+/// nor with --min-release-age, whose solutions change as time passes.
+fn solve_memo_key(
+    args: &Args,
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    extras: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+) -> Option<String> {
+    if args.no_solve_cache
+        || args.record_test_case.is_some()
+        || args.provenance.is_some()
+        || args.debug_dump.is_some()
+        || args.min_release_age.is_some()
+        || !args.overrides.is_empty()
+    {
+        return None;
+    }
+    let policies = serde_json::to_value(&args.policies).ok()?;
+    let federation = serde_json::to_value(&args.federation).ok()?;
+    let renames = serde_json::to_value(&args.renames).ok()?;
+    let installed: BTreeMap<&String, Cache> = (elm_versions().iter())
+        .map(|v| Cache::scan_all_installed(elm_home(), v).map(|cache| (v, cache)))
+        .collect::<Result<_, _>>()
+        .ok()?;
+    let installed = serde_json::to_value(&installed).ok()?;
+    let configuration = format!(
+        "elm-solve-deps {} offline={} strategy={:?} root-pinning={} tie-break={} \
+        indirect-pins={} objective={:?} candidates={} policies={} federation={} \
+        elm-versions={} remote={} github-fallback={} installed={} \
+        follow-renames={} renames={} ecosystem={} inject-mandatory={}",
+        env!("CARGO_PKG_VERSION"),
+        args.offline,
+        args.online_strat,
+        args.root_pinning,
+        args.tie_break,
+        args.indirect_pins,
+        args.objective,
+        args.candidates,
+        policies,
+        federation,
+        elm_versions().join(","),
+        remote(),
+        args.github_fallback,
+        installed,
+        args.follow_renames,
+        renames,
+        args.ecosystem,
+        args.inject_mandatory,
+    );
+    let registry_sequence = Cache::load(elm_home()).map_or(0, |cache| cache.versions_count())
+        + (args.federation.remotes.iter())
+            .map(|r| Cache::load_remote(elm_home(), &r.name).map_or(0, |c| c.versions_count()))
+            .sum::<usize>();
+    let key = SolveMemo::key(
+        project_elm_json,
+        use_test,
+        extras,
+        extra_mode,
+        &configuration,
+        registry_sequence,
+    );
+    key.ok()
+}
+
+/// Assess the health of the packages of a solution.
+///
+/// Release times are loaded from the cache of the --min-release-age option,
+/// and fetched again for packages with new versions unless offline.
+/// Dependents are counted among the latest versions of the packages available locally.
+fn solution_health(
+    args: &Args,
+    solution: &AppDependencies,
+    versions_cache: &Cache,
+) -> anyhow::Result<Vec<PackageHealth>> {
+    let mut release_times = ReleaseTimes::new();
+    let remotes: &[&str] = if args.offline { &[] } else { &[remote()] };
+    for (pkg, version) in solution.direct.iter().chain(solution.indirect.iter()) {
+        let versions: Vec<SemVer> = match versions_cache.cache.get(pkg) {
+            Some(versions) => versions.iter().copied().collect(),
+            None => vec![*version],
+        };
+        let ensured = release_times.ensure(elm_home(), pkg, &versions, remotes, &http_fetch);
+        if let Err(err) = ensured {
+            eprintln!("Warning: failed to fetch the releases of {}: {}", pkg, err);
+        }
+    }
+    let configs = elm_solve_deps::stats::latest_configs(versions_cache, |pkg_version| {
+        load_local_config(pkg_version).ok()
+    });
+    let dependents = elm_solve_deps::stats::reverse_dependencies(&configs);
+    let thresholds = HealthThresholds::default();
+    Ok(health::assess(
+        solution,
+        &release_times,
+        &dependents,
+        &thresholds,
+        clock(),
+    ))
+}
+
+/// Load the versions cache, and update it with the package server unless offline.
+fn load_versions_cache(offline: bool) -> anyhow::Result<Cache> {
+    let mut versions_cache = Cache::load(elm_home()).unwrap_or_default();
+    if !offline {
+        let update = versions_cache
+            .update_with_resync(elm_home(), remote(), &http_fetch, clock())
+            .context("Failed to update the versions cache")?;
+        versions_cache.save(elm_home())?;
+        check_registry_pin();
+        warn_removed_versions(&update);
+    }
+    Ok(versions_cache)
+}
+
+/// Warn about package versions deleted from the package server,
+/// especially the ones pinned by the elm.json of the current application.
+fn warn_removed_versions(update: &CacheUpdate) {
+    if update.removed.is_empty() {
+        return;
+    }
+    eprintln!(
+        "{} package versions were deleted from the package server",
+        update.removed.len()
+    );
+    if let Ok(ProjectConfig::Application(app)) = load_project_config(None) {
+        let pinned = (update.removed_from(&app.dependencies))
+            .into_iter()
+            .chain(update.removed_from(&app.test_dependencies));
+        for pkg_version in pinned {
+            eprintln!(
+                "Warning: elm.json depends on {}, which was deleted from the package server",
+                pkg_version
+            );
         }
+    }
+}
+
+/// Load the elm.json of the package given as argument or of the current folder.
+fn load_project_config(maybe_pkg_version: Option<&PkgVersion>) -> anyhow::Result<ProjectConfig> {
+    match maybe_pkg_version {
+        Some(pkg_version) => Ok(ProjectConfig::Package(load_package_config(pkg_version)?)),
         None => {
             let elm_json_str = std::fs::read_to_string("elm.json")
                 .context("Are you in an elm project? there was an issue loading the elm.json")?;
-            serde_json::from_str(&elm_json_str).context("Failed to decode the elm.json")?
+            serde_json::from_str(&elm_json_str).context("Failed to decode the elm.json")
+        }
+    }
+}
+
+/// Load the elm.json of a package installed for one of the versions of elm
+/// given with --elm-version, in order of preference, or from the dependency solver cache.
+///
+/// Installed packages are looked up with the registry view of an offline solver,
+/// like the resolutions do.
+fn load_local_config(pkg_version: &PkgVersion) -> Result<PackageConfig, PkgVersionError> {
+    let (pkg, version) = (&pkg_version.author_pkg, pkg_version.version);
+    match INSTALLED.with(|installed| installed.config(pkg, version)) {
+        Ok(Some(config)) => Ok(config),
+        _ => load_from_cache(pkg_version),
+    }
+}
+
+/// Load the elm.json of a package from the dependency solver cache,
+/// from its own file, or from the packed store.
+fn load_from_cache(pkg_version: &PkgVersion) -> Result<PackageConfig, PkgVersionError> {
+    pkg_version.load_from_cache(elm_home()).or_else(|err| {
+        PACKED_STORE.with(|packed| match packed {
+            Some(packed) if packed.contains(pkg_version) => packed.load_config(pkg_version),
+            _ => Err(err),
+        })
+    })
+}
+
+/// Load the elm.json of a package, from ELM_HOME, the cache or the package server,
+/// unless it is known to be missing on the package server.
+/// A package version not found is remembered as missing,
+/// or its whole package if the versions cache does not list it.
+fn load_package_config(pkg_version: &PkgVersion) -> anyhow::Result<PackageConfig> {
+    let loaded = load_local_config(pkg_version);
+    if let Ok(config) = loaded {
+        return Ok(config);
+    }
+    let mut missing = MissingCache::load(elm_home()).unwrap_or_default();
+    if missing.is_missing(pkg_version, clock()) {
+        anyhow::bail!(
+            "{} is known to be missing on the package server, \
+            run \"elm-solve-deps cache forget-missing {}\" to check again",
+            pkg_version,
+            pkg_version.author_pkg
+        );
+    }
+    let fetched = pkg_version.fetch_config(elm_home(), remote(), &http_fetch);
+    if let Err(PkgVersionError::FetchError {
+        source: FetchError::NotFound,
+        ..
+    }) = &fetched
+    {
+        let versions_cache = Cache::load(elm_home()).unwrap_or_default();
+        if versions_cache.cache.contains_key(&pkg_version.author_pkg) {
+            missing.record_version(pkg_version, clock());
+        } else {
+            missing.record_package(&pkg_version.author_pkg, clock());
         }
+        missing.prune(clock());
+        let _ = missing.save(elm_home());
+    }
+    fetched.context(format!(
+        "Failed to load the elm.json config of {}",
+        pkg_version
+    ))
+}
+
+/// Project config and additional constraints actually solved,
+/// with obsolete dependencies replaced by their successors and mandatory packages added,
+/// if requested, and the corresponding diagnostics recorded.
+fn effective_inputs<'a>(
+    args: &Args,
+    offline_solver: &solver::Offline,
+    project_elm_json: &'a ProjectConfig,
+    use_test: bool,
+    extras: &'a [(Pkg, Constraint)],
+) -> (Cow<'a, ProjectConfig>, Cow<'a, [(Pkg, Constraint)]>) {
+    // Warn about obsolete dependencies, or replace them by their successors.
+    let (project_elm_json, extras) = if args.follow_renames {
+        let renamed = args.renames.apply(project_elm_json, use_test, extras);
+        report_renames(offline_solver, &renamed.renamed, true);
+        let extras = Cow::Owned(renamed.additional_constraints);
+        (Cow::Owned(renamed.project), extras)
+    } else {
+        let obsolete = args
+            .renames
+            .obsolete_dependencies(project_elm_json, use_test);
+        report_renames(offline_solver, &obsolete, false);
+        (Cow::Borrowed(project_elm_json), Cow::Borrowed(extras))
     };
 
-    // Define an offline solver.
-    let offline_solver = solver::Offline::new(elm_home(), "0.19.1");
+    // Warn about missing mandatory packages, or add them to the resolution.
+    let missing = (args.ecosystem).missing_packages(&project_elm_json, &extras);
+    for pkg in &missing {
+        offline_solver
+            .diagnostics()
+            .push(Diagnostic::MissingMandatory {
+                pkg: pkg.clone(),
+                injected: args.inject_mandatory,
+            });
+    }
+    if args.inject_mandatory && !missing.is_empty() {
+        let injected = args.ecosystem.inject(&project_elm_json, &extras);
+        (project_elm_json, Cow::Owned(injected))
+    } else {
+        (project_elm_json, extras)
+    }
+}
+
+/// Solve dependencies of a project config with the connectivity and strategy of the arguments.
+/// Solving closures return the errors of pubgrub unchanged, however large they are.
+#[allow(clippy::result_large_err)]
+fn solve_deps(
+    args: &Args,
+    offline_solver: &solver::Offline,
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    extras: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+) -> anyhow::Result<AppDependencies> {
+    let (project_elm_json, extras) =
+        effective_inputs(args, offline_solver, project_elm_json, use_test, extras);
+    let (project_elm_json, extras) = (project_elm_json.as_ref(), extras.as_ref());
 
     // Define an online solver if needed.
-    let remote = "https://package.elm-lang.org";
-    let strat = online_strat.unwrap_or(VersionStrategy::Newest);
-    let mk_online_solver =
-        |offline_solver| solver::Online::new(offline_solver, remote, http_fetch, strat);
+    let strat = args.online_strat.unwrap_or(VersionStrategy::Newest);
+    let mk_online_solver = || online_solver(args, offline_solver, strat);
+    let locate = |err: PubGrubError<Pkg, SemVer>, solve: &SolveFn| {
+        let suggestions = match &err {
+            PubGrubError::NoSolution(tree) => conflict::suggest_resolutions(
+                tree,
+                project_elm_json,
+                use_test,
+                extras,
+                extra_mode,
+                MAX_SUGGESTION_CANDIDATES,
+                solve,
+            ),
+            _ => Vec::new(),
+        };
+        let err = located_pubgrub_error(err, project_elm_json, use_test, extras, extra_mode);
+        with_suggestions(err, &suggestions)
+    };
+    let solve_offline: &SolveFn = &|project, use_test, extras, extra_mode| {
+        offline_solver.solve_deps(project, use_test, extras, extra_mode)
+    };
 
-    let solution: AppDependencies = match (offline, online_strat) {
+    match (args.offline, args.online_strat) {
         (true, _) => {
             eprintln!("Solving offline");
             offline_solver
-                .solve_deps(&project_elm_json, use_test, extras)
-                .map_err(handle_pubgrub_error)?
+                .solve_deps(project_elm_json, use_test, extras, extra_mode)
+                .map_err(|err| locate(err, solve_offline))
         }
-        (false, None) => {
+        (false, None) if args.min_release_age.is_none() => {
             eprintln!("Trying to solve offline first");
             offline_solver
-                .solve_deps(&project_elm_json, use_test, extras)
+                .solve_deps(project_elm_json, use_test, extras, extra_mode)
                 .or_else(|_| {
                     eprintln!("Offline solving failed, switching to online");
-                    mk_online_solver(offline_solver)
-                        .context("Failed to initialize the online solver")?
-                        .solve_deps(&project_elm_json, use_test, extras)
-                        .map_err(handle_pubgrub_error)
-                })?
+                    let online_solver =
+                        mk_online_solver().context("Failed to initialize the online solver")?;
+                    let solve_online: &SolveFn = &|project, use_test, extras, extra_mode| {
+                        online_solver.solve_deps(project, use_test, extras, extra_mode)
+                    };
+                    (online_solver.solve_deps(project_elm_json, use_test, extras, extra_mode))
+                        .map_err(|err| locate(err, solve_online))
+                })
         }
-        (false, Some(_)) => {
+        (false, _) => {
             eprintln!("Solving online with strategy {:?}", &strat);
-            mk_online_solver(offline_solver)
-                .context("Failed to initialize the online solver")?
-                .solve_deps(&project_elm_json, use_test, extras)
-                .map_err(handle_pubgrub_error)?
+            let online_solver =
+                mk_online_solver().context("Failed to initialize the online solver")?;
+            let solve_online: &SolveFn = &|project, use_test, extras, extra_mode| {
+                online_solver.solve_deps(project, use_test, extras, extra_mode)
+            };
+            (online_solver.solve_deps(project_elm_json, use_test, extras, extra_mode))
+                .map_err(|err| locate(err, solve_online))
         }
-    };
+    }
+}
 
-    // Write solution to stdout.
-    println!("{}", serde_json::to_string_pretty(&solution)?);
-    Ok(())
+/// A resolution of a project, by one of the solvers.
+type SolveFn<'a> = dyn Fn(
+        &ProjectConfig,
+        bool,
+        &[(Pkg, Constraint)],
+        ExtraMode,
+    ) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>>
+    + 'a;
+
+/// Number of direct dependencies tried when suggesting how to resolve a conflict.
+const MAX_SUGGESTION_CANDIDATES: usize = 3;
+
+/// Append to the report of a failed resolution the changes of direct dependencies
+/// that would allow a solution.
+fn with_suggestions(err: anyhow::Error, suggestions: &[conflict::Suggestion]) -> anyhow::Error {
+    match err.downcast::<CodedError>() {
+        Ok(mut coded) if !suggestions.is_empty() => {
+            coded.message.push_str("\n\nPossible resolutions:");
+            for suggestion in suggestions {
+                coded.message.push_str(&format!("\n    {}", suggestion));
+            }
+            anyhow::Error::new(coded)
+        }
+        Ok(coded) => anyhow::Error::new(coded),
+        Err(err) => err,
+    }
+}
+
+/// Solve several times, excluding versions of the previous solutions,
+/// and keep the best solution according to the objective.
+fn solve_best(
+    args: &Args,
+    offline_solver: &solver::Offline,
+    project_elm_json: &ProjectConfig,
+    extras: &[(Pkg, Constraint)],
+    objective: Objective,
+) -> anyhow::Result<AppDependencies> {
+    let strat = args.online_strat.unwrap_or(VersionStrategy::Newest);
+    let base_online_solver = if args.offline {
+        eprintln!("Comparing {} solutions offline", args.candidates);
+        None
+    } else {
+        eprintln!("Comparing {} solutions online", args.candidates);
+        let created = online_solver(args, offline_solver, strat)
+            .context("Failed to initialize the online solver")?;
+        Some(created)
+    };
+    let (use_test, extra_mode) = (args.use_test, args.extra_mode);
+    let locate = |err| located_pubgrub_error(err, project_elm_json, use_test, extras, extra_mode);
+    let solve = |excluded: &[(Pkg, SemVer)]| {
+        let mut policies = args.policies.clone();
+        for (pkg, version) in excluded {
+            policies.exclude(pkg.clone(), *version);
+        }
+        match &base_online_solver {
+            None => (offline_solver.clone().with_policies(policies))
+                .solve_deps(project_elm_json, use_test, extras, extra_mode)
+                .map_err(locate),
+            Some(online) => (online.clone().with_policies(policies))
+                .solve_deps(project_elm_json, use_test, extras, extra_mode)
+                .map_err(locate),
+        }
+    };
+    let versions_cache = load_versions_cache(true)?;
+    let score = |solution: &AppDependencies| objective.score(solution, &versions_cache);
+    let best = scoring::solve_best(args.candidates, score, solve)?;
+    eprintln!(
+        "Picked the {} solution among {} candidates, with a score of {}",
+        objective, best.candidates, best.score
+    );
+    Ok(best.solution)
 }
 
 // Helper functions ######################################################################
 
+/// Parse a package constraint of the shape "author/package: constraint".
+fn parse_package_constraint(s: &str) -> anyhow::Result<(Pkg, Constraint)> {
+    let (pkg_str, range_str) = s
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Did not find the separator ':' in the constraint {}", s))?;
+    Ok((
+        Pkg::from_str(pkg_str.trim())?,
+        Constraint::from_str(range_str.trim())?,
+    ))
+}
+
+fn online_solver(
+    args: &Args,
+    offline_solver: &solver::Offline,
+    strat: VersionStrategy,
+) -> Result<solver::Online<impl HttpFetch + Clone + Send + 'static>, CacheError> {
+    let online_solver = solver::Online::new(offline_solver.clone(), remote(), http_fetch, strat)?;
+    check_registry_pin();
+    let online_solver = match &args.shared_cache {
+        Some(dir) => online_solver.with_shared_cache(SharedCache::new(dir)),
+        None => online_solver,
+    };
+    let online_solver = if args.github_fallback {
+        online_solver.with_config_fallbacks(GITHUB_FALLBACKS.to_vec())
+    } else {
+        online_solver
+    };
+    let online_solver = match args.min_release_age {
+        Some(min_age) => online_solver.with_min_release_age(min_age),
+        None => online_solver,
+    };
+    if args.federation.remotes.is_empty() {
+        Ok(online_solver)
+    } else {
+        online_solver.with_federation(args.federation.clone())
+    }
+}
+
 fn elm_home() -> PathBuf {
     match std::env::var_os("ELM_HOME") {
         None => default_elm_home(),
@@ -191,52 +2603,182 @@ fn default_elm_home() -> PathBuf {
         .join("elm")
 }
 
-fn http_fetch(url: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
-    ureq::get(url)
-        .timeout_connect(10_000)
-        .call()
-        .into_string()
-        .map_err(|e| e.into())
+/// Count the allocated bytes, to enforce --max-memory.
+#[cfg(feature = "max-memory")]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+thread_local! {
+    static HTTP_CLIENT: UreqFetcher = UreqFetcher::new();
+    static PACKED_STORE: Option<PackedStore> = PackedStore::open(elm_home()).ok();
+    static INSTALLED: solver::Offline =
+        solver::Offline::new(elm_home(), &elm_versions()[0]).with_elm_versions(elm_versions());
 }
 
-fn handle_pubgrub_error(err: PubGrubError<Pkg, SemVer>) -> anyhow::Error {
-    match err {
-        PubGrubError::NoSolution(tree) => {
-            anyhow::anyhow!(DefaultStringReporter::report(&tree))
+/// Whether errors are printed as JSON, set by the --error-format flag.
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Whether network requests are forbidden, set by the --frozen flag.
+static FROZEN: AtomicBool = AtomicBool::new(false);
+
+/// Url of the package server, set by the --remote option.
+static REMOTE: OnceLock<String> = OnceLock::new();
+
+/// Versions of elm whose installed packages are used, set by the --elm-version option.
+static ELM_VERSIONS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Source of the current time, fixed by the SOURCE_DATE_EPOCH environment variable.
+static CLOCK: OnceLock<Box<dyn Clock + Send + Sync>> = OnceLock::new();
+
+/// Check of the identity of the package server, done before the first request.
+static REGISTRY_CHECK: Once = Once::new();
+
+/// Url of the package server, or of the mirror given with --remote.
+fn remote() -> &'static str {
+    REMOTE.get_or_init(|| "https://package.elm-lang.org".to_string())
+}
+
+/// The current time, of the system unless SOURCE_DATE_EPOCH is set.
+fn clock() -> &'static (dyn Clock + Send + Sync) {
+    CLOCK.get_or_init(|| Box::new(SystemClock)).as_ref()
+}
+
+/// Versions of elm given with --elm-version, in order of preference, 0.19.1 by default.
+fn elm_versions() -> &'static [String] {
+    ELM_VERSIONS.get_or_init(|| vec!["0.19.1".to_string()])
+}
+
+/// Make a GET request, reusing connections to the package server between calls.
+/// Fail right away in frozen mode.
+fn http_fetch(url: &str) -> Result<String, FetchError> {
+    if FROZEN.load(Ordering::Relaxed) {
+        return Frozen.fetch(url);
+    }
+    HTTP_CLIENT.with(|client| client.fetch(url))
+}
+
+/// Identity of the package server, from the versions cache just updated with it.
+fn registry_identity() -> anyhow::Result<RegistryIdentity> {
+    let versions_cache = Cache::load(elm_home()).context("Failed to load the versions cache")?;
+    Ok(RegistryIdentity::of_versions(&versions_cache, clock()))
+}
+
+/// Pin the identity of the package server on first use,
+/// and warn loudly if it changed since then, once per run,
+/// after the versions cache was updated with the package server.
+/// Failing to check is not an error, the versions cache is checked again in the next runs.
+fn check_registry_pin() {
+    REGISTRY_CHECK.call_once(|| {
+        let Ok(identity) = registry_identity() else {
+            return;
+        };
+        let mut pinned_registries = PinnedRegistries::load(elm_home()).unwrap_or_default();
+        match pinned_registries.check(remote(), identity) {
+            PinCheck::Trusted | PinCheck::FirstUse => {
+                if let Err(err) = pinned_registries.save(elm_home()) {
+                    eprintln!(
+                        "Failed to pin the identity of the package server: {:#}",
+                        err
+                    );
+                }
+            }
+            PinCheck::Changed { pinned } => {
+                eprintln!(
+                    "WARNING: the package server at {} is not the one used before!",
+                    remote()
+                );
+                eprintln!(
+                    "WARNING: it lists other package versions than the {} ones it listed before,",
+                    pinned.versions
+                );
+                eprintln!("WARNING: the url may have been redirected to another registry.");
+                eprintln!(
+                    "WARNING: if this change is expected, trust the new registry with: cache trust-registry"
+                );
+            }
         }
+    });
+}
+
+/// Turn a failed resolution of a project into a printable error,
+/// locating the dependencies it is attributable to when the project is the one of elm.json.
+fn located_pubgrub_error(
+    err: PubGrubError<Pkg, SemVer>,
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    extras: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+) -> anyhow::Error {
+    let is_elm_json = load_project_config(None).is_ok_and(|project| &project == project_elm_json);
+    let root = RootDependencies::new(project_elm_json, use_test, extras, extra_mode);
+    let locations = match (is_elm_json, ElmJsonSource::load("elm.json"), root) {
+        (true, Some(source), Ok(root)) => source_span::locate_error(&err, &root.pkg, &source),
+        _ => Vec::new(),
+    };
+    anyhow::Error::new(CodedError {
+        locations,
+        ..coded_pubgrub_error(err)
+    })
+}
+
+fn handle_pubgrub_error(err: PubGrubError<Pkg, SemVer>) -> anyhow::Error {
+    anyhow::Error::new(coded_pubgrub_error(err))
+}
+
+/// Format a pubgrub error for users, keeping its code.
+fn coded_pubgrub_error(err: PubGrubError<Pkg, SemVer>) -> CodedError {
+    let code = err.code();
+    let message = match err {
+        PubGrubError::NoSolution(tree) => DefaultStringReporter::report(&tree),
         PubGrubError::ErrorRetrievingDependencies {
             package,
             version,
             source,
-        } => anyhow::anyhow!(
+        } => format!(
             "An error occured while trying to retrieve dependencies of {}@{}:\n\n{}",
-            package,
-            version,
-            source
+            package, version, source
         ),
         PubGrubError::DependencyOnTheEmptySet {
             package,
             version,
             dependent,
-        } => anyhow::anyhow!(
+        } => format!(
             "{}@{} has an imposible dependency on {}",
-            package,
-            version,
-            dependent
+            package, version, dependent
         ),
         PubGrubError::SelfDependency { package, version } => {
-            anyhow::anyhow!("{}@{} somehow depends on itself", package, version)
+            format!("{}@{} somehow depends on itself", package, version)
         }
-        PubGrubError::ErrorChoosingPackageVersion(err) => anyhow::anyhow!(
+        PubGrubError::ErrorChoosingPackageVersion(err) => format!(
             "There was an error while picking packages for dependency resolution:\n\n{}",
             err
         ),
-        PubGrubError::ErrorInShouldCancel(err) => {
-            anyhow::anyhow!("Dependency resolution was cancelled.\n\n{}", err)
-        }
-        PubGrubError::Failure(err) => anyhow::anyhow!(
+        PubGrubError::ErrorInShouldCancel(err) => match err.downcast::<Interrupted>() {
+            Ok(interrupted) => {
+                let partial = &interrupted.partial;
+                let decided: Vec<String> = (partial.decided.iter())
+                    .map(|(p, v)| format!("    {}@{}", p, v))
+                    .collect();
+                let undetermined: Vec<String> = (partial.undetermined.iter())
+                    .map(|p| format!("    {}", p))
+                    .collect();
+                format!(
+                    "Dependency resolution {}.\n\nDecided packages:\n{}\n\nUndetermined packages:\n{}",
+                    interrupted.reason,
+                    decided.join("\n"),
+                    undetermined.join("\n")
+                )
+            }
+            Err(err) => format!("Dependency resolution was cancelled.\n\n{}", err),
+        },
+        PubGrubError::Failure(err) => format!(
             "An unrecoverable error happened while solving dependencies:\n\n{}",
             err
         ),
+    };
+    CodedError {
+        code,
+        message,
+        locations: Vec::new(),
     }
 }
@@ -0,0 +1,111 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `outdated` command, reporting how outdated the selected versions are.
+
+use std::collections::BTreeMap;
+
+use elm_solve_deps::health::{Health, PackageHealth};
+use elm_solve_deps::report::{Freshness, Outdated};
+use elm_solve_deps::semver::SemanticVersion;
+
+/// Print the freshness report as a table on stdout,
+/// with the health of each package if available.
+pub fn print_table(rows: &[Outdated], health: Option<&[PackageHealth]>) {
+    let mut header = vec!["Package", "Current", "Compatible", "Latest", "Status"];
+    if health.is_some() {
+        header.extend(["Releases", "Last release", "Dependents", "Health"]);
+    }
+    let health: BTreeMap<_, _> = (health.unwrap_or_default().iter())
+        .map(|h| (&h.pkg, h))
+        .collect();
+    let lines: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            let mut line = vec![
+                row.pkg.to_string(),
+                row.current.to_string(),
+                display_version(row.newest_compatible),
+                display_version(row.newest),
+                display_freshness(row.freshness).to_string(),
+            ];
+            if let Some(h) = health.get(&row.pkg) {
+                line.extend([
+                    h.release_count
+                        .map_or_else(|| "---".to_string(), |n| n.to_string()),
+                    h.last_release
+                        .map_or_else(|| "---".to_string(), display_date),
+                    h.dependents.to_string(),
+                    display_health(h.health).to_string(),
+                ]);
+            }
+            line
+        })
+        .collect();
+    let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+    for line in &lines {
+        for (width, cell) in widths.iter_mut().zip(line) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let header: Vec<String> = header.iter().map(|h| h.to_string()).collect();
+    print_line(&header, &widths);
+    print_line(
+        &widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>(),
+        &widths,
+    );
+    for line in &lines {
+        print_line(line, &widths);
+    }
+}
+
+fn print_line(cells: &[String], widths: &[usize]) {
+    let padded: Vec<String> = cells
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{:width$}", cell, width = width))
+        .collect();
+    println!("{}", padded.join("  ").trim_end());
+}
+
+fn display_version(version: Option<SemanticVersion>) -> String {
+    version.map_or_else(|| "---".to_string(), |v| v.to_string())
+}
+
+fn display_freshness(freshness: Freshness) -> &'static str {
+    match freshness {
+        Freshness::UpToDate => "up to date",
+        Freshness::CompatibleUpdate => "compatible update",
+        Freshness::MajorUpdate => "major update",
+        Freshness::Unknown => "unknown",
+    }
+}
+
+fn display_health(health: Health) -> &'static str {
+    match health {
+        Health::Active => "active",
+        Health::Mature => "mature",
+        Health::Abandoned => "ABANDONED",
+        Health::Unknown => "unknown",
+    }
+}
+
+/// Format a unix timestamp as a YYYY-MM-DD date, in UTC.
+fn display_date(unix_secs: u64) -> String {
+    // Civil date from the number of days since 1970-01-01,
+    // counting in eras of 400 years starting on March 1st.
+    let days = unix_secs / 86_400 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `--race` and `--race-all` modes, solving with several strategies in parallel.
+
+use anyhow::Context;
+use std::collections::BTreeMap;
+
+use elm_solve_deps::project_config::{AppDependencies, ProjectConfig};
+use elm_solve_deps::race;
+use elm_solve_deps::solver::{self, VersionStrategy};
+
+use crate::Args;
+
+/// Print the first solution found, or with `--race-all`,
+/// the outcome of every strategy and all the solutions.
+pub fn run(
+    args: &Args,
+    offline_solver: &solver::Offline,
+    project_elm_json: &ProjectConfig,
+) -> anyhow::Result<()> {
+    let online_solver = crate::online_solver(args, offline_solver, VersionStrategy::Newest)
+        .context("Failed to initialize the online solver")?;
+    let (use_test, extras, extra_mode) = (args.use_test, &args.extras, args.extra_mode);
+    if args.race_all {
+        let outcomes = race::race_all(
+            offline_solver,
+            &online_solver,
+            project_elm_json,
+            use_test,
+            extras,
+            extra_mode,
+        );
+        for outcome in &outcomes {
+            let status = if outcome.result.is_ok() {
+                "solved"
+            } else {
+                "failed"
+            };
+            eprintln!("{} {} in {:?}", outcome.strategy, status, outcome.duration);
+        }
+        let solutions: BTreeMap<String, Option<&AppDependencies>> = (outcomes.iter())
+            .map(|o| (o.strategy.to_string(), o.result.as_ref().ok()))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&solutions)?);
+        return Ok(());
+    }
+    let outcome = race::race(
+        offline_solver,
+        &online_solver,
+        project_elm_json,
+        use_test,
+        extras,
+        extra_mode,
+    );
+    match outcome {
+        Ok(race::RaceOutcome {
+            strategy,
+            duration,
+            result: Ok(solution),
+        }) => {
+            eprintln!("Solved with strategy {} in {:?}", strategy, duration);
+            print!("{}", solution.to_canonical_json());
+            Ok(())
+        }
+        Ok(_) => unreachable!("only successful outcomes win the race"),
+        Err(failures) => {
+            for failure in &failures {
+                if let Err(report) = &failure.result {
+                    eprintln!("Strategy {} failed:\n{}\n", failure.strategy, report);
+                }
+            }
+            anyhow::bail!("All strategies failed")
+        }
+    }
+}
@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `repair` command, fixing inconsistent indirect dependencies pinned in elm.json.
+
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::pkg_version::PkgVersion;
+use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+use elm_solve_deps::repair;
+use elm_solve_deps::solver::{self, ExtraMode};
+
+use crate::Args;
+
+/// Check the versions pinned in the elm.json of the current directory,
+/// and print the changes of indirect dependencies restoring their consistency.
+pub fn run(args: &Args, offline_solver: &solver::Offline) -> anyhow::Result<()> {
+    let app_config = match crate::load_project_config(None)? {
+        ProjectConfig::Application(app_config) => app_config,
+        ProjectConfig::Package(_) => anyhow::bail!("repair only works for applications"),
+    };
+    let load_config = |pkg: &Pkg, version| {
+        crate::load_package_config(&PkgVersion {
+            author_pkg: pkg.clone(),
+            version,
+        })
+    };
+    let inconsistencies = repair::check(&app_config, load_config)?;
+    if inconsistencies.is_empty() {
+        eprintln!("The versions pinned in elm.json are consistent");
+        return Ok(());
+    }
+    for inconsistency in &inconsistencies {
+        eprintln!("Inconsistent: {}", inconsistency);
+    }
+
+    // Try pinned versions first, to keep as many of them as the solver can.
+    let offline_solver =
+        (offline_solver.clone()).with_preferred_versions(app_config.recorded_versions());
+    let solve = |project_elm_json: &ProjectConfig,
+                 use_test: bool,
+                 extras: &[(Pkg, Constraint)],
+                 extra_mode: ExtraMode| {
+        crate::solve_deps(
+            args,
+            &offline_solver,
+            project_elm_json,
+            use_test,
+            extras,
+            extra_mode,
+        )
+    };
+    let repair = repair::repair(&app_config, solve)?;
+    for change in &repair.changes {
+        println!("{}", change);
+    }
+    if args.write {
+        let solution = &repair.solution;
+        crate::write_solution(
+            args,
+            &solution.dependencies,
+            Some(&solution.test_dependencies),
+        )?;
+    }
+    Ok(())
+}
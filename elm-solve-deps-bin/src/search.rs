@@ -0,0 +1,31 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `search` command, looking for packages by name and summary.
+
+use elm_solve_deps::pkg_version::PkgVersion;
+use elm_solve_deps::registry::{self, SearchResult};
+
+/// Search packages and print the results on stdout.
+///
+/// Packages are selected by name before searching their summaries,
+/// and only the `elm.json` files available locally are used,
+/// so summaries of packages never installed nor solved are not searched.
+pub fn run(query: &str, offline: bool) -> anyhow::Result<()> {
+    let versions_cache = crate::load_versions_cache(offline)?;
+    let load_config = |pkg_version: &PkgVersion| crate::load_local_config(pkg_version).ok();
+    let results = registry::search(query, &versions_cache, load_config);
+    if results.is_empty() {
+        eprintln!("No package found for \"{}\"", query);
+    }
+    for result in &results {
+        print_result(result);
+    }
+    Ok(())
+}
+
+fn print_result(result: &SearchResult) {
+    println!("{}@{}", result.pkg, result.version);
+    if let Some(summary) = &result.summary {
+        println!("    {}", summary);
+    }
+}
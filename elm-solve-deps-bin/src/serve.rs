@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `serve` command, solving dependencies over HTTP.
+//!
+//! Build farms running many short-lived jobs can query one long-lived server
+//! instead of each job needing its own `ELM_HOME` state.
+//! The server exposes two endpoints:
+//!
+//!  - `POST /solve` with a JSON body containing the `elm.json` to solve and options,
+//!    responds with the solution, or with the error message and its code
+//!    if solving fails;
+//!  - `GET /versions/author/package` responds with the known versions of that package.
+//!
+//! The server only listens on the loopback interface unless given another address,
+//! and rejects request bodies larger than [`MAX_BODY`] bytes.
+//!
+//! Requests are handled one at a time by a [`Daemon`], reusing the same solvers
+//! and thus their warm caches.
+//! Online solvers update their list of existing packages in the background every few minutes.
+//! This module is only the HTTP shell of the daemon, which lives in the library.
+
+use std::io::Read;
+use std::str::FromStr;
+use std::time::Instant;
+
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use elm_solve_deps::daemon::{self, Daemon, DaemonError, SolveRequest};
+use elm_solve_deps::fetch::HttpFetch;
+use elm_solve_deps::pkg_version::{Cache, CacheError};
+use elm_solve_deps::project_config::Pkg;
+use elm_solve_deps::semver::SemVer;
+use elm_solve_deps::solver::{self, VersionStrategy};
+
+use crate::Args;
+
+/// Maximum size of request bodies, in bytes, far above the size of any `elm.json`.
+pub const MAX_BODY: u64 = 1024 * 1024;
+
+/// Listen on the given port and answer requests until the process is stopped.
+pub fn run(args: &Args, offline_solver: &solver::Offline) -> anyhow::Result<()> {
+    let server = Server::http((args.host, args.port)).map_err(|err| anyhow::anyhow!(err))?;
+    eprintln!("Listening on {}:{}", args.host, args.port);
+    let mut daemon = Daemon::new(
+        offline_solver.clone(),
+        crate::load_versions_cache(args.offline)?,
+        |strat| crate::online_solver(args, offline_solver, strat),
+        || crate::load_versions_cache(false),
+        Instant::now(),
+    )
+    .with_offline(args.offline);
+    let mut next_poll = Instant::now();
+    loop {
+        let timeout = next_poll.saturating_duration_since(Instant::now());
+        if let Some(request) = server.recv_timeout(timeout)? {
+            handle(&mut daemon, request);
+        }
+        let now = Instant::now();
+        if now >= next_poll {
+            match daemon.poll(now) {
+                Ok(wait) => next_poll = now + wait,
+                Err(err) => eprintln!("{:#}", err),
+            }
+        }
+    }
+}
+
+/// Decode an HTTP request for the daemon, and send back its response.
+fn handle<F, MkOnline, UpdateVersions>(
+    daemon: &mut Daemon<F, MkOnline, UpdateVersions>,
+    mut request: Request,
+) where
+    F: HttpFetch + Clone + Send + 'static,
+    MkOnline: FnMut(VersionStrategy) -> Result<solver::Online<F>, CacheError>,
+    UpdateVersions: FnMut() -> anyhow::Result<Cache>,
+{
+    let method = request.method().clone();
+    let url = request.url().to_string();
+    let daemon_request = match (&method, url.as_str()) {
+        (Method::Post, "/solve") => read_body(&mut request).and_then(|body| {
+            serde_json::from_str::<SolveRequest>(&body)
+                .map(|request| daemon::Request::Solve(Box::new(request)))
+                .map_err(error_json)
+        }),
+        (Method::Get, url) if url.starts_with("/versions/") => {
+            (Pkg::from_str(&url["/versions/".len()..]))
+                .map(daemon::Request::Versions)
+                .map_err(error_json)
+        }
+        (_, url) => Err((404, json!({ "error": format!("Not found: {}", url) }))),
+    };
+    let (status, body) = match daemon_request {
+        Ok(daemon_request) => match daemon.handle(&daemon_request, Instant::now()) {
+            Ok(daemon::Response::Solution(solution)) => (200, json!(solution)),
+            Ok(daemon::Response::Versions(versions)) => {
+                let versions: Vec<String> = versions.iter().map(SemVer::to_string).collect();
+                (200, json!(versions))
+            }
+            Err(err) => daemon_error_json(err),
+        },
+        Err(error) => error,
+    };
+    eprintln!("{} {} {}", method, url, status);
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(content_type);
+    if let Err(err) = request.respond(response) {
+        eprintln!("Failed to respond: {}", err);
+    }
+}
+
+/// Read the body of a request, up to its `Content-Length` and at most [`MAX_BODY`] bytes.
+fn read_body(request: &mut Request) -> Result<String, (u16, serde_json::Value)> {
+    let too_large = || {
+        let error = format!("Request body larger than {} bytes", MAX_BODY);
+        (413, json!({ "error": error }))
+    };
+    let length = request
+        .body_length()
+        .map_or(MAX_BODY, |length| length as u64);
+    if length > MAX_BODY {
+        return Err(too_large());
+    }
+    let mut body = String::new();
+    // One more byte to detect bodies without Content-Length exceeding the maximum.
+    let mut reader = request.as_reader().take(length + 1);
+    reader.read_to_string(&mut body).map_err(error_json)?;
+    if body.len() as u64 > length {
+        return Err(too_large());
+    }
+    Ok(body)
+}
+
+/// Status and body of the response to a request the daemon failed to handle.
+fn daemon_error_json(err: DaemonError) -> (u16, serde_json::Value) {
+    let (status, err) = match err {
+        DaemonError::OfflineStrategy | DaemonError::OfflineOnly => {
+            return (400, json!({ "error": err.to_string() }))
+        }
+        DaemonError::UnknownPackage(_) => return (404, json!({ "error": err.to_string() })),
+        DaemonError::Solve(err) => (422, crate::handle_pubgrub_error(*err)),
+        DaemonError::OnlineSolver(err) => (
+            422,
+            anyhow::Error::from(err).context("Failed to initialize the online solver"),
+        ),
+    };
+    let code = crate::error_code(&err).to_string();
+    (
+        status,
+        json!({ "error": format!("{:#}", err), "code": code }),
+    )
+}
+
+/// Status and body of the response to an invalid request.
+fn error_json<E: ToString>(err: E) -> (u16, serde_json::Value) {
+    (400, json!({ "error": err.to_string() }))
+}
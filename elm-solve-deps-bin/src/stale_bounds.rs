@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `stale-bounds` command, reporting published constraints excluding
+//! the latest versions of dependencies, across the whole registry.
+
+use elm_solve_deps::pkg_version::PkgVersion;
+use elm_solve_deps::project_config::Pkg;
+use elm_solve_deps::stale_bounds;
+
+/// Find and print the stale bounds of packages, in CSV or JSON.
+pub fn run(format: &str, authors: &[&str], offline: bool) -> anyhow::Result<()> {
+    let versions_cache = crate::load_versions_cache(offline)?;
+    let select = |pkg: &Pkg| authors.is_empty() || authors.contains(&pkg.author.as_str());
+    let load_config = |pkg_version: &PkgVersion| {
+        if offline {
+            crate::load_local_config(pkg_version).ok()
+        } else {
+            crate::load_package_config(pkg_version).ok()
+        }
+    };
+    let stale = stale_bounds::stale_bounds(&versions_cache, select, load_config);
+    match format {
+        "csv" => {
+            let mut writer = csv::Writer::from_writer(std::io::stdout());
+            for record in &stale {
+                writer.serialize(record)?;
+            }
+            writer.flush()?;
+        }
+        "json" => println!("{}", serde_json::to_string_pretty(&stale)?),
+        _ => anyhow::bail!(
+            "Unknown stale-bounds format: {}, expected csv or json",
+            format
+        ),
+    }
+    Ok(())
+}
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `stats` command, printing statistics about all packages of the registry.
+
+use anyhow::Context;
+
+use elm_solve_deps::pkg_version::PkgVersion;
+use elm_solve_deps::project_config::Pkg;
+use elm_solve_deps::release_age::ReleaseTimes;
+use elm_solve_deps::stats;
+
+/// Compute and print the statistics of packages, in CSV or JSON.
+///
+/// With `released_since`, in seconds since the unix epoch, only packages
+/// whose latest version was released since then are printed.
+/// Release times are fetched from the package server unless offline,
+/// and packages with an unknown release time are skipped.
+pub fn run(
+    format: &str,
+    authors: &[&str],
+    released_since: Option<u64>,
+    offline: bool,
+) -> anyhow::Result<()> {
+    let versions_cache = crate::load_versions_cache(offline)?;
+    let remotes: &[&str] = if offline { &[] } else { &[crate::remote()] };
+    let release_times = std::cell::RefCell::new(ReleaseTimes::new());
+    let select = |pkg: &Pkg| {
+        if !authors.is_empty() && !authors.contains(&pkg.author.as_str()) {
+            return false;
+        }
+        let since = match released_since {
+            Some(since) => since,
+            None => return true,
+        };
+        let latest = match versions_cache.cache.get(pkg).and_then(|v| v.last()) {
+            Some(latest) => *latest,
+            None => return false,
+        };
+        let mut release_times = release_times.borrow_mut();
+        let ensured = release_times.ensure(
+            crate::elm_home(),
+            pkg,
+            &[latest],
+            remotes,
+            &crate::http_fetch,
+        );
+        if let Err(err) = ensured {
+            eprintln!("Warning: failed to fetch the releases of {}: {}", pkg, err);
+        }
+        (release_times.release_time(pkg, latest)).is_some_and(|time| time >= since)
+    };
+    let load_config = |pkg_version: &PkgVersion| {
+        if offline {
+            crate::load_local_config(pkg_version).ok()
+        } else {
+            crate::load_package_config(pkg_version).ok()
+        }
+    };
+    let stats = stats::registry_stats(&versions_cache, select, load_config);
+    match format {
+        "csv" => {
+            let mut writer = csv::Writer::from_writer(std::io::stdout());
+            for record in &stats {
+                writer.serialize(record)?;
+            }
+            writer.flush()?;
+        }
+        "json" => println!("{}", serde_json::to_string_pretty(&stats)?),
+        _ => anyhow::bail!("Unknown stats format: {}, expected csv or json", format),
+    }
+    Ok(())
+}
+
+/// Parse a YYYY-MM-DD date, in UTC, into seconds since the unix epoch.
+pub fn parse_date(date: &str) -> anyhow::Result<u64> {
+    let invalid = || format!("Invalid date: {}, expected YYYY-MM-DD", date);
+    let parts: Vec<&str> = date.split('-').collect();
+    let (year, month, day): (u64, u64, u64) = match parts[..] {
+        [year, month, day] if year.len() == 4 && month.len() == 2 && day.len() == 2 => (
+            year.parse().with_context(invalid)?,
+            month.parse().with_context(invalid)?,
+            day.parse().with_context(invalid)?,
+        ),
+        _ => anyhow::bail!(invalid()),
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if year < 1970 || !(1..=12).contains(&month) || !(1..=month_days).contains(&day) {
+        anyhow::bail!(invalid());
+    }
+    // Number of days since 1970-01-01, counting in eras of 400 years
+    // starting on March 1st, the inverse of the dates of the outdated command.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Ok((era * 146_097 + day_of_era - 719_468) * 86_400)
+}
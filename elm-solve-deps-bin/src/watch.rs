@@ -0,0 +1,235 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `--watch` mode, solving again every time the elm.json changes.
+//!
+//! The elm.json of the project, and the one of the review configuration with `--review`,
+//! are watched for modifications with filesystem notifications, with the `fs-events` feature.
+//! Without it, or if notifications are not available, the files are polled instead,
+//! which works the same on every platform.
+//! The first solution is printed in full, and the next ones as differences with the previous.
+//! Solvers are kept between runs, such that their caches stay warm,
+//! and solutions are memoized, such that reverting a change is instant.
+
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::project_config::{AppDependencies, Pkg, ProjectConfig};
+use elm_solve_deps::report::{self, VersionDifference};
+use elm_solve_deps::review;
+use elm_solve_deps::solver::{self, ExtraMode, VersionStrategy};
+
+use crate::Args;
+
+/// How often watched files are checked for modifications, when polling them.
+const POLL_PERIOD: Duration = Duration::from_millis(300);
+
+/// How long watched files must stay unchanged after a notification, when watching events.
+#[cfg(feature = "fs-events")]
+const SETTLE_PERIOD: Duration = Duration::from_millis(300);
+
+/// Solve, then solve again after every modification of the watched files, until interrupted.
+pub fn run(args: &Args, offline_solver: &solver::Offline) -> anyhow::Result<()> {
+    let mut paths = vec![PathBuf::from("elm.json")];
+    if args.review {
+        paths.push(review::config_path("."));
+    }
+
+    // The online solver is only created when needed, and then reused.
+    let online_solver = RefCell::new(None);
+    let solve = |project_elm_json: &ProjectConfig,
+                 use_test: bool,
+                 extras: &[(Pkg, Constraint)],
+                 extra_mode: ExtraMode|
+     -> anyhow::Result<AppDependencies> {
+        let solve = |project_elm_json: &ProjectConfig, extras: &[(Pkg, Constraint)]| {
+            if args.online_strat.is_none() {
+                let offline =
+                    offline_solver.solve_deps(project_elm_json, use_test, extras, extra_mode);
+                match offline {
+                    Ok(solution) => return Ok(solution),
+                    Err(err) if args.offline => return Err(crate::handle_pubgrub_error(err)),
+                    Err(_) => eprintln!("Offline solving failed, switching to online"),
+                }
+            }
+            let mut online_solver = online_solver.borrow_mut();
+            if online_solver.is_none() {
+                let strat = args.online_strat.unwrap_or(VersionStrategy::Newest);
+                let created = crate::online_solver(args, offline_solver, strat)
+                    .context("Failed to initialize the online solver")?;
+                *online_solver = Some(created);
+            }
+            (online_solver.as_ref().unwrap())
+                .solve_deps(project_elm_json, use_test, extras, extra_mode)
+                .map_err(crate::handle_pubgrub_error)
+        };
+        crate::solve_memoized(
+            args,
+            offline_solver,
+            project_elm_json,
+            use_test,
+            extras,
+            extra_mode,
+            solve,
+        )
+    };
+
+    let mut previous: Option<Vec<(&str, AppDependencies)>> = None;
+    loop {
+        let modified = modification_times(&paths);
+        let solutions = if args.review {
+            review::solve_with_review(".", args.use_test, &args.extras, args.extra_mode, solve)
+                .map(|solution| vec![("project", solution.project), ("review", solution.review)])
+                .map_err(anyhow::Error::from)
+        } else {
+            crate::load_project_config(None)
+                .and_then(|project_elm_json| {
+                    solve(
+                        &project_elm_json,
+                        args.use_test,
+                        &args.extras,
+                        args.extra_mode,
+                    )
+                })
+                .map(|solution| vec![("project", solution)])
+        };
+        match solutions {
+            Ok(solutions) => {
+                print_changes(previous.as_deref(), &solutions);
+                previous = Some(solutions);
+            }
+            Err(err) => eprintln!("Error [{}]: {:?}", crate::error_code(&err), err),
+        }
+        eprintln!("Watching {} for changes", display_paths(&paths));
+        wait_for_changes(&paths, modified);
+    }
+}
+
+/// Print the solutions in full the first time, and then only what changed.
+fn print_changes(
+    previous: Option<&[(&str, AppDependencies)]>,
+    solutions: &[(&str, AppDependencies)],
+) {
+    let previous = match previous {
+        Some(previous) => previous,
+        None => {
+            for (name, solution) in solutions {
+                if solutions.len() > 1 {
+                    println!("{}:", name);
+                }
+                print!("{}", solution.to_canonical_json());
+            }
+            return;
+        }
+    };
+    for ((name, old), (_, new)) in previous.iter().zip(solutions) {
+        let differences = report::differences(old, new);
+        if differences.is_empty() {
+            println!("The {} solution is unchanged", name);
+        } else {
+            println!("The {} solution changed:", name);
+            for diff in &differences {
+                println!("    {}", display_difference(diff));
+            }
+        }
+    }
+}
+
+fn display_difference(diff: &VersionDifference) -> String {
+    match (diff.left, diff.right) {
+        (None, Some(new)) => format!("+ {} {}", diff.pkg, new),
+        (Some(old), None) => format!("- {} {}", diff.pkg, old),
+        (Some(old), Some(new)) => format!("  {} {} -> {}", diff.pkg, old, new),
+        // Differences have a version on at least one side.
+        (None, None) => diff.pkg.to_string(),
+    }
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+    paths.join(" and ")
+}
+
+/// Last modification times of the files, `None` for the ones missing.
+fn modification_times(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    (paths.iter())
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+/// Block until one of the files is modified, created or removed,
+/// since their `modified` times were read.
+/// Editors may write a file in several steps, so wait for the files to settle.
+fn wait_for_changes(paths: &[PathBuf], modified: Vec<Option<SystemTime>>) {
+    #[cfg(feature = "fs-events")]
+    match wait_for_events(paths, &modified) {
+        Ok(()) => return,
+        Err(err) => eprintln!(
+            "Failed to watch filesystem events, polling instead: {}",
+            err
+        ),
+    }
+    poll_for_changes(paths, modified)
+}
+
+/// Block until one of the files changes, by polling their modification times.
+fn poll_for_changes(paths: &[PathBuf], mut modified: Vec<Option<SystemTime>>) {
+    let mut changed = false;
+    loop {
+        std::thread::sleep(POLL_PERIOD);
+        let now = modification_times(paths);
+        if now != modified {
+            modified = now;
+            changed = true;
+        } else if changed {
+            return;
+        }
+    }
+}
+
+/// Block until one of the files changes, with filesystem notifications.
+///
+/// The directories of the files are watched, instead of the files themselves,
+/// since editors often save by replacing files, and files may not exist yet.
+#[cfg(feature = "fs-events")]
+fn wait_for_events(paths: &[PathBuf], modified: &[Option<SystemTime>]) -> notify::Result<()> {
+    use notify::{RecursiveMode, Watcher};
+    use std::sync::mpsc::{self, RecvTimeoutError};
+    use std::time::Instant;
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    let mut watched = Vec::with_capacity(paths.len());
+    for path in paths {
+        let dir = match path.parent() {
+            Some(dir) if dir != std::path::Path::new("") => dir,
+            _ => std::path::Path::new("."),
+        };
+        let dir = std::fs::canonicalize(dir)?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        watched.push(dir.join(path.file_name().unwrap_or_default()));
+    }
+    let disconnected = || notify::Error::generic("the filesystem watcher stopped");
+
+    // Changes made before the watcher started are not notified.
+    let mut settled_at =
+        (modification_times(paths) != modified).then(|| Instant::now() + SETTLE_PERIOD);
+    loop {
+        let event = match settled_at {
+            None => receiver.recv().map_err(|_| disconnected())?,
+            Some(settled_at) => {
+                let timeout = settled_at.saturating_duration_since(Instant::now());
+                match receiver.recv_timeout(timeout) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => return Ok(()),
+                    Err(RecvTimeoutError::Disconnected) => return Err(disconnected()),
+                }
+            }
+        }?;
+        if event.paths.iter().any(|path| watched.contains(path)) {
+            settled_at = Some(Instant::now() + SETTLE_PERIOD);
+        }
+    }
+}
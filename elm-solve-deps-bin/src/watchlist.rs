@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The `watchlist` and `notify` commands, watching packages for new versions.
+
+use anyhow::Context;
+use std::str::FromStr;
+
+use elm_solve_deps::pkg_version::{Cache, PkgVersion};
+use elm_solve_deps::project_config::Pkg;
+use elm_solve_deps::watchlist::Watchlist;
+
+/// Run `watchlist`, `watchlist add PACKAGES...` or `watchlist remove PACKAGES...`.
+pub fn run(positional: &[&str]) -> anyhow::Result<()> {
+    let elm_home = crate::elm_home();
+    let mut watchlist = Watchlist::load(&elm_home).unwrap_or_default();
+    match positional {
+        [] => {
+            for (pkg, seen) in &watchlist.packages {
+                match seen {
+                    Some(version) => println!("{} (newest seen: {})", pkg, version),
+                    None => println!("{} (not seen yet)", pkg),
+                }
+            }
+        }
+        ["add", pkgs @ ..] if !pkgs.is_empty() => {
+            let versions_cache = Cache::load(&elm_home).unwrap_or_default();
+            for pkg in pkgs {
+                let pkg = Pkg::from_str(pkg)?;
+                if !versions_cache.cache.contains_key(&pkg) {
+                    eprintln!("Warning: {} is not in the versions cache", pkg);
+                }
+                if !watchlist.watch(pkg.clone()) {
+                    eprintln!("{} was already watched", pkg);
+                }
+            }
+            // Only versions published from now on are new.
+            watchlist.mark_seen(&versions_cache);
+            watchlist.save(&elm_home)?;
+        }
+        ["remove", pkgs @ ..] if !pkgs.is_empty() => {
+            for pkg in pkgs {
+                let pkg = Pkg::from_str(pkg)?;
+                if !watchlist.unwatch(&pkg) {
+                    eprintln!("{} was not watched", pkg);
+                }
+            }
+            watchlist.save(&elm_home)?;
+        }
+        _ => anyhow::bail!("watchlist expects nothing, add PACKAGES... or remove PACKAGES..."),
+    }
+    Ok(())
+}
+
+/// Run `notify`, printing the new versions of watched packages since the last run.
+/// Offline, only versions already added to the versions cache by other commands are reported,
+/// and only when newer than the newest versions seen.
+pub fn notify(offline: bool) -> anyhow::Result<()> {
+    let elm_home = crate::elm_home();
+    let mut watchlist = Watchlist::load(&elm_home).unwrap_or_default();
+    if watchlist.packages.is_empty() {
+        eprintln!("No package is watched, add some with: watchlist add PACKAGE");
+        return Ok(());
+    }
+    let mut count = 0;
+    let mut print = |pkg_version: &PkgVersion| {
+        println!("{}", pkg_version);
+        count += 1;
+    };
+    if offline {
+        let versions_cache = Cache::load(&elm_home).unwrap_or_default();
+        watchlist
+            .new_versions(&versions_cache)
+            .iter()
+            .for_each(print);
+        watchlist.mark_seen(&versions_cache);
+        watchlist.save(&elm_home)?;
+    } else {
+        (watchlist.poll(
+            &elm_home,
+            crate::remote(),
+            &crate::http_fetch,
+            crate::clock(),
+            &mut print,
+        ))
+        .context("Failed to check the package server for new versions")?;
+    }
+    eprintln!("{} new versions of watched packages", count);
+    Ok(())
+}
@@ -1,23 +1,22 @@
-use pubgrub::solver::OfflineDependencyProvider;
-use pubgrub::version::SemanticVersion as SemVer;
-use serde_json;
 use std::str::FromStr;
 
+use elm_solve_deps::dependency_provider::OfflineDependencyProvider;
+use elm_solve_deps::fetch::FetchError;
 use elm_solve_deps::pkg_version::PkgVersion;
 use elm_solve_deps::project_config::{PackageConfig, Pkg};
+use elm_solve_deps::semver::SemVer;
 
 /// Read the history of all packages and fetch all their elm.json files.
 fn main() {
     let s = std::fs::read_to_string("registry/all-packages-history.json").expect("woops file");
     let raw: Vec<String> = serde_json::from_str(&s).expect("woops serde");
-    let pkg_versions: Vec<PkgVersion> =
-        raw.iter().map(|s| FromStr::from_str(&s).unwrap()).collect();
-    let http_fetch = |url: &str| {
+    let pkg_versions: Vec<PkgVersion> = raw.iter().map(|s| FromStr::from_str(s).unwrap()).collect();
+    let http_fetch = |url: &str| -> Result<String, FetchError> {
         ureq::get(url)
             .timeout_connect(10_000)
             .call()
             .into_string()
-            .map_err(|e| e.into())
+            .map_err(|e| FetchError::Transport(e.into()))
     };
     let configs: Vec<PackageConfig> = pkg_versions
         .into_iter()
@@ -25,7 +24,9 @@ fn main() {
         // .take(2)
         .map(|p| {
             p.load_from_cache("download")
-                .or_else(|_| p.fetch_config("download", "https://package.elm-lang.org", http_fetch))
+                .or_else(|_| {
+                    p.fetch_config("download", "https://package.elm-lang.org", &http_fetch)
+                })
                 .unwrap()
         })
         .collect();
@@ -49,7 +50,7 @@ fn main() {
                 Pkg::new("elm", ""),
                 config.elm_version.0.clone(),
             )));
-        dep_provider.add_dependencies(config.name.clone(), config.version.clone(), deps);
+        dep_provider.add_dependencies(config.name.clone(), config.version, deps);
     });
     let pretty_config = ron::ser::PrettyConfig::new()
         .with_depth_limit(6)
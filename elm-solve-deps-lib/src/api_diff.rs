@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module comparing the exposed API of two versions of a package.
+//!
+//! The comparison is coarse, based only on the exposed modules listed in their `elm.json`.
+//! It is still enough to flag suspicious releases, such as removing a module in a minor bump,
+//! which the package server should normally prevent.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+
+use crate::project_config::{PackageConfig, Pkg};
+
+/// Kind of version bump between two releases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Bump {
+    /// The major version increased.
+    Major,
+    /// The minor version increased.
+    Minor,
+    /// The patch version increased.
+    Patch,
+    /// Both versions are the same.
+    Same,
+    /// The new version is older than the previous one.
+    Downgrade,
+}
+
+/// Differences between the exposed modules of two versions of a package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ApiDiff {
+    /// The package compared.
+    pub pkg: Pkg,
+    /// The previous version.
+    pub from: SemVer,
+    /// The new version.
+    pub to: SemVer,
+    /// The kind of version bump.
+    pub bump: Bump,
+    /// Modules exposed in the new version but not in the previous one.
+    pub added: Vec<String>,
+    /// Modules exposed in the previous version but not in the new one.
+    pub removed: Vec<String>,
+}
+
+impl Bump {
+    /// Classify the version bump from `from` to `to`.
+    pub fn between(from: SemVer, to: SemVer) -> Self {
+        let (from_major, from_minor, _): (u32, u32, u32) = from.into();
+        let (to_major, to_minor, _): (u32, u32, u32) = to.into();
+        if to < from {
+            Bump::Downgrade
+        } else if to == from {
+            Bump::Same
+        } else if to_major > from_major {
+            Bump::Major
+        } else if to_minor > from_minor {
+            Bump::Minor
+        } else {
+            Bump::Patch
+        }
+    }
+}
+
+impl ApiDiff {
+    /// Compare the exposed modules of two versions of the same package.
+    pub fn between(from: &PackageConfig, to: &PackageConfig) -> Self {
+        let from_modules = from.exposed_modules.modules();
+        let to_modules = to.exposed_modules.modules();
+        ApiDiff {
+            pkg: to.name.clone(),
+            from: from.version,
+            to: to.version,
+            bump: Bump::between(from.version, to.version),
+            added: to_modules
+                .difference(&from_modules)
+                .map(|m| m.to_string())
+                .collect(),
+            removed: from_modules
+                .difference(&to_modules)
+                .map(|m| m.to_string())
+                .collect(),
+        }
+    }
+
+    /// Check if the changes of exposed modules are not allowed by the version bump.
+    ///
+    /// Removing modules requires a major bump, and adding modules requires at least a minor bump.
+    pub fn is_suspicious(&self) -> bool {
+        match self.bump {
+            Bump::Major | Bump::Downgrade => false,
+            Bump::Minor => !self.removed.is_empty(),
+            Bump::Patch | Bump::Same => !self.removed.is_empty() || !self.added.is_empty(),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module auditing solutions for suspicious packages.
+//!
+//! Only packages from the `elm/` and `elm-explorations/` authors may contain kernel code,
+//! so they are treated specially by the ecosystem.
+//! A package with the same name as a kernel package, or an author name very similar
+//! to a trusted one, is likely a typosquatting attempt and deserves a warning.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::project_config::{AppDependencies, Pkg};
+
+/// Authors trusted to publish kernel code packages, with the known kernel packages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TrustedAuthors {
+    /// Trusted authors, such as `elm` and `elm-explorations`.
+    pub authors: BTreeSet<String>,
+    /// Known packages containing kernel code.
+    pub kernel_packages: BTreeSet<Pkg>,
+}
+
+/// A suspicious package found in a solution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum AuditWarning {
+    /// A package from an untrusted author has the same name as a kernel package.
+    KernelNameFromUntrustedAuthor {
+        /// The suspicious package.
+        pkg: Pkg,
+        /// The kernel package it has the same name as.
+        kernel_pkg: Pkg,
+    },
+    /// A package author is very similar to a trusted author, without being the same.
+    LookalikeAuthor {
+        /// The suspicious package.
+        pkg: Pkg,
+        /// The trusted author it looks like.
+        trusted_author: String,
+    },
+}
+
+impl Default for TrustedAuthors {
+    fn default() -> Self {
+        let kernel_packages = [
+            "elm/browser",
+            "elm/bytes",
+            "elm/core",
+            "elm/file",
+            "elm/html",
+            "elm/http",
+            "elm/json",
+            "elm/parser",
+            "elm/regex",
+            "elm/time",
+            "elm/url",
+            "elm/virtual-dom",
+            "elm-explorations/benchmark",
+            "elm-explorations/markdown",
+            "elm-explorations/test",
+            "elm-explorations/webgl",
+        ];
+        Self {
+            authors: ["elm", "elm-explorations"]
+                .iter()
+                .map(|a| a.to_string())
+                .collect(),
+            kernel_packages: kernel_packages
+                .iter()
+                .filter_map(|p| p.parse().ok())
+                .collect(),
+        }
+    }
+}
+
+impl TrustedAuthors {
+    /// Check all packages of a solution for suspicious authors.
+    pub fn audit(&self, solution: &AppDependencies) -> Vec<AuditWarning> {
+        (solution.direct.keys())
+            .chain(solution.indirect.keys())
+            .filter(|pkg| !self.authors.contains(&pkg.author))
+            .flat_map(|pkg| self.audit_pkg(pkg))
+            .collect()
+    }
+
+    fn audit_pkg(&self, pkg: &Pkg) -> Vec<AuditWarning> {
+        let kernel_names = (self.kernel_packages.iter())
+            .filter(|kernel_pkg| kernel_pkg.pkg == pkg.pkg)
+            .map(|kernel_pkg| AuditWarning::KernelNameFromUntrustedAuthor {
+                pkg: pkg.clone(),
+                kernel_pkg: kernel_pkg.clone(),
+            });
+        let lookalikes = (self.authors.iter())
+            // Allow one typo every 8 characters, and at least one.
+            .filter(|trusted| edit_distance(trusted, &pkg.author) <= (trusted.len() / 8).max(1))
+            .map(|trusted| AuditWarning::LookalikeAuthor {
+                pkg: pkg.clone(),
+                trusted_author: trusted.clone(),
+            });
+        kernel_names.chain(lookalikes).collect()
+    }
+}
+
+impl fmt::Display for AuditWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditWarning::KernelNameFromUntrustedAuthor { pkg, kernel_pkg } => write!(
+                f,
+                "{} has the same name as the kernel package {}",
+                pkg, kernel_pkg
+            ),
+            AuditWarning::LookalikeAuthor {
+                pkg,
+                trusted_author,
+            } => write!(
+                f,
+                "the author of {} looks like the trusted author {}",
+                pkg, trusted_author
+            ),
+        }
+    }
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
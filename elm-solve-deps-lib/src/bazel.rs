@@ -0,0 +1,136 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module exporting solutions for Bazel and similar monorepo build systems.
+//!
+//! Bazel declares external dependencies as repositories downloaded from an url,
+//! checked with their sha256, and stripped of the directory prefixing their files.
+//! Every package of a solution becomes a [`BazelArchive`],
+//! serialized as JSON for custom repository rules,
+//! or printed with [`to_bzl`] as a `.bzl` file declaring one `http_archive` per package.
+//!
+//! Archives are the GitHub tarballs of the version tags, since elm packages are published
+//! from these tags, and their prefix directory is known without opening them.
+//! Their sha256 is computed by downloading them with [`fetch_archives`],
+//! since the package server only publishes the sha1 of zipballs.
+//!
+//! This module is only available with the `sha2` feature.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::fetch::HttpFetch;
+use crate::pkg_version::CacheError;
+use crate::project_config::{AppDependencies, Pkg};
+
+/// Content of the `BUILD` file of each package repository.
+pub const BUILD_FILE_CONTENT: &str = r#"filegroup(
+    name = "package",
+    srcs = ["elm.json"] + glob(["src/**"]),
+    visibility = ["//visibility:public"],
+)
+"#;
+
+/// External repository of a package version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BazelArchive {
+    /// Name of the repository, such as `elm_core` for `elm/core`.
+    pub name: String,
+    /// The package identifier.
+    pub package: Pkg,
+    /// The version of the package.
+    pub version: SemVer,
+    /// Urls of the archive.
+    pub urls: Vec<String>,
+    /// Sha256 of the archive, in hexadecimal.
+    pub sha256: String,
+    /// Directory prefixing all the files of the archive.
+    pub strip_prefix: String,
+}
+
+impl BazelArchive {
+    /// Describe the archive of a package version, with the sha256 of its content.
+    ///
+    /// ```
+    /// # use elm_solve_deps::bazel::BazelArchive;
+    /// # use elm_solve_deps::project_config::Pkg;
+    /// # use elm_solve_deps::semver::SemVer;
+    /// let archive = BazelArchive::new(Pkg::new("elm-community", "list-extra"), SemVer::new(8, 7, 0), b"");
+    /// assert_eq!(archive.name, "elm_community_list_extra");
+    /// assert_eq!(archive.urls, ["https://github.com/elm-community/list-extra/archive/refs/tags/8.7.0.tar.gz"]);
+    /// assert_eq!(archive.strip_prefix, "list-extra-8.7.0");
+    /// assert_eq!(archive.sha256, "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    /// ```
+    pub fn new(package: Pkg, version: SemVer, content: &[u8]) -> Self {
+        Self {
+            name: Self::repository_name(&package),
+            urls: vec![Self::url(&package, version)],
+            sha256: format!("{:x}", Sha256::digest(content)),
+            strip_prefix: format!("{}-{}", package.pkg, version),
+            package,
+            version,
+        }
+    }
+
+    /// Url of the GitHub tarball of a package version.
+    pub fn url(pkg: &Pkg, version: SemVer) -> String {
+        format!(
+            "https://github.com/{}/{}/archive/refs/tags/{}.tar.gz",
+            pkg.author, pkg.pkg, version
+        )
+    }
+
+    /// Name of the repository of a package, valid in Bazel,
+    /// with `_` replacing the characters other than letters and digits.
+    pub fn repository_name(pkg: &Pkg) -> String {
+        let name = format!("{}_{}", pkg.author, pkg.pkg);
+        name.replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+    }
+}
+
+/// Download the archives of all the packages of a solution, to compute their sha256.
+pub fn fetch_archives<H: HttpFetch + ?Sized>(
+    solution: &AppDependencies,
+    http_fetch: &H,
+) -> Result<Vec<BazelArchive>, CacheError> {
+    (solution.direct.iter())
+        .chain(solution.indirect.iter())
+        .map(|(pkg, version)| {
+            let url = BazelArchive::url(pkg, *version);
+            let content = (http_fetch.fetch_bytes(&url))
+                .map_err(|source| CacheError::FetchError { url, source })?;
+            Ok(BazelArchive::new(pkg.clone(), *version, &content))
+        })
+        .collect()
+}
+
+/// Print a `.bzl` file defining an `elm_dependencies` macro,
+/// which declares one `http_archive` repository per package.
+pub fn to_bzl(archives: &[BazelArchive]) -> String {
+    // JSON strings are valid Starlark strings.
+    let quote = |s: &str| serde_json::Value::from(s).to_string();
+    let mut bzl = String::from(
+        "\"\"\"Elm dependencies, generated by elm-solve-deps.\"\"\"\n\n\
+        load(\"@bazel_tools//tools/build_defs/repo:http.bzl\", \"http_archive\")\n\n",
+    );
+    bzl.push_str(&format!(
+        "_BUILD_FILE_CONTENT = {}\n\n",
+        quote(BUILD_FILE_CONTENT)
+    ));
+    bzl.push_str("def elm_dependencies():\n");
+    bzl.push_str("    \"\"\"Declare the repositories of the elm packages.\"\"\"\n");
+    for archive in archives {
+        let urls: Vec<String> = archive.urls.iter().map(|url| quote(url)).collect();
+        bzl.push_str(&format!(
+            "\n    http_archive(\n        name = {},\n        urls = [{}],\n        sha256 = {},\n        strip_prefix = {},\n        build_file_content = _BUILD_FILE_CONTENT,\n    )\n",
+            quote(&archive.name),
+            urls.join(", "),
+            quote(&archive.sha256),
+            quote(&archive.strip_prefix),
+        ));
+    }
+    if archives.is_empty() {
+        bzl.push_str("    pass\n");
+    }
+    bzl
+}
@@ -0,0 +1,265 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module benchmarking the dependency solver on every package version of the registry.
+//!
+//! Every version of every package of the versions cache is solved as a published package,
+//! and the outcome, time and number of decisions of each resolution are recorded.
+//! Records of two runs can be compared to spot regressions of the solver,
+//! such as package versions that do not solve anymore, or that need more backtracking.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::pkg_version::{Cache, PkgVersion};
+use crate::project_config::{PackageConfig, Pkg, ProjectConfig};
+use crate::solver::{solve_deps_with, ExtraMode};
+
+/// Outcome of the resolution of one package version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct BenchRecord {
+    /// The package identifier.
+    pub pkg: Pkg,
+    /// The solved version of the package.
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::SemVerSchema"))]
+    pub version: SemVer,
+    /// Whether its dependencies could be solved.
+    pub solved: bool,
+    /// Duration of the resolution, in microseconds.
+    pub time_us: u64,
+    /// Number of package versions decided during the resolution.
+    pub decisions: usize,
+    /// Number of decisions beyond one per package of the solution,
+    /// made because the solver had to backtrack.
+    pub backtracks: usize,
+}
+
+/// Aggregated records of a benchmark run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BenchSummary {
+    /// Number of solved package versions, successfully or not.
+    pub solves: usize,
+    /// Number of package versions successfully solved.
+    pub successes: usize,
+    /// Total duration of all resolutions.
+    pub total_time: Duration,
+    /// Total number of decisions.
+    pub decisions: usize,
+    /// Total number of backtracking decisions.
+    pub backtracks: usize,
+}
+
+/// Comparison of a benchmark run with a baseline run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchComparison {
+    /// Summary of the baseline run, restricted to package versions in both runs.
+    pub baseline: BenchSummary,
+    /// Summary of the current run, restricted to package versions in both runs.
+    pub current: BenchSummary,
+    /// Package versions solved in the baseline, but not anymore.
+    pub newly_failing: Vec<PkgVersion>,
+    /// Package versions not solved in the baseline, but solved now.
+    pub newly_solved: Vec<PkgVersion>,
+    /// Package versions needing more backtracking than in the baseline,
+    /// with their baseline and current backtracks counts.
+    pub more_backtracks: Vec<(PkgVersion, usize, usize)>,
+}
+
+impl BenchSummary {
+    /// Aggregate benchmark records.
+    pub fn of<'a>(records: impl IntoIterator<Item = &'a BenchRecord>) -> Self {
+        let mut summary = Self::default();
+        for record in records {
+            summary.solves += 1;
+            summary.successes += record.solved as usize;
+            summary.total_time += Duration::from_micros(record.time_us);
+            summary.decisions += record.decisions;
+            summary.backtracks += record.backtracks;
+        }
+        summary
+    }
+
+    /// Ratio of successful resolutions, between 0 and 1.
+    pub fn success_rate(&self) -> f64 {
+        if self.solves == 0 {
+            return 0.0;
+        }
+        self.successes as f64 / self.solves as f64
+    }
+
+    /// Mean duration of a resolution.
+    pub fn mean_time(&self) -> Duration {
+        if self.solves == 0 {
+            return Duration::ZERO;
+        }
+        self.total_time / self.solves as u32
+    }
+}
+
+impl BenchComparison {
+    /// Compare the records of a benchmark run with the records of a baseline run.
+    /// Package versions absent from one of the runs are ignored.
+    pub fn between(baseline: &[BenchRecord], current: &[BenchRecord]) -> Self {
+        let key = |r: &BenchRecord| (r.pkg.clone(), r.version);
+        let baseline: BTreeMap<_, _> = baseline.iter().map(|r| (key(r), r)).collect();
+        let pairs: Vec<(&BenchRecord, &BenchRecord)> = (current.iter())
+            .filter_map(|r| Some((*baseline.get(&key(r))?, r)))
+            .collect();
+        let pkg_version = |r: &BenchRecord| PkgVersion {
+            author_pkg: r.pkg.clone(),
+            version: r.version,
+        };
+        let mut comparison = Self {
+            baseline: BenchSummary::of(pairs.iter().map(|(b, _)| *b)),
+            current: BenchSummary::of(pairs.iter().map(|(_, c)| *c)),
+            newly_failing: Vec::new(),
+            newly_solved: Vec::new(),
+            more_backtracks: Vec::new(),
+        };
+        for (b, c) in pairs {
+            match (b.solved, c.solved) {
+                (true, false) => comparison.newly_failing.push(pkg_version(c)),
+                (false, true) => comparison.newly_solved.push(pkg_version(c)),
+                _ if c.backtracks > b.backtracks => {
+                    (comparison.more_backtracks).push((pkg_version(c), b.backtracks, c.backtracks))
+                }
+                _ => {}
+            }
+        }
+        comparison
+    }
+}
+
+impl fmt::Display for BenchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "solved {} / {} ({:.2}%)",
+            self.successes,
+            self.solves,
+            100.0 * self.success_rate()
+        )?;
+        writeln!(
+            f,
+            "time: {:?} total, {:?} per solve",
+            self.total_time,
+            self.mean_time()
+        )?;
+        write!(
+            f,
+            "decisions: {}, backtracks: {}",
+            self.decisions, self.backtracks
+        )
+    }
+}
+
+impl fmt::Display for BenchComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (b, c) = (&self.baseline, &self.current);
+        writeln!(
+            f,
+            "success rate: {:.2}% -> {:.2}%",
+            100.0 * b.success_rate(),
+            100.0 * c.success_rate()
+        )?;
+        writeln!(
+            f,
+            "time per solve: {:?} -> {:?}",
+            b.mean_time(),
+            c.mean_time()
+        )?;
+        writeln!(f, "decisions: {} -> {}", b.decisions, c.decisions)?;
+        write!(f, "backtracks: {} -> {}", b.backtracks, c.backtracks)?;
+        for pkg_version in &self.newly_failing {
+            write!(f, "\nnewly failing: {}", pkg_version)?;
+        }
+        for pkg_version in &self.newly_solved {
+            write!(f, "\nnewly solved: {}", pkg_version)?;
+        }
+        for (pkg_version, before, after) in &self.more_backtracks {
+            write!(
+                f,
+                "\nmore backtracks: {} ({} -> {})",
+                pkg_version, before, after
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Solve every version of every package of the versions cache
+/// selected by the `select` function, as published packages.
+///
+/// The `load_config` function retrieves the `elm.json` of a package version,
+/// and typically looks into `ELM_HOME`, the dependency solver cache,
+/// and eventually the package server.
+/// All configs are loaded before solving, such that timings only measure the solver.
+/// Package versions whose config cannot be loaded are skipped.
+pub fn bench_registry<Select, Load>(
+    cache: &Cache,
+    select: Select,
+    load_config: Load,
+) -> Vec<BenchRecord>
+where
+    Select: Fn(&Pkg) -> bool,
+    Load: Fn(&PkgVersion) -> Option<PackageConfig>,
+{
+    // Load the configs of all package versions.
+    let mut configs: BTreeMap<(Pkg, SemVer), PackageConfig> = BTreeMap::new();
+    for (pkg, versions) in &cache.cache {
+        for version in versions {
+            let pkg_version = PkgVersion {
+                author_pkg: pkg.clone(),
+                version: *version,
+            };
+            if let Some(config) = load_config(&pkg_version) {
+                configs.insert((pkg.clone(), *version), config);
+            }
+        }
+    }
+
+    // Solve selected package versions, counting the decisions of the solver.
+    let decisions = Cell::new(0);
+    let fetch_elm_json = |pkg: &Pkg, version| {
+        decisions.set(decisions.get() + 1);
+        Ok::<_, Infallible>(configs.get(&(pkg.clone(), version)).cloned())
+    };
+    let list_available_versions = |pkg: &Pkg| {
+        let versions = cache.cache.get(pkg).into_iter().flatten();
+        Ok(versions.rev().copied())
+    };
+    (configs.iter())
+        .filter(|((pkg, _), _)| select(pkg))
+        .map(|((pkg, version), config)| {
+            decisions.set(0);
+            let start = Instant::now();
+            let solution = solve_deps_with(
+                &ProjectConfig::Package(config.clone()),
+                false,
+                &[],
+                ExtraMode::default(),
+                fetch_elm_json,
+                list_available_versions,
+            );
+            let time_us = start.elapsed().as_micros() as u64;
+            let decisions = decisions.get();
+            let solution_size = (solution.as_ref())
+                .map(|s| s.direct.len() + s.indirect.len())
+                .unwrap_or(0);
+            BenchRecord {
+                pkg: pkg.clone(),
+                version: *version,
+                solved: solution.is_ok(),
+                time_us,
+                decisions,
+                backtracks: decisions.saturating_sub(solution_size),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module limiting how long a dependency resolution may run.
+//!
+//! A resolution can be given a budget of decisions, a ceiling on the memory of the process,
+//! measured by the [`CountingAllocator`](crate::memory::CountingAllocator),
+//! and a flag to cancel it from another thread.
+//! When it is interrupted, the error contains the partial solution reached so far,
+//! with the packages decided and the ones that were still undetermined,
+//! such that interactive tools can show where the solver got stuck.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use thiserror::Error;
+
+use crate::memory;
+use crate::project_config::Pkg;
+
+/// Limits of a dependency resolution.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    /// Maximum number of package versions decided before giving up.
+    /// Backtracking may decide the same package multiple times.
+    pub max_decisions: Option<usize>,
+    /// Maximum number of bytes allocated by the process before giving up,
+    /// only enforced with the [`CountingAllocator`](crate::memory::CountingAllocator).
+    pub max_memory: Option<usize>,
+    /// Flag cancelling the resolution when set to `true`.
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+/// Why a resolution was interrupted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Interruption {
+    /// The cancellation flag was set.
+    Cancelled,
+    /// The maximum number of decisions was reached.
+    BudgetExhausted,
+    /// The process allocated more memory than allowed.
+    MemoryCeiling,
+}
+
+/// Partial assignment reached when a resolution was interrupted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialSolution {
+    /// Number of decisions made before the interruption.
+    pub decisions: usize,
+    /// Packages decided so far, with the last version picked for them.
+    pub decided: BTreeMap<Pkg, SemVer>,
+    /// Packages required by decided ones, but not decided yet.
+    pub undetermined: BTreeSet<Pkg>,
+}
+
+/// Error of an interrupted resolution.
+#[derive(Error, Debug, Clone)]
+#[error("dependency resolution {reason} after {} decisions", .partial.decisions)]
+pub struct Interrupted {
+    /// Why the resolution was interrupted.
+    pub reason: Interruption,
+    /// The partial solution at the time of the interruption.
+    pub partial: PartialSolution,
+}
+
+impl Limits {
+    /// Check if the resolution must stop, given the number of decisions already made.
+    pub fn check(&self, decisions: usize) -> Result<(), Interruption> {
+        if let Some(cancel) = &self.cancel {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(Interruption::Cancelled);
+            }
+        }
+        if let (Some(max), Some(allocated)) = (self.max_memory, memory::allocated()) {
+            if allocated > max {
+                return Err(Interruption::MemoryCeiling);
+            }
+        }
+        match self.max_decisions {
+            Some(max) if decisions >= max => Err(Interruption::BudgetExhausted),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Interruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Interruption::Cancelled => write!(f, "was cancelled"),
+            Interruption::BudgetExhausted => write!(f, "exhausted its budget"),
+            Interruption::MemoryCeiling => write!(f, "exceeded the memory ceiling"),
+        }
+    }
+}
+
+impl PartialSolution {
+    /// Record the decision of a package version.
+    pub(crate) fn decide(&mut self, pkg: &Pkg, version: SemVer) {
+        self.decisions += 1;
+        self.undetermined.remove(pkg);
+        self.decided.insert(pkg.clone(), version);
+    }
+
+    /// Record packages required by decided ones.
+    pub(crate) fn require<'a>(&mut self, pkgs: impl Iterator<Item = &'a Pkg>) {
+        for pkg in pkgs {
+            if !self.decided.contains_key(pkg) {
+                self.undetermined.insert(pkg.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,161 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module persisting the most recent solution between invocations.
+//!
+//! Together with the solution, we record the inputs of the resolution
+//! and the dependencies of every selected package version.
+//! That way, when solving again the exact same inputs, the previous solution
+//! can be verified and returned without running the dependency solver at all.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap as Map;
+use std::path::{Path, PathBuf};
+
+use crate::constraint::Constraint;
+use crate::pkg_version::CacheError;
+use crate::project_config::{AppDependencies, PackageConfig, Pkg, ProjectConfig};
+use crate::solver::{ExtraMode, RootDependencies};
+
+/// A solution recorded with everything needed to verify it later.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CachedSolution {
+    inputs: SolveInputs,
+    /// The recorded solution.
+    pub solution: AppDependencies,
+    /// The dependencies of every package version in the solution.
+    pub dependencies: Map<Pkg, Map<Pkg, Constraint>>,
+}
+
+/// Inputs of a dependency resolution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct SolveInputs {
+    project: serde_json::Value,
+    use_test: bool,
+    additional_constraints: Vec<(Pkg, Constraint)>,
+    extra_mode: ExtraMode,
+}
+
+impl SolveInputs {
+    pub(crate) fn new(
+        project_elm_json: &ProjectConfig,
+        use_test: bool,
+        additional_constraints: &[(Pkg, Constraint)],
+        extra_mode: ExtraMode,
+    ) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            project: serde_json::to_value(project_elm_json)?,
+            use_test,
+            additional_constraints: additional_constraints.to_vec(),
+            extra_mode,
+        })
+    }
+}
+
+impl CachedSolution {
+    /// Record a solution, with the dependencies of its packages loaded with `fetch_elm_json`.
+    ///
+    /// Since all these configs were needed to find the solution,
+    /// `fetch_elm_json` should only need to look into local caches.
+    pub fn record<Fetch, E>(
+        project_elm_json: &ProjectConfig,
+        use_test: bool,
+        additional_constraints: &[(Pkg, Constraint)],
+        extra_mode: ExtraMode,
+        solution: AppDependencies,
+        fetch_elm_json: Fetch,
+    ) -> Result<Self, E>
+    where
+        Fetch: Fn(&Pkg, SemVer) -> Result<PackageConfig, E>,
+        E: From<serde_json::Error>,
+    {
+        let inputs = SolveInputs::new(
+            project_elm_json,
+            use_test,
+            additional_constraints,
+            extra_mode,
+        )?;
+        let dependencies = (solution.direct.iter())
+            .chain(solution.indirect.iter())
+            .map(|(p, v)| Ok((p.clone(), fetch_elm_json(p, *v)?.dependencies)))
+            .collect::<Result<_, E>>()?;
+        Ok(Self {
+            inputs,
+            solution,
+            dependencies,
+        })
+    }
+
+    /// Return the recorded solution if it was obtained with the exact same inputs,
+    /// and if it still satisfies all the recorded dependency constraints.
+    pub fn verify(
+        &self,
+        project_elm_json: &ProjectConfig,
+        use_test: bool,
+        additional_constraints: &[(Pkg, Constraint)],
+        extra_mode: ExtraMode,
+    ) -> Option<&AppDependencies> {
+        let inputs = SolveInputs::new(
+            project_elm_json,
+            use_test,
+            additional_constraints,
+            extra_mode,
+        )
+        .ok()?;
+        if inputs != self.inputs {
+            return None;
+        }
+        let root = RootDependencies::new(
+            project_elm_json,
+            use_test,
+            additional_constraints,
+            extra_mode,
+        )
+        .ok()?;
+        let versions: Map<&Pkg, &SemVer> = (self.solution.direct.iter())
+            .chain(self.solution.indirect.iter())
+            .collect();
+        let satisfies = |p: &Pkg, c: &pubgrub::range::Range<SemVer>| match versions.get(p) {
+            Some(v) => c.contains(v),
+            None => false,
+        };
+        let root_ok = root.dependencies.len() == self.solution.direct.len()
+            && root.dependencies.iter().all(|(p, r)| satisfies(p, r));
+        let deps_ok = versions.keys().all(|p| match self.dependencies.get(*p) {
+            Some(deps) => deps.iter().all(|(d, c)| satisfies(d, &c.0)),
+            None => false,
+        });
+        if root_ok && deps_ok {
+            Some(&self.solution)
+        } else {
+            None
+        }
+    }
+
+    /// Load the cached solution from its default location.
+    pub fn load<P: AsRef<Path>>(elm_home: P) -> Result<Self, CacheError> {
+        let s = std::fs::read_to_string(Self::file_path(elm_home))?;
+        serde_json::from_str(&s).map_err(|e| e.into())
+    }
+
+    /// Save the cached solution to its default location.
+    pub fn save<P: AsRef<Path>>(&self, elm_home: P) -> Result<(), CacheError> {
+        let s = serde_json::to_string(self)?;
+        let file_path = Self::file_path(elm_home);
+        std::fs::create_dir_all(file_path.parent().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{}", file_path.display()),
+            )
+        })?)?;
+        std::fs::write(file_path, &s).map_err(|e| e.into())
+    }
+
+    /// Path the to file used to store the most recent solution.
+    /// ~/.elm/pubgrub/cached_solution.json
+    pub fn file_path<P: AsRef<Path>>(elm_home: P) -> PathBuf {
+        Pkg::pubgrub_cache_dir(elm_home).join("cached_solution.json")
+    }
+}
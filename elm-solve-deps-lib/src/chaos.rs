@@ -0,0 +1,445 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module injecting transient failures into dependency providers, to test robustness.
+//!
+//! A [`ChaosProvider`] wraps any dependency provider and makes some of its calls fail
+//! with the kinds of errors real providers encounter: IO errors, malformed JSON and timeouts.
+//! The same failures are injected into the real solvers by wrapping their sources:
+//! their installed packages with a [`ChaosInstalled`],
+//! and the http client of online solvers with a [`ChaosFetch`].
+//! Failures are drawn from a seeded generator, such that a failing run can be replayed,
+//! and can be limited in number, such that retrying callers eventually recover.
+//! Robustness tests then check with [`check_outcome`] that every resolution
+//! either finds the solution found without failures,
+//! or fails with a well-typed error of an injected fault.
+//!
+//! This module is only available with the `chaos` feature.
+
+use pubgrub::range::Range;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+#[cfg(feature = "fs")]
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt::Debug;
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use thiserror::Error;
+
+use crate::dependency_provider::{Dependencies, DependencyProvider};
+#[cfg(feature = "fs")]
+use crate::elm_home::InstalledPackages;
+use crate::error_code::{Coded, ErrorCode};
+use crate::fetch::{FetchError, HttpFetch, Response};
+#[cfg(feature = "fs")]
+use crate::pkg_version::{Cache, PkgVersion, PkgVersionError};
+use crate::project_config::Pkg;
+#[cfg(feature = "fs")]
+use crate::project_config::{PackageConfig, PkgParseError};
+use crate::solver::PubGrubError;
+
+/// Kind of failure injected by a [`ChaosProvider`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Fault {
+    /// An IO error, like when reading a corrupted `ELM_HOME`.
+    Io,
+    /// A malformed JSON document, like a truncated `elm.json`.
+    MalformedJson,
+    /// A request to the package server that timed out.
+    Timeout,
+}
+
+/// Configuration of the failures injected by a [`ChaosProvider`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ChaosConfig {
+    /// Probability for each call to the provider to fail, between 0 and 1.
+    pub rate: f64,
+    /// Seed of the generator drawing failures, to replay a run.
+    pub seed: u64,
+    /// Kinds of failures injected, drawn uniformly.
+    pub faults: Vec<Fault>,
+    /// Maximum number of failures injected, unlimited if `None`.
+    pub max_faults: Option<usize>,
+}
+
+/// Error injected by a [`ChaosProvider`].
+#[derive(Error, Debug)]
+pub enum InjectedError {
+    /// Injected [`Fault::Io`].
+    #[error("injected IO failure")]
+    Io(#[source] std::io::Error),
+
+    /// Injected [`Fault::MalformedJson`].
+    #[error("injected malformed JSON")]
+    MalformedJson(#[source] serde_json::Error),
+
+    /// Injected [`Fault::Timeout`].
+    #[error("injected timeout")]
+    Timeout(#[source] FetchError),
+}
+
+/// Dependency provider failing some of its calls, according to a [`ChaosConfig`].
+///
+/// ```
+/// # use elm_solve_deps::chaos::{check_outcome, ChaosConfig, ChaosProvider};
+/// # use elm_solve_deps::dependency_provider::{resolve, OfflineDependencyProvider};
+/// # use elm_solve_deps::project_config::Pkg;
+/// # use elm_solve_deps::range::Range;
+/// # use elm_solve_deps::semver::SemVer;
+/// let (app, core) = (Pkg::new("author", "app"), Pkg::new("elm", "core"));
+/// let mut offline = OfflineDependencyProvider::<Pkg, SemVer>::new();
+/// offline.add_dependencies(app.clone(), (1, 0, 0), vec![(core.clone(), Range::any())]);
+/// offline.add_dependencies(core.clone(), (1, 0, 5), vec![]);
+/// let expected = resolve(&offline, app.clone(), (1, 0, 0)).unwrap();
+/// for seed in 0..20 {
+///     let config = ChaosConfig { seed, ..ChaosConfig::default() };
+///     let chaos = ChaosProvider::new(&offline, config.clone());
+///     let result = resolve(&chaos, app.clone(), (1, 0, 0));
+///     assert_eq!(check_outcome(&result, &expected, &config), Ok(()));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ChaosProvider<'a, DP> {
+    provider: &'a DP,
+    draws: Draws,
+}
+
+/// Installed packages failing some of their reads, according to a [`ChaosConfig`],
+/// to inject failures into an [`Offline`](crate::solver::Offline) solver
+/// with [`with_installed_packages`](crate::solver::Offline::with_installed_packages).
+///
+/// Loading a config fails with an IO error, a malformed JSON error,
+/// or an IO error of kind [`ErrorKind::TimedOut`] for a [`Fault::Timeout`].
+/// Listing the installed versions of a package never fails,
+/// like a directory listing of a corrupted `ELM_HOME`.
+/// Clones share their generator and their number of injected failures.
+#[cfg(feature = "fs")]
+#[derive(Debug, Clone)]
+pub struct ChaosInstalled<I> {
+    installed: I,
+    draws: Arc<Draws>,
+}
+
+/// Http client failing some of its requests, according to a [`ChaosConfig`],
+/// to inject failures into an [`Online`](crate::solver::Online) solver.
+///
+/// Requests fail with a [`FetchError::Transport`] IO error, or a [`FetchError::Timeout`],
+/// or succeed with a truncated JSON body for a [`Fault::MalformedJson`].
+/// Clones share their generator and their number of injected failures.
+#[derive(Debug, Clone)]
+pub struct ChaosFetch<H> {
+    http_fetch: H,
+    draws: Arc<Draws>,
+}
+
+/// Seeded generator of the failures of a [`ChaosConfig`], counting the injected ones.
+#[derive(Debug)]
+struct Draws {
+    config: ChaosConfig,
+    state: AtomicU64,
+    injected: AtomicUsize,
+}
+
+/// Resolution outcome that robustness tests must never observe.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ChaosViolation {
+    /// The error is not one of this crate.
+    #[error("untyped error: {0}")]
+    Untyped(String),
+
+    /// The solver reached an inconsistent state.
+    #[error("solver failure: {0}")]
+    SolverFailure(String),
+
+    /// The resolution succeeded with another solution than the one found without failures.
+    #[error("wrong solution: {0}")]
+    WrongSolution(String),
+
+    /// The resolution failed with an error that none of the injected faults can cause,
+    /// such as having no solution.
+    #[error("unexpected error: {0}")]
+    Unexpected(String),
+}
+
+impl Fault {
+    /// Codes of the errors this fault causes, with the wrappers of this module.
+    /// Malformed package lists are invalid registry responses.
+    pub fn codes(self) -> &'static [ErrorCode] {
+        match self {
+            Self::Io => &[ErrorCode::Io, ErrorCode::FetchTransport],
+            Self::MalformedJson => &[ErrorCode::Json, ErrorCode::InvalidRegistry],
+            Self::Timeout => &[ErrorCode::Io, ErrorCode::FetchTimeout],
+        }
+    }
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            rate: 0.1,
+            seed: 0,
+            faults: vec![Fault::Io, Fault::MalformedJson, Fault::Timeout],
+            max_faults: None,
+        }
+    }
+}
+
+impl<'a, DP> ChaosProvider<'a, DP> {
+    /// Wrap a dependency provider.
+    pub fn new(provider: &'a DP, config: ChaosConfig) -> Self {
+        Self {
+            provider,
+            draws: Draws::new(config),
+        }
+    }
+
+    /// Number of failures injected so far.
+    pub fn injected(&self) -> usize {
+        self.draws.injected()
+    }
+
+    /// Draw whether the next call fails, and how.
+    fn draw(&self) -> Result<(), InjectedError> {
+        match self.draws.draw() {
+            None => Ok(()),
+            Some(fault) => Err(fault.into()),
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl<I> ChaosInstalled<I> {
+    /// Wrap installed packages.
+    pub fn new(installed: I, config: ChaosConfig) -> Self {
+        Self {
+            installed,
+            draws: Arc::new(Draws::new(config)),
+        }
+    }
+
+    /// Number of failures injected so far, by these installed packages and their clones.
+    pub fn injected(&self) -> usize {
+        self.draws.injected()
+    }
+}
+
+impl<H> ChaosFetch<H> {
+    /// Wrap an http client.
+    pub fn new(http_fetch: H, config: ChaosConfig) -> Self {
+        Self {
+            http_fetch,
+            draws: Arc::new(Draws::new(config)),
+        }
+    }
+
+    /// Number of failures injected so far, by this client and its clones.
+    pub fn injected(&self) -> usize {
+        self.draws.injected()
+    }
+}
+
+impl Draws {
+    fn new(config: ChaosConfig) -> Self {
+        Self {
+            state: AtomicU64::new(config.seed),
+            config,
+            injected: AtomicUsize::new(0),
+        }
+    }
+
+    fn injected(&self) -> usize {
+        self.injected.load(Ordering::SeqCst)
+    }
+
+    /// Draw whether the next call fails, and how.
+    fn draw(&self) -> Option<Fault> {
+        let exhausted = (self.config.max_faults).is_some_and(|max| self.injected() >= max);
+        if exhausted || self.config.faults.is_empty() {
+            return None;
+        }
+        // Uniform number in [0, 1) from the 53 high bits.
+        let sample = (self.next_random() >> 11) as f64 / (1u64 << 53) as f64;
+        if sample >= self.config.rate {
+            return None;
+        }
+        self.injected.fetch_add(1, Ordering::SeqCst);
+        Some(self.config.faults[self.next_random() as usize % self.config.faults.len()])
+    }
+
+    /// SplitMix64 generator, good enough for drawing failures and without dependencies.
+    fn next_random(&self) -> u64 {
+        let increment = 0x9e37_79b9_7f4a_7c15;
+        let state = (self.state.fetch_add(increment, Ordering::SeqCst)).wrapping_add(increment);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl<'a, DP: DependencyProvider<Pkg, SemVer>> DependencyProvider<Pkg, SemVer>
+    for ChaosProvider<'a, DP>
+{
+    fn choose_package_version<T: Borrow<Pkg>, U: Borrow<Range<SemVer>>>(
+        &self,
+        potential_packages: impl Iterator<Item = (T, U)>,
+    ) -> Result<(T, Option<SemVer>), Box<dyn Error>> {
+        self.draw()?;
+        self.provider.choose_package_version(potential_packages)
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &Pkg,
+        version: &SemVer,
+    ) -> Result<Dependencies<Pkg, SemVer>, Box<dyn Error>> {
+        self.draw()?;
+        self.provider.get_dependencies(package, version)
+    }
+
+    /// Forward cancellation to the wrapped dependency provider, cancellation is never injected.
+    fn should_cancel(&self) -> Result<(), Box<dyn Error>> {
+        self.provider.should_cancel()
+    }
+}
+
+#[cfg(feature = "fs")]
+impl<I: InstalledPackages> InstalledPackages for ChaosInstalled<I> {
+    fn list_installed_versions(&self, author_pkg: &Pkg) -> Result<BTreeSet<SemVer>, PkgParseError> {
+        self.installed.list_installed_versions(author_pkg)
+    }
+
+    fn load_config(&self, pkg_version: &PkgVersion) -> Result<PackageConfig, PkgVersionError> {
+        match self.draws.draw() {
+            None => self.installed.load_config(pkg_version),
+            Some(Fault::MalformedJson) => Err(PkgVersionError::JsonError(malformed_json())),
+            Some(fault) => Err(PkgVersionError::FileIoError(io_error(fault))),
+        }
+    }
+
+    fn list_all_installed(&self) -> std::io::Result<Cache> {
+        match self.draws.draw() {
+            None => self.installed.list_all_installed(),
+            Some(fault) => Err(io_error(fault)),
+        }
+    }
+
+    fn elm_version_of(&self, pkg_version: &PkgVersion) -> Option<String> {
+        self.installed.elm_version_of(pkg_version)
+    }
+}
+
+impl<H: HttpFetch> HttpFetch for ChaosFetch<H> {
+    fn get(&self, url: &str) -> Result<Response, FetchError> {
+        match self.draws.draw() {
+            None => self.http_fetch.get(url),
+            Some(Fault::MalformedJson) => Ok(Response {
+                status: 200,
+                body: TRUNCATED_JSON.to_string(),
+            }),
+            Some(fault) => Err(fetch_error(fault)),
+        }
+    }
+
+    fn fetch(&self, url: &str) -> Result<String, FetchError> {
+        match self.draws.draw() {
+            None => self.http_fetch.fetch(url),
+            Some(Fault::MalformedJson) => Ok(TRUNCATED_JSON.to_string()),
+            Some(fault) => Err(fetch_error(fault)),
+        }
+    }
+
+    fn fetch_bytes(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        match self.draws.draw() {
+            None => self.http_fetch.fetch_bytes(url),
+            Some(Fault::MalformedJson) => Ok(TRUNCATED_JSON.as_bytes().to_vec()),
+            Some(fault) => Err(fetch_error(fault)),
+        }
+    }
+}
+
+/// Body of the JSON documents truncated by a [`Fault::MalformedJson`].
+const TRUNCATED_JSON: &str = "{\"";
+
+/// JSON error of a truncated document.
+fn malformed_json() -> serde_json::Error {
+    serde_json::from_str::<serde_json::Value>(TRUNCATED_JSON).unwrap_err()
+}
+
+/// IO error injected for a fault, timing out for a [`Fault::Timeout`].
+#[cfg(feature = "fs")]
+fn io_error(fault: Fault) -> std::io::Error {
+    match fault {
+        Fault::Timeout => std::io::Error::new(ErrorKind::TimedOut, "chaos testing"),
+        _ => std::io::Error::other("chaos testing"),
+    }
+}
+
+/// Network error injected for a fault other than [`Fault::MalformedJson`].
+fn fetch_error(fault: Fault) -> FetchError {
+    match fault {
+        Fault::Timeout => FetchError::Timeout,
+        _ => FetchError::Transport(Box::new(std::io::Error::new(
+            ErrorKind::ConnectionReset,
+            "chaos testing",
+        ))),
+    }
+}
+
+impl From<Fault> for InjectedError {
+    fn from(fault: Fault) -> Self {
+        match fault {
+            Fault::Io => Self::Io(std::io::Error::other("chaos testing")),
+            Fault::MalformedJson => Self::MalformedJson(malformed_json()),
+            Fault::Timeout => Self::Timeout(FetchError::Timeout),
+        }
+    }
+}
+
+impl Coded for InjectedError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::Io(_) => ErrorCode::Io,
+            Self::MalformedJson(_) => ErrorCode::Json,
+            Self::Timeout(err) => err.code(),
+        }
+    }
+}
+
+/// Check that a resolution with the failures of `config` either found the `expected` solution,
+/// found without failures, or failed with a well-typed error of one of the injected faults,
+/// identified by one of their [`Fault::codes`].
+/// Failures of the solver itself, other solutions and other errors, like having no solution,
+/// are never acceptable.
+pub fn check_outcome<T: PartialEq + Debug>(
+    result: &Result<T, PubGrubError<Pkg, SemVer>>,
+    expected: &T,
+    config: &ChaosConfig,
+) -> Result<(), ChaosViolation> {
+    let err = match result {
+        Ok(solution) if solution == expected => return Ok(()),
+        Ok(solution) => return Err(ChaosViolation::WrongSolution(format!("{:?}", solution))),
+        Err(err) => err,
+    };
+    let source = match err {
+        PubGrubError::Failure(msg) => return Err(ChaosViolation::SolverFailure(msg.clone())),
+        PubGrubError::ErrorRetrievingDependencies { source, .. } => source.as_ref(),
+        PubGrubError::ErrorChoosingPackageVersion(source) => source.as_ref(),
+        PubGrubError::ErrorInShouldCancel(source) => source.as_ref(),
+        _ => return Err(ChaosViolation::Unexpected(err.to_string())),
+    };
+    let code = match source.downcast_ref::<InjectedError>() {
+        Some(injected) => injected.code(),
+        None => ErrorCode::of(source),
+    };
+    let mut codes = config.faults.iter().flat_map(|fault| fault.codes());
+    match code {
+        ErrorCode::Other => Err(ChaosViolation::Untyped(source.to_string())),
+        _ if codes.any(|c| *c == code) => Ok(()),
+        _ => Err(ChaosViolation::Unexpected(source.to_string())),
+    }
+}
@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module abstracting the current time, for reproducible outputs.
+//!
+//! Outputs embedding timestamps, such as lock files or reports,
+//! must get the current time from a [`Clock`] instead of calling [`SystemTime::now`].
+//! Reproducible-build environments can then produce byte-identical artifacts
+//! by setting the `SOURCE_DATE_EPOCH` environment variable,
+//! following the convention of <https://reproducible-builds.org/specs/source-date-epoch/>,
+//! and tests can inject a [`FixedClock`].
+
+use std::fmt::Debug;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Name of the environment variable fixing the current time, in seconds since the unix epoch.
+pub const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+/// Source of the current time.
+pub trait Clock: Debug {
+    /// The current time.
+    fn now(&self) -> SystemTime;
+
+    /// The current time, in seconds since the unix epoch.
+    fn unix_secs(&self) -> u64 {
+        (self.now().duration_since(UNIX_EPOCH)).map_or(0, |d| d.as_secs())
+    }
+}
+
+/// Clock of the system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+/// Clock always returning the same time.
+///
+/// ```
+/// # use elm_solve_deps::clock::{Clock, FixedClock};
+/// let clock = FixedClock::from_unix_secs(1_600_000_000);
+/// assert_eq!(clock.unix_secs(), 1_600_000_000);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub SystemTime);
+
+/// Error parsing the `SOURCE_DATE_EPOCH` environment variable.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid SOURCE_DATE_EPOCH `{0}`, expected a number of seconds since the unix epoch")]
+pub struct SourceDateEpochError(pub String);
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+impl FixedClock {
+    /// Clock fixed at the given number of seconds since the unix epoch.
+    pub fn from_unix_secs(secs: u64) -> Self {
+        Self(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Clock fixed at the time of the `SOURCE_DATE_EPOCH` environment variable, if set.
+    pub fn source_date_epoch() -> Result<Option<Self>, SourceDateEpochError> {
+        match std::env::var(SOURCE_DATE_EPOCH) {
+            Ok(secs) => (secs.trim().parse())
+                .map(|secs| Some(Self::from_unix_secs(secs)))
+                .map_err(|_| SourceDateEpochError(secs)),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        self.0
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Box<C> {
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
+
+/// The clock of the current environment:
+/// fixed at `SOURCE_DATE_EPOCH` if set, for deterministic outputs,
+/// and the system clock otherwise.
+pub fn from_env() -> Result<Box<dyn Clock + Send + Sync>, SourceDateEpochError> {
+    match FixedClock::source_date_epoch()? {
+        Some(clock) => Ok(Box::new(clock)),
+        None => Ok(Box::new(SystemClock)),
+    }
+}
@@ -0,0 +1,209 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module compressing the package configs and versions caches of the dependency solver.
+//!
+//! The `elm_json_cache` in `ELM_HOME/pubgrub` holds tens of thousands of small `elm.json` files,
+//! and the versions cache lists every version of every package,
+//! which adds up to a lot of reads and writes on slow disks.
+//! These files can be compressed with gzip, enabled for an `ELM_HOME`
+//! by saving its [`Compression`] in `ELM_HOME/pubgrub/compression`,
+//! which [`compact`] does while migrating the existing files.
+//!
+//! Reads detect compressed files from their first bytes,
+//! such that compressed and uncompressed files can coexist,
+//! and compression can be enabled or disabled at any time.
+//! Compressing and decompressing files requires the `flate2` feature.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::paths;
+use crate::project_config::Pkg;
+
+/// First bytes of gzip files.
+pub const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Compression of the files of the dependency solver cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Compression {
+    /// Plain files.
+    #[default]
+    None,
+    /// Files compressed with gzip.
+    Gzip,
+}
+
+/// Error parsing a [`Compression`], which must be either `none` or `gzip`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown compression `{0}`, expected `none` or `gzip`")]
+pub struct CompressionParseError(pub String);
+
+/// Summary of a [`compact`] migration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompactReport {
+    /// Number of migrated files.
+    pub files: usize,
+    /// Total size of the files before the migration, in bytes.
+    pub bytes_before: u64,
+    /// Total size of the files after the migration, in bytes.
+    pub bytes_after: u64,
+}
+
+impl FromStr for Compression {
+    type Err = CompressionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "gzip" => Ok(Self::Gzip),
+            _ => Err(CompressionParseError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Gzip => write!(f, "gzip"),
+        }
+    }
+}
+
+impl Compression {
+    /// Compression of the files written in the cache of an `ELM_HOME`,
+    /// none unless enabled by [`save`](Self::save).
+    pub fn of_cache<P: AsRef<Path>>(elm_home: P) -> Self {
+        std::fs::read_to_string(Self::file_path(elm_home))
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Use this compression for the files written in the cache of an `ELM_HOME`.
+    pub fn save<P: AsRef<Path>>(self, elm_home: P) -> io::Result<()> {
+        std::fs::create_dir_all(Pkg::pubgrub_cache_dir(&elm_home))?;
+        std::fs::write(Self::file_path(elm_home), self.to_string())
+    }
+
+    /// Path to the file recording the compression of the cache.
+    /// ~/.elm/pubgrub/compression
+    pub fn file_path<P: AsRef<Path>>(elm_home: P) -> PathBuf {
+        Pkg::pubgrub_cache_dir(elm_home).join("compression")
+    }
+
+    /// Compress some content.
+    pub fn encode(self, content: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(content.to_vec()),
+            #[cfg(feature = "flate2")]
+            Self::Gzip => {
+                use std::io::Write;
+                let level = flate2::Compression::default();
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), level);
+                encoder.write_all(content)?;
+                encoder.finish()
+            }
+            #[cfg(not(feature = "flate2"))]
+            Self::Gzip => Err(gzip_unsupported()),
+        }
+    }
+}
+
+/// Decode the content of a file, decompressing it if needed.
+///
+/// ```
+/// # use elm_solve_deps::compression::decode;
+/// assert_eq!(decode(b"{}".to_vec()).unwrap(), "{}");
+/// ```
+pub fn decode(bytes: Vec<u8>) -> io::Result<String> {
+    let bytes = if bytes.starts_with(&GZIP_MAGIC) {
+        decompress(&bytes)?
+    } else {
+        bytes
+    };
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(feature = "flate2")]
+fn decompress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    use std::io::Read;
+    let mut decompressed = Vec::new();
+    flate2::read::GzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+#[cfg(not(feature = "flate2"))]
+fn decompress(_bytes: &[u8]) -> io::Result<Vec<u8>> {
+    Err(gzip_unsupported())
+}
+
+#[cfg(not(feature = "flate2"))]
+fn gzip_unsupported() -> io::Error {
+    let msg = "gzip compressed caches require the flate2 feature";
+    io::Error::new(io::ErrorKind::Unsupported, msg)
+}
+
+/// Read a file of the cache, compressed or not.
+pub fn read_to_string<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    decode(std::fs::read(path)?)
+}
+
+/// Write a file of the cache, with the given compression.
+pub fn write<P: AsRef<Path>>(path: P, content: &str, compression: Compression) -> io::Result<()> {
+    std::fs::write(path, compression.encode(content.as_bytes())?)
+}
+
+/// Migrate the cached package configs and versions caches of an `ELM_HOME` to a compression,
+/// and use it for the files written from now on.
+pub fn compact<P: AsRef<Path>>(elm_home: P, compression: Compression) -> io::Result<CompactReport> {
+    let pubgrub_dir = Pkg::pubgrub_cache_dir(&elm_home);
+    let mut files = Vec::new();
+    collect_files(
+        &paths::elm_json_cache_dir(&elm_home),
+        "elm.json",
+        &mut files,
+    )?;
+    collect_files(&pubgrub_dir, "versions_cache.json", &mut files)?;
+    let mut report = CompactReport::default();
+    for path in files {
+        let bytes = std::fs::read(&path)?;
+        let encoded = compression.encode(decode(bytes.clone())?.as_bytes())?;
+        report.files += 1;
+        report.bytes_before += bytes.len() as u64;
+        report.bytes_after += encoded.len() as u64;
+        if encoded != bytes {
+            // Replace the file instead of writing into it,
+            // since it may be hard-linked from a shared cache.
+            let tmp_path = path.with_extension("json.tmp");
+            std::fs::write(&tmp_path, encoded)?;
+            std::fs::rename(tmp_path, &path)?;
+        }
+    }
+    compression.save(elm_home)?;
+    Ok(report)
+}
+
+/// Recursively collect the files with a given name inside a directory.
+fn collect_files(dir: &Path, name: &str, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_files(&entry.path(), name, files)?;
+        } else if file_type.is_file() && entry.file_name() == name {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
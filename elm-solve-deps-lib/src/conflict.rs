@@ -0,0 +1,288 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module searching for a minimal set of conflicting direct dependencies.
+//!
+//! When a project with many dependencies fails to solve, the derivation tree
+//! explaining the failure can be very long.
+//! Using delta debugging over the direct dependencies, this module finds a minimal subset
+//! of them that still cannot be solved together, typically only two or three packages.
+//! Removing any package of that subset makes it solvable.
+//!
+//! Without searching, [`suggest_resolutions`] ranks the direct dependencies
+//! the most involved in the derivation tree of the failure,
+//! and proposes to remove or downgrade them,
+//! keeping only the changes verified to allow a solution.
+
+use pubgrub::report::{DerivationTree, External};
+use pubgrub::version::SemanticVersion as SemVer;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::constraint::Constraint;
+use crate::project_config::{Pkg, ProjectConfig};
+use crate::range::Range;
+use crate::solver::{ExtraMode, RootDependencies};
+
+/// A minimal set of direct dependencies that cannot be solved together.
+#[derive(Debug, Clone)]
+pub struct MinimalConflict<E> {
+    /// The conflicting direct dependencies.
+    pub packages: BTreeSet<Pkg>,
+    /// The error of the resolution with only these direct dependencies.
+    pub error: E,
+    /// Number of resolutions attempted during the search.
+    pub attempts: usize,
+}
+
+/// Find a minimal subset of the direct dependencies of a project that cannot be solved together.
+///
+/// Direct dependencies include the additional constraints.
+/// The `solve` argument is typically the `solve_deps` method of one of the solvers,
+/// such as [`Offline::solve_deps`](crate::solver::Offline::solve_deps).
+/// Every error of `solve` is considered a conflict, so it should not fail for other reasons,
+/// like network issues, during the search.
+///
+/// Returns `None` if the project can be solved with all its direct dependencies.
+pub fn minimize_conflict<Solve, T, E>(
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+    solve: Solve,
+) -> Option<MinimalConflict<E>>
+where
+    Solve: Fn(&ProjectConfig, bool, &[(Pkg, Constraint)], ExtraMode) -> Result<T, E>,
+{
+    let mut attempts = 0;
+    let mut conflict = |pkgs: &[Pkg]| {
+        attempts += 1;
+        let (project, extras) = restrict(project_elm_json, use_test, additional_constraints, pkgs);
+        solve(&project, use_test, &extras, extra_mode).err()
+    };
+
+    let mut pkgs = direct_packages(project_elm_json, use_test, additional_constraints);
+    let mut error = conflict(&pkgs)?;
+
+    // Delta debugging: try removing chunks of packages with a decreasing chunk size.
+    let mut granularity = 2;
+    while pkgs.len() >= 2 {
+        let chunk_size = pkgs.len().div_ceil(granularity);
+        let chunks: Vec<&[Pkg]> = pkgs.chunks(chunk_size).collect();
+        let mut reduced = None;
+        // Check if a single chunk is enough to conflict.
+        for chunk in &chunks {
+            if let Some(err) = conflict(chunk) {
+                reduced = Some((chunk.to_vec(), err, 2));
+                break;
+            }
+        }
+        // Otherwise, check if removing a single chunk keeps the conflict.
+        if reduced.is_none() && chunks.len() > 2 {
+            for i in 0..chunks.len() {
+                let complement: Vec<Pkg> = (chunks.iter().enumerate())
+                    .filter(|(j, _)| *j != i)
+                    .flat_map(|(_, chunk)| chunk.iter().cloned())
+                    .collect();
+                if let Some(err) = conflict(&complement) {
+                    reduced = Some((complement, err, (granularity - 1).max(2)));
+                    break;
+                }
+            }
+        }
+        match reduced {
+            Some((subset, err, new_granularity)) => {
+                pkgs = subset;
+                error = err;
+                granularity = new_granularity;
+            }
+            None if granularity >= pkgs.len() => break,
+            None => granularity = (2 * granularity).min(pkgs.len()),
+        }
+    }
+
+    Some(MinimalConflict {
+        packages: pkgs.into_iter().collect(),
+        error,
+        attempts,
+    })
+}
+
+/// A change of a direct dependency verified to allow a solution.
+///
+/// ```
+/// # use elm_solve_deps::conflict::Suggestion;
+/// # use elm_solve_deps::project_config::Pkg;
+/// # use elm_solve_deps::semver::SemVer;
+/// let suggestion = Suggestion {
+///     pkg: Pkg::new("author", "x"),
+///     responsibility: 3,
+///     remove: true,
+///     downgrade_below: Some(SemVer::new(3, 0, 0)),
+/// };
+/// assert_eq!(
+///     suggestion.to_string(),
+///     "removing author/x or downgrading it below 3.0.0 would allow a solution"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// The direct dependency.
+    pub pkg: Pkg,
+    /// Number of incompatibilities of the derivation tree involving it.
+    pub responsibility: usize,
+    /// Whether removing it allows a solution.
+    pub remove: bool,
+    /// The lowest version it is allowed to have,
+    /// if requiring versions strictly lower than it allows a solution.
+    pub downgrade_below: Option<SemVer>,
+}
+
+impl fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.remove, self.downgrade_below) {
+            (true, Some(v)) => write!(f, "removing {} or downgrading it below {}", self.pkg, v)?,
+            (true, None) => write!(f, "removing {}", self.pkg)?,
+            (false, Some(v)) => write!(f, "downgrading {} below {}", self.pkg, v)?,
+            (false, None) => write!(f, "changing {}", self.pkg)?,
+        }
+        write!(f, " would allow a solution")
+    }
+}
+
+/// Suggest removals and downgrades of direct dependencies allowing a failed resolution to succeed.
+///
+/// Direct dependencies are ranked by the number of incompatibilities of the derivation tree
+/// of the failure they are involved in, and the `max_candidates` most involved ones are tried,
+/// first removed, then restricted to versions below the lowest one they currently allow.
+/// Every suggestion is verified by solving again with `solve`, which is typically
+/// the `solve_deps` method of the solver that failed, so there are at most
+/// two resolutions per candidate.
+/// Suggestions are returned most involved first.
+pub fn suggest_resolutions<Solve, T, E>(
+    tree: &DerivationTree<Pkg, SemVer>,
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+    max_candidates: usize,
+    solve: Solve,
+) -> Vec<Suggestion>
+where
+    Solve: Fn(&ProjectConfig, bool, &[(Pkg, Constraint)], ExtraMode) -> Result<T, E>,
+{
+    let root = match RootDependencies::new(
+        project_elm_json,
+        use_test,
+        additional_constraints,
+        extra_mode,
+    ) {
+        Ok(root) => root,
+        Err(_) => return Vec::new(),
+    };
+    let mut mentions = BTreeMap::new();
+    count_mentions(tree, &mut mentions);
+    let mut ranked: Vec<(Pkg, usize)> = (mentions.into_iter())
+        .filter(|(pkg, _)| root.dependencies.contains_key(pkg))
+        .collect();
+    ranked.sort_by(|(p1, n1), (p2, n2)| n2.cmp(n1).then_with(|| p1.cmp(p2)));
+
+    let all_pkgs = direct_packages(project_elm_json, use_test, additional_constraints);
+    let mut suggestions = Vec::new();
+    for (pkg, responsibility) in ranked.into_iter().take(max_candidates) {
+        let others: Vec<Pkg> = all_pkgs.iter().filter(|p| **p != pkg).cloned().collect();
+        let (project, extras) =
+            restrict(project_elm_json, use_test, additional_constraints, &others);
+        let remove = solve(&project, use_test, &extras, extra_mode).is_ok();
+        let downgrade_below = (root.dependencies.get(&pkg))
+            .and_then(|range| range.lowest_version())
+            .filter(|lowest| *lowest != SemVer::zero())
+            .filter(|lowest| {
+                let mut extras = extras.clone();
+                let below = Constraint(Range::strictly_lower_than(*lowest));
+                extras.push((pkg.clone(), below));
+                solve(&project, use_test, &extras, extra_mode).is_ok()
+            });
+        if remove || downgrade_below.is_some() {
+            suggestions.push(Suggestion {
+                pkg,
+                responsibility,
+                remove,
+                downgrade_below,
+            });
+        }
+    }
+    suggestions
+}
+
+/// Count the external incompatibilities of a derivation tree each package is involved in.
+fn count_mentions(tree: &DerivationTree<Pkg, SemVer>, mentions: &mut BTreeMap<Pkg, usize>) {
+    let mut mention = |pkg: &Pkg| *mentions.entry(pkg.clone()).or_default() += 1;
+    match tree {
+        DerivationTree::External(External::FromDependencyOf(pkg, _, dep, _)) => {
+            mention(pkg);
+            mention(dep);
+        }
+        DerivationTree::External(External::NotRoot(pkg, _))
+        | DerivationTree::External(External::NoVersions(pkg, _))
+        | DerivationTree::External(External::UnavailableDependencies(pkg, _)) => mention(pkg),
+        DerivationTree::Derived(derived) => {
+            count_mentions(&derived.cause1, mentions);
+            count_mentions(&derived.cause2, mentions);
+        }
+    }
+}
+
+/// All direct dependencies of a project, including the additional constraints.
+fn direct_packages(
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+) -> Vec<Pkg> {
+    let mut pkgs: BTreeSet<Pkg> = BTreeSet::new();
+    match project_elm_json {
+        ProjectConfig::Application(app_config) => {
+            pkgs.extend(app_config.dependencies.direct.keys().cloned());
+            if use_test {
+                pkgs.extend(app_config.test_dependencies.direct.keys().cloned());
+            }
+        }
+        ProjectConfig::Package(pkg_config) => {
+            pkgs.extend(pkg_config.dependencies.keys().cloned());
+            if use_test {
+                pkgs.extend(pkg_config.test_dependencies.keys().cloned());
+            }
+        }
+    }
+    pkgs.extend(additional_constraints.iter().map(|(p, _)| p.clone()));
+    pkgs.into_iter().collect()
+}
+
+/// Restrict the direct dependencies of a project and the additional constraints
+/// to the given packages.
+fn restrict(
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    pkgs: &[Pkg],
+) -> (ProjectConfig, Vec<(Pkg, Constraint)>) {
+    let mut project = project_elm_json.clone();
+    match &mut project {
+        ProjectConfig::Application(app_config) => {
+            (app_config.dependencies.direct).retain(|p, _| pkgs.contains(p));
+            if use_test {
+                (app_config.test_dependencies.direct).retain(|p, _| pkgs.contains(p));
+            }
+        }
+        ProjectConfig::Package(pkg_config) => {
+            (pkg_config.dependencies).retain(|p, _| pkgs.contains(p));
+            if use_test {
+                (pkg_config.test_dependencies).retain(|p, _| pkgs.contains(p));
+            }
+        }
+    }
+    let extras = (additional_constraints.iter())
+        .filter(|(p, _)| pkgs.contains(p))
+        .cloned()
+        .collect();
+    (project, extras)
+}
@@ -5,11 +5,23 @@
 use pubgrub::range::Range;
 use pubgrub::version::{SemanticVersion as SemVer, VersionParseError};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 use thiserror::Error;
 
 /// A constraint is a simple newtype for ranges of versions defined in the pubgrub crate.
-#[derive(Debug, Clone)]
+///
+/// It is displayed and serialized with the elm syntax `"1.0.0 <= v < 2.0.0"`
+/// whenever the range has that shape.
+///
+/// ```
+/// # use elm_solve_deps::constraint::Constraint;
+/// let constraint: Constraint = "1.0.2 <= v < 2.0.0".parse().unwrap();
+/// assert_eq!(constraint.to_string(), "1.0.2 <= v < 2.0.0");
+/// assert_eq!(constraint, "1.0.1 < v < 2.0.0".parse().unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Constraint(pub Range<SemVer>);
 
 /// Error creating [Constraint] from [String].
@@ -73,12 +85,69 @@ impl FromStr for Constraint {
     }
 }
 
+impl Constraint {
+    /// Check if a version satisfies the constraint.
+    ///
+    /// ```
+    /// # use elm_solve_deps::constraint::Constraint;
+    /// # use elm_solve_deps::semver::SemVer;
+    /// let constraint: Constraint = "1.1.2 <= v < 2.0.0".parse().unwrap();
+    /// assert!(constraint.allows(&SemVer::new(1, 1, 3)));
+    /// assert!(!constraint.allows(&SemVer::new(2, 0, 0)));
+    /// ```
+    pub fn allows(&self, version: &SemVer) -> bool {
+        self.0.contains(version)
+    }
+
+    /// Bounds of the constraint, if it has the shape `low <= v < high`.
+    fn bounds(&self) -> Option<(SemVer, SemVer)> {
+        let low = self.0.lowest_version()?;
+        let above = self.0.negate().intersection(&Range::higher_than(low));
+        let high = above.lowest_version()?;
+        if self.0 == Range::between(low, high) {
+            Some((low, high))
+        } else {
+            None
+        }
+    }
+}
+
+impl From<Range<SemVer>> for Constraint {
+    fn from(range: Range<SemVer>) -> Self {
+        Self(range)
+    }
+}
+
+impl From<Constraint> for Range<SemVer> {
+    fn from(constraint: Constraint) -> Self {
+        constraint.0
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.bounds() {
+            Some((low, high)) => write!(f, "{} <= v < {}", low, high),
+            // Ranges without an elm equivalent fall back to the pubgrub syntax.
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+// Implemented manually since ranges are not hashable.
+// Equal ranges have the same normalized representation, hence the same string.
+impl Hash for Constraint {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_string().hash(state);
+    }
+}
+
 impl Serialize for Constraint {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serializer.serialize_str(self.0.to_string().as_str())
+        serializer.serialize_str(self.to_string().as_str())
     }
 }
 
@@ -0,0 +1,232 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module recording reproducible test cases from real dependency resolutions.
+//!
+//! A [`Recorder`] attached to a solver with
+//! [`Offline::with_recorder`](crate::solver::Offline::with_recorder)
+//! keeps every package config and every versions list consulted while solving.
+//! They can then be saved as a self-contained [`TestCase`] directory,
+//! and loaded later to replay the same resolution without `ELM_HOME` or network access.
+//! This helps building a regression corpus from real-world projects.
+//!
+//! A test case directory has the following layout.
+//!
+//! ```txt
+//! test-case/
+//! ├── elm.json            the project config
+//! ├── inputs.json         use-test, additional-constraints and extra-mode
+//! ├── versions.json       versions listed for every package, in order of priority
+//! ├── solution.json       the solution found, absent if the resolution failed
+//! └── packages/
+//!     └── author/package/version/elm.json
+//! ```
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap as Map;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+use crate::constraint::Constraint;
+use crate::project_config::{AppDependencies, PackageConfig, Pkg, ProjectConfig};
+use crate::solver::{solve_deps_with, ExtraMode, SolveError};
+
+/// Recorder of the package configs and versions lists consulted while solving.
+///
+/// Clones share the same records.
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    records: Arc<Mutex<Records>>,
+}
+
+#[derive(Debug, Default)]
+struct Records {
+    configs: Map<Pkg, Map<SemVer, PackageConfig>>,
+    versions: Map<Pkg, Vec<SemVer>>,
+}
+
+/// A self-contained dependency resolution, with everything needed to replay it.
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    /// The project config.
+    pub project: ProjectConfig,
+    /// Whether test dependencies are solved too.
+    pub use_test: bool,
+    /// Additional constraints of the resolution.
+    pub additional_constraints: Vec<(Pkg, Constraint)>,
+    /// How additional constraints are combined with the project ones.
+    pub extra_mode: ExtraMode,
+    /// Configs of all consulted package versions.
+    pub configs: Map<Pkg, Map<SemVer, PackageConfig>>,
+    /// Versions listed for every consulted package, in order of priority.
+    pub versions: Map<Pkg, Vec<SemVer>>,
+    /// The solution found, if the resolution succeeded.
+    pub solution: Option<AppDependencies>,
+}
+
+/// Inputs of a test case, other than the project config.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Inputs {
+    use_test: bool,
+    additional_constraints: Vec<(Pkg, Constraint)>,
+    extra_mode: ExtraMode,
+}
+
+/// Error type for test cases.
+#[derive(Error, Debug)]
+pub enum CorpusError {
+    /// Error reading or writing a test case directory.
+    #[error("failed to read or write the test case")]
+    FileIoError(#[from] std::io::Error),
+
+    /// Error decoding or encoding a file of the test case.
+    #[error("invalid file in the test case")]
+    JsonError(#[from] serde_json::Error),
+
+    /// A package config needed to replay the resolution is missing.
+    #[error("the elm.json of {0}@{1} is not part of the test case")]
+    MissingConfig(Pkg, SemVer),
+}
+
+impl Recorder {
+    /// Initialize an empty recorder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a consulted package config.
+    pub fn record_config(&self, config: &PackageConfig) {
+        let mut records = self.records.lock().unwrap();
+        (records.configs.entry(config.name.clone()).or_default())
+            .insert(config.version, config.clone());
+    }
+
+    /// Record the versions listed for a package, in order of priority.
+    pub fn record_versions(&self, pkg: &Pkg, versions: &[SemVer]) {
+        let mut records = self.records.lock().unwrap();
+        records.versions.insert(pkg.clone(), versions.to_vec());
+    }
+
+    /// Build a test case from the records and the inputs of the resolution.
+    pub fn test_case(
+        &self,
+        project: &ProjectConfig,
+        use_test: bool,
+        additional_constraints: &[(Pkg, Constraint)],
+        extra_mode: ExtraMode,
+        solution: Option<&AppDependencies>,
+    ) -> TestCase {
+        let records = self.records.lock().unwrap();
+        TestCase {
+            project: project.clone(),
+            use_test,
+            additional_constraints: additional_constraints.to_vec(),
+            extra_mode,
+            configs: records.configs.clone(),
+            versions: records.versions.clone(),
+            solution: solution.cloned(),
+        }
+    }
+}
+
+impl TestCase {
+    /// Save the test case into a directory, with the layout described in the module doc.
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> Result<(), CorpusError> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        write_json(dir.join("elm.json"), &self.project)?;
+        let inputs = Inputs {
+            use_test: self.use_test,
+            additional_constraints: self.additional_constraints.clone(),
+            extra_mode: self.extra_mode,
+        };
+        write_json(dir.join("inputs.json"), &inputs)?;
+        write_json(dir.join("versions.json"), &self.versions)?;
+        if let Some(solution) = &self.solution {
+            write_json(dir.join("solution.json"), solution)?;
+        }
+        for (pkg, configs) in &self.configs {
+            for (version, config) in configs {
+                let pkg_dir = dir.join("packages").join(&pkg.author).join(&pkg.pkg);
+                let version_dir = pkg_dir.join(version.to_string());
+                fs::create_dir_all(&version_dir)?;
+                write_json(version_dir.join("elm.json"), config)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Load a test case saved with [`TestCase::save`].
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, CorpusError> {
+        let dir = dir.as_ref();
+        let project = read_json(dir.join("elm.json"))?;
+        let inputs: Inputs = read_json(dir.join("inputs.json"))?;
+        let versions = read_json(dir.join("versions.json"))?;
+        let solution_path = dir.join("solution.json");
+        let solution = if solution_path.exists() {
+            Some(read_json(solution_path)?)
+        } else {
+            None
+        };
+        let mut configs: Map<Pkg, Map<SemVer, PackageConfig>> = Map::new();
+        let packages_dir = dir.join("packages");
+        if packages_dir.exists() {
+            for author in fs::read_dir(packages_dir)? {
+                for pkg in fs::read_dir(author?.path())? {
+                    for version in fs::read_dir(pkg?.path())? {
+                        let config: PackageConfig = read_json(version?.path().join("elm.json"))?;
+                        (configs.entry(config.name.clone()).or_default())
+                            .insert(config.version, config);
+                    }
+                }
+            }
+        }
+        Ok(Self {
+            project,
+            use_test: inputs.use_test,
+            additional_constraints: inputs.additional_constraints,
+            extra_mode: inputs.extra_mode,
+            configs,
+            versions,
+            solution,
+        })
+    }
+
+    /// Replay the resolution with the recorded configs and versions lists only.
+    ///
+    /// The result is expected to be the recorded [`solution`](TestCase::solution).
+    pub fn solve(&self) -> Result<AppDependencies, SolveError<CorpusError>> {
+        let fetch_elm_json = |pkg: &Pkg, version: SemVer| {
+            (self.configs.get(pkg))
+                .and_then(|configs| configs.get(&version))
+                .cloned()
+                .map(Some)
+                .ok_or_else(|| CorpusError::MissingConfig(pkg.clone(), version))
+        };
+        let list_available_versions = |pkg: &Pkg| {
+            let versions = self.versions.get(pkg).cloned().unwrap_or_default();
+            Ok(versions.into_iter())
+        };
+        solve_deps_with(
+            &self.project,
+            self.use_test,
+            &self.additional_constraints,
+            self.extra_mode,
+            fetch_elm_json,
+            list_available_versions,
+        )
+    }
+}
+
+fn write_json<P: AsRef<Path>, T: Serialize>(path: P, value: &T) -> Result<(), CorpusError> {
+    fs::write(path, serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
+
+fn read_json<P: AsRef<Path>, T: for<'de> Deserialize<'de>>(path: P) -> Result<T, CorpusError> {
+    let s = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&s)?)
+}
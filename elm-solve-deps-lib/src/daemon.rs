@@ -0,0 +1,258 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module defining the runtime-agnostic core of long-running dependency solving daemons.
+//!
+//! A [`Daemon`] keeps its solvers and caches warm between requests,
+//! which is what makes servers, such as the `serve` command of the binary,
+//! faster than solving from scratch in every job of a build farm.
+//!
+//! The daemon owns no event loop and does not pick an async runtime,
+//! although its solvers read `ELM_HOME`, fetch packages over HTTP,
+//! and refresh their caches in background threads.
+//! A thin shell receives requests, in whatever protocol, passes them to [`Daemon::handle`],
+//! and sends back the responses.
+//! Periodic maintenance, such as updating the list of existing packages,
+//! happens in [`Daemon::poll`], which tells when it should be called next,
+//! such that shells can wait with a tokio or async-std timer,
+//! or with a plain thread and a receive timeout.
+//! The current time is always given by the shell, which keeps the daemon deterministic.
+//!
+//! Handling a request and polling are synchronous steps, and the daemon is never left
+//! in an inconsistent state between two steps.
+//! Async shells, which would typically run these steps with `spawn_blocking` or similar,
+//! can thus drop pending requests at any time without corrupting the daemon.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use pubgrub::error::PubGrubError;
+
+use crate::constraint::Constraint;
+use crate::fetch::HttpFetch;
+use crate::pkg_version::{Cache, CacheError};
+use crate::project_config::{AppDependencies, Pkg, ProjectConfig};
+use crate::semver::SemVer;
+use crate::solver::{ExtraMode, Offline, Online, VersionStrategy};
+
+/// Default duration between two updates of the list of existing packages.
+pub const DEFAULT_REFRESH_PERIOD: Duration = Duration::from_secs(300);
+
+/// Request to solve the dependencies of a project.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SolveRequest {
+    /// The `elm.json` of the project.
+    pub elm_json: ProjectConfig,
+    /// Whether to include the test dependencies.
+    #[serde(default)]
+    pub test: bool,
+    /// Additional constraints.
+    #[serde(default)]
+    pub extra: BTreeMap<Pkg, Constraint>,
+    /// How the additional constraints combine with the dependencies of the project.
+    #[serde(default)]
+    pub extra_mode: ExtraMode,
+    /// Strategy of the online solver, which is only used if set,
+    /// or if solving offline fails.
+    #[serde(default)]
+    pub strategy: Option<VersionStrategy>,
+    /// Whether to only solve offline.
+    #[serde(default)]
+    pub offline: bool,
+}
+
+/// Request handled by a [`Daemon`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    /// Solve the dependencies of a project.
+    Solve(Box<SolveRequest>),
+    /// List the known versions of a package.
+    Versions(Pkg),
+}
+
+/// Response of a [`Daemon`] to a [`Request`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    /// The solution of a [`Request::Solve`].
+    Solution(AppDependencies),
+    /// The known versions of the package of a [`Request::Versions`].
+    Versions(BTreeSet<SemVer>),
+}
+
+/// Error handling a [`Request`].
+#[derive(Error, Debug)]
+pub enum DaemonError {
+    /// The request asks for an online strategy while solving offline.
+    #[error("offline and strategy are mutually exclusive")]
+    OfflineStrategy,
+    /// The request needs the online solver, but the daemon only solves offline.
+    #[error("the server only solves offline")]
+    OfflineOnly,
+    /// The package of a [`Request::Versions`] is unknown.
+    #[error("unknown package {0}")]
+    UnknownPackage(Pkg),
+    /// The online solver could not be created.
+    #[error("failed to initialize the online solver")]
+    OnlineSolver(#[source] CacheError),
+    /// Solving the dependencies failed.
+    #[error(transparent)]
+    Solve(Box<PubGrubError<Pkg, SemVer>>),
+}
+
+/// Solvers and caches kept between requests, and when they were last updated.
+///
+/// Online solvers, one per [`VersionStrategy`], are created on first use by `mk_online_solver`.
+/// The list of existing packages is updated by `update_versions`, unless solving offline only.
+pub struct Daemon<F: HttpFetch, MkOnline, UpdateVersions> {
+    offline: bool,
+    refresh_period: Duration,
+    offline_solver: Offline,
+    mk_online_solver: MkOnline,
+    online_solvers: Vec<(VersionStrategy, Instant, Online<F>)>,
+    update_versions: UpdateVersions,
+    versions_cache: Cache,
+    versions_updated: Instant,
+}
+
+impl<F, MkOnline, UpdateVersions, E> Daemon<F, MkOnline, UpdateVersions>
+where
+    F: HttpFetch + Clone + Send + 'static,
+    MkOnline: FnMut(VersionStrategy) -> Result<Online<F>, CacheError>,
+    UpdateVersions: FnMut() -> Result<Cache, E>,
+{
+    /// Initialize a daemon at the current time `now`, with a versions cache just loaded.
+    pub fn new(
+        offline_solver: Offline,
+        versions_cache: Cache,
+        mk_online_solver: MkOnline,
+        update_versions: UpdateVersions,
+        now: Instant,
+    ) -> Self {
+        Self {
+            offline: false,
+            refresh_period: DEFAULT_REFRESH_PERIOD,
+            offline_solver,
+            mk_online_solver,
+            online_solvers: Vec::new(),
+            update_versions,
+            versions_cache,
+            versions_updated: now,
+        }
+    }
+
+    /// Only solve offline, never creating online solvers nor updating the versions cache.
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Change the duration between two updates of the list of existing packages.
+    pub fn with_refresh_period(mut self, refresh_period: Duration) -> Self {
+        self.refresh_period = refresh_period;
+        self
+    }
+
+    /// Handle a request at the current time `now`.
+    pub fn handle(&mut self, request: &Request, now: Instant) -> Result<Response, DaemonError> {
+        match request {
+            Request::Solve(request) => self.solve(request, now).map(Response::Solution),
+            Request::Versions(pkg) => match self.versions_cache.cache.get(pkg) {
+                Some(versions) => Ok(Response::Versions(versions.clone())),
+                None => Err(DaemonError::UnknownPackage(pkg.clone())),
+            },
+        }
+    }
+
+    /// Run the maintenance due at the current time `now`,
+    /// and return how long to wait before polling again.
+    ///
+    /// Online solvers are refreshed in the background,
+    /// and the versions cache is updated in place, keeping the previous one if that fails.
+    /// Failed updates are retried at the next period, so it is fine to poll again right away.
+    pub fn poll(&mut self, now: Instant) -> Result<Duration, E> {
+        for (_, refreshed, online_solver) in &mut self.online_solvers {
+            if now.saturating_duration_since(*refreshed) >= self.refresh_period {
+                online_solver.refresh_in_background();
+                *refreshed = now;
+            }
+        }
+        if !self.offline
+            && now.saturating_duration_since(self.versions_updated) >= self.refresh_period
+        {
+            self.versions_updated = now;
+            self.versions_cache = (self.update_versions)()?;
+        }
+        let mut last_updates: Vec<Instant> = self
+            .online_solvers
+            .iter()
+            .map(|(_, refreshed, _)| *refreshed)
+            .collect();
+        if !self.offline {
+            last_updates.push(self.versions_updated);
+        }
+        let next = last_updates
+            .into_iter()
+            .min()
+            .map(|last| last + self.refresh_period);
+        Ok(next.map_or(self.refresh_period, |next| {
+            next.saturating_duration_since(now)
+        }))
+    }
+
+    fn solve(
+        &mut self,
+        request: &SolveRequest,
+        now: Instant,
+    ) -> Result<AppDependencies, DaemonError> {
+        if request.offline && request.strategy.is_some() {
+            return Err(DaemonError::OfflineStrategy);
+        }
+        if self.offline && !request.offline {
+            return Err(DaemonError::OfflineOnly);
+        }
+        let extras: Vec<(Pkg, Constraint)> = request.extra.clone().into_iter().collect();
+        let solve_offline = || {
+            (self.offline_solver)
+                .solve_deps(&request.elm_json, request.test, &extras, request.extra_mode)
+                .map_err(|err| DaemonError::Solve(Box::new(err)))
+        };
+        match request.strategy {
+            _ if request.offline => solve_offline(),
+            None => solve_offline().or_else(|_| self.solve_online(request, &extras, now)),
+            Some(_) => self.solve_online(request, &extras, now),
+        }
+    }
+
+    fn solve_online(
+        &mut self,
+        request: &SolveRequest,
+        extras: &[(Pkg, Constraint)],
+        now: Instant,
+    ) -> Result<AppDependencies, DaemonError> {
+        let strat = request.strategy.unwrap_or(VersionStrategy::Newest);
+        self.online_solver(strat, now)?
+            .solve_deps(&request.elm_json, request.test, extras, request.extra_mode)
+            .map_err(|err| DaemonError::Solve(Box::new(err)))
+    }
+
+    /// Online solver of the given strategy, created if needed.
+    fn online_solver(
+        &mut self,
+        strat: VersionStrategy,
+        now: Instant,
+    ) -> Result<&Online<F>, DaemonError> {
+        let position = self.online_solvers.iter().position(|(s, _, _)| *s == strat);
+        let i = match position {
+            Some(i) => i,
+            None => {
+                let online_solver =
+                    (self.mk_online_solver)(strat).map_err(DaemonError::OnlineSolver)?;
+                (self.online_solvers).push((strat, now, online_solver));
+                self.online_solvers.len() - 1
+            }
+        };
+        Ok(&self.online_solvers[i].2)
+    }
+}
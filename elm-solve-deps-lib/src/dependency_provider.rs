@@ -4,7 +4,6 @@
 //! provider into one that is using a project `elm.json` as root.
 
 use pubgrub::range::Range;
-use pubgrub::solver::{Dependencies, DependencyProvider};
 use pubgrub::type_aliases::Map;
 use pubgrub::version::SemanticVersion as SemVer;
 use std::borrow::Borrow;
@@ -12,6 +11,11 @@ use std::error::Error;
 
 use crate::project_config::Pkg;
 
+/// Dependency provider interface of pubgrub, its offline implementation,
+/// and the resolution function using them,
+/// re-exported such that downstream code does not depend on pubgrub.
+pub use pubgrub::solver::{resolve, Dependencies, DependencyProvider, OfflineDependencyProvider};
+
 /// Dependency provider of a package or an application elm project.
 /// Will only work properly if used to resolve dependencies for its root.
 ///
@@ -79,4 +83,9 @@ impl<'a, DP: DependencyProvider<Pkg, SemVer>> DependencyProvider<Pkg, SemVer>
             self.deps_provider.get_dependencies(package, version)
         }
     }
+
+    /// Forward cancellation to the wrapped dependency provider.
+    fn should_cancel(&self) -> Result<(), Box<dyn Error>> {
+        self.deps_provider.should_cancel()
+    }
 }
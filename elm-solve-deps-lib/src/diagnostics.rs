@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module collecting non-fatal issues encountered while solving dependencies.
+//!
+//! Some inconsistencies in the packages data do not prevent dependency resolution,
+//! but are worth reporting to users.
+//! Solvers record them as structured [`Diagnostic`] values in a shared [`Diagnostics`] channel,
+//! that can be drained after the resolution.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::pkg_version::PkgVersion;
+use crate::project_config::{PackageConfig, Pkg};
+
+/// A non-fatal issue encountered while solving dependencies.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Diagnostic {
+    /// The `name` field of a package `elm.json` does not match the package it was loaded for.
+    /// The identity of the package it was loaded for is used instead.
+    NameMismatch {
+        /// The package version the `elm.json` was loaded for.
+        pkg_version: PkgVersion,
+        /// The name found in the `elm.json`.
+        found: Pkg,
+    },
+    /// A dependency of the project has an obsolete name, see [`Renames`](crate::renames::Renames).
+    Renamed {
+        /// The obsolete name.
+        from: Pkg,
+        /// The name of its successor.
+        to: Pkg,
+        /// Whether the successor replaced the obsolete dependency in the resolution.
+        replaced: bool,
+    },
+    /// The project does not depend on a package mandatory in its ecosystem,
+    /// see [`Ecosystem`](crate::ecosystem::Ecosystem).
+    MissingMandatory {
+        /// The missing package.
+        pkg: Pkg,
+        /// Whether the package was added to the resolution.
+        injected: bool,
+    },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::NameMismatch { pkg_version, found } => write!(
+                f,
+                "the elm.json of {} is named {}, using {} instead",
+                pkg_version, found, pkg_version.author_pkg
+            ),
+            Diagnostic::Renamed {
+                from,
+                to,
+                replaced: true,
+            } => write!(f, "{} was renamed to {}, which replaced it", from, to),
+            Diagnostic::Renamed {
+                from,
+                to,
+                replaced: false,
+            } => write!(
+                f,
+                "{} was renamed to {}, consider depending on it instead",
+                from, to
+            ),
+            Diagnostic::MissingMandatory {
+                pkg,
+                injected: true,
+            } => write!(f, "{} is mandatory but missing, it was added", pkg),
+            Diagnostic::MissingMandatory {
+                pkg,
+                injected: false,
+            } => write!(
+                f,
+                "{} is mandatory but missing, the compiler will reject the solution, \
+                consider adding it to the dependencies",
+                pkg
+            ),
+        }
+    }
+}
+
+/// Shared channel of diagnostics.
+///
+/// Clones share the same underlying list of diagnostics,
+/// such that diagnostics recorded by a solver are visible to all handles.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    diagnostics: Arc<Mutex<Vec<Diagnostic>>>,
+}
+
+impl Diagnostics {
+    /// Initialize an empty channel of diagnostics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a diagnostic, unless an identical one was already recorded.
+    pub fn push(&self, diagnostic: Diagnostic) {
+        let mut diagnostics = self.diagnostics.lock().unwrap();
+        if !diagnostics.contains(&diagnostic) {
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Remove and return all the diagnostics recorded until now.
+    pub fn take(&self) -> Vec<Diagnostic> {
+        std::mem::take(&mut *self.diagnostics.lock().unwrap())
+    }
+
+    /// Make sure that a package config loaded for `pkg_version` has the matching name.
+    ///
+    /// In case of mismatch, the name derived from where the config was loaded is preferred,
+    /// and a [`Diagnostic::NameMismatch`] is recorded.
+    pub fn check_name(&self, pkg_version: &PkgVersion, mut config: PackageConfig) -> PackageConfig {
+        if config.name != pkg_version.author_pkg {
+            let found = std::mem::replace(&mut config.name, pkg_version.author_pkg.clone());
+            self.push(Diagnostic::NameMismatch {
+                pkg_version: pkg_version.clone(),
+                found,
+            });
+        }
+        config
+    }
+}
@@ -0,0 +1,424 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module checking the integrity of `ELM_HOME`.
+//!
+//! Interrupted downloads, concurrent writes, or files created by another user
+//! leave `ELM_HOME` in states that break dependency solving in obscure ways.
+//! This module scans `ELM_HOME` for such common problems,
+//! and fixes them when possible, typically by removing the broken files,
+//! such that they are downloaded or rebuilt again when needed.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use pubgrub::version::SemanticVersion as SemVer;
+
+use crate::compression;
+use crate::packed::PackedStore;
+use crate::paths;
+use crate::pkg_version::{Cache, PkgVersion};
+use crate::project_config::{PackageConfig, Pkg};
+
+/// A problem found in `ELM_HOME`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Problem {
+    /// An installed package version has no `elm.json`.
+    MissingElmJson {
+        /// The installed package version.
+        pkg_version: PkgVersion,
+        /// Its installation directory.
+        path: PathBuf,
+    },
+    /// An installed package version has an `elm.json`, but no `src/` directory,
+    /// typically because its extraction was interrupted.
+    PartialExtraction {
+        /// The installed package version.
+        pkg_version: PkgVersion,
+        /// Its installation directory.
+        path: PathBuf,
+    },
+    /// The `elm.json` of an installed package version is invalid.
+    InvalidElmJson {
+        /// The installed package version.
+        pkg_version: PkgVersion,
+        /// Its installation directory.
+        path: PathBuf,
+        /// Why the `elm.json` is invalid.
+        error: String,
+    },
+    /// An `elm.json` of the dependency solver cache is invalid.
+    CorruptedCachedConfig {
+        /// The cached package version.
+        pkg_version: PkgVersion,
+        /// The path of the cached `elm.json`.
+        path: PathBuf,
+        /// Why the cached `elm.json` is invalid.
+        error: String,
+    },
+    /// A config of the packed store of the dependency solver cache is unreadable,
+    /// typically because its data file was truncated.
+    CorruptedPack {
+        /// The first packed package version whose config is unreadable.
+        pkg_version: PkgVersion,
+        /// The path of the data file of the packed store.
+        path: PathBuf,
+        /// Why the packed config is unreadable.
+        error: String,
+    },
+    /// The versions cache of the dependency solver is invalid.
+    CorruptedVersionsCache {
+        /// The path of the versions cache.
+        path: PathBuf,
+        /// Why the versions cache is invalid.
+        error: String,
+    },
+    /// Installed package versions are missing from the versions cache,
+    /// which thus is older than the installed packages.
+    StaleVersionsCache {
+        /// The path of the versions cache.
+        path: PathBuf,
+        /// The installed package versions missing from the versions cache.
+        missing: Vec<PkgVersion>,
+    },
+    /// The `registry.dat` of the elm compiler is unreadable,
+    /// or knows fewer package versions than are installed.
+    ///
+    /// The elm compiler only installs versions listed in its `registry.dat`,
+    /// but only refreshes it when it needs a version it does not know,
+    /// so knowing fewer versions than the versions cache is expected, and not reported.
+    MismatchedRegistry {
+        /// The path of `registry.dat`.
+        path: PathBuf,
+        /// Number of package versions known to `registry.dat`, if readable.
+        registry_versions: Option<u64>,
+        /// Number of installed package versions.
+        installed_versions: u64,
+    },
+    /// A file or directory cannot be read or written, typically because of its permissions.
+    Inaccessible {
+        /// The inaccessible path.
+        path: PathBuf,
+        /// The error when accessing it.
+        error: String,
+    },
+}
+
+impl Problem {
+    /// What can be done about this problem.
+    pub fn suggestion(&self) -> String {
+        match self {
+            Problem::MissingElmJson { path, .. }
+            | Problem::PartialExtraction { path, .. }
+            | Problem::InvalidElmJson { path, .. }
+            | Problem::CorruptedCachedConfig { path, .. } => {
+                format!("remove {} to download it again", path.display())
+            }
+            Problem::CorruptedPack { path, .. } => format!(
+                "remove {} and its index to download the packed configs again",
+                path.display()
+            ),
+            Problem::CorruptedVersionsCache { path, .. } => {
+                format!("remove {} to rebuild it", path.display())
+            }
+            Problem::StaleVersionsCache { .. } => {
+                "add the installed versions to the versions cache, or solve online".to_string()
+            }
+            Problem::MismatchedRegistry { path, .. } => format!(
+                "run elm install for elm to update {}, or remove it at your own risk",
+                path.display()
+            ),
+            Problem::Inaccessible { path, .. } => format!(
+                "give the current user read and write access to {}",
+                path.display()
+            ),
+        }
+    }
+
+    /// Fix the problem, if it can be fixed automatically.
+    /// Return whether it was fixed.
+    pub fn fix<P: AsRef<Path>>(&self, elm_home: P) -> io::Result<bool> {
+        match self {
+            Problem::MissingElmJson { path, .. }
+            | Problem::PartialExtraction { path, .. }
+            | Problem::InvalidElmJson { path, .. } => std::fs::remove_dir_all(path)?,
+            Problem::CorruptedCachedConfig { path, .. }
+            | Problem::CorruptedVersionsCache { path, .. } => std::fs::remove_file(path)?,
+            Problem::CorruptedPack { .. } => {
+                let store = PackedStore::open(&elm_home)?;
+                for path in [store.index_path(), store.data_path()] {
+                    match std::fs::remove_file(path) {
+                        Err(err) if err.kind() != ErrorKind::NotFound => return Err(err),
+                        _ => {}
+                    }
+                }
+            }
+            Problem::StaleVersionsCache { missing, .. } => {
+                let mut cache = Cache::load(&elm_home).map_err(io::Error::other)?;
+                for pkg_version in missing {
+                    (cache.cache.entry(pkg_version.author_pkg.clone()))
+                        .or_default()
+                        .insert(pkg_version.version);
+                }
+                cache.save(&elm_home).map_err(io::Error::other)?;
+            }
+            // registry.dat belongs to the elm compiler, which cannot work offline without it.
+            Problem::MismatchedRegistry { .. } | Problem::Inaccessible { .. } => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::MissingElmJson { pkg_version, .. } => {
+                write!(f, "{} is installed without elm.json", pkg_version)
+            }
+            Problem::PartialExtraction { pkg_version, .. } => {
+                write!(f, "{} is partially extracted", pkg_version)
+            }
+            Problem::InvalidElmJson {
+                pkg_version, error, ..
+            } => write!(
+                f,
+                "the installed elm.json of {} is invalid: {}",
+                pkg_version, error
+            ),
+            Problem::CorruptedCachedConfig {
+                pkg_version, error, ..
+            } => write!(
+                f,
+                "the cached elm.json of {} is corrupted: {}",
+                pkg_version, error
+            ),
+            Problem::CorruptedPack {
+                pkg_version, error, ..
+            } => write!(
+                f,
+                "the packed elm.json of {} is corrupted: {}",
+                pkg_version, error
+            ),
+            Problem::CorruptedVersionsCache { path, error } => write!(
+                f,
+                "the versions cache {} is corrupted: {}",
+                path.display(),
+                error
+            ),
+            Problem::StaleVersionsCache { missing, .. } => write!(
+                f,
+                "the versions cache is missing {} installed versions, such as {}",
+                missing.len(),
+                missing
+                    .first()
+                    .map(PkgVersion::to_string)
+                    .unwrap_or_default()
+            ),
+            Problem::MismatchedRegistry {
+                path,
+                registry_versions: Some(count),
+                installed_versions,
+            } => write!(
+                f,
+                "{} knows {} package versions, but {} are installed",
+                path.display(),
+                count,
+                installed_versions
+            ),
+            Problem::MismatchedRegistry { path, .. } => {
+                write!(f, "{} is unreadable", path.display())
+            }
+            Problem::Inaccessible { path, error } => {
+                write!(f, "cannot access {}: {}", path.display(), error)
+            }
+        }
+    }
+}
+
+/// Scan `ELM_HOME` for problems breaking dependency solving, for the given version of elm.
+///
+/// Problems are reported in the order of the scan:
+/// installed packages first, then the dependency solver caches,
+/// and finally `registry.dat`.
+pub fn diagnose<P: AsRef<Path>>(elm_home: P, elm_version: &str) -> Vec<Problem> {
+    let elm_home = elm_home.as_ref();
+    let mut problems = Vec::new();
+
+    // Installed packages.
+    let packages_dir = paths::packages_dir(elm_home, elm_version);
+    let installed = walk_versions(&packages_dir, &mut problems);
+    let installed_versions = installed.len() as u64;
+    for (pkg_version, path) in &installed {
+        let problem = match std::fs::read_to_string(path.join("elm.json")) {
+            Err(err) if err.kind() == ErrorKind::NotFound => Some(Problem::MissingElmJson {
+                pkg_version: pkg_version.clone(),
+                path: path.clone(),
+            }),
+            Err(err) => Some(io_problem(&path.join("elm.json"), err)),
+            Ok(config_str) => match serde_json::from_str::<PackageConfig>(&config_str) {
+                Err(err) => Some(Problem::InvalidElmJson {
+                    pkg_version: pkg_version.clone(),
+                    path: path.clone(),
+                    error: err.to_string(),
+                }),
+                Ok(_) if !path.join("src").is_dir() => Some(Problem::PartialExtraction {
+                    pkg_version: pkg_version.clone(),
+                    path: path.clone(),
+                }),
+                Ok(_) => None,
+            },
+        };
+        problems.extend(problem);
+    }
+
+    // Cached package configs.
+    let pubgrub_dir = Pkg::pubgrub_cache_dir(elm_home);
+    let cache_dir = paths::elm_json_cache_dir(elm_home);
+    for (pkg_version, path) in walk_versions(&cache_dir, &mut problems) {
+        let path = path.join("elm.json");
+        match compression::read_to_string(&path) {
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => problems.push(io_problem(&path, err)),
+            Ok(config_str) => {
+                if let Err(err) = serde_json::from_str::<PackageConfig>(&config_str) {
+                    problems.push(Problem::CorruptedCachedConfig {
+                        pkg_version,
+                        path,
+                        error: err.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    // Packed package configs.
+    match PackedStore::open(elm_home) {
+        Err(err) => problems.push(io_problem(&pubgrub_dir, err)),
+        Ok(store) => {
+            let corrupted = (store.versions()).find_map(|p| Some((p, store.load_config(p).err()?)));
+            if let Some((pkg_version, err)) = corrupted {
+                let error = match std::error::Error::source(&err) {
+                    Some(source) => format!("{}: {}", err, source),
+                    None => err.to_string(),
+                };
+                problems.push(Problem::CorruptedPack {
+                    pkg_version: pkg_version.clone(),
+                    path: store.data_path().to_path_buf(),
+                    error,
+                });
+            }
+        }
+    }
+
+    // Versions cache.
+    let cache_path = Cache::file_path(elm_home);
+    let cache = match compression::read_to_string(&cache_path) {
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(err) => {
+            problems.push(io_problem(&cache_path, err));
+            None
+        }
+        Ok(cache_str) => match serde_json::from_str::<Cache>(&cache_str) {
+            Ok(cache) => Some(cache),
+            Err(err) => {
+                problems.push(Problem::CorruptedVersionsCache {
+                    path: cache_path.clone(),
+                    error: err.to_string(),
+                });
+                None
+            }
+        },
+    };
+    if let Some(cache) = &cache {
+        let missing: Vec<PkgVersion> = (installed.into_iter())
+            .map(|(pkg_version, _)| pkg_version)
+            .filter(|p| !(cache.cache.get(&p.author_pkg)).is_some_and(|v| v.contains(&p.version)))
+            .collect();
+        if !missing.is_empty() {
+            problems.push(Problem::StaleVersionsCache {
+                path: cache_path,
+                missing,
+            });
+        }
+    }
+
+    // Check that the dependency solver cache is writable.
+    if pubgrub_dir.is_dir() {
+        let probe = pubgrub_dir.join(".doctor");
+        match std::fs::write(&probe, "") {
+            Ok(()) => std::fs::remove_file(&probe).unwrap_or(()),
+            Err(err) => problems.push(io_problem(&pubgrub_dir, err)),
+        }
+    }
+
+    // registry.dat of the elm compiler.
+    let registry_path = packages_dir.join("registry.dat");
+    if let Ok(registry) = std::fs::read(&registry_path) {
+        let registry_versions = registry_count(&registry);
+        if registry_versions.is_none_or(|count| count < installed_versions) {
+            problems.push(Problem::MismatchedRegistry {
+                path: registry_path,
+                registry_versions,
+                installed_versions,
+            });
+        }
+    }
+    problems
+}
+
+/// Number of package versions known to a `registry.dat` file,
+/// stored by the elm compiler as a big-endian 64 bits integer at its start.
+fn registry_count(registry: &[u8]) -> Option<u64> {
+    let count: [u8; 8] = registry.get(..8)?.try_into().ok()?;
+    Some(u64::from_be_bytes(count))
+}
+
+/// List the `author/package/version` directories inside `dir`,
+/// recording the directories that cannot be read.
+fn walk_versions(dir: &Path, problems: &mut Vec<Problem>) -> Vec<(PkgVersion, PathBuf)> {
+    let mut versions = Vec::new();
+    for (author, author_dir) in sub_dirs(dir, problems) {
+        for (pkg, pkg_dir) in sub_dirs(&author_dir, problems) {
+            for (version, version_dir) in sub_dirs(&pkg_dir, problems) {
+                if let Ok(version) = SemVer::from_str(&version) {
+                    let pkg_version = PkgVersion {
+                        author_pkg: Pkg::new(&author, &pkg),
+                        version,
+                    };
+                    versions.push((pkg_version, version_dir));
+                }
+            }
+        }
+    }
+    versions
+}
+
+/// List the sub directories of a directory, with their names, sorted.
+/// Nothing is recorded if the directory does not exist.
+fn sub_dirs(dir: &Path, problems: &mut Vec<Problem>) -> Vec<(String, PathBuf)> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Vec::new(),
+        Err(err) => {
+            problems.push(io_problem(dir, err));
+            return Vec::new();
+        }
+    };
+    let mut dirs: Vec<(String, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|f| f.is_dir()))
+        .filter_map(|entry| Some((entry.file_name().into_string().ok()?, entry.path())))
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+fn io_problem(path: &Path, err: io::Error) -> Problem {
+    Problem::Inaccessible {
+        path: path.to_path_buf(),
+        error: err.to_string(),
+    }
+}
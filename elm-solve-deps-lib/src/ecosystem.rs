@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module defining the packages mandatory in the projects of each elm-compatible ecosystem.
+//!
+//! The compiler rejects applications that do not depend directly on `elm/core`,
+//! or that do not depend on `elm/json`, and packages that do not depend on `elm/core`.
+//! Hand-edited `elm.json` files sometimes omit them,
+//! and the solutions then miss them too, unless another dependency brings them in.
+//! Other compilers, such as the one of Lamdera, have their own mandatory packages.
+//!
+//! [`Ecosystem::missing_packages`] finds the mandatory packages a project does not depend on,
+//! and [`Ecosystem::inject`] adds them to the additional constraints of the resolution,
+//! without constraining their versions.
+
+use pubgrub::range::Range;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::constraint::Constraint;
+use crate::project_config::{Pkg, ProjectConfig};
+
+/// Mandatory packages of the applications of the elm ecosystem.
+pub const ELM_APPLICATION_PACKAGES: &[&str] = &["elm/core", "elm/json"];
+
+/// Mandatory packages of the applications of the Lamdera ecosystem.
+pub const LAMDERA_APPLICATION_PACKAGES: &[&str] =
+    &["elm/core", "elm/json", "lamdera/codecs", "lamdera/core"];
+
+/// Mandatory packages of the packages of all ecosystems.
+pub const PACKAGE_PACKAGES: &[&str] = &["elm/core"];
+
+/// An elm-compatible ecosystem, with its own mandatory packages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Ecosystem {
+    /// The official elm compiler.
+    #[default]
+    Elm,
+    /// The Lamdera compiler, for full-stack applications.
+    Lamdera,
+}
+
+/// Error parsing an [`Ecosystem`], which must be either `elm` or `lamdera`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown ecosystem `{0}`, expected `elm` or `lamdera`")]
+pub struct EcosystemParseError(pub String);
+
+impl FromStr for Ecosystem {
+    type Err = EcosystemParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "elm" => Ok(Self::Elm),
+            "lamdera" => Ok(Self::Lamdera),
+            _ => Err(EcosystemParseError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Ecosystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Elm => write!(f, "elm"),
+            Self::Lamdera => write!(f, "lamdera"),
+        }
+    }
+}
+
+impl Ecosystem {
+    /// Packages a project must depend on for the compiler of this ecosystem to accept it.
+    pub fn mandatory_packages(self, project: &ProjectConfig) -> Vec<Pkg> {
+        let names = match (self, project) {
+            (Self::Elm, ProjectConfig::Application(_)) => ELM_APPLICATION_PACKAGES,
+            (Self::Lamdera, ProjectConfig::Application(_)) => LAMDERA_APPLICATION_PACKAGES,
+            (_, ProjectConfig::Package(_)) => PACKAGE_PACKAGES,
+        };
+        // Mandatory package names are valid.
+        names.iter().map(|name| name.parse().unwrap()).collect()
+    }
+
+    /// Mandatory packages that are neither dependencies of a project,
+    /// nor in the additional constraints of its resolution.
+    ///
+    /// Applications must depend on `elm/core` directly, an indirect dependency does not count.
+    /// Test dependencies do not count either, since the compiler needs these packages outside of tests.
+    ///
+    /// ```
+    /// # use elm_solve_deps::ecosystem::Ecosystem;
+    /// # use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+    /// let project: ProjectConfig = serde_json::from_str(
+    ///     r#"{
+    ///         "type": "application",
+    ///         "source-directories": ["src"],
+    ///         "elm-version": "0.19.1",
+    ///         "dependencies": {
+    ///             "direct": { "elm/html": "1.0.0" },
+    ///             "indirect": {
+    ///                 "elm/core": "1.0.5",
+    ///                 "elm/json": "1.1.3",
+    ///                 "elm/virtual-dom": "1.0.3"
+    ///             }
+    ///         },
+    ///         "test-dependencies": { "direct": {}, "indirect": {} }
+    ///     }"#,
+    /// )
+    /// .unwrap();
+    /// let missing = Ecosystem::Elm.missing_packages(&project, &[]);
+    /// assert_eq!(missing, vec![Pkg::new("elm", "core")]);
+    /// let extras = Ecosystem::Elm.inject(&project, &[]);
+    /// assert_eq!(extras[0].0, Pkg::new("elm", "core"));
+    /// ```
+    pub fn missing_packages(
+        self,
+        project: &ProjectConfig,
+        additional_constraints: &[(Pkg, Constraint)],
+    ) -> Vec<Pkg> {
+        let core = Pkg::new("elm", "core");
+        let depends_on = |pkg: &Pkg| match project {
+            ProjectConfig::Application(app) if pkg == &core => {
+                app.dependencies.direct.contains_key(pkg)
+            }
+            ProjectConfig::Application(app) => {
+                app.dependencies.direct.contains_key(pkg)
+                    || app.dependencies.indirect.contains_key(pkg)
+            }
+            ProjectConfig::Package(config) => config.dependencies.contains_key(pkg),
+        };
+        (self.mandatory_packages(project).into_iter())
+            .filter(|pkg| !depends_on(pkg))
+            .filter(|pkg| !additional_constraints.iter().any(|(p, _)| p == pkg))
+            .collect()
+    }
+
+    /// Additional constraints of the resolution of a project, with its missing mandatory packages,
+    /// without constraining their versions.
+    pub fn inject(
+        self,
+        project: &ProjectConfig,
+        additional_constraints: &[(Pkg, Constraint)],
+    ) -> Vec<(Pkg, Constraint)> {
+        let missing = self.missing_packages(project, additional_constraints);
+        let mut constraints = additional_constraints.to_vec();
+        constraints.extend(
+            missing
+                .into_iter()
+                .map(|pkg| (pkg, Constraint(Range::any()))),
+        );
+        constraints
+    }
+}
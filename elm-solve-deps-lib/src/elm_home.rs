@@ -0,0 +1,295 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module abstracting how installed packages are read from `ELM_HOME`.
+//!
+//! By default, packages are read from the `ELM_HOME` directory on the filesystem,
+//! with the [`Directory`] implementation.
+//! Users keeping the toolchains of several versions of elm in the same `ELM_HOME`,
+//! such as 0.19.0 and 0.19.1, can merge their packages directories with [`Directories`].
+//! Some CI setups ship `ELM_HOME` as an archive instead, to avoid thousands of small files.
+//! In that case, the [`Archive`] implementation reads installed package configs
+//! directly from a `.tar` (with the `tar` feature) or `.zip` (with the `zip` feature)
+//! archive of `ELM_HOME`, without extracting it.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+
+use crate::pkg_version::{Cache, PkgVersion, PkgVersionError};
+use crate::project_config::{PackageConfig, Pkg, PkgParseError};
+
+/// Read access to the packages installed in `ELM_HOME`.
+pub trait InstalledPackages: Debug {
+    /// List installed versions of a package.
+    fn list_installed_versions(&self, author_pkg: &Pkg) -> Result<BTreeSet<SemVer>, PkgParseError>;
+
+    /// Load the `elm.json` config of an installed package version.
+    fn load_config(&self, pkg_version: &PkgVersion) -> Result<PackageConfig, PkgVersionError>;
+
+    /// List installed versions of all packages at once.
+    fn list_all_installed(&self) -> std::io::Result<Cache>;
+
+    /// Version of elm of the packages directory an installed package version is read from,
+    /// if several are merged, like with [`Directories`].
+    fn elm_version_of(&self, _pkg_version: &PkgVersion) -> Option<String> {
+        None
+    }
+}
+
+/// Installed packages read from the `ELM_HOME` directory.
+#[derive(Debug, Clone)]
+pub struct Directory {
+    elm_home: PathBuf,
+    elm_version: String,
+}
+
+impl Directory {
+    /// Read installed packages inside `elm_home`, for the given version of elm.
+    pub fn new<PB: Into<PathBuf>, S: ToString>(elm_home: PB, elm_version: S) -> Self {
+        Self {
+            elm_home: elm_home.into(),
+            elm_version: elm_version.to_string(),
+        }
+    }
+}
+
+impl InstalledPackages for Directory {
+    fn list_installed_versions(&self, author_pkg: &Pkg) -> Result<BTreeSet<SemVer>, PkgParseError> {
+        Cache::list_installed_versions(&self.elm_home, &self.elm_version, author_pkg)
+    }
+
+    fn load_config(&self, pkg_version: &PkgVersion) -> Result<PackageConfig, PkgVersionError> {
+        pkg_version.load_config(&self.elm_home, &self.elm_version)
+    }
+
+    fn list_all_installed(&self) -> std::io::Result<Cache> {
+        Cache::scan_all_installed(&self.elm_home, &self.elm_version)
+    }
+}
+
+/// Installed packages read from the packages directories of several versions of elm
+/// inside `ELM_HOME`, merged in order of preference.
+///
+/// A package version installed for several versions of elm is read from the first one.
+#[derive(Debug, Clone)]
+pub struct Directories {
+    elm_home: PathBuf,
+    elm_versions: Vec<String>,
+}
+
+impl Directories {
+    /// Read installed packages inside `elm_home`, for the given versions of elm,
+    /// such as `["0.19.1", "0.19.0"]`, in order of preference.
+    pub fn new<PB: Into<PathBuf>, S: ToString>(elm_home: PB, elm_versions: &[S]) -> Self {
+        Self {
+            elm_home: elm_home.into(),
+            elm_versions: elm_versions.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+}
+
+impl InstalledPackages for Directories {
+    fn list_installed_versions(&self, author_pkg: &Pkg) -> Result<BTreeSet<SemVer>, PkgParseError> {
+        let mut versions = BTreeSet::new();
+        for elm_version in &self.elm_versions {
+            versions.extend(Cache::list_installed_versions(
+                &self.elm_home,
+                elm_version,
+                author_pkg,
+            )?);
+        }
+        Ok(versions)
+    }
+
+    fn load_config(&self, pkg_version: &PkgVersion) -> Result<PackageConfig, PkgVersionError> {
+        let elm_version = (self.elm_version_of(pkg_version))
+            .or_else(|| self.elm_versions.first().cloned())
+            .unwrap_or_default();
+        pkg_version.load_config(&self.elm_home, &elm_version)
+    }
+
+    fn list_all_installed(&self) -> std::io::Result<Cache> {
+        let mut all_installed = Cache::new();
+        for elm_version in &self.elm_versions {
+            let installed = Cache::scan_all_installed(&self.elm_home, elm_version)?;
+            for (pkg, versions) in installed.cache {
+                all_installed.cache.entry(pkg).or_default().extend(versions);
+            }
+        }
+        Ok(all_installed)
+    }
+
+    fn elm_version_of(&self, pkg_version: &PkgVersion) -> Option<String> {
+        (self.elm_versions.iter())
+            .find(|elm_version| {
+                let config_path = pkg_version.config_path(&self.elm_home, elm_version);
+                config_path.is_file()
+            })
+            .cloned()
+    }
+}
+
+/// Installed packages read from an archive of `ELM_HOME`.
+///
+/// The archive is indexed once when opened, and only the `elm.json` files
+/// of installed packages are kept in memory.
+/// Paths inside the archive are expected to end with
+/// `elm_version/packages/author/package/version/elm.json`,
+/// whatever the prefix, so archives of `ELM_HOME` itself or of its parent both work.
+#[derive(Debug, Clone)]
+pub struct Archive {
+    configs: BTreeMap<Pkg, BTreeMap<SemVer, String>>,
+}
+
+impl Archive {
+    /// Open an archive of `ELM_HOME`, choosing the format from the file extension.
+    ///
+    /// Supported formats are `.tar` with the `tar` feature and `.zip` with the `zip` feature.
+    #[cfg_attr(not(any(feature = "tar", feature = "zip")), allow(unused_variables))]
+    pub fn open<P: AsRef<Path>>(path: P, elm_version: &str) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            #[cfg(feature = "tar")]
+            Some("tar") => Self::open_tar(path, elm_version),
+            #[cfg(feature = "zip")]
+            Some("zip") => Self::open_zip(path, elm_version),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("unsupported ELM_HOME archive format: {}", path.display()),
+            )),
+        }
+    }
+
+    /// Open a `.tar` archive of `ELM_HOME`.
+    #[cfg(feature = "tar")]
+    pub fn open_tar<P: AsRef<Path>>(path: P, elm_version: &str) -> std::io::Result<Self> {
+        use std::io::Read;
+        let mut archive = Self::empty();
+        let mut tar = tar::Archive::new(std::fs::File::open(path)?);
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            let key = match Self::config_key(&entry.path()?, elm_version) {
+                Some(key) => key,
+                None => continue,
+            };
+            let mut config_str = String::new();
+            entry.read_to_string(&mut config_str)?;
+            archive.insert(key, config_str);
+        }
+        Ok(archive)
+    }
+
+    /// Open a `.zip` archive of `ELM_HOME`.
+    #[cfg(feature = "zip")]
+    pub fn open_zip<P: AsRef<Path>>(path: P, elm_version: &str) -> std::io::Result<Self> {
+        use std::io::Read;
+        let to_io_err = |e| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+        let mut archive = Self::empty();
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(path)?).map_err(to_io_err)?;
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i).map_err(to_io_err)?;
+            let key = match Self::config_key(Path::new(file.name()), elm_version) {
+                Some(key) => key,
+                None => continue,
+            };
+            let mut config_str = String::new();
+            file.read_to_string(&mut config_str)?;
+            archive.insert(key, config_str);
+        }
+        Ok(archive)
+    }
+
+    /// Write the `elm.json` files of the archive into `elm_home`,
+    /// at the same place as installed packages.
+    /// Only the configs are written, not the source code of packages.
+    pub fn extract_configs<P: AsRef<Path>>(
+        &self,
+        elm_home: P,
+        elm_version: &str,
+    ) -> std::io::Result<()> {
+        for (pkg, versions) in &self.configs {
+            for (version, config_str) in versions {
+                let pkg_version = PkgVersion {
+                    author_pkg: pkg.clone(),
+                    version: *version,
+                };
+                let config_path = pkg_version.config_path(&elm_home, elm_version);
+                if let Some(dir) = config_path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::write(config_path, config_str)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(any(feature = "tar", feature = "zip"))]
+    fn empty() -> Self {
+        Self {
+            configs: BTreeMap::new(),
+        }
+    }
+
+    #[cfg(any(feature = "tar", feature = "zip"))]
+    fn insert(&mut self, (author_pkg, version): (Pkg, SemVer), config_str: String) {
+        let versions = self.configs.entry(author_pkg).or_default();
+        versions.insert(version, config_str);
+    }
+
+    /// Extract the package version of a path ending with
+    /// `elm_version/packages/author/package/version/elm.json`.
+    #[cfg(any(feature = "tar", feature = "zip"))]
+    fn config_key(path: &Path, elm_version: &str) -> Option<(Pkg, SemVer)> {
+        use std::path::Component;
+        use std::str::FromStr;
+        let components: Vec<&str> = path
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(s) => s.to_str(),
+                _ => None,
+            })
+            .collect();
+        match components.as_slice() {
+            [.., elm, "packages", author, pkg, version, "elm.json"] if *elm == elm_version => {
+                let version = SemVer::from_str(version).ok()?;
+                Some((Pkg::new(author, pkg), version))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl InstalledPackages for Archive {
+    fn list_installed_versions(&self, author_pkg: &Pkg) -> Result<BTreeSet<SemVer>, PkgParseError> {
+        Ok(self
+            .configs
+            .get(author_pkg)
+            .map(|versions| versions.keys().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn load_config(&self, pkg_version: &PkgVersion) -> Result<PackageConfig, PkgVersionError> {
+        let config_str = self
+            .configs
+            .get(&pkg_version.author_pkg)
+            .and_then(|versions| versions.get(&pkg_version.version))
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!(
+                        "{}@{} is not in the archive",
+                        pkg_version.author_pkg, pkg_version.version
+                    ),
+                )
+            })?;
+        Ok(serde_json::from_str(config_str)?)
+    }
+
+    fn list_all_installed(&self) -> std::io::Result<Cache> {
+        let cache = (self.configs.iter())
+            .map(|(pkg, versions)| (pkg.clone(), versions.keys().cloned().collect()))
+            .collect();
+        Ok(Cache { cache })
+    }
+}
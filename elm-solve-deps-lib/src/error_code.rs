@@ -0,0 +1,718 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module attaching stable codes to the errors of this crate.
+//!
+//! Error messages are meant for humans and may be reworded between versions.
+//! Scripts, editors, and issue reports should instead key off an [`ErrorCode`],
+//! which has a stable number and a stable kebab-case name, printed like `E-no-solution`.
+//!
+//! Every error type of this crate implements [`Coded`].
+//! Errors wrapping another error of this crate report the code of the wrapped one,
+//! which is more specific, such that a failed download reports `E-fetch-timeout`
+//! rather than a generic cache error.
+//! When only a `dyn Error` is at hand, for example behind a `Box<dyn Error>`,
+//! [`ErrorCode::of`] recognizes the errors of this crate along its chain of sources.
+
+use pubgrub::error::PubGrubError;
+use pubgrub::version::{SemanticVersion as SemVer, VersionParseError};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::budget::Interrupted;
+use crate::clock::SourceDateEpochError;
+#[cfg(feature = "fs")]
+use crate::compression::CompressionParseError;
+use crate::constraint::ConstraintParseError;
+#[cfg(feature = "fs")]
+use crate::corpus::CorpusError;
+#[cfg(feature = "fs")]
+use crate::daemon::DaemonError;
+use crate::ecosystem::EcosystemParseError;
+#[cfg(feature = "fs")]
+use crate::federation::RemoteParseError;
+use crate::fetch::FetchError;
+use crate::footprint::FootprintError;
+use crate::infer::InferPolicyParseError;
+use crate::interop::{CacheFormatParseError, InteropError};
+#[cfg(feature = "fs")]
+use crate::overrides::{OverrideError, OverrideParseError};
+use crate::phased::PhaseError;
+use crate::pkg_version::{CacheError, PkgVersionError, PkgVersionParseError, PrimeError};
+use crate::policy::HoldError;
+use crate::project_config::{
+    AsApplicationError, ElmJsonWriteError, FragmentParseError, Pkg, PkgParseError,
+};
+use crate::registry::RegistryError;
+use crate::review::ReviewError;
+#[cfg(feature = "schemars")]
+use crate::schema::UnknownSchemaError;
+use crate::scoring::ObjectiveParseError;
+use crate::solver::{
+    IndirectPinsParseError, RootPinningParseError, SolveError, TieBreakParseError,
+    VersionStrategyParseError,
+};
+#[cfg(feature = "fs")]
+use crate::solver_config::SolveWithConfigError;
+use crate::source_span::SourceLocation;
+
+/// Stable code of an error.
+///
+/// Numbers are grouped by kind of failure: 1xx for dependency resolution,
+/// 2xx for network requests, and 3xx for invalid or unreadable inputs.
+/// Codes are never renumbered nor reused, new ones only take unused numbers.
+///
+/// ```
+/// # use elm_solve_deps::error_code::ErrorCode;
+/// # use std::str::FromStr;
+/// assert_eq!(ErrorCode::NoSolution.number(), 100);
+/// assert_eq!(ErrorCode::NoSolution.to_string(), "E-no-solution");
+/// assert_eq!(ErrorCode::from_str("E-fetch-timeout"), Ok(ErrorCode::FetchTimeout));
+/// assert_eq!(ErrorCode::from_str("fetch-timeout"), Ok(ErrorCode::FetchTimeout));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorCode {
+    /// An error that does not come from this crate.
+    Other,
+    /// The dependencies of the project cannot be satisfied together.
+    NoSolution,
+    /// The resolution was interrupted, because it exceeded its limits or was cancelled.
+    Interrupted,
+    /// A package depends on itself, or on an empty set of versions.
+    InvalidDependencies,
+    /// An unrecoverable failure of the solver.
+    SolverFailure,
+    /// A failure of the functions providing package configs and versions to the solver.
+    ProviderError,
+    /// The test dependencies are incompatible with the normal ones.
+    TestIncompatible,
+    /// The elm-review configuration is incompatible with the reviewed project.
+    ReviewIncompatible,
+    /// The solver hints of the `elm.json` are invalid.
+    InvalidHints,
+    /// The solution has more packages, or longer dependency chains, than allowed.
+    FootprintExceeded,
+    /// The package server responded with a 404 status.
+    FetchNotFound,
+    /// The package server responded with an error status other than 404.
+    FetchStatus,
+    /// The package server did not respond in time.
+    FetchTimeout,
+    /// A request failed without a response.
+    FetchTransport,
+    /// A response body exceeds the maximum size.
+    FetchTooLarge,
+    /// A response body could not be decoded.
+    FetchDecoding,
+    /// A request was refused because network access is forbidden.
+    Frozen,
+    /// A file could not be read or written.
+    Io,
+    /// Some JSON could not be parsed or converted.
+    Json,
+    /// A response of the package server has an unexpected shape.
+    InvalidRegistry,
+    /// A package identifier is invalid.
+    InvalidPackage,
+    /// A version is invalid.
+    InvalidVersion,
+    /// A version constraint is invalid.
+    InvalidConstraint,
+    /// A dependencies fragment is invalid.
+    InvalidFragment,
+    /// An `elm.json` cannot be updated or generated.
+    InvalidElmJson,
+    /// An option has an unknown value.
+    InvalidOption,
+    /// A package config is missing from a recorded test case.
+    MissingConfig,
+}
+
+/// Error parsing an [`ErrorCode`] from its name, optionally prefixed by `E-`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown error code `{0}`")]
+pub struct ErrorCodeParseError(pub String);
+
+/// Errors with a stable [`ErrorCode`].
+pub trait Coded {
+    /// Code of the error.
+    fn code(&self) -> ErrorCode;
+}
+
+/// An error already formatted for users, keeping the code of the error it comes from.
+///
+/// Useful to turn a rich error, such as a pubgrub derivation tree, into a printable report
+/// without losing its code.
+///
+/// Serialized, it is the structured JSON of an error, for tools and editors.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+#[error("{message}")]
+pub struct CodedError {
+    /// Code of the original error.
+    pub code: ErrorCode,
+    /// The formatted message.
+    pub message: String,
+    /// Entries of the `elm.json` of the project the error is attributable to, if known.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locations: Vec<SourceLocation>,
+}
+
+const ALL: [ErrorCode; 27] = [
+    ErrorCode::Other,
+    ErrorCode::NoSolution,
+    ErrorCode::Interrupted,
+    ErrorCode::InvalidDependencies,
+    ErrorCode::SolverFailure,
+    ErrorCode::ProviderError,
+    ErrorCode::TestIncompatible,
+    ErrorCode::ReviewIncompatible,
+    ErrorCode::InvalidHints,
+    ErrorCode::FootprintExceeded,
+    ErrorCode::FetchNotFound,
+    ErrorCode::FetchStatus,
+    ErrorCode::FetchTimeout,
+    ErrorCode::FetchTransport,
+    ErrorCode::FetchTooLarge,
+    ErrorCode::FetchDecoding,
+    ErrorCode::Frozen,
+    ErrorCode::Io,
+    ErrorCode::Json,
+    ErrorCode::InvalidRegistry,
+    ErrorCode::InvalidPackage,
+    ErrorCode::InvalidVersion,
+    ErrorCode::InvalidConstraint,
+    ErrorCode::InvalidFragment,
+    ErrorCode::InvalidElmJson,
+    ErrorCode::InvalidOption,
+    ErrorCode::MissingConfig,
+];
+
+impl ErrorCode {
+    /// All error codes, by increasing number.
+    pub fn all() -> &'static [ErrorCode] {
+        &ALL
+    }
+
+    /// Stable number of the code.
+    pub fn number(self) -> u16 {
+        match self {
+            Self::Other => 0,
+            Self::NoSolution => 100,
+            Self::Interrupted => 101,
+            Self::InvalidDependencies => 102,
+            Self::SolverFailure => 103,
+            Self::ProviderError => 104,
+            Self::TestIncompatible => 105,
+            Self::ReviewIncompatible => 106,
+            Self::InvalidHints => 107,
+            Self::FootprintExceeded => 108,
+            Self::FetchNotFound => 200,
+            Self::FetchStatus => 201,
+            Self::FetchTimeout => 202,
+            Self::FetchTransport => 203,
+            Self::FetchTooLarge => 204,
+            Self::FetchDecoding => 205,
+            Self::Frozen => 206,
+            Self::Io => 300,
+            Self::Json => 301,
+            Self::InvalidRegistry => 302,
+            Self::InvalidPackage => 303,
+            Self::InvalidVersion => 304,
+            Self::InvalidConstraint => 305,
+            Self::InvalidFragment => 306,
+            Self::InvalidElmJson => 307,
+            Self::InvalidOption => 308,
+            Self::MissingConfig => 309,
+        }
+    }
+
+    /// Stable kebab-case name of the code.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Other => "other",
+            Self::NoSolution => "no-solution",
+            Self::Interrupted => "interrupted",
+            Self::InvalidDependencies => "invalid-dependencies",
+            Self::SolverFailure => "solver-failure",
+            Self::ProviderError => "provider-error",
+            Self::TestIncompatible => "test-incompatible",
+            Self::ReviewIncompatible => "review-incompatible",
+            Self::InvalidHints => "invalid-hints",
+            Self::FootprintExceeded => "footprint-exceeded",
+            Self::FetchNotFound => "fetch-not-found",
+            Self::FetchStatus => "fetch-status",
+            Self::FetchTimeout => "fetch-timeout",
+            Self::FetchTransport => "fetch-transport",
+            Self::FetchTooLarge => "fetch-too-large",
+            Self::FetchDecoding => "fetch-decoding",
+            Self::Frozen => "frozen",
+            Self::Io => "io",
+            Self::Json => "json",
+            Self::InvalidRegistry => "invalid-registry",
+            Self::InvalidPackage => "invalid-package",
+            Self::InvalidVersion => "invalid-version",
+            Self::InvalidConstraint => "invalid-constraint",
+            Self::InvalidFragment => "invalid-fragment",
+            Self::InvalidElmJson => "invalid-elm-json",
+            Self::InvalidOption => "invalid-option",
+            Self::MissingConfig => "missing-config",
+        }
+    }
+
+    /// Code of an error, found by walking its chain of sources
+    /// until reaching an error of this crate, or [`ErrorCode::Other`] if there is none.
+    ///
+    /// ```
+    /// # use elm_solve_deps::error_code::ErrorCode;
+    /// # use elm_solve_deps::fetch::FetchError;
+    /// # use elm_solve_deps::pkg_version::CacheError;
+    /// let err = CacheError::FetchError {
+    ///     url: "https://package.elm-lang.org/all-packages".to_string(),
+    ///     source: FetchError::Timeout,
+    /// };
+    /// let boxed: Box<dyn std::error::Error> = Box::new(err);
+    /// assert_eq!(ErrorCode::of(boxed.as_ref()), ErrorCode::FetchTimeout);
+    /// ```
+    pub fn of(err: &(dyn Error + 'static)) -> ErrorCode {
+        let mut current = Some(err);
+        while let Some(err) = current {
+            if let Some(code) = code_of_one(err) {
+                return code;
+            }
+            current = err.source();
+        }
+        ErrorCode::Other
+    }
+}
+
+/// Code of an error if it is one of this crate, without looking at its sources.
+fn code_of_one(err: &(dyn Error + 'static)) -> Option<ErrorCode> {
+    let recognizers: &[fn(&(dyn Error + 'static)) -> Option<ErrorCode>] = &[
+        code_as::<CodedError>,
+        code_as::<PubGrubError<Pkg, SemVer>>,
+        code_as::<Interrupted>,
+        code_as::<SourceDateEpochError>,
+        code_as::<ConstraintParseError>,
+        #[cfg(feature = "fs")]
+        code_as::<CorpusError>,
+        #[cfg(feature = "fs")]
+        code_as::<SolveError<CorpusError>>,
+        #[cfg(feature = "fs")]
+        code_as::<DaemonError>,
+        code_as::<FetchError>,
+        code_as::<FootprintError>,
+        code_as::<EcosystemParseError>,
+        code_as::<InferPolicyParseError>,
+        code_as::<CacheFormatParseError>,
+        #[cfg(feature = "fs")]
+        code_as::<CompressionParseError>,
+        code_as::<InteropError>,
+        code_as::<CacheError>,
+        code_as::<PkgVersionError>,
+        code_as::<PkgVersionParseError>,
+        code_as::<PrimeError>,
+        code_as::<HoldError>,
+        code_as::<PkgParseError>,
+        code_as::<FragmentParseError>,
+        code_as::<ElmJsonWriteError>,
+        code_as::<AsApplicationError>,
+        code_as::<RegistryError>,
+        code_as::<RootPinningParseError>,
+        code_as::<ObjectiveParseError>,
+        code_as::<TieBreakParseError>,
+        code_as::<IndirectPinsParseError>,
+        code_as::<VersionStrategyParseError>,
+        #[cfg(feature = "fs")]
+        code_as::<SolveWithConfigError>,
+        #[cfg(feature = "fs")]
+        code_as::<RemoteParseError>,
+        #[cfg(feature = "fs")]
+        code_as::<OverrideError>,
+        #[cfg(feature = "fs")]
+        code_as::<OverrideParseError>,
+        code_as::<ErrorCodeParseError>,
+        #[cfg(feature = "schemars")]
+        code_as::<UnknownSchemaError>,
+    ];
+    if let Some(code) = recognizers.iter().find_map(|code_as| code_as(err)) {
+        Some(code)
+    } else if err.is::<VersionParseError>() {
+        Some(ErrorCode::InvalidVersion)
+    } else if err.is::<std::io::Error>() {
+        Some(ErrorCode::Io)
+    } else if err.is::<serde_json::Error>() {
+        Some(ErrorCode::Json)
+    } else {
+        None
+    }
+}
+
+/// Code of an error if it has the type `T`.
+fn code_as<T: Coded + Error + 'static>(err: &(dyn Error + 'static)) -> Option<ErrorCode> {
+    err.downcast_ref::<T>().map(Coded::code)
+}
+
+/// Code of an error wrapped by an error of this crate,
+/// or `fallback` if the wrapped error does not come from this crate.
+fn code_or(err: &(dyn Error + 'static), fallback: ErrorCode) -> ErrorCode {
+    match ErrorCode::of(err) {
+        ErrorCode::Other => fallback,
+        code => code,
+    }
+}
+
+impl FromStr for ErrorCode {
+    type Err = ErrorCodeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.strip_prefix("E-").unwrap_or(s);
+        (ALL.iter().copied())
+            .find(|code| code.name() == name)
+            .ok_or_else(|| ErrorCodeParseError(s.to_string()))
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "E-{}", self.name())
+    }
+}
+
+impl Coded for CodedError {
+    fn code(&self) -> ErrorCode {
+        self.code
+    }
+}
+
+impl Coded for PubGrubError<Pkg, SemVer> {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::NoSolution(_) => ErrorCode::NoSolution,
+            Self::ErrorRetrievingDependencies { source, .. } => {
+                code_or(source.as_ref(), ErrorCode::ProviderError)
+            }
+            Self::ErrorChoosingPackageVersion(source) => {
+                code_or(source.as_ref(), ErrorCode::ProviderError)
+            }
+            Self::ErrorInShouldCancel(source) => code_or(source.as_ref(), ErrorCode::Interrupted),
+            Self::DependencyOnTheEmptySet { .. } | Self::SelfDependency { .. } => {
+                ErrorCode::InvalidDependencies
+            }
+            Self::Failure(_) => ErrorCode::SolverFailure,
+        }
+    }
+}
+
+impl<E: Error + 'static> Coded for SolveError<E> {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::ListVersions(source) => code_or(source, ErrorCode::ProviderError),
+            Self::FetchElmJson { source, .. } => code_or(source, ErrorCode::ProviderError),
+            Self::Interrupted(_) => ErrorCode::Interrupted,
+            Self::Hints(_) => ErrorCode::InvalidHints,
+            Self::PubGrub(err) => err.code(),
+        }
+    }
+}
+
+impl<E> PhaseError<E> {
+    /// Code of the error, given how to get the code of the errors of the solving function.
+    /// Useful when `E` does not implement [`Error`], such as `anyhow::Error`.
+    pub fn code_with<F: Fn(&E) -> ErrorCode>(&self, code_of: F) -> ErrorCode {
+        match self {
+            Self::Normal(err) => code_of(err),
+            Self::Test(err) => match code_of(err) {
+                ErrorCode::NoSolution => ErrorCode::TestIncompatible,
+                code => code,
+            },
+        }
+    }
+}
+
+impl<E: Error + 'static> Coded for PhaseError<E> {
+    fn code(&self) -> ErrorCode {
+        self.code_with(|err| ErrorCode::of(err))
+    }
+}
+
+impl<E> ReviewError<E> {
+    /// Code of the error, given how to get the code of the errors of the solving function.
+    /// Useful when `E` does not implement [`Error`], such as `anyhow::Error`.
+    pub fn code_with<F: Fn(&E) -> ErrorCode>(&self, code_of: F) -> ErrorCode {
+        match self {
+            Self::FileIoError(..) => ErrorCode::Io,
+            Self::JsonError(..) => ErrorCode::Json,
+            Self::ProjectSolve(err) | Self::ReviewSolve(err) => code_of(err),
+            Self::Incompatible { .. } => ErrorCode::ReviewIncompatible,
+        }
+    }
+}
+
+impl<E: Error + 'static> Coded for ReviewError<E> {
+    fn code(&self) -> ErrorCode {
+        self.code_with(|err| ErrorCode::of(err))
+    }
+}
+
+impl Coded for Interrupted {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::Interrupted
+    }
+}
+
+impl Coded for FetchError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::NotFound => ErrorCode::FetchNotFound,
+            Self::Status { .. } => ErrorCode::FetchStatus,
+            Self::Timeout => ErrorCode::FetchTimeout,
+            Self::Transport(_) => ErrorCode::FetchTransport,
+            Self::TooLarge { .. } => ErrorCode::FetchTooLarge,
+            Self::Decoding { .. } => ErrorCode::FetchDecoding,
+            Self::Frozen { .. } => ErrorCode::Frozen,
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Coded for DaemonError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::OfflineStrategy | Self::OfflineOnly => ErrorCode::InvalidOption,
+            Self::UnknownPackage(_) => ErrorCode::InvalidPackage,
+            Self::OnlineSolver(err) => err.code(),
+            Self::Solve(err) => err.code(),
+        }
+    }
+}
+
+impl Coded for EcosystemParseError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidOption
+    }
+}
+
+impl Coded for FootprintError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::FootprintExceeded
+    }
+}
+
+impl Coded for CacheError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::FileIoError(_) => ErrorCode::Io,
+            Self::JsonError(_) => ErrorCode::Json,
+            Self::FetchError { source, .. } => source.code(),
+            Self::InvalidResponse { source, .. } => source.code(),
+            Self::PkgVersionFromStrError(err) => err.code(),
+        }
+    }
+}
+
+impl Coded for PrimeError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::FileIoError { .. } => ErrorCode::Io,
+            Self::JsonError { .. } => ErrorCode::Json,
+            Self::Mismatch { .. } => ErrorCode::InvalidPackage,
+        }
+    }
+}
+
+impl Coded for PkgVersionError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::FileIoError(_) => ErrorCode::Io,
+            Self::JsonError(_) => ErrorCode::Json,
+            Self::FetchError { source, .. } => source.code(),
+            Self::ParseError(err) => err.code(),
+        }
+    }
+}
+
+impl Coded for PkgVersionParseError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::NoVersionSeparator(_) | Self::PkgParseError(_) => ErrorCode::InvalidPackage,
+            Self::VersionParseError(_) => ErrorCode::InvalidVersion,
+        }
+    }
+}
+
+impl Coded for PkgParseError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidPackage
+    }
+}
+
+impl Coded for RegistryError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidRegistry
+    }
+}
+
+impl Coded for ConstraintParseError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidConstraint
+    }
+}
+
+impl Coded for FragmentParseError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidFragment
+    }
+}
+
+impl Coded for ElmJsonWriteError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::FileIoError(_) => ErrorCode::Io,
+            Self::JsonError(_) | Self::MissingField(_) => ErrorCode::InvalidElmJson,
+        }
+    }
+}
+
+impl Coded for AsApplicationError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidElmJson
+    }
+}
+
+impl Coded for HoldError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidHints
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Coded for CorpusError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::FileIoError(_) => ErrorCode::Io,
+            Self::JsonError(_) => ErrorCode::Json,
+            Self::MissingConfig(..) => ErrorCode::MissingConfig,
+        }
+    }
+}
+
+impl Coded for InteropError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::FileIoError(_) => ErrorCode::Io,
+            Self::JsonError(_) => ErrorCode::Json,
+            Self::Invalid(err) => err.code(),
+            Self::InvalidRegistryDat(_) => ErrorCode::InvalidRegistry,
+        }
+    }
+}
+
+impl Coded for SourceDateEpochError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidOption
+    }
+}
+
+impl Coded for InferPolicyParseError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidOption
+    }
+}
+
+impl Coded for CacheFormatParseError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidOption
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Coded for CompressionParseError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidOption
+    }
+}
+
+impl Coded for RootPinningParseError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidOption
+    }
+}
+
+impl Coded for ObjectiveParseError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidOption
+    }
+}
+
+impl Coded for TieBreakParseError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidOption
+    }
+}
+
+impl Coded for IndirectPinsParseError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidOption
+    }
+}
+
+impl Coded for VersionStrategyParseError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidOption
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Coded for SolveWithConfigError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::Online(err) => err.code(),
+            Self::Solve(err) => err.code(),
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Coded for RemoteParseError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidOption
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Coded for OverrideError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::FileIoError(_) => ErrorCode::Io,
+            Self::JsonError(_) => ErrorCode::Json,
+            Self::GitError { .. } => ErrorCode::FetchTransport,
+            Self::NameMismatch { .. } => ErrorCode::InvalidElmJson,
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Coded for OverrideParseError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidOption
+    }
+}
+
+impl Coded for ErrorCodeParseError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidOption
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl Coded for UnknownSchemaError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidOption
+    }
+}
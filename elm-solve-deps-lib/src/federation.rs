@@ -0,0 +1,208 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module federating several package servers into a single registry.
+//!
+//! Companies publishing private packages on their own package server still depend on
+//! the packages of the official one.
+//! A [`Federation`] declares additional package servers, each with a name,
+//! whose versions are unioned with the ones of the main package server of an
+//! [`Online`](crate::solver::Online) solver, see
+//! [`Online::with_federation`](crate::solver::Online::with_federation).
+//!
+//! When several package servers publish the same package version,
+//! the main one is preferred, then the additional ones in the order they were declared,
+//! unless another precedence is configured for that package.
+//! The versions of each additional package server are cached separately,
+//! in [`Cache::remote_dir`], and the solution provenance records
+//! the package server each selected version comes from.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::clock::Clock;
+use crate::fetch::HttpFetch;
+use crate::pkg_version::{Cache, CacheError, CacheUpdate};
+use crate::project_config::Pkg;
+
+/// Name of the main package server of an online solver, in a [`Federation`].
+pub const MAIN_REMOTE: &str = "main";
+
+/// An additional package server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Remote {
+    /// Name of the package server, made of letters, digits, `-` and `_`.
+    pub name: String,
+    /// Url of the package server.
+    pub url: String,
+}
+
+/// Error parsing a [`Remote`], which must be written `name=url`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid remote `{0}`, expected NAME=URL with a name made of letters, digits, - and _")]
+pub struct RemoteParseError(pub String);
+
+/// Package servers whose versions are unioned,
+/// with the package server preferred for some packages.
+///
+/// ```
+/// # use elm_solve_deps::federation::{Federation, Remote, MAIN_REMOTE};
+/// # use elm_solve_deps::project_config::Pkg;
+/// # use std::str::FromStr;
+/// let corporate = Remote::from_str("corp=https://elm.corp.example.com").unwrap();
+/// let federation = Federation::new()
+///     .with_remote(corporate)
+///     .with_precedence(Pkg::new("corp", "design-system"), "corp");
+/// let design_system = Pkg::new("corp", "design-system");
+/// assert_eq!(federation.order(&design_system), vec!["corp", MAIN_REMOTE]);
+/// assert_eq!(federation.order(&Pkg::new("elm", "core")), vec![MAIN_REMOTE, "corp"]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Federation {
+    /// The additional package servers, in order of precedence.
+    pub remotes: Vec<Remote>,
+    /// The package server preferred for some packages, by name.
+    pub precedence: BTreeMap<Pkg, String>,
+}
+
+/// Versions caches of the additional package servers of a [`Federation`], by name.
+#[derive(Debug, Clone, Default)]
+pub struct FederatedCache {
+    /// The versions cache of each additional package server.
+    pub caches: BTreeMap<String, Cache>,
+}
+
+impl FromStr for Remote {
+    type Err = RemoteParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || RemoteParseError(s.to_string());
+        let (name, url) = s.split_once('=').ok_or_else(err)?;
+        let valid_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        let valid_name = !name.is_empty() && name.chars().all(valid_char) && name != MAIN_REMOTE;
+        if !valid_name || url.is_empty() {
+            return Err(err());
+        }
+        Ok(Self {
+            name: name.to_string(),
+            url: url.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+impl Federation {
+    /// Initialize without any additional package server.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a package server, after the ones already added,
+    /// or replace the one with the same name.
+    pub fn with_remote(mut self, remote: Remote) -> Self {
+        match self.remotes.iter_mut().find(|r| r.name == remote.name) {
+            Some(existing) => *existing = remote,
+            None => self.remotes.push(remote),
+        }
+        self
+    }
+
+    /// Prefer the versions of a package published on the package server named `remote_name`,
+    /// which may be [`MAIN_REMOTE`].
+    pub fn with_precedence<S: ToString>(mut self, pkg: Pkg, remote_name: S) -> Self {
+        self.precedence.insert(pkg, remote_name.to_string());
+        self
+    }
+
+    /// Url of an additional package server.
+    pub fn url(&self, remote_name: &str) -> Option<&str> {
+        (self.remotes.iter())
+            .find(|r| r.name == remote_name)
+            .map(|r| r.url.as_str())
+    }
+
+    /// Names of the package servers, in the order they are tried for a package.
+    /// A precedence naming an unknown package server is ignored.
+    pub fn order(&self, pkg: &Pkg) -> Vec<&str> {
+        let names =
+            std::iter::once(MAIN_REMOTE).chain(self.remotes.iter().map(|r| r.name.as_str()));
+        let mut order: Vec<&str> = names.collect();
+        if let Some(preferred) = self.precedence.get(pkg) {
+            if let Some(index) = order.iter().position(|name| name == preferred) {
+                let name = order.remove(index);
+                order.insert(0, name);
+            }
+        }
+        order
+    }
+}
+
+impl FederatedCache {
+    /// Load the versions caches of the package servers of a federation.
+    /// Missing or unreadable caches start empty.
+    pub fn load<P: AsRef<Path>>(elm_home: P, federation: &Federation) -> Self {
+        let caches = (federation.remotes.iter())
+            .map(|r| {
+                let cache = Cache::load_remote(&elm_home, &r.name).unwrap_or_default();
+                (r.name.clone(), cache)
+            })
+            .collect();
+        Self { caches }
+    }
+
+    /// Update the versions cache of every package server of a federation,
+    /// see [`Cache::update_remote_with_resync`].
+    pub fn update<P, H, C>(
+        &mut self,
+        elm_home: P,
+        federation: &Federation,
+        http_fetch: &H,
+        clock: &C,
+    ) -> Result<BTreeMap<String, CacheUpdate>, CacheError>
+    where
+        P: AsRef<Path>,
+        H: HttpFetch + ?Sized,
+        C: Clock + ?Sized,
+    {
+        let mut updates = BTreeMap::new();
+        for remote in &federation.remotes {
+            let cache = self.caches.entry(remote.name.clone()).or_default();
+            let update = cache.update_remote_with_resync(
+                &elm_home,
+                &remote.name,
+                &remote.url,
+                http_fetch,
+                clock,
+            )?;
+            updates.insert(remote.name.clone(), update);
+        }
+        Ok(updates)
+    }
+
+    /// Save the versions cache of every package server.
+    pub fn save<P: AsRef<Path>>(&self, elm_home: P) -> Result<(), CacheError> {
+        for (name, cache) in &self.caches {
+            cache.save_remote(&elm_home, name)?;
+        }
+        Ok(())
+    }
+
+    /// Union of the versions of a package on all package servers.
+    pub fn versions(&self, pkg: &Pkg) -> BTreeSet<SemVer> {
+        (self.caches.values())
+            .filter_map(|cache| cache.cache.get(pkg))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    /// Whether a package server publishes a package version.
+    pub fn contains(&self, remote_name: &str, pkg: &Pkg, version: SemVer) -> bool {
+        (self.caches.get(remote_name))
+            .and_then(|cache| cache.cache.get(pkg))
+            .is_some_and(|versions| versions.contains(&version))
+    }
+}
@@ -0,0 +1,340 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module defining how HTTP requests to the package server are made.
+//!
+//! Functions requiring network access take an [`HttpFetch`] argument.
+//! It is implemented for any function or closure with the signature
+//! `Fn(&str) -> Result<String, FetchError>`,
+//! but also for stateful clients, such as the [`UreqFetcher`] provided with the `ureq` feature,
+//! which keeps connections alive between requests instead of paying a TLS handshake
+//! for each of the hundreds of `elm.json` files fetched during a resolution.
+//!
+//! Errors tell apart resources missing on the server ([`FetchError::NotFound`]),
+//! other error statuses, and requests that failed without a response, such as timeouts.
+//! This matters for retry and fallback logic.
+//!
+//! For hermetic builds, the [`Frozen`] client refuses every request,
+//! guaranteeing that nothing is ever fetched, even by the online solver.
+//!
+//! Responses of the package server compress extremely well.
+//! With the `flate2` feature, clients can announce [`ACCEPT_ENCODING`]
+//! and decode gzip or deflate bodies with [`Response::from_reader`],
+//! which also protects against malicious mirrors by limiting the size of bodies.
+
+use std::error::Error;
+use std::io::Read;
+use thiserror::Error;
+
+/// Default maximum size of response bodies, of 64 MiB once decoded.
+pub const DEFAULT_MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Value of the `Accept-Encoding` header of requests,
+/// listing the encodings supported by [`Response::from_reader`].
+#[cfg(feature = "flate2")]
+pub const ACCEPT_ENCODING: &str = "gzip, deflate";
+
+/// Value of the `Accept-Encoding` header of requests,
+/// listing the encodings supported by [`Response::from_reader`].
+#[cfg(not(feature = "flate2"))]
+pub const ACCEPT_ENCODING: &str = "identity";
+
+/// Response to a GET request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// HTTP status code of the response.
+    pub status: u16,
+    /// Body of the response.
+    pub body: String,
+}
+
+/// Error type of HTTP requests.
+#[derive(Error, Debug)]
+pub enum FetchError {
+    /// The server responded with a 404 status,
+    /// for example because the requested package version does not exist.
+    #[error("not found")]
+    NotFound,
+
+    /// The server responded with an error status other than 404.
+    #[error("unexpected status {status}")]
+    Status {
+        /// HTTP status code of the response.
+        status: u16,
+        /// Body of the response.
+        body: String,
+    },
+
+    /// The server did not respond in time.
+    #[error("the request timed out")]
+    Timeout,
+
+    /// The request failed without a response, for example because the connection failed.
+    #[error(transparent)]
+    Transport(Box<dyn Error + Send + Sync>),
+
+    /// The body of the response exceeds the maximum size, once decoded.
+    #[error("response body larger than {max_size} bytes")]
+    TooLarge {
+        /// The maximum size of bodies, in bytes.
+        max_size: u64,
+    },
+
+    /// The body of the response could not be decoded,
+    /// because its encoding is not supported, or it is corrupted.
+    #[error("failed to decode the {encoding} response body")]
+    Decoding {
+        /// The `Content-Encoding` of the response.
+        encoding: String,
+        /// The decoding error.
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The request was refused because network access is forbidden, see [`Frozen`].
+    #[error("network access is forbidden, refused to fetch {url}")]
+    Frozen {
+        /// The url of the refused request.
+        url: String,
+    },
+}
+
+/// An HTTP client able to make GET requests.
+pub trait HttpFetch {
+    /// Make a GET request at the given url, and return the response whatever its status.
+    fn get(&self, url: &str) -> Result<Response, FetchError>;
+
+    /// Fetch the body of the response to a GET request at the given url.
+    ///
+    /// Error statuses are converted into [`FetchError::NotFound`] or [`FetchError::Status`].
+    fn fetch(&self, url: &str) -> Result<String, FetchError> {
+        self.get(url)?.into_body()
+    }
+
+    /// Fetch the raw body of the response to a GET request at the given url,
+    /// such as the bytes of an archive.
+    ///
+    /// The default implementation only supports UTF-8 bodies,
+    /// clients able to download binary files should override it.
+    fn fetch_bytes(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        self.fetch(url).map(String::into_bytes)
+    }
+}
+
+impl<F: Fn(&str) -> Result<String, FetchError>> HttpFetch for F {
+    /// Functions only return the body of successful responses, with a 200 status.
+    fn get(&self, url: &str) -> Result<Response, FetchError> {
+        let body = self(url)?;
+        Ok(Response { status: 200, body })
+    }
+
+    fn fetch(&self, url: &str) -> Result<String, FetchError> {
+        self(url)
+    }
+}
+
+impl Response {
+    /// Read a response body, decoding it according to its `Content-Encoding`,
+    /// and failing if it is larger than `max_size` bytes once decoded.
+    ///
+    /// The `gzip` and `deflate` encodings are supported with the `flate2` feature.
+    /// HTTP clients implementing [`HttpFetch`] can use it to decode their responses.
+    ///
+    /// ```
+    /// # use elm_solve_deps::fetch::{FetchError, Response};
+    /// let response = Response::from_reader(200, None, "{}".as_bytes(), 1024).unwrap();
+    /// assert_eq!(response.body, "{}");
+    /// let too_large = Response::from_reader(200, Some("identity"), "{}".as_bytes(), 1);
+    /// assert!(matches!(too_large, Err(FetchError::TooLarge { max_size: 1 })));
+    /// ```
+    pub fn from_reader<R: Read>(
+        status: u16,
+        content_encoding: Option<&str>,
+        body: R,
+        max_size: u64,
+    ) -> Result<Self, FetchError> {
+        let bytes = read_body(content_encoding, body, max_size)?;
+        let body = String::from_utf8(bytes).map_err(|e| FetchError::Decoding {
+            encoding: content_encoding.unwrap_or("identity").trim().to_lowercase(),
+            source: std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+        })?;
+        Ok(Self { status, body })
+    }
+
+    /// Whether the status of the response is a success, in the 2xx range.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Body of the response if successful, or the corresponding error otherwise.
+    pub fn into_body(self) -> Result<String, FetchError> {
+        match self.status {
+            _ if self.is_success() => Ok(self.body),
+            404 => Err(FetchError::NotFound),
+            status => Err(FetchError::Status {
+                status,
+                body: self.body,
+            }),
+        }
+    }
+}
+
+/// Read a response body, decoding it according to its `Content-Encoding`,
+/// and failing if it is larger than `max_size` bytes once decoded.
+fn read_body<R: Read>(
+    content_encoding: Option<&str>,
+    body: R,
+    max_size: u64,
+) -> Result<Vec<u8>, FetchError> {
+    let encoding = content_encoding.unwrap_or("identity").trim().to_lowercase();
+    let decoding_error = |source| FetchError::Decoding {
+        encoding: encoding.clone(),
+        source,
+    };
+    let decoder: Box<dyn Read + '_> = match encoding.as_str() {
+        "identity" | "" => Box::new(body),
+        #[cfg(feature = "flate2")]
+        "gzip" | "x-gzip" => Box::new(flate2::read::GzDecoder::new(body)),
+        #[cfg(feature = "flate2")]
+        "deflate" => Box::new(flate2::read::ZlibDecoder::new(body)),
+        _ => {
+            let unsupported = std::io::ErrorKind::Unsupported.into();
+            return Err(decoding_error(unsupported));
+        }
+    };
+    // Read one more byte than allowed to detect bodies that are too large.
+    let mut bytes = Vec::new();
+    (decoder.take(max_size.saturating_add(1)))
+        .read_to_end(&mut bytes)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::TimedOut => FetchError::Timeout,
+            _ => decoding_error(e),
+        })?;
+    if bytes.len() as u64 > max_size {
+        return Err(FetchError::TooLarge { max_size });
+    }
+    Ok(bytes)
+}
+
+impl FetchError {
+    /// HTTP status code of the response, if the server responded.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::NotFound => Some(404),
+            Self::Status { status, .. } => Some(*status),
+            Self::TooLarge { .. } | Self::Decoding { .. } => None,
+            Self::Timeout | Self::Transport(_) | Self::Frozen { .. } => None,
+        }
+    }
+}
+
+/// HTTP client refusing every request with [`FetchError::Frozen`].
+///
+/// Using it with the [`Online`](crate::solver::Online) solver guarantees that no request is made,
+/// neither to update the list of existing packages, nor to download missing `elm.json` files.
+/// Such attempts fail right away, reporting the offending url,
+/// instead of silently falling back to the network.
+///
+/// ```
+/// # use elm_solve_deps::fetch::{FetchError, Frozen, HttpFetch};
+/// let url = "https://package.elm-lang.org/all-packages";
+/// assert!(matches!(Frozen.fetch(url), Err(FetchError::Frozen { url: u }) if u == url));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Frozen;
+
+impl HttpFetch for Frozen {
+    fn get(&self, url: &str) -> Result<Response, FetchError> {
+        Err(FetchError::Frozen {
+            url: url.to_string(),
+        })
+    }
+}
+
+/// Built-in HTTP client based on a [`ureq::Agent`],
+/// reusing connections to the package server between requests.
+#[cfg(feature = "ureq")]
+#[derive(Debug, Clone)]
+pub struct UreqFetcher {
+    agent: ureq::Agent,
+    timeout_connect: u64,
+    max_size: u64,
+}
+
+#[cfg(feature = "ureq")]
+impl UreqFetcher {
+    /// Create a new client, with a connection timeout of 10s,
+    /// and a maximum body size of [`DEFAULT_MAX_SIZE`].
+    pub fn new() -> Self {
+        Self {
+            agent: ureq::Agent::new(),
+            timeout_connect: 10_000,
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+
+    /// Change the connection timeout, in milliseconds.
+    pub fn with_timeout_connect(mut self, timeout_connect: u64) -> Self {
+        self.timeout_connect = timeout_connect;
+        self
+    }
+
+    /// Change the maximum size of response bodies once decoded, in bytes.
+    /// Larger responses fail with [`FetchError::TooLarge`].
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+}
+
+#[cfg(feature = "ureq")]
+impl Default for UreqFetcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "ureq")]
+impl HttpFetch for UreqFetcher {
+    fn get(&self, url: &str) -> Result<Response, FetchError> {
+        let response = self.call(url)?;
+        let status = response.status();
+        let encoding = response.header("Content-Encoding").map(|e| e.to_string());
+        let body = response.into_reader();
+        Response::from_reader(status, encoding.as_deref(), body, self.max_size)
+    }
+
+    fn fetch_bytes(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        let response = self.call(url)?;
+        match response.status() {
+            200..=299 => {}
+            404 => return Err(FetchError::NotFound),
+            status => {
+                let body = response.into_string().unwrap_or_default();
+                return Err(FetchError::Status { status, body });
+            }
+        }
+        let encoding = response.header("Content-Encoding").map(|e| e.to_string());
+        read_body(encoding.as_deref(), response.into_reader(), self.max_size)
+    }
+}
+
+#[cfg(feature = "ureq")]
+impl UreqFetcher {
+    /// Make a GET request, failing only if there is no response.
+    fn call(&self, url: &str) -> Result<ureq::Response, FetchError> {
+        let response = self
+            .agent
+            .get(url)
+            .set("Accept-Encoding", ACCEPT_ENCODING)
+            .timeout_connect(self.timeout_connect)
+            .call();
+        match response.synthetic_error() {
+            Some(ureq::Error::Io(err)) if err.kind() == std::io::ErrorKind::TimedOut => {
+                return Err(FetchError::Timeout);
+            }
+            Some(err) => return Err(FetchError::Transport(err.to_string().into())),
+            None => {}
+        }
+        Ok(response)
+    }
+}
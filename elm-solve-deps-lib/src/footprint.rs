@@ -0,0 +1,199 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module bounding the footprint of solutions, in number of packages and dependency depth.
+//!
+//! Some teams keep their dependencies in check with budgets,
+//! such as "no more than 40 transitive packages".
+//! The [`Footprint`] of a solution is measured on its [`DependencyGraph`],
+//! and then checked against [`FootprintLimits`].
+//! When a limit is exceeded, the [`FootprintError`] names what to trim first:
+//! the direct dependencies bringing the most packages,
+//! or the longest chain of dependencies.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use thiserror::Error;
+
+use crate::graph::{DependencyGraph, DependencyPath};
+use crate::project_config::Pkg;
+
+/// Number of heaviest direct dependencies named by [`FootprintError::TooManyPackages`].
+pub const REPORTED_HEAVIEST: usize = 3;
+
+/// Size of the dependencies of a project.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Footprint {
+    /// Number of packages the project depends on, directly or indirectly.
+    pub packages: usize,
+    /// Longest chain of dependencies, starting with a direct dependency.
+    pub deepest: DependencyPath,
+    /// Direct dependencies with the number of packages they bring, themselves included,
+    /// the heaviest first.
+    pub heaviest: Vec<(Pkg, usize)>,
+}
+
+/// Maximum footprint allowed for solutions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FootprintLimits {
+    /// Maximum number of packages, direct and indirect.
+    pub max_packages: Option<usize>,
+    /// Maximum length of dependency chains, direct dependencies being at depth 1.
+    pub max_depth: Option<usize>,
+}
+
+/// Footprint exceeding its limits.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FootprintError {
+    /// The solution has too many packages.
+    #[error(
+        "the solution has {count} packages, more than the maximum of {max}, \
+        the heaviest direct dependencies being {}",
+        display_heaviest(.heaviest)
+    )]
+    TooManyPackages {
+        /// Number of packages of the solution.
+        count: usize,
+        /// Maximum number of packages.
+        max: usize,
+        /// Heaviest direct dependencies, with the number of packages they bring.
+        heaviest: Vec<(Pkg, usize)>,
+    },
+    /// The solution has a chain of dependencies too long.
+    #[error("the solution has a dependency chain of depth {depth}, more than the maximum of {max}: {chain}")]
+    TooDeep {
+        /// Depth of the chain.
+        depth: usize,
+        /// Maximum depth.
+        max: usize,
+        /// Longest chain of dependencies.
+        chain: DependencyPath,
+    },
+}
+
+fn display_heaviest(heaviest: &[(Pkg, usize)]) -> String {
+    let heaviest: Vec<String> = (heaviest.iter())
+        .map(|(pkg, count)| format!("{} ({} packages)", pkg, count))
+        .collect();
+    heaviest.join(", ")
+}
+
+impl Footprint {
+    /// Measure the dependencies of a package of a graph, typically the root of a solution.
+    ///
+    /// ```
+    /// # use elm_solve_deps::footprint::Footprint;
+    /// # use elm_solve_deps::graph::DependencyGraph;
+    /// # use elm_solve_deps::project_config::Pkg;
+    /// let (app, core, json, http) = (
+    ///     Pkg::new("author", "app"),
+    ///     Pkg::new("elm", "core"),
+    ///     Pkg::new("elm", "json"),
+    ///     Pkg::new("elm", "http"),
+    /// );
+    /// let any = || "1.0.0 <= v < 2.0.0".parse().unwrap();
+    /// let mut graph = DependencyGraph::new();
+    /// graph.add(app.clone(), None, vec![(core.clone(), any()), (http.clone(), any())]);
+    /// graph.add(http.clone(), None, vec![(core.clone(), any()), (json.clone(), any())]);
+    /// graph.add(json.clone(), None, vec![(core.clone(), any())]);
+    /// let footprint = Footprint::measure(&graph, &app);
+    /// assert_eq!(footprint.packages, 3);
+    /// assert_eq!(footprint.depth(), 3);
+    /// assert_eq!(footprint.deepest.to_string(), "elm/http -> elm/json -> elm/core");
+    /// assert_eq!(footprint.heaviest, vec![(http, 3), (core, 1)]);
+    /// ```
+    pub fn measure(graph: &DependencyGraph, root: &Pkg) -> Self {
+        let mut heaviest: Vec<(Pkg, usize)> = (graph.dependencies_of(root))
+            .map(|(dep, _)| (dep.clone(), reachable(graph, dep).len()))
+            .collect();
+        heaviest.sort_by(|(pkg_a, a), (pkg_b, b)| b.cmp(a).then_with(|| pkg_a.cmp(pkg_b)));
+        let mut longest = BTreeMap::new();
+        let mut in_progress = BTreeSet::new();
+        longest_chain(graph, root, &mut longest, &mut in_progress);
+        let mut steps = Vec::new();
+        let mut current = longest.get(root).and_then(|(_, next)| next.clone());
+        while let Some(pkg) = current {
+            current = longest.get(&pkg).and_then(|(_, next)| next.clone());
+            steps.push(graph.step(&pkg));
+        }
+        let mut packages = reachable(graph, root);
+        packages.remove(root);
+        Self {
+            packages: packages.len(),
+            deepest: DependencyPath { steps },
+            heaviest,
+        }
+    }
+
+    /// Length of the longest chain of dependencies, direct dependencies being at depth 1.
+    pub fn depth(&self) -> usize {
+        self.deepest.steps.len()
+    }
+}
+
+impl FootprintLimits {
+    /// Whether there is no limit at all.
+    pub fn is_unbounded(&self) -> bool {
+        self.max_packages.is_none() && self.max_depth.is_none()
+    }
+
+    /// Check a footprint against these limits, the number of packages first.
+    pub fn check(&self, footprint: &Footprint) -> Result<(), FootprintError> {
+        if let Some(max) = self.max_packages.filter(|max| footprint.packages > *max) {
+            return Err(FootprintError::TooManyPackages {
+                count: footprint.packages,
+                max,
+                heaviest: (footprint.heaviest.iter().take(REPORTED_HEAVIEST).cloned()).collect(),
+            });
+        }
+        if let Some(max) = self.max_depth.filter(|max| footprint.depth() > *max) {
+            return Err(FootprintError::TooDeep {
+                depth: footprint.depth(),
+                max,
+                chain: footprint.deepest.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Packages reachable from a package, itself included.
+fn reachable(graph: &DependencyGraph, pkg: &Pkg) -> BTreeSet<Pkg> {
+    let mut visited = BTreeSet::new();
+    let mut to_visit = vec![pkg.clone()];
+    while let Some(pkg) = to_visit.pop() {
+        if visited.insert(pkg.clone()) {
+            to_visit.extend(graph.dependencies_of(&pkg).map(|(dep, _)| dep.clone()));
+        }
+    }
+    visited
+}
+
+/// Length of the longest chain of dependencies of a package,
+/// memoized with the first package of that chain.
+/// Dependency cycles, which are invalid anyway, are cut where they are found.
+fn longest_chain(
+    graph: &DependencyGraph,
+    pkg: &Pkg,
+    longest: &mut BTreeMap<Pkg, (usize, Option<Pkg>)>,
+    in_progress: &mut BTreeSet<Pkg>,
+) -> usize {
+    if let Some((length, _)) = longest.get(pkg) {
+        return *length;
+    }
+    in_progress.insert(pkg.clone());
+    let mut best = (0, None);
+    for (dep, _) in graph.dependencies_of(pkg) {
+        if in_progress.contains(dep) {
+            continue;
+        }
+        let length = 1 + longest_chain(graph, dep, longest, in_progress);
+        if length > best.0 {
+            best = (length, Some(dep.clone()));
+        }
+    }
+    in_progress.remove(pkg);
+    longest.insert(pkg.clone(), best.clone());
+    best.0
+}
@@ -0,0 +1,374 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module exploring the dependency graph of a project.
+//!
+//! Explaining why a package is part of a solution, auditing where a suspicious package
+//! comes from, or drawing the dependencies of a project,
+//! all need the chains of dependencies connecting two packages.
+//! A [`DependencyGraph`] is built either from a solution, with one node per package
+//! at its selected version, or from raw package configs, merging the constraints
+//! of all their versions.
+//! Then [`paths_between`] enumerates the dependency paths between two of its packages,
+//! bounded by [`PathLimits`] since their number grows exponentially with the depth,
+//! and [`render_tree`] draws the tree of the dependencies of a package,
+//! or of its dependents in the [`DependencyGraph::inverted`] graph.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fmt::Write;
+
+use crate::constraint::Constraint;
+use crate::project_config::{AppDependencies, PackageConfig, Pkg};
+use crate::solver::RootDependencies;
+
+/// Graph of the dependencies between packages.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyGraph {
+    /// Versions of the packages, only known in graphs of solutions.
+    versions: BTreeMap<Pkg, SemVer>,
+    /// Dependencies of each package, with their constraints.
+    dependencies: BTreeMap<Pkg, BTreeMap<Pkg, Constraint>>,
+}
+
+/// Bounds on the enumeration of dependency paths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathLimits {
+    /// Maximum number of dependency edges of a path.
+    pub max_depth: usize,
+    /// Maximum number of paths returned.
+    pub max_paths: usize,
+}
+
+/// A package on a dependency path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PathStep {
+    /// The package identifier.
+    pub pkg: Pkg,
+    /// Its version, if the graph comes from a solution.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<SemVer>,
+}
+
+/// A chain of dependencies, where each package depends on the next one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DependencyPath {
+    /// The packages of the chain, starting with the dependent one.
+    pub steps: Vec<PathStep>,
+}
+
+/// Options of [`render_tree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeOptions {
+    /// Maximum depth of the tree, the dependencies of the root being at depth 1.
+    pub max_depth: Option<usize>,
+    /// Packages removed from the tree, with their dependencies.
+    pub prune: BTreeSet<Pkg>,
+    /// Whether the dependencies of a package are only shown where it first appears,
+    /// its other appearances being marked with `(*)`.
+    pub dedupe: bool,
+}
+
+impl Default for TreeOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            prune: BTreeSet::new(),
+            dedupe: true,
+        }
+    }
+}
+
+impl Default for PathLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 16,
+            max_paths: 100,
+        }
+    }
+}
+
+impl DependencyGraph {
+    /// Initialize an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a package with its dependencies, merged with the ones already known for it.
+    pub fn add<I>(&mut self, pkg: Pkg, version: Option<SemVer>, dependencies: I)
+    where
+        I: IntoIterator<Item = (Pkg, Constraint)>,
+    {
+        if let Some(version) = version {
+            self.versions.insert(pkg.clone(), version);
+        }
+        let known = self.dependencies.entry(pkg).or_default();
+        for (dep, constraint) in dependencies {
+            match known.get_mut(&dep) {
+                Some(c) => *c = Constraint(c.0.union(&constraint.0)),
+                None => {
+                    known.insert(dep, constraint);
+                }
+            }
+        }
+    }
+
+    /// Graph of a solution, rooted at the root of the project,
+    /// as computed by [`RootDependencies`].
+    ///
+    /// The `load_config` function provides the config of each package of the solution
+    /// at its selected version, typically from `ELM_HOME` or the cache.
+    pub fn of_solution<F, E>(
+        root: &RootDependencies,
+        solution: &AppDependencies,
+        load_config: F,
+    ) -> Result<Self, E>
+    where
+        F: Fn(&Pkg, SemVer) -> Result<PackageConfig, E>,
+    {
+        let mut graph = Self::new();
+        let root_deps = (root.dependencies.iter()).map(|(p, r)| (p.clone(), Constraint(r.clone())));
+        graph.add(root.pkg.clone(), Some(root.version), root_deps);
+        for (pkg, version) in solution.direct.iter().chain(solution.indirect.iter()) {
+            let config = load_config(pkg, *version)?;
+            graph.add(pkg.clone(), Some(*version), config.dependencies);
+        }
+        Ok(graph)
+    }
+
+    /// Raw constraint graph of package configs.
+    /// When several versions of a package are given, their dependencies are merged.
+    pub fn of_configs<'a, I>(configs: I) -> Self
+    where
+        I: IntoIterator<Item = &'a PackageConfig>,
+    {
+        let mut graph = Self::new();
+        for config in configs {
+            graph.add(config.name.clone(), None, config.dependencies.clone());
+        }
+        graph
+    }
+
+    /// Dependencies of a package, with their constraints, in alphabetical order.
+    pub fn dependencies_of(&self, pkg: &Pkg) -> impl Iterator<Item = (&Pkg, &Constraint)> {
+        self.dependencies.get(pkg).into_iter().flatten()
+    }
+
+    /// Graph with the same packages, where each package depends on its dependents,
+    /// to explore where a package is used.
+    pub fn inverted(&self) -> Self {
+        let mut inverted = Self::new();
+        inverted.versions = self.versions.clone();
+        for (pkg, dependencies) in &self.dependencies {
+            inverted.dependencies.entry(pkg.clone()).or_default();
+            for (dep, constraint) in dependencies {
+                let dependents = inverted.dependencies.entry(dep.clone()).or_default();
+                dependents.insert(pkg.clone(), constraint.clone());
+            }
+        }
+        inverted
+    }
+
+    /// Version of a package, if the graph comes from a solution.
+    pub fn version_of(&self, pkg: &Pkg) -> Option<SemVer> {
+        self.versions.get(pkg).copied()
+    }
+
+    pub(crate) fn step(&self, pkg: &Pkg) -> PathStep {
+        PathStep {
+            pkg: pkg.clone(),
+            version: self.version_of(pkg),
+        }
+    }
+}
+
+/// Enumerate the dependency paths from one package to another, without cycles.
+///
+/// Shorter paths come first, and paths of the same length are in alphabetical order,
+/// such that the most relevant ones are kept when reaching the limits.
+///
+/// ```
+/// # use elm_solve_deps::graph::{paths_between, DependencyGraph, PathLimits};
+/// # use elm_solve_deps::project_config::Pkg;
+/// let (core, json, http) = (Pkg::new("elm", "core"), Pkg::new("elm", "json"), Pkg::new("elm", "http"));
+/// let any = || "1.0.0 <= v < 2.0.0".parse().unwrap();
+/// let mut graph = DependencyGraph::new();
+/// graph.add(http.clone(), None, vec![(core.clone(), any()), (json.clone(), any())]);
+/// graph.add(json.clone(), None, vec![(core.clone(), any())]);
+/// let paths = paths_between(&http, &core, &graph, PathLimits::default());
+/// let paths: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+/// assert_eq!(paths, vec!["elm/http -> elm/core", "elm/http -> elm/json -> elm/core"]);
+/// ```
+pub fn paths_between(
+    from: &Pkg,
+    to: &Pkg,
+    graph: &DependencyGraph,
+    limits: PathLimits,
+) -> Vec<DependencyPath> {
+    let mut paths = Vec::new();
+    if from == to {
+        paths.push(vec![from]);
+    }
+    // Iterative deepening, to find shorter paths first.
+    let distances = distances_to(graph, to);
+    for depth in 1..=limits.max_depth {
+        if paths.len() >= limits.max_paths {
+            break;
+        }
+        let mut current = vec![from];
+        let max_paths = limits.max_paths;
+        paths_of_depth(
+            graph,
+            to,
+            &distances,
+            depth,
+            max_paths,
+            &mut current,
+            &mut paths,
+        );
+    }
+    paths.truncate(limits.max_paths);
+    (paths.into_iter())
+        .map(|path| DependencyPath {
+            steps: path.into_iter().map(|pkg| graph.step(pkg)).collect(),
+        })
+        .collect()
+}
+
+/// Number of edges of the shortest path from each package to `to`.
+///
+/// Packages that cannot reach `to` are missing, so that searching paths
+/// never explores them, nor the packages too far away for the remaining depth.
+fn distances_to(graph: &DependencyGraph, to: &Pkg) -> BTreeMap<Pkg, usize> {
+    let dependents = graph.inverted();
+    let mut distances = BTreeMap::from([(to.clone(), 0)]);
+    let mut frontier = vec![to.clone()];
+    let mut distance = 0;
+    while !frontier.is_empty() {
+        distance += 1;
+        let mut next = Vec::new();
+        for pkg in &frontier {
+            for (dependent, _) in dependents.dependencies_of(pkg) {
+                if !distances.contains_key(dependent) {
+                    distances.insert(dependent.clone(), distance);
+                    next.push(dependent.clone());
+                }
+            }
+        }
+        frontier = next;
+    }
+    distances
+}
+
+/// Depth first search of the paths with exactly `depth` more edges, continuing `current`.
+fn paths_of_depth<'a>(
+    graph: &'a DependencyGraph,
+    to: &Pkg,
+    distances: &BTreeMap<Pkg, usize>,
+    depth: usize,
+    max_paths: usize,
+    current: &mut Vec<&'a Pkg>,
+    paths: &mut Vec<Vec<&'a Pkg>>,
+) {
+    let last = current[current.len() - 1];
+    for (dep, _) in graph.dependencies_of(last) {
+        if paths.len() >= max_paths {
+            return;
+        }
+        let reachable = (distances.get(dep)).is_some_and(|distance| *distance < depth);
+        if !reachable || current.contains(&dep) {
+            continue;
+        }
+        current.push(dep);
+        if depth == 1 {
+            if dep == to {
+                paths.push(current.clone());
+            }
+        } else if dep != to {
+            paths_of_depth(graph, to, distances, depth - 1, max_paths, current, paths);
+        }
+        current.pop();
+    }
+}
+
+/// Draw the tree of the dependencies of a package, one package per line,
+/// like `cargo tree`.
+///
+/// Packages whose dependencies were already shown are marked with `(*)`,
+/// unless disabled in the options, and so are dependency cycles.
+///
+/// ```
+/// # use elm_solve_deps::graph::{render_tree, DependencyGraph, TreeOptions};
+/// # use elm_solve_deps::project_config::Pkg;
+/// let (core, json, http) = (Pkg::new("elm", "core"), Pkg::new("elm", "json"), Pkg::new("elm", "http"));
+/// let any = || "1.0.0 <= v < 2.0.0".parse().unwrap();
+/// let mut graph = DependencyGraph::new();
+/// graph.add(http.clone(), None, vec![(core.clone(), any()), (json.clone(), any())]);
+/// graph.add(json.clone(), None, vec![(core.clone(), any())]);
+/// let tree = render_tree(&graph, &http, &TreeOptions::default());
+/// assert_eq!(tree, "elm/http\n├── elm/core\n└── elm/json\n    └── elm/core\n");
+/// let inverted = render_tree(&graph.inverted(), &core, &TreeOptions::default());
+/// assert_eq!(inverted, "elm/core\n├── elm/http\n└── elm/json\n    └── elm/http\n");
+/// ```
+pub fn render_tree(graph: &DependencyGraph, root: &Pkg, options: &TreeOptions) -> String {
+    let mut tree = format!("{}\n", graph.step(root));
+    if options.max_depth != Some(0) {
+        let mut ancestors = vec![root];
+        let mut expanded = BTreeSet::from([root]);
+        render_children(graph, options, "", &mut ancestors, &mut expanded, &mut tree);
+    }
+    tree
+}
+
+/// Draw the dependencies of the last of the ancestors, each line starting with `prefix`.
+fn render_children<'a>(
+    graph: &'a DependencyGraph,
+    options: &TreeOptions,
+    prefix: &str,
+    ancestors: &mut Vec<&'a Pkg>,
+    expanded: &mut BTreeSet<&'a Pkg>,
+    tree: &mut String,
+) {
+    let children = |pkg: &'a Pkg| {
+        (graph.dependencies_of(pkg))
+            .map(|(dep, _)| dep)
+            .filter(|dep| !options.prune.contains(*dep))
+    };
+    let deps: Vec<&Pkg> = children(ancestors[ancestors.len() - 1]).collect();
+    let depth = ancestors.len();
+    for (i, &dep) in deps.iter().enumerate() {
+        let last = i + 1 == deps.len();
+        let too_deep = options.max_depth.is_some_and(|max| depth >= max);
+        let expandable = !too_deep && children(dep).next().is_some();
+        let repeated = ancestors.contains(&dep) || (options.dedupe && expanded.contains(dep));
+        let mark = if expandable && repeated { " (*)" } else { "" };
+        let branch = if last { "└── " } else { "├── " };
+        let _ = writeln!(tree, "{}{}{}{}", prefix, branch, graph.step(dep), mark);
+        if expandable && !repeated {
+            expanded.insert(dep);
+            ancestors.push(dep);
+            let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            render_children(graph, options, &prefix, ancestors, expanded, tree);
+            ancestors.pop();
+        }
+    }
+}
+
+impl fmt::Display for PathStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.version {
+            Some(version) => write!(f, "{}@{}", self.pkg, version),
+            None => write!(f, "{}", self.pkg),
+        }
+    }
+}
+
+impl fmt::Display for DependencyPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let steps: Vec<String> = self.steps.iter().map(|s| s.to_string()).collect();
+        f.write_str(&steps.join(" -> "))
+    }
+}
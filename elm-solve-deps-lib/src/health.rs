@@ -0,0 +1,131 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module assessing the health of the packages of a solution, to spot abandoned dependencies.
+//!
+//! The package site lists the release time of every version of a package,
+//! which [`ReleaseTimes`] fetches and caches in `ELM_HOME`,
+//! fetching them again only when new versions are published.
+//! The number of releases and the last release time of each package,
+//! combined with the number of packages depending on it, computed locally
+//! with [`reverse_dependencies`](crate::stats::reverse_dependencies),
+//! let [`assess`] classify the packages of a solution with a [`Health`]:
+//! packages without recent releases are only considered abandoned
+//! if few other packages rely on them, since many mature elm packages are simply done.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap as Map, BTreeSet};
+use std::time::Duration;
+
+use crate::clock::Clock;
+use crate::project_config::{AppDependencies, Pkg};
+use crate::release_age::ReleaseTimes;
+
+/// Health of a package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Health {
+    /// The package had a release recently.
+    Active,
+    /// The package had no recent release, but many packages depend on it.
+    Mature,
+    /// The package had no recent release, and few packages depend on it.
+    Abandoned,
+    /// The release times of the package are unknown.
+    Unknown,
+}
+
+/// Thresholds of the [`Health`] classification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthThresholds {
+    /// How long after its last release a package is still active.
+    pub active_for: Duration,
+    /// Number of dependents from which an inactive package is mature instead of abandoned.
+    pub mature_dependents: usize,
+}
+
+impl Default for HealthThresholds {
+    /// Active for two years after the last release, and mature from ten dependents.
+    fn default() -> Self {
+        Self {
+            active_for: Duration::from_secs(2 * 365 * 24 * 60 * 60),
+            mature_dependents: 10,
+        }
+    }
+}
+
+/// Health of one package of a solution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct PackageHealth {
+    /// The package identifier.
+    pub pkg: Pkg,
+    /// Number of published versions, if known.
+    pub release_count: Option<usize>,
+    /// Release time of the latest published version, in seconds since the unix epoch, if known.
+    pub last_release: Option<u64>,
+    /// Number of packages whose latest version depends directly on this package.
+    pub dependents: usize,
+    /// The health classification.
+    pub health: Health,
+}
+
+impl Health {
+    /// Classify a package from its last release time and its number of dependents.
+    ///
+    /// ```
+    /// # use elm_solve_deps::health::{Health, HealthThresholds};
+    /// let thresholds = HealthThresholds::default();
+    /// let year = 365 * 24 * 60 * 60;
+    /// let now = 10 * year;
+    /// assert_eq!(Health::classify(Some(9 * year), 0, &thresholds, now), Health::Active);
+    /// assert_eq!(Health::classify(Some(2 * year), 50, &thresholds, now), Health::Mature);
+    /// assert_eq!(Health::classify(Some(2 * year), 1, &thresholds, now), Health::Abandoned);
+    /// assert_eq!(Health::classify(None, 50, &thresholds, now), Health::Unknown);
+    /// ```
+    pub fn classify(
+        last_release: Option<u64>,
+        dependents: usize,
+        thresholds: &HealthThresholds,
+        now: u64,
+    ) -> Self {
+        match last_release {
+            None => Self::Unknown,
+            Some(time) if now.saturating_sub(time) < thresholds.active_for.as_secs() => {
+                Self::Active
+            }
+            Some(_) if dependents >= thresholds.mature_dependents => Self::Mature,
+            Some(_) => Self::Abandoned,
+        }
+    }
+}
+
+/// Health of each package of a solution, from the known release times of its versions,
+/// see [`ReleaseTimes::ensure`], and the dependents of each package.
+///
+/// Direct dependencies are listed first, then indirect ones, each in alphabetical order.
+pub fn assess<C: Clock + ?Sized>(
+    solution: &AppDependencies,
+    release_times: &ReleaseTimes,
+    dependents: &Map<Pkg, BTreeSet<Pkg>>,
+    thresholds: &HealthThresholds,
+    clock: &C,
+) -> Vec<PackageHealth> {
+    let now = clock.unix_secs();
+    (solution.direct.keys())
+        .chain(solution.indirect.keys())
+        .map(|pkg| {
+            let times = release_times.times.get(pkg);
+            let last_release = times.and_then(|times| times.values().max().copied());
+            let dependents = dependents.get(pkg).map_or(0, |d| d.len());
+            PackageHealth {
+                pkg: pkg.clone(),
+                release_count: times.map(|times| times.len()),
+                last_release,
+                dependents,
+                health: Health::classify(last_release, dependents, thresholds, now),
+            }
+        })
+        .collect()
+}
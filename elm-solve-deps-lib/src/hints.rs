@@ -0,0 +1,94 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module defining dependency solving hints embedded in the `elm.json` of a project.
+//!
+//! The elm compiler ignores unknown fields of `elm.json`,
+//! so a project can carry hints for the dependency solver in an `"x-solve-deps"` field,
+//! such that project-specific solver behavior travels with the repository.
+//!
+//! ```json
+//! "x-solve-deps": {
+//!   "prefer": { "elm/core": "1.0.5" },
+//!   "exclude": { "author/pkg": ["5.0.0 <= v < 6.0.0"] },
+//!   "hold": ["elm/json"],
+//!   "strategy": "oldest"
+//! }
+//! ```
+//!
+//! Hints only apply when the project is the root of the resolution,
+//! and are honored by [`solve_deps_with`](crate::solver::solve_deps_with) and all solvers.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap as Map;
+
+use crate::constraint::Constraint;
+use crate::policy::{HoldError, Policies, Policy};
+use crate::project_config::{Pkg, ProjectConfig};
+use crate::solver::VersionStrategy;
+
+/// Hints for the dependency solver, from the `"x-solve-deps"` field of an `elm.json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SolveHints {
+    /// Versions tried first for some packages, if compatible.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub prefer: Map<Pkg, SemVer>,
+    /// Versions of some packages that are never picked.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub exclude: Map<Pkg, Vec<Constraint>>,
+    /// Packages kept at the version recorded in the `elm.json` of an application.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hold: Vec<Pkg>,
+    /// Whether to pick the newest or oldest compatible versions,
+    /// instead of the order of the available versions.
+    /// The `prefer-installed` strategy keeps the order of the solver,
+    /// since only solvers know which versions are installed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<VersionStrategy>,
+}
+
+impl SolveHints {
+    /// Policies excluding versions and holding packages as hinted.
+    ///
+    /// Held packages must have a version recorded in the application `elm.json`,
+    /// so a package `elm.json` cannot hold any package.
+    pub fn policies(&self, project: &ProjectConfig) -> Result<Policies, HoldError> {
+        let mut policies = Policies::new();
+        for (pkg, excluded) in &self.exclude {
+            let policy = Policy {
+                exclude: excluded.clone(),
+                reason: Some("excluded in elm.json".to_string()),
+                ..Policy::default()
+            };
+            policies.insert(pkg.clone(), policy);
+        }
+        let recorded = match project {
+            ProjectConfig::Application(app_config) => app_config.recorded_versions(),
+            ProjectConfig::Package(_) => Map::new(),
+        };
+        policies.hold_recorded(&recorded, &self.hold)?;
+        Ok(policies)
+    }
+
+    /// Order the available versions of a package as hinted,
+    /// with the preferred version first, if available.
+    pub fn order_versions<I>(&self, pkg: &Pkg, versions: I) -> Vec<SemVer>
+    where
+        I: Iterator<Item = SemVer>,
+    {
+        let mut versions: Vec<SemVer> = versions.collect();
+        match self.strategy {
+            Some(VersionStrategy::Newest) => versions.sort_by(|a, b| b.cmp(a)),
+            Some(VersionStrategy::Oldest) => versions.sort(),
+            // Only solvers know which versions are installed, keep their order.
+            Some(VersionStrategy::PreferInstalled) | None => {}
+        }
+        if let Some(preferred) = self.prefer.get(pkg) {
+            if let Some(i) = versions.iter().position(|v| v == preferred) {
+                versions[..=i].rotate_right(1);
+            }
+        }
+        versions
+    }
+}
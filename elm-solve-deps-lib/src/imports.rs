@@ -0,0 +1,214 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module scanning the modules imported by Elm source files.
+//!
+//! Only the `import` declarations are parsed, which is enough to know
+//! which modules, and thus which packages, the sources of a project rely on.
+//! Declarations must start at the beginning of a line, like the compiler requires,
+//! and the ones inside comments or multiline strings are ignored.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "fs")]
+use std::io;
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::path::PathBuf;
+
+/// An `import` declaration of an Elm source file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Import {
+    /// The imported module, such as `Json.Decode`.
+    pub module: String,
+    /// Line of the declaration, starting at 1.
+    pub line: usize,
+}
+
+/// An Elm source file, with its imports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SourceFile {
+    /// Path to the file.
+    pub path: PathBuf,
+    /// Name of the module defined by the file, deduced from its path.
+    pub module: String,
+    /// Imports of the file, in order.
+    pub imports: Vec<Import>,
+}
+
+/// Parse the `import` declarations of the source of an Elm module.
+///
+/// ```
+/// # use elm_solve_deps::imports::parse_imports;
+/// let source = r#"module Main exposing (main)
+///
+/// import Html exposing (Html)
+/// import Json.Decode as Decode
+/// {- import Commented.Out -}
+/// -- import Commented.Out
+/// "#;
+/// let modules: Vec<(String, usize)> = (parse_imports(source).into_iter())
+///     .map(|import| (import.module, import.line))
+///     .collect();
+/// assert_eq!(modules, [("Html".to_string(), 3), ("Json.Decode".to_string(), 4)]);
+/// ```
+pub fn parse_imports(source: &str) -> Vec<Import> {
+    let mut imports = Vec::new();
+    let mut state = LexState::default();
+    for (index, line) in source.lines().enumerate() {
+        if state.is_code() {
+            if let Some(module) = import_declaration(line) {
+                imports.push(Import {
+                    module: module.to_string(),
+                    line: index + 1,
+                });
+            }
+        }
+        state.skip_line(line);
+    }
+    imports
+}
+
+/// Name of the module imported by a line, if it is an `import` declaration.
+fn import_declaration(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("import")?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let module = rest.split_whitespace().next()?;
+    is_module_name(module).then_some(module)
+}
+
+/// Whether a name is a valid module name, such as `Json.Decode`.
+fn is_module_name(name: &str) -> bool {
+    name.split('.').all(|part| {
+        part.starts_with(|c: char| c.is_ascii_uppercase())
+            && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+/// What the parser is inside of, at the start of a line.
+#[derive(Debug, Default)]
+struct LexState {
+    /// Depth of nested block comments.
+    comment_depth: usize,
+    /// Whether inside a multiline string.
+    in_string: bool,
+}
+
+impl LexState {
+    fn is_code(&self) -> bool {
+        self.comment_depth == 0 && !self.in_string
+    }
+
+    /// Update the state with the comments and strings opened or closed by a line.
+    fn skip_line(&mut self, line: &str) {
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let rest = &bytes[i..];
+            if self.in_string {
+                if rest.starts_with(b"\\") {
+                    i += 2;
+                    continue;
+                } else if rest.starts_with(b"\"\"\"") {
+                    self.in_string = false;
+                    i += 3;
+                    continue;
+                }
+            } else if self.comment_depth > 0 {
+                if rest.starts_with(b"{-") {
+                    self.comment_depth += 1;
+                    i += 2;
+                    continue;
+                } else if rest.starts_with(b"-}") {
+                    self.comment_depth -= 1;
+                    i += 2;
+                    continue;
+                }
+            } else if rest.starts_with(b"--") {
+                return;
+            } else if rest.starts_with(b"{-") {
+                self.comment_depth = 1;
+                i += 2;
+                continue;
+            } else if rest.starts_with(b"\"\"\"") {
+                self.in_string = true;
+                i += 3;
+                continue;
+            } else if rest.starts_with(b"\"") {
+                i += 1 + single_line_literal_len(&bytes[i + 1..], b'"');
+                continue;
+            } else if rest.starts_with(b"'") {
+                i += 1 + single_line_literal_len(&bytes[i + 1..], b'\'');
+                continue;
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Length of the rest of a string or char literal, its closing quote included.
+fn single_line_literal_len(bytes: &[u8], quote: u8) -> usize {
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' {
+            i += 2;
+        } else if bytes[i] == quote {
+            return i + 1;
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+/// Scan the imports of all the Elm source files of a source directory, such as `src/`,
+/// sorted by path.
+#[cfg(feature = "fs")]
+pub fn scan_dir<P: AsRef<Path>>(src_dir: P) -> io::Result<Vec<SourceFile>> {
+    let src_dir = src_dir.as_ref();
+    let mut paths = Vec::new();
+    collect_elm_files(src_dir, &mut paths)?;
+    paths.sort();
+    let mut files = Vec::with_capacity(paths.len());
+    for path in paths {
+        let source = std::fs::read_to_string(&path)?;
+        let module = module_name(src_dir, &path);
+        files.push(SourceFile {
+            imports: parse_imports(&source),
+            path,
+            module,
+        });
+    }
+    Ok(files)
+}
+
+/// Name of the module of a source file, such as `Json.Decode` for `src/Json/Decode.elm`.
+#[cfg(feature = "fs")]
+fn module_name(src_dir: &Path, path: &Path) -> String {
+    let relative = path
+        .strip_prefix(src_dir)
+        .unwrap_or(path)
+        .with_extension("");
+    let parts: Vec<String> = (relative.components())
+        .map(|part| part.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    parts.join(".")
+}
+
+/// Recursively collect the Elm files inside a directory.
+#[cfg(feature = "fs")]
+fn collect_elm_files(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_elm_files(&path, paths)?;
+        } else if file_type.is_file() && path.extension().is_some_and(|ext| ext == "elm") {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
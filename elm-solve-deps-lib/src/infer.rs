@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module inferring publishable dependency constraints of a package
+//! from the exact versions used during its development.
+//!
+//! Package authors often develop with an application `elm.json`,
+//! for example for examples or a demo, where dependencies are pinned to exact versions.
+//! This module infers the constraints to publish in the package `elm.json`
+//! from those versions, such as `1.0.2 <= v < 2.0.0` from the used `1.0.2`.
+
+use pubgrub::range::Range;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap as Map;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::constraint::Constraint;
+use crate::project_config::{ApplicationConfig, Pkg};
+
+/// How a constraint is inferred from a used version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InferPolicy {
+    /// From the used version up to the next major version, such as `1.0.2 <= v < 2.0.0`.
+    #[default]
+    Caret,
+    /// The whole major version of the used version, such as `1.0.0 <= v < 2.0.0`.
+    /// Beware that the package may then use functions missing from older minor versions.
+    FullMajor,
+}
+
+/// Error parsing an [`InferPolicy`], which must be either `caret` or `full-major`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown constraint policy `{0}`, expected `caret` or `full-major`")]
+pub struct InferPolicyParseError(pub String);
+
+/// Dependencies inferred for a package, with the same layout than a package `elm.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct InferredDependencies {
+    /// Constraints inferred for the dependencies.
+    pub dependencies: Map<Pkg, Constraint>,
+    /// Constraints inferred for the test dependencies.
+    pub test_dependencies: Map<Pkg, Constraint>,
+}
+
+impl InferPolicy {
+    /// Infer the constraint of a dependency from its used version.
+    ///
+    /// ```
+    /// # use elm_solve_deps::infer::InferPolicy;
+    /// # use elm_solve_deps::semver::SemVer;
+    /// let used = SemVer::new(1, 0, 2);
+    /// assert_eq!(InferPolicy::Caret.constraint(used).to_string(), "1.0.2 <= v < 2.0.0");
+    /// assert_eq!(InferPolicy::FullMajor.constraint(used).to_string(), "1.0.0 <= v < 2.0.0");
+    /// ```
+    pub fn constraint(self, version: SemVer) -> Constraint {
+        let (major, _, _): (u32, u32, u32) = version.into();
+        let low = match self {
+            Self::Caret => version,
+            Self::FullMajor => SemVer::new(major, 0, 0),
+        };
+        Constraint(Range::between(low, version.bump_major()))
+    }
+}
+
+impl FromStr for InferPolicy {
+    type Err = InferPolicyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "caret" => Ok(Self::Caret),
+            "full-major" => Ok(Self::FullMajor),
+            _ => Err(InferPolicyParseError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for InferPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Caret => write!(f, "caret"),
+            Self::FullMajor => write!(f, "full-major"),
+        }
+    }
+}
+
+/// Infer the dependencies of a package from the application `elm.json`
+/// used during its development.
+///
+/// Direct dependencies of the application become dependencies of the package,
+/// and its direct test dependencies become test dependencies,
+/// unless they already are normal dependencies.
+/// Indirect dependencies are ignored, as they are not needed by the package itself.
+pub fn infer_dependencies(
+    dev_config: &ApplicationConfig,
+    policy: InferPolicy,
+) -> InferredDependencies {
+    let infer = |deps: &Map<Pkg, SemVer>| -> Map<Pkg, Constraint> {
+        (deps.iter())
+            .map(|(p, v)| (p.clone(), policy.constraint(*v)))
+            .collect()
+    };
+    let dependencies = infer(&dev_config.dependencies.direct);
+    let mut test_dependencies = infer(&dev_config.test_dependencies.direct);
+    test_dependencies.retain(|p, _| !dependencies.contains_key(p));
+    InferredDependencies {
+        dependencies,
+        test_dependencies,
+    }
+}
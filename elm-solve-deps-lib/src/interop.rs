@@ -0,0 +1,291 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module importing and exporting the versions cache in formats understood by other tools.
+//!
+//! Other tools of the elm ecosystem maintain their own index of the published packages.
+//! Rather than each tool downloading the registry state again,
+//! indexes can be exchanged in the layouts of these tools:
+//!
+//! - `elm-json`: the binary `registry.dat` index of the elm compiler,
+//!   which elm-json reads and updates next to the installed packages;
+//! - `elm-tooling`: an `ELM_HOME` directory, like the ones elm-tooling installs
+//!   the compiler and elm-json into, in CI caches typically.
+//!   Its index is the `0.19.1/packages/registry.dat` file,
+//!   and packages installed there are imported too;
+//!
+//! or in one of the formats of the package server responses:
+//!
+//! - `all-packages`: a JSON object mapping every package to its versions,
+//!   like the response of the `/all-packages` endpoint;
+//! - `list`: a JSON array of `author/package@version` entries,
+//!   like the response of the `/all-packages/since/0` endpoint.
+//!
+//! Imported indexes are typically merged into the existing versions cache
+//! with [`merge`], which never forgets versions already known.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::nix::registry_dat;
+#[cfg(feature = "fs")]
+use crate::paths;
+use crate::pkg_version::{Cache, PkgVersion};
+use crate::project_config::Pkg;
+use crate::registry::{AllPackages, RegistryError, SinceResponse};
+use crate::semver::SemVer;
+
+/// Version of elm whose packages are exchanged with an `ELM_HOME` of elm-tooling.
+pub const ELM_TOOLING_ELM_VERSION: &str = "0.19.1";
+
+/// Format of an exchanged index of packages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CacheFormat {
+    /// JSON object mapping every package to its versions.
+    #[default]
+    AllPackages,
+    /// JSON array of `author/package@version` entries.
+    List,
+    /// The `registry.dat` file of the elm compiler, read by elm-json.
+    ElmJson,
+    /// An `ELM_HOME` directory managed by elm-tooling,
+    /// with its `registry.dat` file and its installed packages.
+    ElmTooling,
+}
+
+/// Error parsing a [`CacheFormat`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown cache format `{0}`, expected `all-packages`, `list`, `elm-json` or `elm-tooling`")]
+pub struct CacheFormatParseError(pub String);
+
+/// Error importing or exporting an index of packages.
+#[derive(Error, Debug)]
+pub enum InteropError {
+    /// Failed to read or write the index file.
+    #[error("unable to read/write the index file")]
+    FileIoError(#[from] std::io::Error),
+
+    /// Failed to encode the index.
+    #[error("failed to encode the index")]
+    JsonError(#[from] serde_json::Error),
+
+    /// The index file contains invalid entries.
+    #[error("invalid index file")]
+    Invalid(#[from] RegistryError),
+
+    /// The `registry.dat` file is truncated or malformed.
+    #[error("invalid registry.dat file at byte {0}")]
+    InvalidRegistryDat(usize),
+}
+
+impl FromStr for CacheFormat {
+    type Err = CacheFormatParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all-packages" => Ok(Self::AllPackages),
+            "list" => Ok(Self::List),
+            "elm-json" => Ok(Self::ElmJson),
+            "elm-tooling" => Ok(Self::ElmTooling),
+            _ => Err(CacheFormatParseError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for CacheFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AllPackages => write!(f, "all-packages"),
+            Self::List => write!(f, "list"),
+            Self::ElmJson => write!(f, "elm-json"),
+            Self::ElmTooling => write!(f, "elm-tooling"),
+        }
+    }
+}
+
+/// Decode an index of packages.
+/// The index of the `elm-tooling` format is its `registry.dat` file.
+///
+/// ```
+/// # use elm_solve_deps::interop::{self, CacheFormat};
+/// # use elm_solve_deps::project_config::Pkg;
+/// let list = r#"["elm/core@1.0.5", "elm/json@1.1.3", "elm/core@1.0.4"]"#;
+/// let cache = interop::decode(list.as_bytes(), CacheFormat::List).unwrap();
+/// assert_eq!(cache.cache[&Pkg::new("elm", "core")].len(), 2);
+/// let all = interop::encode(&cache, CacheFormat::AllPackages).unwrap();
+/// assert_eq!(interop::decode(&all, CacheFormat::AllPackages).unwrap().cache, cache.cache);
+/// let dat = interop::encode(&cache, CacheFormat::ElmJson).unwrap();
+/// assert_eq!(interop::decode(&dat, CacheFormat::ElmJson).unwrap().cache, cache.cache);
+/// assert!(interop::decode(&dat[..dat.len() - 1], CacheFormat::ElmJson).is_err());
+/// ```
+pub fn decode(bytes: &[u8], format: CacheFormat) -> Result<Cache, InteropError> {
+    let s = || String::from_utf8_lossy(bytes);
+    match format {
+        CacheFormat::AllPackages => Ok(AllPackages::from_json(&s())?.into()),
+        CacheFormat::List => {
+            let mut cache = Cache::new();
+            for pkg_version in SinceResponse::from_json(&s())?.versions {
+                (cache.cache.entry(pkg_version.author_pkg))
+                    .or_default()
+                    .insert(pkg_version.version);
+            }
+            Ok(cache)
+        }
+        CacheFormat::ElmJson | CacheFormat::ElmTooling => decode_registry_dat(bytes),
+    }
+}
+
+/// Encode an index of packages.
+/// Entries of the `list` format are sorted by package, newest versions first.
+pub fn encode(cache: &Cache, format: CacheFormat) -> Result<Vec<u8>, InteropError> {
+    match format {
+        CacheFormat::AllPackages => {
+            let all = AllPackages {
+                packages: cache.cache.clone(),
+            };
+            Ok(serde_json::to_vec(&all)?)
+        }
+        CacheFormat::List => {
+            let versions = (cache.cache.iter())
+                .flat_map(|(pkg, versions)| {
+                    versions.iter().rev().map(move |v| PkgVersion {
+                        author_pkg: pkg.clone(),
+                        version: *v,
+                    })
+                })
+                .collect();
+            Ok(serde_json::to_vec(&SinceResponse { versions })?)
+        }
+        CacheFormat::ElmJson | CacheFormat::ElmTooling => Ok(registry_dat(cache)),
+    }
+}
+
+/// Decode a `registry.dat` file, in the binary format written by [`registry_dat`].
+fn decode_registry_dat(bytes: &[u8]) -> Result<Cache, InteropError> {
+    let mut reader = DatReader { bytes, offset: 0 };
+    let _versions_count = reader.u64()?;
+    let mut cache = Cache::new();
+    for _ in 0..reader.u64()? {
+        let author = reader.name()?;
+        let name = reader.name()?;
+        let entry = format!("{}/{}", author, name);
+        let pkg: Pkg =
+            (entry.parse()).map_err(|source| RegistryError::InvalidPackage { entry, source })?;
+        let mut versions = vec![reader.version()?];
+        for _ in 0..reader.u64()? {
+            versions.push(reader.version()?);
+        }
+        cache.cache.entry(pkg).or_default().extend(versions);
+    }
+    if reader.offset != bytes.len() {
+        return Err(InteropError::InvalidRegistryDat(reader.offset));
+    }
+    Ok(cache)
+}
+
+/// Cursor reading the fields of a `registry.dat` file.
+struct DatReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> DatReader<'a> {
+    /// Read the next `n` bytes.
+    fn take(&mut self, n: usize) -> Result<&'a [u8], InteropError> {
+        let end = (self.offset.checked_add(n))
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(InteropError::InvalidRegistryDat(self.offset))?;
+        let taken = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(taken)
+    }
+
+    /// Read a big-endian 64 bits integer.
+    fn u64(&mut self) -> Result<u64, InteropError> {
+        let bytes: [u8; 8] = self.take(8)?.try_into().unwrap_or_default();
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    /// Read a name prefixed by its length in one byte.
+    fn name(&mut self) -> Result<String, InteropError> {
+        let len = self.take(1)?[0] as usize;
+        let offset = self.offset;
+        let name = self.take(len)?;
+        String::from_utf8(name.to_vec()).map_err(|_| InteropError::InvalidRegistryDat(offset))
+    }
+
+    /// Read a version, one byte per number,
+    /// or two bytes per number after a 255 byte.
+    fn version(&mut self) -> Result<SemVer, InteropError> {
+        let first = self.take(1)?[0];
+        if first < 255 {
+            let rest = self.take(2)?;
+            return Ok(SemVer::new(first as u32, rest[0] as u32, rest[1] as u32));
+        }
+        let numbers = self.take(6)?;
+        let number = |i: usize| u16::from_be_bytes([numbers[2 * i], numbers[2 * i + 1]]) as u32;
+        Ok(SemVer::new(number(0), number(1), number(2)))
+    }
+}
+
+/// Path of the index file of a format, inside the given path.
+/// The `elm-tooling` format is an `ELM_HOME` directory, whose index is its `registry.dat`.
+#[cfg(feature = "fs")]
+fn index_path(path: &Path, format: CacheFormat) -> PathBuf {
+    match format {
+        CacheFormat::ElmTooling => {
+            paths::packages_dir(path, ELM_TOOLING_ELM_VERSION).join("registry.dat")
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Load an index of packages from a file,
+/// or from an `ELM_HOME` directory with the `elm-tooling` format,
+/// including its installed packages.
+#[cfg(feature = "fs")]
+pub fn import<P: AsRef<Path>>(path: P, format: CacheFormat) -> Result<Cache, InteropError> {
+    let path = path.as_ref();
+    if format != CacheFormat::ElmTooling {
+        return decode(&std::fs::read(path)?, format);
+    }
+    let mut cache = Cache::scan_all_installed(path, ELM_TOOLING_ELM_VERSION)?;
+    match std::fs::read(index_path(path, format)) {
+        Ok(bytes) => merge(&mut cache, decode(&bytes, format)?),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => 0,
+        Err(err) => return Err(err.into()),
+    };
+    Ok(cache)
+}
+
+/// Save an index of packages into a file,
+/// or into an `ELM_HOME` directory with the `elm-tooling` format.
+#[cfg(feature = "fs")]
+pub fn export<P: AsRef<Path>>(
+    cache: &Cache,
+    path: P,
+    format: CacheFormat,
+) -> Result<(), InteropError> {
+    let path = index_path(path.as_ref(), format);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, encode(cache, format)?).map_err(|e| e.into())
+}
+
+/// Add the versions of an imported index to a versions cache.
+/// Return the number of versions that were not known yet.
+pub fn merge(cache: &mut Cache, imported: Cache) -> usize {
+    let mut added = 0;
+    for (pkg, versions) in imported.cache {
+        let known = cache.cache.entry(pkg).or_default();
+        let before = known.len();
+        known.extend(versions);
+        added += known.len() - before;
+    }
+    added
+}
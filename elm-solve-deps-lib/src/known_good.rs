@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module defining known good sets, curated package versions known to work together.
+//!
+//! Like Stackage snapshots for Haskell, a known good set pins packages to exact versions,
+//! vetted once and shared by a team, or published at a url for everyone.
+//! Solving within a known good set gives reproducible dependencies:
+//! the packages of the set are restricted to their version with [`KnownGoodSet::restrict`],
+//! and [`KnownGoodSet::unlisted`] tells which packages of a solution are outside of it.
+//! Escape hatches release some packages from the set, to upgrade them individually,
+//! or to depend on packages not curated yet.
+//!
+//! Known good sets are typically loaded from a JSON file such as the following.
+//!
+//! ```json
+//! {
+//!   "name": "elm-2024.06",
+//!   "packages": {
+//!     "elm/core": "1.0.5",
+//!     "elm/json": "1.1.3"
+//!   }
+//! }
+//! ```
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+#[cfg(feature = "fs")]
+use crate::fetch::HttpFetch;
+#[cfg(feature = "fs")]
+use crate::pkg_version::CacheError;
+use crate::policy::{Policies, Policy};
+use crate::project_config::{AppDependencies, Pkg};
+
+/// Package versions known to work together.
+///
+/// ```
+/// # use elm_solve_deps::known_good::KnownGoodSet;
+/// # use elm_solve_deps::policy::Policies;
+/// # use elm_solve_deps::project_config::{AppDependencies, Pkg};
+/// # use elm_solve_deps::semver::SemVer;
+/// # use std::collections::BTreeSet;
+/// let set: KnownGoodSet = serde_json::from_str(
+///     r#"{ "name": "elm-2024.06", "packages": { "elm/core": "1.0.5", "elm/json": "1.1.3" } }"#,
+/// )
+/// .unwrap();
+/// let escaped: BTreeSet<Pkg> = [Pkg::new("elm", "json")].into_iter().collect();
+/// let mut policies = Policies::new();
+/// set.restrict(&mut policies, &escaped);
+/// assert!(policies.policies.contains_key(&Pkg::new("elm", "core")));
+/// assert!(!policies.policies.contains_key(&Pkg::new("elm", "json")));
+///
+/// let mut solution = AppDependencies::default();
+/// solution.direct.insert(Pkg::new("elm", "core"), SemVer::new(1, 0, 5));
+/// solution.direct.insert(Pkg::new("elm", "http"), SemVer::new(2, 0, 0));
+/// assert_eq!(set.unlisted(&solution, &escaped), vec![Pkg::new("elm", "http")]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct KnownGoodSet {
+    /// Name of the set, for reporting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The version of each package of the set.
+    pub packages: BTreeMap<Pkg, SemVer>,
+}
+
+impl KnownGoodSet {
+    /// Restrict the packages of the set to their version, except the escaped ones,
+    /// on top of their previous policies.
+    /// A version of the set excluded by a previous policy, like a vulnerable one,
+    /// thus stays excluded, and no version of its package can be picked.
+    pub fn restrict(&self, policies: &mut Policies, escaped: &BTreeSet<Pkg>) {
+        let reason = match &self.name {
+            Some(name) => format!("in the known good set {}", name),
+            None => "in the known good set".to_string(),
+        };
+        for (pkg, version) in &self.packages {
+            if !escaped.contains(pkg) {
+                let policy = Policy {
+                    min: Some(*version),
+                    max: Some(version.bump_patch()),
+                    exclude: Vec::new(),
+                    reason: Some(reason.clone()),
+                };
+                policies.intersect(pkg.clone(), policy);
+            }
+        }
+    }
+
+    /// Packages of a solution that are neither in the set nor escaped.
+    pub fn unlisted(&self, solution: &AppDependencies, escaped: &BTreeSet<Pkg>) -> Vec<Pkg> {
+        (solution.direct.keys())
+            .chain(solution.indirect.keys())
+            .filter(|pkg| !self.packages.contains_key(pkg) && !escaped.contains(pkg))
+            .cloned()
+            .collect()
+    }
+
+    /// Load a known good set from a JSON file.
+    #[cfg(feature = "fs")]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CacheError> {
+        let s = std::fs::read_to_string(path)?;
+        serde_json::from_str(&s).map_err(|e| e.into())
+    }
+
+    /// Fetch a known good set published at a url.
+    #[cfg(feature = "fs")]
+    pub fn fetch<H: HttpFetch + ?Sized>(url: &str, http_fetch: &H) -> Result<Self, CacheError> {
+        let s = http_fetch
+            .fetch(url)
+            .map_err(|source| CacheError::FetchError {
+                url: url.to_string(),
+                source,
+            })?;
+        serde_json::from_str(&s).map_err(|e| e.into())
+    }
+}
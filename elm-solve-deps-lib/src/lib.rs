@@ -83,7 +83,8 @@
 //! You can use it as follows.
 //!
 //! ```no_run
-//! # use elm_solve_deps::solver;
+//! # #[cfg(feature = "fs")] {
+//! # use elm_solve_deps::solver::{self, ExtraMode};
 //! # let elm_home = || "";
 //! // Define an offline solver.
 //! let offline_solver = solver::Offline::new(elm_home(), "0.19.1");
@@ -102,8 +103,9 @@
 //!
 //! // Solve dependencies.
 //! let solution = offline_solver
-//!     .solve_deps(&project_elm_json, use_test, extras)
+//!     .solve_deps(&project_elm_json, use_test, extras, ExtraMode::default())
 //!     .expect("Dependency solving failed");
+//! # }
 //! ```
 //!
 //! Note that it is possible to provide additional package constraints,
@@ -131,7 +133,7 @@
 //! implementing the following pseudo trait bounds:
 //!
 //! ```ignore
-//! fetch_elm_json: Fn(&Pkg, SemVer) -> Result<PackageConfig, Error>
+//! fetch_elm_json: Fn(&Pkg, SemVer) -> Result<Option<PackageConfig>, Error>
 //! list_available_versions: Fn(&Pkg) -> Result<Iterator<SemVer>, Error>
 //! ```
 //!
@@ -141,6 +143,18 @@
 //! to the prioritization for picking versions.
 //! This means prioritizing newest or oldest versions is just a `.reverse()` on your part.
 //!
+//! If the `elm.json` of a listed version turns out to be unavailable,
+//! `fetch_elm_json` can return `Ok(None)` instead of an error,
+//! and the solver will backtrack to pick other versions.
+//!
+//! ## Without the filesystem
+//!
+//! The `fs` feature, enabled by default, provides everything reading or writing files,
+//! such as the offline and online solvers, and the caches in `ELM_HOME`.
+//! With `default-features = false`, only the core solving logic remains,
+//! like [`solver::solve_deps_with`], which compiles to small wasm modules.
+//! The HTTP client of the `ureq` feature is also optional.
+//!
 //! ## Other helper modules
 //!
 //! In order for the different solver types to come together nicely,
@@ -148,16 +162,183 @@
 //!
 //! - [`project_config`]: module dealing with the serialization and deserialization of config `elm.json` files.
 //! - [`pkg_version`]: module defining the base type identifying a unique package version. It also
-//! provides a few helper types and functions to read/write to a cache in `ELM_HOME` and to fetch
-//! packages from a server following the same API than the official elm package server.
+//!   provides a few helper types and functions to read/write to a cache in `ELM_HOME` and to fetch
+//!   packages from a server following the same API than the official elm package server.
 //! - [`constraint`]: module helping with serialization and deserialization of version constraints.
 //! - [`dependency_provider`]: module with a helper implementation converting a generic dependency
-//! provider into one that is using a project `elm.json` as root.
+//!   provider into one that is using a project `elm.json` as root.
+//! - [`elm_home`]: module abstracting how installed packages are read from `ELM_HOME`,
+//!   either from its directory or from an archive.
+//! - [`cached_solution`]: module persisting the most recent solution between invocations.
+//! - [`report`]: module providing reports about solutions, such as how outdated they are.
+//! - [`review`]: module solving the dependencies of an elm-review configuration
+//!   together with the ones of the reviewed project.
+//! - [`policy`]: module defining version policies applied across all dependency resolutions.
+//! - [`registry`]: module defining typed wrappers for the responses of the package server.
+//! - [`diagnostics`]: module collecting non-fatal issues encountered while solving dependencies.
+//! - [`phased`]: module solving normal and test dependencies in two separate phases.
+//! - [`api_diff`]: module comparing the exposed modules of two versions of a package.
+//! - [`budget`]: module limiting how long a dependency resolution may run.
+//! - [`audit`]: module auditing solutions for suspicious packages, such as typosquatting.
+//! - [`stats`]: module computing registry-wide statistics about packages.
+//! - [`conflict`]: module searching for a minimal set of conflicting direct dependencies.
+//! - [`fetch`]: module defining how HTTP requests to the package server are made,
+//!   with a built-in client reusing connections when the `ureq` feature is enabled,
+//!   and decoding compressed responses when the `flate2` feature is enabled.
+//! - [`corpus`]: module recording reproducible test cases from real dependency resolutions.
+//! - [`repair`]: module repairing inconsistent versions pinned in the `elm.json` of an application.
+//! - [`infer`]: module inferring publishable dependency constraints of a package
+//!   from the exact versions used during its development.
+//! - [`bench`]: module benchmarking the dependency solver on every package version of the registry.
+//! - [`hints`]: module defining dependency solving hints embedded in the `elm.json` of a project.
+//! - [`clock`]: module abstracting the current time, for reproducible outputs.
+//! - [`doctor`]: module checking the integrity of `ELM_HOME`.
+//! - [`race`]: module racing several solving strategies in parallel.
+//! - [`interop`]: module importing and exporting the versions cache in formats understood by other tools.
+//! - [`missing`]: module remembering packages and versions missing on the package server.
+//! - [`provenance`]: module tracking where the config of each package version was loaded from.
+//! - [`sandbox`]: module solving dependencies within a temporary `ELM_HOME`.
+//! - [`error_code`]: module attaching stable codes to the errors of this crate.
+//! - [`optional`]: module solving dependencies with optional additional constraints.
+//! - [`semver`] and [`range`]: modules re-exporting the versions and ranges of versions
+//!   of the pubgrub crate, such that downstream code does not depend on pubgrub directly.
+//! - [`graph`]: module exploring the dependency graph of a project, such as the paths between two packages.
+//! - [`source_span`]: module locating dependency entries in the source of an `elm.json`, for editor diagnostics.
+//! - [`scoring`]: module scoring solutions, to pick the best one according to custom objectives.
+//! - [`watchlist`]: module notifying about new versions of watched packages.
+//! - [`progress`]: module reporting the progress of dependency resolutions as they run.
+//! - [`stale_bounds`]: module auditing the registry for constraints excluding the latest versions of dependencies.
+//! - [`solver_config`]: module capturing every option of the dependency solver in a serializable configuration.
+//! - [`federation`]: module federating several package servers into a single registry, such as official and corporate ones.
+//! - [`license`]: module parsing the license of published packages, even nonstandard ones.
+//! - [`search_trace`]: module recording how the solver explores the search space, to find what makes it slow.
+//! - [`memory`]: module measuring the memory allocated by the process, to enforce memory ceilings.
+//! - [`known_good`]: module defining known good sets, curated package versions known to work together.
+//! - [`release_age`]: module tracking when package versions were released, to avoid the most recent ones.
+//! - [`overrides`]: module overriding packages with unreleased versions, from git repositories or local paths.
+//! - [`registry_view`]: module defining a common view of package registries.
+//! - [`health`]: module assessing the health of the packages of a solution, to spot abandoned dependencies.
+//! - [`lint`]: module linting the dependency constraints of a package `elm.json`.
+//! - [`paths`]: module building the paths of installed packages and caches inside `ELM_HOME`, including Windows long paths.
+//! - [`nix`]: module exporting solutions as Nix expressions, to package elm applications with Nix.
+//! - [`compression`]: module compressing the package configs and versions caches of the dependency solver.
+//! - [`packed`]: module packing the cached package configs into a single file, for slow filesystems.
+//! - [`footprint`]: module bounding the footprint of solutions, in number of packages and dependency depth.
+//! - [`renames`]: module tracking packages renamed over time, such as the ones moved between authors.
+//! - [`imports`]: module scanning the modules imported by Elm source files.
+//! - [`daemon`]: module defining the runtime-agnostic core of long-running dependency solving daemons.
+//! - [`ecosystem`]: module defining the packages mandatory in the projects of each elm-compatible ecosystem.
+//! - `chaos`: module injecting transient failures into dependency providers, to test robustness,
+//!   when the `chaos` feature is enabled.
+//! - `shared_cache`: module defining a content-addressed cache of `elm.json` files,
+//!   shared across `ELM_HOME`s, when the `sha2` feature is enabled.
+//! - `registry_pin`: module pinning the identity of package servers on first use,
+//!   when the `sha2` feature is enabled.
+//! - `solve_memo`: module memoizing solutions across invocations, keyed by a hash of their inputs,
+//!   when the `sha2` feature is enabled.
+//! - `test_runner`: module helping test runners such as elm-test-rs reuse their last test solution,
+//!   when the `sha2` feature is enabled.
+//! - `bazel`: module exporting solutions for Bazel and similar monorepo build systems,
+//!   when the `sha2` feature is enabled.
+//! - `schema`: module providing JSON schemas of the machine-readable outputs of this crate,
+//!   when the `schemars` feature is enabled.
+//! - [`telemetry`]: module emitting OpenTelemetry spans when the `otel` feature is enabled.
 
 #![warn(missing_docs)]
 
+/// The whole pubgrub crate, for advanced uses not covered by the re-exports of this crate.
+/// Its API is not covered by the stability of this crate, hence the feature gate.
+#[cfg(feature = "unstable-pubgrub")]
+pub use pubgrub;
+
+pub mod api_diff;
+pub mod audit;
+#[cfg(feature = "sha2")]
+pub mod bazel;
+pub mod bench;
+pub mod budget;
+#[cfg(feature = "fs")]
+pub mod cached_solution;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod clock;
+#[cfg(feature = "fs")]
+pub mod compression;
+pub mod conflict;
 pub mod constraint;
+#[cfg(feature = "fs")]
+pub mod corpus;
+#[cfg(feature = "fs")]
+pub mod daemon;
 pub mod dependency_provider;
+pub mod diagnostics;
+#[cfg(feature = "fs")]
+pub mod doctor;
+pub mod ecosystem;
+#[cfg(feature = "fs")]
+pub mod elm_home;
+pub mod error_code;
+#[cfg(feature = "fs")]
+pub mod federation;
+pub mod fetch;
+pub mod footprint;
+pub mod graph;
+#[cfg(feature = "fs")]
+pub mod health;
+pub mod hints;
+pub mod imports;
+pub mod infer;
+pub mod interop;
+pub mod known_good;
+pub mod license;
+pub mod lint;
+pub mod memory;
+#[cfg(feature = "fs")]
+pub mod missing;
+pub mod nix;
+pub mod optional;
+#[cfg(feature = "fs")]
+pub mod overrides;
+#[cfg(feature = "fs")]
+pub mod packed;
+pub mod paths;
+pub mod phased;
 pub mod pkg_version;
+pub mod policy;
+pub mod progress;
 pub mod project_config;
+pub mod provenance;
+#[cfg(feature = "fs")]
+pub mod race;
+pub mod range;
+pub mod registry;
+#[cfg(all(feature = "fs", feature = "sha2"))]
+pub mod registry_pin;
+pub mod registry_view;
+#[cfg(feature = "fs")]
+pub mod release_age;
+pub mod renames;
+pub mod repair;
+pub mod report;
+pub mod review;
+#[cfg(feature = "fs")]
+pub mod sandbox;
+#[cfg(feature = "schemars")]
+pub mod schema;
+pub mod scoring;
+pub mod search_trace;
+pub mod semver;
+#[cfg(all(feature = "fs", feature = "sha2"))]
+pub mod shared_cache;
+#[cfg(all(feature = "fs", feature = "sha2"))]
+pub mod solve_memo;
 pub mod solver;
+#[cfg(feature = "fs")]
+pub mod solver_config;
+pub mod source_span;
+pub mod stale_bounds;
+pub mod stats;
+pub mod telemetry;
+#[cfg(all(feature = "fs", feature = "sha2"))]
+pub mod test_runner;
+pub mod watchlist;
@@ -0,0 +1,238 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module parsing the license of published packages.
+//!
+//! The `license` of a package `elm.json` should be an SPDX license identifier,
+//! such as `BSD-3-Clause`, but some historical entries of the registry have
+//! SPDX expressions, nonstandard strings, or even structured JSON values.
+//! A [`License`] keeps these cases apart, such that license policies and reports
+//! handle them explicitly instead of failing on them,
+//! and serializes back to the exact value it was parsed from.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// License of a package.
+///
+/// ```
+/// # use elm_solve_deps::license::License;
+/// assert_eq!(License::parse("BSD-3-Clause"), License::Spdx("BSD-3-Clause".to_string()));
+/// let dual = License::parse("MIT OR Apache-2.0");
+/// assert_eq!(dual, License::Expression("MIT OR Apache-2.0".to_string()));
+/// assert_eq!(License::parse("BSD3"), License::Other("BSD3".to_string()));
+/// let structured: License = serde_json::from_str(r#"{ "type": "MIT" }"#).unwrap();
+/// assert!(!structured.is_spdx());
+/// assert_eq!(serde_json::to_string(&structured).unwrap(), r#"{"type":"MIT"}"#);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "serde_json::Value", into = "serde_json::Value")]
+pub enum License {
+    /// A single SPDX license identifier, such as `MIT` or `Apache-2.0`.
+    Spdx(String),
+    /// An SPDX license expression, combining identifiers with `AND`, `OR`, `WITH`,
+    /// and parentheses, such as `MIT OR Apache-2.0`.
+    Expression(String),
+    /// A string that is not an SPDX license identifier or expression.
+    Other(String),
+    /// A value that is not a string, such as a JSON object.
+    Structured(serde_json::Value),
+}
+
+/// SPDX identifiers of the licenses in use in the Elm ecosystem,
+/// and of the other common OSI-approved ones.
+const SPDX_IDS: &[&str] = &[
+    "0BSD",
+    "AFL-3.0",
+    "AGPL-3.0",
+    "AGPL-3.0-only",
+    "AGPL-3.0-or-later",
+    "Apache-2.0",
+    "Artistic-2.0",
+    "BlueOak-1.0.0",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "BSD-3-Clause-Clear",
+    "BSD-4-Clause",
+    "BSL-1.0",
+    "CC-BY-4.0",
+    "CC-BY-SA-4.0",
+    "CC0-1.0",
+    "ECL-2.0",
+    "EPL-1.0",
+    "EPL-2.0",
+    "EUPL-1.1",
+    "EUPL-1.2",
+    "GPL-2.0",
+    "GPL-2.0-only",
+    "GPL-2.0-or-later",
+    "GPL-3.0",
+    "GPL-3.0-only",
+    "GPL-3.0-or-later",
+    "ISC",
+    "LGPL-2.1",
+    "LGPL-2.1-only",
+    "LGPL-2.1-or-later",
+    "LGPL-3.0",
+    "LGPL-3.0-only",
+    "LGPL-3.0-or-later",
+    "LPPL-1.3c",
+    "MIT",
+    "MIT-0",
+    "MPL-1.1",
+    "MPL-2.0",
+    "MS-PL",
+    "MS-RL",
+    "MulanPSL-2.0",
+    "NCSA",
+    "OFL-1.1",
+    "OSL-3.0",
+    "PostgreSQL",
+    "UPL-1.0",
+    "Unlicense",
+    "W3C",
+    "WTFPL",
+    "Zlib",
+];
+
+impl License {
+    /// Classify a license string.
+    ///
+    /// Identifiers are recognized regardless of their case, like in the SPDX specification,
+    /// as well as `LicenseRef-` identifiers and the `+` suffix of "or later" versions.
+    pub fn parse(s: &str) -> Self {
+        let tokens = tokenize(s);
+        match tokens.as_slice() {
+            [id] if is_spdx_id(id) => Self::Spdx(s.to_string()),
+            _ if !tokens.is_empty() && is_expression(&tokens) => Self::Expression(s.to_string()),
+            _ => Self::Other(s.to_string()),
+        }
+    }
+
+    /// Whether this is an SPDX license identifier or expression.
+    pub fn is_spdx(&self) -> bool {
+        matches!(self, Self::Spdx(_) | Self::Expression(_))
+    }
+
+    /// The license string, unless it is structured.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Spdx(s) | Self::Expression(s) | Self::Other(s) => Some(s),
+            Self::Structured(_) => None,
+        }
+    }
+}
+
+/// The license string, or the compact JSON of a structured license.
+impl fmt::Display for License {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Spdx(s) | Self::Expression(s) | Self::Other(s) => f.write_str(s),
+            Self::Structured(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl From<serde_json::Value> for License {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::String(s) => Self::parse(&s),
+            value => Self::Structured(value),
+        }
+    }
+}
+
+impl From<License> for serde_json::Value {
+    fn from(license: License) -> Self {
+        match license {
+            License::Spdx(s) | License::Expression(s) | License::Other(s) => Self::String(s),
+            License::Structured(value) => value,
+        }
+    }
+}
+
+/// Split a license string into identifiers, operators and parentheses.
+fn tokenize(s: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    for word in s.split_whitespace() {
+        let mut rest = word;
+        while !rest.is_empty() {
+            let end = match rest.find(['(', ')']) {
+                Some(0) => 1,
+                Some(index) => index,
+                None => rest.len(),
+            };
+            tokens.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+    }
+    tokens
+}
+
+fn is_spdx_id(token: &str) -> bool {
+    let id = token.strip_suffix('+').unwrap_or(token);
+    let prefix = "LicenseRef-";
+    let is_ref = id.len() > prefix.len()
+        && (id.get(..prefix.len())).is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+        && has_id_syntax(id);
+    is_ref || SPDX_IDS.iter().any(|known| known.eq_ignore_ascii_case(id))
+}
+
+/// Check the syntax of an identifier, without checking it against the SPDX list,
+/// like for the exception identifiers following `WITH`.
+fn has_id_syntax(token: &str) -> bool {
+    (token.chars()).all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+}
+
+fn is_operator(token: &str, operator: &str) -> bool {
+    token == operator || token == operator.to_ascii_lowercase()
+}
+
+/// Check the grammar of an SPDX expression:
+/// `expr := term (("AND" | "OR") term)*` and `term := "(" expr ")" | id ("WITH" exception)?`.
+fn is_expression(tokens: &[&str]) -> bool {
+    let mut position = 0;
+    parse_expression(tokens, &mut position) && position == tokens.len()
+}
+
+fn parse_expression(tokens: &[&str], position: &mut usize) -> bool {
+    if !parse_term(tokens, position) {
+        return false;
+    }
+    while let Some(token) = tokens.get(*position) {
+        if !is_operator(token, "AND") && !is_operator(token, "OR") {
+            break;
+        }
+        *position += 1;
+        if !parse_term(tokens, position) {
+            return false;
+        }
+    }
+    true
+}
+
+fn parse_term(tokens: &[&str], position: &mut usize) -> bool {
+    match tokens.get(*position) {
+        Some(&"(") => {
+            *position += 1;
+            let valid = parse_expression(tokens, position);
+            let closed = tokens.get(*position) == Some(&")");
+            *position += 1;
+            valid && closed
+        }
+        Some(id) if is_spdx_id(id) => {
+            *position += 1;
+            if tokens
+                .get(*position)
+                .is_some_and(|t| is_operator(t, "WITH"))
+            {
+                *position += 1;
+                let exception = tokens.get(*position).is_some_and(|t| has_id_syntax(t));
+                *position += 1;
+                exception
+            } else {
+                true
+            }
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,295 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module linting the dependency constraints of a package `elm.json`.
+//!
+//! The constraints published by a package restrict the solutions of every project using it.
+//! An exact version, such as `1.0.2 <= v < 1.0.3`, forces all these projects
+//! to pick that version, and makes conflicts with other packages much more likely.
+//! A constraint spanning several major versions promises compatibility
+//! with breaking changes that were probably never tested.
+//! And a test dependency looser than the same regular dependency
+//! lets tests run against versions the package does not support.
+//!
+//! Each [`Lint`] comes with a suggested replacement when one can be computed,
+//! using the same helpers than the inference of constraints in [`infer`](crate::infer).
+//!
+//! Beyond constraints, [`test_dependency_leaks`] checks the sources of a package,
+//! scanned by [`imports`](crate::imports), for modules of test dependencies
+//! imported outside of tests.
+//! They compile within the package, but break every project using it,
+//! since test dependencies are not installed for consumers.
+
+use pubgrub::range::Range;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap as Map, BTreeSet};
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::constraint::Constraint;
+use crate::imports::SourceFile;
+use crate::infer::InferPolicy;
+use crate::project_config::{PackageConfig, Pkg};
+
+/// Kind of problem found in a constraint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum LintKind {
+    /// The constraint allows a single version.
+    ExactVersion,
+    /// The constraint allows versions of several major versions, or has no upper bound.
+    MultipleMajors,
+    /// The test constraint allows versions that the regular constraint excludes.
+    LooseTestConstraint,
+}
+
+/// A problem found in a dependency constraint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct Lint {
+    /// The dependency whose constraint is problematic.
+    pub pkg: Pkg,
+    /// Whether the constraint is the one of the test dependencies.
+    pub test: bool,
+    /// Kind of problem.
+    pub kind: LintKind,
+    /// The problematic constraint.
+    pub constraint: Constraint,
+    /// Suggested replacement of the constraint, if any.
+    pub suggestion: Option<Constraint>,
+}
+
+/// A module of a test dependency imported by the sources of a package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct TestDependencyLeak {
+    /// The test dependency exposing the module.
+    pub pkg: Pkg,
+    /// The imported module.
+    pub module: String,
+    /// The source file importing the module.
+    pub file: PathBuf,
+    /// Line of the import in the source file.
+    pub line: usize,
+}
+
+/// Problems found in the dependencies of a package, as printed by the `lint` command.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct LintReport {
+    /// Problems found in the dependency constraints.
+    pub lints: Vec<Lint>,
+    /// Modules of test dependencies imported by the sources.
+    pub test_dependency_leaks: Vec<TestDependencyLeak>,
+}
+
+impl fmt::Display for TestDependencyLeak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: imports {} from {}, which is only a test dependency",
+            self.file.display(),
+            self.line,
+            self.module,
+            self.pkg
+        )
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let deps = if self.test {
+            "test-dependencies"
+        } else {
+            "dependencies"
+        };
+        let problem = match self.kind {
+            LintKind::ExactVersion => "only allows a single version",
+            LintKind::MultipleMajors => "spans several major versions",
+            LintKind::LooseTestConstraint => "is looser than the regular one",
+        };
+        write!(
+            f,
+            "{} {}: \"{}\" {}",
+            deps, self.pkg, self.constraint, problem
+        )?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ", consider \"{}\"", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lint all the dependency constraints of a package,
+/// regular ones first, then test ones, each in alphabetical order.
+///
+/// ```
+/// # use elm_solve_deps::lint::{lint_package, LintKind};
+/// # use elm_solve_deps::project_config::PackageConfig;
+/// let config: PackageConfig = serde_json::from_str(
+///     r#"{
+///         "type": "package",
+///         "name": "author/pkg",
+///         "summary": "",
+///         "license": "BSD-3-Clause",
+///         "version": "1.0.0",
+///         "exposed-modules": [],
+///         "elm-version": "0.19.0 <= v < 0.20.0",
+///         "dependencies": {
+///             "elm/core": "1.0.0 <= v < 2.0.0",
+///             "elm/json": "1.1.3 <= v < 1.1.4",
+///             "elm/html": "1.0.0 <= v < 3.0.0"
+///         },
+///         "test-dependencies": {
+///             "elm/core": "1.0.0 <= v < 3.0.0"
+///         }
+///     }"#,
+/// )
+/// .unwrap();
+/// let lints = lint_package(&config);
+/// let kinds: Vec<LintKind> = lints.iter().map(|lint| lint.kind).collect();
+/// assert_eq!(
+///     kinds,
+///     [LintKind::MultipleMajors, LintKind::ExactVersion, LintKind::MultipleMajors, LintKind::LooseTestConstraint]
+/// );
+/// assert_eq!(lints[1].suggestion.as_ref().unwrap().to_string(), "1.1.3 <= v < 2.0.0");
+/// ```
+pub fn lint_package(config: &PackageConfig) -> Vec<Lint> {
+    let mut lints = lint_constraints(&config.dependencies, false);
+    lints.extend(lint_constraints(&config.test_dependencies, true));
+    for (pkg, test_constraint) in &config.test_dependencies {
+        if let Some(constraint) = config.dependencies.get(pkg) {
+            if test_constraint.0.intersection(&constraint.0) != test_constraint.0 {
+                lints.push(Lint {
+                    pkg: pkg.clone(),
+                    test: true,
+                    kind: LintKind::LooseTestConstraint,
+                    constraint: test_constraint.clone(),
+                    suggestion: Some(constraint.clone()),
+                });
+            }
+        }
+    }
+    lints
+}
+
+/// Lint each constraint on its own.
+fn lint_constraints(constraints: &Map<Pkg, Constraint>, test: bool) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    for (pkg, constraint) in constraints {
+        let low = match constraint.0.lowest_version() {
+            Some(low) => low,
+            None => continue,
+        };
+        let kind = if constraint.0 == Range::exact(low) {
+            LintKind::ExactVersion
+        } else if spans_several_majors(constraint, low) {
+            LintKind::MultipleMajors
+        } else {
+            continue;
+        };
+        lints.push(Lint {
+            pkg: pkg.clone(),
+            test,
+            kind,
+            constraint: constraint.clone(),
+            suggestion: Some(InferPolicy::Caret.constraint(low)),
+        });
+    }
+    lints
+}
+
+/// Whether a constraint allows versions above the major version of its lowest one.
+fn spans_several_majors(constraint: &Constraint, low: SemVer) -> bool {
+    let next_majors = Range::higher_than(low.bump_major());
+    constraint.0.intersection(&next_majors) != Range::none()
+}
+
+/// Find the modules of test dependencies imported by the sources of a package,
+/// given the configs of its dependencies.
+///
+/// Only test dependencies that are not also regular dependencies are checked,
+/// and modules also exposed by a regular dependency or defined by the package are skipped.
+/// Dependencies without config are ignored.
+///
+/// ```
+/// # use elm_solve_deps::imports::{parse_imports, SourceFile};
+/// # use elm_solve_deps::lint::test_dependency_leaks;
+/// # use elm_solve_deps::project_config::{PackageConfig, Pkg};
+/// # use std::collections::BTreeMap;
+/// let package = |name: &str, modules: &str, deps: &str, test_deps: &str| -> PackageConfig {
+///     serde_json::from_str(&format!(
+///         r#"{{
+///             "type": "package", "name": "{}", "summary": "", "license": "BSD-3-Clause",
+///             "version": "1.0.0", "exposed-modules": [{}], "elm-version": "0.19.0 <= v < 0.20.0",
+///             "dependencies": {{ {} }}, "test-dependencies": {{ {} }}
+///         }}"#,
+///         name, modules, deps, test_deps
+///     ))
+///     .unwrap()
+/// };
+/// let config = package(
+///     "author/pkg",
+///     r#""Pkg""#,
+///     r#""elm/json": "1.0.0 <= v < 2.0.0""#,
+///     r#""elm-explorations/test": "2.0.0 <= v < 3.0.0""#,
+/// );
+/// let mut dependencies = BTreeMap::new();
+/// dependencies.insert(Pkg::new("elm", "json"), package("elm/json", r#""Json.Decode""#, "", ""));
+/// dependencies.insert(
+///     Pkg::new("elm-explorations", "test"),
+///     package("elm-explorations/test", r#""Expect", "Test""#, "", ""),
+/// );
+/// let sources = vec![SourceFile {
+///     path: "src/Pkg.elm".into(),
+///     module: "Pkg".to_string(),
+///     imports: parse_imports("module Pkg exposing (..)\nimport Json.Decode\nimport Expect\n"),
+/// }];
+/// let leaks = test_dependency_leaks(&config, &sources, &dependencies);
+/// assert_eq!(leaks.len(), 1);
+/// assert_eq!(
+///     leaks[0].to_string(),
+///     "src/Pkg.elm:3: imports Expect from elm-explorations/test, which is only a test dependency"
+/// );
+/// ```
+pub fn test_dependency_leaks(
+    config: &PackageConfig,
+    sources: &[SourceFile],
+    dependency_configs: &Map<Pkg, PackageConfig>,
+) -> Vec<TestDependencyLeak> {
+    let exposed = |pkg: &Pkg| {
+        (dependency_configs.get(pkg).into_iter())
+            .flat_map(|dep_config| dep_config.exposed_modules.modules())
+    };
+    let mut available: BTreeSet<&String> = sources.iter().map(|source| &source.module).collect();
+    available.extend(config.dependencies.keys().flat_map(exposed));
+    let mut test_only: Map<&String, &Pkg> = Map::new();
+    for pkg in config.test_dependencies.keys() {
+        if !config.dependencies.contains_key(pkg) {
+            for module in exposed(pkg) {
+                test_only.entry(module).or_insert(pkg);
+            }
+        }
+    }
+    let mut leaks = Vec::new();
+    for source in sources {
+        for import in &source.imports {
+            if available.contains(&import.module) {
+                continue;
+            }
+            if let Some(pkg) = test_only.get(&import.module) {
+                leaks.push(TestDependencyLeak {
+                    pkg: (*pkg).clone(),
+                    module: import.module.clone(),
+                    file: source.path.clone(),
+                    line: import.line,
+                });
+            }
+        }
+    }
+    leaks
+}
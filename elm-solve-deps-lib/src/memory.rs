@@ -0,0 +1,93 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module measuring the memory allocated by the process, to enforce memory ceilings.
+//!
+//! Registry-wide solves build very large maps of package versions and ranges,
+//! and a server answering many solving requests should not be taken down by one of them.
+//! Installing the [`CountingAllocator`] as the global allocator of a program
+//! keeps track of the bytes it currently allocates, returned by [`allocated`],
+//! such that resolutions exceeding the `max_memory` of their [`Limits`](crate::budget::Limits)
+//! are interrupted with a typed error, instead of exhausting the memory of the host.
+//!
+//! The ceiling applies to the whole process, not to a single resolution,
+//! and is checked between the steps of the solver,
+//! so a resolution may exceed it by the allocations of one step before being interrupted.
+//! Without the [`CountingAllocator`], memory ceilings are not enforced.
+//!
+//! ```
+//! use elm_solve_deps::memory::{self, CountingAllocator};
+//!
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator = CountingAllocator;
+//!
+//! fn main() {
+//!     let big = vec![0_u8; 1 << 20];
+//!     assert!(memory::allocated().unwrap() >= big.len());
+//! }
+//! ```
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Bytes currently allocated through the [`CountingAllocator`].
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether the [`CountingAllocator`] allocated anything, and is thus the global allocator.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Global allocator counting the bytes allocated by the process,
+/// delegating the allocations themselves to the system allocator.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CountingAllocator;
+
+// SAFETY: allocations are delegated to the system allocator with the same arguments,
+// only counters are updated on top of it.
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record_alloc(new_size);
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
+
+fn record_alloc(size: usize) {
+    ALLOCATED.fetch_add(size, Ordering::Relaxed);
+    // Only written once, to keep the cache line shared between threads afterwards.
+    if !INSTALLED.load(Ordering::Relaxed) {
+        INSTALLED.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Bytes currently allocated by the process,
+/// or `None` if the [`CountingAllocator`] is not the global allocator.
+pub fn allocated() -> Option<usize> {
+    if INSTALLED.load(Ordering::Relaxed) {
+        Some(ALLOCATED.load(Ordering::Relaxed))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module remembering packages and versions missing on the package server.
+//!
+//! When the package server answers with a 404, for example because the versions cache
+//! is out of sync with a mirror, the same request would be made again at every run.
+//! The [`MissingCache`] records such "known missing" entries in `ELM_HOME/pubgrub`,
+//! such that they are consulted before making network requests.
+//! Entries expire after a time to live, since missing versions may be published later,
+//! and can be invalidated explicitly.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap as Map;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::clock::Clock;
+use crate::pkg_version::{CacheError, PkgVersion};
+use crate::project_config::Pkg;
+
+/// Default time to live of the entries of a [`MissingCache`], of one day.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A cache of packages and versions known to be missing on the package server.
+///
+/// ```
+/// # use elm_solve_deps::clock::FixedClock;
+/// # use elm_solve_deps::missing::MissingCache;
+/// # use elm_solve_deps::pkg_version::PkgVersion;
+/// # use std::str::FromStr;
+/// let pkg_version = PkgVersion::from_str("elm/core@9.9.9").unwrap();
+/// let mut missing = MissingCache::new();
+/// missing.record_version(&pkg_version, &FixedClock::from_unix_secs(0));
+/// assert!(missing.is_missing(&pkg_version, &FixedClock::from_unix_secs(60)));
+/// assert!(!missing.is_missing(&pkg_version, &FixedClock::from_unix_secs(2 * 24 * 60 * 60)));
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MissingCache {
+    /// Packages found missing, with when they were found missing,
+    /// in seconds since the unix epoch.
+    packages: Map<Pkg, u64>,
+    /// Package versions found missing, with when they were found missing,
+    /// in seconds since the unix epoch.
+    versions: Map<Pkg, Map<SemVer, u64>>,
+    /// How long entries are trusted.
+    #[serde(skip, default = "default_ttl")]
+    ttl: Duration,
+}
+
+fn default_ttl() -> Duration {
+    DEFAULT_TTL
+}
+
+impl Default for MissingCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MissingCache {
+    /// Initialize an empty cache, with the default time to live.
+    pub fn new() -> Self {
+        Self {
+            packages: Map::new(),
+            versions: Map::new(),
+            ttl: DEFAULT_TTL,
+        }
+    }
+
+    /// Change how long entries are trusted.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Whether the package is known to be missing, and the entry did not expire.
+    pub fn is_package_missing<C: Clock + ?Sized>(&self, pkg: &Pkg, clock: &C) -> bool {
+        (self.packages.get(pkg)).is_some_and(|found| self.is_fresh(*found, clock))
+    }
+
+    /// Whether the package version, or its whole package, is known to be missing,
+    /// and the entry did not expire.
+    pub fn is_missing<C: Clock + ?Sized>(&self, pkg_version: &PkgVersion, clock: &C) -> bool {
+        let pkg = &pkg_version.author_pkg;
+        let version_missing = (self.versions.get(pkg))
+            .and_then(|versions| versions.get(&pkg_version.version))
+            .is_some_and(|found| self.is_fresh(*found, clock));
+        version_missing || self.is_package_missing(pkg, clock)
+    }
+
+    /// Record that the package is missing.
+    pub fn record_package<C: Clock + ?Sized>(&mut self, pkg: &Pkg, clock: &C) {
+        self.packages.insert(pkg.clone(), clock.unix_secs());
+    }
+
+    /// Record that the package version is missing.
+    pub fn record_version<C: Clock + ?Sized>(&mut self, pkg_version: &PkgVersion, clock: &C) {
+        (self.versions.entry(pkg_version.author_pkg.clone()))
+            .or_default()
+            .insert(pkg_version.version, clock.unix_secs());
+    }
+
+    /// Forget the entries of a package and of all its versions.
+    /// Return whether there were any.
+    pub fn invalidate(&mut self, pkg: &Pkg) -> bool {
+        let package = self.packages.remove(pkg).is_some();
+        let versions = self.versions.remove(pkg).is_some();
+        package || versions
+    }
+
+    /// Forget all entries.
+    pub fn clear(&mut self) {
+        self.packages.clear();
+        self.versions.clear();
+    }
+
+    /// Forget expired entries.
+    pub fn prune<C: Clock + ?Sized>(&mut self, clock: &C) {
+        let now = clock.unix_secs();
+        let ttl = self.ttl.as_secs();
+        let fresh = |found: &u64| now.saturating_sub(*found) < ttl;
+        self.packages.retain(|_, found| fresh(found));
+        for versions in self.versions.values_mut() {
+            versions.retain(|_, found| fresh(found));
+        }
+        self.versions.retain(|_, versions| !versions.is_empty());
+    }
+
+    /// Number of entries, including expired ones.
+    pub fn len(&self) -> usize {
+        self.packages.len() + self.versions.values().map(|vs| vs.len()).sum::<usize>()
+    }
+
+    /// Whether there is no entry.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Load the cache from its default location.
+    pub fn load<P: AsRef<Path>>(elm_home: P) -> Result<Self, CacheError> {
+        let s = std::fs::read_to_string(Self::file_path(elm_home))?;
+        serde_json::from_str(&s).map_err(|e| e.into())
+    }
+
+    /// Save the cache to its default location.
+    pub fn save<P: AsRef<Path>>(&self, elm_home: P) -> Result<(), CacheError> {
+        let s = serde_json::to_string(self)?;
+        let file_path = Self::file_path(elm_home);
+        std::fs::create_dir_all(file_path.parent().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{}", file_path.display()),
+            )
+        })?)?;
+        std::fs::write(file_path, &s).map_err(|e| e.into())
+    }
+
+    /// Path to the file used to store the cache.
+    /// ~/.elm/pubgrub/missing.json
+    pub fn file_path<P: AsRef<Path>>(elm_home: P) -> PathBuf {
+        Pkg::pubgrub_cache_dir(elm_home).join("missing.json")
+    }
+
+    fn is_fresh<C: Clock + ?Sized>(&self, found: u64, clock: &C) -> bool {
+        clock.unix_secs().saturating_sub(found) < self.ttl.as_secs()
+    }
+}
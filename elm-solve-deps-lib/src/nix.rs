@@ -0,0 +1,376 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module exporting solutions as Nix expressions, to package elm applications with Nix.
+//!
+//! Nix builds are hermetic, so every dependency must be pinned with a hash
+//! before the build starts.
+//! Following the conventions of elm2nix, [`elm_srcs`] prints an attribute set
+//! with one entry per package of a solution, usually saved as `elm-srcs.nix`.
+//! Each entry gives the version of the package and the sha256 of the GitHub tarball of its tag,
+//! which the Nix expressions of elm2nix download with `fetchzip`.
+//! That sha256 is the hash of the unpacked tarball, as printed by `nix-prefetch-url --unpack`,
+//! so [`fetch_sources`] downloads the tarballs to compute it, and caches it.
+//!
+//! The elm compiler also refuses to build offline without its `registry.dat`,
+//! listing the existing package versions, which elm2nix generates with its `snapshot` command.
+//! [`registry_dat`] generates the same file from the versions cache.
+//!
+//! Downloading and hashing tarballs is only available with the `sha2`, `flate2` and `tar` features.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use std::collections::BTreeMap as Map;
+#[cfg(all(feature = "sha2", feature = "flate2", feature = "tar"))]
+use std::io;
+#[cfg(all(feature = "fs", feature = "sha2", feature = "flate2", feature = "tar"))]
+use std::path::Path;
+
+#[cfg(all(feature = "sha2", feature = "flate2", feature = "tar"))]
+use sha2::{Digest, Sha256};
+
+#[cfg(all(feature = "fs", feature = "sha2", feature = "flate2", feature = "tar"))]
+use crate::fetch::HttpFetch;
+use crate::pkg_version::Cache;
+#[cfg(all(feature = "fs", feature = "sha2", feature = "flate2", feature = "tar"))]
+use crate::pkg_version::CacheError;
+#[cfg(all(feature = "fs", feature = "sha2", feature = "flate2", feature = "tar"))]
+use crate::project_config::AppDependencies;
+use crate::project_config::Pkg;
+
+/// Pinned source of a package version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NixSource {
+    /// Version of the package.
+    pub version: SemVer,
+    /// Sha256 of the unpacked GitHub tarball of the package version, in the base32 of Nix.
+    pub sha256: String,
+}
+
+/// Print the sources of packages as a Nix attribute set, in the format of the `elm-srcs.nix` of elm2nix.
+///
+/// ```
+/// # use elm_solve_deps::nix::{elm_srcs, NixSource};
+/// # use elm_solve_deps::project_config::Pkg;
+/// # use elm_solve_deps::semver::SemVer;
+/// let core = NixSource {
+///     version: SemVer::new(1, 0, 5),
+///     sha256: "0jnfkbsi1gh74szci41k2ax0ywaxslmmq4grrgmnn8biw99gzipq".to_string(),
+/// };
+/// let sources = [(Pkg::new("elm", "core"), core)].into_iter().collect();
+/// assert_eq!(
+///     elm_srcs(&sources),
+///     r#"{
+///
+///   "elm/core" = {
+///     sha256 = "0jnfkbsi1gh74szci41k2ax0ywaxslmmq4grrgmnn8biw99gzipq";
+///     version = "1.0.5";
+///   };
+/// }
+/// "#
+/// );
+/// ```
+pub fn elm_srcs(sources: &Map<Pkg, NixSource>) -> String {
+    let mut nix = String::from("{\n");
+    for (pkg, source) in sources {
+        nix.push_str(&format!(
+            "\n  {} = {{\n    sha256 = {};\n    version = {};\n  }};\n",
+            nix_string(&pkg.to_string()),
+            nix_string(&source.sha256),
+            nix_string(&source.version.to_string()),
+        ));
+    }
+    nix.push_str("}\n");
+    nix
+}
+
+/// Quote a string for Nix, escaping the characters with a special meaning in Nix strings.
+fn nix_string(s: &str) -> String {
+    let escaped = (s.replace('\\', "\\\\"))
+        .replace('"', "\\\"")
+        .replace("${", "\\${");
+    format!("\"{}\"", escaped)
+}
+
+/// Url of the GitHub tarball of a package version, the one downloaded by elm2nix.
+pub fn tarball_url(pkg: &Pkg, version: SemVer) -> String {
+    format!(
+        "https://github.com/{}/{}/archive/{}.tar.gz",
+        pkg.author, pkg.pkg, version
+    )
+}
+
+/// Encode a hash in the base32 of Nix, which uses its own alphabet,
+/// and starts with the last bits of the hash.
+///
+/// ```
+/// # use elm_solve_deps::nix::nix_base32;
+/// // sha256 of the empty string.
+/// let sha256 = [
+///     0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f, 0xb9, 0x24,
+///     0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b, 0x78, 0x52, 0xb8, 0x55,
+/// ];
+/// assert_eq!(nix_base32(&sha256), "0mdqa9w1p6cmli6976v4wi0sw9r4p5prkj7lzfd1877wk11c9c73");
+/// ```
+pub fn nix_base32(hash: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"0123456789abcdfghijklmnpqrsvwxyz";
+    let len = (hash.len() * 8).saturating_sub(1) / 5 + 1;
+    (0..len)
+        .rev()
+        .map(|n| {
+            let (i, j) = (n * 5 / 8, n * 5 % 8);
+            let low = u16::from(hash[i]) >> j;
+            let high = hash.get(i + 1).map_or(0, |h| u16::from(*h) << (8 - j));
+            ALPHABET[usize::from((low | high) & 0x1f)] as char
+        })
+        .collect()
+}
+
+/// Sha256 of the content of a gzipped tarball, unpacked like `fetchzip` does,
+/// in the base32 of Nix.
+///
+/// The tarball must contain a single top directory, which is stripped,
+/// and the hash is the one of the serialization of the remaining files as a Nix archive (NAR),
+/// which only keeps the names, contents, executable bits and symlinks targets of files.
+#[cfg(all(feature = "sha2", feature = "flate2", feature = "tar"))]
+pub fn unpacked_sha256(tarball: &[u8]) -> io::Result<String> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut root = NarNode::Directory(Map::new());
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(tarball));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let node = match entry.header().entry_type() {
+            tar::EntryType::Regular | tar::EntryType::Continuous => {
+                let executable = entry.header().mode()? & 0o100 != 0;
+                let mut contents = Vec::new();
+                io::Read::read_to_end(&mut entry, &mut contents)?;
+                NarNode::File {
+                    executable,
+                    contents,
+                }
+            }
+            tar::EntryType::Directory => NarNode::Directory(Map::new()),
+            tar::EntryType::Symlink => {
+                let target = entry
+                    .link_name()?
+                    .ok_or_else(|| invalid("symlink without target"))?;
+                let target = target
+                    .to_str()
+                    .ok_or_else(|| invalid("non UTF-8 symlink"))?;
+                NarNode::Symlink(target.to_string())
+            }
+            // Comments of the archive, such as the commit of GitHub tarballs.
+            tar::EntryType::XGlobalHeader => continue,
+            _ => return Err(invalid("unsupported entry type in the tarball")),
+        };
+        let path = entry.path()?;
+        let components: Vec<&str> = (path.components())
+            .filter_map(|c| match c {
+                std::path::Component::Normal(name) => Some(name.to_str()),
+                _ => None,
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(|| invalid("non UTF-8 path in the tarball"))?;
+        root.insert(&components, node)
+            .ok_or_else(|| invalid("conflicting paths in the tarball"))?;
+    }
+    let top = match root {
+        NarNode::Directory(entries) if entries.len() == 1 => entries.into_values().next(),
+        _ => None,
+    };
+    match top {
+        Some(top @ NarNode::Directory(_)) => {
+            let mut hasher = Sha256::new();
+            nar_string(&mut hasher, b"nix-archive-1");
+            top.serialize(&mut hasher);
+            Ok(nix_base32(&hasher.finalize()))
+        }
+        _ => Err(invalid(
+            "the tarball does not contain a single top directory",
+        )),
+    }
+}
+
+/// File system object serialized in a Nix archive.
+#[cfg(all(feature = "sha2", feature = "flate2", feature = "tar"))]
+enum NarNode {
+    File { executable: bool, contents: Vec<u8> },
+    Symlink(String),
+    Directory(Map<String, NarNode>),
+}
+
+#[cfg(all(feature = "sha2", feature = "flate2", feature = "tar"))]
+impl NarNode {
+    /// Insert a node at a path of this directory, creating the missing directories.
+    /// Directories listed after their content are kept.
+    /// Returns `None` if a parent of the path is not a directory.
+    fn insert(&mut self, path: &[&str], node: NarNode) -> Option<()> {
+        let entries = match self {
+            NarNode::Directory(entries) => entries,
+            _ => return None,
+        };
+        match path {
+            [] => Some(()),
+            [name] => {
+                let existing = entries.get(*name);
+                if !matches!(
+                    (existing, &node),
+                    (Some(NarNode::Directory(_)), NarNode::Directory(_))
+                ) {
+                    entries.insert(name.to_string(), node);
+                }
+                Some(())
+            }
+            [name, rest @ ..] => (entries.entry(name.to_string()))
+                .or_insert_with(|| NarNode::Directory(Map::new()))
+                .insert(rest, node),
+        }
+    }
+
+    /// Serialize this node in the format of Nix archives,
+    /// with the entries of directories sorted by name.
+    fn serialize(&self, hasher: &mut Sha256) {
+        nar_string(hasher, b"(");
+        nar_string(hasher, b"type");
+        match self {
+            NarNode::File {
+                executable,
+                contents,
+            } => {
+                nar_string(hasher, b"regular");
+                if *executable {
+                    nar_string(hasher, b"executable");
+                    nar_string(hasher, b"");
+                }
+                nar_string(hasher, b"contents");
+                nar_string(hasher, contents);
+            }
+            NarNode::Symlink(target) => {
+                nar_string(hasher, b"symlink");
+                nar_string(hasher, b"target");
+                nar_string(hasher, target.as_bytes());
+            }
+            NarNode::Directory(entries) => {
+                nar_string(hasher, b"directory");
+                for (name, node) in entries {
+                    nar_string(hasher, b"entry");
+                    nar_string(hasher, b"(");
+                    nar_string(hasher, b"name");
+                    nar_string(hasher, name.as_bytes());
+                    nar_string(hasher, b"node");
+                    node.serialize(hasher);
+                    nar_string(hasher, b")");
+                }
+            }
+        }
+        nar_string(hasher, b")");
+    }
+}
+
+/// Serialize a string of a Nix archive: its length, its bytes, and a padding to 8 bytes.
+#[cfg(all(feature = "sha2", feature = "flate2", feature = "tar"))]
+fn nar_string(hasher: &mut Sha256, s: &[u8]) {
+    hasher.update((s.len() as u64).to_le_bytes());
+    hasher.update(s);
+    hasher.update(&[0; 8][..(8 - s.len() % 8) % 8]);
+}
+
+/// Retrieve the sources of all the packages of a solution,
+/// from the dependency solver cache if available,
+/// otherwise by downloading and hashing their GitHub tarballs.
+#[cfg(all(feature = "fs", feature = "sha2", feature = "flate2", feature = "tar"))]
+pub fn fetch_sources<P, H>(
+    solution: &AppDependencies,
+    elm_home: P,
+    http_fetch: &H,
+) -> Result<Map<Pkg, NixSource>, CacheError>
+where
+    P: AsRef<Path>,
+    H: HttpFetch + ?Sized,
+{
+    (solution.direct.iter())
+        .chain(solution.indirect.iter())
+        .map(|(pkg, version)| {
+            let cache_dir = pkg
+                .pubgrub_cache_dir_json(&elm_home)
+                .join(version.to_string());
+            let cache_path = cache_dir.join("nix.sha256");
+            let sha256 = match std::fs::read_to_string(&cache_path) {
+                Ok(sha256) => sha256,
+                Err(_) => {
+                    let url = tarball_url(pkg, *version);
+                    let tarball = (http_fetch.fetch_bytes(&url))
+                        .map_err(|source| CacheError::FetchError { url, source })?;
+                    let sha256 = unpacked_sha256(&tarball)?;
+                    std::fs::create_dir_all(&cache_dir)?;
+                    std::fs::write(&cache_path, &sha256)?;
+                    sha256
+                }
+            };
+            let source = NixSource {
+                version: *version,
+                sha256,
+            };
+            Ok((pkg.clone(), source))
+        })
+        .collect()
+}
+
+/// Generate the `registry.dat` of the elm compiler, listing the package versions of the versions cache,
+/// in the binary format of the elm compiler.
+///
+/// It starts with the number of package versions, followed by every package,
+/// by increasing author and name, with its newest version and its other versions, newest first.
+///
+/// ```
+/// # use elm_solve_deps::nix::registry_dat;
+/// # use elm_solve_deps::pkg_version::Cache;
+/// # use elm_solve_deps::project_config::Pkg;
+/// let mut cache = Cache::new();
+/// let versions = [(1, 0, 4).into(), (1, 0, 5).into()].into_iter().collect();
+/// cache.cache.insert(Pkg::new("elm", "core"), versions);
+/// let dat = registry_dat(&cache);
+/// assert_eq!(dat[..8], 2_u64.to_be_bytes());
+/// assert_eq!(dat[8..16], 1_u64.to_be_bytes());
+/// assert_eq!(dat[16..], *b"\x03elm\x04core\x01\x00\x05\0\0\0\0\0\0\0\x01\x01\x00\x04");
+/// ```
+pub fn registry_dat(versions: &Cache) -> Vec<u8> {
+    let packages: Vec<_> = (versions.cache.iter())
+        .filter(|(_, versions)| !versions.is_empty())
+        .collect();
+    let mut dat = Vec::new();
+    dat.extend((versions.versions_count() as u64).to_be_bytes());
+    dat.extend((packages.len() as u64).to_be_bytes());
+    for (pkg, versions) in packages {
+        for name in [&pkg.author, &pkg.pkg] {
+            // Names are shorter than 256 bytes on the package server.
+            dat.push(name.len() as u8);
+            dat.extend(name.as_bytes());
+        }
+        let mut newest_first = versions.iter().rev();
+        if let Some(newest) = newest_first.next() {
+            push_version(&mut dat, *newest);
+        }
+        dat.extend(((versions.len() - 1) as u64).to_be_bytes());
+        newest_first.for_each(|version| push_version(&mut dat, *version));
+    }
+    dat
+}
+
+/// Serialize a version in the binary format of the elm compiler:
+/// one byte per number, unless one of them does not fit,
+/// in which case a 255 byte is followed by two bytes per number.
+fn push_version(dat: &mut Vec<u8>, version: SemVer) {
+    let (major, minor, patch) = version.into();
+    match (
+        u8::try_from(major),
+        u8::try_from(minor),
+        u8::try_from(patch),
+    ) {
+        (Ok(major), Ok(minor), Ok(patch)) if major < 255 => dat.extend([major, minor, patch]),
+        _ => {
+            dat.push(255);
+            for number in [major, minor, patch] {
+                dat.extend((number as u16).to_be_bytes());
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module solving dependencies with optional additional constraints.
+//!
+//! Tools sometimes want to include a package if some version of it fits,
+//! and silently skip it otherwise, like an elm-review package added to a project.
+//! An [`OptionalExtra`] is such an "if available" constraint.
+//! The project and its required additional constraints are solved first,
+//! then secondary resolutions tell which optional extras can be added on top of them,
+//! and the [`OptionalSolution`] reports which ones made it into the final solution.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::constraint::Constraint;
+use crate::error_code::ErrorCode;
+use crate::project_config::{AppDependencies, Pkg, ProjectConfig};
+use crate::solver::ExtraMode;
+
+/// An additional constraint, only included in the solution
+/// if it can be satisfied together with the other dependencies.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct OptionalExtra {
+    /// The package to include.
+    pub pkg: Pkg,
+    /// The versions of that package which are acceptable.
+    pub constraint: Constraint,
+}
+
+/// Solution of a resolution with optional extras.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct OptionalSolution {
+    /// The final solution.
+    pub solution: AppDependencies,
+    /// Optional extras included in the solution.
+    pub included: Vec<OptionalExtra>,
+    /// Optional extras skipped, because no version solves with the rest.
+    pub skipped: Vec<OptionalExtra>,
+}
+
+impl fmt::Display for OptionalExtra {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.pkg, self.constraint)
+    }
+}
+
+/// Solve the dependencies of a project, with the optional extras that can be satisfied.
+///
+/// The `solve` argument is typically the `solve_deps` method of one of the solvers,
+/// such as [`Offline::solve_deps`](crate::solver::Offline::solve_deps),
+/// and `code_of` gives the [`ErrorCode`] of its errors, such as [`Coded::code`](crate::error_code::Coded::code).
+/// Optional extras are combined with the project dependencies like the required ones,
+/// according to `extra_mode`.
+///
+/// The project with its required additional constraints must solve,
+/// otherwise the error of that first resolution is returned.
+/// A secondary resolution then tries all optional extras at once.
+/// If it has no solution, optional extras are tried one at a time, in the given order,
+/// each one kept if it solves together with the ones kept before it.
+/// Only optional extras without solution are skipped,
+/// other failures of secondary resolutions, such as network or IO errors, are returned.
+pub fn solve_with_optional_extras<Solve, Code, E>(
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+    optional_extras: &[OptionalExtra],
+    solve: Solve,
+    code_of: Code,
+) -> Result<OptionalSolution, E>
+where
+    Solve: Fn(&ProjectConfig, bool, &[(Pkg, Constraint)], ExtraMode) -> Result<AppDependencies, E>,
+    Code: Fn(&E) -> ErrorCode,
+{
+    let solution = solve(
+        project_elm_json,
+        use_test,
+        additional_constraints,
+        extra_mode,
+    )?;
+    let solve_with = |included: &[OptionalExtra]| {
+        let optional = included
+            .iter()
+            .map(|o| (o.pkg.clone(), o.constraint.clone()));
+        let constraints: Vec<(Pkg, Constraint)> = additional_constraints
+            .iter()
+            .cloned()
+            .chain(optional)
+            .collect();
+        match solve(project_elm_json, use_test, &constraints, extra_mode) {
+            Ok(solution) => Ok(Some(solution)),
+            Err(err) if code_of(&err) == ErrorCode::NoSolution => Ok(None),
+            Err(err) => Err(err),
+        }
+    };
+    let mut outcome = OptionalSolution {
+        solution,
+        included: Vec::new(),
+        skipped: Vec::new(),
+    };
+    if optional_extras.is_empty() {
+        return Ok(outcome);
+    }
+
+    // Most of the time, all optional extras fit.
+    if let Some(solution) = solve_with(optional_extras)? {
+        outcome.solution = solution;
+        outcome.included = optional_extras.to_vec();
+        return Ok(outcome);
+    }
+
+    // Otherwise, keep them one at a time.
+    for extra in optional_extras {
+        outcome.included.push(extra.clone());
+        match solve_with(&outcome.included)? {
+            Some(solution) => outcome.solution = solution,
+            None => {
+                outcome.included.pop();
+                outcome.skipped.push(extra.clone());
+            }
+        }
+    }
+    Ok(outcome)
+}
@@ -0,0 +1,220 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module overriding packages with unreleased versions, from git repositories or local paths.
+//!
+//! Testing an upstream fix before it is published requires resolving the dependencies
+//! of a project with that fix, as if it was released.
+//! A [`PackageOverride`] points a package at a git repository and a ref,
+//! shallow-fetched into `ELM_HOME`, or at a directory already cloned.
+//! Its `elm.json` replaces the published ones of the package:
+//! the version it declares is the only candidate of the package,
+//! and its dependencies are resolved with the rest of the graph.
+//!
+//! An [`Offline`](crate::solver::Offline) solver configured with
+//! [`Offline::with_overrides`](crate::solver::Offline::with_overrides),
+//! or an online solver built on top of it, records overridden package versions
+//! with an [`Override`](crate::provenance::Source::Override) source in its provenance.
+//!
+//! Overrides are written `author/package=git+REPOSITORY#REF` for git repositories,
+//! and `author/package=PATH` for local paths, which may contain `#`.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::project_config::{PackageConfig, Pkg};
+
+/// Where the `elm.json` of an overridden package is read from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverrideSource {
+    /// A git repository, shallow-fetched at a ref.
+    Git {
+        /// Url of the repository.
+        repository: String,
+        /// Branch, tag, or commit to fetch.
+        reference: String,
+    },
+    /// A directory, typically a clone of the package repository.
+    Path(PathBuf),
+}
+
+/// A package overridden by an unreleased version.
+///
+/// ```
+/// # use elm_solve_deps::overrides::{OverrideSource, PackageOverride};
+/// # use elm_solve_deps::project_config::Pkg;
+/// # use std::str::FromStr;
+/// let fix =
+///     PackageOverride::from_str("elm/parser=git+https://github.com/elm/parser#fix-chompUntil")
+///         .unwrap();
+/// assert_eq!(fix.pkg, Pkg::new("elm", "parser"));
+/// assert_eq!(
+///     fix.source,
+///     OverrideSource::Git {
+///         repository: "https://github.com/elm/parser".to_string(),
+///         reference: "fix-chompUntil".to_string(),
+///     }
+/// );
+/// let local = PackageOverride::from_str("elm/parser=../parser#2").unwrap();
+/// assert_eq!(local.source, OverrideSource::Path("../parser#2".into()));
+/// assert!(PackageOverride::from_str("elm/parser=git+https://github.com/elm/parser").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackageOverride {
+    /// The overridden package.
+    pub pkg: Pkg,
+    /// Where its `elm.json` is read from.
+    pub source: OverrideSource,
+}
+
+/// Error parsing a [`PackageOverride`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid override `{0}`, expected PKG=git+REPOSITORY#REF or PKG=PATH")]
+pub struct OverrideParseError(pub String);
+
+/// Error loading the `elm.json` of an overridden package.
+#[derive(Error, Debug)]
+pub enum OverrideError {
+    /// Failure to read the `elm.json`, or to run git.
+    #[error("unable to read the overridden package")]
+    FileIoError(#[from] std::io::Error),
+
+    /// Failure to parse the `elm.json`.
+    #[error("failed to parse the elm.json of the overridden package")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Failure of a git command.
+    #[error("failed to fetch {reference} of {repository}: {message}")]
+    GitError {
+        /// Url of the repository.
+        repository: String,
+        /// The fetched ref.
+        reference: String,
+        /// What git reported.
+        message: String,
+    },
+
+    /// The `elm.json` is the one of another package.
+    #[error("the override of {expected} is the package {found}")]
+    NameMismatch {
+        /// The overridden package.
+        expected: Pkg,
+        /// The package of the `elm.json`.
+        found: Pkg,
+    },
+}
+
+impl fmt::Display for OverrideSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Git {
+                repository,
+                reference,
+            } => write!(f, "git+{}#{}", repository, reference),
+            Self::Path(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl fmt::Display for PackageOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.pkg, self.source)
+    }
+}
+
+impl FromStr for PackageOverride {
+    type Err = OverrideParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || OverrideParseError(s.to_string());
+        let (pkg, source) = s.split_once('=').ok_or_else(err)?;
+        let pkg = Pkg::from_str(pkg).map_err(|_| err())?;
+        let source = match source.strip_prefix("git+") {
+            Some(git) => match git.rsplit_once('#') {
+                Some((repository, reference))
+                    if !repository.is_empty() && !reference.is_empty() =>
+                {
+                    OverrideSource::Git {
+                        repository: repository.to_string(),
+                        reference: reference.to_string(),
+                    }
+                }
+                _ => return Err(err()),
+            },
+            None if source.is_empty() => return Err(err()),
+            None => OverrideSource::Path(source.into()),
+        };
+        Ok(Self { pkg, source })
+    }
+}
+
+impl PackageOverride {
+    /// Directory where the git repository of an overridden package is fetched.
+    /// ~/.elm/pubgrub/overrides/author/package/
+    pub fn checkout_dir<P: AsRef<Path>>(elm_home: P, pkg: &Pkg) -> PathBuf {
+        Pkg::pubgrub_cache_dir(elm_home)
+            .join("overrides")
+            .join(&pkg.author)
+            .join(&pkg.pkg)
+    }
+
+    /// Directory containing the overridden package,
+    /// after shallow-fetching its ref for git repositories.
+    ///
+    /// Refs are fetched again every time, since branches move.
+    pub fn checkout<P: AsRef<Path>>(&self, elm_home: P) -> Result<PathBuf, OverrideError> {
+        let (repository, reference) = match &self.source {
+            OverrideSource::Path(path) => return Ok(path.clone()),
+            OverrideSource::Git {
+                repository,
+                reference,
+            } => (repository, reference),
+        };
+        let dir = Self::checkout_dir(elm_home, &self.pkg);
+        std::fs::create_dir_all(&dir)?;
+        let git = |args: &[&str]| -> Result<(), OverrideError> {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(&dir)
+                .args(args)
+                .output()?;
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(OverrideError::GitError {
+                    repository: repository.clone(),
+                    reference: reference.clone(),
+                    message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                })
+            }
+        };
+        if !dir.join(".git").exists() {
+            git(&["init", "--quiet"])?;
+        }
+        // The repository and ref are given by the user, so they must never be read as options.
+        git(&[
+            "fetch", "--quiet", "--depth", "1", "--", repository, reference,
+        ])?;
+        git(&["checkout", "--quiet", "--force", "FETCH_HEAD"])?;
+        Ok(dir)
+    }
+
+    /// Load the `elm.json` of the overridden package, fetching it first if needed.
+    pub fn load<P: AsRef<Path>>(&self, elm_home: P) -> Result<PackageConfig, OverrideError> {
+        let dir = self.checkout(elm_home)?;
+        let s = std::fs::read_to_string(dir.join("elm.json"))?;
+        let config: PackageConfig = serde_json::from_str(&s)?;
+        if config.name != self.pkg {
+            return Err(OverrideError::NameMismatch {
+                expected: self.pkg.clone(),
+                found: config.name,
+            });
+        }
+        Ok(config)
+    }
+}
@@ -0,0 +1,253 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module packing the cached package configs of the dependency solver into a single file.
+//!
+//! The dependency solver cache stores the `elm.json` of every package version in its own file
+//! of `ELM_HOME/pubgrub/elm_json_cache/`, which wastes inodes, and makes cold solves slow
+//! on filesystems with slow metadata operations, such as the ones of many CI machines.
+//! A [`PackedStore`] appends all configs to a single data file, `elm_json_cache.pack`,
+//! and records where each one is in an index file, `elm_json_cache.idx`,
+//! with one `author/package@version offset length` line per config.
+//!
+//! Both files are only ever appended to, and the last entry of a package version wins.
+//! An interrupted write thus leaves at worst some unreachable data,
+//! or a truncated index line, which is ignored since it misses its line ending,
+//! and the next entry starts on a new line.
+//! Appends lock the data file, such that concurrent [`pack`] runs never interleave entries.
+//!
+//! Solvers look for configs in their own files before the packed store,
+//! such that configs fetched again since the last [`pack`] win over the packed ones.
+//! Packed configs keep the [`Compression`](crate::compression::Compression)
+//! they had in their own files.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use pubgrub::version::SemanticVersion as SemVer;
+
+use crate::compression;
+use crate::paths;
+use crate::pkg_version::{sub_dirs, PkgVersion, PkgVersionError};
+use crate::project_config::{PackageConfig, Pkg};
+
+/// Package configs packed into a single data file, with an index of their locations.
+#[derive(Debug)]
+pub struct PackedStore {
+    data_path: PathBuf,
+    index_path: PathBuf,
+    data: Option<File>,
+    index: BTreeMap<PkgVersion, (u64, u64)>,
+}
+
+/// Summary of a [`pack`] migration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PackReport {
+    /// Number of configs moved into the packed store.
+    pub packed: usize,
+    /// Number of corrupted configs left in their own files.
+    pub skipped: usize,
+    /// Total size of the packed configs, in bytes.
+    pub bytes: u64,
+}
+
+impl PackedStore {
+    /// Open the packed store of an `ELM_HOME`, empty if it does not exist yet.
+    pub fn open<P: AsRef<Path>>(elm_home: P) -> io::Result<Self> {
+        let pubgrub_dir = Pkg::pubgrub_cache_dir(elm_home);
+        let data_path = pubgrub_dir.join("elm_json_cache.pack");
+        let index_path = pubgrub_dir.join("elm_json_cache.idx");
+        let index = match fs::read_to_string(&index_path) {
+            Ok(index_str) => parse_index(&index_str),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err),
+        };
+        let data = match File::open(&data_path) {
+            Ok(data) => Some(data),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        };
+        Ok(Self {
+            data_path,
+            index_path,
+            data,
+            index,
+        })
+    }
+
+    /// Path to the data file, holding the packed configs.
+    pub fn data_path(&self) -> &Path {
+        &self.data_path
+    }
+
+    /// Path to the index file, holding the location of each config in the data file.
+    pub fn index_path(&self) -> &Path {
+        &self.index_path
+    }
+
+    /// Number of packed configs.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Whether no config is packed.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Whether the config of a package version is packed.
+    pub fn contains(&self, pkg_version: &PkgVersion) -> bool {
+        self.index.contains_key(pkg_version)
+    }
+
+    /// Packed package versions, in increasing order.
+    pub fn versions(&self) -> impl Iterator<Item = &PkgVersion> {
+        self.index.keys()
+    }
+
+    /// Read the packed content of the `elm.json` of a package version.
+    pub fn read(&self, pkg_version: &PkgVersion) -> io::Result<String> {
+        let not_packed = || {
+            let msg = format!("{} is not in the packed store", pkg_version);
+            io::Error::new(io::ErrorKind::NotFound, msg)
+        };
+        let (offset, length) = self.index.get(pkg_version).ok_or_else(not_packed)?;
+        let mut data = self.data.as_ref().ok_or_else(not_packed)?;
+        data.seek(SeekFrom::Start(*offset))?;
+        let mut bytes = vec![0; *length as usize];
+        data.read_exact(&mut bytes)?;
+        compression::decode(bytes)
+    }
+
+    /// Load the packed `elm.json` config of a package version.
+    pub fn load_config(&self, pkg_version: &PkgVersion) -> Result<PackageConfig, PkgVersionError> {
+        let config_str = self.read(pkg_version)?;
+        let config = serde_json::from_str(&config_str)?;
+        Ok(config)
+    }
+
+    /// Append the content of the `elm.json` of a package version,
+    /// replacing the one previously packed, if any.
+    pub fn append(&mut self, pkg_version: &PkgVersion, content: &[u8]) -> io::Result<()> {
+        if let Some(parent) = self.data_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut data = (OpenOptions::new().create(true).append(true)).open(&self.data_path)?;
+        // Held until the index line is written, such that other processes appending
+        // in the meantime cannot move the end of the data file, nor interleave index lines.
+        data.lock()?;
+        let offset = data.metadata()?.len();
+        data.write_all(content)?;
+        let mut line = format!("{} {} {}\n", pkg_version, offset, content.len());
+        let mut index =
+            (OpenOptions::new().create(true).read(true).append(true)).open(&self.index_path)?;
+        if !ends_with_newline(&mut index)? {
+            line.insert(0, '\n');
+        }
+        index.write_all(line.as_bytes())?;
+        (self.index).insert(pkg_version.clone(), (offset, content.len() as u64));
+        if self.data.is_none() {
+            self.data = Some(File::open(&self.data_path)?);
+        }
+        Ok(())
+    }
+
+    /// Flush the data and index files to disk,
+    /// such that the configs appended so far survive a crash.
+    pub fn sync(&self) -> io::Result<()> {
+        for path in [&self.data_path, &self.index_path] {
+            // Opened for writing, which some platforms require to flush a file.
+            match OpenOptions::new().append(true).open(path) {
+                Ok(file) => file.sync_all()?,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether a file is empty or ends with a line ending.
+fn ends_with_newline(file: &mut File) -> io::Result<bool> {
+    if file.metadata()?.len() == 0 {
+        return Ok(true);
+    }
+    file.seek(SeekFrom::End(-1))?;
+    let mut last = [0];
+    file.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
+/// Parse the lines of an index file, skipping the invalid ones,
+/// and the last one if it misses its line ending, since its write was interrupted.
+fn parse_index(index_str: &str) -> BTreeMap<PkgVersion, (u64, u64)> {
+    let parse_line = |line: &str| {
+        let mut parts = line.split(' ');
+        let pkg_version = PkgVersion::from_str(parts.next()?).ok()?;
+        let offset = parts.next()?.parse().ok()?;
+        let length = parts.next()?.parse().ok()?;
+        parts
+            .next()
+            .is_none()
+            .then_some((pkg_version, (offset, length)))
+    };
+    (index_str.split_inclusive('\n'))
+        .filter_map(|line| parse_line(line.strip_suffix('\n')?))
+        .collect()
+}
+
+/// Move the cached package configs of an `ELM_HOME` from their own files into its packed store.
+///
+/// Corrupted configs are left in their own files, to be reported by the doctor.
+/// The other ones are only removed once the packed store is synced to disk.
+pub fn pack<P: AsRef<Path>>(elm_home: P) -> io::Result<PackReport> {
+    let cache_dir = paths::elm_json_cache_dir(&elm_home);
+    if !cache_dir.is_dir() {
+        return Ok(PackReport::default());
+    }
+    let mut store = PackedStore::open(&elm_home)?;
+    let mut report = PackReport::default();
+    let mut packed_paths = Vec::new();
+    for (author, author_dir) in sub_dirs(&cache_dir)? {
+        for (pkg, pkg_dir) in sub_dirs(&author_dir)? {
+            for (version, version_dir) in sub_dirs(&pkg_dir)? {
+                let pkg_version = match SemVer::from_str(&version) {
+                    Ok(version) => PkgVersion {
+                        author_pkg: Pkg::new(&author, &pkg),
+                        version,
+                    },
+                    Err(_) => continue,
+                };
+                let path = version_dir.join("elm.json");
+                let content = match fs::read(&path) {
+                    Ok(content) => content,
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                    Err(err) => return Err(err),
+                };
+                let valid = compression::decode(content.clone())
+                    .is_ok_and(|s| serde_json::from_str::<PackageConfig>(&s).is_ok());
+                if !valid {
+                    report.skipped += 1;
+                    continue;
+                }
+                store.append(&pkg_version, &content)?;
+                packed_paths.push((path, version_dir));
+                report.packed += 1;
+                report.bytes += content.len() as u64;
+            }
+        }
+    }
+    store.sync()?;
+    for (path, version_dir) in packed_paths {
+        // Already removed by a concurrent run, which packed it too.
+        match fs::remove_file(&path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+        // Only removed if empty, endpoints are still cached in their own files.
+        let _ = fs::remove_dir(&version_dir);
+    }
+    Ok(report)
+}
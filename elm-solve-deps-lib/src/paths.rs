@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module building the paths of installed packages and caches inside `ELM_HOME`.
+//!
+//! The dependency solver cache nests package configs several directories deep,
+//! such as `ELM_HOME/pubgrub/elm_json_cache/author/package/1.0.0/elm.json`.
+//! On Windows, with a deep `ELM_HOME`, these paths exceed the historical limit
+//! of 260 characters, unless they use the `\\?\` long path syntax,
+//! which also applies to network shares, written `\\?\UNC\server\share`.
+//! All directories inside `ELM_HOME` are built by this module,
+//! from an `ELM_HOME` path first normalized by [`normalize`],
+//! and other modules only name their files inside these directories.
+//!
+//! Filesystems of Windows and macOS are case-insensitive by default,
+//! such that the directory of `noredink/elm-json` would also be found
+//! when only `NoRedInk/elm-json` is installed.
+//! [`installed_package_dir`] only finds directories whose names match exactly.
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::project_config::Pkg;
+
+/// Whether the default filesystem of the target platform is case-insensitive.
+pub const CASE_INSENSITIVE_FS: bool = cfg!(any(windows, target_os = "macos"));
+
+/// Normalize a path before building other paths on top of it.
+///
+/// `.` components are removed, and on Windows, absolute paths
+/// are converted to the long path syntax with [`windows_long_path`].
+///
+/// ```
+/// # use elm_solve_deps::paths::normalize;
+/// # use std::path::PathBuf;
+/// assert_eq!(normalize("./elm-home/./0.19.1"), PathBuf::from("elm-home").join("0.19.1"));
+/// ```
+pub fn normalize<P: AsRef<Path>>(path: P) -> PathBuf {
+    let path = path.as_ref();
+    #[cfg(windows)]
+    if let Some(absolute) = path.to_str().filter(|_| path.is_absolute()) {
+        return PathBuf::from(windows_long_path(absolute));
+    }
+    (path.components())
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+/// Convert an absolute Windows path to the long path syntax,
+/// resolving `.` and `..` components, which are not allowed in long paths.
+///
+/// Paths already using the long path syntax, and relative paths, are returned unchanged.
+///
+/// ```
+/// # use elm_solve_deps::paths::windows_long_path;
+/// assert_eq!(windows_long_path(r"C:\Users\elm\AppData\Roaming\elm"), r"\\?\C:\Users\elm\AppData\Roaming\elm");
+/// assert_eq!(windows_long_path("C:/Users/elm/../ci/./elm"), r"\\?\C:\Users\ci\elm");
+/// assert_eq!(windows_long_path(r"\\server\share\elm"), r"\\?\UNC\server\share\elm");
+/// assert_eq!(windows_long_path(r"\\?\C:\elm"), r"\\?\C:\elm");
+/// assert_eq!(windows_long_path(r"C:\"), r"\\?\C:\");
+/// assert_eq!(windows_long_path(r"elm\0.19.1"), r"elm\0.19.1");
+/// ```
+pub fn windows_long_path(path: &str) -> String {
+    if path.starts_with(r"\\?\") {
+        return path.to_string();
+    }
+    let backslashed = path.replace('/', "\\");
+    let bytes = backslashed.as_bytes();
+    let (mut long, rest) = if let Some(unc) = backslashed.strip_prefix(r"\\") {
+        let mut parts = unc.splitn(3, '\\');
+        let server = parts.next().unwrap_or_default();
+        let share = parts.next().unwrap_or_default();
+        let prefix = format!(r"\\?\UNC\{}\{}", server, share);
+        (prefix, parts.next().unwrap_or_default())
+    } else if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && &bytes[1..3] == b":\\" {
+        (format!(r"\\?\{}", &backslashed[..2]), &backslashed[3..])
+    } else {
+        // Relative paths, and paths relative to the current directory of a drive, like C:elm.
+        return path.to_string();
+    };
+    let mut components = Vec::new();
+    for component in rest.split('\\') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            _ => components.push(component),
+        }
+    }
+    if components.is_empty() && !long.contains("UNC") {
+        long.push('\\');
+    }
+    for component in components {
+        long.push('\\');
+        long.push_str(component);
+    }
+    long
+}
+
+/// Directory of the caches of the dependency solver.
+/// ~/.elm/pubgrub/
+pub fn pubgrub_cache_dir<P: AsRef<Path>>(elm_home: P) -> PathBuf {
+    normalize(elm_home).join("pubgrub")
+}
+
+/// Directory of the package configs cached by the dependency solver.
+/// ~/.elm/pubgrub/elm_json_cache/
+pub fn elm_json_cache_dir<P: AsRef<Path>>(elm_home: P) -> PathBuf {
+    pubgrub_cache_dir(elm_home).join("elm_json_cache")
+}
+
+/// Directory of the cached configs of the versions of a package.
+/// ~/.elm/pubgrub/elm_json_cache/author/package/
+pub fn package_cache_dir<P: AsRef<Path>>(elm_home: P, pkg: &Pkg) -> PathBuf {
+    elm_json_cache_dir(elm_home)
+        .join(&pkg.author)
+        .join(&pkg.pkg)
+}
+
+/// Directory of the caches of an additional package server of a federation.
+/// ~/.elm/pubgrub/remotes/remote_name/
+pub fn remote_cache_dir<P: AsRef<Path>>(elm_home: P, remote_name: &str) -> PathBuf {
+    pubgrub_cache_dir(elm_home)
+        .join("remotes")
+        .join(remote_name)
+}
+
+/// Directory of the cached configs of the versions of a package,
+/// as served by an additional package server of a federation.
+/// ~/.elm/pubgrub/remotes/remote_name/elm_json_cache/author/package/
+pub fn remote_package_cache_dir<P: AsRef<Path>>(
+    elm_home: P,
+    remote_name: &str,
+    pkg: &Pkg,
+) -> PathBuf {
+    remote_cache_dir(elm_home, remote_name)
+        .join("elm_json_cache")
+        .join(&pkg.author)
+        .join(&pkg.pkg)
+}
+
+/// Directory of the packages installed by a version of elm.
+/// ~/.elm/0.19.1/packages/
+pub fn packages_dir<P: AsRef<Path>>(elm_home: P, elm_version: &str) -> PathBuf {
+    normalize(elm_home).join(elm_version).join("packages")
+}
+
+/// Directory of the installed versions of a package.
+/// ~/.elm/0.19.1/packages/author/package/
+pub fn package_dir<P: AsRef<Path>>(elm_home: P, elm_version: &str, pkg: &Pkg) -> PathBuf {
+    packages_dir(elm_home, elm_version)
+        .join(&pkg.author)
+        .join(&pkg.pkg)
+}
+
+/// Directory of the installed versions of a package, if it exists
+/// with the exact case of the package name, even on case-insensitive filesystems.
+#[cfg(feature = "fs")]
+pub fn installed_package_dir<P: AsRef<Path>>(
+    elm_home: P,
+    elm_version: &str,
+    pkg: &Pkg,
+) -> Option<PathBuf> {
+    let dir = package_dir(elm_home, elm_version, pkg);
+    if !dir.is_dir() {
+        return None;
+    }
+    if CASE_INSENSITIVE_FS {
+        let author_dir = dir.parent()?;
+        let exact = |parent: &Path, name: &str| {
+            (std::fs::read_dir(parent).into_iter().flatten())
+                .filter_map(|entry| entry.ok())
+                .any(|entry| entry.file_name() == name)
+        };
+        if !exact(author_dir.parent()?, &pkg.author) || !exact(author_dir, &pkg.pkg) {
+            return None;
+        }
+    }
+    Some(dir)
+}
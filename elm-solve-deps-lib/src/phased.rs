@@ -0,0 +1,201 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module solving normal and test dependencies in two separate phases.
+//!
+//! When solving with test dependencies fails, a single resolution cannot tell
+//! whether the normal dependencies alone are fine.
+//! Similarly to how the elm compiler treats test dependencies,
+//! this module first solves the normal dependencies,
+//! and then solves the test dependencies on top of the versions picked for the normal ones.
+//! Errors then report which of the two phases failed.
+//! A solution already obtained with test dependencies can also be [`split`]
+//! into the two sections of an application `elm.json`.
+
+use pubgrub::range::Range;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap as Map, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::constraint::Constraint;
+use crate::graph::DependencyGraph;
+use crate::project_config::{
+    canonical_json, check_disjoint, AppDependencies, ApplicationConfig, FragmentParseError, Pkg,
+    ProjectConfig, SolutionDetailed, StrictAppDependencies,
+};
+use crate::solver::ExtraMode;
+
+/// Solution of a resolution in two phases, with the same layout than an application `elm.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PhasedSolution {
+    /// Solution for the normal dependencies.
+    pub dependencies: AppDependencies,
+    /// Additional packages needed by the test dependencies.
+    pub test_dependencies: AppDependencies,
+}
+
+/// Strict counterpart of [`PhasedSolution`] for parsing fragments, rejecting unknown fields.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct StrictPhasedSolution {
+    dependencies: StrictAppDependencies,
+    test_dependencies: StrictAppDependencies,
+}
+
+/// Error type for a resolution in two phases, telling which phase failed.
+#[derive(Error, Debug)]
+pub enum PhaseError<E> {
+    /// Solving the normal dependencies failed.
+    #[error("failed to solve the normal dependencies: {0}")]
+    Normal(E),
+
+    /// The normal dependencies are fine, but the test dependencies
+    /// are incompatible with the versions picked for them.
+    #[error("the test dependencies are incompatible with the normal ones: {0}")]
+    Test(E),
+}
+
+impl PhasedSolution {
+    /// Serialize the solution into its canonical JSON form,
+    /// as described in [`AppDependencies::to_canonical_json`].
+    pub fn to_canonical_json(&self) -> String {
+        canonical_json(self)
+    }
+
+    /// Tag every package of the solution with its origin.
+    pub fn detailed(&self) -> SolutionDetailed {
+        SolutionDetailed::from_phases(&self.dependencies, &self.test_dependencies)
+    }
+
+    /// Merge normal and test dependencies into a single solution.
+    pub fn combined(&self) -> AppDependencies {
+        let mut direct = self.dependencies.direct.clone();
+        direct.extend(self.test_dependencies.direct.clone());
+        let mut indirect = self.dependencies.indirect.clone();
+        indirect.extend(self.test_dependencies.indirect.clone());
+        AppDependencies { direct, indirect }
+    }
+}
+
+/// Print the solution like the `"dependencies"` and `"test-dependencies"` fields
+/// of an application `elm.json`, in canonical form without the final newline.
+impl fmt::Display for PhasedSolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_canonical_json().trim_end())
+    }
+}
+
+/// Parse the `"dependencies"` and `"test-dependencies"` fields of an application `elm.json`,
+/// without the rest of the file.
+///
+/// All four sections are required, other fields are rejected,
+/// and packages cannot be listed in two sections,
+/// since test dependencies only list packages not already in the normal dependencies.
+///
+/// ```
+/// # use elm_solve_deps::phased::PhasedSolution;
+/// # use std::str::FromStr;
+/// let fragment = r#"{
+///     "dependencies": { "direct": { "elm/core": "1.0.5" }, "indirect": {} },
+///     "test-dependencies": { "direct": { "elm-explorations/test": "2.1.1" }, "indirect": {} }
+/// }"#;
+/// let solution = PhasedSolution::from_str(fragment).unwrap();
+/// assert_eq!(PhasedSolution::from_str(&solution.to_string()).unwrap(), solution);
+/// ```
+impl FromStr for PhasedSolution {
+    type Err = FragmentParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let strict: StrictPhasedSolution = serde_json::from_str(s)?;
+        let solution = Self {
+            dependencies: strict.dependencies.into(),
+            test_dependencies: strict.test_dependencies.into(),
+        };
+        check_disjoint(&[
+            ("dependencies.direct", &solution.dependencies.direct),
+            ("dependencies.indirect", &solution.dependencies.indirect),
+            (
+                "test-dependencies.direct",
+                &solution.test_dependencies.direct,
+            ),
+            (
+                "test-dependencies.indirect",
+                &solution.test_dependencies.indirect,
+            ),
+        ])?;
+        Ok(solution)
+    }
+}
+
+/// Solve the normal dependencies of a project, then its test dependencies.
+///
+/// The `solve` argument is typically the `solve_deps` method of one of the solvers,
+/// such as [`Offline::solve_deps`](crate::solver::Offline::solve_deps).
+/// Additional constraints only apply to the normal dependencies phase.
+/// In the test phase, every package of the normal solution is pinned to its picked version.
+pub fn solve_in_phases<Solve, E>(
+    project_elm_json: &ProjectConfig,
+    additional_constraints: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+    solve: Solve,
+) -> Result<PhasedSolution, PhaseError<E>>
+where
+    Solve: Fn(&ProjectConfig, bool, &[(Pkg, Constraint)], ExtraMode) -> Result<AppDependencies, E>,
+{
+    let normal = solve(project_elm_json, false, additional_constraints, extra_mode)
+        .map_err(PhaseError::Normal)?;
+    let pins: Vec<(Pkg, Constraint)> = (normal.direct.iter())
+        .chain(normal.indirect.iter())
+        .map(|(p, v)| (p.clone(), Constraint(Range::exact(*v))))
+        .collect();
+    let with_test =
+        solve(project_elm_json, true, &pins, ExtraMode::Override).map_err(PhaseError::Test)?;
+    let test_dependencies = ApplicationConfig::test_dependencies_of(&normal, &with_test);
+    Ok(PhasedSolution {
+        dependencies: normal,
+        test_dependencies,
+    })
+}
+
+/// Split a solution obtained with test dependencies into normal and test dependencies,
+/// without solving again.
+///
+/// The normal dependencies are the packages of the solution reachable in `graph`
+/// from the direct dependencies that are not test dependencies of the project,
+/// and the test dependencies are the other packages.
+/// Unlike [`solve_in_phases`], versions of the normal dependencies
+/// may thus be constrained by the test dependencies.
+pub fn split(
+    project_elm_json: &ProjectConfig,
+    solution: &AppDependencies,
+    graph: &DependencyGraph,
+) -> PhasedSolution {
+    let test_direct: BTreeSet<&Pkg> = match project_elm_json {
+        ProjectConfig::Application(app) => app.test_dependencies.direct.keys().collect(),
+        ProjectConfig::Package(pkg) => pkg.test_dependencies.keys().collect(),
+    };
+    let direct: Map<Pkg, SemVer> = (solution.direct.iter())
+        .filter(|(p, _)| !test_direct.contains(p))
+        .map(|(p, v)| (p.clone(), *v))
+        .collect();
+    let mut reachable = BTreeSet::new();
+    let mut to_visit: Vec<&Pkg> = direct.keys().collect();
+    while let Some(pkg) = to_visit.pop() {
+        if reachable.insert(pkg) {
+            to_visit.extend(graph.dependencies_of(pkg).map(|(dep, _)| dep));
+        }
+    }
+    let indirect = (solution.indirect.iter())
+        .filter(|(p, _)| reachable.contains(p))
+        .map(|(p, v)| (p.clone(), *v))
+        .collect();
+    let normal = AppDependencies { direct, indirect };
+    let test_dependencies = ApplicationConfig::test_dependencies_of(&normal, solution);
+    PhasedSolution {
+        dependencies: normal,
+        test_dependencies,
+    }
+}
@@ -4,15 +4,31 @@
 //!
 //! It also provides a few helper types and functions to read/write to a cache in `ELM_HOME`
 //! and to fetch packages from a server following the same API than the official elm package server.
+//! When the package server is unavailable, `elm.json` files can also be fetched from the
+//! GitHub repositories of packages, at the tag of their version, see [`ConfigFallback`].
 
 use pubgrub::version::{SemanticVersion as SemVer, VersionParseError};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 use thiserror::Error;
 
-use crate::project_config::{PackageConfig, Pkg, PkgParseError};
+#[cfg(feature = "fs")]
+use crate::clock::Clock;
+#[cfg(feature = "fs")]
+use crate::compression::{self, Compression};
+#[cfg(feature = "fs")]
+use crate::federation::MAIN_REMOTE;
+use crate::fetch::{FetchError, HttpFetch};
+use crate::paths;
+#[cfg(feature = "fs")]
+use crate::project_config::PackageConfig;
+use crate::project_config::{AppDependencies, Pkg, PkgParseError};
+use crate::registry::{AllPackages, RegistryError, SinceResponse};
+use crate::telemetry::Span;
 
 /// A cache to record existing package versions.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,8 +38,72 @@ pub struct Cache {
     pub cache: BTreeMap<Pkg, BTreeSet<SemVer>>,
 }
 
+/// How long the versions cache is updated incrementally
+/// before being checked again against the full list of packages of the package server,
+/// with [`Cache::update_with_resync`].
+pub const RESYNC_PERIOD: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Changes of the versions cache after an update with the package server.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CacheUpdate {
+    /// Package versions published since the previous update.
+    pub added: Vec<PkgVersion>,
+    /// Package versions of the cache that were deleted from the package server.
+    pub removed: Vec<PkgVersion>,
+    /// Whether the cache was compared with the full list of packages of the package server,
+    /// which is the only way to detect deleted versions.
+    pub resynced: bool,
+}
+
+/// Source of `elm.json` files tried when the package server fails to provide one,
+/// see [`PkgVersion::fetch_config_with_fallbacks`].
+///
+/// Published packages are GitHub repositories with a tag for each version,
+/// so their `elm.json` files remain available during outages of the package server.
+///
+/// ```
+/// # use elm_solve_deps::pkg_version::{ConfigFallback, PkgVersion};
+/// # use std::str::FromStr;
+/// let core = PkgVersion::from_str("elm/core@1.0.5").unwrap();
+/// assert_eq!(
+///     ConfigFallback::GitHubRaw.url(&core),
+///     "https://raw.githubusercontent.com/elm/core/1.0.5/elm.json"
+/// );
+/// assert_eq!(
+///     ConfigFallback::GitHubApi.url(&core),
+///     "https://api.github.com/repos/elm/core/contents/elm.json?ref=1.0.5"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfigFallback {
+    /// Raw files of the repository, on `raw.githubusercontent.com`.
+    GitHubRaw,
+    /// The contents API of GitHub, on `api.github.com`, returning base64-encoded files.
+    GitHubApi,
+}
+
+/// The GitHub fallbacks, raw files first, then the contents API.
+pub const GITHUB_FALLBACKS: &[ConfigFallback] =
+    &[ConfigFallback::GitHubRaw, ConfigFallback::GitHubApi];
+
 /// Type uniquely identifying a package version.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Package versions are ordered by package, then by version,
+/// and are printed and serialized like `author/package@version`.
+///
+/// ```
+/// # use elm_solve_deps::pkg_version::PkgVersion;
+/// # use std::str::FromStr;
+/// let core = PkgVersion::from_str("elm/core@1.0.5").unwrap();
+/// assert_eq!(core.to_string(), "elm/core@1.0.5");
+/// assert!(core < PkgVersion::from_str("elm/core@1.0.10").unwrap());
+/// assert!(core < PkgVersion::from_str("elm/json@1.0.0").unwrap());
+/// assert_eq!(serde_json::to_string(&core).unwrap(), r#""elm/core@1.0.5""#);
+/// assert!(PkgVersion::from_str("elm/ core@1.0.5").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct PkgVersion {
     /// The package identifier (author + package name).
     pub author_pkg: Pkg,
@@ -31,6 +111,17 @@ pub struct PkgVersion {
     pub version: SemVer,
 }
 
+/// Location of the published archive of a package version, with its hash.
+///
+/// This is the content of the `endpoint.json` file of a package version on the package server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Endpoint {
+    /// Url of the zipball archive of the package version, typically on GitHub.
+    pub url: String,
+    /// SHA-1 hash of the zipball archive.
+    pub hash: String,
+}
+
 /// Type for errors arising when interacting with the local cache on the disk
 /// of package versions.
 ///
@@ -52,7 +143,17 @@ pub enum CacheError {
         url: String,
         /// The actual network error that happened.
         #[source]
-        source: Box<dyn std::error::Error + Send + Sync>,
+        source: FetchError,
+    },
+
+    /// Error arising when the package server returns an invalid response.
+    #[error("invalid response from {url}")]
+    InvalidResponse {
+        /// The url corresponding to the invalid response.
+        url: String,
+        /// The validation error of the response.
+        #[source]
+        source: RegistryError,
     },
 
     /// Error arising when parsing a package version string from the cache fails.
@@ -60,6 +161,50 @@ pub enum CacheError {
     PkgVersionFromStrError(#[from] PkgVersionError),
 }
 
+/// Type for errors of package configs rejected by [`Cache::prime_from_dir`].
+#[derive(Error, Debug)]
+pub enum PrimeError {
+    /// The `elm.json` file could not be read.
+    #[error("unable to read {path}")]
+    FileIoError {
+        /// Path of the `elm.json` file.
+        path: PathBuf,
+        /// The reading error.
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The `elm.json` file is not a valid package config.
+    #[error("invalid package config {path}")]
+    JsonError {
+        /// Path of the `elm.json` file.
+        path: PathBuf,
+        /// The decoding error.
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// The `elm.json` file is the config of another package version than its directory.
+    #[error("{path} is the config of {found}, expected {expected}")]
+    Mismatch {
+        /// Path of the `elm.json` file.
+        path: PathBuf,
+        /// The package version of the directory, as `author/package@version`.
+        expected: String,
+        /// The package version of the config, as `author/package@version`.
+        found: String,
+    },
+}
+
+/// Outcome of [`Cache::prime_from_dir`].
+#[derive(Debug, Default)]
+pub struct PrimeReport {
+    /// Package versions whose config was imported.
+    pub imported: Vec<PkgVersion>,
+    /// Package configs that failed validation, and were not imported.
+    pub rejected: Vec<PrimeError>,
+}
+
 /// Type for errors related to package versions.
 ///
 /// TODO: merge errors with CacheError since there are duplicates?
@@ -79,7 +224,7 @@ pub enum PkgVersionError {
         /// The url corresponding to the failed request.
         url: String,
         /// The actual network error that happened.
-        source: Box<dyn std::error::Error + Sync + Send>,
+        source: FetchError,
     },
 
     /// Failure to parse a package version from string.
@@ -103,6 +248,45 @@ pub enum PkgVersionParseError {
     PkgParseError(#[from] PkgParseError),
 }
 
+impl ConfigFallback {
+    /// Url of the `elm.json` file of a package version.
+    pub fn url(self, pkg_version: &PkgVersion) -> String {
+        let Pkg { author, pkg } = &pkg_version.author_pkg;
+        match self {
+            Self::GitHubRaw => format!(
+                "https://raw.githubusercontent.com/{}/{}/{}/elm.json",
+                author, pkg, pkg_version.version
+            ),
+            Self::GitHubApi => format!(
+                "https://api.github.com/repos/{}/{}/contents/elm.json?ref={}",
+                author, pkg, pkg_version.version
+            ),
+        }
+    }
+
+    /// Extract the `elm.json` file from the body of a response.
+    #[cfg(feature = "fs")]
+    pub fn extract(self, body: String) -> Result<String, PkgVersionError> {
+        match self {
+            Self::GitHubRaw => Ok(body),
+            Self::GitHubApi => {
+                #[derive(Deserialize)]
+                struct Contents {
+                    content: String,
+                }
+                let contents: Contents = serde_json::from_str(&body)?;
+                decode_base64(&contents.content).ok_or_else(|| {
+                    let err = std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "invalid base64 content from the GitHub API",
+                    );
+                    PkgVersionError::FileIoError(err)
+                })
+            }
+        }
+    }
+}
+
 impl Cache {
     /// Initialize an empty cache.
     pub fn new() -> Self {
@@ -111,16 +295,26 @@ impl Cache {
         }
     }
 
+    /// Number of package versions in the cache.
+    ///
+    /// The package server numbers package versions in publication order,
+    /// so for an up-to-date cache, this is also the sequence number of the newest one.
+    pub fn versions_count(&self) -> usize {
+        self.cache.values().map(|v| v.len()).sum()
+    }
+
     /// List installed versions in `ELM_HOME`.
+    #[cfg(feature = "fs")]
     pub fn list_installed_versions<P: AsRef<Path>>(
         elm_home: P,
         elm_version: &str,
         author_pkg: &Pkg,
     ) -> Result<BTreeSet<SemVer>, PkgParseError> {
-        let p_dir = author_pkg.config_path(elm_home, elm_version);
-        let sub_dirs = match std::fs::read_dir(&p_dir) {
-            Ok(s) => s,
-            Err(_) => {
+        let sub_dirs = match paths::installed_package_dir(elm_home, elm_version, author_pkg)
+            .and_then(|p_dir| std::fs::read_dir(p_dir).ok())
+        {
+            Some(s) => s,
+            None => {
                 // The directory does not exist so probably
                 // no version of this package have ever been installed.
                 return Ok(BTreeSet::new());
@@ -139,31 +333,153 @@ impl Cache {
             .collect())
     }
 
+    /// List all installed versions of all packages in `ELM_HOME`, in a single pass.
+    ///
+    /// Contrary to [`list_installed_versions`](Self::list_installed_versions),
+    /// which walks the directory of one package at a time,
+    /// this walks the whole packages directory at once,
+    /// which is faster to prime a fresh cache on slow filesystems.
+    /// With the `rayon` feature, the directories of different authors are walked in parallel.
+    #[cfg(feature = "fs")]
+    pub fn scan_all_installed<P: AsRef<Path>>(
+        elm_home: P,
+        elm_version: &str,
+    ) -> std::io::Result<Self> {
+        let packages_dir = paths::packages_dir(elm_home, elm_version);
+        let authors = match sub_dirs(&packages_dir) {
+            Ok(authors) => authors,
+            // No package was ever installed.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(e),
+        };
+        #[cfg(feature = "rayon")]
+        let scanned: Vec<_> = {
+            use rayon::prelude::*;
+            authors.into_par_iter().map(scan_author_dir).collect()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let scanned: Vec<_> = authors.into_iter().map(scan_author_dir).collect();
+        Ok(Self {
+            cache: scanned.into_iter().flatten().collect(),
+        })
+    }
+
+    /// Prime the cache of package configs in `ELM_HOME`
+    /// from a directory of pre-downloaded `elm.json` files,
+    /// such as the ones baked into CI images.
+    ///
+    /// The directory is organized like the packages of `ELM_HOME`,
+    /// with one `author/package/version/elm.json` file per package version.
+    /// Every config is validated, it must be a package config whose name and version
+    /// match its directory, otherwise it is rejected and reported, without failing the import.
+    /// The `progress` function is called after each config, with the number of configs
+    /// processed so far and the total number of configs.
+    ///
+    /// The versions cache is left untouched: it must list the versions of the package server
+    /// in publication order to be updated incrementally, see [`Cache::update`],
+    /// so the primed versions are only listed once the package server lists them.
+    #[cfg(feature = "fs")]
+    pub fn prime_from_dir<P, Q, F>(
+        dir: P,
+        elm_home: Q,
+        mut progress: F,
+    ) -> Result<PrimeReport, CacheError>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+        F: FnMut(usize, usize),
+    {
+        let mut configs = Vec::new();
+        for (author, author_dir) in sub_dirs(dir.as_ref())? {
+            for (pkg, pkg_dir) in sub_dirs(&author_dir)? {
+                for (version, version_dir) in sub_dirs(&pkg_dir)? {
+                    if let Ok(version) = SemVer::from_str(&version) {
+                        let pkg_version = PkgVersion {
+                            author_pkg: Pkg::new(&author, &pkg),
+                            version,
+                        };
+                        configs.push((pkg_version, version_dir.join("elm.json")));
+                    }
+                }
+            }
+        }
+        let mut report = PrimeReport::default();
+        let total = configs.len();
+        let compression = Compression::of_cache(&elm_home);
+        for (done, (pkg_version, path)) in configs.into_iter().enumerate() {
+            match validate_primed_config(&pkg_version, &path) {
+                Ok(config_str) => {
+                    std::fs::create_dir_all(pkg_version.pubgrub_cache_dir(&elm_home))?;
+                    let cache_file = pkg_version.pubgrub_cache_file(&elm_home);
+                    compression::write(cache_file, &config_str, compression)?;
+                    report.imported.push(pkg_version);
+                }
+                Err(err) => report.rejected.push(err),
+            }
+            progress(done + 1, total);
+        }
+        Ok(report)
+    }
+
     /// Load the cache from its default location.
+    #[cfg(feature = "fs")]
     pub fn load<P: AsRef<Path>>(elm_home: P) -> Result<Self, CacheError> {
         // eprintln!(
         //     "Loading versions cache from {}",
         //     Self::file_path(&elm_home).display()
         // );
-        let s = std::fs::read_to_string(Self::file_path(elm_home))?;
-        serde_json::from_str(&s).map_err(|e| e.into())
+        Self::load_from(Self::file_path(elm_home))
     }
 
     /// Save the cache to its default location.
+    #[cfg(feature = "fs")]
     pub fn save<P: AsRef<Path>>(&self, elm_home: P) -> Result<(), CacheError> {
         // eprintln!(
         //     "Saving versions cache into {}",
         //     Self::file_path(&elm_home).display()
         // );
+        let compression = Compression::of_cache(&elm_home);
+        self.save_to(Self::file_path(elm_home), compression)
+    }
+
+    /// Load the cache of an additional package server, named `remote_name`,
+    /// from its location in [`Cache::remote_dir`].
+    ///
+    /// The versions of each package server are kept in separate caches,
+    /// such that the updates of one do not mix with the versions of the others.
+    #[cfg(feature = "fs")]
+    pub fn load_remote<P: AsRef<Path>>(elm_home: P, remote_name: &str) -> Result<Self, CacheError> {
+        Self::load_from(Self::remote_dir(elm_home, remote_name).join("versions_cache.json"))
+    }
+
+    /// Save the cache of an additional package server, named `remote_name`.
+    #[cfg(feature = "fs")]
+    pub fn save_remote<P: AsRef<Path>>(
+        &self,
+        elm_home: P,
+        remote_name: &str,
+    ) -> Result<(), CacheError> {
+        let compression = Compression::of_cache(&elm_home);
+        let file_path = Self::remote_dir(elm_home, remote_name).join("versions_cache.json");
+        self.save_to(file_path, compression)
+    }
+
+    #[cfg(feature = "fs")]
+    fn load_from(file_path: PathBuf) -> Result<Self, CacheError> {
+        let s = compression::read_to_string(file_path)?;
+        serde_json::from_str(&s).map_err(|e| e.into())
+    }
+
+    #[cfg(feature = "fs")]
+    fn save_to(&self, file_path: PathBuf, compression: Compression) -> Result<(), CacheError> {
         let s = serde_json::to_string(self)?;
-        let file_path = Self::file_path(elm_home);
         std::fs::create_dir_all(file_path.parent().ok_or_else(|| {
             std::io::Error::new(
                 std::io::ErrorKind::NotFound,
                 format!("{}", file_path.display()),
             )
         })?)?;
-        std::fs::write(file_path, &s).map_err(|e| e.into())
+        compression::write(file_path, &s, compression).map_err(|e| e.into())
     }
 
     /// Path the to file used to store a cache of all existing versions.
@@ -172,75 +488,230 @@ impl Cache {
         Pkg::pubgrub_cache_dir(elm_home).join("versions_cache.json")
     }
 
+    /// Path to the file recording when the cache was last resynchronized
+    /// with the full list of packages of the package server.
+    /// ~/.elm/pubgrub/versions_resync
+    pub fn resync_file_path<P: AsRef<Path>>(elm_home: P) -> PathBuf {
+        Pkg::pubgrub_cache_dir(elm_home).join("versions_resync")
+    }
+
+    /// Directory of the caches of an additional package server, named `remote_name`.
+    /// ~/.elm/pubgrub/remotes/remote_name/
+    pub fn remote_dir<P: AsRef<Path>>(elm_home: P, remote_name: &str) -> PathBuf {
+        paths::remote_cache_dir(elm_home, remote_name)
+    }
+
     /// Fetch packages online.
-    pub fn update(
+    ///
+    /// The update is incremental, only fetching the package versions published since
+    /// the last update, unless it detects that versions were deleted from the package server.
+    /// The cache is then resynchronized with the full list of packages,
+    /// and the deleted versions are reported in the [`CacheUpdate`].
+    pub fn update<H: HttpFetch + ?Sized>(
         &mut self,
         remote_base_url: &str,
-        http_fetch: impl Fn(&str) -> Result<String, Box<dyn std::error::Error + Send + Sync>>,
-    ) -> Result<(), CacheError> {
-        if self.cache.is_empty() {
-            *self = Self::from_remote_all_pkg(remote_base_url, http_fetch)?;
-            Ok(())
+        http_fetch: &H,
+    ) -> Result<CacheUpdate, CacheError> {
+        let mut span = Span::start("cache_update").with_attribute("remote", remote_base_url);
+        let result = self.update_from_remote(remote_base_url, http_fetch);
+        span.record_result(&result);
+        result
+    }
+
+    /// Replace the cache with the full list of packages of the package server,
+    /// and report the package versions added and removed.
+    ///
+    /// Deletions at the end of the registry can be hidden from incremental updates
+    /// by the publication of as many new versions, so this should be done periodically.
+    pub fn resync<H: HttpFetch + ?Sized>(
+        &mut self,
+        remote_base_url: &str,
+        http_fetch: &H,
+    ) -> Result<CacheUpdate, CacheError> {
+        let mut span = Span::start("cache_resync").with_attribute("remote", remote_base_url);
+        let result = Self::from_remote_all_pkg(remote_base_url, http_fetch).map(|all| {
+            let update = self.changes_to(&all);
+            *self = all;
+            update
+        });
+        span.record_result(&result);
+        result
+    }
+
+    /// Update the cache, resynchronizing it if it was not resynchronized
+    /// during the last [`RESYNC_PERIOD`], and recording the time of the resynchronization.
+    ///
+    /// The cache itself is not saved, call [`Cache::save`] afterward.
+    #[cfg(feature = "fs")]
+    pub fn update_with_resync<P: AsRef<Path>, H: HttpFetch + ?Sized, C: Clock + ?Sized>(
+        &mut self,
+        elm_home: P,
+        remote_base_url: &str,
+        http_fetch: &H,
+        clock: &C,
+    ) -> Result<CacheUpdate, CacheError> {
+        let resync_path = Self::resync_file_path(elm_home);
+        self.update_with_resync_at(resync_path, remote_base_url, http_fetch, clock)
+    }
+
+    /// Update the cache of an additional package server, named `remote_name`,
+    /// like [`Cache::update_with_resync`] but recording its resynchronization separately.
+    #[cfg(feature = "fs")]
+    pub fn update_remote_with_resync<P: AsRef<Path>, H: HttpFetch + ?Sized, C: Clock + ?Sized>(
+        &mut self,
+        elm_home: P,
+        remote_name: &str,
+        remote_base_url: &str,
+        http_fetch: &H,
+        clock: &C,
+    ) -> Result<CacheUpdate, CacheError> {
+        let resync_path = Self::remote_dir(elm_home, remote_name).join("versions_resync");
+        self.update_with_resync_at(resync_path, remote_base_url, http_fetch, clock)
+    }
+
+    #[cfg(feature = "fs")]
+    fn update_with_resync_at<H: HttpFetch + ?Sized, C: Clock + ?Sized>(
+        &mut self,
+        resync_path: PathBuf,
+        remote_base_url: &str,
+        http_fetch: &H,
+        clock: &C,
+    ) -> Result<CacheUpdate, CacheError> {
+        let last_resync: Option<u64> =
+            (std::fs::read_to_string(&resync_path).ok()).and_then(|secs| secs.trim().parse().ok());
+        let now = clock.unix_secs();
+        let update = match last_resync {
+            Some(last) if now.saturating_sub(last) < RESYNC_PERIOD.as_secs() => {
+                self.update(remote_base_url, http_fetch)?
+            }
+            _ => self.resync(remote_base_url, http_fetch)?,
+        };
+        if update.resynced {
+            if let Some(dir) = resync_path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(resync_path, now.to_string())?;
+        }
+        Ok(update)
+    }
+
+    /// Compare with another cache, typically more recent.
+    fn changes_to(&self, other: &Self) -> CacheUpdate {
+        let only_in = |a: &Self, b: &Self| -> Vec<PkgVersion> {
+            let empty = BTreeSet::new();
+            (a.cache.iter())
+                .flat_map(|(pkg, versions)| {
+                    let b_versions = b.cache.get(pkg).unwrap_or(&empty);
+                    (versions.difference(b_versions)).map(|v| PkgVersion {
+                        author_pkg: pkg.clone(),
+                        version: *v,
+                    })
+                })
+                .collect()
+        };
+        CacheUpdate {
+            added: only_in(other, self),
+            removed: only_in(self, other),
+            resynced: true,
+        }
+    }
+
+    /// The package server numbers package versions in publication order,
+    /// and `/all-packages/since/N` returns the ones after the N first, newest first.
+    /// With a cache of N versions, asking for the ones since N - 1 must thus return
+    /// the newest cached version last.
+    /// If the response is empty, or if its oldest version is not cached,
+    /// the package server has less versions than the cache, and some were deleted.
+    fn update_from_remote<H: HttpFetch + ?Sized>(
+        &mut self,
+        remote_base_url: &str,
+        http_fetch: &H,
+    ) -> Result<CacheUpdate, CacheError> {
+        // A cache listing packages without versions also has nothing to update from.
+        let versions_count = self.versions_count();
+        if versions_count == 0 {
+            self.resync(remote_base_url, http_fetch)
         } else {
-            let versions_count: usize = self.cache.values().map(|v| v.len()).sum();
             let url = format!(
                 "{}/all-packages/since/{}",
                 remote_base_url,
-                versions_count.max(1) - 1
+                versions_count.saturating_sub(1)
             );
             // eprintln!("Request to {}", url);
-            let pkgs_str = http_fetch(&url).map_err(|e| CacheError::FetchError {
-                url: url.clone(),
-                source: e,
+            let pkgs_str = traced_fetch(Span::start("fetch"), &url, http_fetch).map_err(|e| {
+                CacheError::FetchError {
+                    url: url.clone(),
+                    source: e,
+                }
             })?;
-            let new_versions_str: Vec<&str> =
-                serde_json::from_str(&pkgs_str).map_err(|_| CacheError::FetchError {
-                    url,
-                    source: format!("Got an unexpected response: {}", pkgs_str).into(),
-                })?;
-            if new_versions_str.is_empty() {
-                // Reload from scratch since it means a package was deleted from the registry
-                // and no new package showed up
-                *self = Self::from_remote_all_pkg(remote_base_url, http_fetch)?;
-                return Ok(());
+            let since = SinceResponse::from_json(&pkgs_str)
+                .map_err(|source| CacheError::InvalidResponse { url, source })?;
+            let last_pkg = match since.oldest() {
+                Some(last_pkg) => last_pkg,
+                // Versions were deleted from the registry and no new version showed up.
+                None => return self.resync(remote_base_url, http_fetch),
+            };
+            // Check that the oldest version of the response was already in cache.
+            let known = (self.cache.get(&last_pkg.author_pkg))
+                .is_some_and(|pkg_versions| pkg_versions.contains(&last_pkg.version));
+            if !known {
+                // Versions were deleted from the registry, shifting the numbering.
+                return self.resync(remote_base_url, http_fetch);
             }
-            // Check that the last package in the list was already in cache
-            // (the list returned by the package server is sorted newest first)
-            let (last, newers) = new_versions_str.split_last().unwrap(); // This unwrap is fine since we checked that new_versions_str is not empty
-            let last_pkg = PkgVersion::from_str(last).map_err(PkgVersionError::ParseError)?;
-            if self
-                .cache
-                .get(&last_pkg.author_pkg)
-                .and_then(|pkg_versions| pkg_versions.get(&last_pkg.version))
-                .is_some()
-            {
-                // Continue as normal: register every new package version
-                for version_str in &newers[..] {
-                    let PkgVersion {
-                        author_pkg,
-                        version,
-                    } = PkgVersion::from_str(version_str).map_err(PkgVersionError::ParseError)?;
-                    let pkg_entry = self.cache.entry(author_pkg).or_default();
-                    pkg_entry.insert(version);
+            // Continue as normal: register every new package version
+            let mut update = CacheUpdate::default();
+            for new_version in since.newer() {
+                let pkg_entry = self
+                    .cache
+                    .entry(new_version.author_pkg.clone())
+                    .or_default();
+                if pkg_entry.insert(new_version.version) {
+                    update.added.push(new_version.clone());
                 }
-            } else {
-                // Reload from scratch since it means a package was deleted from the registry
-                *self = Self::from_remote_all_pkg(remote_base_url, http_fetch)?;
             }
-            Ok(())
+            Ok(update)
         }
     }
 
     /// curl -L https://package.elm-lang.org/all-packages | jq .
-    fn from_remote_all_pkg(
+    fn from_remote_all_pkg<H: HttpFetch + ?Sized>(
         remote_base_url: &str,
-        http_fetch: impl Fn(&str) -> Result<String, Box<dyn std::error::Error + Send + Sync>>,
+        http_fetch: &H,
     ) -> Result<Self, CacheError> {
         let url = format!("{}/all-packages", remote_base_url);
         // eprintln!("Request to {}", url);
-        let all_pkg_str =
-            http_fetch(&url).map_err(|e| CacheError::FetchError { url, source: e })?;
-        serde_json::from_str(&all_pkg_str).map_err(|e| e.into())
+        let all_pkg_str = traced_fetch(Span::start("fetch"), &url, http_fetch).map_err(|e| {
+            CacheError::FetchError {
+                url: url.clone(),
+                source: e,
+            }
+        })?;
+        let all = AllPackages::from_json(&all_pkg_str)
+            .map_err(|source| CacheError::InvalidResponse { url, source })?;
+        Ok(all.into())
+    }
+}
+
+impl From<AllPackages> for Cache {
+    fn from(all: AllPackages) -> Self {
+        Self {
+            cache: all.packages,
+        }
+    }
+}
+
+impl CacheUpdate {
+    /// Removed package versions that are part of the given solution,
+    /// such as the dependencies of an application `elm.json`,
+    /// which thus cannot be installed anymore.
+    pub fn removed_from<'a>(&'a self, solution: &AppDependencies) -> Vec<&'a PkgVersion> {
+        (self.removed.iter())
+            .filter(|removed| {
+                let pinned = (solution.direct.get(&removed.author_pkg))
+                    .or_else(|| solution.indirect.get(&removed.author_pkg));
+                pinned == Some(&removed.version)
+            })
+            .collect()
     }
 }
 
@@ -254,25 +725,125 @@ impl Default for Cache {
 // Public PkgVersion methods.
 impl PkgVersion {
     /// Fetch the `elm.json` config for this package version from the package server.
-    pub fn fetch_config<P: AsRef<Path>>(
+    #[cfg(feature = "fs")]
+    pub fn fetch_config<P: AsRef<Path>, H: HttpFetch + ?Sized>(
         &self,
         elm_home: P,
         remote_base_url: &str,
-        http_fetch: impl Fn(&str) -> Result<String, Box<dyn std::error::Error + Sync + Send>>,
+        http_fetch: &H,
     ) -> Result<PackageConfig, PkgVersionError> {
+        (self.fetch_config_with_fallbacks(elm_home, remote_base_url, &[], http_fetch))
+            .map(|(config, _)| config)
+    }
+
+    /// Fetch the `elm.json` config for this package version from the package server,
+    /// or from the fallbacks, in order, if the package server fails to respond.
+    ///
+    /// Fallbacks are not tried if the package server answers that the package version
+    /// does not exist, or with a body too large.
+    /// If they all fail, the error of the package server is returned.
+    /// The fallback the config was fetched from, if any, is returned alongside it,
+    /// and only configs of the package server are saved in the cache.
+    #[cfg(feature = "fs")]
+    pub fn fetch_config_with_fallbacks<P: AsRef<Path>, H: HttpFetch + ?Sized>(
+        &self,
+        elm_home: P,
+        remote_base_url: &str,
+        fallbacks: &[ConfigFallback],
+        http_fetch: &H,
+    ) -> Result<(PackageConfig, Option<ConfigFallback>), PkgVersionError> {
+        let remote = (MAIN_REMOTE, remote_base_url);
+        self.fetch_remote_config_with_fallbacks(elm_home, remote, fallbacks, http_fetch)
+    }
+
+    /// Fetch the `elm.json` config for this package version from a named package server
+    /// of a federation, like [`PkgVersion::fetch_config_with_fallbacks`],
+    /// and save it in the cache of that package server, see [`PkgVersion::remote_cache_file`].
+    #[cfg(feature = "fs")]
+    pub fn fetch_remote_config_with_fallbacks<P: AsRef<Path>, H: HttpFetch + ?Sized>(
+        &self,
+        elm_home: P,
+        (remote_name, remote_base_url): (&str, &str),
+        fallbacks: &[ConfigFallback],
+        http_fetch: &H,
+    ) -> Result<(PackageConfig, Option<ConfigFallback>), PkgVersionError> {
         let remote_url = self.to_url(remote_base_url);
         // eprintln!("Fetching {}", &remote_url);
-        let config_str = http_fetch(&remote_url).map_err(|e| PkgVersionError::FetchError {
-            url: remote_url,
-            source: e,
+        let span = || {
+            Span::start("fetch")
+                .with_attribute("package", &self.author_pkg)
+                .with_attribute("version", self.version)
+        };
+        let (config_str, fallback) = match traced_fetch(span(), &remote_url, http_fetch) {
+            Ok(config_str) => (config_str, None),
+            Err(e) if matches!(e, FetchError::NotFound | FetchError::TooLarge { .. }) => {
+                return Err(PkgVersionError::FetchError {
+                    url: remote_url,
+                    source: e,
+                })
+            }
+            Err(e) => {
+                let fetched = fallbacks.iter().find_map(|fallback| {
+                    let span = span().with_attribute("fallback", format!("{:?}", fallback));
+                    let body = traced_fetch(span, &fallback.url(self), http_fetch).ok()?;
+                    let config_str = fallback.extract(body).ok()?;
+                    Some((config_str, Some(*fallback)))
+                });
+                fetched.ok_or(PkgVersionError::FetchError {
+                    url: remote_url,
+                    source: e,
+                })?
+            }
+        };
+        let config = serde_json::from_str(&config_str)?;
+        // Configs of the fallbacks are not the ones of the package server, so they are not cached,
+        // and are fetched again from the package server once it is back.
+        if fallback.is_none() {
+            let cache_file = self.remote_cache_file(&elm_home, remote_name);
+            if let Some(dir) = cache_file.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let compression = Compression::of_cache(&elm_home);
+            compression::write(cache_file, &config_str, compression)?;
+        }
+        Ok((config, fallback))
+    }
+
+    /// Retrieve the [`Endpoint`] of this package version.
+    ///
+    /// It is loaded from the dependency solver cache if available,
+    /// otherwise it is fetched from the package server and saved in the cache.
+    #[cfg(feature = "fs")]
+    pub fn fetch_endpoint<P: AsRef<Path>, H: HttpFetch + ?Sized>(
+        &self,
+        elm_home: P,
+        remote_base_url: &str,
+        http_fetch: &H,
+    ) -> Result<Endpoint, PkgVersionError> {
+        let cache_path = self.pubgrub_cache_dir(&elm_home).join("endpoint.json");
+        if let Ok(endpoint_str) = std::fs::read_to_string(&cache_path) {
+            if let Ok(endpoint) = serde_json::from_str(&endpoint_str) {
+                return Ok(endpoint);
+            }
+        }
+        let remote_url = self.to_endpoint_url(remote_base_url);
+        let span = Span::start("fetch")
+            .with_attribute("package", &self.author_pkg)
+            .with_attribute("version", self.version);
+        let endpoint_str = traced_fetch(span, &remote_url, http_fetch).map_err(|e| {
+            PkgVersionError::FetchError {
+                url: remote_url,
+                source: e,
+            }
         })?;
+        let endpoint = serde_json::from_str(&endpoint_str)?;
         std::fs::create_dir_all(self.pubgrub_cache_dir(&elm_home))?;
-        std::fs::write(self.pubgrub_cache_file(&elm_home), &config_str)?;
-        let config = serde_json::from_str(&config_str)?;
-        Ok(config)
+        std::fs::write(cache_path, &endpoint_str)?;
+        Ok(endpoint)
     }
 
     /// Load the `elm.json` config for this package version from its installed location.
+    #[cfg(feature = "fs")]
     pub fn load_config<P: AsRef<Path>>(
         &self,
         elm_home: P,
@@ -286,13 +857,25 @@ impl PkgVersion {
     }
 
     /// Load the `elm.json` config for this package version from the dependency solver cache.
+    #[cfg(feature = "fs")]
     pub fn load_from_cache<P: AsRef<Path>>(
         &self,
         elm_home: P,
     ) -> Result<PackageConfig, PkgVersionError> {
-        let cache_path = self.pubgrub_cache_file(elm_home);
+        self.load_from_remote_cache(elm_home, MAIN_REMOTE)
+    }
+
+    /// Load the `elm.json` config for this package version
+    /// from the cache of a named package server of a federation.
+    #[cfg(feature = "fs")]
+    pub fn load_from_remote_cache<P: AsRef<Path>>(
+        &self,
+        elm_home: P,
+        remote_name: &str,
+    ) -> Result<PackageConfig, PkgVersionError> {
+        let cache_path = self.remote_cache_file(elm_home, remote_name);
         // eprintln!("Cache-loading {:?}", &cache_path);
-        let config_str = std::fs::read_to_string(&cache_path)?;
+        let config_str = compression::read_to_string(&cache_path)?;
         let config = serde_json::from_str(&config_str)?;
         Ok(config)
     }
@@ -301,14 +884,15 @@ impl PkgVersion {
     pub fn config_path<P: AsRef<Path>>(&self, elm_home: P, elm_version: &str) -> PathBuf {
         self.author_pkg
             .config_path(elm_home, elm_version)
-            .join(&self.version.to_string())
+            .join(self.version.to_string())
             .join("elm.json")
     }
 }
 
 // Private PkgVersion methods.
+#[cfg(feature = "fs")]
 impl PkgVersion {
-    fn to_url(&self, remote_base_url: &str) -> String {
+    pub(crate) fn to_url(&self, remote_base_url: &str) -> String {
         format!(
             "{}/{}/elm.json",
             self.author_pkg.to_url(remote_base_url),
@@ -316,14 +900,168 @@ impl PkgVersion {
         )
     }
 
-    fn pubgrub_cache_file<P: AsRef<Path>>(&self, elm_home: P) -> PathBuf {
+    fn to_endpoint_url(&self, remote_base_url: &str) -> String {
+        format!(
+            "{}/{}/endpoint.json",
+            self.author_pkg.to_url(remote_base_url),
+            self.version
+        )
+    }
+
+    pub(crate) fn pubgrub_cache_file<P: AsRef<Path>>(&self, elm_home: P) -> PathBuf {
         self.pubgrub_cache_dir(elm_home).join("elm.json")
     }
 
+    /// Location of the cached `elm.json` config of this package version,
+    /// as served by a named package server of a federation.
+    /// Package servers may serve different configs for the same version,
+    /// so only the main one uses ~/.elm/pubgrub/elm_json_cache/author/package/version/elm.json,
+    /// and the other ones ~/.elm/pubgrub/remotes/NAME/elm_json_cache/author/package/version/elm.json
+    #[cfg(feature = "fs")]
+    pub fn remote_cache_file<P: AsRef<Path>>(&self, elm_home: P, remote_name: &str) -> PathBuf {
+        if remote_name == MAIN_REMOTE {
+            return self.pubgrub_cache_file(elm_home);
+        }
+        paths::remote_package_cache_dir(elm_home, remote_name, &self.author_pkg)
+            .join(self.version.to_string())
+            .join("elm.json")
+    }
+
     fn pubgrub_cache_dir<P: AsRef<Path>>(&self, elm_home: P) -> PathBuf {
         self.author_pkg
             .pubgrub_cache_dir_json(elm_home)
-            .join(&self.version.to_string())
+            .join(self.version.to_string())
+    }
+}
+
+/// List the sub directories of a directory, with their names.
+#[cfg(feature = "fs")]
+pub(crate) fn sub_dirs(dir: &Path) -> std::io::Result<Vec<(String, PathBuf)>> {
+    Ok(std::fs::read_dir(dir)?
+        .filter_map(|f| f.ok())
+        .filter(|entry| entry.file_type().map(|f| f.is_dir()).unwrap_or(false))
+        .filter_map(|entry| Some((entry.file_name().into_string().ok()?, entry.path())))
+        .collect())
+}
+
+/// List installed versions of all packages of one author.
+#[cfg(feature = "fs")]
+fn scan_author_dir((author, author_dir): (String, PathBuf)) -> Vec<(Pkg, BTreeSet<SemVer>)> {
+    sub_dirs(&author_dir)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(pkg, pkg_dir)| {
+            let versions = sub_dirs(&pkg_dir)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|(version, _)| SemVer::from_str(&version).ok())
+                .collect();
+            (Pkg::new(&author, pkg), versions)
+        })
+        .collect()
+}
+
+/// Read a package config of a priming directory, and check that it matches its directory.
+#[cfg(feature = "fs")]
+fn validate_primed_config(pkg_version: &PkgVersion, path: &Path) -> Result<String, PrimeError> {
+    let config_str = std::fs::read_to_string(path).map_err(|source| PrimeError::FileIoError {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let config: PackageConfig =
+        serde_json::from_str(&config_str).map_err(|source| PrimeError::JsonError {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    if config.name != pkg_version.author_pkg || config.version != pkg_version.version {
+        return Err(PrimeError::Mismatch {
+            path: path.to_path_buf(),
+            expected: pkg_version.to_string(),
+            found: format!("{}@{}", config.name, config.version),
+        });
+    }
+    Ok(config_str)
+}
+
+/// Decode standard base64, ignoring line breaks like the ones of the GitHub API.
+/// Padding is optional, but only allowed at the end.
+#[cfg(feature = "fs")]
+fn decode_base64(encoded: &str) -> Option<String> {
+    let encoded: Vec<u8> = encoded
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    let unpadded = encoded.strip_suffix(b"==").or(encoded.strip_suffix(b"="));
+    let data = unpadded.unwrap_or(&encoded);
+    if unpadded.is_some() && !encoded.len().is_multiple_of(4) || data.len() % 4 == 1 {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0_u32, 0);
+    for &c in data {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Call `http_fetch` on the given url, recording the request in the given span.
+fn traced_fetch<H: HttpFetch + ?Sized>(
+    span: Span,
+    url: &str,
+    http_fetch: &H,
+) -> Result<String, FetchError> {
+    let mut span = span.with_attribute("url", url);
+    let result = http_fetch.fetch(url);
+    span.record_result(&result);
+    result
+}
+
+impl fmt::Display for PkgVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.author_pkg, self.version)
+    }
+}
+
+impl Serialize for PkgVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// Also accept the object with `author_pkg` and `version` fields,
+/// used to serialize package versions before, such that existing files stay readable.
+impl<'de> Deserialize<'de> for PkgVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Str(String),
+            Fields { author_pkg: Pkg, version: SemVer },
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Str(s) => s.parse().map_err(serde::de::Error::custom),
+            Repr::Fields {
+                author_pkg,
+                version,
+            } => Ok(Self {
+                author_pkg,
+                version,
+            }),
+        }
     }
 }
 
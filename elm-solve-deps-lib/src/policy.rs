@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module defining version policies, applied to all dependency resolutions.
+//!
+//! Policies are global rules such as "never pick versions older than 1.0.5 for elm/core"
+//! or "never pick major version 5 of author/pkg".
+//! Contrary to additional constraints, which only add direct dependencies to one resolution,
+//! policies restrict the versions of packages wherever they appear in the dependency graph.
+//!
+//! Policies are typically loaded from a JSON file such as the following.
+//!
+//! ```json
+//! {
+//!   "elm/core": { "min": "1.0.5", "reason": "security fix" },
+//!   "author/pkg": { "exclude": ["5.0.0 <= v < 6.0.0"] },
+//!   "other/pkg": { "max": "3.0.0" }
+//! }
+//! ```
+//!
+//! Packages can also be held at the version recorded in an application `elm.json`,
+//! such that solving again upgrades every other package.
+
+use pubgrub::range::Range;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap as Map;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::constraint::Constraint;
+#[cfg(feature = "fs")]
+use crate::pkg_version::CacheError;
+use crate::project_config::{AppDependencies, Pkg};
+
+/// A table of policies, indexed by package.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Policies {
+    /// The policy of each package.
+    pub policies: Map<Pkg, Policy>,
+}
+
+/// Policy restricting the versions allowed for one package.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Policy {
+    /// Versions older than this one are never picked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<SemVer>,
+    /// This version and newer ones are never picked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<SemVer>,
+    /// Versions in these ranges are never picked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<Constraint>,
+    /// Why this policy exists, for reporting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Error holding a package without a recorded version.
+#[derive(Error, Debug)]
+#[error("cannot hold {0}, it has no recorded version")]
+pub struct HoldError(pub Pkg);
+
+/// A policy which excluded versions that would otherwise have been candidates
+/// for a package in the solution.
+#[derive(Debug, Clone)]
+pub struct PolicyEffect {
+    /// The package concerned.
+    pub pkg: Pkg,
+    /// The version selected in the solution.
+    pub selected: SemVer,
+    /// Available versions excluded by the policy.
+    pub excluded: Vec<SemVer>,
+    /// The reason of the policy, if any.
+    pub reason: Option<String>,
+}
+
+impl Policy {
+    /// Range of versions allowed by this policy.
+    pub fn allowed(&self) -> Range<SemVer> {
+        let mut allowed = match self.min {
+            Some(min) => Range::higher_than(min),
+            None => Range::any(),
+        };
+        if let Some(max) = self.max {
+            allowed = allowed.intersection(&Range::strictly_lower_than(max));
+        }
+        for excluded in &self.exclude {
+            allowed = allowed.intersection(&excluded.0.negate());
+        }
+        allowed
+    }
+}
+
+impl Policies {
+    /// Initialize an empty table of policies.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a policy for a package, replacing any previous one.
+    pub fn insert(&mut self, pkg: Pkg, policy: Policy) {
+        self.policies.insert(pkg, policy);
+    }
+
+    /// Only allow one exact version of a package, replacing any previous policy.
+    pub fn hold(&mut self, pkg: Pkg, version: SemVer) {
+        let policy = Policy {
+            min: Some(version),
+            max: Some(version.bump_patch()),
+            exclude: Vec::new(),
+            reason: Some(format!("held at {}", version)),
+        };
+        self.insert(pkg, policy);
+    }
+
+    /// Add a policy for a package on top of its current policy,
+    /// such that only the versions allowed by both of them are picked.
+    pub fn intersect(&mut self, pkg: Pkg, policy: Policy) {
+        let current = match self.policies.get_mut(&pkg) {
+            Some(current) => current,
+            None => return self.insert(pkg, policy),
+        };
+        current.min = current.min.max(policy.min);
+        current.max = match (current.max, policy.max) {
+            (Some(max), Some(other)) => Some(max.min(other)),
+            (max, other) => max.or(other),
+        };
+        current.exclude.extend(policy.exclude);
+        current.reason = match (current.reason.take(), policy.reason) {
+            (Some(reason), Some(other)) => Some(format!("{}, {}", reason, other)),
+            (reason, other) => reason.or(other),
+        };
+    }
+
+    /// Never pick one version of a package, on top of its current policy.
+    pub fn exclude(&mut self, pkg: Pkg, version: SemVer) {
+        let policy = self.policies.entry(pkg).or_default();
+        policy.exclude.push(Constraint(Range::exact(version)));
+    }
+
+    /// Hold packages at their recorded version, typically obtained with
+    /// [`ApplicationConfig::recorded_versions`](crate::project_config::ApplicationConfig::recorded_versions).
+    pub fn hold_recorded(
+        &mut self,
+        recorded: &Map<Pkg, SemVer>,
+        held: &[Pkg],
+    ) -> Result<(), HoldError> {
+        for pkg in held {
+            let version = recorded.get(pkg).ok_or_else(|| HoldError(pkg.clone()))?;
+            self.hold(pkg.clone(), *version);
+        }
+        Ok(())
+    }
+
+    /// Check if a version of a package is allowed by the policies.
+    pub fn allows(&self, pkg: &Pkg, version: &SemVer) -> bool {
+        match self.policies.get(pkg) {
+            Some(policy) => policy.allowed().contains(version),
+            None => true,
+        }
+    }
+
+    /// Filter out versions of a package that are not allowed by the policies.
+    ///
+    /// This is intended to wrap the `list_available_versions` function
+    /// given to [`solve_deps_with`](crate::solver::solve_deps_with).
+    pub fn filter_versions<'a, I>(
+        &self,
+        pkg: &Pkg,
+        versions: I,
+    ) -> impl Iterator<Item = SemVer> + 'a
+    where
+        I: Iterator<Item = SemVer> + 'a,
+    {
+        let allowed = self.policies.get(pkg).map(|policy| policy.allowed());
+        versions.filter(move |v| match &allowed {
+            Some(range) => range.contains(v),
+            None => true,
+        })
+    }
+
+    /// Report policies which excluded available versions of packages in the solution.
+    pub fn effects<L, I>(
+        &self,
+        solution: &AppDependencies,
+        list_available_versions: L,
+    ) -> Vec<PolicyEffect>
+    where
+        L: Fn(&Pkg) -> I,
+        I: Iterator<Item = SemVer>,
+    {
+        (solution.direct.iter())
+            .chain(solution.indirect.iter())
+            .filter_map(|(pkg, selected)| {
+                let policy = self.policies.get(pkg)?;
+                let allowed = policy.allowed();
+                let excluded: Vec<SemVer> = list_available_versions(pkg)
+                    .filter(|v| !allowed.contains(v))
+                    .collect();
+                if excluded.is_empty() {
+                    return None;
+                }
+                Some(PolicyEffect {
+                    pkg: pkg.clone(),
+                    selected: *selected,
+                    excluded,
+                    reason: policy.reason.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Load policies from a JSON file.
+    #[cfg(feature = "fs")]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CacheError> {
+        let s = std::fs::read_to_string(path)?;
+        serde_json::from_str(&s).map_err(|e| e.into())
+    }
+
+    /// Default location of the policies file.
+    /// ~/.elm/pubgrub/policies.json
+    pub fn default_path<P: AsRef<Path>>(elm_home: P) -> PathBuf {
+        Pkg::pubgrub_cache_dir(elm_home).join("policies.json")
+    }
+}
@@ -0,0 +1,145 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module reporting the progress of dependency resolutions as they run.
+//!
+//! Resolutions needing downloads can take a while, and editors or GUIs
+//! wrapping the solver want to show what it is doing.
+//! A [`Progress`] handle attached to a solver receives a [`ProgressEvent`]
+//! when a phase starts, when a package is chosen, when a package config is loaded,
+//! and when a version is decided or found unavailable.
+//! Events are serializable, for example to stream them as newline-delimited JSON.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::project_config::Pkg;
+use crate::provenance::Source;
+
+/// Phase of a dependency resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    /// Updating the list of existing package versions from the package server.
+    UpdateCache,
+    /// Solving the dependencies.
+    Solve,
+}
+
+/// Event of a dependency resolution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum ProgressEvent {
+    /// A phase started.
+    Phase {
+        /// The phase.
+        phase: Phase,
+    },
+    /// The config of a package version was loaded.
+    Loaded {
+        /// The package.
+        pkg: Pkg,
+        /// Its version.
+        #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::SemVerSchema"))]
+        version: SemVer,
+        /// Where its config was loaded from.
+        source: Source,
+    },
+    /// The solver chose the next package to decide, among the undecided ones.
+    Chose {
+        /// The package with the fewest compatible versions.
+        pkg: Pkg,
+        /// Its newest compatible version, or `None` if there is none,
+        /// in which case the solver derives a conflict and backtracks.
+        #[cfg_attr(
+            feature = "schemars",
+            schemars(with = "Option<crate::schema::SemVerSchema>")
+        )]
+        version: Option<SemVer>,
+        /// Number of versions compatible with the constraints on the package.
+        candidates: usize,
+    },
+    /// The config of a chosen package version does not exist,
+    /// so the solver considers it unavailable.
+    Unavailable {
+        /// The package.
+        pkg: Pkg,
+        /// Its version.
+        #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::SemVerSchema"))]
+        version: SemVer,
+    },
+    /// The solver picked a version of a package,
+    /// after loading its config, or right away if already loaded before backtracking.
+    /// A version conflicting with the previous decisions is still picked,
+    /// the solver then derives a conflict and picks another one.
+    Decided {
+        /// The package.
+        pkg: Pkg,
+        /// The version picked.
+        #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::SemVerSchema"))]
+        version: SemVer,
+        /// Number of decisions so far, including this one.
+        /// Backtracking may decide the same package multiple times.
+        decisions: usize,
+        /// Number of packages required by decided ones, but not decided yet.
+        undetermined: usize,
+    },
+    /// The resolution finished.
+    Finished {
+        /// Whether a solution was found.
+        solved: bool,
+        /// Number of decisions made.
+        decisions: usize,
+    },
+}
+
+/// Handle receiving the progress events of solvers.
+///
+/// Clones share the same callback, which may be called from several threads
+/// when solvers run in parallel.
+///
+/// ```
+/// # use elm_solve_deps::progress::{Phase, Progress, ProgressEvent};
+/// let progress = Progress::new(|event| {
+///     eprintln!("{}", serde_json::to_string(event).unwrap());
+/// });
+/// progress.emit(ProgressEvent::Phase { phase: Phase::Solve });
+/// ```
+#[derive(Clone)]
+pub struct Progress {
+    callback: Arc<dyn Fn(&ProgressEvent) + Send + Sync>,
+}
+
+impl Progress {
+    /// Create a handle calling `callback` for every event.
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(&ProgressEvent) + Send + Sync + 'static,
+    {
+        Self {
+            callback: Arc::new(callback),
+        }
+    }
+
+    /// Report an event.
+    pub fn emit(&self, event: ProgressEvent) {
+        (self.callback)(&event)
+    }
+
+    /// Handle reporting every event to this handle, then to another one.
+    pub fn and(self, other: Progress) -> Self {
+        Self::new(move |event| {
+            (self.callback)(event);
+            (other.callback)(event);
+        })
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Progress").finish_non_exhaustive()
+    }
+}
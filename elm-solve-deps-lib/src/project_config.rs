@@ -3,6 +3,9 @@
 //! Module dealing with project configuration related to the `elm.json` file.
 
 use crate::constraint::Constraint;
+use crate::hints::SolveHints;
+use crate::license::License;
+use crate::paths;
 use pubgrub::range::Range;
 use pubgrub::version::SemanticVersion as SemVer;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -15,7 +18,7 @@ use thiserror::Error;
 /// Project configuration corresponding to an `elm.json` file.
 /// It either is a package or an application.
 /// Both have different sets of fields.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum ProjectConfig {
     /// Application variant of a project config.
@@ -25,7 +28,7 @@ pub enum ProjectConfig {
 }
 
 /// Struct representing the `elm.json` of an application.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct ApplicationConfig {
     /// Source directories.
@@ -36,19 +39,35 @@ pub struct ApplicationConfig {
     pub dependencies: AppDependencies,
     /// Test dependencies of the application.
     pub test_dependencies: AppDependencies,
+    /// Hints for the dependency solver, ignored by the elm compiler.
+    #[serde(
+        rename = "x-solve-deps",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub solve_hints: Option<SolveHints>,
 }
 
 /// Dependencies of an elm application.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct AppDependencies {
     /// Direct dependencies.
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(with = "Map<Pkg, crate::schema::SemVerSchema>")
+    )]
     pub direct: Map<Pkg, SemVer>,
     /// Indirect dependencies.
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(with = "Map<Pkg, crate::schema::SemVerSchema>")
+    )]
     pub indirect: Map<Pkg, SemVer>,
 }
 
 /// Struct representing the `elm.json` of a package.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct PackageConfig {
     /// Package identifier (author + package name).
@@ -56,7 +75,7 @@ pub struct PackageConfig {
     /// Summary explanation of the package.
     pub summary: String,
     /// License of the package.
-    pub license: String,
+    pub license: License,
     /// Version of the package.
     pub version: SemVer,
     /// Version of elm that is compatible with this package.
@@ -67,6 +86,13 @@ pub struct PackageConfig {
     pub dependencies: Map<Pkg, Constraint>,
     /// Test dependencies of the package.
     pub test_dependencies: Map<Pkg, Constraint>,
+    /// Hints for the dependency solver, ignored by the elm compiler.
+    #[serde(
+        rename = "x-solve-deps",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub solve_hints: Option<SolveHints>,
 }
 
 /// A package identifier, composed of the author name and the package name.
@@ -84,10 +110,81 @@ pub enum PkgParseError {
     /// Error corresponding to a missing separator between the author and package name.
     #[error("no author/package separation found in `{0}`")]
     NoAuthorSeparator(String),
+    /// The author name is empty.
+    #[error("empty author in `{0}`")]
+    EmptyAuthor(String),
+    /// The package name is empty.
+    #[error("empty package name in `{0}`")]
+    EmptyPackage(String),
+    /// The package identifier contains whitespace.
+    #[error("whitespace in the package identifier `{0}`")]
+    Whitespace(String),
+}
+
+/// How a package entered the solution of an application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Origin {
+    /// Direct dependency.
+    NormalDirect,
+    /// Direct test dependency, that is not a normal dependency.
+    TestDirect,
+    /// Indirect dependency, needed by the normal dependencies.
+    IndirectOfNormal,
+    /// Indirect test dependency, only needed by the test dependencies.
+    IndirectOfTest,
+}
+
+/// Version of a package in a [`SolutionDetailed`], with its origin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DetailedVersion {
+    /// The picked version.
+    pub version: SemVer,
+    /// How the package entered the solution.
+    pub origin: Origin,
+}
+
+/// Solution of an application where every package is tagged with its origin.
+///
+/// Contrary to an [`AppDependencies`] solved with test dependencies,
+/// it tells which packages are only needed for tests,
+/// which is required to write back the test sections of an application `elm.json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SolutionDetailed {
+    /// The version and origin of every package of the solution.
+    pub packages: Map<Pkg, DetailedVersion>,
+}
+
+/// Error type for parsing a standalone dependencies fragment of an application `elm.json`.
+#[derive(Error, Debug)]
+pub enum FragmentParseError {
+    /// The fragment is not valid JSON, or has missing or unknown fields.
+    #[error("invalid dependencies fragment")]
+    JsonError(#[from] serde_json::Error),
+
+    /// A package is listed in two sections of the fragment.
+    #[error("{pkg} is listed both in {first} and {second}")]
+    Duplicate {
+        /// The package listed twice.
+        pkg: Pkg,
+        /// The first section listing it.
+        first: &'static str,
+        /// The second section listing it.
+        second: &'static str,
+    },
+}
+
+/// Strict counterpart of [`AppDependencies`] for parsing fragments, rejecting unknown fields.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct StrictAppDependencies {
+    direct: Map<Pkg, SemVer>,
+    indirect: Map<Pkg, SemVer>,
 }
 
 /// Exposed modules, potentially regrouped by categories.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum ExposedModules {
     /// All modules are exposed at the same hierarchy.
@@ -96,6 +193,613 @@ pub enum ExposedModules {
     WithCategories(Map<String, Vec<String>>),
 }
 
+/// Error type for in-place updates of an application `elm.json`.
+#[derive(Error, Debug)]
+pub enum ElmJsonWriteError {
+    /// Failed to read or write the `elm.json` file.
+    #[error("unable to read/write elm.json")]
+    FileIoError(#[from] std::io::Error),
+
+    /// The `elm.json` file is not valid JSON or not an application config.
+    #[error("failed to parse the application elm.json")]
+    JsonError(#[from] serde_json::Error),
+
+    /// A dependencies section is missing at the top level of the `elm.json` file.
+    #[error("no top level \"{0}\" field found in elm.json")]
+    MissingField(&'static str),
+}
+
+/// Difference between the dependencies of a package and of an application built from it,
+/// as reported by [`ApplicationConfig::check_against_package`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PackageMismatch {
+    /// The elm version of the application is not allowed by the package.
+    ElmVersion {
+        /// The elm versions allowed by the package.
+        constraint: Constraint,
+        /// The elm version of the application.
+        version: SemVer,
+    },
+    /// A dependency of the package is not a direct dependency of the application.
+    Missing {
+        /// The missing dependency.
+        pkg: Pkg,
+        /// Its constraint in the package.
+        constraint: Constraint,
+        /// Whether it is a test dependency of the package.
+        test: bool,
+    },
+    /// A dependency of the application is at a version outside of its constraint in the package.
+    OutOfRange {
+        /// The dependency.
+        pkg: Pkg,
+        /// Its constraint in the package.
+        constraint: Constraint,
+        /// Its version in the application.
+        version: SemVer,
+    },
+    /// A direct dependency of the application is not a dependency of the package.
+    Extra {
+        /// The dependency.
+        pkg: Pkg,
+        /// Its version in the application.
+        version: SemVer,
+    },
+}
+
+/// Error converting a package config into an application config,
+/// because the solution does not satisfy the dependencies of the package.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("the solution does not fit the package: {}", display_mismatches(.0))]
+pub struct AsApplicationError(pub Vec<PackageMismatch>);
+
+fn display_mismatches(mismatches: &[PackageMismatch]) -> String {
+    let mismatches: Vec<String> = mismatches.iter().map(|m| m.to_string()).collect();
+    mismatches.join(", ")
+}
+
+impl fmt::Display for PackageMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ElmVersion {
+                constraint,
+                version,
+            } => write!(f, "elm {} is not in {}", version, constraint),
+            Self::Missing {
+                pkg, test: true, ..
+            } => write!(f, "missing test dependency {}", pkg),
+            Self::Missing { pkg, .. } => write!(f, "missing dependency {}", pkg),
+            Self::OutOfRange {
+                pkg,
+                constraint,
+                version,
+            } => write!(f, "{} {} is not in {}", pkg, version, constraint),
+            Self::Extra { pkg, version } => write!(f, "extra dependency {} {}", pkg, version),
+        }
+    }
+}
+
+impl ProjectConfig {
+    /// Hints for the dependency solver, from the `"x-solve-deps"` field of the `elm.json`.
+    pub fn solve_hints(&self) -> Option<&SolveHints> {
+        match self {
+            ProjectConfig::Application(app_config) => app_config.solve_hints.as_ref(),
+            ProjectConfig::Package(pkg_config) => pkg_config.solve_hints.as_ref(),
+        }
+    }
+
+    /// Serialize the config like the elm compiler writes `elm.json` files.
+    pub fn to_elm_json(&self) -> String {
+        // Fields are declared in the order of the elm compiler, only the formatting matters.
+        canonical_json(self)
+    }
+
+    /// Application config to build the examples or tests of a package,
+    /// with its dependencies pinned to the versions of a solution,
+    /// typically obtained by solving the package with its test dependencies,
+    /// and its modules in the given source directories.
+    ///
+    /// Dependencies of the package are the direct dependencies of the application,
+    /// and its other test dependencies are the direct test dependencies of the application.
+    /// The other packages of the solution are all indirect normal dependencies,
+    /// since the solution does not tell which ones are only needed by tests,
+    /// and the elm compiler accepts that.
+    /// The application is then checked with [`ApplicationConfig::check_against_package`].
+    ///
+    /// ```
+    /// # use elm_solve_deps::project_config::{AppDependencies, PackageConfig, Pkg, ProjectConfig};
+    /// # use elm_solve_deps::semver::SemVer;
+    /// let package: PackageConfig = serde_json::from_str(
+    ///     r#"{
+    ///         "type": "package",
+    ///         "name": "author/package",
+    ///         "summary": "A package with tests",
+    ///         "license": "BSD-3-Clause",
+    ///         "version": "1.0.0",
+    ///         "exposed-modules": [],
+    ///         "elm-version": "0.19.0 <= v < 0.20.0",
+    ///         "dependencies": { "elm/core": "1.0.0 <= v < 2.0.0" },
+    ///         "test-dependencies": { "elm-explorations/test": "2.0.0 <= v < 3.0.0" }
+    ///     }"#,
+    /// )
+    /// .unwrap();
+    /// let (core, test, random) = (
+    ///     Pkg::new("elm", "core"),
+    ///     Pkg::new("elm-explorations", "test"),
+    ///     Pkg::new("elm", "random"),
+    /// );
+    /// let mut solution = AppDependencies::default();
+    /// solution.direct.insert(core.clone(), SemVer::new(1, 0, 5));
+    /// solution.direct.insert(test.clone(), SemVer::new(2, 1, 1));
+    /// solution.indirect.insert(random.clone(), SemVer::new(1, 0, 0));
+    /// let dirs = vec![".".to_string(), "../src".to_string()];
+    /// let app = ProjectConfig::package_to_application(&package, &solution, dirs).unwrap();
+    /// assert_eq!(app.source_directories, [".", "../src"]);
+    /// assert_eq!(app.dependencies.direct.keys().collect::<Vec<_>>(), [&core]);
+    /// assert_eq!(app.dependencies.indirect.keys().collect::<Vec<_>>(), [&random]);
+    /// assert_eq!(app.test_dependencies.direct.keys().collect::<Vec<_>>(), [&test]);
+    /// assert!(app.test_dependencies.indirect.is_empty());
+    /// ```
+    pub fn package_to_application(
+        package: &PackageConfig,
+        solution: &AppDependencies,
+        source_directories: Vec<String>,
+    ) -> Result<ApplicationConfig, AsApplicationError> {
+        let version_of = |pkg: &Pkg| {
+            (solution.direct.get(pkg))
+                .or_else(|| solution.indirect.get(pkg))
+                .map(|v| (pkg.clone(), *v))
+        };
+        let direct: Map<Pkg, SemVer> = (package.dependencies.keys())
+            .filter_map(version_of)
+            .collect();
+        let test_direct: Map<Pkg, SemVer> = (package.test_dependencies.keys())
+            .filter(|pkg| !direct.contains_key(pkg))
+            .filter_map(version_of)
+            .collect();
+        let indirect = (solution.direct.iter())
+            .chain(solution.indirect.iter())
+            .filter(|(pkg, _)| !direct.contains_key(pkg) && !test_direct.contains_key(pkg))
+            .map(|(pkg, v)| (pkg.clone(), *v))
+            .collect();
+        let application = ApplicationConfig {
+            source_directories,
+            elm_version: SemVer::new(0, 19, 1),
+            dependencies: AppDependencies { direct, indirect },
+            test_dependencies: AppDependencies {
+                direct: test_direct,
+                indirect: Map::new(),
+            },
+            solve_hints: package.solve_hints.clone(),
+        };
+        let mismatches = application.check_against_package(package);
+        if mismatches.is_empty() {
+            Ok(application)
+        } else {
+            Err(AsApplicationError(mismatches))
+        }
+    }
+}
+
+impl AppDependencies {
+    /// Serialize the dependencies into their canonical JSON form.
+    ///
+    /// The canonical form is stable across runs for a given solution,
+    /// such that lock files and golden tests do not churn:
+    /// keys are sorted, versions are strings, indentation is 4 spaces like the elm compiler,
+    /// and the output ends with exactly one newline.
+    ///
+    /// ```
+    /// # use elm_solve_deps::project_config::{AppDependencies, Pkg};
+    /// # use elm_solve_deps::semver::SemVer;
+    /// let mut deps = AppDependencies::default();
+    /// deps.direct.insert(Pkg::new("elm", "json"), SemVer::new(1, 1, 3));
+    /// deps.direct.insert(Pkg::new("elm", "core"), SemVer::new(1, 0, 5));
+    /// let expected = r#"{
+    ///     "direct": {
+    ///         "elm/core": "1.0.5",
+    ///         "elm/json": "1.1.3"
+    ///     },
+    ///     "indirect": {}
+    /// }
+    /// "#;
+    /// assert_eq!(deps.to_canonical_json(), expected);
+    /// ```
+    pub fn to_canonical_json(&self) -> String {
+        canonical_json(self)
+    }
+
+    /// Directories containing the modules of every package of the solution, in `ELM_HOME`.
+    ///
+    /// Tools close to the compiler, such as elm-review or language servers,
+    /// need them to build their module search path.
+    /// Direct dependencies come first, then indirect ones, each in alphabetical order.
+    /// Paths are absolute if `elm_home` is, and are listed whether the packages are installed or not.
+    ///
+    /// ```
+    /// # use elm_solve_deps::project_config::{AppDependencies, Pkg};
+    /// # use elm_solve_deps::semver::SemVer;
+    /// # use std::path::PathBuf;
+    /// let mut deps = AppDependencies::default();
+    /// deps.direct.insert(Pkg::new("elm", "core"), SemVer::new(1, 0, 5));
+    /// let paths = deps.module_search_paths("/home/user/.elm", "0.19.1");
+    /// let core_src = PathBuf::from("/home/user/.elm/0.19.1/packages/elm/core/1.0.5/src");
+    /// assert_eq!(paths, vec![core_src]);
+    /// ```
+    pub fn module_search_paths<P: AsRef<Path>>(
+        &self,
+        elm_home: P,
+        elm_version: &str,
+    ) -> Vec<PathBuf> {
+        (self.direct.iter())
+            .chain(self.indirect.iter())
+            .map(|(pkg, version)| {
+                (pkg.config_path(&elm_home, elm_version))
+                    .join(version.to_string())
+                    .join("src")
+            })
+            .collect()
+    }
+}
+
+/// Print the dependencies like the `"dependencies"` field of an application `elm.json`,
+/// in canonical form without the final newline.
+impl fmt::Display for AppDependencies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_canonical_json().trim_end())
+    }
+}
+
+/// Parse the `"dependencies"` field of an application `elm.json` on its own.
+///
+/// Both the `"direct"` and `"indirect"` sections are required,
+/// other fields are rejected, and packages cannot be listed in both sections.
+///
+/// ```
+/// # use elm_solve_deps::project_config::{AppDependencies, Pkg};
+/// # use std::str::FromStr;
+/// let fragment = r#"{ "direct": { "elm/core": "1.0.5" }, "indirect": { "elm/json": "1.1.3" } }"#;
+/// let deps = AppDependencies::from_str(fragment).unwrap();
+/// assert_eq!(deps.indirect[&Pkg::new("elm", "json")], (1, 1, 3).into());
+/// assert_eq!(AppDependencies::from_str(&deps.to_string()).unwrap(), deps);
+///
+/// let duplicate = r#"{ "direct": { "elm/core": "1.0.5" }, "indirect": { "elm/core": "1.0.5" } }"#;
+/// assert!(AppDependencies::from_str(duplicate).is_err());
+/// ```
+impl FromStr for AppDependencies {
+    type Err = FragmentParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let strict: StrictAppDependencies = serde_json::from_str(s)?;
+        let deps: Self = strict.into();
+        check_disjoint(&[("direct", &deps.direct), ("indirect", &deps.indirect)])?;
+        Ok(deps)
+    }
+}
+
+impl From<StrictAppDependencies> for AppDependencies {
+    fn from(strict: StrictAppDependencies) -> Self {
+        Self {
+            direct: strict.direct,
+            indirect: strict.indirect,
+        }
+    }
+}
+
+/// Fail if a package is listed in two of the given named sections.
+pub(crate) fn check_disjoint(
+    sections: &[(&'static str, &Map<Pkg, SemVer>)],
+) -> Result<(), FragmentParseError> {
+    for (i, (first, packages)) in sections.iter().enumerate() {
+        for (second, others) in &sections[i + 1..] {
+            if let Some(pkg) = packages.keys().find(|pkg| others.contains_key(*pkg)) {
+                return Err(FragmentParseError::Duplicate {
+                    pkg: pkg.clone(),
+                    first,
+                    second,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Serialize a value into its canonical JSON form,
+/// as described in [`AppDependencies::to_canonical_json`].
+///
+/// Only valid for types whose fields are declared in sorted order,
+/// and whose maps are sorted, such as `BTreeMap`.
+pub(crate) fn canonical_json<T: Serialize>(value: &T) -> String {
+    let mut json = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut json, formatter);
+    (value.serialize(&mut serializer)).expect("Solutions only contain string keys and values");
+    json.push(b'\n');
+    String::from_utf8(json).expect("serde_json only produces valid UTF-8")
+}
+
+impl SolutionDetailed {
+    /// Tag the packages of normal dependencies, and of the additional test dependencies,
+    /// typically obtained with [`ApplicationConfig::test_dependencies_of`].
+    /// Packages of the normal dependencies keep their normal origin.
+    pub fn from_phases(
+        dependencies: &AppDependencies,
+        test_dependencies: &AppDependencies,
+    ) -> Self {
+        let sections = [
+            (&test_dependencies.indirect, Origin::IndirectOfTest),
+            (&test_dependencies.direct, Origin::TestDirect),
+            (&dependencies.indirect, Origin::IndirectOfNormal),
+            (&dependencies.direct, Origin::NormalDirect),
+        ];
+        let mut packages = Map::new();
+        for (section, origin) in sections {
+            for (pkg, version) in section {
+                let version = *version;
+                packages.insert(pkg.clone(), DetailedVersion { version, origin });
+            }
+        }
+        Self { packages }
+    }
+
+    /// Packages of the given origins.
+    fn select(&self, origins: &[Origin]) -> Map<Pkg, SemVer> {
+        (self.packages.iter())
+            .filter(|(_, v)| origins.contains(&v.origin))
+            .map(|(p, v)| (p.clone(), v.version))
+            .collect()
+    }
+
+    /// The normal dependencies, as in the `"dependencies"` field of an application `elm.json`.
+    pub fn dependencies(&self) -> AppDependencies {
+        AppDependencies {
+            direct: self.select(&[Origin::NormalDirect]),
+            indirect: self.select(&[Origin::IndirectOfNormal]),
+        }
+    }
+
+    /// The test dependencies, as in the `"test-dependencies"` field of an application `elm.json`.
+    pub fn test_dependencies(&self) -> AppDependencies {
+        AppDependencies {
+            direct: self.select(&[Origin::TestDirect]),
+            indirect: self.select(&[Origin::IndirectOfTest]),
+        }
+    }
+
+    /// All packages split into direct and indirect ones,
+    /// like a solution obtained by solving with test dependencies.
+    pub fn combined(&self) -> AppDependencies {
+        AppDependencies {
+            direct: self.select(&[Origin::NormalDirect, Origin::TestDirect]),
+            indirect: self.select(&[Origin::IndirectOfNormal, Origin::IndirectOfTest]),
+        }
+    }
+}
+
+impl ApplicationConfig {
+    /// Update the dependencies of the application `elm.json` at `path` with a solution.
+    ///
+    /// Only the values of the `"dependencies"` and `"test-dependencies"` fields are rewritten,
+    /// the rest of the file is left untouched, including key order and formatting.
+    /// The `test-dependencies` field is only rewritten if a `test_solution` is provided,
+    /// obtained by solving with test dependencies.
+    /// Test dependencies then only list packages not already in the normal dependencies,
+    /// as expected by the elm compiler.
+    #[cfg(feature = "fs")]
+    pub fn apply_and_write<P: AsRef<Path>>(
+        path: P,
+        solution: &AppDependencies,
+        test_solution: Option<&AppDependencies>,
+    ) -> Result<(), ElmJsonWriteError> {
+        let path = path.as_ref();
+        let mut elm_json = std::fs::read_to_string(path)?;
+        // Check that this is a valid application config before touching it.
+        let _: ApplicationConfig = serde_json::from_str(&elm_json)?;
+        if let Some(test_solution) = test_solution {
+            let test_deps = Self::test_dependencies_of(solution, test_solution);
+            replace_top_level_value(&mut elm_json, "test-dependencies", &test_deps)?;
+        }
+        replace_top_level_value(&mut elm_json, "dependencies", solution)?;
+        std::fs::write(path, elm_json).map_err(|e| e.into())
+    }
+
+    /// Update both the normal and test dependencies of the application `elm.json` at `path`
+    /// with a detailed solution, as described in [`apply_and_write`](Self::apply_and_write).
+    #[cfg(feature = "fs")]
+    pub fn apply_detailed_and_write<P: AsRef<Path>>(
+        path: P,
+        solution: &SolutionDetailed,
+    ) -> Result<(), ElmJsonWriteError> {
+        let dependencies = solution.dependencies();
+        let test_dependencies = solution.test_dependencies();
+        Self::apply_and_write(path, &dependencies, Some(&test_dependencies))
+    }
+
+    /// Extract the test dependencies from a solution obtained with test dependencies,
+    /// removing packages already part of the normal solution.
+    pub fn test_dependencies_of(
+        solution: &AppDependencies,
+        test_solution: &AppDependencies,
+    ) -> AppDependencies {
+        let is_normal =
+            |p: &Pkg| solution.direct.contains_key(p) || solution.indirect.contains_key(p);
+        let direct: Map<Pkg, SemVer> = (test_solution.direct.iter())
+            .filter(|(p, _)| !is_normal(p))
+            .map(|(p, v)| (p.clone(), *v))
+            .collect();
+        let indirect = (test_solution.indirect.iter())
+            .filter(|(p, _)| !is_normal(p) && !direct.contains_key(p))
+            .map(|(p, v)| (p.clone(), *v))
+            .collect();
+        AppDependencies { direct, indirect }
+    }
+
+    /// Check that the application builds a package, which is the inverse
+    /// of [`ProjectConfig::package_to_application`].
+    ///
+    /// The elm version and all direct dependencies of the application must satisfy
+    /// the constraints of the package, and test dependencies of the package
+    /// must be direct dependencies or direct test dependencies of the application.
+    /// Direct dependencies of the application that the package does not declare
+    /// are reported as extra.
+    pub fn check_against_package(&self, package: &PackageConfig) -> Vec<PackageMismatch> {
+        let mut mismatches = Vec::new();
+        if !package.elm_version.0.contains(&self.elm_version) {
+            mismatches.push(PackageMismatch::ElmVersion {
+                constraint: package.elm_version.clone(),
+                version: self.elm_version,
+            });
+        }
+        let normal = &self.dependencies.direct;
+        let test = &self.test_dependencies.direct;
+        let declared = [
+            (&package.dependencies, false),
+            (&package.test_dependencies, true),
+        ];
+        for (dependencies, is_test) in declared {
+            for (pkg, constraint) in dependencies {
+                let version = match normal.get(pkg).or(test.get(pkg).filter(|_| is_test)) {
+                    Some(version) => *version,
+                    None => {
+                        mismatches.push(PackageMismatch::Missing {
+                            pkg: pkg.clone(),
+                            constraint: constraint.clone(),
+                            test: is_test,
+                        });
+                        continue;
+                    }
+                };
+                if !constraint.0.contains(&version) {
+                    mismatches.push(PackageMismatch::OutOfRange {
+                        pkg: pkg.clone(),
+                        constraint: constraint.clone(),
+                        version,
+                    });
+                }
+            }
+        }
+        let is_declared = |pkg: &Pkg| {
+            package.dependencies.contains_key(pkg) || package.test_dependencies.contains_key(pkg)
+        };
+        for (pkg, version) in normal.iter().chain(test).filter(|(p, _)| !is_declared(p)) {
+            mismatches.push(PackageMismatch::Extra {
+                pkg: pkg.clone(),
+                version: *version,
+            });
+        }
+        mismatches
+    }
+
+    /// All package versions recorded in the `elm.json`,
+    /// including indirect and test dependencies.
+    pub fn recorded_versions(&self) -> Map<Pkg, SemVer> {
+        let deps = [&self.dependencies, &self.test_dependencies];
+        (deps.iter())
+            .flat_map(|d| d.direct.iter().chain(d.indirect.iter()))
+            .map(|(p, v)| (p.clone(), *v))
+            .collect()
+    }
+}
+
+/// Replace the value of a top level field of a JSON object, given as text,
+/// with the dependencies formatted like the elm compiler does.
+#[cfg(feature = "fs")]
+fn replace_top_level_value(
+    json: &mut String,
+    key: &'static str,
+    deps: &AppDependencies,
+) -> Result<(), ElmJsonWriteError> {
+    let value_span = find_top_level_value(json, key).ok_or(ElmJsonWriteError::MissingField(key))?;
+    // Reuse the indentation of the line containing the field.
+    let line_start = json[..value_span.start].rfind('\n').map_or(0, |i| i + 1);
+    let indent: String = json[line_start..]
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect();
+    let unit = if indent.is_empty() { "    " } else { &indent };
+    let formatted = format_app_dependencies(deps, &indent, unit);
+    json.replace_range(value_span, &formatted);
+    Ok(())
+}
+
+/// Format dependencies with the nested indentation style of the elm compiler.
+#[cfg(feature = "fs")]
+fn format_app_dependencies(deps: &AppDependencies, indent: &str, unit: &str) -> String {
+    let format_map = |map: &Map<Pkg, SemVer>| {
+        if map.is_empty() {
+            return "{}".to_string();
+        }
+        let entries: Vec<String> = map
+            .iter()
+            .map(|(p, v)| format!("{}{}{}\"{}\": \"{}\"", indent, unit, unit, p, v))
+            .collect();
+        format!("{{\n{}\n{}{}}}", entries.join(",\n"), indent, unit)
+    };
+    format!(
+        "{{\n{i}{u}\"direct\": {},\n{i}{u}\"indirect\": {}\n{i}}}",
+        format_map(&deps.direct),
+        format_map(&deps.indirect),
+        i = indent,
+        u = unit,
+    )
+}
+
+/// Find the byte span of the value of a top level field in a JSON object.
+#[cfg(feature = "fs")]
+fn find_top_level_value(json: &str, key: &str) -> Option<std::ops::Range<usize>> {
+    let bytes = json.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    let mut value_start = None;
+    let mut last_key: Option<std::ops::Range<usize>> = None;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                let start = i + 1;
+                i = start;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    // Skip escaped characters.
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                if depth == 1 && value_start.is_none() {
+                    last_key = Some(start..i.min(bytes.len()));
+                }
+            }
+            b':' if depth == 1 => {
+                value_start = Some(i + 1);
+            }
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' | b',' if depth == 1 => {
+                if let (Some(start), Some(k)) = (value_start.take(), last_key.take()) {
+                    if &json[k] == key {
+                        let trimmed = json[start..i].trim();
+                        let offset = json[start..i].find(trimmed)?;
+                        return Some(start + offset..start + offset + trimmed.len());
+                    }
+                }
+                if bytes[i] == b'}' {
+                    return None;
+                }
+            }
+            b'}' | b']' => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+impl ExposedModules {
+    /// Set of all exposed modules, whatever their category.
+    pub fn modules(&self) -> std::collections::BTreeSet<&String> {
+        match self {
+            ExposedModules::NoCategory(modules) => modules.iter().collect(),
+            ExposedModules::WithCategories(categories) => categories.values().flatten().collect(),
+        }
+    }
+}
+
 impl PackageConfig {
     /// Generate an iterator over a package dependencies.
     pub fn dependencies_iter(&self) -> impl Iterator<Item = (&Pkg, &Range<SemVer>)> {
@@ -117,16 +821,14 @@ impl Pkg {
 
     /// Get the location of the cache directory for the dependency solver.
     ///
-    /// TODO: why is this function here?
+    /// Same as [`paths::pubgrub_cache_dir`](crate::paths::pubgrub_cache_dir).
     pub fn pubgrub_cache_dir<P: AsRef<Path>>(elm_home: P) -> PathBuf {
-        elm_home.as_ref().join("pubgrub")
+        paths::pubgrub_cache_dir(elm_home)
     }
 
     /// Get the path to the folder inside `ELM_HOME` containing the different installed versions of this package.
     pub fn config_path<P: AsRef<Path>>(&self, elm_home: P, elm_version: &str) -> PathBuf {
-        Self::packages_dir(elm_home, elm_version)
-            .join(&self.author)
-            .join(&self.pkg)
+        paths::package_dir(elm_home, elm_version, self)
     }
 
     /// Get the url corresponding to this package on the package server.
@@ -139,18 +841,10 @@ impl Pkg {
     /// Get the path to the dependency solver's cache folder for this package.
     ///
     /// This looks like `cache_home/elm_json_cache/author/package/`.
+    ///
+    /// Same as [`paths::package_cache_dir`](crate::paths::package_cache_dir).
     pub fn pubgrub_cache_dir_json<P: AsRef<Path>>(&self, elm_home: P) -> PathBuf {
-        Self::pubgrub_cache_dir(elm_home)
-            .join("elm_json_cache")
-            .join(&self.author)
-            .join(&self.pkg)
-    }
-}
-
-// Private Pkg methods.
-impl Pkg {
-    fn packages_dir<P: AsRef<Path>>(elm_home: P, elm_version: &str) -> PathBuf {
-        elm_home.as_ref().join(elm_version).join("packages")
+        paths::package_cache_dir(elm_home, self)
     }
 }
 
@@ -162,6 +856,13 @@ impl FromStr for Pkg {
             .ok_or_else(|| PkgParseError::NoAuthorSeparator(s.to_string()))?;
         let author = s[0..author_sep].to_string();
         let pkg = s[(author_sep + 1)..].to_string();
+        if s.contains(char::is_whitespace) {
+            return Err(PkgParseError::Whitespace(s.to_string()));
+        } else if author.is_empty() {
+            return Err(PkgParseError::EmptyAuthor(s.to_string()));
+        } else if pkg.is_empty() {
+            return Err(PkgParseError::EmptyPackage(s.to_string()));
+        }
         Ok(Pkg { author, pkg })
     }
 }
@@ -0,0 +1,170 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module tracking where the config of each package version was loaded from.
+//!
+//! Solvers record the [`Source`] of every `elm.json` they load in a shared [`Provenance`] log:
+//! the installed packages of `ELM_HOME`, the dependency solver cache,
+//! a shared cache, the package server, a fallback when the package server was unavailable,
+//! or an unreleased version overriding the package.
+//! Once solved, [`Provenance::report`] tells where each package of the solution comes from,
+//! which helps debugging stale caches, and keeping audit trails.
+//! With a [`Federation`](crate::federation::Federation) of package servers,
+//! it also tells which package server each package version comes from,
+//! and with the packages directories of several versions of elm merged,
+//! with [`Directories`](crate::elm_home::Directories), which one each installed package comes from.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::pkg_version::PkgVersion;
+use crate::project_config::{AppDependencies, Pkg};
+
+/// Where the config of a package version was loaded from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Source {
+    /// Installed packages, in `ELM_HOME` or an archive of it.
+    Installed,
+    /// The dependency solver cache, in `ELM_HOME/pubgrub`.
+    Cache,
+    /// A cache shared between `ELM_HOME`s.
+    SharedCache,
+    /// The package server.
+    Fetched {
+        /// The url of the request.
+        url: String,
+        /// When the request was made, in seconds since the unix epoch.
+        timestamp: u64,
+    },
+    /// A fallback of the package server, such as GitHub,
+    /// see [`ConfigFallback`](crate::pkg_version::ConfigFallback).
+    Fallback {
+        /// The url of the request.
+        url: String,
+        /// When the request was made, in seconds since the unix epoch.
+        timestamp: u64,
+    },
+    /// An unreleased version overriding the package,
+    /// see [`PackageOverride`](crate::overrides::PackageOverride).
+    Override {
+        /// The git repository and ref, or the local path, of the override.
+        origin: String,
+    },
+}
+
+/// Source of one package version of a solution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct PackageProvenance {
+    /// The package identifier.
+    pub pkg: Pkg,
+    /// The version selected in the solution.
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::SemVerSchema"))]
+    pub version: SemVer,
+    /// Where its config was loaded from,
+    /// or `None` if it was not loaded, for example when reusing a previous solution.
+    pub source: Option<Source>,
+    /// Name of the package server publishing it, with a federation of package servers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+    /// Version of elm of the packages directory it was installed in,
+    /// with the packages directories of several versions of elm merged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elm_version: Option<String>,
+}
+
+/// Shared log of the sources of loaded package configs.
+///
+/// Clones share the same underlying log,
+/// such that sources recorded by a solver are visible to all handles.
+#[derive(Debug, Clone, Default)]
+pub struct Provenance {
+    sources: Arc<Mutex<BTreeMap<(Pkg, SemVer), Source>>>,
+    remotes: Arc<Mutex<BTreeMap<(Pkg, SemVer), String>>>,
+    elm_versions: Arc<Mutex<BTreeMap<(Pkg, SemVer), String>>>,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Installed => write!(f, "installed"),
+            Self::Cache => write!(f, "cache"),
+            Self::SharedCache => write!(f, "shared cache"),
+            Self::Fetched { url, timestamp } => write!(f, "fetched {} at {}", url, timestamp),
+            Self::Fallback { url, timestamp } => {
+                write!(f, "fetched {} at {} (fallback)", url, timestamp)
+            }
+            Self::Override { origin } => write!(f, "overridden by {}", origin),
+        }
+    }
+}
+
+impl Provenance {
+    /// Initialize an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the source of a loaded package config,
+    /// replacing the one recorded by a previous resolution if any.
+    pub fn record(&self, pkg_version: &PkgVersion, source: Source) {
+        let key = (pkg_version.author_pkg.clone(), pkg_version.version);
+        self.sources.lock().unwrap().insert(key, source);
+    }
+
+    /// Source of the config of a package version, if it was loaded.
+    pub fn source(&self, pkg: &Pkg, version: SemVer) -> Option<Source> {
+        let sources = self.sources.lock().unwrap();
+        sources.get(&(pkg.clone(), version)).cloned()
+    }
+
+    /// Record the name of the package server publishing a loaded package version.
+    pub fn record_remote(&self, pkg_version: &PkgVersion, remote_name: &str) {
+        let key = (pkg_version.author_pkg.clone(), pkg_version.version);
+        self.remotes
+            .lock()
+            .unwrap()
+            .insert(key, remote_name.to_string());
+    }
+
+    /// Name of the package server publishing a package version, if it was recorded.
+    pub fn remote(&self, pkg: &Pkg, version: SemVer) -> Option<String> {
+        let remotes = self.remotes.lock().unwrap();
+        remotes.get(&(pkg.clone(), version)).cloned()
+    }
+
+    /// Record the version of elm of the packages directory
+    /// an installed package version was loaded from.
+    pub fn record_elm_version(&self, pkg_version: &PkgVersion, elm_version: &str) {
+        let key = (pkg_version.author_pkg.clone(), pkg_version.version);
+        (self.elm_versions.lock().unwrap()).insert(key, elm_version.to_string());
+    }
+
+    /// Version of elm of the packages directory of an installed package version,
+    /// if it was recorded.
+    pub fn elm_version(&self, pkg: &Pkg, version: SemVer) -> Option<String> {
+        let elm_versions = self.elm_versions.lock().unwrap();
+        elm_versions.get(&(pkg.clone(), version)).cloned()
+    }
+
+    /// Source of every package version of a solution.
+    ///
+    /// Direct dependencies are listed first, then indirect ones, each in alphabetical order.
+    pub fn report(&self, solution: &AppDependencies) -> Vec<PackageProvenance> {
+        (solution.direct.iter())
+            .chain(solution.indirect.iter())
+            .map(|(pkg, version)| PackageProvenance {
+                pkg: pkg.clone(),
+                version: *version,
+                source: self.source(pkg, *version),
+                remote: self.remote(pkg, *version),
+                elm_version: self.elm_version(pkg, *version),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,177 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module racing several solving strategies in parallel.
+//!
+//! On pathological inputs, one strategy may need an enormous amount of backtracking
+//! while another one terminates right away.
+//! This module runs the newest, oldest and prefer-installed strategies in parallel threads,
+//! and returns the first successful solution, or all of them for comparison.
+//! Online solvers of the race share the same database of known packages,
+//! and downloaded `elm.json` files are shared through the cache in `ELM_HOME`.
+
+use pubgrub::error::PubGrubError;
+use pubgrub::report::{DefaultStringReporter, Reporter};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::budget::Limits;
+use crate::constraint::Constraint;
+use crate::fetch::HttpFetch;
+use crate::project_config::{AppDependencies, Pkg, ProjectConfig};
+use crate::solver::{ExtraMode, Offline, Online, VersionStrategy};
+
+/// A strategy taking part in a race.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RaceStrategy {
+    /// Online solver choosing the newest compatible versions.
+    Newest,
+    /// Online solver choosing the oldest compatible versions.
+    Oldest,
+    /// Online solver choosing installed versions first, then the newest compatible ones.
+    PreferInstalled,
+}
+
+/// All the strategies taking part in a race.
+pub const STRATEGIES: [RaceStrategy; 3] = [
+    RaceStrategy::Newest,
+    RaceStrategy::Oldest,
+    RaceStrategy::PreferInstalled,
+];
+
+/// Outcome of one strategy of a race.
+#[derive(Debug, Clone)]
+pub struct RaceOutcome {
+    /// The strategy.
+    pub strategy: RaceStrategy,
+    /// How long the strategy ran.
+    pub duration: Duration,
+    /// The solution, or the report of why solving failed.
+    pub result: Result<AppDependencies, String>,
+}
+
+impl fmt::Display for RaceStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Newest => write!(f, "newest"),
+            Self::Oldest => write!(f, "oldest"),
+            Self::PreferInstalled => write!(f, "prefer-installed"),
+        }
+    }
+}
+
+/// Run all strategies in parallel, and return the first successful outcome,
+/// or all the failed outcomes, in the order of [`STRATEGIES`].
+///
+/// Other strategies are cancelled as soon as one succeeds.
+/// The cancellation flag of the solvers limits is replaced by the one of the race,
+/// but their maximum number of decisions is kept.
+pub fn race<F>(
+    offline: &Offline,
+    online: &Online<F>,
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+) -> Result<RaceOutcome, Vec<RaceOutcome>>
+where
+    F: HttpFetch + Clone + Send + 'static,
+{
+    let (outcomes, cancel) = start(
+        offline,
+        online,
+        project_elm_json,
+        use_test,
+        additional_constraints,
+        extra_mode,
+    );
+    let mut failures = Vec::new();
+    for outcome in outcomes {
+        if outcome.result.is_ok() {
+            cancel.store(true, Ordering::Relaxed);
+            return Ok(outcome);
+        }
+        failures.push(outcome);
+    }
+    failures.sort_by_key(|outcome| outcome.strategy);
+    Err(failures)
+}
+
+/// Run all strategies in parallel until they all finish,
+/// and return their outcomes in the order of [`STRATEGIES`].
+pub fn race_all<F>(
+    offline: &Offline,
+    online: &Online<F>,
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+) -> Vec<RaceOutcome>
+where
+    F: HttpFetch + Clone + Send + 'static,
+{
+    let (outcomes, _) = start(
+        offline,
+        online,
+        project_elm_json,
+        use_test,
+        additional_constraints,
+        extra_mode,
+    );
+    let mut outcomes: Vec<RaceOutcome> = outcomes.into_iter().collect();
+    outcomes.sort_by_key(|outcome| outcome.strategy);
+    outcomes
+}
+
+/// Start one thread per strategy, sending their outcomes as soon as they finish.
+fn start<F>(
+    offline: &Offline,
+    online: &Online<F>,
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+) -> (Receiver<RaceOutcome>, Arc<AtomicBool>)
+where
+    F: HttpFetch + Clone + Send + 'static,
+{
+    let cancel = Arc::new(AtomicBool::new(false));
+    let limits = Limits {
+        cancel: Some(cancel.clone()),
+        ..offline.limits().clone()
+    };
+    let (sender, receiver) = mpsc::channel();
+    for strategy in STRATEGIES {
+        let project_elm_json = project_elm_json.clone();
+        let extras = additional_constraints.to_vec();
+        let version_strategy = match strategy {
+            RaceStrategy::Newest => VersionStrategy::Newest,
+            RaceStrategy::Oldest => VersionStrategy::Oldest,
+            RaceStrategy::PreferInstalled => VersionStrategy::PreferInstalled,
+        };
+        let solver = (online.clone())
+            .with_strategy(version_strategy)
+            .with_limits(limits.clone());
+        let sender = sender.clone();
+        thread::spawn(move || {
+            let started = Instant::now();
+            let result = solver.solve_deps(&project_elm_json, use_test, &extras, extra_mode);
+            let result = result.map_err(|err| match err {
+                PubGrubError::NoSolution(tree) => DefaultStringReporter::report(&tree),
+                err => err.to_string(),
+            });
+            // The receiver is gone if the race was already won.
+            let _ = sender.send(RaceOutcome {
+                strategy,
+                duration: started.elapsed(),
+                result,
+            });
+        });
+    }
+    (receiver, cancel)
+}
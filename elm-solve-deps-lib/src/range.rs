@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module re-exporting the ranges of versions used throughout this crate.
+//!
+//! This is the [`Range`] type of the pubgrub crate, re-exported unchanged,
+//! such that downstream code does not need its own dependency on pubgrub.
+//! Most of the time, the [`Constraint`](crate::constraint::Constraint) wrapper,
+//! which converts from and into a [`Range`], is more convenient.
+//!
+//! ```
+//! # use elm_solve_deps::constraint::Constraint;
+//! # use elm_solve_deps::range::Range;
+//! # use elm_solve_deps::semver::SemVer;
+//! let range: Range<SemVer> = Range::between((1, 0, 0), (2, 0, 0));
+//! assert_eq!(Constraint::from(range).to_string(), "1.0.0 <= v < 2.0.0");
+//! ```
+
+pub use pubgrub::range::Range;
@@ -0,0 +1,397 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module defining typed wrappers for the responses of the package server.
+//!
+//! The package server exposes two endpoints listing published packages:
+//!
+//! - `/all-packages`, returning a JSON object mapping every package to its published versions,
+//!   parsed into [`AllPackages`].
+//! - `/all-packages/since/N`, returning a JSON array of the `author/package@version`
+//!   published after the first `N` ones, newest first, parsed into [`SinceResponse`].
+//!
+//! Both types validate every entry when decoded,
+//! such that a malformed entry is reported with a precise error
+//! instead of failing in the middle of a cache update.
+//!
+//! This module also provides a [`search`] of packages by name and summary,
+//! working offline with the versions cache and the locally available `elm.json` files,
+//! and the [`updates`] of a package server, to watch or mirror the packages it publishes.
+
+use pubgrub::version::{SemanticVersion as SemVer, VersionParseError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::fetch::HttpFetch;
+use crate::pkg_version::{Cache, CacheError, PkgVersion, PkgVersionParseError};
+use crate::project_config::{PackageConfig, Pkg, PkgParseError};
+
+/// Response of the `/all-packages` endpoint.
+///
+/// ```
+/// # use elm_solve_deps::registry::AllPackages;
+/// # use elm_solve_deps::project_config::Pkg;
+/// // Extract of https://package.elm-lang.org/all-packages
+/// let payload = r#"{
+///     "elm/browser": ["1.0.0", "1.0.1", "1.0.2"],
+///     "elm/core": ["1.0.0", "1.0.1", "1.0.2", "1.0.3", "1.0.4", "1.0.5"],
+///     "elm/json": ["1.0.0", "1.1.0", "1.1.1", "1.1.2", "1.1.3"]
+/// }"#;
+/// let all = AllPackages::from_json(payload).unwrap();
+/// assert_eq!(all.packages[&Pkg::new("elm", "core")].len(), 6);
+///
+/// // Malformed entries are rejected with the offending entry.
+/// let err = AllPackages::from_json(r#"{ "elm/core": ["1.0"] }"#).unwrap_err();
+/// assert_eq!(err.to_string(), "invalid version `1.0` of elm/core");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "BTreeMap<String, Vec<String>>")]
+#[serde(into = "BTreeMap<String, Vec<String>>")]
+pub struct AllPackages {
+    /// Published versions of every package.
+    pub packages: BTreeMap<Pkg, BTreeSet<SemVer>>,
+}
+
+/// Response of the `/all-packages/since/N` endpoint.
+///
+/// ```
+/// # use elm_solve_deps::registry::SinceResponse;
+/// // Extract of https://package.elm-lang.org/all-packages/since/12000
+/// let payload = r#"[
+///     "dillonkearns/elm-pages@10.1.0",
+///     "elm-community/list-extra@8.7.0",
+///     "elm/core@1.0.5"
+/// ]"#;
+/// let since = SinceResponse::from_json(payload).unwrap();
+/// assert_eq!(since.oldest().unwrap().author_pkg.to_string(), "elm/core");
+/// assert_eq!(since.newer().len(), 2);
+///
+/// let err = SinceResponse::from_json(r#"["elm/core"]"#).unwrap_err();
+/// assert_eq!(err.to_string(), "invalid package version `elm/core`");
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+#[serde(into = "Vec<String>")]
+pub struct SinceResponse {
+    /// Newly published package versions, newest first.
+    pub versions: Vec<PkgVersion>,
+}
+
+/// A package matching a search query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchResult {
+    /// The package identifier.
+    pub pkg: Pkg,
+    /// The latest version of the package.
+    pub version: SemVer,
+    /// The summary of the latest version, if its config could be loaded.
+    pub summary: Option<String>,
+    /// Relevance of the package for the query, higher is better.
+    pub score: usize,
+}
+
+/// Error type for invalid responses of the package server.
+#[derive(Error, Debug)]
+pub enum RegistryError {
+    /// The response is not valid JSON, or does not have the expected shape.
+    #[error("unexpected response shape")]
+    JsonError(#[from] serde_json::Error),
+
+    /// A package identifier is malformed.
+    #[error("invalid package `{entry}`")]
+    InvalidPackage {
+        /// The malformed entry.
+        entry: String,
+        /// The parsing error.
+        #[source]
+        source: PkgParseError,
+    },
+
+    /// A version of a package is malformed.
+    #[error("invalid version `{entry}` of {pkg}")]
+    InvalidVersion {
+        /// The package this version belongs to.
+        pkg: Pkg,
+        /// The malformed entry.
+        entry: String,
+        /// The parsing error.
+        #[source]
+        source: Box<VersionParseError>,
+    },
+
+    /// A package version is malformed.
+    #[error("invalid package version `{entry}`")]
+    InvalidPkgVersion {
+        /// The malformed entry.
+        entry: String,
+        /// The parsing error.
+        #[source]
+        source: Box<PkgVersionParseError>,
+    },
+}
+
+impl AllPackages {
+    /// Decode and validate a response of the `/all-packages` endpoint.
+    pub fn from_json(s: &str) -> Result<Self, RegistryError> {
+        let raw: BTreeMap<String, Vec<String>> = serde_json::from_str(s)?;
+        Self::try_from(raw)
+    }
+}
+
+impl TryFrom<BTreeMap<String, Vec<String>>> for AllPackages {
+    type Error = RegistryError;
+    fn try_from(raw: BTreeMap<String, Vec<String>>) -> Result<Self, Self::Error> {
+        let mut packages = BTreeMap::new();
+        for (pkg_str, versions_str) in raw {
+            let pkg = Pkg::from_str(&pkg_str).map_err(|source| RegistryError::InvalidPackage {
+                entry: pkg_str.clone(),
+                source,
+            })?;
+            let versions = versions_str
+                .into_iter()
+                .map(|v| {
+                    SemVer::from_str(&v).map_err(|source| RegistryError::InvalidVersion {
+                        pkg: pkg.clone(),
+                        entry: v,
+                        source: Box::new(source),
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+            packages.insert(pkg, versions);
+        }
+        Ok(Self { packages })
+    }
+}
+
+impl From<AllPackages> for BTreeMap<String, Vec<String>> {
+    fn from(all: AllPackages) -> Self {
+        (all.packages.into_iter())
+            .map(|(p, vs)| (p.to_string(), vs.iter().map(|v| v.to_string()).collect()))
+            .collect()
+    }
+}
+
+impl SinceResponse {
+    /// Decode and validate a response of the `/all-packages/since/N` endpoint.
+    pub fn from_json(s: &str) -> Result<Self, RegistryError> {
+        let raw: Vec<String> = serde_json::from_str(s)?;
+        Self::try_from(raw)
+    }
+
+    /// Check if no package version was published since.
+    pub fn is_empty(&self) -> bool {
+        self.versions.is_empty()
+    }
+
+    /// The oldest package version of the response,
+    /// which is expected to be already known by the caller.
+    pub fn oldest(&self) -> Option<&PkgVersion> {
+        self.versions.last()
+    }
+
+    /// All package versions of the response except the oldest one.
+    pub fn newer(&self) -> &[PkgVersion] {
+        match self.versions.split_last() {
+            Some((_, newer)) => newer,
+            None => &[],
+        }
+    }
+}
+
+impl TryFrom<Vec<String>> for SinceResponse {
+    type Error = RegistryError;
+    fn try_from(raw: Vec<String>) -> Result<Self, Self::Error> {
+        let versions = raw
+            .into_iter()
+            .map(|entry| {
+                PkgVersion::from_str(&entry).map_err(|source| RegistryError::InvalidPkgVersion {
+                    entry,
+                    source: Box::new(source),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { versions })
+    }
+}
+
+impl From<SinceResponse> for Vec<String> {
+    fn from(since: SinceResponse) -> Self {
+        (since.versions.iter()).map(|v| v.to_string()).collect()
+    }
+}
+
+/// Search packages of the versions cache whose name or summary match the query.
+///
+/// The query is split into case insensitive terms.
+/// Packages are first selected by name, with at least one term matching
+/// their identifier, and every other term must match either the identifier
+/// or the summary of their latest version.
+/// Results are ranked by relevance, with matches on the package name first,
+/// then on the author, and then on the summary.
+///
+/// The `load_config` function retrieves the `elm.json` of a package version,
+/// and typically looks into `ELM_HOME` and the dependency solver cache.
+/// It is only called for the packages selected by name.
+/// Packages whose config cannot be loaded are only matched by name.
+///
+/// ```
+/// # use elm_solve_deps::registry::{search, AllPackages};
+/// # use elm_solve_deps::pkg_version::Cache;
+/// let all = AllPackages::from_json(r#"{
+///     "dillonkearns/elm-markdown": ["7.0.0"],
+///     "elm-explorations/markdown": ["1.0.0"],
+///     "elm/json": ["1.1.3"]
+/// }"#).unwrap();
+/// // Only the configs of packages selected by name are loaded.
+/// let results = search("markdown", &Cache::from(all), |pkg_version| {
+///     assert_ne!(pkg_version.author_pkg.pkg, "json");
+///     None
+/// });
+/// let names: Vec<String> = results.iter().map(|r| r.pkg.to_string()).collect();
+/// assert_eq!(names, ["elm-explorations/markdown", "dillonkearns/elm-markdown"]);
+/// ```
+pub fn search<Load>(query: &str, cache: &Cache, load_config: Load) -> Vec<SearchResult>
+where
+    Load: Fn(&PkgVersion) -> Option<PackageConfig>,
+{
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    let mut results: Vec<SearchResult> = (cache.cache.iter())
+        .filter(|(pkg, _)| terms.is_empty() || terms.iter().any(|t| matches_name(t, pkg)))
+        .filter_map(|(pkg, versions)| {
+            let version = *versions.iter().next_back()?;
+            let summary = load_config(&PkgVersion {
+                author_pkg: pkg.clone(),
+                version,
+            })
+            .map(|config| config.summary);
+            let score = search_score(&terms, pkg, summary.as_deref())?;
+            Some(SearchResult {
+                pkg: pkg.clone(),
+                version,
+                summary,
+                score,
+            })
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.pkg.cmp(&b.pkg)));
+    results
+}
+
+/// Whether a search term matches the name or the author of a package.
+fn matches_name(term: &str, pkg: &Pkg) -> bool {
+    pkg.pkg.to_lowercase().contains(term) || pkg.author.to_lowercase().contains(term)
+}
+
+/// Relevance of a package for the given search terms,
+/// or `None` if one of the terms does not match.
+fn search_score(terms: &[String], pkg: &Pkg, summary: Option<&str>) -> Option<usize> {
+    let name = pkg.pkg.to_lowercase();
+    let author = pkg.author.to_lowercase();
+    let summary = summary.unwrap_or_default().to_lowercase();
+    terms.iter().try_fold(0, |score, term| {
+        let term_score = if name == *term {
+            8
+        } else if name.contains(term.as_str()) {
+            4
+        } else if author.contains(term.as_str()) {
+            2
+        } else if summary.contains(term.as_str()) {
+            1
+        } else {
+            return None;
+        };
+        Some(score + term_score)
+    })
+}
+
+/// Iterator over the package versions published on a package server, created by [`updates`].
+pub struct Updates<'a, H: ?Sized> {
+    cache: Cache,
+    remote: String,
+    http_fetch: &'a H,
+    interval: Duration,
+    last_poll: Option<Instant>,
+}
+
+/// Poll a package server every `interval` for newly published package versions.
+///
+/// The returned iterator blocks until new package versions are published,
+/// and then yields them in a batch, in publication order, or the error of the last poll.
+/// It never ends, and the next poll after an error happens at the next interval.
+///
+/// Polls use the `/all-packages/since/N` endpoint, with the numbering of the versions `cache`.
+/// It is typically the versions cache of `ELM_HOME`, to only get versions published since,
+/// or an empty cache, to get every published package version in the first batch.
+/// When versions are deleted from the package server, shifting its numbering,
+/// the cache is resynchronized with the full list of packages,
+/// and the batch then holds the versions missing from the cache, in alphabetical order.
+///
+/// ```
+/// # use elm_solve_deps::fetch::FetchError;
+/// # use elm_solve_deps::pkg_version::Cache;
+/// # use elm_solve_deps::registry::{updates, AllPackages};
+/// # use std::time::Duration;
+/// let cache = Cache::from(AllPackages::from_json(r#"{ "elm/core": ["1.0.0"] }"#).unwrap());
+/// let http_fetch = |url: &str| -> Result<String, FetchError> {
+///     assert_eq!(url, "https://package.elm-lang.org/all-packages/since/0");
+///     Ok(r#"["elm/json@1.0.0", "elm/core@1.0.1", "elm/core@1.0.0"]"#.to_string())
+/// };
+/// let remote = "https://package.elm-lang.org";
+/// let mut updates = updates(remote, &http_fetch, cache, Duration::from_secs(60));
+/// let batch: Vec<String> = updates.next().unwrap().unwrap().iter().map(|v| v.to_string()).collect();
+/// assert_eq!(batch, ["elm/core@1.0.1", "elm/json@1.0.0"]);
+/// assert_eq!(updates.cache().versions_count(), 3);
+/// ```
+pub fn updates<'a, H: HttpFetch + ?Sized>(
+    remote: &str,
+    http_fetch: &'a H,
+    cache: Cache,
+    interval: Duration,
+) -> Updates<'a, H> {
+    Updates {
+        cache,
+        remote: remote.to_string(),
+        http_fetch,
+        interval,
+        last_poll: None,
+    }
+}
+
+impl<H: ?Sized> Updates<'_, H> {
+    /// The versions cache, up to date with the package server as of the last poll.
+    pub fn cache(&self) -> &Cache {
+        &self.cache
+    }
+
+    /// Stop polling, and return the versions cache, to save it for example.
+    pub fn into_cache(self) -> Cache {
+        self.cache
+    }
+}
+
+impl<H: HttpFetch + ?Sized> Iterator for Updates<'_, H> {
+    type Item = Result<Vec<PkgVersion>, CacheError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(last_poll) = self.last_poll {
+                std::thread::sleep(self.interval.saturating_sub(last_poll.elapsed()));
+            }
+            self.last_poll = Some(Instant::now());
+            match self.cache.update(&self.remote, self.http_fetch) {
+                Err(err) => return Some(Err(err)),
+                Ok(update) if update.added.is_empty() => continue,
+                Ok(mut update) => {
+                    if !update.resynced {
+                        // Incremental updates list versions newest first.
+                        update.added.reverse();
+                    }
+                    return Some(Ok(update.added));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module pinning the identity of package servers on first use.
+//!
+//! Users of a mirror of the package server trust it the first time they use it.
+//! Its identity is then recorded, as the number of package versions it lists
+//! and the fingerprint of that list, taken from the versions cache,
+//! such that checking it never makes a request of its own.
+//! The package versions of a registry are only ever added,
+//! so a later run listing fewer package versions for the same remote url,
+//! or as many but other ones, likely reaches another registry,
+//! and users should be warned before resolving with packages they never trusted.
+//! A registry listing more package versions is trusted, and its new identity pinned.
+//! The pinned identities are stored in `ELM_HOME/pubgrub/registries.json`.
+//!
+//! This module is only available with the `sha2` feature.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::clock::Clock;
+use crate::pkg_version::{Cache, CacheError};
+use crate::project_config::Pkg;
+
+/// Identity of a package server, recorded the first time it is used.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RegistryIdentity {
+    /// Number of package versions listed by the package server.
+    pub versions: u64,
+    /// The sha256 of the sorted list of those package versions.
+    pub fingerprint: String,
+    /// When the identity was recorded, in seconds since the unix epoch.
+    pub first_seen: u64,
+}
+
+/// Identities of the package servers already used, by remote url.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PinnedRegistries {
+    /// The pinned identities.
+    pub registries: BTreeMap<String, RegistryIdentity>,
+}
+
+/// Outcome of [`PinnedRegistries::check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PinCheck {
+    /// The package server was never used, its identity is now pinned.
+    FirstUse,
+    /// The package server has its pinned identity, or lists more package versions since then,
+    /// in which case its new identity is now pinned.
+    Trusted,
+    /// The package server has another identity than the pinned one, which is kept.
+    Changed {
+        /// The pinned identity.
+        pinned: RegistryIdentity,
+    },
+}
+
+impl RegistryIdentity {
+    /// Identity of the package server a versions cache is up to date with.
+    pub fn of_versions<C: Clock + ?Sized>(versions_cache: &Cache, clock: &C) -> Self {
+        let mut hasher = Sha256::new();
+        let mut versions = 0;
+        for (pkg, pkg_versions) in &versions_cache.cache {
+            for version in pkg_versions {
+                hasher.update(format!("{}@{}\n", pkg, version));
+                versions += 1;
+            }
+        }
+        Self {
+            versions,
+            fingerprint: format!("{:x}", hasher.finalize()),
+            first_seen: clock.unix_secs(),
+        }
+    }
+
+    /// Check if a later identity can be the one of the same registry,
+    /// listing the same package versions, or more.
+    pub fn same_registry(&self, later: &Self) -> bool {
+        match later.versions.cmp(&self.versions) {
+            std::cmp::Ordering::Less => false,
+            std::cmp::Ordering::Equal => self.fingerprint == later.fingerprint,
+            std::cmp::Ordering::Greater => true,
+        }
+    }
+}
+
+impl PinnedRegistries {
+    /// Initialize without any pinned registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compare the identity of a package server with the pinned one,
+    /// pinning it if the package server was never used.
+    ///
+    /// A changed identity is not pinned, such that it keeps being reported
+    /// until it is explicitly trusted with [`PinnedRegistries::trust`].
+    ///
+    /// ```
+    /// # use elm_solve_deps::registry_pin::{PinCheck, PinnedRegistries, RegistryIdentity};
+    /// let identity = |versions, fingerprint: &str, first_seen| RegistryIdentity {
+    ///     versions,
+    ///     fingerprint: fingerprint.to_string(),
+    ///     first_seen,
+    /// };
+    /// let remote = "https://mirror.example.com";
+    /// let mut pinned = PinnedRegistries::new();
+    /// assert_eq!(pinned.check(remote, identity(10, "abc", 1)), PinCheck::FirstUse);
+    /// assert_eq!(pinned.check(remote, identity(10, "abc", 2)), PinCheck::Trusted);
+    /// assert_eq!(pinned.check(remote, identity(12, "def", 3)), PinCheck::Trusted);
+    /// assert_eq!(pinned.registries[remote], identity(12, "def", 1));
+    /// let changed = pinned.check(remote, identity(12, "ghi", 4));
+    /// assert_eq!(changed, PinCheck::Changed { pinned: identity(12, "def", 1) });
+    /// let changed = pinned.check(remote, identity(11, "jkl", 5));
+    /// assert_eq!(changed, PinCheck::Changed { pinned: identity(12, "def", 1) });
+    /// ```
+    pub fn check(&mut self, remote_base_url: &str, identity: RegistryIdentity) -> PinCheck {
+        match self.registries.get_mut(remote_base_url) {
+            None => {
+                self.trust(remote_base_url, identity);
+                PinCheck::FirstUse
+            }
+            Some(pinned) if pinned.same_registry(&identity) => {
+                let first_seen = pinned.first_seen;
+                *pinned = RegistryIdentity {
+                    first_seen,
+                    ..identity
+                };
+                PinCheck::Trusted
+            }
+            Some(pinned) => PinCheck::Changed {
+                pinned: pinned.clone(),
+            },
+        }
+    }
+
+    /// Pin the identity of a package server, replacing the previous one.
+    pub fn trust(&mut self, remote_base_url: &str, identity: RegistryIdentity) {
+        self.registries
+            .insert(remote_base_url.to_string(), identity);
+    }
+
+    /// Load the pinned registries from their default location.
+    pub fn load<P: AsRef<Path>>(elm_home: P) -> Result<Self, CacheError> {
+        let s = std::fs::read_to_string(Self::file_path(elm_home))?;
+        serde_json::from_str(&s).map_err(|e| e.into())
+    }
+
+    /// Save the pinned registries to their default location.
+    pub fn save<P: AsRef<Path>>(&self, elm_home: P) -> Result<(), CacheError> {
+        let s = serde_json::to_string(self)?;
+        std::fs::create_dir_all(Pkg::pubgrub_cache_dir(&elm_home))?;
+        std::fs::write(Self::file_path(elm_home), &s).map_err(|e| e.into())
+    }
+
+    /// Path to the file used to store the pinned registries.
+    /// ~/.elm/pubgrub/registries.json
+    pub fn file_path<P: AsRef<Path>>(elm_home: P) -> PathBuf {
+        Pkg::pubgrub_cache_dir(elm_home).join("registries.json")
+    }
+}
@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module defining a common view of package registries.
+//!
+//! Tools embedding this crate, such as other package managers,
+//! want to consult the same packages as the solver without running a resolution:
+//! which versions of a package exist, which one is the latest,
+//! and what the `elm.json` of a given version contains.
+//! The [`RegistryView`] trait answers these questions for every source of packages:
+//! the [`Offline`](crate::solver::Offline) solver reads installed packages,
+//! the [`Online`](crate::solver::Online) solver adds those of the package server,
+//! and a [`Snapshot`] keeps package configs in memory.
+//!
+//! The solvers resolve dependencies with the same lookups as their views,
+//! and apply their policies, preferred versions and provenance recording on top.
+//! Looking up a view is not always free of side effects,
+//! the view of an online solver downloads and caches configs like a resolution would.
+//!
+//! Any registry view can also be solved against with [`solve_deps_with_view`].
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::Infallible;
+use std::error::Error;
+
+use crate::constraint::Constraint;
+use crate::project_config::{AppDependencies, PackageConfig, Pkg, ProjectConfig};
+use crate::solver::{solve_deps_with, ExtraMode, SolveError};
+
+/// Lookups of the packages of a registry.
+pub trait RegistryView {
+    /// Error of the lookups.
+    type Error: Error + 'static;
+
+    /// Existing versions of a package, empty if the package does not exist.
+    fn versions(&self, pkg: &Pkg) -> Result<BTreeSet<SemVer>, Self::Error>;
+
+    /// The `elm.json` config of a package version, or `None` if it does not exist.
+    fn config(&self, pkg: &Pkg, version: SemVer) -> Result<Option<PackageConfig>, Self::Error>;
+
+    /// Latest version of a package, if any.
+    fn latest(&self, pkg: &Pkg) -> Result<Option<SemVer>, Self::Error> {
+        Ok(self.versions(pkg)?.last().copied())
+    }
+
+    /// Whether a package version exists.
+    fn exists(&self, pkg: &Pkg, version: SemVer) -> Result<bool, Self::Error> {
+        Ok(self.versions(pkg)?.contains(&version))
+    }
+}
+
+impl<R: RegistryView + ?Sized> RegistryView for &R {
+    type Error = R::Error;
+
+    fn versions(&self, pkg: &Pkg) -> Result<BTreeSet<SemVer>, Self::Error> {
+        (**self).versions(pkg)
+    }
+
+    fn config(&self, pkg: &Pkg, version: SemVer) -> Result<Option<PackageConfig>, Self::Error> {
+        (**self).config(pkg, version)
+    }
+
+    fn latest(&self, pkg: &Pkg) -> Result<Option<SemVer>, Self::Error> {
+        (**self).latest(pkg)
+    }
+
+    fn exists(&self, pkg: &Pkg, version: SemVer) -> Result<bool, Self::Error> {
+        (**self).exists(pkg, version)
+    }
+}
+
+/// Package configs kept in memory.
+///
+/// ```
+/// # use elm_solve_deps::registry_view::{RegistryView, Snapshot};
+/// # use elm_solve_deps::project_config::{PackageConfig, Pkg};
+/// # use elm_solve_deps::semver::SemVer;
+/// let config: PackageConfig = serde_json::from_str(
+///     r#"{
+///         "type": "package",
+///         "name": "elm/core",
+///         "summary": "Elm's standard libraries",
+///         "license": "BSD-3-Clause",
+///         "version": "1.0.5",
+///         "exposed-modules": [],
+///         "elm-version": "0.19.0 <= v < 0.20.0",
+///         "dependencies": {},
+///         "test-dependencies": {}
+///     }"#,
+/// )
+/// .unwrap();
+/// let mut snapshot = Snapshot::new();
+/// snapshot.insert(config);
+/// let core = Pkg::new("elm", "core");
+/// assert_eq!(snapshot.latest(&core), Ok(Some(SemVer::new(1, 0, 5))));
+/// assert_eq!(snapshot.exists(&core, SemVer::new(1, 0, 4)), Ok(false));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Snapshot {
+    /// The config of every version of every package.
+    pub configs: BTreeMap<Pkg, BTreeMap<SemVer, PackageConfig>>,
+}
+
+impl Snapshot {
+    /// Initialize without any package.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the config of a package version, replacing the previous one if any.
+    pub fn insert(&mut self, config: PackageConfig) {
+        let versions = self.configs.entry(config.name.clone()).or_default();
+        versions.insert(config.version, config);
+    }
+
+    /// Copy all the package configs of another registry view.
+    pub fn copy<R, I>(view: &R, pkgs: I) -> Result<Self, R::Error>
+    where
+        R: RegistryView,
+        I: IntoIterator<Item = Pkg>,
+    {
+        let mut snapshot = Self::new();
+        for pkg in pkgs {
+            for version in view.versions(&pkg)? {
+                if let Some(config) = view.config(&pkg, version)? {
+                    snapshot.insert(config);
+                }
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+impl RegistryView for Snapshot {
+    type Error = Infallible;
+
+    fn versions(&self, pkg: &Pkg) -> Result<BTreeSet<SemVer>, Self::Error> {
+        let versions = self.configs.get(pkg).into_iter().flat_map(|v| v.keys());
+        Ok(versions.copied().collect())
+    }
+
+    fn config(&self, pkg: &Pkg, version: SemVer) -> Result<Option<PackageConfig>, Self::Error> {
+        Ok((self.configs.get(pkg)).and_then(|versions| versions.get(&version).cloned()))
+    }
+}
+
+/// Solve the dependencies of a project with the packages of a registry view,
+/// trying the newest versions first.
+///
+/// Contrary to the solvers, no policy nor limit applies,
+/// use [`solve_deps_with`] directly for finer control.
+pub fn solve_deps_with_view<R: RegistryView>(
+    view: &R,
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+) -> Result<AppDependencies, SolveError<R::Error>> {
+    solve_deps_with(
+        project_elm_json,
+        use_test,
+        additional_constraints,
+        extra_mode,
+        |pkg: &Pkg, version| view.config(pkg, version),
+        |pkg: &Pkg| Ok(view.versions(pkg)?.into_iter().rev()),
+    )
+}
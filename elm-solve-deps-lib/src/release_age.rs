@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module tracking when package versions were released, to avoid the most recent ones.
+//!
+//! Supply-chain cautious teams only pick versions released at least a few days ago,
+//! leaving time for compromised releases to be spotted and removed from the package server.
+//! The package server lists the release time of every version of a package
+//! in its `releases.json`, which [`ReleaseTimes`] fetches lazily, one package at a time,
+//! and caches in `ELM_HOME`, fetching it again when new versions are published.
+//!
+//! An [`Online`](crate::solver::Online) solver configured with
+//! [`Online::with_min_release_age`](crate::solver::Online::with_min_release_age)
+//! only considers versions old enough,
+//! and never the ones whose release time is unknown.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::fetch::HttpFetch;
+use crate::pkg_version::CacheError;
+use crate::project_config::Pkg;
+
+/// Release times of package versions, in seconds since the unix epoch.
+///
+/// ```
+/// # use elm_solve_deps::project_config::Pkg;
+/// # use elm_solve_deps::release_age::ReleaseTimes;
+/// # use elm_solve_deps::semver::SemVer;
+/// # use std::time::Duration;
+/// let core = Pkg::new("elm", "core");
+/// let mut release_times = ReleaseTimes::new();
+/// release_times.times.insert(
+///     core.clone(),
+///     [(SemVer::new(1, 0, 4), 1_000_000), (SemVer::new(1, 0, 5), 1_500_000)].into(),
+/// );
+/// let week = Duration::from_secs(7 * 24 * 60 * 60);
+/// let now = 2_000_000;
+/// assert!(release_times.old_enough(&core, SemVer::new(1, 0, 4), week, now));
+/// assert!(!release_times.old_enough(&core, SemVer::new(1, 0, 5), week, now));
+/// assert!(!release_times.old_enough(&core, SemVer::new(1, 0, 6), week, now));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ReleaseTimes {
+    /// The release time of each version of each package.
+    pub times: BTreeMap<Pkg, BTreeMap<SemVer, u64>>,
+}
+
+impl ReleaseTimes {
+    /// Initialize without any release time.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Url of the release times of a package on a package server.
+    pub fn url(remote_base_url: &str, pkg: &Pkg) -> String {
+        format!("{}/releases.json", pkg.to_url(remote_base_url))
+    }
+
+    /// Location of the cached release times of a package.
+    /// ~/.elm/pubgrub/elm_json_cache/author/package/releases.json
+    pub fn cache_path<P: AsRef<Path>>(elm_home: P, pkg: &Pkg) -> PathBuf {
+        pkg.pubgrub_cache_dir_json(elm_home).join("releases.json")
+    }
+
+    /// Make sure the release times of some versions of a package are known.
+    ///
+    /// Unknown ones are loaded from the cache in `ELM_HOME`, and if still missing,
+    /// fetched from the package servers, whose release times are merged,
+    /// the first package server taking precedence, and saved into the cache.
+    /// Fails if none of the package servers responded.
+    pub fn ensure<P, H>(
+        &mut self,
+        elm_home: P,
+        pkg: &Pkg,
+        versions: &[SemVer],
+        remote_base_urls: &[&str],
+        http_fetch: &H,
+    ) -> Result<(), CacheError>
+    where
+        P: AsRef<Path>,
+        H: HttpFetch + ?Sized,
+    {
+        if self.knows(pkg, versions) {
+            return Ok(());
+        }
+        let cache_path = Self::cache_path(&elm_home, pkg);
+        if let Ok(cached) = std::fs::read_to_string(&cache_path) {
+            if let Ok(cached) = serde_json::from_str::<BTreeMap<SemVer, u64>>(&cached) {
+                self.times.entry(pkg.clone()).or_default().extend(cached);
+                if self.knows(pkg, versions) {
+                    return Ok(());
+                }
+            }
+        }
+        let mut fetched: Option<BTreeMap<SemVer, u64>> = None;
+        let mut last_error = None;
+        for remote_base_url in remote_base_urls {
+            let url = Self::url(remote_base_url, pkg);
+            let times = (http_fetch.fetch(&url))
+                .map_err(|source| CacheError::FetchError { url, source })
+                .and_then(|s| Ok(serde_json::from_str::<BTreeMap<SemVer, u64>>(&s)?));
+            match times {
+                Ok(times) => {
+                    let merged = fetched.get_or_insert_with(BTreeMap::new);
+                    for (version, time) in times {
+                        merged.entry(version).or_insert(time);
+                    }
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+        match (fetched, last_error) {
+            (Some(fetched), _) => {
+                std::fs::create_dir_all(pkg.pubgrub_cache_dir_json(&elm_home))?;
+                std::fs::write(&cache_path, serde_json::to_string(&fetched)?)?;
+                self.times.insert(pkg.clone(), fetched);
+                Ok(())
+            }
+            (None, Some(err)) => Err(err),
+            (None, None) => Ok(()),
+        }
+    }
+
+    /// Release time of a package version, if known.
+    pub fn release_time(&self, pkg: &Pkg, version: SemVer) -> Option<u64> {
+        self.times.get(pkg)?.get(&version).copied()
+    }
+
+    /// Whether a package version was released at least `min_age` before `now`,
+    /// in seconds since the unix epoch.
+    /// Versions with an unknown release time are never old enough.
+    pub fn old_enough(&self, pkg: &Pkg, version: SemVer, min_age: Duration, now: u64) -> bool {
+        (self.release_time(pkg, version))
+            .is_some_and(|time| now.saturating_sub(time) >= min_age.as_secs())
+    }
+
+    /// Whether the release times of all these versions of a package are known.
+    fn knows(&self, pkg: &Pkg, versions: &[SemVer]) -> bool {
+        let known = self.times.get(pkg);
+        (versions.iter()).all(|v| known.is_some_and(|times| times.contains_key(v)))
+    }
+}
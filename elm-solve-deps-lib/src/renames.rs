@@ -0,0 +1,239 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module tracking packages renamed over time, such as the ones moved between authors.
+//!
+//! Many packages of the elm 0.18 era were published again under new names,
+//! like `elm-lang/core` becoming `elm/core`,
+//! or `elm-community` packages adopted by `elm-explorations`.
+//! Projects still depending on the obsolete names cannot be solved,
+//! since the newer versions are only published under the new names.
+//!
+//! A [`Renames`] table maps obsolete names to their successors,
+//! starting from the well-known ones of [`Renames::builtin`], extended by users.
+//! [`Renames::obsolete_dependencies`] finds the dependencies of a project to rename,
+//! and [`Renames::apply`] replaces them by their successors, without version constraints,
+//! since the versions of successors are unrelated to the ones of obsolete names.
+
+use pubgrub::range::Range;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+
+use crate::constraint::Constraint;
+#[cfg(feature = "fs")]
+use crate::pkg_version::CacheError;
+use crate::project_config::{Pkg, ProjectConfig};
+
+/// Well-known packages published again under another name, with their successors.
+pub const BUILTIN_RENAMES: &[(&str, &str)] = &[
+    ("elm-lang/core", "elm/core"),
+    ("elm-lang/html", "elm/html"),
+    ("elm-lang/http", "elm/http"),
+    ("elm-lang/svg", "elm/svg"),
+    ("elm-lang/virtual-dom", "elm/virtual-dom"),
+    ("elm-tools/parser", "elm/parser"),
+    ("elm-community/elm-test", "elm-explorations/test"),
+    (
+        "elm-community/linear-algebra",
+        "elm-explorations/linear-algebra",
+    ),
+    ("elm-community/webgl", "elm-explorations/webgl"),
+    ("evancz/elm-markdown", "elm-explorations/markdown"),
+    (
+        "NoRedInk/elm-decode-pipeline",
+        "NoRedInk/elm-json-decode-pipeline",
+    ),
+    ("mdgriffith/stylish-elephants", "mdgriffith/elm-ui"),
+];
+
+/// Table of obsolete package names, with their successors.
+///
+/// It is saved as a JSON object, such as `{ "elm-lang/core": "elm/core" }`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Renames {
+    successors: BTreeMap<Pkg, Pkg>,
+}
+
+/// An obsolete package name with its successor.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Rename {
+    /// The obsolete name.
+    pub from: Pkg,
+    /// The name of its successor.
+    pub to: Pkg,
+}
+
+/// A project whose obsolete dependencies were replaced by their successors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamedProject {
+    /// The project without its obsolete dependencies.
+    pub project: ProjectConfig,
+    /// The additional constraints, with the successors of the obsolete direct dependencies.
+    pub additional_constraints: Vec<(Pkg, Constraint)>,
+    /// The replaced dependencies.
+    pub renamed: Vec<Rename>,
+}
+
+impl fmt::Display for Rename {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.from, self.to)
+    }
+}
+
+impl Renames {
+    /// Initialize an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Table of the well-known renames of [`BUILTIN_RENAMES`].
+    pub fn builtin() -> Self {
+        let mut renames = Self::new();
+        for (from, to) in BUILTIN_RENAMES {
+            // Built-in names are valid.
+            renames.insert(from.parse().unwrap(), to.parse().unwrap());
+        }
+        renames
+    }
+
+    /// Record that a package was renamed, replacing its previously known successor.
+    pub fn insert(&mut self, from: Pkg, to: Pkg) {
+        self.successors.insert(from, to);
+    }
+
+    /// Add the renames of another table, which take precedence over the ones of this table.
+    pub fn extend(&mut self, other: Renames) {
+        self.successors.extend(other.successors);
+    }
+
+    /// Current name of a renamed package, following packages renamed several times,
+    /// or `None` if the package was not renamed.
+    ///
+    /// ```
+    /// # use elm_solve_deps::renames::Renames;
+    /// # use elm_solve_deps::project_config::Pkg;
+    /// let renames = Renames::builtin();
+    /// let successor = renames.successor(&Pkg::new("elm-community", "elm-test"));
+    /// assert_eq!(successor, Some(Pkg::new("elm-explorations", "test")));
+    /// assert_eq!(renames.successor(&Pkg::new("elm", "core")), None);
+    /// ```
+    pub fn successor(&self, pkg: &Pkg) -> Option<Pkg> {
+        let mut visited = BTreeSet::from([pkg]);
+        let mut current = self.successors.get(pkg)?;
+        while let Some(next) = self.successors.get(current) {
+            if !visited.insert(current) {
+                // Renamed in a cycle, the table is invalid.
+                break;
+            }
+            current = next;
+        }
+        (current != pkg).then(|| current.clone())
+    }
+
+    /// Obsolete direct dependencies of a project, with their successors,
+    /// including test dependencies if `use_test` is true.
+    pub fn obsolete_dependencies(&self, project: &ProjectConfig, use_test: bool) -> Vec<Rename> {
+        let direct: Vec<&Pkg> = match project {
+            ProjectConfig::Application(app) => {
+                let mut direct: Vec<&Pkg> = app.dependencies.direct.keys().collect();
+                if use_test {
+                    direct.extend(app.test_dependencies.direct.keys());
+                }
+                direct
+            }
+            ProjectConfig::Package(pkg) => {
+                let mut direct: Vec<&Pkg> = pkg.dependencies.keys().collect();
+                if use_test {
+                    direct.extend(pkg.test_dependencies.keys());
+                }
+                direct
+            }
+        };
+        let renamed: BTreeSet<Rename> = (direct.into_iter())
+            .filter_map(|from| {
+                let to = self.successor(from)?;
+                Some(Rename {
+                    from: from.clone(),
+                    to,
+                })
+            })
+            .collect();
+        renamed.into_iter().collect()
+    }
+
+    /// Replace the obsolete dependencies of a project by their successors.
+    ///
+    /// Obsolete dependencies are removed from the project,
+    /// and the successors of the direct ones are added to the additional constraints,
+    /// without constraining their versions, unless they already are dependencies.
+    /// Obsolete packages of the additional constraints are replaced the same way.
+    pub fn apply(
+        &self,
+        project: &ProjectConfig,
+        use_test: bool,
+        additional_constraints: &[(Pkg, Constraint)],
+    ) -> RenamedProject {
+        let mut renamed: BTreeSet<Rename> =
+            (self.obsolete_dependencies(project, use_test).into_iter()).collect();
+        let mut project = project.clone();
+        let is_current = |pkg: &Pkg| self.successor(pkg).is_none();
+        let mut direct: BTreeSet<Pkg> = BTreeSet::new();
+        match &mut project {
+            ProjectConfig::Application(app) => {
+                for deps in [&mut app.dependencies, &mut app.test_dependencies] {
+                    deps.direct.retain(|pkg, _| is_current(pkg));
+                    deps.indirect.retain(|pkg, _| is_current(pkg));
+                }
+                direct.extend(app.dependencies.direct.keys().cloned());
+                direct.extend(app.test_dependencies.direct.keys().cloned());
+            }
+            ProjectConfig::Package(pkg) => {
+                pkg.dependencies.retain(|pkg, _| is_current(pkg));
+                pkg.test_dependencies.retain(|pkg, _| is_current(pkg));
+                direct.extend(pkg.dependencies.keys().cloned());
+                direct.extend(pkg.test_dependencies.keys().cloned());
+            }
+        }
+        let mut constraints = Vec::new();
+        for (pkg, constraint) in additional_constraints {
+            match self.successor(pkg) {
+                None => constraints.push((pkg.clone(), constraint.clone())),
+                Some(to) => {
+                    renamed.insert(Rename {
+                        from: pkg.clone(),
+                        to,
+                    });
+                }
+            }
+        }
+        let successors: BTreeSet<&Pkg> = renamed.iter().map(|rename| &rename.to).collect();
+        for to in successors {
+            let constrained = constraints.iter().any(|(pkg, _)| pkg == to);
+            if !direct.contains(to) && !constrained {
+                constraints.push((to.clone(), Constraint(Range::any())));
+            }
+        }
+        RenamedProject {
+            project,
+            additional_constraints: constraints,
+            renamed: renamed.into_iter().collect(),
+        }
+    }
+
+    /// Load a table of renames from a JSON file.
+    #[cfg(feature = "fs")]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CacheError> {
+        let s = std::fs::read_to_string(path)?;
+        serde_json::from_str(&s).map_err(|e| e.into())
+    }
+
+    /// Default location of the renames added by users.
+    /// ~/.elm/pubgrub/renames.json
+    #[cfg(feature = "fs")]
+    pub fn default_path<P: AsRef<Path>>(elm_home: P) -> PathBuf {
+        Pkg::pubgrub_cache_dir(elm_home).join("renames.json")
+    }
+}
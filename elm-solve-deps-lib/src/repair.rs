@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module repairing inconsistent versions pinned in the `elm.json` of an application.
+//!
+//! The elm compiler expects every dependency of a package pinned in an application `elm.json`
+//! to also be pinned, with a version satisfying the constraint of that package.
+//! When it is not the case, for example after editing the indirect dependencies by hand,
+//! the compiler fails with an error that does not say which pin is wrong.
+//! This module detects such inconsistencies,
+//! and computes new consistent indirect dependencies, keeping pins the solver can keep.
+//! The search is not exhaustive: kept pins are preferred one package at a time,
+//! so the changes are usually few, but not guaranteed to be the fewest possible.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use std::collections::BTreeMap as Map;
+use std::fmt;
+
+use crate::constraint::Constraint;
+use crate::phased::{self, PhaseError, PhasedSolution};
+use crate::project_config::{
+    AppDependencies, ApplicationConfig, PackageConfig, Pkg, ProjectConfig,
+};
+use crate::solver::ExtraMode;
+
+/// A dependency of a pinned package that is not satisfied by the pinned versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inconsistency {
+    /// The pinned package with the unsatisfied dependency.
+    pub dependent: Pkg,
+    /// The pinned version of that package.
+    pub dependent_version: SemVer,
+    /// The unsatisfied dependency.
+    pub dependency: Pkg,
+    /// The constraint of the dependent package on that dependency.
+    pub constraint: Constraint,
+    /// The version pinned for the dependency, if any.
+    pub pinned: Option<SemVer>,
+}
+
+/// A change of a pinned indirect dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinChange {
+    /// The changed package.
+    pub pkg: Pkg,
+    /// Whether the package is an indirect test dependency.
+    pub test: bool,
+    /// The previously pinned version, `None` if the package is added.
+    pub from: Option<SemVer>,
+    /// The new pinned version, `None` if the package is removed.
+    pub to: Option<SemVer>,
+}
+
+/// The repaired dependencies of an application, and the changes compared to its `elm.json`.
+#[derive(Debug, Clone)]
+pub struct Repair {
+    /// The consistent dependencies, with the same layout than an application `elm.json`.
+    pub solution: PhasedSolution,
+    /// Changes of the indirect dependencies, normal ones first.
+    pub changes: Vec<PinChange>,
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}@{} depends on {} {}, ",
+            self.dependent, self.dependent_version, self.dependency, self.constraint
+        )?;
+        match self.pinned {
+            Some(version) => write!(f, "but {} is pinned", version),
+            None => write!(f, "but it is not pinned"),
+        }
+    }
+}
+
+impl fmt::Display for PinChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let section = if self.test { " (test)" } else { "" };
+        match (self.from, self.to) {
+            (Some(from), Some(to)) => write!(f, "~ {} {} -> {}{}", self.pkg, from, to, section),
+            (None, Some(to)) => write!(f, "+ {} {}{}", self.pkg, to, section),
+            (Some(from), None) => write!(f, "- {} {}{}", self.pkg, from, section),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Check that the versions pinned in an application `elm.json` are consistent.
+///
+/// Normal dependencies must be consistent on their own,
+/// and test dependencies together with the normal ones.
+/// The `load_config` argument loads the `elm.json` of a given package version.
+pub fn check<Load, E>(
+    app_config: &ApplicationConfig,
+    load_config: Load,
+) -> Result<Vec<Inconsistency>, E>
+where
+    Load: Fn(&Pkg, SemVer) -> Result<PackageConfig, E>,
+{
+    let normal_pins = pins(&[&app_config.dependencies]);
+    let all_pins = pins(&[&app_config.dependencies, &app_config.test_dependencies]);
+    let test_pins = pins(&[&app_config.test_dependencies]);
+    let mut inconsistencies = Vec::new();
+    for (checked, available) in [(&normal_pins, &normal_pins), (&test_pins, &all_pins)] {
+        for (pkg, version) in checked {
+            let config = load_config(pkg, *version)?;
+            for (dependency, constraint) in config.dependencies {
+                let pinned = available.get(&dependency).copied();
+                if !pinned.is_some_and(|v| constraint.0.contains(&v)) {
+                    inconsistencies.push(Inconsistency {
+                        dependent: pkg.clone(),
+                        dependent_version: *version,
+                        dependency,
+                        constraint,
+                        pinned,
+                    });
+                }
+            }
+        }
+    }
+    Ok(inconsistencies)
+}
+
+/// Compute consistent indirect dependencies for an application,
+/// keeping its direct dependencies at their pinned versions.
+///
+/// The `solve` argument is typically the `solve_deps` method of one of the solvers.
+/// To keep pins, it should try pinned versions first,
+/// such as an [`Offline`](crate::solver::Offline) solver configured
+/// [`with_preferred_versions`](crate::solver::Offline::with_preferred_versions)
+/// set to the [`recorded_versions`](ApplicationConfig::recorded_versions) of the application.
+/// A pin is then changed when the solver, picking packages one at a time,
+/// finds its pinned version conflicting with earlier picks.
+/// This is a greedy choice, so another set of changes may sometimes be smaller.
+pub fn repair<Solve, E>(
+    app_config: &ApplicationConfig,
+    solve: Solve,
+) -> Result<Repair, PhaseError<E>>
+where
+    Solve: Fn(&ProjectConfig, bool, &[(Pkg, Constraint)], ExtraMode) -> Result<AppDependencies, E>,
+{
+    let project_elm_json = ProjectConfig::Application(app_config.clone());
+    let solution = phased::solve_in_phases(&project_elm_json, &[], ExtraMode::default(), solve)?;
+    let mut changes = diff(
+        &app_config.dependencies.indirect,
+        &solution.dependencies.indirect,
+        false,
+    );
+    changes.extend(diff(
+        &app_config.test_dependencies.indirect,
+        &solution.test_dependencies.indirect,
+        true,
+    ));
+    Ok(Repair { solution, changes })
+}
+
+/// All pinned versions of the given dependencies sections.
+fn pins(sections: &[&AppDependencies]) -> Map<Pkg, SemVer> {
+    (sections.iter())
+        .flat_map(|deps| deps.direct.iter().chain(deps.indirect.iter()))
+        .map(|(p, v)| (p.clone(), *v))
+        .collect()
+}
+
+/// Changes between two sets of pinned versions.
+fn diff(old: &Map<Pkg, SemVer>, new: &Map<Pkg, SemVer>, test: bool) -> Vec<PinChange> {
+    let mut pkgs: Vec<&Pkg> = old.keys().chain(new.keys()).collect();
+    pkgs.sort();
+    pkgs.dedup();
+    (pkgs.into_iter())
+        .map(|pkg| PinChange {
+            pkg: pkg.clone(),
+            test,
+            from: old.get(pkg).copied(),
+            to: new.get(pkg).copied(),
+        })
+        .filter(|change| change.from != change.to)
+        .collect()
+}
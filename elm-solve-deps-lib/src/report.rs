@@ -0,0 +1,201 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module providing reports about dependency solutions.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::constraint::Constraint;
+use crate::pkg_version::Cache;
+use crate::project_config::{AppDependencies, Pkg};
+
+/// Freshness classification of a selected package version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Freshness {
+    /// The selected version is the newest available.
+    UpToDate,
+    /// A newer version exists within the same major version.
+    CompatibleUpdate,
+    /// The selected version is the newest of its major version,
+    /// but a newer major version exists.
+    MajorUpdate,
+    /// The package is not known in the versions cache.
+    Unknown,
+}
+
+/// Freshness of one selected package version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Outdated {
+    /// The package identifier.
+    pub pkg: Pkg,
+    /// The version selected in the solution.
+    pub current: SemVer,
+    /// The newest version with the same major version as the current one.
+    pub newest_compatible: Option<SemVer>,
+    /// The newest version available.
+    pub newest: Option<SemVer>,
+    /// The freshness classification.
+    pub freshness: Freshness,
+}
+
+/// Compare each selected version of a solution against the newest versions available,
+/// as recorded in the versions cache.
+///
+/// Direct dependencies are listed first, then indirect ones, each in alphabetical order.
+pub fn outdated(solution: &AppDependencies, cache: &Cache) -> Vec<Outdated> {
+    (solution.direct.iter())
+        .chain(solution.indirect.iter())
+        .map(|(pkg, current)| freshness_of(pkg, *current, cache))
+        .collect()
+}
+
+fn freshness_of(pkg: &Pkg, current: SemVer, cache: &Cache) -> Outdated {
+    let versions = cache.cache.get(pkg);
+    let newest = versions.and_then(|vs| vs.iter().next_back()).copied();
+    let (major, _, _): (u32, u32, u32) = current.into();
+    let next_major = SemVer::new(major + 1, 0, 0);
+    let newest_compatible = versions
+        .and_then(|vs| vs.range(current..next_major).next_back())
+        .copied();
+    let freshness = match (newest_compatible, newest) {
+        (_, None) => Freshness::Unknown,
+        (Some(v), _) if v > current => Freshness::CompatibleUpdate,
+        (_, Some(v)) if v > current => Freshness::MajorUpdate,
+        _ => Freshness::UpToDate,
+    };
+    Outdated {
+        pkg: pkg.clone(),
+        current,
+        newest_compatible,
+        newest,
+        freshness,
+    }
+}
+
+/// A package picked at different versions in two solutions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct VersionDifference {
+    /// The package identifier.
+    pub pkg: Pkg,
+    /// The version in the first solution, `None` if the package is not in it.
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(with = "Option<crate::schema::SemVerSchema>")
+    )]
+    pub left: Option<SemVer>,
+    /// The version in the second solution, `None` if the package is not in it.
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(with = "Option<crate::schema::SemVerSchema>")
+    )]
+    pub right: Option<SemVer>,
+}
+
+/// Comparison of the solutions of a project obtained offline and online.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct ModesComparison {
+    /// The offline solution, if solving offline succeeded.
+    pub offline: Option<AppDependencies>,
+    /// Why solving offline failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offline_error: Option<String>,
+    /// The online solution, if solving online succeeded.
+    pub online: Option<AppDependencies>,
+    /// Why solving online failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub online_error: Option<String>,
+    /// Packages picked at different versions, offline on the left and online on the right,
+    /// if both resolutions succeeded.
+    pub differences: Vec<VersionDifference>,
+    /// Whether solving offline was sufficient, without downloading anything.
+    pub offline_sufficient: bool,
+}
+
+impl ModesComparison {
+    /// Compare the outcomes of solving offline and online.
+    pub fn new<E: ToString>(
+        offline: Result<AppDependencies, E>,
+        online: Result<AppDependencies, E>,
+    ) -> Self {
+        let differences = match (&offline, &online) {
+            (Ok(offline), Ok(online)) => differences(offline, online),
+            _ => Vec::new(),
+        };
+        Self {
+            offline_sufficient: offline.is_ok(),
+            offline_error: offline.as_ref().err().map(E::to_string),
+            offline: offline.ok(),
+            online_error: online.as_ref().err().map(E::to_string),
+            online: online.ok(),
+            differences,
+        }
+    }
+
+    /// Whether both resolutions succeeded with the same versions.
+    pub fn identical(&self) -> bool {
+        self.offline.is_some() && self.online.is_some() && self.differences.is_empty()
+    }
+}
+
+/// Packages picked at different versions in two solutions, in alphabetical order.
+/// Whether a package is direct or indirect is ignored.
+pub fn differences(left: &AppDependencies, right: &AppDependencies) -> Vec<VersionDifference> {
+    let versions = |solution: &AppDependencies| -> BTreeMap<Pkg, SemVer> {
+        (solution.direct.iter())
+            .chain(solution.indirect.iter())
+            .map(|(p, v)| (p.clone(), *v))
+            .collect()
+    };
+    let (left, right) = (versions(left), versions(right));
+    let pkgs: BTreeSet<&Pkg> = left.keys().chain(right.keys()).collect();
+    (pkgs.into_iter())
+        .map(|pkg| VersionDifference {
+            pkg: pkg.clone(),
+            left: left.get(pkg).copied(),
+            right: right.get(pkg).copied(),
+        })
+        .filter(|diff| diff.left != diff.right)
+        .collect()
+}
+
+/// Outcome of checking a constraint against the version of a package in a solution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ConstraintCheck {
+    /// The constrained package.
+    pub pkg: Pkg,
+    /// The checked constraint.
+    pub constraint: Constraint,
+    /// The version of the package in the solution, `None` if it is not in the solution.
+    pub version: Option<SemVer>,
+}
+
+impl ConstraintCheck {
+    /// Whether the package is in the solution, with a version satisfying the constraint.
+    pub fn satisfied(&self) -> bool {
+        self.version.is_some_and(|v| self.constraint.allows(&v))
+    }
+}
+
+/// Check constraints against the versions of a solution, without solving again.
+pub fn check_constraints(
+    solution: &AppDependencies,
+    constraints: &[(Pkg, Constraint)],
+) -> Vec<ConstraintCheck> {
+    (constraints.iter())
+        .map(|(pkg, constraint)| ConstraintCheck {
+            pkg: pkg.clone(),
+            constraint: constraint.clone(),
+            version: (solution.direct.get(pkg))
+                .or_else(|| solution.indirect.get(pkg))
+                .copied(),
+        })
+        .collect()
+}
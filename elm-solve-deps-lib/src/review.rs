@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module helping to solve the dependencies of an elm-review configuration
+//! together with the dependencies of the reviewed project.
+//!
+//! elm-review stores its configuration in a secondary application `elm.json`,
+//! inside the `review/` directory of the project.
+//! Packages shared between the project and the review configuration, such as `elm/core`,
+//! should resolve to the same versions, otherwise the review rules would run against
+//! different code than the one compiled for the project.
+
+use pubgrub::range::Range;
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::constraint::Constraint;
+use crate::project_config::{canonical_json, AppDependencies, Pkg, ProjectConfig};
+use crate::solver::ExtraMode;
+
+/// Solutions of both the project and its review configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewSolution {
+    /// Solution for the dependencies of the project.
+    pub project: AppDependencies,
+    /// Solution for the dependencies of the review configuration.
+    pub review: AppDependencies,
+}
+
+/// Error type for the combined resolution of a project and its review configuration.
+#[derive(Error, Debug)]
+pub enum ReviewError<E> {
+    /// Failed to read one of the `elm.json` files.
+    #[error("unable to read {}", .0.display())]
+    FileIoError(PathBuf, #[source] std::io::Error),
+
+    /// Failed to decode one of the `elm.json` files.
+    #[error("failed to decode {}", .0.display())]
+    JsonError(PathBuf, #[source] serde_json::Error),
+
+    /// Solving the project dependencies failed.
+    #[error("failed to solve the project dependencies: {0}")]
+    ProjectSolve(E),
+
+    /// Solving the review configuration dependencies failed.
+    #[error("failed to solve the review configuration dependencies: {0}")]
+    ReviewSolve(E),
+
+    /// The review configuration cannot use the same versions as the project
+    /// for the packages they have in common.
+    #[error("the review configuration is incompatible with the project versions of {}: {error}", display_shared(.shared))]
+    Incompatible {
+        /// Shared packages, with their versions in the project and the review solutions.
+        shared: Vec<(Pkg, SemVer, SemVer)>,
+        /// The error when solving the review configuration with the project versions.
+        error: E,
+    },
+}
+
+impl ReviewSolution {
+    /// Serialize the solutions into their canonical JSON form,
+    /// as described in [`AppDependencies::to_canonical_json`].
+    pub fn to_canonical_json(&self) -> String {
+        canonical_json(self)
+    }
+}
+
+fn display_shared(shared: &[(Pkg, SemVer, SemVer)]) -> String {
+    let pkgs: Vec<String> = shared.iter().map(|(p, _, _)| p.to_string()).collect();
+    pkgs.join(", ")
+}
+
+/// Path of the review configuration `elm.json` of a project.
+///
+/// This looks like `project_dir/review/elm.json`.
+pub fn config_path<P: AsRef<Path>>(project_dir: P) -> PathBuf {
+    project_dir.as_ref().join("review").join("elm.json")
+}
+
+/// Load both the project `elm.json` and the review configuration `elm.json`.
+#[cfg(feature = "fs")]
+pub fn load_configs<P: AsRef<Path>, E>(
+    project_dir: P,
+) -> Result<(ProjectConfig, ProjectConfig), ReviewError<E>> {
+    let load = |path: PathBuf| {
+        let s = std::fs::read_to_string(&path)
+            .map_err(|e| ReviewError::FileIoError(path.clone(), e))?;
+        serde_json::from_str(&s).map_err(|e| ReviewError::JsonError(path, e))
+    };
+    let project = load(project_dir.as_ref().join("elm.json"))?;
+    let review = load(config_path(project_dir))?;
+    Ok((project, review))
+}
+
+/// Locate and solve both the project in `project_dir` and its review configuration.
+///
+/// The `solve` argument is typically the `solve_deps` method of one of the solvers,
+/// such as [`Offline::solve_deps`](crate::solver::Offline::solve_deps).
+/// The project is solved first, with the given `use_test` and additional constraints.
+/// Then the review configuration is solved (without its test dependencies),
+/// with shared packages matching the project solution, see [`solve_review`].
+#[cfg(feature = "fs")]
+pub fn solve_with_review<P, Solve, E>(
+    project_dir: P,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+    solve: Solve,
+) -> Result<ReviewSolution, ReviewError<E>>
+where
+    P: AsRef<Path>,
+    Solve: Fn(&ProjectConfig, bool, &[(Pkg, Constraint)], ExtraMode) -> Result<AppDependencies, E>,
+{
+    let (project_config, review_config) = load_configs(project_dir)?;
+    let project = solve(
+        &project_config,
+        use_test,
+        additional_constraints,
+        extra_mode,
+    )
+    .map_err(ReviewError::ProjectSolve)?;
+    let review = solve_review(&review_config, &project, &solve)?;
+    Ok(ReviewSolution { project, review })
+}
+
+/// Solve the review configuration, with shared packages matching the project solution.
+///
+/// While both solutions disagree on the versions of shared packages,
+/// the review configuration is solved again with those packages pinned
+/// to the project versions, in addition to the ones pinned before.
+/// Pinning a package may change the versions of its dependencies,
+/// so this is repeated until the solutions agree, and fails if that is not possible.
+pub fn solve_review<Solve, E>(
+    review_config: &ProjectConfig,
+    project_solution: &AppDependencies,
+    solve: Solve,
+) -> Result<AppDependencies, ReviewError<E>>
+where
+    Solve: Fn(&ProjectConfig, bool, &[(Pkg, Constraint)], ExtraMode) -> Result<AppDependencies, E>,
+{
+    let mut review =
+        solve(review_config, false, &[], ExtraMode::Tighten).map_err(ReviewError::ReviewSolve)?;
+    let project_versions = all_versions(project_solution);
+    let mut shared: Vec<(Pkg, SemVer, SemVer)> = Vec::new();
+    loop {
+        let disagreeing: Vec<(Pkg, SemVer, SemVer)> = all_versions(&review)
+            .into_iter()
+            .filter_map(|(p, v)| match project_versions.get(&p) {
+                Some(v_project) if v_project != &v => Some((p, *v_project, v)),
+                _ => None,
+            })
+            .collect();
+        if disagreeing.is_empty() {
+            break;
+        }
+        // Pinned packages always agree, so every iteration pins new ones, and this terminates.
+        shared.extend(disagreeing);
+
+        // Pin the shared packages to the versions picked for the project.
+        let pins: Vec<(Pkg, Constraint)> = shared
+            .iter()
+            .map(|(p, v, _)| (p.clone(), Constraint(Range::exact(*v))))
+            .collect();
+        review = match solve(review_config, false, &pins, ExtraMode::Override) {
+            Ok(review) => review,
+            Err(error) => return Err(ReviewError::Incompatible { shared, error }),
+        };
+    }
+
+    // Pins were added as direct dependencies, move them back where they belong.
+    if let ProjectConfig::Application(app) = review_config {
+        for (p, _, _) in &shared {
+            if !app.dependencies.direct.contains_key(p) {
+                if let Some(v) = review.direct.remove(p) {
+                    review.indirect.insert(p.clone(), v);
+                }
+            }
+        }
+    }
+    Ok(review)
+}
+
+fn all_versions(solution: &AppDependencies) -> std::collections::BTreeMap<Pkg, SemVer> {
+    (solution.direct.iter())
+        .chain(solution.indirect.iter())
+        .map(|(p, v)| (p.clone(), *v))
+        .collect()
+}
@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module solving dependencies within a temporary `ELM_HOME`.
+//!
+//! Tools testing candidate solutions, for example by installing packages with the elm compiler,
+//! should not touch the package cache of the user.
+//! A [`Sandbox`] creates a temporary `ELM_HOME`, empty or seeded from an existing one
+//! or from an [`Archive`] snapshot of it, and removes it when dropped.
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::elm_home::Archive;
+use crate::paths;
+use crate::project_config::Pkg;
+use crate::solver::Offline;
+
+/// A temporary `ELM_HOME`, removed when dropped.
+///
+/// ```no_run
+/// # use elm_solve_deps::sandbox::Sandbox;
+/// # use elm_solve_deps::solver::ExtraMode;
+/// # let project_elm_json = serde_json::from_str("").unwrap();
+/// let sandbox = Sandbox::seeded_from("/home/user/.elm", "0.19.1").expect("Failed to seed");
+/// let solution = sandbox
+///     .offline_solver()
+///     .solve_deps(&project_elm_json, false, &[], ExtraMode::default());
+/// let status = sandbox.command("elm").args(["make", "src/Main.elm"]).status();
+/// ```
+#[derive(Debug)]
+pub struct Sandbox {
+    elm_home: PathBuf,
+    elm_version: String,
+    keep: bool,
+}
+
+/// Counter making the directories of sandboxes of the same process unique.
+static SANDBOX_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+impl Sandbox {
+    /// Create an empty sandbox, in the temporary directory of the system.
+    pub fn new<S: ToString>(elm_version: S) -> std::io::Result<Self> {
+        let temp_dir = std::env::temp_dir();
+        loop {
+            let count = SANDBOX_COUNT.fetch_add(1, Ordering::Relaxed);
+            let name = format!("elm-solve-deps-sandbox-{}-{}", std::process::id(), count);
+            let elm_home = temp_dir.join(name);
+            match std::fs::create_dir(&elm_home) {
+                Ok(()) => {
+                    return Ok(Self {
+                        elm_home,
+                        elm_version: elm_version.to_string(),
+                        keep: false,
+                    })
+                }
+                // Left behind by a previous process with the same id.
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Create a sandbox with a copy of the installed packages
+    /// and of the dependency solver cache of an existing `ELM_HOME`.
+    pub fn seeded_from<P: AsRef<Path>, S: ToString>(
+        elm_home: P,
+        elm_version: S,
+    ) -> std::io::Result<Self> {
+        let sandbox = Self::new(elm_version)?;
+        let elm_home = elm_home.as_ref();
+        let packages = |elm_home: &Path| paths::packages_dir(elm_home, &sandbox.elm_version);
+        let dirs = [
+            (packages(elm_home), packages(&sandbox.elm_home)),
+            (
+                Pkg::pubgrub_cache_dir(elm_home),
+                Pkg::pubgrub_cache_dir(&sandbox.elm_home),
+            ),
+        ];
+        for (from, to) in dirs {
+            if from.is_dir() {
+                copy_dir(&from, &to)?;
+            }
+        }
+        Ok(sandbox)
+    }
+
+    /// Also copy the packages installed for another version of elm in an existing `ELM_HOME`,
+    /// for solvers merging the packages of several versions.
+    pub fn seed_elm_version<P: AsRef<Path>>(
+        &self,
+        elm_home: P,
+        elm_version: &str,
+    ) -> std::io::Result<()> {
+        let from = paths::packages_dir(elm_home.as_ref(), elm_version);
+        if from.is_dir() {
+            copy_dir(&from, &paths::packages_dir(&self.elm_home, elm_version))?;
+        }
+        Ok(())
+    }
+
+    /// Create a sandbox where the packages of an archive of `ELM_HOME` are installed.
+    /// Only their `elm.json` files are written, which is enough to solve dependencies offline.
+    pub fn from_snapshot<S: ToString>(snapshot: &Archive, elm_version: S) -> std::io::Result<Self> {
+        let sandbox = Self::new(elm_version)?;
+        snapshot.extract_configs(&sandbox.elm_home, &sandbox.elm_version)?;
+        Ok(sandbox)
+    }
+
+    /// Path of the temporary `ELM_HOME`.
+    pub fn elm_home(&self) -> &Path {
+        &self.elm_home
+    }
+
+    /// Offline solver reading installed packages from the sandbox.
+    /// Online solvers built on top of it also download packages into the sandbox.
+    pub fn offline_solver(&self) -> Offline {
+        Offline::new(&self.elm_home, &self.elm_version)
+    }
+
+    /// Command running a program, such as the elm compiler, with `ELM_HOME` set to the sandbox.
+    pub fn command<S: AsRef<OsStr>>(&self, program: S) -> Command {
+        let mut command = Command::new(program);
+        command.env("ELM_HOME", &self.elm_home);
+        command
+    }
+
+    /// Keep the temporary `ELM_HOME` instead of removing it, and return its path.
+    pub fn keep(mut self) -> PathBuf {
+        self.keep = true;
+        self.elm_home.clone()
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        if !self.keep {
+            // Failing to clean up is not an error, the system cleans its temporary directory.
+            let _ = std::fs::remove_dir_all(&self.elm_home);
+        }
+    }
+}
+
+/// Recursively copy a directory.
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
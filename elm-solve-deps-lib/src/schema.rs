@@ -0,0 +1,144 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module providing JSON schemas of the machine-readable outputs of this crate.
+//!
+//! Tools integrating the solver parse its JSON outputs: solutions, structured errors,
+//! progress events, provenance reports, comparisons of offline and online solutions,
+//! and the reports of the registry and lint commands.
+//! Their JSON schemas are generated from the Rust types serializing them,
+//! such that they never drift, and integrators can validate outputs
+//! and generate code against them.
+//!
+//! These outputs are stable contracts: fields may be added,
+//! but existing fields are never renamed nor removed without a major version bump.
+//! Each schema has a stable name, listed in [`NAMES`].
+//!
+//! ```
+//! # use elm_solve_deps::schema;
+//! let solution = schema::schema("solution").unwrap();
+//! assert_eq!(solution["title"], "AppDependencies");
+//! assert!(schema::schema("sbom").is_err());
+//! for name in schema::NAMES {
+//!     assert!(schema::schema(name).is_ok());
+//! }
+//! ```
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject, StringValidation};
+use schemars::{schema_for, JsonSchema};
+use thiserror::Error;
+
+use crate::bench::BenchRecord;
+use crate::constraint::Constraint;
+use crate::error_code::CodedError;
+#[cfg(feature = "fs")]
+use crate::health::PackageHealth;
+use crate::lint::LintReport;
+use crate::progress::ProgressEvent;
+use crate::project_config::{AppDependencies, Pkg};
+use crate::provenance::PackageProvenance;
+use crate::report::ModesComparison;
+use crate::stale_bounds::StaleBound;
+use crate::stats::PkgStats;
+
+/// Names of the available schemas.
+///
+/// - `solution`: a solution, as printed by the solver.
+/// - `error`: a structured error, with its stable code.
+/// - `progress`: a progress event of a resolution.
+/// - `provenance`: the source of every package version of a solution.
+/// - `modes-comparison`: a comparison of the offline and online solutions of a project.
+/// - `stats`: the statistics of the packages of the registry.
+/// - `stale-bounds`: the stale upper bounds of the packages of the registry.
+/// - `bench`: the outcome of the resolution of every package version of the registry.
+/// - `lint`: the problems found in the dependencies of a package.
+/// - `health`: the health of the packages of a solution, with the `fs` feature.
+pub const NAMES: [&str; 10] = [
+    "solution",
+    "error",
+    "progress",
+    "provenance",
+    "modes-comparison",
+    "stats",
+    "stale-bounds",
+    "bench",
+    "lint",
+    "health",
+];
+
+/// Error requesting a schema that does not exist.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown schema `{0}`, expected one of: {}", NAMES.join(", "))]
+pub struct UnknownSchemaError(pub String);
+
+/// JSON schema of a machine-readable output, by name.
+pub fn schema(name: &str) -> Result<serde_json::Value, UnknownSchemaError> {
+    let root = match name {
+        "solution" => schema_for!(AppDependencies),
+        "error" => schema_for!(CodedError),
+        "progress" => schema_for!(ProgressEvent),
+        "provenance" => schema_for!(Vec<PackageProvenance>),
+        "modes-comparison" => schema_for!(ModesComparison),
+        "stats" => schema_for!(Vec<PkgStats>),
+        "stale-bounds" => schema_for!(Vec<StaleBound>),
+        "bench" => schema_for!(Vec<BenchRecord>),
+        "lint" => schema_for!(LintReport),
+        #[cfg(feature = "fs")]
+        "health" => schema_for!(Vec<PackageHealth>),
+        _ => return Err(UnknownSchemaError(name.to_string())),
+    };
+    Ok(serde_json::to_value(root).unwrap_or_default())
+}
+
+/// String schema matching a pattern.
+fn pattern(pattern: &str) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        string: Some(Box::new(StringValidation {
+            pattern: Some(pattern.to_string()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+impl JsonSchema for Pkg {
+    fn schema_name() -> String {
+        "Pkg".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        pattern("^[^/]+/[^/]+$")
+    }
+}
+
+impl JsonSchema for Constraint {
+    fn schema_name() -> String {
+        "Constraint".to_string()
+    }
+
+    // Constraints without an elm equivalent are printed in the pubgrub syntax,
+    // so only the string type is enforced.
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+/// Schema of versions, which are defined by pubgrub,
+/// for the fields of type [`SemVer`](crate::semver::SemVer).
+pub(crate) struct SemVerSchema;
+
+impl JsonSchema for SemVerSchema {
+    fn schema_name() -> String {
+        "SemVer".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        pattern(r"^\d+\.\d+\.\d+$")
+    }
+}
@@ -0,0 +1,209 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module scoring solutions, to pick the best one according to custom objectives.
+//!
+//! The solver picks versions greedily, one package at a time,
+//! so its solution is not necessarily the best one overall.
+//! [`enumerate_solutions`] finds other solutions in the neighbourhood of the first one,
+//! by excluding versions it picked, and [`best_solution`] keeps the one
+//! with the lowest score according to a scoring function.
+//! Any function of an [`AppDependencies`] can be a scoring function,
+//! and [`Objective`] provides presets, such as the freshest solution overall.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::pkg_version::Cache;
+use crate::project_config::{AppDependencies, Pkg};
+
+/// Preset objectives, to score solutions with [`Objective::score`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Objective {
+    /// Prefer the newest versions overall,
+    /// minimizing the total number of newer versions than the selected ones.
+    Freshest,
+    /// Prefer mature and established versions, first minimizing the number
+    /// of packages selected before their 1.1.0 version,
+    /// then the total number of older versions than the selected ones.
+    Conservative,
+}
+
+/// Error parsing an [`Objective`], which must be either `freshest` or `conservative`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown objective `{0}`, expected `freshest` or `conservative`")]
+pub struct ObjectiveParseError(pub String);
+
+/// A solution with its score, lower is better.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ScoredSolution {
+    /// The solution.
+    pub solution: AppDependencies,
+    /// Its score.
+    pub score: f64,
+    /// Number of solutions compared to pick this one.
+    pub candidates: usize,
+}
+
+impl Objective {
+    /// Score a solution according to the objective, lower is better.
+    ///
+    /// The `versions` cache tells which versions exist for each package,
+    /// packages missing from it count as having a single version.
+    pub fn score(self, solution: &AppDependencies, versions: &Cache) -> f64 {
+        match self {
+            Self::Freshest => newer_versions(solution, versions) as f64,
+            Self::Conservative => {
+                // Older versions never outnumber the versions of the whole registry.
+                let registry_size = versions.versions_count();
+                let immature = immature_packages(solution) as f64;
+                immature * (registry_size + 1) as f64 + older_versions(solution, versions) as f64
+            }
+        }
+    }
+}
+
+impl FromStr for Objective {
+    type Err = ObjectiveParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "freshest" => Ok(Self::Freshest),
+            "conservative" => Ok(Self::Conservative),
+            _ => Err(ObjectiveParseError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Freshest => write!(f, "freshest"),
+            Self::Conservative => write!(f, "conservative"),
+        }
+    }
+}
+
+/// Total number of versions newer than the selected ones, a measure of the age of a solution.
+pub fn newer_versions(solution: &AppDependencies, versions: &Cache) -> usize {
+    (all_packages(solution))
+        .filter_map(|(pkg, v)| Some(versions.cache.get(pkg)?.range(v.bump_patch()..).count()))
+        .sum()
+}
+
+/// Total number of versions older than the selected ones.
+pub fn older_versions(solution: &AppDependencies, versions: &Cache) -> usize {
+    (all_packages(solution))
+        .filter_map(|(pkg, v)| Some(versions.cache.get(pkg)?.range(..*v).count()))
+        .sum()
+}
+
+/// Number of packages selected before their 1.1.0 version,
+/// which are often the first drafts of their API.
+pub fn immature_packages(solution: &AppDependencies) -> usize {
+    let mature = SemVer::new(1, 1, 0);
+    all_packages(solution).filter(|(_, v)| **v < mature).count()
+}
+
+fn all_packages(solution: &AppDependencies) -> impl Iterator<Item = (&Pkg, &SemVer)> {
+    solution.direct.iter().chain(solution.indirect.iter())
+}
+
+/// Enumerate up to `max_solutions` distinct solutions, starting with the one of the solver.
+///
+/// The `solve` function is given versions to exclude from the resolution,
+/// typically with [`Policies::exclude`](crate::policy::Policies::exclude).
+/// The first resolution excludes nothing, and its error is returned if it fails.
+/// Then, breadth first, each version picked in a solution is excluded in turn,
+/// on top of the exclusions leading to that solution, to find other ones.
+/// Failures of these other resolutions only mean there is no solution this way,
+/// they are not errors.
+///
+/// Solutions are not all the possible ones, but the ones closest to the first solution.
+/// Excluding a version excludes it for every dependent package,
+/// so some combinations of versions are never reached.
+pub fn enumerate_solutions<Solve, E>(
+    max_solutions: usize,
+    solve: Solve,
+) -> Result<Vec<AppDependencies>, E>
+where
+    Solve: Fn(&[(Pkg, SemVer)]) -> Result<AppDependencies, E>,
+{
+    let mut solutions = vec![solve(&[])?];
+    let mut tried: BTreeSet<Vec<(Pkg, SemVer)>> = BTreeSet::new();
+    let mut queue = VecDeque::from([(Vec::new(), 0)]);
+    while let Some((excluded, index)) = queue.pop_front() {
+        let picked: Vec<(Pkg, SemVer)> = (all_packages(&solutions[index]))
+            .map(|(pkg, v)| (pkg.clone(), *v))
+            .collect();
+        for pkg_version in picked {
+            if solutions.len() >= max_solutions {
+                return Ok(solutions);
+            }
+            let mut next: Vec<(Pkg, SemVer)> = excluded.clone();
+            next.push(pkg_version);
+            next.sort();
+            if !tried.insert(next.clone()) {
+                continue;
+            }
+            if let Ok(solution) = solve(&next) {
+                if !solutions.contains(&solution) {
+                    solutions.push(solution);
+                    queue.push_back((next, solutions.len() - 1));
+                }
+            }
+        }
+    }
+    Ok(solutions)
+}
+
+/// Pick the solution with the lowest score.
+/// In case of a tie, the first one is picked, such that the solution of the solver wins.
+///
+/// ```
+/// # use elm_solve_deps::project_config::{AppDependencies, Pkg};
+/// # use elm_solve_deps::scoring::{best_solution, immature_packages};
+/// let solution = |version| AppDependencies {
+///     direct: [(Pkg::new("elm", "core"), version)].into_iter().collect(),
+///     indirect: Default::default(),
+/// };
+/// let candidates = vec![solution((1, 0, 0).into()), solution((1, 0, 5).into()), solution((1, 1, 0).into())];
+/// let best = best_solution(candidates, |s| immature_packages(s) as f64).unwrap();
+/// assert_eq!(best.solution, solution((1, 1, 0).into()));
+/// assert_eq!((best.score, best.candidates), (0.0, 3));
+/// ```
+pub fn best_solution<Score>(solutions: Vec<AppDependencies>, score: Score) -> Option<ScoredSolution>
+where
+    Score: Fn(&AppDependencies) -> f64,
+{
+    let candidates = solutions.len();
+    (solutions.into_iter())
+        .map(|solution| (score(&solution), solution))
+        .reduce(|best, next| if next.0 < best.0 { next } else { best })
+        .map(|(score, solution)| ScoredSolution {
+            solution,
+            score,
+            candidates,
+        })
+}
+
+/// Enumerate up to `max_solutions` solutions with [`enumerate_solutions`],
+/// and pick the best one with [`best_solution`].
+pub fn solve_best<Solve, Score, E>(
+    max_solutions: usize,
+    score: Score,
+    solve: Solve,
+) -> Result<ScoredSolution, E>
+where
+    Solve: Fn(&[(Pkg, SemVer)]) -> Result<AppDependencies, E>,
+    Score: Fn(&AppDependencies) -> f64,
+{
+    let solutions = enumerate_solutions(max_solutions.max(1), solve)?;
+    // There is always the first solution.
+    Ok(best_solution(solutions, score).unwrap())
+}
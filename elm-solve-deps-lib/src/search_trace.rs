@@ -0,0 +1,221 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module recording how the solver explores the search space, to find what makes it slow.
+//!
+//! A [`TraceRecorder`] provides a [`Progress`] handle timestamping every [`ProgressEvent`]
+//! of the resolutions it is attached to, and gathers them into a [`SearchTrace`].
+//! Pubgrub does not expose its derivations, so the trace records what the solver
+//! asks of its dependency provider: the package chosen at each step with its number
+//! of compatible versions, or without version when the solver derives a conflict,
+//! the configs loaded, and the versions decided or unavailable.
+//! A package decided more than once in a resolution was decided again after backtracking.
+//!
+//! Serialized, a trace is a JSON object with the `format` of the trace, currently `1`,
+//! and its `entries`, each one being a progress event with the number of microseconds
+//! elapsed since the recorder was created:
+//!
+//! ```json
+//! {
+//!   "format": 1,
+//!   "entries": [
+//!     { "elapsed-us": 0, "event": "phase", "phase": "solve" },
+//!     { "elapsed-us": 41, "event": "chose", "pkg": "elm/core", "version": "1.0.5", "candidates": 6 },
+//!     { "elapsed-us": 97, "event": "decided", "pkg": "elm/core", "version": "1.0.5", "decisions": 1, "undetermined": 0 }
+//!   ]
+//! }
+//! ```
+//!
+//! [`SearchTrace::summarize`] then points at the packages the solver struggles with.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::progress::{Phase, Progress, ProgressEvent};
+use crate::project_config::Pkg;
+
+/// Current format of serialized traces, incremented on incompatible changes.
+pub const TRACE_FORMAT: u32 = 1;
+
+/// Timestamped events of dependency resolutions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchTrace {
+    /// Format of the trace, see [`TRACE_FORMAT`].
+    pub format: u32,
+    /// The events, in the order they happened.
+    pub entries: Vec<TraceEntry>,
+}
+
+/// An event of a [`SearchTrace`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TraceEntry {
+    /// Microseconds elapsed since the recorder was created.
+    pub elapsed_us: u64,
+    /// The event.
+    #[serde(flatten)]
+    pub event: ProgressEvent,
+}
+
+/// Recorder of the events of the solvers it is attached to, with [`TraceRecorder::progress`].
+///
+/// Clones share the same recorded events.
+///
+/// ```
+/// # use elm_solve_deps::progress::{Phase, ProgressEvent};
+/// # use elm_solve_deps::project_config::Pkg;
+/// # use elm_solve_deps::search_trace::{SearchTrace, TraceRecorder};
+/// # use elm_solve_deps::semver::SemVer;
+/// let recorder = TraceRecorder::new();
+/// let progress = recorder.progress();
+/// let decided = |decisions| ProgressEvent::Decided {
+///     pkg: Pkg::new("elm", "core"),
+///     version: SemVer::new(1, 0, 5),
+///     decisions,
+///     undetermined: 0,
+/// };
+/// progress.emit(ProgressEvent::Phase { phase: Phase::Solve });
+/// progress.emit(decided(1));
+/// progress.emit(decided(2));
+/// let trace = recorder.trace();
+/// let json = serde_json::to_string(&trace).unwrap();
+/// assert_eq!(serde_json::from_str::<SearchTrace>(&json).unwrap(), trace);
+///
+/// let summary = trace.summarize();
+/// assert_eq!((summary.decisions, summary.backtracks), (2, 1));
+/// assert_eq!(summary.hot_spots[0].pkg, Pkg::new("elm", "core"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TraceRecorder {
+    start: Instant,
+    entries: Arc<Mutex<Vec<TraceEntry>>>,
+}
+
+/// Effort spent by the solver on one package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct HotSpot {
+    /// The package.
+    pub pkg: Pkg,
+    /// Number of times one of its versions was decided.
+    pub decisions: usize,
+    /// Number of decisions beyond the first one of each resolution, made after backtracking.
+    pub backtracks: usize,
+    /// Number of times it was chosen without any compatible version,
+    /// making the solver derive a conflict.
+    pub conflicts: usize,
+    /// Number of its versions found unavailable.
+    pub unavailable: usize,
+    /// Microseconds spent loading its configs.
+    pub load_us: u64,
+}
+
+/// Summary of a [`SearchTrace`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TraceSummary {
+    /// Microseconds elapsed between the first and last events.
+    pub duration_us: u64,
+    /// Total number of decisions.
+    pub decisions: usize,
+    /// Total number of decisions made after backtracking.
+    pub backtracks: usize,
+    /// Total number of conflicts derived from packages without compatible versions.
+    pub conflicts: usize,
+    /// Packages, by decreasing number of backtracks and conflicts, then loading time.
+    pub hot_spots: Vec<HotSpot>,
+}
+
+impl TraceRecorder {
+    /// Start recording, timestamps are relative to now.
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            entries: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Handle recording the events it receives,
+    /// to attach to solvers, possibly combined with other handles with [`Progress::and`].
+    pub fn progress(&self) -> Progress {
+        let recorder = self.clone();
+        Progress::new(move |event| {
+            let entry = TraceEntry {
+                elapsed_us: recorder.start.elapsed().as_micros() as u64,
+                event: event.clone(),
+            };
+            recorder.entries.lock().unwrap().push(entry);
+        })
+    }
+
+    /// The events recorded so far.
+    pub fn trace(&self) -> SearchTrace {
+        SearchTrace {
+            format: TRACE_FORMAT,
+            entries: self.entries.lock().unwrap().clone(),
+        }
+    }
+}
+
+impl Default for TraceRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SearchTrace {
+    /// Count the decisions, backtracks and conflicts of each package,
+    /// and the time spent loading their configs,
+    /// which is the time elapsed since the event preceding each load.
+    pub fn summarize(&self) -> TraceSummary {
+        let mut spots: BTreeMap<Pkg, HotSpot> = BTreeMap::new();
+        // Packages decided during the current resolution.
+        let mut decided: BTreeSet<&Pkg> = BTreeSet::new();
+        let mut previous_us = self.entries.first().map_or(0, |e| e.elapsed_us);
+        for entry in &self.entries {
+            match &entry.event {
+                ProgressEvent::Phase {
+                    phase: Phase::Solve,
+                } => decided.clear(),
+                ProgressEvent::Chose {
+                    pkg, version: None, ..
+                } => spot(&mut spots, pkg).conflicts += 1,
+                ProgressEvent::Unavailable { pkg, .. } => spot(&mut spots, pkg).unavailable += 1,
+                ProgressEvent::Loaded { pkg, .. } => {
+                    spot(&mut spots, pkg).load_us += entry.elapsed_us.saturating_sub(previous_us)
+                }
+                ProgressEvent::Decided { pkg, .. } => {
+                    let spot = spot(&mut spots, pkg);
+                    spot.backtracks += !decided.insert(pkg) as usize;
+                    spot.decisions += 1;
+                }
+                _ => {}
+            }
+            previous_us = entry.elapsed_us;
+        }
+        let mut hot_spots: Vec<HotSpot> = spots.into_values().collect();
+        hot_spots.sort_by_key(|s| (Reverse(s.backtracks + s.conflicts), Reverse(s.load_us)));
+        let first_us = self.entries.first().map_or(0, |e| e.elapsed_us);
+        TraceSummary {
+            duration_us: previous_us.saturating_sub(first_us),
+            decisions: hot_spots.iter().map(|s| s.decisions).sum(),
+            backtracks: hot_spots.iter().map(|s| s.backtracks).sum(),
+            conflicts: hot_spots.iter().map(|s| s.conflicts).sum(),
+            hot_spots,
+        }
+    }
+}
+
+/// The hot spot of a package, added if it is not there yet.
+fn spot<'a>(spots: &'a mut BTreeMap<Pkg, HotSpot>, pkg: &Pkg) -> &'a mut HotSpot {
+    (spots.entry(pkg.clone())).or_insert_with(|| HotSpot {
+        pkg: pkg.clone(),
+        decisions: 0,
+        backtracks: 0,
+        conflicts: 0,
+        unavailable: 0,
+        load_us: 0,
+    })
+}
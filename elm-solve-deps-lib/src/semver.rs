@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module re-exporting the semantic versions used throughout this crate.
+//!
+//! Versions and their parsing errors are the pubgrub ones, as is,
+//! available here to avoid adding a dependency on the matching pubgrub version.
+//!
+//! ```
+//! # use elm_solve_deps::semver::SemVer;
+//! let version: SemVer = "1.0.5".parse().unwrap();
+//! assert_eq!(version, SemVer::new(1, 0, 5));
+//! ```
+
+pub use pubgrub::version::{SemanticVersion, SemanticVersion as SemVer, VersionParseError};
@@ -0,0 +1,111 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module defining a content-addressed cache of `elm.json` files, shared across `ELM_HOME`s.
+//!
+//! Users isolating projects with one `ELM_HOME` per project
+//! would otherwise download and store the same package configs once per `ELM_HOME`.
+//! The shared cache stores every config once, addressed by the sha256 of its content,
+//! and is consulted before the dependency solver cache of the current `ELM_HOME`.
+//! Configs found there are hard-linked, or copied if that fails,
+//! into that dependency solver cache, if not already there.
+//!
+//! The shared cache directory has the following layout.
+//!
+//! ```txt
+//! shared-cache/
+//! ├── objects/ab/cdef...         elm.json files, named by the sha256 of their content
+//! └── index/author/package/version   the sha256 of the elm.json of that package version
+//! ```
+
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::compression;
+use crate::pkg_version::{PkgVersion, PkgVersionError};
+use crate::project_config::PackageConfig;
+
+/// A content-addressed cache of `elm.json` files.
+#[derive(Debug, Clone)]
+pub struct SharedCache {
+    root: PathBuf,
+}
+
+impl SharedCache {
+    /// Use the given directory as shared cache.
+    /// It is created when the first config is stored.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Load the `elm.json` of a package version from the shared cache,
+    /// and link it into the dependency solver cache of `elm_home`.
+    ///
+    /// Fails if the package version is not in the shared cache,
+    /// or if its content does not match its hash anymore.
+    pub fn load_config<P: AsRef<Path>>(
+        &self,
+        pkg_version: &PkgVersion,
+        elm_home: P,
+    ) -> Result<PackageConfig, PkgVersionError> {
+        let hash = fs::read_to_string(self.index_file(pkg_version))?;
+        let object = self.object_file(hash.trim());
+        let bytes = fs::read(&object)?;
+        if sha256_hex(&bytes) != hash.trim() {
+            let msg = format!("corrupted object in the shared cache: {}", object.display());
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg).into());
+        }
+        let config = serde_json::from_str(&compression::decode(bytes)?)?;
+        let cache_file = pkg_version.pubgrub_cache_file(elm_home);
+        if !cache_file.exists() {
+            link_or_copy(&object, &cache_file)?;
+        }
+        Ok(config)
+    }
+
+    /// Store the `elm.json` of a package version,
+    /// already saved in the dependency solver cache of `elm_home`, into the shared cache.
+    pub fn store<P: AsRef<Path>>(&self, pkg_version: &PkgVersion, elm_home: P) -> io::Result<()> {
+        let source = pkg_version.pubgrub_cache_file(elm_home);
+        let hash = sha256_hex(&fs::read(&source)?);
+        let object = self.object_file(&hash);
+        if !object.exists() {
+            link_or_copy(&source, &object)?;
+        }
+        let index_file = self.index_file(pkg_version);
+        if let Some(parent) = index_file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(index_file, hash)
+    }
+
+    fn index_file(&self, pkg_version: &PkgVersion) -> PathBuf {
+        let pkg = &pkg_version.author_pkg;
+        (self.root.join("index"))
+            .join(&pkg.author)
+            .join(&pkg.pkg)
+            .join(pkg_version.version.to_string())
+    }
+
+    fn object_file(&self, hash: &str) -> PathBuf {
+        let (prefix, rest) = hash.split_at(hash.len().min(2));
+        self.root.join("objects").join(prefix).join(rest)
+    }
+}
+
+fn sha256_hex(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Hard-link a file to a new location, or copy it if hard-linking fails,
+/// for example when both locations are on different filesystems.
+fn link_or_copy(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if to.exists() {
+        fs::remove_file(to)?;
+    }
+    fs::hard_link(from, to).or_else(|_| fs::copy(from, to).map(|_| ()))
+}
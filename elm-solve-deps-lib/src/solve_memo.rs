@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module memoizing solutions across invocations, keyed by a hash of their inputs.
+//!
+//! CI matrices and watch modes solve the same inputs over and over.
+//! Contrary to the [`CachedSolution`] of the most recent resolution,
+//! the [`SolveMemo`] keeps the solutions of many different inputs,
+//! each one in a file of `ELM_HOME/pubgrub/solve_memo/` named by the sha256 of its key.
+//! The key covers the project config, the additional constraints,
+//! a description of the solver configuration such as its version strategy,
+//! and the number of package versions known in the versions cache,
+//! such that solutions are solved again when new versions are published.
+//!
+//! This module is only available with the `sha2` feature.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::cached_solution::{CachedSolution, SolveInputs};
+use crate::constraint::Constraint;
+use crate::pkg_version::CacheError;
+use crate::project_config::{Pkg, ProjectConfig};
+use crate::solver::ExtraMode;
+
+/// Default maximum number of memoized solutions, the least recently written ones are removed.
+pub const DEFAULT_MAX_ENTRIES: usize = 256;
+
+/// Store of solutions, keyed by a hash of their inputs.
+#[derive(Debug, Clone)]
+pub struct SolveMemo {
+    dir: PathBuf,
+    max_entries: usize,
+}
+
+/// Everything a solution depends on, hashed into the key of a memoized solution.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct MemoKey<'a> {
+    inputs: SolveInputs,
+    configuration: &'a str,
+    registry_sequence: usize,
+}
+
+impl SolveMemo {
+    /// Open the store of `ELM_HOME`, created on the first insertion.
+    pub fn new<P: AsRef<Path>>(elm_home: P) -> Self {
+        Self {
+            dir: Pkg::pubgrub_cache_dir(elm_home).join("solve_memo"),
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+
+    /// Change the maximum number of memoized solutions.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Directory of the memoized solutions.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Hash the inputs of a resolution into a key.
+    ///
+    /// The `configuration` describes every other setting changing the solution,
+    /// such as the version strategy and the version policies.
+    /// The `registry_sequence` is typically [`Cache::versions_count`](crate::pkg_version::Cache::versions_count),
+    /// such that keys change when new package versions are published.
+    pub fn key(
+        project_elm_json: &ProjectConfig,
+        use_test: bool,
+        additional_constraints: &[(Pkg, Constraint)],
+        extra_mode: ExtraMode,
+        configuration: &str,
+        registry_sequence: usize,
+    ) -> Result<String, serde_json::Error> {
+        let key = MemoKey {
+            inputs: SolveInputs::new(
+                project_elm_json,
+                use_test,
+                additional_constraints,
+                extra_mode,
+            )?,
+            configuration,
+            registry_sequence,
+        };
+        // Going through a JSON value sorts the keys of all objects.
+        let json = serde_json::to_value(key)?.to_string();
+        Ok(format!("{:x}", Sha256::digest(json.as_bytes())))
+    }
+
+    /// The memoized solution of a key, if any.
+    ///
+    /// Check it with [`CachedSolution::verify`] before using it,
+    /// in case of a corrupted or tampered entry.
+    pub fn get(&self, key: &str) -> Option<CachedSolution> {
+        let s = std::fs::read_to_string(self.entry_path(key)).ok()?;
+        serde_json::from_str(&s).ok()
+    }
+
+    /// Memoize the solution of a key, and remove the least recently written solutions
+    /// beyond the maximum number of entries.
+    pub fn insert(&self, key: &str, solution: &CachedSolution) -> Result<(), CacheError> {
+        let s = serde_json::to_string(solution)?;
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.entry_path(key), &s)?;
+        self.prune()?;
+        Ok(())
+    }
+
+    /// Remove the memoized solution of a key.
+    /// Return false if there was none.
+    pub fn invalidate(&self, key: &str) -> std::io::Result<bool> {
+        match std::fs::remove_file(self.entry_path(key)) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Remove all memoized solutions, and return how many there were.
+    pub fn clear(&self) -> std::io::Result<usize> {
+        let entries = self.entries()?;
+        for (path, _) in &entries {
+            std::fs::remove_file(path)?;
+        }
+        Ok(entries.len())
+    }
+
+    /// Remove the least recently written solutions beyond the maximum number of entries.
+    fn prune(&self) -> std::io::Result<()> {
+        let mut entries = self.entries()?;
+        if entries.len() <= self.max_entries {
+            return Ok(());
+        }
+        entries.sort_by_key(|(_, modified)| *modified);
+        let excess = entries.len() - self.max_entries;
+        for (path, _) in entries.into_iter().take(excess) {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Memoized solution files, with their modification time.
+    fn entries(&self) -> std::io::Result<Vec<(PathBuf, std::time::SystemTime)>> {
+        let dir = match std::fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut entries = Vec::new();
+        for entry in dir {
+            let entry = entry?;
+            if entry.path().extension().is_some_and(|ext| ext == "json") {
+                entries.push((entry.path(), entry.metadata()?.modified()?));
+            }
+        }
+        Ok(entries)
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(key).with_extension("json")
+    }
+}
@@ -2,22 +2,81 @@
 
 //! Module providing helper functions to solve dependencies in the elm ecosystem.
 
+// The solvers return the errors of pubgrub unchanged, larger than what clippy recommends.
+#![allow(clippy::result_large_err)]
+
 use std::borrow::Borrow;
 use std::cell::RefCell;
+use std::collections::BTreeMap;
+#[cfg(feature = "fs")]
 use std::collections::BTreeSet;
 use std::error::Error;
-use std::path::PathBuf;
+use std::fmt;
+use std::marker::PhantomData;
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+#[cfg(feature = "fs")]
+use std::sync::{Arc, Mutex, RwLock};
+#[cfg(feature = "fs")]
+use std::thread::{self, JoinHandle};
+#[cfg(feature = "fs")]
+use std::time::Duration;
 
-use pubgrub::error::PubGrubError;
 use pubgrub::solver::DependencyProvider;
 use pubgrub::type_aliases::Map;
 use pubgrub::version::SemanticVersion as SemVer;
 use pubgrub::{range::Range, solver::Dependencies};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
+use crate::budget::{Interrupted, Limits, PartialSolution};
+#[cfg(feature = "fs")]
+use crate::clock::{Clock, SystemClock};
 use crate::constraint::Constraint;
+#[cfg(feature = "fs")]
+use crate::corpus::Recorder;
 use crate::dependency_provider::ProjectAdapter;
-use crate::pkg_version::{Cache, CacheError, PkgVersion, PkgVersionError};
-use crate::project_config::{AppDependencies, PackageConfig, Pkg, PkgParseError, ProjectConfig};
+#[cfg(feature = "fs")]
+use crate::diagnostics::Diagnostics;
+#[cfg(feature = "fs")]
+use crate::elm_home::{Directories, Directory, InstalledPackages};
+#[cfg(feature = "fs")]
+use crate::federation::{FederatedCache, Federation, MAIN_REMOTE};
+#[cfg(feature = "fs")]
+use crate::fetch::{FetchError, HttpFetch};
+#[cfg(feature = "fs")]
+use crate::missing::MissingCache;
+#[cfg(feature = "fs")]
+use crate::overrides::{OverrideError, PackageOverride};
+#[cfg(feature = "fs")]
+use crate::packed::PackedStore;
+#[cfg(feature = "fs")]
+use crate::pkg_version::{
+    Cache, CacheError, ConfigFallback, PkgVersion, PkgVersionError, PkgVersionParseError,
+};
+use crate::policy::HoldError;
+#[cfg(feature = "fs")]
+use crate::policy::Policies;
+use crate::progress::{Phase, Progress, ProgressEvent};
+#[cfg(feature = "fs")]
+use crate::project_config::PkgParseError;
+use crate::project_config::{AppDependencies, PackageConfig, Pkg, ProjectConfig};
+#[cfg(feature = "fs")]
+use crate::provenance::{Provenance, Source};
+#[cfg(feature = "fs")]
+use crate::registry_view::RegistryView;
+#[cfg(feature = "fs")]
+use crate::release_age::ReleaseTimes;
+#[cfg(all(feature = "fs", feature = "sha2"))]
+use crate::shared_cache::SharedCache;
+use crate::telemetry::Span;
+
+/// Error of a resolution, re-exported such that downstream code does not depend on pubgrub.
+pub use pubgrub::error::PubGrubError;
+/// Explanations of failed resolutions, re-exported such that downstream code
+/// does not depend on pubgrub.
+pub use pubgrub::report::{DefaultStringReporter, DerivationTree, Reporter};
 
 /// Advanced configurable function to solve dependencies of an elm project.
 ///
@@ -29,19 +88,23 @@ use crate::project_config::{AppDependencies, PackageConfig, Pkg, PkgParseError,
 /// ```
 /// # use elm_solve_deps::project_config::Pkg;
 /// # use elm_solve_deps::constraint::Constraint;
-/// # use pubgrub::range::Range;
+/// # use elm_solve_deps::range::Range;
 /// let extra = &[(
 ///   Pkg::new("jfmengels", "elm-review"),
 ///   Constraint(Range::between( (2,6,1), (3,0,0) )),
 /// )];
 /// ```
 ///
+/// The `extra_mode` argument controls how these additional constraints combine
+/// with the dependencies already declared in the project config.
+/// See [`ExtraMode`] for the different possibilities.
+///
 /// You are required to provide two functions,
 /// namely `fetch_elm_json` and `list_available_versions`,
 /// implementing the following pseudo trait bounds:
 ///
 /// ```ignore
-/// fetch_elm_json: Fn(&Pkg, SemVer) -> Result<PackageConfig, Error>
+/// fetch_elm_json: Fn(&Pkg, SemVer) -> Result<Option<PackageConfig>, Error>
 /// list_available_versions: Fn(&Pkg) -> Result<Iterator<SemVer>, Error>
 /// ```
 ///
@@ -50,63 +113,606 @@ use crate::project_config::{AppDependencies, PackageConfig, Pkg, PkgParseError,
 /// Remark that the order in the versions iterator returned will correspond
 /// to the prioritization for picking versions.
 /// This means prioritizing newest or oldest versions is just a `.reverse()` on your part.
-pub fn solve_deps_with<Fetch, L, Versions>(
+///
+/// If the `elm.json` of a listed version turns out to be unavailable,
+/// `fetch_elm_json` can return `Ok(None)` instead of an error,
+/// and the solver will backtrack to pick other versions.
+///
+/// Hints from the `"x-solve-deps"` field of the project `elm.json`, if any,
+/// further exclude and reorder the versions listed by `list_available_versions`.
+/// See the [`hints`](crate::hints) module.
+/// The versions of the indirect dependencies recorded in the `elm.json` of an application
+/// are also tried first, see [`IndirectPins`].
+///
+/// Both functions share the same error type `E`, which is kept as is in the returned
+/// [`SolveError`], instead of being erased into a `Box<dyn Error>`.
+/// A [`SolveError`] can still be converted into a [`PubGrubError`] if needed.
+pub fn solve_deps_with<Fetch, L, Versions, E>(
     project_elm_json: &ProjectConfig,
     use_test: bool,
     additional_constraints: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
     fetch_elm_json: Fetch,
     list_available_versions: L,
-) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>>
+) -> Result<AppDependencies, SolveError<E>>
 where
-    Fetch: Fn(&Pkg, SemVer) -> Result<PackageConfig, Box<dyn Error>>,
-    L: Fn(&Pkg) -> Result<Versions, Box<dyn Error>>,
+    Fetch: Fn(&Pkg, SemVer) -> Result<Option<PackageConfig>, E>,
+    L: Fn(&Pkg) -> Result<Versions, E>,
     Versions: Iterator<Item = SemVer>,
+    E: Error + 'static,
 {
+    solve_deps_with_limits(
+        project_elm_json,
+        use_test,
+        additional_constraints,
+        extra_mode,
+        &Limits::default(),
+        fetch_elm_json,
+        list_available_versions,
+    )
+}
+
+/// Same as [`solve_deps_with`], but interrupting the resolution if it exceeds the given limits.
+///
+/// In that case, the [`SolveError::Interrupted`] error contains the partial solution
+/// reached before the interruption.
+pub fn solve_deps_with_limits<Fetch, L, Versions, E>(
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+    limits: &Limits,
+    fetch_elm_json: Fetch,
+    list_available_versions: L,
+) -> Result<AppDependencies, SolveError<E>>
+where
+    Fetch: Fn(&Pkg, SemVer) -> Result<Option<PackageConfig>, E>,
+    L: Fn(&Pkg) -> Result<Versions, E>,
+    Versions: Iterator<Item = SemVer>,
+    E: Error + 'static,
+{
+    solve_deps_with_root_pinning(
+        project_elm_json,
+        use_test,
+        additional_constraints,
+        extra_mode,
+        RootPinning::Exact,
+        limits,
+        fetch_elm_json,
+        list_available_versions,
+    )
+}
+
+/// Same as [`solve_deps_with_limits`], but relaxing the versions of the direct dependencies
+/// of an application according to `root_pinning`, to allow upgrades.
+/// See [`RootPinning`].
+#[allow(clippy::too_many_arguments)]
+pub fn solve_deps_with_root_pinning<Fetch, L, Versions, E>(
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+    root_pinning: RootPinning,
+    limits: &Limits,
+    fetch_elm_json: Fetch,
+    list_available_versions: L,
+) -> Result<AppDependencies, SolveError<E>>
+where
+    Fetch: Fn(&Pkg, SemVer) -> Result<Option<PackageConfig>, E>,
+    L: Fn(&Pkg) -> Result<Versions, E>,
+    Versions: Iterator<Item = SemVer>,
+    E: Error + 'static,
+{
+    solve_deps_configured(
+        project_elm_json,
+        use_test,
+        additional_constraints,
+        extra_mode,
+        root_pinning,
+        TieBreak::default(),
+        limits,
+        None,
+        fetch_elm_json,
+        list_available_versions,
+    )
+}
+
+/// Same as [`solve_deps_with_root_pinning`], with the tie-breaking rule of the solver,
+/// reporting the progress of the resolution if a [`Progress`] handle is given.
+#[allow(clippy::too_many_arguments)]
+fn solve_deps_configured<Fetch, L, Versions, E>(
+    project_elm_json: &ProjectConfig,
+    use_test: bool,
+    additional_constraints: &[(Pkg, Constraint)],
+    extra_mode: ExtraMode,
+    root_pinning: RootPinning,
+    tie_break: TieBreak,
+    limits: &Limits,
+    progress: Option<&Progress>,
+    fetch_elm_json: Fetch,
+    list_available_versions: L,
+) -> Result<AppDependencies, SolveError<E>>
+where
+    Fetch: Fn(&Pkg, SemVer) -> Result<Option<PackageConfig>, E>,
+    L: Fn(&Pkg) -> Result<Versions, E>,
+    Versions: Iterator<Item = SemVer>,
+    E: Error + 'static,
+{
+    let hints = project_elm_json.solve_hints().cloned().unwrap_or_default();
+    let policies = hints
+        .policies(project_elm_json)
+        .map_err(SolveError::Hints)?;
+    let list_available_versions = |pkg: &Pkg| {
+        let versions = policies.filter_versions(pkg, list_available_versions(pkg)?);
+        Ok(hints.order_versions(pkg, versions).into_iter())
+    };
     let solver = Solver {
         fetch_elm_json,
         list_available_versions,
+        limits: limits.clone(),
+        tie_break,
+        partial: RefCell::new(PartialSolution::default()),
+        loaded: RefCell::new(Map::default()),
+        progress,
+        error: PhantomData,
     };
-    match project_elm_json {
-        ProjectConfig::Application(app_config) => {
-            let normal_deps = app_config.dependencies.direct.iter();
-            let test_deps = app_config.test_dependencies.direct.iter();
-            // Merge normal and test dependencies if solving with "use_test".
-            let mut direct_deps: Map<Pkg, Range<SemVer>> = if use_test {
-                normal_deps
-                    .chain(test_deps)
-                    .map(|(p, v)| (p.clone(), Range::exact(*v)))
-                    .collect()
-            } else {
-                normal_deps
-                    .map(|(p, v)| (p.clone(), Range::exact(*v)))
-                    .collect()
-            };
-            // Include the additional constraints.
-            for (p, r) in additional_constraints {
-                let dep_range = direct_deps.entry(p.clone()).or_insert_with(Range::any);
-                *dep_range = dep_range.intersection(&r.0);
+    if let Some(progress) = progress {
+        progress.emit(ProgressEvent::Phase {
+            phase: Phase::Solve,
+        });
+    }
+    let mut span = Span::start("solve").with_attribute("use_test", use_test);
+    let result = RootDependencies::with_root_pinning(
+        project_elm_json,
+        use_test,
+        additional_constraints,
+        extra_mode,
+        root_pinning,
+    )
+    .and_then(|root| {
+        span.set_attribute("root", &root.pkg);
+        span.set_attribute("version", root.version);
+        solve_helper(&root.pkg, root.version, root.dependencies, &solver)
+    })
+    .map_err(SolveError::from_pubgrub);
+    span.record_result(&result);
+    if let Some(progress) = progress {
+        progress.emit(ProgressEvent::Finished {
+            solved: result.is_ok(),
+            decisions: solver.partial.borrow().decisions,
+        });
+    }
+    result
+}
+
+/// Error type of [`solve_deps_with`], keeping the error type `E`
+/// of the `fetch_elm_json` and `list_available_versions` functions.
+#[derive(Error, Debug)]
+pub enum SolveError<E: Error + 'static> {
+    /// Failure of the `list_available_versions` function.
+    #[error("failed to list available versions")]
+    ListVersions(#[source] E),
+
+    /// Failure of the `fetch_elm_json` function.
+    #[error("failed to retrieve the elm.json of {package}@{version}")]
+    FetchElmJson {
+        /// The package whose elm.json could not be retrieved.
+        package: Pkg,
+        /// The version of that package.
+        version: SemVer,
+        /// The error of the `fetch_elm_json` function.
+        #[source]
+        source: E,
+    },
+
+    /// The resolution was interrupted before completion.
+    #[error(transparent)]
+    Interrupted(Interrupted),
+
+    /// The solver hints of the project `elm.json` cannot be applied.
+    #[error("invalid solver hints in elm.json")]
+    Hints(#[source] HoldError),
+
+    /// Any other error of the dependency solver, such as having no solution.
+    #[error(transparent)]
+    PubGrub(Box<PubGrubError<Pkg, SemVer>>),
+}
+
+impl<E: Error + 'static> SolveError<E> {
+    /// Recover the typed errors of the provider functions, boxed by pubgrub.
+    fn from_pubgrub(err: PubGrubError<Pkg, SemVer>) -> Self {
+        match err {
+            PubGrubError::ErrorChoosingPackageVersion(source) => match source.downcast::<E>() {
+                Ok(source) => SolveError::ListVersions(*source),
+                Err(source) => {
+                    SolveError::PubGrub(Box::new(PubGrubError::ErrorChoosingPackageVersion(source)))
+                }
+            },
+            PubGrubError::ErrorRetrievingDependencies {
+                package,
+                version,
+                source,
+            } => match source.downcast::<E>() {
+                Ok(source) => SolveError::FetchElmJson {
+                    package,
+                    version,
+                    source: *source,
+                },
+                Err(source) => {
+                    SolveError::PubGrub(Box::new(PubGrubError::ErrorRetrievingDependencies {
+                        package,
+                        version,
+                        source,
+                    }))
+                }
+            },
+            PubGrubError::ErrorInShouldCancel(source) => match source.downcast::<Interrupted>() {
+                Ok(interrupted) => SolveError::Interrupted(*interrupted),
+                Err(source) => {
+                    SolveError::PubGrub(Box::new(PubGrubError::ErrorInShouldCancel(source)))
+                }
+            },
+            err => SolveError::PubGrub(Box::new(err)),
+        }
+    }
+}
+
+impl<E: Error + 'static> From<SolveError<E>> for PubGrubError<Pkg, SemVer> {
+    fn from(err: SolveError<E>) -> Self {
+        match err {
+            SolveError::ListVersions(source) => {
+                PubGrubError::ErrorChoosingPackageVersion(Box::new(source))
             }
-            // TODO: take somehow into account already picked versions for indirect deps?
-            solve_helper(&Pkg::new("root", ""), SemVer::zero(), direct_deps, solver)
-        }
-        ProjectConfig::Package(pkg_config) => {
-            let normal_deps = pkg_config.dependencies.iter();
-            let test_deps = pkg_config.test_dependencies.iter();
-            // Merge normal and test dependencies if solving with "use_test".
-            let mut deps: Map<Pkg, Range<SemVer>> = if use_test {
-                normal_deps
-                    .chain(test_deps)
-                    .map(|(p, c)| (p.clone(), c.0.clone()))
-                    .collect()
-            } else {
-                normal_deps.map(|(p, c)| (p.clone(), c.0.clone())).collect()
-            };
-            // Include the additional constraints.
-            for (p, r) in additional_constraints {
-                let dep_range = deps.entry(p.clone()).or_insert_with(Range::any);
-                *dep_range = dep_range.intersection(&r.0);
+            SolveError::FetchElmJson {
+                package,
+                version,
+                source,
+            } => PubGrubError::ErrorRetrievingDependencies {
+                package,
+                version,
+                source: Box::new(source),
+            },
+            SolveError::Interrupted(interrupted) => {
+                PubGrubError::ErrorInShouldCancel(Box::new(interrupted))
+            }
+            SolveError::Hints(err) => PubGrubError::Failure(err.to_string()),
+            SolveError::PubGrub(err) => *err,
+        }
+    }
+}
+
+/// The root of a dependency resolution, with its direct dependencies.
+///
+/// For an application, the root is a fake `root/` package
+/// and direct dependencies are pinned to the versions of its `elm.json`,
+/// exactly unless relaxed with a [`RootPinning`].
+#[derive(Debug, Clone)]
+pub struct RootDependencies {
+    /// The root package identifier.
+    pub pkg: Pkg,
+    /// The root package version.
+    pub version: SemVer,
+    /// The direct dependencies of the root.
+    pub dependencies: Map<Pkg, Range<SemVer>>,
+}
+
+impl RootDependencies {
+    /// Compute the root direct dependencies of a project,
+    /// with the same rules than [`solve_deps_with`].
+    pub fn new(
+        project_elm_json: &ProjectConfig,
+        use_test: bool,
+        additional_constraints: &[(Pkg, Constraint)],
+        extra_mode: ExtraMode,
+    ) -> Result<Self, PubGrubError<Pkg, SemVer>> {
+        Self::with_root_pinning(
+            project_elm_json,
+            use_test,
+            additional_constraints,
+            extra_mode,
+            RootPinning::Exact,
+        )
+    }
+
+    /// Compute the root direct dependencies of a project,
+    /// with the same rules than [`solve_deps_with_root_pinning`].
+    pub fn with_root_pinning(
+        project_elm_json: &ProjectConfig,
+        use_test: bool,
+        additional_constraints: &[(Pkg, Constraint)],
+        extra_mode: ExtraMode,
+        root_pinning: RootPinning,
+    ) -> Result<Self, PubGrubError<Pkg, SemVer>> {
+        match project_elm_json {
+            ProjectConfig::Application(app_config) => {
+                let normal_deps = app_config.dependencies.direct.iter();
+                let test_deps = app_config.test_dependencies.direct.iter();
+                // Merge normal and test dependencies if solving with "use_test".
+                let mut direct_deps: Map<Pkg, Range<SemVer>> = if use_test {
+                    normal_deps
+                        .chain(test_deps)
+                        .map(|(p, v)| (p.clone(), root_pinning.range(*v)))
+                        .collect()
+                } else {
+                    normal_deps
+                        .map(|(p, v)| (p.clone(), root_pinning.range(*v)))
+                        .collect()
+                };
+                // Include the additional constraints.
+                extra_mode.include(&mut direct_deps, additional_constraints)?;
+                // Indirect dependencies are not constraints, see IndirectPins.
+                Ok(Self {
+                    pkg: Pkg::new("root", ""),
+                    version: SemVer::zero(),
+                    dependencies: direct_deps,
+                })
+            }
+            ProjectConfig::Package(pkg_config) => {
+                let normal_deps = pkg_config.dependencies.iter();
+                let test_deps = pkg_config.test_dependencies.iter();
+                // Merge normal and test dependencies if solving with "use_test".
+                let mut deps: Map<Pkg, Range<SemVer>> = if use_test {
+                    normal_deps
+                        .chain(test_deps)
+                        .map(|(p, c)| (p.clone(), c.0.clone()))
+                        .collect()
+                } else {
+                    normal_deps.map(|(p, c)| (p.clone(), c.0.clone())).collect()
+                };
+                // Include the additional constraints.
+                extra_mode.include(&mut deps, additional_constraints)?;
+                Ok(Self {
+                    pkg: pkg_config.name.clone(),
+                    version: pkg_config.version,
+                    dependencies: deps,
+                })
+            }
+        }
+    }
+}
+
+/// How additional constraints are combined with the project declared dependencies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExtraMode {
+    /// Intersect the additional constraint with the declared range, if any.
+    /// Beware that this may result in an empty range.
+    #[default]
+    Tighten,
+    /// Replace the declared range, if any, by the additional constraint.
+    Override,
+    /// Only add new packages, and fail if the package already is a direct dependency.
+    Add,
+}
+
+impl ExtraMode {
+    /// Include the additional constraints into the direct dependencies, according to the mode.
+    fn include(
+        self,
+        deps: &mut Map<Pkg, Range<SemVer>>,
+        additional_constraints: &[(Pkg, Constraint)],
+    ) -> Result<(), PubGrubError<Pkg, SemVer>> {
+        for (p, c) in additional_constraints {
+            match self {
+                Self::Tighten => {
+                    let dep_range = deps.entry(p.clone()).or_insert_with(Range::any);
+                    *dep_range = dep_range.intersection(&c.0);
+                }
+                Self::Override => {
+                    deps.insert(p.clone(), c.0.clone());
+                }
+                Self::Add if deps.contains_key(p) => {
+                    return Err(PubGrubError::Failure(format!(
+                        "{} is already a direct dependency of the project",
+                        p
+                    )));
+                }
+                Self::Add => {
+                    deps.insert(p.clone(), c.0.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How strictly the direct dependencies of an application are pinned
+/// to the versions recorded in its `elm.json`.
+///
+/// Relaxing them lets the solver propose upgrades of direct dependencies.
+/// It has no effect on packages, whose dependencies already are ranges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RootPinning {
+    /// Keep the exact recorded versions.
+    #[default]
+    Exact,
+    /// Allow the recorded versions and newer ones with the same major version.
+    Major,
+    /// Allow any version.
+    Any,
+}
+
+/// Error parsing a [`RootPinning`], which must be either `exact`, `major` or `any`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown root pinning `{0}`, expected `exact`, `major` or `any`")]
+pub struct RootPinningParseError(pub String);
+
+impl RootPinning {
+    /// Range of versions allowed for a direct dependency recorded at the given version.
+    ///
+    /// ```
+    /// # use elm_solve_deps::solver::RootPinning;
+    /// # use elm_solve_deps::range::Range;
+    /// assert_eq!(RootPinning::Exact.range((1, 2, 3).into()), Range::exact((1, 2, 3)));
+    /// assert_eq!(RootPinning::Major.range((1, 2, 3).into()), Range::between((1, 2, 3), (2, 0, 0)));
+    /// assert_eq!(RootPinning::Any.range((1, 2, 3).into()), Range::any());
+    /// ```
+    pub fn range(self, version: SemVer) -> Range<SemVer> {
+        match self {
+            Self::Exact => Range::exact(version),
+            Self::Major => {
+                let (major, _, _): (u32, u32, u32) = version.into();
+                Range::between(version, SemVer::new(major + 1, 0, 0))
+            }
+            Self::Any => Range::any(),
+        }
+    }
+}
+
+impl FromStr for RootPinning {
+    type Err = RootPinningParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(Self::Exact),
+            "major" => Ok(Self::Major),
+            "any" => Ok(Self::Any),
+            _ => Err(RootPinningParseError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for RootPinning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exact => write!(f, "exact"),
+            Self::Major => write!(f, "major"),
+            Self::Any => write!(f, "any"),
+        }
+    }
+}
+
+/// How the solver breaks ties between packages with the same number of candidate versions.
+///
+/// At each step, the solver decides the version of the package with the fewest versions
+/// compatible with the current constraints.
+/// When several packages have that same number of versions,
+/// the package picked is the smallest one for [`TieBreak::compare`].
+/// This is part of the API contract: for the same project, registry and tie-breaking rule,
+/// the solver takes the same decisions in the same order on every platform,
+/// independently of the iteration order of the internal pubgrub structures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TieBreak {
+    /// Pick the package whose identifier comes first in alphabetical order.
+    #[default]
+    Alphabetical,
+    /// Pick the package whose identifier comes last in alphabetical order.
+    ReverseAlphabetical,
+}
+
+/// Error parsing a [`TieBreak`], which must be either `alphabetical` or `reverse-alphabetical`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown tie-breaking rule `{0}`, expected `alphabetical` or `reverse-alphabetical`")]
+pub struct TieBreakParseError(pub String);
+
+impl TieBreak {
+    /// Order of two packages with the same number of candidate versions.
+    /// The solver decides the smaller one first.
+    ///
+    /// ```
+    /// # use elm_solve_deps::project_config::Pkg;
+    /// # use elm_solve_deps::solver::TieBreak;
+    /// # use std::cmp::Ordering;
+    /// let core = Pkg::new("elm", "core");
+    /// let json = Pkg::new("elm", "json");
+    /// assert_eq!(TieBreak::Alphabetical.compare(&core, &json), Ordering::Less);
+    /// assert_eq!(TieBreak::ReverseAlphabetical.compare(&core, &json), Ordering::Greater);
+    /// ```
+    pub fn compare(self, a: &Pkg, b: &Pkg) -> std::cmp::Ordering {
+        match self {
+            Self::Alphabetical => a.cmp(b),
+            Self::ReverseAlphabetical => b.cmp(a),
+        }
+    }
+}
+
+impl FromStr for TieBreak {
+    type Err = TieBreakParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "alphabetical" => Ok(Self::Alphabetical),
+            "reverse-alphabetical" => Ok(Self::ReverseAlphabetical),
+            _ => Err(TieBreakParseError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for TieBreak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Alphabetical => write!(f, "alphabetical"),
+            Self::ReverseAlphabetical => write!(f, "reverse-alphabetical"),
+        }
+    }
+}
+
+/// How the indirect dependencies recorded in the `elm.json` of an application
+/// are taken into account.
+///
+/// Only the direct dependencies of an application are constraints of the resolution.
+/// Its indirect dependencies can still be tried first, such that solving again
+/// keeps them whenever possible, and regenerated `elm.json` files change less.
+/// They are soft preferences, other versions are picked if the recorded ones conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IndirectPins {
+    /// Try the recorded versions of indirect dependencies first.
+    #[default]
+    Prefer,
+    /// Ignore the recorded versions of indirect dependencies.
+    Ignore,
+}
+
+/// Error parsing an [`IndirectPins`], which must be either `prefer` or `ignore`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown indirect pins `{0}`, expected `prefer` or `ignore`")]
+pub struct IndirectPinsParseError(pub String);
+
+impl IndirectPins {
+    /// Versions tried first, the recorded indirect dependencies and indirect test dependencies
+    /// of an application, or none for a package.
+    pub fn preferred_versions(self, project_elm_json: &ProjectConfig) -> BTreeMap<Pkg, SemVer> {
+        match (self, project_elm_json) {
+            (Self::Prefer, ProjectConfig::Application(app_config)) => {
+                let normal = app_config.dependencies.indirect.iter();
+                let test = app_config.test_dependencies.indirect.iter();
+                normal.chain(test).map(|(p, v)| (p.clone(), *v)).collect()
             }
-            solve_helper(&pkg_config.name, pkg_config.version, deps, solver)
+            _ => BTreeMap::new(),
+        }
+    }
+}
+
+impl FromStr for IndirectPins {
+    type Err = IndirectPinsParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "prefer" => Ok(Self::Prefer),
+            "ignore" => Ok(Self::Ignore),
+            _ => Err(IndirectPinsParseError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for IndirectPins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Prefer => write!(f, "prefer"),
+            Self::Ignore => write!(f, "ignore"),
+        }
+    }
+}
+
+/// Move the preferred version of a package, if any, in front of the other versions.
+#[cfg(feature = "fs")]
+fn prefer(preferred_versions: &BTreeMap<Pkg, SemVer>, pkg: &Pkg, versions: &mut [SemVer]) {
+    if let Some(preferred) = preferred_versions.get(pkg) {
+        if let Some(i) = versions.iter().position(|v| v == preferred) {
+            versions[..=i].rotate_right(1);
         }
     }
 }
@@ -115,20 +721,24 @@ where
 /// with the given root package version.
 ///
 /// TODO: handle error case.
-fn solve_helper<Fetch, L, Versions>(
+fn solve_helper<Fetch, L, Versions, E>(
     root_pkg: &Pkg,
     root_version: SemVer,
     direct_deps: Map<Pkg, Range<SemVer>>,
-    solver: Solver<Fetch, L, Versions>,
+    solver: &Solver<Fetch, L, Versions, E>,
 ) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>>
 where
-    Fetch: Fn(&Pkg, SemVer) -> Result<PackageConfig, Box<dyn Error>>,
-    L: Fn(&Pkg) -> Result<Versions, Box<dyn Error>>,
+    Fetch: Fn(&Pkg, SemVer) -> Result<Option<PackageConfig>, E>,
+    L: Fn(&Pkg) -> Result<Versions, E>,
     Versions: Iterator<Item = SemVer>,
+    E: Error + 'static,
 {
+    // Direct dependencies are undetermined until decided.
+    solver.partial.borrow_mut().require(direct_deps.keys());
+
     // Transform the generic dependency solver into one that is specific for the current project.
     let project_deps_provider =
-        ProjectAdapter::new(root_pkg.clone(), root_version, &direct_deps, &solver);
+        ProjectAdapter::new(root_pkg.clone(), root_version, &direct_deps, solver);
 
     // Solve dependencies and remove the root dependency from the solution.
     let mut solution =
@@ -145,49 +755,120 @@ where
 #[derive(Debug, Clone)]
 /// A type that implements the `DependencyProvider` trait
 /// to be able to solve dependencies with pubgrub.
-struct Solver<Fetch, L, Versions>
+struct Solver<'a, Fetch, L, Versions, E>
 where
-    Fetch: Fn(&Pkg, SemVer) -> Result<PackageConfig, Box<dyn Error>>,
-    L: Fn(&Pkg) -> Result<Versions, Box<dyn Error>>,
+    Fetch: Fn(&Pkg, SemVer) -> Result<Option<PackageConfig>, E>,
+    L: Fn(&Pkg) -> Result<Versions, E>,
     Versions: Iterator<Item = SemVer>,
+    E: Error + 'static,
 {
     fetch_elm_json: Fetch,
     list_available_versions: L,
+    limits: Limits,
+    tie_break: TieBreak,
+    partial: RefCell<PartialSolution>,
+    /// Dependencies of the package versions whose config was loaded,
+    /// which pubgrub decides again without asking for their dependencies.
+    loaded: RefCell<Map<(Pkg, SemVer), Vec<Pkg>>>,
+    progress: Option<&'a Progress>,
+    error: PhantomData<E>,
+}
+
+impl<Fetch, L, Versions, E> Solver<'_, Fetch, L, Versions, E>
+where
+    Fetch: Fn(&Pkg, SemVer) -> Result<Option<PackageConfig>, E>,
+    L: Fn(&Pkg) -> Result<Versions, E>,
+    Versions: Iterator<Item = SemVer>,
+    E: Error + 'static,
+{
+    /// Record the decision of a package version with the given dependencies, and report it.
+    fn decide(&self, pkg: &Pkg, version: SemVer, dependencies: &[Pkg]) {
+        let mut partial = self.partial.borrow_mut();
+        partial.decide(pkg, version);
+        partial.require(dependencies.iter());
+        if let Some(progress) = self.progress {
+            progress.emit(ProgressEvent::Decided {
+                pkg: pkg.clone(),
+                version,
+                decisions: partial.decisions,
+                undetermined: partial.undetermined.len(),
+            });
+        }
+    }
 }
 
-impl<Fetch, L, Versions> DependencyProvider<Pkg, SemVer> for Solver<Fetch, L, Versions>
+impl<Fetch, L, Versions, E> DependencyProvider<Pkg, SemVer> for Solver<'_, Fetch, L, Versions, E>
 where
-    Fetch: Fn(&Pkg, SemVer) -> Result<PackageConfig, Box<dyn Error>>,
-    L: Fn(&Pkg) -> Result<Versions, Box<dyn Error>>,
+    Fetch: Fn(&Pkg, SemVer) -> Result<Option<PackageConfig>, E>,
+    L: Fn(&Pkg) -> Result<Versions, E>,
     Versions: Iterator<Item = SemVer>,
+    E: Error + 'static,
 {
-    /// Use `self.list_available_versions` and pick the package with the fewest versions.
+    /// Use `self.list_available_versions` and pick the package with the fewest versions,
+    /// breaking ties with `self.tie_break` such that the choice does not depend
+    /// on the order of `potential_packages`.
+    ///
+    /// This is where pubgrub decides package versions, again after backtracking.
+    /// A version whose config was already loaded is decided right away,
+    /// otherwise it is decided once its dependencies are known, in `get_dependencies`.
     fn choose_package_version<T: Borrow<Pkg>, U: Borrow<Range<SemVer>>>(
         &self,
         potential_packages: impl Iterator<Item = (T, U)>,
     ) -> Result<(T, Option<SemVer>), Box<dyn Error>> {
         let count_valid = |(p, range): &(T, U)| match (self.list_available_versions)(p.borrow()) {
-            Ok(versions) => versions
-                .filter(|v| range.borrow().contains(v.borrow()))
-                .count(),
+            Ok(versions) => versions.filter(|v| range.borrow().contains(v)).count(),
             Err(_) => 0,
         };
-        let (pkg, range) = potential_packages
-            .min_by_key(count_valid)
+        let (candidates, (pkg, range)) = potential_packages
+            .map(|candidate| (count_valid(&candidate), candidate))
+            .min_by(|(count_a, (a, _)), (count_b, (b, _))| {
+                (count_a.cmp(count_b)).then_with(|| self.tie_break.compare(a.borrow(), b.borrow()))
+            })
             .expect("potential_packages gave us an empty iterator");
-        let version = (self.list_available_versions)(pkg.borrow())?
-            .find(|v| range.borrow().contains(v.borrow()));
+        let version =
+            (self.list_available_versions)(pkg.borrow())?.find(|v| range.borrow().contains(v));
+        if let Some(progress) = self.progress {
+            progress.emit(ProgressEvent::Chose {
+                pkg: pkg.borrow().clone(),
+                version,
+                candidates,
+            });
+        }
+        if let Some(version) = version {
+            let key = (pkg.borrow().clone(), version);
+            if let Some(dependencies) = self.loaded.borrow().get(&key) {
+                self.decide(pkg.borrow(), version, dependencies);
+            }
+        }
         Ok((pkg, version))
     }
 
-    /// Load the dependencies from the elm.json retrieved with `self.fetch_elm_json`.
+    /// Load the dependencies from the elm.json retrieved with `self.fetch_elm_json`,
+    /// and decide that package version.
+    ///
+    /// pubgrub only asks once for the dependencies of a package version,
+    /// the first time it decides it.
     fn get_dependencies(
         &self,
         package: &Pkg,
         version: &SemVer,
     ) -> Result<Dependencies<Pkg, SemVer>, Box<dyn Error>> {
-        // TODO: handle the unknown case (change fetch_elm_json signature)
-        let pkg_config = (self.fetch_elm_json)(package, *version)?;
+        let pkg_config = match (self.fetch_elm_json)(package, *version)? {
+            Some(pkg_config) => pkg_config,
+            // Let pubgrub consider this version unavailable and backtrack.
+            None => {
+                if let Some(progress) = self.progress {
+                    progress.emit(ProgressEvent::Unavailable {
+                        pkg: package.clone(),
+                        version: *version,
+                    });
+                }
+                return Ok(Dependencies::Unknown);
+            }
+        };
+        let dependencies: Vec<Pkg> = pkg_config.dependencies.keys().cloned().collect();
+        self.decide(package, *version, &dependencies);
+        (self.loaded.borrow_mut()).insert((package.clone(), *version), dependencies);
         Ok(Dependencies::Known(
             pkg_config
                 .dependencies
@@ -196,6 +877,18 @@ where
                 .collect(),
         ))
     }
+
+    /// Interrupt the resolution when exceeding the limits.
+    fn should_cancel(&self) -> Result<(), Box<dyn Error>> {
+        let partial = self.partial.borrow();
+        self.limits.check(partial.decisions).map_err(|reason| {
+            let interrupted = Interrupted {
+                reason,
+                partial: partial.clone(),
+            };
+            Box::new(interrupted) as Box<dyn Error>
+        })
+    }
 }
 
 // #############################################################################
@@ -214,7 +907,7 @@ where
 /// You can use it as follows.
 ///
 /// ```no_run
-/// # use elm_solve_deps::solver;
+/// # use elm_solve_deps::solver::{self, ExtraMode};
 /// # let elm_home = || "";
 /// // Define an offline solver.
 /// let offline_solver = solver::Offline::new(elm_home(), "0.19.1");
@@ -233,20 +926,34 @@ where
 ///
 /// // Solve dependencies.
 /// let solution = offline_solver
-///     .solve_deps(&project_elm_json, use_test, extras)
+///     .solve_deps(&project_elm_json, use_test, extras, ExtraMode::default())
 ///     .expect("Dependency solving failed");
 /// ```
 ///
 /// Note that it is possible to provide additional package constraints,
 /// which is convenient for tooling when requiring additional packages that are not recorded
 /// directly in the original `elm.json` file.
+#[cfg(feature = "fs")]
 #[derive(Debug, Clone)]
 pub struct Offline {
     elm_home: PathBuf,
-    elm_version: String,
+    installed: Arc<dyn InstalledPackages + Send + Sync>,
     versions_cache: RefCell<Cache>,
+    policies: Policies,
+    diagnostics: Diagnostics,
+    provenance: Provenance,
+    limits: Limits,
+    recorder: Option<Recorder>,
+    progress: Option<Progress>,
+    preferred_versions: BTreeMap<Pkg, SemVer>,
+    root_pinning: RootPinning,
+    tie_break: TieBreak,
+    indirect_pins: IndirectPins,
+    overrides: BTreeMap<Pkg, (PackageConfig, String)>,
+    clock: Arc<dyn Clock + Send + Sync>,
 }
 
+#[cfg(feature = "fs")]
 impl Offline {
     /// Constructor for the offline solver.
     ///
@@ -254,11 +961,213 @@ impl Offline {
     /// The `elm_version` argument should be "0.19.1"
     /// as it is currently the only version supported.
     pub fn new<PB: Into<PathBuf>, S: ToString>(elm_home: PB, elm_version: S) -> Self {
+        let elm_home = elm_home.into();
         Offline {
-            elm_home: elm_home.into(),
-            elm_version: elm_version.to_string(),
+            installed: Arc::new(Directory::new(elm_home.clone(), elm_version)),
+            elm_home,
             versions_cache: RefCell::new(Cache::new()),
+            policies: Policies::new(),
+            diagnostics: Diagnostics::new(),
+            provenance: Provenance::new(),
+            limits: Limits::default(),
+            recorder: None,
+            progress: None,
+            preferred_versions: BTreeMap::new(),
+            root_pinning: RootPinning::default(),
+            tie_break: TieBreak::default(),
+            indirect_pins: IndirectPins::default(),
+            overrides: BTreeMap::new(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Get the current time from this clock instead of the system one,
+    /// in this solver and in online solvers built on top of it,
+    /// for example a [`FixedClock`](crate::clock::FixedClock) at `SOURCE_DATE_EPOCH`
+    /// with [`clock::from_env`](crate::clock::from_env).
+    /// It decides when cached package lists and missing versions are stale,
+    /// and which versions are old enough for [`Online::with_min_release_age`].
+    pub fn with_clock<C: Clock + Send + Sync + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// The clock of this solver, see [`Offline::with_clock`].
+    pub fn clock(&self) -> &(dyn Clock + Send + Sync) {
+        &*self.clock
+    }
+
+    /// Change where installed packages are read from.
+    ///
+    /// By default, they are read from the `ELM_HOME` directory,
+    /// but they can also be read from an [`Archive`](crate::elm_home::Archive) of it.
+    /// The `ELM_HOME` directory is still used for the dependency solver cache.
+    ///
+    /// ```no_run
+    /// # use elm_solve_deps::{elm_home::Archive, solver::Offline};
+    /// let archive = Archive::open("elm-home.tar", "0.19.1").expect("Failed to open archive");
+    /// let offline_solver = Offline::new("/home/user/.elm", "0.19.1").with_installed_packages(archive);
+    /// ```
+    pub fn with_installed_packages<I>(mut self, installed: I) -> Self
+    where
+        I: InstalledPackages + Send + Sync + 'static,
+    {
+        self.installed = Arc::new(installed);
+        self.versions_cache = RefCell::new(Cache::new());
+        self
+    }
+
+    /// Read installed packages from the packages directories of several versions of elm,
+    /// in order of preference, instead of a single one, see [`Directories`].
+    ///
+    /// ```no_run
+    /// # use elm_solve_deps::solver::Offline;
+    /// let offline_solver =
+    ///     Offline::new("/home/user/.elm", "0.19.1").with_elm_versions(&["0.19.1", "0.19.0"]);
+    /// ```
+    pub fn with_elm_versions<S: ToString>(self, elm_versions: &[S]) -> Self {
+        let directories = Directories::new(self.elm_home.clone(), elm_versions);
+        self.with_installed_packages(directories)
+    }
+
+    /// Override packages with unreleased versions, from git repositories or local paths,
+    /// in the resolutions of this solver, and of online solvers built on top of it.
+    ///
+    /// The `elm.json` of each override is loaded right away, shallow-fetching git repositories
+    /// into `ELM_HOME`, and the version it declares becomes the only candidate of its package,
+    /// regardless of installed versions and policies. See [`PackageOverride`].
+    ///
+    /// ```no_run
+    /// # use elm_solve_deps::{overrides::PackageOverride, solver::Offline};
+    /// # use std::str::FromStr;
+    /// let fix = PackageOverride::from_str("elm/parser=git+https://github.com/elm/parser#master")
+    ///     .expect("Invalid override");
+    /// let offline_solver = Offline::new("/home/user/.elm", "0.19.1")
+    ///     .with_overrides(&[fix])
+    ///     .expect("Failed to load the override");
+    /// ```
+    pub fn with_overrides(mut self, overrides: &[PackageOverride]) -> Result<Self, OverrideError> {
+        for package_override in overrides {
+            let config = package_override.load(&self.elm_home)?;
+            let origin = package_override.source.to_string();
+            (self.overrides).insert(package_override.pkg.clone(), (config, origin));
         }
+        Ok(self)
+    }
+
+    /// Apply version policies to all dependency resolutions of this solver.
+    ///
+    /// Contrary to additional constraints, policies do not add dependencies,
+    /// they only remove versions from the candidates of the packages they concern.
+    ///
+    /// ```no_run
+    /// # use elm_solve_deps::{policy::Policies, solver::Offline};
+    /// let policies = Policies::load("policies.json").expect("Failed to load policies");
+    /// let offline_solver = Offline::new("/home/user/.elm", "0.19.1").with_policies(policies);
+    /// ```
+    pub fn with_policies(mut self, policies: Policies) -> Self {
+        self.policies = policies;
+        self
+    }
+
+    /// List all installed versions of all packages at once,
+    /// instead of lazily listing them package by package during resolution.
+    ///
+    /// This is faster on slow filesystems when many packages are involved.
+    #[deprecated(note = "use `Offline::scan_all`, which does not mutate a shared solver")]
+    pub fn prime_versions_cache(&self) -> std::io::Result<()> {
+        self.list_all_installed()
+    }
+
+    /// Version policies applied by this solver.
+    pub fn policies(&self) -> &Policies {
+        &self.policies
+    }
+
+    /// Interrupt resolutions exceeding the given limits.
+    ///
+    /// Interrupted resolutions fail with a [`PubGrubError::ErrorInShouldCancel`] error,
+    /// whose source is an [`Interrupted`] error containing the partial solution.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Limits of the resolutions of this solver.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Record the package configs and versions lists consulted by this solver,
+    /// and by online solvers built on top of it, to build reproducible test cases.
+    ///
+    /// ```no_run
+    /// # use elm_solve_deps::{corpus::Recorder, solver::{ExtraMode, Offline}};
+    /// # let project_elm_json = serde_json::from_str("").unwrap();
+    /// let recorder = Recorder::new();
+    /// let offline_solver = Offline::new("/home/user/.elm", "0.19.1").with_recorder(recorder.clone());
+    /// let solution = offline_solver.solve_deps(&project_elm_json, false, &[], ExtraMode::default());
+    /// recorder
+    ///     .test_case(&project_elm_json, false, &[], ExtraMode::default(), solution.as_ref().ok())
+    ///     .save("test-case")
+    ///     .expect("Failed to save the test case");
+    /// ```
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Report the progress of the resolutions of this solver,
+    /// and of online solvers built on top of it, including their updates of the versions cache.
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Try the given versions first when picking versions of their packages,
+    /// before following the usual order of priority.
+    ///
+    /// Contrary to policies, other versions are still allowed if the preferred ones conflict.
+    /// This is convenient to keep versions already recorded in an `elm.json` whenever possible.
+    /// They take precedence over the indirect dependencies tried first with [`IndirectPins`].
+    pub fn with_preferred_versions(mut self, preferred_versions: BTreeMap<Pkg, SemVer>) -> Self {
+        self.preferred_versions = preferred_versions;
+        self
+    }
+
+    /// Relax the versions of the direct dependencies of applications
+    /// in the resolutions of this solver, and of online solvers built on top of it,
+    /// to let them propose upgrades. See [`RootPinning`].
+    pub fn with_root_pinning(mut self, root_pinning: RootPinning) -> Self {
+        self.root_pinning = root_pinning;
+        self
+    }
+
+    /// Change how ties between packages with the same number of candidate versions are broken
+    /// in the resolutions of this solver, and of online solvers built on top of it.
+    /// See [`TieBreak`].
+    pub fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Change how the indirect dependencies recorded in the `elm.json` of applications are used
+    /// in the resolutions of this solver, and of online solvers built on top of it.
+    /// See [`IndirectPins`].
+    pub fn with_indirect_pins(mut self, indirect_pins: IndirectPins) -> Self {
+        self.indirect_pins = indirect_pins;
+        self
+    }
+
+    /// Diagnostics recorded by this solver, and by online solvers built on top of it.
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    /// Sources of the package configs loaded by this solver,
+    /// and by online solvers built on top of it.
+    pub fn provenance(&self) -> &Provenance {
+        &self.provenance
     }
 
     /// Run the dependency solver on a given project config, obtained from an `elm.json`.
@@ -272,47 +1181,263 @@ impl Offline {
     /// ```
     /// # use elm_solve_deps::project_config::Pkg;
     /// # use elm_solve_deps::constraint::Constraint;
-    /// # use pubgrub::range::Range;
+    /// # use elm_solve_deps::range::Range;
     /// let extra = &[(
     ///   Pkg::new("jfmengels", "elm-review"),
     ///   Constraint(Range::between( (2,6,1), (3,0,0) )),
     /// )];
     /// ```
+    ///
+    /// The `extra_mode` argument controls how these additional constraints combine
+    /// with the dependencies already declared in the project config.
+    ///
+    /// Installed versions of packages are listed lazily, the first time the solver needs them,
+    /// and kept in the internal cache of this solver, although it is borrowed immutably.
+    /// To separate the listing from the resolution, see [`Offline::solve_deps_scanned`].
     pub fn solve_deps(
         &self,
         project_elm_json: &ProjectConfig,
         use_test: bool,
         additional_constraints: &[(Pkg, Constraint)],
+        extra_mode: ExtraMode,
     ) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>> {
+        self.solve_deps_listing(
+            project_elm_json,
+            use_test,
+            additional_constraints,
+            extra_mode,
+            |pkg| self.load_installed_versions_of(pkg),
+        )
+    }
+
+    /// List the installed versions of the given packages into the internal cache of this solver,
+    /// to prepare resolutions with [`Offline::solve_deps_scanned`].
+    ///
+    /// Packages already scanned are listed again, to see versions installed since then.
+    pub fn scan<'a, I>(&mut self, packages: I) -> Result<(), PkgParseError>
+    where
+        I: IntoIterator<Item = &'a Pkg>,
+    {
+        let cache = &mut self.versions_cache.get_mut().cache;
+        for pkg in packages {
+            let versions = self.installed.list_installed_versions(pkg)?;
+            cache.insert(pkg.clone(), versions);
+        }
+        Ok(())
+    }
+
+    /// List all installed versions of all packages into the internal cache of this solver,
+    /// to prepare resolutions with [`Offline::solve_deps_scanned`].
+    pub fn scan_all(&mut self) -> std::io::Result<()> {
+        self.list_all_installed()
+    }
+
+    /// List all installed versions of all packages into the internal cache of this solver.
+    fn list_all_installed(&self) -> std::io::Result<()> {
+        let all_installed = self.installed.list_all_installed()?;
+        (self.versions_cache.borrow_mut().cache).extend(all_installed.cache);
+        Ok(())
+    }
+
+    /// Run the dependency solver with the installed versions scanned beforehand
+    /// with [`Offline::scan`] or [`Offline::scan_all`], and nothing else.
+    ///
+    /// Contrary to [`Offline::solve_deps`], the resolution never lists `ELM_HOME`
+    /// and never changes the internal cache of this solver.
+    /// Packages that were not scanned are considered not installed.
+    ///
+    /// ```no_run
+    /// # use elm_solve_deps::solver::{ExtraMode, Offline};
+    /// # let project_elm_json = serde_json::from_str("").unwrap();
+    /// let mut offline_solver = Offline::new("/home/user/.elm", "0.19.1");
+    /// // Mutable phase, listing ELM_HOME.
+    /// offline_solver.scan_all().expect("Failed to scan ELM_HOME");
+    /// // Read-only phase, shareable between resolutions.
+    /// let solution =
+    ///     offline_solver.solve_deps_scanned(&project_elm_json, false, &[], ExtraMode::default());
+    /// ```
+    pub fn solve_deps_scanned(
+        &self,
+        project_elm_json: &ProjectConfig,
+        use_test: bool,
+        additional_constraints: &[(Pkg, Constraint)],
+        extra_mode: ExtraMode,
+    ) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>> {
+        self.solve_deps_listing(
+            project_elm_json,
+            use_test,
+            additional_constraints,
+            extra_mode,
+            |pkg| {
+                let versions_cache = self.versions_cache.borrow();
+                let versions = versions_cache.cache.get(pkg).into_iter().flatten();
+                Ok(versions.rev().cloned().collect())
+            },
+        )
+    }
+
+    /// Run the dependency solver, listing installed versions with `list_installed`,
+    /// newest first.
+    fn solve_deps_listing<L>(
+        &self,
+        project_elm_json: &ProjectConfig,
+        use_test: bool,
+        additional_constraints: &[(Pkg, Constraint)],
+        extra_mode: ExtraMode,
+        list_installed: L,
+    ) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>>
+    where
+        L: Fn(&Pkg) -> Result<Vec<SemVer>, PkgParseError>,
+    {
+        let preferred_versions = self.preferences(project_elm_json);
         let list_available_versions = |pkg: &Pkg| {
-            self.load_installed_versions_of(pkg)
-                .map(|vs| vs.into_iter())
-                .map_err(|err| err.into())
+            let versions = self.lookup_versions(pkg, &list_installed)?;
+            let mut versions = self.apply_policies(pkg, versions.into_iter());
+            prefer(&preferred_versions, pkg, &mut versions);
+            self.record_versions(pkg, &versions);
+            Ok::<_, PkgVersionError>(versions.into_iter())
         };
         let fetch_elm_json = |pkg: &Pkg, version| {
             let pkg_version = PkgVersion {
                 author_pkg: pkg.clone(),
                 version,
             };
-            pkg_version
-                .load_config(&self.elm_home, &self.elm_version)
-                .map_err(|err| err.into())
+            let (config, source) = self.lookup_config(&pkg_version)?;
+            Ok(Some(self.record_lookup(&pkg_version, config, source)))
         };
-        solve_deps_with(
+        solve_deps_configured(
             project_elm_json,
             use_test,
             additional_constraints,
+            extra_mode,
+            self.root_pinning,
+            self.tie_break,
+            &self.limits,
+            self.progress.as_ref(),
             fetch_elm_json,
             list_available_versions,
         )
+        .map_err(|err| err.into())
+    }
+
+    /// Versions tried first when solving the dependencies of a project,
+    /// the preferred versions of this solver,
+    /// and otherwise the recorded indirect dependencies of an application with [`IndirectPins`].
+    fn preferences(&self, project_elm_json: &ProjectConfig) -> BTreeMap<Pkg, SemVer> {
+        let mut preferred_versions = self.indirect_pins.preferred_versions(project_elm_json);
+        preferred_versions.extend(self.preferred_versions.clone());
+        preferred_versions
+    }
+
+    /// Version of an overridden package, its only candidate.
+    fn overridden_version(&self, pkg: &Pkg) -> Option<SemVer> {
+        self.overrides.get(pkg).map(|(config, _)| config.version)
+    }
+
+    /// Keep the versions of a package allowed by the policies of this solver,
+    /// unless it is overridden, in which case its only version is always kept.
+    fn apply_policies<I>(&self, pkg: &Pkg, versions: I) -> Vec<SemVer>
+    where
+        I: Iterator<Item = SemVer>,
+    {
+        if self.overrides.contains_key(pkg) {
+            return versions.collect();
+        }
+        self.policies.filter_versions(pkg, versions).collect()
+    }
+
+    /// Versions of a package, newest first: the overridden one,
+    /// or the installed ones listed with `list_installed`.
+    ///
+    /// Resolutions and the [`RegistryView`] of this solver share this lookup,
+    /// resolutions then apply the policies and preferred versions of the solver.
+    fn lookup_versions<L>(
+        &self,
+        pkg: &Pkg,
+        list_installed: L,
+    ) -> Result<Vec<SemVer>, PkgVersionError>
+    where
+        L: Fn(&Pkg) -> Result<Vec<SemVer>, PkgParseError>,
+    {
+        if let Some(version) = self.overridden_version(pkg) {
+            return Ok(vec![version]);
+        }
+        list_installed(pkg).map_err(|err| PkgVersionError::from(PkgVersionParseError::from(err)))
+    }
+
+    /// Config of a package version, from its override or from the installed packages,
+    /// with where it was loaded from.
+    ///
+    /// Resolutions and the [`RegistryView`] of this solver share this lookup,
+    /// resolutions then record it with [`Offline::record_lookup`].
+    fn lookup_config(
+        &self,
+        pkg_version: &PkgVersion,
+    ) -> Result<(PackageConfig, Source), PkgVersionError> {
+        if let Some((config, origin)) = self.overrides.get(&pkg_version.author_pkg) {
+            if config.version == pkg_version.version {
+                let origin = origin.clone();
+                return Ok((config.clone(), Source::Override { origin }));
+            }
+        }
+        let config = self.installed.load_config(pkg_version)?;
+        Ok((config, Source::Installed))
+    }
+
+    /// Record the source of a config looked up during a resolution,
+    /// check its name and record it.
+    fn record_lookup(
+        &self,
+        pkg_version: &PkgVersion,
+        config: PackageConfig,
+        source: Source,
+    ) -> PackageConfig {
+        self.record_source(pkg_version, source);
+        let config = self.diagnostics.check_name(pkg_version, config);
+        self.record_config(config)
+    }
+
+    /// Record the versions listed for a package, if a recorder is attached.
+    fn record_versions(&self, pkg: &Pkg, versions: &[SemVer]) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record_versions(pkg, versions);
+        }
+    }
+
+    /// Record where the config of a package version was loaded from,
+    /// and report it if a progress handle is attached.
+    fn record_source(&self, pkg_version: &PkgVersion, source: Source) {
+        if source == Source::Installed {
+            if let Some(elm_version) = self.installed.elm_version_of(pkg_version) {
+                self.provenance
+                    .record_elm_version(pkg_version, &elm_version);
+            }
+        }
+        if let Some(progress) = &self.progress {
+            progress.emit(ProgressEvent::Loaded {
+                pkg: pkg_version.author_pkg.clone(),
+                version: pkg_version.version,
+                source: source.clone(),
+            });
+        }
+        self.provenance.record(pkg_version, source);
+    }
+
+    /// Record a consulted package config, if a recorder is attached.
+    fn record_config(&self, config: PackageConfig) -> PackageConfig {
+        if let Some(recorder) = &self.recorder {
+            recorder.record_config(&config);
+        }
+        config
     }
 
     /// Load existing versions already installed for the potential packages.
     ///
-    /// Self is mutated to update the cache but we are cheating with RefCell
-    /// to make it believe that it's not mutated.
-    /// This is to be able to use the dependency provider,
-    /// and I think it is OK as long as we don't make this function public?
+    /// The cache is updated through a `RefCell`, since the dependency provider
+    /// only has a shared reference to the solver.
+    /// The borrows never outlive this function, and the solver is not `Sync`,
+    /// so they cannot conflict, but resolutions with [`Offline::solve_deps_scanned`]
+    /// avoid this hidden mutation altogether.
     fn load_installed_versions_of(&self, pkg: &Pkg) -> Result<Vec<SemVer>, PkgParseError> {
         let versions_cache = self.versions_cache.borrow();
         match versions_cache.cache.get(pkg) {
@@ -320,8 +1445,7 @@ impl Offline {
             None => {
                 drop(versions_cache);
                 // Only load versions existing in elm home for packages we see for the first time.
-                let versions: BTreeSet<SemVer> =
-                    Cache::list_installed_versions(&self.elm_home, &self.elm_version, pkg)?;
+                let versions: BTreeSet<SemVer> = self.installed.list_installed_versions(pkg)?;
                 let sorted_versions = versions.iter().rev().cloned().collect();
                 let cache = &mut self.versions_cache.borrow_mut().cache;
                 cache.insert(pkg.clone(), versions);
@@ -331,48 +1455,130 @@ impl Offline {
     }
 }
 
+/// Installed packages, with the overrides of this solver,
+/// ignoring its policies and preferred versions.
+#[cfg(feature = "fs")]
+impl RegistryView for Offline {
+    type Error = PkgVersionError;
+
+    fn versions(&self, pkg: &Pkg) -> Result<BTreeSet<SemVer>, Self::Error> {
+        let versions = self.lookup_versions(pkg, |pkg| self.load_installed_versions_of(pkg))?;
+        Ok(versions.into_iter().collect())
+    }
+
+    fn config(&self, pkg: &Pkg, version: SemVer) -> Result<Option<PackageConfig>, Self::Error> {
+        if !self.exists(pkg, version)? {
+            return Ok(None);
+        }
+        let pkg_version = PkgVersion {
+            author_pkg: pkg.clone(),
+            version,
+        };
+        let (config, _) = self.lookup_config(&pkg_version)?;
+        Ok(Some(config))
+    }
+}
+
 // #############################################################################
 // ONLINE ######################################################################
 // #############################################################################
 
 /// Online variant of the dependency solver.
 ///
-/// When initialized, it starts by updating its database of known packages.
+/// When initialized, it starts by updating its database of known packages,
+/// either right away with [`Online::new`], or in the background with [`Online::new_in_background`].
 /// Then when solving dependencies, it works similarly than the [`Offline`] solver,
 /// but with a set of packages that is the union of those existing locally,
-/// and those existing on the package server.
+/// and those existing on the package server,
+/// or on a [`Federation`] of package servers.
+///
+/// Clones share the same database of known packages.
+#[cfg(feature = "fs")]
 #[derive(Debug, Clone)]
-pub struct Online<F: Fn(&str) -> Result<String, Box<dyn Error + Send + Sync>>> {
+pub struct Online<F: HttpFetch> {
     offline: Offline,
-    online_cache: Cache,
+    online_cache: Arc<RwLock<Cache>>,
+    refresh: Arc<Mutex<Option<Refresh>>>,
+    unavailable: RefCell<BTreeSet<(Pkg, SemVer)>>,
+    missing: Arc<Mutex<MissingCache>>,
     remote: String,
+    federation: Federation,
+    federated_cache: Arc<RwLock<FederatedCache>>,
+    fallbacks: Vec<ConfigFallback>,
+    min_release_age: Option<Duration>,
+    release_times: Arc<Mutex<ReleaseTimes>>,
     http_fetch: F,
     strategy: VersionStrategy,
+    #[cfg(feature = "sha2")]
+    shared_cache: Option<SharedCache>,
+    packed: Option<Arc<Mutex<PackedStore>>>,
 }
 
+/// Background update of the database of known packages,
+/// returning the package versions it discovered.
+#[cfg(feature = "fs")]
+type Refresh = JoinHandle<Result<Vec<PkgVersion>, CacheError>>;
+
 /// Strategy of an online solver, consisting of picking either the newest
-/// or oldest compatible versions.
-#[derive(Debug, Clone, Copy)]
+/// or oldest compatible versions, or those already installed first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum VersionStrategy {
     /// Choose the newest compatible versions.
     Newest,
     /// Choose the oldest compatible versions.
     Oldest,
+    /// Choose compatible versions already installed in `ELM_HOME` first, newest first,
+    /// and otherwise the newest compatible versions, to reduce downloads.
+    PreferInstalled,
+}
+
+/// Error parsing a [`VersionStrategy`],
+/// which must be either `newest`, `oldest` or `prefer-installed`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown version strategy `{0}`, expected `newest`, `oldest` or `prefer-installed`")]
+pub struct VersionStrategyParseError(pub String);
+
+impl FromStr for VersionStrategy {
+    type Err = VersionStrategyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "newest" => Ok(Self::Newest),
+            "oldest" => Ok(Self::Oldest),
+            "prefer-installed" => Ok(Self::PreferInstalled),
+            _ => Err(VersionStrategyParseError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for VersionStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Newest => write!(f, "newest"),
+            Self::Oldest => write!(f, "oldest"),
+            Self::PreferInstalled => write!(f, "prefer-installed"),
+        }
+    }
 }
 
-impl<F: Fn(&str) -> Result<String, Box<dyn Error + Send + Sync>>> Online<F> {
+#[cfg(feature = "fs")]
+impl<F: HttpFetch> Online<F> {
     /// Constructor for the online solver.
     ///
     /// At the beginning we make one call to
     /// `https://package.elm-lang.org/packages/since/...`
     /// to update our list of existing packages.
+    /// The full list of packages is fetched instead once per
+    /// [`RESYNC_PERIOD`](crate::pkg_version::RESYNC_PERIOD), to detect deleted versions.
     ///
     /// The address of the remote package server is configurable
     /// in case you want to use a mirror of the package server.
     /// Typically, this should be set to `"https://package.elm-lang.org"`.
     ///
     /// The caller must also provide the http client to make the get requests.
-    /// One simple option is to use the [`ureq`](https://crates.io/crates/ureq) crate for this.
+    /// It can be a simple function or closure, or a client reusing connections between requests,
+    /// such as [`UreqFetcher`](crate::fetch::UreqFetcher) with the `ureq` feature.
     pub fn new<S: ToString>(
         offline: Offline,
         remote: S,
@@ -381,67 +1587,592 @@ impl<F: Fn(&str) -> Result<String, Box<dyn Error + Send + Sync>>> Online<F> {
     ) -> Result<Self, CacheError> {
         let mut online_cache = Cache::load(&offline.elm_home).unwrap_or_else(|_| Cache::new());
         let remote = remote.to_string();
-        online_cache.update(&remote, &http_fetch)?;
+        if let Some(progress) = &offline.progress {
+            progress.emit(ProgressEvent::Phase {
+                phase: Phase::UpdateCache,
+            });
+        }
+        online_cache.update_with_resync(
+            &offline.elm_home,
+            &remote,
+            &http_fetch,
+            offline.clock(),
+        )?;
         online_cache.save(&offline.elm_home)?;
+        let missing = MissingCache::load(&offline.elm_home).unwrap_or_default();
+        let packed = open_packed_store(&offline.elm_home);
         Ok(Self {
             offline,
-            online_cache,
+            online_cache: Arc::new(RwLock::new(online_cache)),
+            refresh: Arc::new(Mutex::new(None)),
+            unavailable: RefCell::new(BTreeSet::new()),
+            missing: Arc::new(Mutex::new(missing)),
             remote,
+            federation: Federation::new(),
+            federated_cache: Arc::new(RwLock::new(FederatedCache::default())),
+            fallbacks: Vec::new(),
+            min_release_age: None,
+            release_times: Arc::new(Mutex::new(ReleaseTimes::new())),
             http_fetch,
             strategy,
+            #[cfg(feature = "sha2")]
+            shared_cache: None,
+            packed,
         })
     }
 
+    /// Look for `elm.json` files in a cache shared with other `ELM_HOME`s
+    /// before fetching them from the package server,
+    /// and save the fetched ones into it.
+    #[cfg(feature = "sha2")]
+    pub fn with_shared_cache(mut self, shared_cache: SharedCache) -> Self {
+        self.shared_cache = Some(shared_cache);
+        self
+    }
+
+    /// Union the versions of additional package servers with the ones of the main one,
+    /// see [`Federation`].
+    /// Their versions caches are updated right away,
+    /// but not by [`Online::refresh_in_background`], which only updates the main one.
+    pub fn with_federation(mut self, federation: Federation) -> Result<Self, CacheError> {
+        let elm_home = &self.offline.elm_home;
+        let mut federated_cache = FederatedCache::load(elm_home, &federation);
+        federated_cache.update(
+            elm_home,
+            &federation,
+            &self.http_fetch,
+            self.offline.clock(),
+        )?;
+        federated_cache.save(elm_home)?;
+        self.federation = federation;
+        self.federated_cache = Arc::new(RwLock::new(federated_cache));
+        Ok(self)
+    }
+
+    /// Fetch `elm.json` files from these fallbacks, in order,
+    /// when the package server fails to provide them, for example during outages,
+    /// see [`PkgVersion::fetch_config_with_fallbacks`].
+    /// Configs fetched from a fallback are recorded as such in the provenance.
+    pub fn with_config_fallbacks(mut self, fallbacks: Vec<ConfigFallback>) -> Self {
+        self.fallbacks = fallbacks;
+        self
+    }
+
+    /// Only consider versions released at least `min_age` ago,
+    /// according to the release times listed by the package server, see [`ReleaseTimes`].
+    /// Versions whose release time cannot be fetched are not considered.
+    pub fn with_min_release_age(mut self, min_age: Duration) -> Self {
+        self.min_release_age = Some(min_age);
+        self
+    }
+
+    /// Replace the cache of package versions known to be missing on the package server,
+    /// loaded by default from `ELM_HOME`, for example to change its time to live.
+    /// The cache stays shared with the clones of this solver.
+    pub fn with_missing_cache(self, missing: MissingCache) -> Self {
+        *self.missing.lock().unwrap() = missing;
+        self
+    }
+
+    /// Change the strategy of the solver.
+    /// The database of known packages stays shared with the clones of this solver.
+    pub fn with_strategy(mut self, strategy: VersionStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Interrupt resolutions exceeding the given limits, see [`Offline::with_limits`].
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.offline.limits = limits;
+        self
+    }
+
+    /// Apply version policies to all dependency resolutions, see [`Offline::with_policies`].
+    /// The database of known packages stays shared with the clones of this solver.
+    pub fn with_policies(mut self, policies: Policies) -> Self {
+        self.offline.policies = policies;
+        self
+    }
+
     /// Run the dependency solver on a given project config, obtained from an `elm.json`.
     ///
+    /// Package versions whose `elm.json` does not exist on the package server,
+    /// for example because the versions cache is out of sync with a mirror,
+    /// are considered unavailable and other versions are picked instead.
+    ///
     /// See [`Offline::solve_deps`].
     pub fn solve_deps(
         &self,
         project_elm_json: &ProjectConfig,
         use_test: bool,
         additional_constraints: &[(Pkg, Constraint)],
+        extra_mode: ExtraMode,
     ) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>> {
-        let list_available_versions = |pkg: &Pkg| Ok(self.list_available_versions(pkg));
-        let fetch_elm_json =
-            |pkg: &Pkg, version| self.fetch_elm_json(pkg, version).map_err(|err| err.into());
-        solve_deps_with(
+        let preferred_versions = self.offline.preferences(project_elm_json);
+        let list_available_versions =
+            |pkg: &Pkg| Ok(self.list_available_versions(pkg, &preferred_versions));
+        let fetch_elm_json = |pkg: &Pkg, version| self.fetch_elm_json(pkg, version);
+        solve_deps_configured(
             project_elm_json,
             use_test,
             additional_constraints,
+            extra_mode,
+            self.offline.root_pinning,
+            self.offline.tie_break,
+            &self.offline.limits,
+            self.offline.progress.as_ref(),
             fetch_elm_json,
             list_available_versions,
         )
+        .map_err(|err| err.into())
     }
 
-    /// Try successively to load the elm.json of this package from
-    ///  - the elm home,
-    ///  - the online cache,
-    ///  - or directly from the package website.
-    fn fetch_elm_json(&self, pkg: &Pkg, version: SemVer) -> Result<PackageConfig, PkgVersionError> {
+    /// Load the elm.json of a package version during a resolution, recording where it came from.
+    ///
+    /// Returns `None` if the package website does not know this version.
+    fn fetch_elm_json(
+        &self,
+        pkg: &Pkg,
+        version: SemVer,
+    ) -> Result<Option<PackageConfig>, PkgVersionError> {
         let pkg_version = PkgVersion {
             author_pkg: pkg.clone(),
             version,
         };
-        pkg_version
-            .load_config(&self.offline.elm_home, &self.offline.elm_version)
-            .or_else(|_| pkg_version.load_from_cache(&self.offline.elm_home))
+        match self.lookup_config(&pkg_version)? {
+            None => Ok(None),
+            Some((config, source, remote_name)) => {
+                let overridden = matches!(source, Source::Override { .. });
+                if !self.federation.remotes.is_empty() && !overridden {
+                    self.offline
+                        .provenance
+                        .record_remote(&pkg_version, remote_name);
+                }
+                Ok(Some(self.offline.record_lookup(
+                    &pkg_version,
+                    config,
+                    source,
+                )))
+            }
+        }
+    }
+
+    /// Try successively to load the elm.json of this package from
+    ///  - its override, if any,
+    ///  - the elm home,
+    ///  - the shared cache, if any,
+    ///  - the online cache,
+    ///  - or directly from the package website.
+    ///
+    /// With a federation, the package website is the first one publishing this version,
+    /// in their order of precedence for this package, and its name is returned with the config.
+    ///
+    /// Returns `None` if the package website does not know this version,
+    /// and skips it in the next resolutions and the next runs.
+    ///
+    /// Resolutions and the [`RegistryView`] of this solver share this lookup,
+    /// resolutions then record it with [`Offline::record_lookup`].
+    fn lookup_config(
+        &self,
+        pkg_version: &PkgVersion,
+    ) -> Result<Option<(PackageConfig, Source, &str)>, PkgVersionError> {
+        let (remote_name, remote) = self.origin(pkg_version);
+        let fetched = (self.offline)
+            .lookup_config(pkg_version)
+            .or_else(|err| {
+                (self.load_shared(remote_name, pkg_version))
+                    .map(|config| (config, Source::SharedCache))
+                    .ok_or(err)
+            })
+            // Configs fetched again since they were packed win over the packed ones.
             .or_else(|_| {
-                pkg_version.fetch_config(&self.offline.elm_home, &self.remote, &self.http_fetch)
+                (pkg_version.load_from_remote_cache(&self.offline.elm_home, remote_name))
+                    .map(|config| (config, Source::Cache))
+            })
+            .or_else(|err| {
+                (self.load_packed(remote_name, pkg_version))
+                    .map(|config| (config, Source::Cache))
+                    .ok_or(err)
             })
+            .or_else(|_| {
+                if self
+                    .missing
+                    .lock()
+                    .unwrap()
+                    .is_missing(pkg_version, self.offline.clock())
+                {
+                    return Err(PkgVersionError::FetchError {
+                        url: pkg_version.to_url(remote),
+                        source: FetchError::NotFound,
+                    });
+                }
+                let elm_home = &self.offline.elm_home;
+                let timestamp = self.offline.clock.unix_secs();
+                let (config, fallback) = pkg_version.fetch_remote_config_with_fallbacks(
+                    elm_home,
+                    (remote_name, remote),
+                    &self.fallbacks,
+                    &self.http_fetch,
+                )?;
+                match fallback {
+                    None => {
+                        self.store_shared(remote_name, pkg_version);
+                        let url = pkg_version.to_url(remote);
+                        Ok((config, Source::Fetched { url, timestamp }))
+                    }
+                    Some(fallback) => {
+                        let url = fallback.url(pkg_version);
+                        Ok((config, Source::Fallback { url, timestamp }))
+                    }
+                }
+            });
+        match fetched {
+            Ok((config, source)) => Ok(Some((config, source, remote_name))),
+            Err(PkgVersionError::FetchError {
+                source: FetchError::NotFound,
+                ..
+            }) => {
+                // Also skip this version in the next resolutions, and the next runs.
+                let unavailable = (pkg_version.author_pkg.clone(), pkg_version.version);
+                self.unavailable.borrow_mut().insert(unavailable);
+                self.record_missing(pkg_version);
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
     }
 
-    /// Combine local versions with online versions listed on the package server.
-    fn list_available_versions(&self, pkg: &Pkg) -> impl Iterator<Item = SemVer> {
+    /// Name and url of the package server publishing a package version,
+    /// the first one in the order of precedence of its package.
+    /// Defaults to the main package server for versions no package server knows.
+    fn origin(&self, pkg_version: &PkgVersion) -> (&str, &str) {
+        let (pkg, version) = (&pkg_version.author_pkg, pkg_version.version);
+        for name in self.federation.order(pkg) {
+            let listed = if name == MAIN_REMOTE {
+                let online_cache = self.online_cache.read().unwrap();
+                (online_cache.cache.get(pkg)).is_some_and(|versions| versions.contains(&version))
+            } else {
+                self.federated_cache
+                    .read()
+                    .unwrap()
+                    .contains(name, pkg, version)
+            };
+            if let (true, Some(url)) = (listed, self.remote_url(name)) {
+                return (name, url);
+            }
+        }
+        (MAIN_REMOTE, &self.remote)
+    }
+
+    /// Url of a package server of the federation, including the main one.
+    fn remote_url(&self, remote_name: &str) -> Option<&str> {
+        if remote_name == MAIN_REMOTE {
+            Some(&self.remote)
+        } else {
+            self.federation.url(remote_name)
+        }
+    }
+
+    /// Keep the versions of a package released at least `min_age` ago.
+    /// Failing to fetch the release times is not an error,
+    /// the versions with unknown ones are just dropped.
+    fn released_before(&self, pkg: &Pkg, versions: Vec<SemVer>, min_age: Duration) -> Vec<SemVer> {
+        let remotes: Vec<&str> = (self.federation.order(pkg).into_iter())
+            .filter_map(|name| self.remote_url(name))
+            .collect();
+        let mut release_times = self.release_times.lock().unwrap();
+        let elm_home = &self.offline.elm_home;
+        let _ = release_times.ensure(elm_home, pkg, &versions, &remotes, &self.http_fetch);
+        let now = self.offline.clock.unix_secs();
+        (versions.into_iter())
+            .filter(|v| release_times.old_enough(pkg, *v, min_age, now))
+            .collect()
+    }
+
+    /// Remember that a package version is missing on the package server,
+    /// or its whole package if no package server lists it,
+    /// and forget the expired entries.
+    /// Failing to save it is not an error, it will just be requested again later.
+    fn record_missing(&self, pkg_version: &PkgVersion) {
+        let clock = self.offline.clock();
+        let mut missing = self.missing.lock().unwrap();
+        if !missing.is_missing(pkg_version, clock) {
+            let pkg = &pkg_version.author_pkg;
+            let listed = self.online_cache.read().unwrap().cache.contains_key(pkg)
+                || !self
+                    .federated_cache
+                    .read()
+                    .unwrap()
+                    .versions(pkg)
+                    .is_empty();
+            if listed {
+                missing.record_version(pkg_version, clock);
+            } else {
+                missing.record_package(pkg, clock);
+            }
+            missing.prune(clock);
+            let _ = missing.save(&self.offline.elm_home);
+        }
+    }
+
+    /// Load the elm.json of a package version from the shared cache, if any.
+    /// The shared and packed caches only hold the configs of the main package server.
+    #[cfg_attr(not(feature = "sha2"), allow(unused_variables))]
+    fn load_shared(&self, remote_name: &str, pkg_version: &PkgVersion) -> Option<PackageConfig> {
+        if remote_name != MAIN_REMOTE {
+            return None;
+        }
+        #[cfg(feature = "sha2")]
+        if let Some(shared_cache) = &self.shared_cache {
+            return shared_cache
+                .load_config(pkg_version, &self.offline.elm_home)
+                .ok();
+        }
+        None
+    }
+
+    /// Load the elm.json of a package version from the packed store, if any.
+    fn load_packed(&self, remote_name: &str, pkg_version: &PkgVersion) -> Option<PackageConfig> {
+        if remote_name != MAIN_REMOTE {
+            return None;
+        }
+        let packed = self.packed.as_ref()?.lock().unwrap();
+        packed.load_config(pkg_version).ok()
+    }
+
+    /// Save a fetched elm.json into the shared cache, if any.
+    /// Failing to do so is not an error, it will just be fetched again later.
+    #[cfg_attr(not(feature = "sha2"), allow(unused_variables))]
+    fn store_shared(&self, remote_name: &str, pkg_version: &PkgVersion) {
+        #[cfg(feature = "sha2")]
+        if let (Some(shared_cache), MAIN_REMOTE) = (&self.shared_cache, remote_name) {
+            let _ = shared_cache.store(pkg_version, &self.offline.elm_home);
+        }
+    }
+
+    /// Versions of a package known locally or on the package servers,
+    /// except the ones found unavailable.
+    fn known_versions(&self, pkg: &Pkg) -> BTreeSet<SemVer> {
         let empty_tree = BTreeSet::new();
         let local_cache = self.offline.versions_cache.borrow();
         let local_versions = local_cache.cache.get(pkg).unwrap_or(&empty_tree);
-        let online_cache = &self.online_cache.cache;
-        let online_versions = online_cache.get(pkg).unwrap_or(&empty_tree);
-        let all_versions: Vec<SemVer> = local_versions.union(online_versions).cloned().collect();
-        let iter: Box<dyn Iterator<Item = SemVer>> = match self.strategy {
-            VersionStrategy::Oldest => Box::new(all_versions.into_iter()),
-            VersionStrategy::Newest => Box::new(all_versions.into_iter().rev()),
+        let online_cache = self.online_cache.read().unwrap();
+        let online_versions = online_cache.cache.get(pkg).unwrap_or(&empty_tree);
+        let mut known_versions = self.federated_cache.read().unwrap().versions(pkg);
+        known_versions.extend(local_versions.union(online_versions));
+        let unavailable = self.unavailable.borrow();
+        known_versions.retain(|v| !unavailable.contains(&(pkg.clone(), *v)));
+        known_versions
+    }
+
+    /// Versions of a package: the overridden one,
+    /// or the ones known locally or on the package servers, except the ones found unavailable.
+    ///
+    /// Resolutions and the [`RegistryView`] of this solver share this lookup,
+    /// resolutions then apply the policies, minimum release age and preferred versions of the solver.
+    fn lookup_versions(&self, pkg: &Pkg) -> BTreeSet<SemVer> {
+        match self.offline.overridden_version(pkg) {
+            Some(version) => BTreeSet::from([version]),
+            None => self.known_versions(pkg),
+        }
+    }
+
+    /// Combine local versions with online versions listed on the package server.
+    fn list_available_versions(
+        &self,
+        pkg: &Pkg,
+        preferred_versions: &BTreeMap<Pkg, SemVer>,
+    ) -> impl Iterator<Item = SemVer> {
+        // Installed versions are only listed on demand, this also adds them to the local cache.
+        let installed: BTreeSet<SemVer> = match self.strategy {
+            VersionStrategy::PreferInstalled => {
+                let installed = self.offline.load_installed_versions_of(pkg);
+                installed.unwrap_or_default().into_iter().collect()
+            }
+            VersionStrategy::Newest | VersionStrategy::Oldest => BTreeSet::new(),
+        };
+        let lookup_versions = self.lookup_versions(pkg).into_iter();
+        let mut all_versions = self.offline.apply_policies(pkg, lookup_versions);
+        let overridden = self.offline.overridden_version(pkg).is_some();
+        if let (Some(min_age), false) = (self.min_release_age, overridden) {
+            all_versions = self.released_before(pkg, all_versions, min_age);
+        }
+        match self.strategy {
+            VersionStrategy::Newest => all_versions.reverse(),
+            VersionStrategy::Oldest => {}
+            VersionStrategy::PreferInstalled => {
+                all_versions.reverse();
+                // Stable sort keeping the newest first among installed and other versions.
+                all_versions.sort_by_key(|v| !installed.contains(v));
+            }
+        }
+        prefer(preferred_versions, pkg, &mut all_versions);
+        self.offline.record_versions(pkg, &all_versions);
+        all_versions.into_iter()
+    }
+}
+
+/// Installed packages and the packages of the package servers, with the overrides of this solver,
+/// ignoring its policies, minimum release age and preferred versions.
+///
+/// Configs are loaded like during resolutions, downloading them if needed,
+/// and writing them to the cache, but their provenance is not recorded.
+#[cfg(feature = "fs")]
+impl<F: HttpFetch> RegistryView for Online<F> {
+    type Error = PkgVersionError;
+
+    fn versions(&self, pkg: &Pkg) -> Result<BTreeSet<SemVer>, Self::Error> {
+        // Also list installed versions, which are otherwise only listed on demand.
+        (self.offline.load_installed_versions_of(pkg))
+            .map_err(|err| PkgVersionError::from(PkgVersionParseError::from(err)))?;
+        Ok(self.lookup_versions(pkg))
+    }
+
+    fn config(&self, pkg: &Pkg, version: SemVer) -> Result<Option<PackageConfig>, Self::Error> {
+        if !self.exists(pkg, version)? {
+            return Ok(None);
+        }
+        let pkg_version = PkgVersion {
+            author_pkg: pkg.clone(),
+            version,
+        };
+        let looked_up = self.lookup_config(&pkg_version)?;
+        Ok(looked_up.map(|(config, _, _)| config))
+    }
+}
+
+/// Open the packed store of an `ELM_HOME`, if it has packed configs.
+/// Failing to open it is not an error, configs are then read from their own files.
+#[cfg(feature = "fs")]
+fn open_packed_store(elm_home: &Path) -> Option<Arc<Mutex<PackedStore>>> {
+    let packed = PackedStore::open(elm_home).ok()?;
+    (!packed.is_empty()).then(|| Arc::new(Mutex::new(packed)))
+}
+
+#[cfg(feature = "fs")]
+impl<F: HttpFetch + Clone + Send + 'static> Online<F> {
+    /// Constructor for the online solver, updating its list of existing packages
+    /// in a background thread instead of waiting for the package server.
+    ///
+    /// Resolutions can start right away with the packages already known in `ELM_HOME`,
+    /// and use [`Online::solve_deps_refreshed`] to solve again if the update brings
+    /// new versions that could change the solution.
+    pub fn new_in_background<S: ToString>(
+        offline: Offline,
+        remote: S,
+        http_fetch: F,
+        strategy: VersionStrategy,
+    ) -> Self {
+        let online_cache = Cache::load(&offline.elm_home).unwrap_or_else(|_| Cache::new());
+        let missing = MissingCache::load(&offline.elm_home).unwrap_or_default();
+        let packed = open_packed_store(&offline.elm_home);
+        let online = Self {
+            offline,
+            online_cache: Arc::new(RwLock::new(online_cache)),
+            refresh: Arc::new(Mutex::new(None)),
+            unavailable: RefCell::new(BTreeSet::new()),
+            missing: Arc::new(Mutex::new(missing)),
+            remote: remote.to_string(),
+            federation: Federation::new(),
+            federated_cache: Arc::new(RwLock::new(FederatedCache::default())),
+            fallbacks: Vec::new(),
+            min_release_age: None,
+            release_times: Arc::new(Mutex::new(ReleaseTimes::new())),
+            http_fetch,
+            strategy,
+            #[cfg(feature = "sha2")]
+            shared_cache: None,
+            packed,
+        };
+        online.refresh_in_background();
+        online
+    }
+
+    /// Update the list of existing packages in a background thread,
+    /// unless an update is already running.
+    ///
+    /// Resolutions keep using the previous list until the update completes.
+    /// This is convenient for long-running programs, such as daemons,
+    /// to periodically refresh their solver instead of creating a new one.
+    /// Call [`Online::wait_for_refresh`] to know the outcome of the update.
+    pub fn refresh_in_background(&self) {
+        let mut refresh = self.refresh.lock().unwrap();
+        if refresh.as_ref().is_some_and(|r| !r.is_finished()) {
+            return;
+        }
+        let online_cache = Arc::clone(&self.online_cache);
+        let elm_home = self.offline.elm_home.clone();
+        let remote = self.remote.clone();
+        let http_fetch = self.http_fetch.clone();
+        let clock = Arc::clone(&self.offline.clock);
+        *refresh = Some(thread::spawn(move || {
+            let mut new_cache = online_cache.read().unwrap().clone();
+            let update = new_cache.update_with_resync(&elm_home, &remote, &http_fetch, &*clock)?;
+            new_cache.save(&elm_home)?;
+            *online_cache.write().unwrap() = new_cache;
+            Ok(update.added)
+        }));
+    }
+
+    /// Wait for the background update of the list of existing packages, if any,
+    /// and return the new package versions it discovered.
+    pub fn wait_for_refresh(&self) -> Result<Vec<PkgVersion>, CacheError> {
+        let refresh = self.refresh.lock().unwrap().take();
+        match refresh {
+            None => Ok(Vec::new()),
+            Some(refresh) => refresh
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic)),
+        }
+    }
+
+    /// Solve with the packages known so far, while the list of existing packages is updated
+    /// in the background, and solve again only if new versions could change the solution.
+    ///
+    /// New versions could change the solution if the resolution failed,
+    /// or if they are preferred by the version strategy over the picked versions.
+    /// If the update fails, the solution obtained with the packages known so far is kept.
+    ///
+    /// See [`Online::solve_deps`].
+    pub fn solve_deps_refreshed(
+        &self,
+        project_elm_json: &ProjectConfig,
+        use_test: bool,
+        additional_constraints: &[(Pkg, Constraint)],
+        extra_mode: ExtraMode,
+    ) -> Result<AppDependencies, PubGrubError<Pkg, SemVer>> {
+        let solve = || {
+            self.solve_deps(
+                project_elm_json,
+                use_test,
+                additional_constraints,
+                extra_mode,
+            )
+        };
+        let result = solve();
+        let new_versions = match self.wait_for_refresh() {
+            Ok(new_versions) => new_versions,
+            Err(_) => return result,
         };
-        iter
+        let could_change = |solution: &AppDependencies| {
+            new_versions.iter().any(|pv| {
+                let picked = (solution.direct.get(&pv.author_pkg))
+                    .or_else(|| solution.indirect.get(&pv.author_pkg));
+                match (picked, self.strategy) {
+                    // New versions are not installed yet, so they could only replace
+                    // older versions with the prefer-installed strategy too.
+                    (Some(picked), VersionStrategy::Newest | VersionStrategy::PreferInstalled) => {
+                        pv.version > *picked
+                    }
+                    (Some(picked), VersionStrategy::Oldest) => pv.version < *picked,
+                    (None, _) => false,
+                }
+            })
+        };
+        match &result {
+            Ok(solution) if !could_change(solution) => result,
+            Err(_) if new_versions.is_empty() => result,
+            _ => solve(),
+        }
     }
 }
@@ -0,0 +1,215 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module capturing every option of the dependency solver in a serializable configuration.
+//!
+//! The [`Offline`] and [`Online`] solvers are configured with builder methods,
+//! which is convenient in code, but leaves no trace of the exact configuration used.
+//! A [`SolverConfig`] gathers all these options in a single value,
+//! that tools can persist, log, and replay with [`solve_with_config`],
+//! alongside the solutions they obtained.
+//! Missing fields take their default values when deserializing.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::budget::Limits;
+use crate::constraint::Constraint;
+use crate::federation::Federation;
+use crate::fetch::HttpFetch;
+use crate::pkg_version::{CacheError, ConfigFallback};
+use crate::policy::Policies;
+use crate::project_config::{AppDependencies, Pkg, ProjectConfig};
+use crate::solver::{
+    ExtraMode, IndirectPins, Offline, Online, PubGrubError, RootPinning, TieBreak, VersionStrategy,
+};
+
+/// Where the solver looks for packages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SolveMode {
+    /// Only use the packages installed in `ELM_HOME`.
+    Offline,
+    /// Try offline first, and online if it fails.
+    #[default]
+    OfflineFirst,
+    /// Use all the packages of the package server.
+    Online,
+}
+
+/// Every option of a dependency resolution, except the project itself.
+///
+/// ```
+/// # use elm_solve_deps::solver::VersionStrategy;
+/// # use elm_solve_deps::solver_config::{SolveMode, SolverConfig};
+/// let config: SolverConfig =
+///     serde_json::from_str(r#"{ "mode": "online", "strategy": "oldest", "use-test": true }"#)
+///         .unwrap();
+/// assert_eq!(config.mode, SolveMode::Online);
+/// assert_eq!(config.strategy, VersionStrategy::Oldest);
+/// assert_eq!(config.elm_version, "0.19.1");
+/// let snapshot = serde_json::to_string(&config).unwrap();
+/// assert_eq!(serde_json::from_str::<SolverConfig>(&snapshot).unwrap(), config);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SolverConfig {
+    /// Where the solver looks for packages.
+    pub mode: SolveMode,
+    /// Which versions the online solver tries first.
+    pub strategy: VersionStrategy,
+    /// Whether test dependencies are also solved.
+    pub use_test: bool,
+    /// Additional constraints, not declared in the project.
+    pub extras: BTreeMap<Pkg, Constraint>,
+    /// How additional constraints combine with the declared ones.
+    pub extra_mode: ExtraMode,
+    /// Version policies applied to all packages.
+    pub policies: Policies,
+    /// Versions excluded from the resolution, on top of the policies.
+    pub exclude: BTreeMap<Pkg, BTreeSet<SemVer>>,
+    /// How strictly the direct dependencies of applications are kept.
+    pub root_pinning: RootPinning,
+    /// Which package is decided first among equally constrained ones.
+    pub tie_break: TieBreak,
+    /// Whether the indirect dependencies recorded in applications are tried first.
+    pub indirect_pins: IndirectPins,
+    /// Maximum number of decisions before giving up.
+    pub max_decisions: Option<usize>,
+    /// Maximum number of bytes allocated by the process before giving up.
+    pub max_memory: Option<usize>,
+    /// Minimum age of the versions considered by the online solver, in seconds.
+    pub min_release_age: Option<u64>,
+    /// Url of the package server, or of a mirror.
+    pub remote: String,
+    /// Additional package servers, unioned with the main one.
+    pub federation: Federation,
+    /// Sources of `elm.json` files tried when the package server fails to provide them.
+    pub config_fallbacks: Vec<ConfigFallback>,
+    /// Version of elm, used to find installed packages.
+    pub elm_version: String,
+    /// Other versions of elm whose installed packages are also used,
+    /// after the ones of `elm_version`, in order of preference.
+    pub merged_elm_versions: Vec<String>,
+}
+
+/// Error of [`solve_with_config`].
+#[derive(Error, Debug)]
+pub enum SolveWithConfigError {
+    /// The versions cache could not be updated for online solving.
+    #[error("failed to initialize the online solver")]
+    Online(#[source] CacheError),
+
+    /// The resolution failed.
+    #[error(transparent)]
+    Solve(Box<PubGrubError<Pkg, SemVer>>),
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            mode: SolveMode::default(),
+            strategy: VersionStrategy::Newest,
+            use_test: false,
+            extras: BTreeMap::new(),
+            extra_mode: ExtraMode::default(),
+            policies: Policies::new(),
+            exclude: BTreeMap::new(),
+            root_pinning: RootPinning::default(),
+            tie_break: TieBreak::default(),
+            indirect_pins: IndirectPins::default(),
+            max_decisions: None,
+            max_memory: None,
+            min_release_age: None,
+            remote: "https://package.elm-lang.org".to_string(),
+            federation: Federation::new(),
+            config_fallbacks: Vec::new(),
+            elm_version: "0.19.1".to_string(),
+            merged_elm_versions: Vec::new(),
+        }
+    }
+}
+
+impl SolverConfig {
+    /// Offline solver with all the options of this configuration.
+    pub fn offline_solver<P: Into<PathBuf>>(&self, elm_home: P) -> Offline {
+        let mut policies = self.policies.clone();
+        for (pkg, versions) in &self.exclude {
+            for version in versions {
+                policies.exclude(pkg.clone(), *version);
+            }
+        }
+        let limits = Limits {
+            max_decisions: self.max_decisions,
+            max_memory: self.max_memory,
+            cancel: None,
+        };
+        let mut offline = Offline::new(elm_home, &self.elm_version)
+            .with_policies(policies)
+            .with_limits(limits)
+            .with_root_pinning(self.root_pinning)
+            .with_tie_break(self.tie_break)
+            .with_indirect_pins(self.indirect_pins);
+        if !self.merged_elm_versions.is_empty() {
+            let mut elm_versions = vec![self.elm_version.clone()];
+            elm_versions.extend(self.merged_elm_versions.iter().cloned());
+            offline = offline.with_elm_versions(&elm_versions);
+        }
+        offline
+    }
+
+    /// Online solver with all the options of this configuration,
+    /// updating the versions cache in `ELM_HOME` when created.
+    pub fn online_solver<P, H>(&self, elm_home: P, http_fetch: H) -> Result<Online<H>, CacheError>
+    where
+        P: Into<PathBuf>,
+        H: HttpFetch,
+    {
+        let offline = self.offline_solver(elm_home);
+        let mut online = Online::new(offline, &self.remote, http_fetch, self.strategy)?
+            .with_config_fallbacks(self.config_fallbacks.clone());
+        if let Some(min_age) = self.min_release_age {
+            online = online.with_min_release_age(Duration::from_secs(min_age));
+        }
+        if self.federation.remotes.is_empty() {
+            Ok(online)
+        } else {
+            online.with_federation(self.federation.clone())
+        }
+    }
+}
+
+/// Solve the dependencies of a project with the exact options of a configuration.
+///
+/// The `http_fetch` client is only used in the online and offline-first modes.
+pub fn solve_with_config<P, H>(
+    config: &SolverConfig,
+    elm_home: P,
+    project_elm_json: &ProjectConfig,
+    http_fetch: H,
+) -> Result<AppDependencies, SolveWithConfigError>
+where
+    P: Into<PathBuf>,
+    H: HttpFetch,
+{
+    let elm_home = elm_home.into();
+    let extras: Vec<(Pkg, Constraint)> = config.extras.clone().into_iter().collect();
+    let (use_test, extra_mode) = (config.use_test, config.extra_mode);
+    if config.mode != SolveMode::Online {
+        let offline = config.offline_solver(elm_home.clone());
+        match offline.solve_deps(project_elm_json, use_test, &extras, extra_mode) {
+            Ok(solution) => return Ok(solution),
+            Err(err) if config.mode == SolveMode::Offline => {
+                return Err(SolveWithConfigError::Solve(Box::new(err)))
+            }
+            Err(_) => {}
+        }
+    }
+    let online =
+        (config.online_solver(elm_home, http_fetch)).map_err(SolveWithConfigError::Online)?;
+    (online.solve_deps(project_elm_json, use_test, &extras, extra_mode))
+        .map_err(|err| SolveWithConfigError::Solve(Box::new(err)))
+}
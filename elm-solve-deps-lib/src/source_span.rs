@@ -0,0 +1,325 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module locating dependency entries in the source of an `elm.json`, for editor diagnostics.
+//!
+//! Deserializing an `elm.json` with serde loses where each entry was written.
+//! An [`ElmJsonSource`] re-parses the text of the file, recording the [`Span`] of every
+//! object member, with byte offsets and line-column positions.
+//! When an error is attributable to dependencies of the project,
+//! [`locate_error`] returns the [`SourceLocation`] of their entries,
+//! such that editors can underline the offending constraints.
+
+use pubgrub::report::{DerivationTree, External};
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::project_config::Pkg;
+use crate::solver::PubGrubError;
+
+/// Location of some text in a source file.
+///
+/// Offsets are in bytes and exclusive at the end.
+/// Lines and columns start at 1, and columns count characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct Span {
+    /// Byte offset of the start.
+    pub start: usize,
+    /// Byte offset of the end.
+    pub end: usize,
+    /// Line of the start.
+    pub start_line: usize,
+    /// Column of the start.
+    pub start_column: usize,
+    /// Line of the end.
+    pub end_line: usize,
+    /// Column of the end.
+    pub end_column: usize,
+}
+
+/// Location of a dependency entry in an `elm.json` file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct SourceLocation {
+    /// Path of the `elm.json` file.
+    pub file: PathBuf,
+    /// The dependency of the entry.
+    pub pkg: Pkg,
+    /// The dependencies section of the entry, such as `dependencies.direct`.
+    pub section: String,
+    /// Span of the entry, from its key to the end of its value.
+    pub span: Span,
+}
+
+/// Spans of the members of the objects of an `elm.json` source.
+///
+/// ```
+/// # use elm_solve_deps::source_span::ElmJsonSource;
+/// # use elm_solve_deps::project_config::Pkg;
+/// let text = r#"{
+///     "type": "package",
+///     "dependencies": {
+///         "elm/core": "1.0.0 <= v < 2.0.0"
+///     }
+/// }"#;
+/// let source = ElmJsonSource::parse("elm.json", text.to_string()).unwrap();
+/// let entries = source.dependency_entries(&Pkg::new("elm", "core"));
+/// assert_eq!(entries[0].section, "dependencies");
+/// assert_eq!((entries[0].span.start_line, entries[0].span.start_column), (4, 9));
+/// let (start, end) = (entries[0].span.start, entries[0].span.end);
+/// assert_eq!(&text[start..end], r#""elm/core": "1.0.0 <= v < 2.0.0""#);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ElmJsonSource {
+    path: PathBuf,
+    text: String,
+    /// Path of keys to each member, with the byte offsets of its start and end.
+    members: Vec<(Vec<String>, usize, usize)>,
+}
+
+impl ElmJsonSource {
+    /// Parse the text of an `elm.json` file.
+    /// Return `None` if it is not valid JSON.
+    pub fn parse<P: AsRef<Path>>(path: P, text: String) -> Option<Self> {
+        let mut scanner = Scanner {
+            bytes: text.as_bytes(),
+            pos: 0,
+            path: Vec::new(),
+            members: Vec::new(),
+        };
+        scanner.value()?;
+        scanner.whitespace();
+        if scanner.pos != text.len() {
+            return None;
+        }
+        let members = scanner.members;
+        Some(Self {
+            path: path.as_ref().to_path_buf(),
+            text,
+            members,
+        })
+    }
+
+    /// Read and parse an `elm.json` file.
+    #[cfg(feature = "fs")]
+    pub fn load<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let text = std::fs::read_to_string(&path).ok()?;
+        Self::parse(path, text)
+    }
+
+    /// Span of the member at the given path of keys, such as `["dependencies", "direct"]`.
+    pub fn member(&self, keys: &[&str]) -> Option<Span> {
+        (self.members.iter())
+            .find(|(path, _, _)| path.iter().map(String::as_str).eq(keys.iter().copied()))
+            .map(|(_, start, end)| self.span(*start, *end))
+    }
+
+    /// Locations of the entries of a dependency,
+    /// in the dependencies and test dependencies of an application or a package.
+    pub fn dependency_entries(&self, pkg: &Pkg) -> Vec<SourceLocation> {
+        let pkg_str = pkg.to_string();
+        (self.members.iter())
+            .filter(|(path, _, _)| match path.as_slice() {
+                [section, key] | [section, _, key] => {
+                    (section == "dependencies" || section == "test-dependencies") && key == &pkg_str
+                }
+                _ => false,
+            })
+            .map(|(path, start, end)| SourceLocation {
+                file: self.path.clone(),
+                pkg: pkg.clone(),
+                section: path[..path.len() - 1].join("."),
+                span: self.span(*start, *end),
+            })
+            .collect()
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        let (start_line, start_column) = self.line_column(start);
+        let (end_line, end_column) = self.line_column(end);
+        Span {
+            start,
+            end,
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+        }
+    }
+
+    fn line_column(&self, offset: usize) -> (usize, usize) {
+        let before = &self.text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line = before.matches('\n').count() + 1;
+        (line, before[line_start..].chars().count() + 1)
+    }
+}
+
+/// Direct dependencies of the root package that an error is attributable to.
+///
+/// For a failed resolution, these are the dependencies of the root
+/// involved in the derivation tree of the failure.
+pub fn implicated_dependencies(err: &PubGrubError<Pkg, SemVer>, root: &Pkg) -> BTreeSet<Pkg> {
+    let mut implicated = BTreeSet::new();
+    match err {
+        PubGrubError::NoSolution(tree) => root_dependencies_of(tree, root, &mut implicated),
+        PubGrubError::DependencyOnTheEmptySet {
+            package, dependent, ..
+        } if package == root => {
+            implicated.insert(dependent.clone());
+        }
+        _ => {}
+    }
+    implicated
+}
+
+/// Locations in the `elm.json` of the project of the dependencies an error is attributable to.
+pub fn locate_error(
+    err: &PubGrubError<Pkg, SemVer>,
+    root: &Pkg,
+    source: &ElmJsonSource,
+) -> Vec<SourceLocation> {
+    (implicated_dependencies(err, root).iter())
+        .flat_map(|pkg| source.dependency_entries(pkg))
+        .collect()
+}
+
+fn root_dependencies_of(
+    tree: &DerivationTree<Pkg, SemVer>,
+    root: &Pkg,
+    implicated: &mut BTreeSet<Pkg>,
+) {
+    match tree {
+        DerivationTree::External(External::FromDependencyOf(pkg, _, dep, _)) if pkg == root => {
+            implicated.insert(dep.clone());
+        }
+        DerivationTree::External(_) => {}
+        DerivationTree::Derived(derived) => {
+            root_dependencies_of(&derived.cause1, root, implicated);
+            root_dependencies_of(&derived.cause2, root, implicated);
+        }
+    }
+}
+
+/// Minimal JSON parser recording the spans of object members.
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    path: Vec<String>,
+    members: Vec<(Vec<String>, usize, usize)>,
+}
+
+impl<'a> Scanner<'a> {
+    fn whitespace(&mut self) {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.whitespace();
+        if self.bytes.get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn value(&mut self) -> Option<()> {
+        self.whitespace();
+        match self.bytes.get(self.pos)? {
+            b'{' => self.object(),
+            b'[' => self.array(),
+            b'"' => self.string().map(|_| ()),
+            _ => self.literal(),
+        }
+    }
+
+    fn object(&mut self) -> Option<()> {
+        self.expect(b'{')?;
+        if self.expect(b'}').is_some() {
+            return Some(());
+        }
+        loop {
+            self.whitespace();
+            let start = self.pos;
+            let key = self.string()?;
+            self.expect(b':')?;
+            self.path.push(key);
+            self.value()?;
+            self.members.push((self.path.clone(), start, self.pos));
+            self.path.pop();
+            if self.expect(b'}').is_some() {
+                return Some(());
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    fn array(&mut self) -> Option<()> {
+        self.expect(b'[')?;
+        if self.expect(b']').is_some() {
+            return Some(());
+        }
+        loop {
+            self.value()?;
+            if self.expect(b']').is_some() {
+                return Some(());
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    /// Numbers, booleans and null.
+    fn literal(&mut self) -> Option<()> {
+        let start = self.pos;
+        while (self.bytes.get(self.pos))
+            .is_some_and(|b| b.is_ascii_alphanumeric() || b"+-.".contains(b))
+        {
+            self.pos += 1;
+        }
+        (self.pos > start).then_some(())
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            let byte = *self.bytes.get(self.pos)?;
+            self.pos += 1;
+            match byte {
+                b'"' => return String::from_utf8(bytes).ok(),
+                b'\\' => {
+                    let escaped = *self.bytes.get(self.pos)?;
+                    self.pos += 1;
+                    let c = match escaped {
+                        b'n' => '\n',
+                        b't' => '\t',
+                        b'r' => '\r',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'u' => {
+                            let hex = std::str::from_utf8(self.bytes.get(self.pos..self.pos + 4)?);
+                            self.pos += 4;
+                            let code = u32::from_str_radix(hex.ok()?, 16).ok()?;
+                            // Surrogates are not needed for keys of elm.json files.
+                            char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                        other => other as char,
+                    };
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                _ => bytes.push(byte),
+            }
+        }
+    }
+}
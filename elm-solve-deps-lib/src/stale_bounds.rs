@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module auditing the registry for constraints excluding the latest versions of dependencies.
+//!
+//! Packages published with an upper bound below the latest version of one of their
+//! dependencies hold back every project depending on them, until a maintainer widens
+//! the constraint and publishes a new version.
+//! The audit checks the latest version of every package of the versions cache,
+//! and weighs each stale bound by the number of packages depending on the stale package,
+//! computed with [`reverse_dependencies`], such that maintainers can start with
+//! the packages blocking the largest part of the ecosystem.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::Serialize;
+use std::cmp::Reverse;
+
+use crate::constraint::Constraint;
+use crate::pkg_version::{Cache, PkgVersion};
+use crate::project_config::{PackageConfig, Pkg};
+use crate::stats::{latest_configs, reverse_dependencies};
+
+/// A dependency constraint of the latest version of a package,
+/// excluding the latest version of that dependency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct StaleBound {
+    /// The package with the stale constraint.
+    pub pkg: Pkg,
+    /// The latest version of the package.
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::SemVerSchema"))]
+    pub version: SemVer,
+    /// The dependency whose latest version is excluded.
+    pub dependency: Pkg,
+    /// The published constraint on the dependency.
+    pub constraint: Constraint,
+    /// The latest version of the dependency.
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::SemVerSchema"))]
+    pub latest: SemVer,
+    /// Number of packages whose latest version depends directly on the stale package.
+    pub dependents: usize,
+}
+
+/// Dependencies of a package config whose constraints exclude their latest version,
+/// with that latest version.
+///
+/// Constraints whose lowest version is above the latest version are not stale bounds,
+/// they are invalid, so they are not reported.
+/// Dependencies missing from the versions cache are skipped.
+pub fn stale_dependencies<'a>(
+    config: &'a PackageConfig,
+    cache: &'a Cache,
+) -> impl Iterator<Item = (&'a Pkg, &'a Constraint, SemVer)> {
+    (config.dependencies.iter()).filter_map(move |(dep, constraint)| {
+        let latest = *cache.cache.get(dep)?.iter().next_back()?;
+        let lowest = constraint.0.lowest_version()?;
+        (lowest <= latest && !constraint.allows(&latest)).then_some((dep, constraint, latest))
+    })
+}
+
+/// Find the stale bounds of the latest version of every package of the versions cache
+/// selected by the `select` function.
+///
+/// The `load_config` function retrieves the `elm.json` of a package version,
+/// like for [`registry_stats`](crate::stats::registry_stats).
+/// Packages whose config cannot be loaded are skipped.
+///
+/// Stale bounds are sorted by decreasing number of dependents, then by package.
+pub fn stale_bounds<Select, Load>(
+    cache: &Cache,
+    select: Select,
+    load_config: Load,
+) -> Vec<StaleBound>
+where
+    Select: Fn(&Pkg) -> bool,
+    Load: Fn(&PkgVersion) -> Option<PackageConfig>,
+{
+    let configs = latest_configs(cache, load_config);
+    let dependents = reverse_dependencies(&configs);
+    let mut stale: Vec<StaleBound> = (configs.iter())
+        .filter(|config| select(&config.name))
+        .flat_map(|config| {
+            let dependents = dependents.get(&config.name).map_or(0, |d| d.len());
+            stale_dependencies(config, cache).map(move |(dep, constraint, latest)| StaleBound {
+                pkg: config.name.clone(),
+                version: config.version,
+                dependency: dep.clone(),
+                constraint: constraint.clone(),
+                latest,
+                dependents,
+            })
+        })
+        .collect();
+    stale.sort_by_key(|s| (Reverse(s.dependents), s.pkg.clone(), s.dependency.clone()));
+    stale
+}
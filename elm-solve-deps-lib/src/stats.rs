@@ -0,0 +1,132 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module computing registry-wide statistics about packages.
+//!
+//! Statistics are computed for the latest version of every package in the versions cache,
+//! including their total number of transitive dependencies,
+//! and their popularity, as the number of packages directly depending on them.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::Infallible;
+
+use crate::pkg_version::{Cache, PkgVersion};
+use crate::project_config::{PackageConfig, Pkg, ProjectConfig};
+use crate::solver::{solve_deps_with, ExtraMode};
+
+/// Statistics about the latest version of a package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct PkgStats {
+    /// The package identifier.
+    pub pkg: Pkg,
+    /// The latest version of the package.
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::SemVerSchema"))]
+    pub version: SemVer,
+    /// The lowest elm version supported.
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(with = "Option<crate::schema::SemVerSchema>")
+    )]
+    pub elm_version: Option<SemVer>,
+    /// The license of the package, or the compact JSON of a structured one.
+    pub license: String,
+    /// Number of direct dependencies.
+    pub direct_deps: usize,
+    /// Number of transitive dependencies, if they can be solved.
+    pub total_deps: Option<usize>,
+    /// Number of packages whose latest version depends directly on this package.
+    pub dependents: usize,
+}
+
+/// Compute statistics for the latest version of every package of the versions cache
+/// selected by the `select` function.
+///
+/// The `load_config` function retrieves the `elm.json` of a package version,
+/// and typically looks into `ELM_HOME`, the dependency solver cache,
+/// and eventually the package server.
+/// Packages whose config cannot be loaded are skipped.
+pub fn registry_stats<Select, Load>(
+    cache: &Cache,
+    select: Select,
+    load_config: Load,
+) -> Vec<PkgStats>
+where
+    Select: Fn(&Pkg) -> bool,
+    Load: Fn(&PkgVersion) -> Option<PackageConfig>,
+{
+    let configs = latest_configs(cache, &load_config);
+    let dependents = reverse_dependencies(&configs);
+
+    // Solve the dependencies of selected packages.
+    let fetch_elm_json = |pkg: &Pkg, version| {
+        let pkg_version = PkgVersion {
+            author_pkg: pkg.clone(),
+            version,
+        };
+        Ok::<_, Infallible>(load_config(&pkg_version))
+    };
+    let list_available_versions = |pkg: &Pkg| {
+        let versions = cache.cache.get(pkg).into_iter().flatten();
+        Ok(versions.rev().copied())
+    };
+    (configs.into_iter())
+        .filter(|config| select(&config.name))
+        .map(|config| {
+            let pkg = config.name.clone();
+            let version = config.version;
+            let elm_version = config.elm_version.0.lowest_version();
+            let license = config.license.to_string();
+            let direct_deps = config.dependencies.len();
+            let total_deps = solve_deps_with(
+                &ProjectConfig::Package(config),
+                false,
+                &[],
+                ExtraMode::default(),
+                fetch_elm_json,
+                list_available_versions,
+            )
+            .ok()
+            .map(|solution| solution.direct.len() + solution.indirect.len());
+            PkgStats {
+                dependents: dependents.get(&pkg).map_or(0, BTreeSet::len),
+                pkg,
+                version,
+                elm_version,
+                license,
+                direct_deps,
+                total_deps,
+            }
+        })
+        .collect()
+}
+
+/// Load the configs of the latest versions of all packages of the versions cache.
+/// Packages whose config cannot be loaded are skipped.
+pub fn latest_configs<Load>(cache: &Cache, load_config: Load) -> Vec<PackageConfig>
+where
+    Load: Fn(&PkgVersion) -> Option<PackageConfig>,
+{
+    (cache.cache.iter())
+        .filter_map(|(pkg, versions)| {
+            let version = *versions.iter().next_back()?;
+            load_config(&PkgVersion {
+                author_pkg: pkg.clone(),
+                version,
+            })
+        })
+        .collect()
+}
+
+/// Packages depending directly on each package, among the given configs.
+pub fn reverse_dependencies(configs: &[PackageConfig]) -> BTreeMap<Pkg, BTreeSet<Pkg>> {
+    let mut dependents: BTreeMap<Pkg, BTreeSet<Pkg>> = BTreeMap::new();
+    for config in configs {
+        for dep in config.dependencies.keys() {
+            (dependents.entry(dep.clone()).or_default()).insert(config.name.clone());
+        }
+    }
+    dependents
+}
@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module providing optional telemetry spans, enabled with the `otel` feature.
+//!
+//! With the `otel` feature, spans are emitted through the global OpenTelemetry tracer,
+//! named `"elm-solve-deps"`, for dependency solving, versions cache updates
+//! and every network fetch, with package and version attributes when relevant.
+//! It is the responsibility of the application to install a tracer provider
+//! and an exporter, with `opentelemetry::global::set_tracer_provider`.
+//!
+//! Without the `otel` feature, all spans are no-ops and no dependency is added.
+
+use std::fmt::Display;
+
+/// Name of the tracer used for all spans emitted by this crate.
+pub const TRACER_NAME: &str = "elm-solve-deps";
+
+/// A span covering a unit of work, ended when dropped.
+pub(crate) struct Span {
+    #[cfg(feature = "otel")]
+    inner: opentelemetry::global::BoxedSpan,
+}
+
+impl Span {
+    /// Start a new span with the given name.
+    pub(crate) fn start(name: &'static str) -> Self {
+        #[cfg(feature = "otel")]
+        {
+            use opentelemetry::trace::Tracer;
+            Self {
+                inner: opentelemetry::global::tracer(TRACER_NAME).start(name),
+            }
+        }
+        #[cfg(not(feature = "otel"))]
+        {
+            let _ = name;
+            Self {}
+        }
+    }
+
+    /// Add an attribute to the span.
+    pub(crate) fn with_attribute<T: Display>(mut self, key: &'static str, value: T) -> Self {
+        self.set_attribute(key, value);
+        self
+    }
+
+    /// Add an attribute to the span.
+    #[allow(unused_variables)]
+    pub(crate) fn set_attribute<T: Display>(&mut self, key: &'static str, value: T) {
+        #[cfg(feature = "otel")]
+        {
+            use opentelemetry::trace::Span as _;
+            self.inner
+                .set_attribute(opentelemetry::KeyValue::new(key, value.to_string()));
+        }
+    }
+
+    /// Mark the span as failed with the given error.
+    #[allow(unused_variables)]
+    pub(crate) fn record_error<E: Display>(&mut self, err: &E) {
+        #[cfg(feature = "otel")]
+        {
+            use opentelemetry::trace::{Span as _, StatusCode};
+            self.inner.set_status(StatusCode::Error, err.to_string());
+        }
+    }
+
+    /// Record the outcome of the work covered by this span.
+    pub(crate) fn record_result<T, E: Display>(&mut self, result: &Result<T, E>) {
+        if let Err(err) = result {
+            self.record_error(err);
+        }
+    }
+}
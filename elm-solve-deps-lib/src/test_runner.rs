@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module helping test runners such as elm-test-rs solve test dependencies quickly.
+//!
+//! Test runners solve the test dependencies of the project at every run,
+//! even though the `elm.json` rarely changes between two runs.
+//! [`TestSolutions`] keeps the last successful test solution of each `elm.json`
+//! and solver strategy, in a file of `ELM_HOME/pubgrub/test_solutions/`
+//! named by the sha256 of the `elm.json` content and of the strategy.
+//! On the next run, [`TestSolutions::solve`] verifies it with [`CachedSolution::verify`],
+//! which only compares versions with the recorded constraints,
+//! and only falls back to a full resolution if that fails.
+//!
+//! Contrary to the [`SolveMemo`](crate::solve_memo::SolveMemo),
+//! the key does not depend on the versions cache, which does not need to be loaded.
+//! The reused solution thus ignores versions published since it was recorded,
+//! which is what test runners want between two runs.
+//!
+//! This module is only available with the `sha2` feature.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::cached_solution::CachedSolution;
+use crate::constraint::Constraint;
+use crate::pkg_version::CacheError;
+use crate::project_config::{AppDependencies, PackageConfig, Pkg, ProjectConfig};
+use crate::solver::ExtraMode;
+
+/// Store of the last successful test solution of each `elm.json` and strategy.
+#[derive(Debug, Clone)]
+pub struct TestSolutions {
+    dir: PathBuf,
+}
+
+/// Test dependencies solved by [`TestSolutions::solve`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestSolution {
+    /// The solution, with normal and test dependencies.
+    pub solution: AppDependencies,
+    /// Whether the solution of a previous run was reused.
+    pub reused: bool,
+}
+
+impl TestSolutions {
+    /// Open the store of `ELM_HOME`, created on the first insertion.
+    pub fn new<P: AsRef<Path>>(elm_home: P) -> Self {
+        Self {
+            dir: Pkg::pubgrub_cache_dir(elm_home).join("test_solutions"),
+        }
+    }
+
+    /// Directory of the stored solutions.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Hash the content of an `elm.json` and a solver strategy into a key.
+    ///
+    /// The `strategy` describes how solutions are found, such as `offline` or `online-newest`,
+    /// since different strategies lead to different solutions of the same `elm.json`.
+    ///
+    /// ```
+    /// # use elm_solve_deps::test_runner::TestSolutions;
+    /// let elm_json = r#"{ "type": "application" }"#;
+    /// let offline = TestSolutions::key(elm_json, "offline");
+    /// assert_eq!(offline, TestSolutions::key(elm_json, "offline"));
+    /// assert_ne!(offline, TestSolutions::key(elm_json, "online-newest"));
+    /// ```
+    pub fn key(elm_json_str: &str, strategy: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(strategy.as_bytes());
+        hasher.update([0]);
+        hasher.update(elm_json_str.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// The stored solution of a key, if any.
+    pub fn get(&self, key: &str) -> Option<CachedSolution> {
+        let s = std::fs::read_to_string(self.entry_path(key)).ok()?;
+        serde_json::from_str(&s).ok()
+    }
+
+    /// Store the solution of a key, replacing the previous one.
+    pub fn insert(&self, key: &str, solution: &CachedSolution) -> Result<(), CacheError> {
+        let s = serde_json::to_string(solution)?;
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.entry_path(key), &s).map_err(|e| e.into())
+    }
+
+    /// Solve the test dependencies of a project, given the content of its `elm.json`.
+    ///
+    /// The stored solution of the same `elm.json` and strategy is returned if it is still valid.
+    /// Otherwise, the project is solved with `solve`, and its solution stored,
+    /// with the dependencies of its packages loaded with `fetch_elm_json`.
+    pub fn solve<Solve, Fetch, E>(
+        &self,
+        elm_json_str: &str,
+        strategy: &str,
+        additional_constraints: &[(Pkg, Constraint)],
+        extra_mode: ExtraMode,
+        solve: Solve,
+        fetch_elm_json: Fetch,
+    ) -> Result<TestSolution, E>
+    where
+        Solve: FnOnce(&ProjectConfig) -> Result<AppDependencies, E>,
+        Fetch: Fn(&Pkg, SemVer) -> Result<PackageConfig, E>,
+        E: From<serde_json::Error> + From<CacheError>,
+    {
+        let project_elm_json: ProjectConfig = serde_json::from_str(elm_json_str)?;
+        let key = Self::key(elm_json_str, strategy);
+        let stored = self.get(&key).and_then(|cached| {
+            let verified =
+                cached.verify(&project_elm_json, true, additional_constraints, extra_mode);
+            verified.cloned()
+        });
+        if let Some(solution) = stored {
+            return Ok(TestSolution {
+                solution,
+                reused: true,
+            });
+        }
+        let solution = solve(&project_elm_json)?;
+        let cached = CachedSolution::record(
+            &project_elm_json,
+            true,
+            additional_constraints,
+            extra_mode,
+            solution.clone(),
+            fetch_elm_json,
+        )?;
+        self.insert(&key, &cached)?;
+        Ok(TestSolution {
+            solution,
+            reused: false,
+        })
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(key).with_extension("json")
+    }
+}
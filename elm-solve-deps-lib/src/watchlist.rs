@@ -0,0 +1,170 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Module notifying about new versions of watched packages.
+//!
+//! Users register the packages they are interested in, such as the ones they depend on
+//! or maintain forks of, into a [`Watchlist`] stored in `ELM_HOME/pubgrub`.
+//! Polling reuses the update of the versions cache, so it only fetches
+//! the package versions published since the last update, and reports all the ones
+//! of watched packages, including the first release of a package
+//! and new patches of older major versions.
+//! The watchlist also remembers the newest version seen of each package,
+//! such that polling reports the newer versions that other commands
+//! added to the versions cache in between.
+
+use pubgrub::version::SemanticVersion as SemVer;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap as Map;
+#[cfg(feature = "fs")]
+use std::collections::BTreeSet as Set;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "fs")]
+use crate::clock::Clock;
+#[cfg(feature = "fs")]
+use crate::fetch::HttpFetch;
+#[cfg(feature = "fs")]
+use crate::pkg_version::CacheError;
+use crate::pkg_version::{Cache, CacheUpdate, PkgVersion};
+use crate::project_config::Pkg;
+
+/// Packages watched for new versions.
+///
+/// ```
+/// # use elm_solve_deps::pkg_version::Cache;
+/// # use elm_solve_deps::project_config::Pkg;
+/// # use elm_solve_deps::watchlist::Watchlist;
+/// let core = Pkg::new("elm", "core");
+/// let mut cache = Cache::new();
+/// cache.cache.insert(core.clone(), [(1, 0, 4).into()].into_iter().collect());
+/// let mut watchlist = Watchlist::new();
+/// watchlist.watch(core.clone());
+/// // Versions existing when a package starts being watched are not new.
+/// watchlist.mark_seen(&cache);
+/// cache.cache.get_mut(&core).unwrap().insert((1, 0, 5).into());
+/// let new_versions = watchlist.new_versions(&cache);
+/// assert_eq!(new_versions.len(), 1);
+/// assert_eq!(new_versions[0].version, (1, 0, 5).into());
+/// watchlist.mark_seen(&cache);
+/// assert!(watchlist.new_versions(&cache).is_empty());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Watchlist {
+    /// The watched packages, with the newest version seen, if any.
+    pub packages: Map<Pkg, Option<SemVer>>,
+}
+
+impl Watchlist {
+    /// Initialize an empty watchlist.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start watching a package.
+    /// Return false if it was already watched.
+    pub fn watch(&mut self, pkg: Pkg) -> bool {
+        if self.packages.contains_key(&pkg) {
+            return false;
+        }
+        self.packages.insert(pkg, None);
+        true
+    }
+
+    /// Stop watching a package.
+    /// Return false if it was not watched.
+    pub fn unwatch(&mut self, pkg: &Pkg) -> bool {
+        self.packages.remove(pkg).is_some()
+    }
+
+    /// Versions of the watched packages newer than the newest ones seen,
+    /// by package and increasing version.
+    ///
+    /// Packages never seen have no new version,
+    /// since all their versions already existed when they started being watched.
+    /// Versions older than the newest ones seen, such as new patches of older major versions,
+    /// are not reported either, only [`Watchlist::added_versions`] knows about them.
+    pub fn new_versions(&self, versions: &Cache) -> Vec<PkgVersion> {
+        (self.packages.iter())
+            .filter_map(|(pkg, seen)| Some((pkg, (*seen)?, versions.cache.get(pkg)?)))
+            .flat_map(|(pkg, seen, versions)| {
+                (versions.range(seen.bump_patch()..)).map(|v| PkgVersion {
+                    author_pkg: pkg.clone(),
+                    version: *v,
+                })
+            })
+            .collect()
+    }
+
+    /// Versions of the watched packages added to the versions cache by an update,
+    /// whatever the versions already seen.
+    pub fn added_versions(&self, update: &CacheUpdate) -> Vec<PkgVersion> {
+        (update.added.iter())
+            .filter(|added| self.packages.contains_key(&added.author_pkg))
+            .cloned()
+            .collect()
+    }
+
+    /// Record the newest versions of the watched packages as seen.
+    pub fn mark_seen(&mut self, versions: &Cache) {
+        for (pkg, seen) in self.packages.iter_mut() {
+            let newest = versions.cache.get(pkg).and_then(|vs| vs.iter().next_back());
+            if let Some(newest) = newest {
+                *seen = Some((*seen).map_or(*newest, |s| s.max(*newest)));
+            }
+        }
+    }
+
+    /// Update the versions cache in `ELM_HOME`, call `notify` for every new version
+    /// of the watched packages since the last poll, and save both the cache and the watchlist.
+    ///
+    /// The versions cache is updated with [`Cache::update_with_resync`],
+    /// and the new versions are its [`Watchlist::added_versions`]
+    /// together with the [`Watchlist::new_versions`] added by other commands since the last poll.
+    #[cfg(feature = "fs")]
+    pub fn poll<P, H, C, F>(
+        &mut self,
+        elm_home: P,
+        remote_base_url: &str,
+        http_fetch: &H,
+        clock: &C,
+        mut notify: F,
+    ) -> Result<(), CacheError>
+    where
+        P: AsRef<Path>,
+        H: HttpFetch + ?Sized,
+        C: Clock + ?Sized,
+        F: FnMut(&PkgVersion),
+    {
+        let mut versions_cache = Cache::load(&elm_home).unwrap_or_default();
+        let update =
+            versions_cache.update_with_resync(&elm_home, remote_base_url, http_fetch, clock)?;
+        versions_cache.save(&elm_home)?;
+        let mut new_versions: Set<PkgVersion> = self.added_versions(&update).into_iter().collect();
+        new_versions.extend(self.new_versions(&versions_cache));
+        new_versions.iter().for_each(&mut notify);
+        self.mark_seen(&versions_cache);
+        self.save(elm_home)
+    }
+
+    /// Load the watchlist from its default location.
+    #[cfg(feature = "fs")]
+    pub fn load<P: AsRef<Path>>(elm_home: P) -> Result<Self, CacheError> {
+        let s = std::fs::read_to_string(Self::file_path(elm_home))?;
+        serde_json::from_str(&s).map_err(|e| e.into())
+    }
+
+    /// Save the watchlist to its default location.
+    #[cfg(feature = "fs")]
+    pub fn save<P: AsRef<Path>>(&self, elm_home: P) -> Result<(), CacheError> {
+        let s = serde_json::to_string(self)?;
+        std::fs::create_dir_all(Pkg::pubgrub_cache_dir(&elm_home))?;
+        std::fs::write(Self::file_path(elm_home), &s).map_err(|e| e.into())
+    }
+
+    /// Path to the file used to store the watchlist.
+    /// ~/.elm/pubgrub/watchlist.json
+    pub fn file_path<P: AsRef<Path>>(elm_home: P) -> PathBuf {
+        Pkg::pubgrub_cache_dir(elm_home).join("watchlist.json")
+    }
+}
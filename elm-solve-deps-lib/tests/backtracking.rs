@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Decisions reported by the solver when it backtracks.
+//!
+//! The newest version of `pick/first`, with the fewest versions, is decided first,
+//! but it requires a version of `dep/x` that no version of `pick/second` accepts.
+//! After trying every version of `pick/second`, the solver backtracks to the oldest
+//! version of `pick/first`, and decides again versions whose configs it already loaded.
+//! Every decision must be reported, including the ones after backtracking,
+//! even though the config of each package version is only loaded once.
+
+mod common;
+
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+use common::{registry_sandbox, Entry};
+use elm_solve_deps::progress::{Progress, ProgressEvent};
+use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+use elm_solve_deps::search_trace::TraceRecorder;
+use elm_solve_deps::solver::ExtraMode;
+
+/// Package versions of the fixed registry, with their dependencies.
+const REGISTRY: &[Entry] = &[
+    ("pick/first", "1.0.0", &[]),
+    ("pick/first", "2.0.0", &[("dep/x", "2.0.0 <= v < 3.0.0")]),
+    ("pick/second", "1.0.0", &[("dep/x", "1.0.0 <= v < 2.0.0")]),
+    ("pick/second", "2.0.0", &[("dep/x", "1.0.0 <= v < 2.0.0")]),
+    ("pick/second", "3.0.0", &[("dep/x", "1.0.0 <= v < 2.0.0")]),
+    ("dep/x", "1.0.0", &[]),
+    ("dep/x", "2.0.0", &[]),
+];
+
+/// The package whose dependencies are solved.
+const PROJECT: &str = r#"{
+    "type": "package",
+    "name": "test/project",
+    "summary": "Project of the backtracking tests",
+    "license": "BSD-3-Clause",
+    "version": "1.0.0",
+    "exposed-modules": [],
+    "elm-version": "0.19.0 <= v < 0.20.0",
+    "dependencies": {
+        "pick/first": "1.0.0 <= v < 3.0.0",
+        "pick/second": "1.0.0 <= v < 4.0.0"
+    },
+    "test-dependencies": {}
+}"#;
+
+/// Decisions of the resolution, in order.
+const DECISIONS: &[(&str, &str)] = &[
+    ("pick/first", "2.0.0"),
+    ("dep/x", "2.0.0"),
+    ("pick/second", "3.0.0"),
+    ("pick/second", "2.0.0"),
+    ("pick/second", "1.0.0"),
+    // Backtracking.
+    ("pick/first", "1.0.0"),
+    ("pick/second", "3.0.0"),
+    ("dep/x", "1.0.0"),
+];
+
+/// Events of the resolution of the project, with a trace recorder of them.
+fn solve() -> (Vec<ProgressEvent>, TraceRecorder) {
+    let sandbox = registry_sandbox(REGISTRY);
+    let project: ProjectConfig = serde_json::from_str(PROJECT).unwrap();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+    let recorder = TraceRecorder::new();
+    let trace_progress = recorder.progress();
+    let progress = Progress::new(move |event| {
+        recorded.lock().unwrap().push(event.clone());
+        trace_progress.emit(event.clone());
+    });
+    let solver = sandbox.offline_solver().with_progress(progress);
+    (solver.solve_deps(&project, false, &[], ExtraMode::default()))
+        .map_err(Box::new)
+        .unwrap();
+    let events = events.lock().unwrap().clone();
+    (events, recorder)
+}
+
+#[test]
+fn decisions_after_backtracking_are_reported() {
+    let (events, _) = solve();
+    let root = Pkg::new("test", "project");
+    let decisions: Vec<(String, String)> = (events.iter())
+        .filter_map(|event| match event {
+            ProgressEvent::Decided { pkg, version, .. } if pkg != &root => {
+                Some((pkg.to_string(), version.to_string()))
+            }
+            _ => None,
+        })
+        .collect();
+    let expected: Vec<(String, String)> = (DECISIONS.iter())
+        .map(|(pkg, version)| (pkg.to_string(), version.to_string()))
+        .collect();
+    assert_eq!(decisions, expected);
+    let finished = events.iter().find_map(|event| match event {
+        ProgressEvent::Finished { solved, decisions } => Some((*solved, *decisions)),
+        _ => None,
+    });
+    assert_eq!(finished, Some((true, DECISIONS.len())));
+}
+
+#[test]
+fn configs_are_loaded_once() {
+    let (events, _) = solve();
+    let loaded = (events.iter())
+        .filter(|event| matches!(event, ProgressEvent::Loaded { .. }))
+        .count();
+    let decided: BTreeSet<&(&str, &str)> = DECISIONS.iter().collect();
+    assert_eq!(loaded, decided.len());
+}
+
+#[test]
+fn trace_counts_backtracks() {
+    let (_, recorder) = solve();
+    let summary = recorder.trace().summarize();
+    assert_eq!(summary.decisions, DECISIONS.len());
+    // pick/first, pick/second and dep/x are each decided once without backtracking.
+    assert_eq!(summary.backtracks, DECISIONS.len() - 3);
+    assert_eq!(summary.hot_spots[0].pkg, Pkg::new("pick", "second"));
+}
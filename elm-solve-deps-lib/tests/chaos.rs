@@ -0,0 +1,272 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Robustness of the offline and online solvers with sources failing some of their calls.
+//!
+//! The installed packages of the offline solver are wrapped in a [`ChaosInstalled`],
+//! and the http client of the online solver in a [`ChaosFetch`],
+//! injecting every kind of [`Fault`], with and without a maximum number of failures.
+//! Every resolution must either find the solution found without failures,
+//! or fail with a typed error of the injected fault.
+//! With a maximum number of failures, retrying must eventually succeed.
+//! The online solver must also recover from every failure of the installed packages,
+//! by loading their configs from the package server instead.
+
+mod common;
+
+use common::{registry_response, registry_sandbox, Entry};
+use elm_solve_deps::chaos::{check_outcome, ChaosConfig, ChaosFetch, ChaosInstalled, Fault};
+use elm_solve_deps::elm_home::Directory;
+use elm_solve_deps::error_code::ErrorCode;
+use elm_solve_deps::fetch::FetchError;
+use elm_solve_deps::project_config::{AppDependencies, ProjectConfig};
+use elm_solve_deps::sandbox::Sandbox;
+use elm_solve_deps::solver::{ExtraMode, Online, PubGrubError, VersionStrategy};
+
+/// Package server of the fixed registry.
+const REMOTE: &str = "http://registry.test";
+
+/// Kinds of failures, each injected alone.
+const FAULTS: [Fault; 3] = [Fault::Io, Fault::MalformedJson, Fault::Timeout];
+
+/// Maximum numbers of failures, unlimited for `None`.
+const MAX_FAULTS: [Option<usize>; 4] = [None, Some(0), Some(1), Some(5)];
+
+/// Seeds of the generators drawing failures, for each configuration.
+const SEEDS: u64 = 20;
+
+/// Package versions of the fixed registry, with their dependencies.
+const REGISTRY: &[Entry] = &[
+    ("elm/core", "1.0.0", &[]),
+    ("elm/core", "1.0.5", &[]),
+    ("elm/json", "1.0.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+    ("elm/json", "1.1.3", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+    (
+        "elm/virtual-dom",
+        "1.0.2",
+        &[
+            ("elm/core", "1.0.0 <= v < 2.0.0"),
+            ("elm/json", "1.0.0 <= v < 2.0.0"),
+        ],
+    ),
+    (
+        "elm/virtual-dom",
+        "1.0.3",
+        &[
+            ("elm/core", "1.0.0 <= v < 2.0.0"),
+            ("elm/json", "1.1.0 <= v < 2.0.0"),
+        ],
+    ),
+    (
+        "elm/html",
+        "1.0.0",
+        &[
+            ("elm/core", "1.0.0 <= v < 2.0.0"),
+            ("elm/json", "1.0.0 <= v < 2.0.0"),
+            ("elm/virtual-dom", "1.0.0 <= v < 2.0.0"),
+        ],
+    ),
+    ("elm/url", "1.0.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+    (
+        "elm/browser",
+        "1.0.2",
+        &[
+            ("elm/core", "1.0.0 <= v < 2.0.0"),
+            ("elm/html", "1.0.0 <= v < 2.0.0"),
+            ("elm/json", "1.1.0 <= v < 2.0.0"),
+            ("elm/url", "1.0.0 <= v < 2.0.0"),
+            ("elm/virtual-dom", "1.0.0 <= v < 2.0.0"),
+        ],
+    ),
+];
+
+/// The package whose dependencies are solved.
+const PROJECT: &str = r#"{
+    "type": "package",
+    "name": "test/project",
+    "summary": "Project of the chaos tests",
+    "license": "BSD-3-Clause",
+    "version": "1.0.0",
+    "exposed-modules": [],
+    "elm-version": "0.19.0 <= v < 0.20.0",
+    "dependencies": {
+        "elm/browser": "1.0.0 <= v < 2.0.0",
+        "elm/core": "1.0.0 <= v < 2.0.0",
+        "elm/html": "1.0.0 <= v < 2.0.0"
+    },
+    "test-dependencies": {}
+}"#;
+
+/// The solution of the project, with the newest versions of the registry.
+const SOLUTION: &[(&str, &str)] = &[
+    ("elm/browser", "1.0.2"),
+    ("elm/core", "1.0.5"),
+    ("elm/html", "1.0.0"),
+    ("elm/json", "1.1.3"),
+    ("elm/url", "1.0.0"),
+    ("elm/virtual-dom", "1.0.3"),
+];
+
+/// Serve the package list and `elm.json` files of the fixed registry.
+fn registry_fetch(url: &str) -> Result<String, FetchError> {
+    registry_response(REMOTE, REGISTRY, url)
+}
+
+/// Fail the test if the solution of the project is not the expected one.
+fn assert_solution(solution: &AppDependencies) {
+    let mut solved: Vec<(String, String)> = (solution.direct.iter())
+        .chain(solution.indirect.iter())
+        .map(|(pkg, version)| (pkg.to_string(), version.to_string()))
+        .collect();
+    let expected: Vec<(String, String)> = (SOLUTION.iter())
+        .map(|(pkg, version)| (pkg.to_string(), version.to_string()))
+        .collect();
+    solved.sort();
+    assert_eq!(solved, expected);
+}
+
+/// Every configuration of failures, with the number of attempts to solve with it,
+/// as many as failures can be injected.
+fn configs() -> impl Iterator<Item = (ChaosConfig, usize)> {
+    FAULTS.into_iter().flat_map(|fault| {
+        MAX_FAULTS.into_iter().flat_map(move |max_faults| {
+            (0..SEEDS).map(move |seed| {
+                let config = ChaosConfig {
+                    rate: 0.3,
+                    seed,
+                    faults: vec![fault],
+                    max_faults,
+                };
+                (config, max_faults.map_or(1, |max| max + 1))
+            })
+        })
+    })
+}
+
+/// Check that a failure is a typed error of one of the injected faults.
+fn assert_injected(err: &(dyn std::error::Error + 'static), config: &ChaosConfig) {
+    let code = ErrorCode::of(err);
+    let injected = (config.faults.iter()).any(|fault| fault.codes().contains(&code));
+    assert!(
+        injected,
+        "Unexpected error {:?} with {:?}: {}",
+        code, config, err
+    );
+}
+
+#[test]
+fn offline_solver_recovers_or_fails_typed() {
+    let sandbox = registry_sandbox(REGISTRY);
+    let project: ProjectConfig = serde_json::from_str(PROJECT).unwrap();
+    let expected = (sandbox.offline_solver())
+        .solve_deps(&project, false, &[], ExtraMode::default())
+        .map_err(Box::new)
+        .unwrap();
+    assert_solution(&expected);
+    for (config, attempts) in configs() {
+        let directory = Directory::new(sandbox.elm_home(), "0.19.1");
+        let installed = ChaosInstalled::new(directory, config.clone());
+        let offline = (sandbox.offline_solver()).with_installed_packages(installed.clone());
+        let mut result = Err(PubGrubError::Failure("No attempt".to_string()));
+        for _ in 0..attempts {
+            result = offline.solve_deps(&project, false, &[], ExtraMode::default());
+            assert_eq!(
+                check_outcome(&result, &expected, &config),
+                Ok(()),
+                "{:?}",
+                config
+            );
+            if result.is_ok() {
+                break;
+            }
+        }
+        if let Some(max) = config.max_faults {
+            assert!(result.is_ok(), "No recovery with {:?}", config);
+            assert!(installed.injected() <= max, "{:?}", config);
+        }
+    }
+}
+
+#[test]
+fn online_solver_recovers_or_fails_typed() {
+    let project: ProjectConfig = serde_json::from_str(PROJECT).unwrap();
+    let sandbox = Sandbox::new("0.19.1").unwrap();
+    let offline = sandbox.offline_solver();
+    let online = Online::new(offline, REMOTE, registry_fetch, VersionStrategy::Newest).unwrap();
+    let expected = (online.solve_deps(&project, false, &[], ExtraMode::default()))
+        .map_err(Box::new)
+        .unwrap();
+    assert_solution(&expected);
+    for (config, attempts) in configs() {
+        let sandbox = Sandbox::new("0.19.1").unwrap();
+        let http_fetch = ChaosFetch::new(registry_fetch, config.clone());
+        let mut solved = false;
+        for _ in 0..attempts {
+            let offline = sandbox.offline_solver();
+            let strategy = VersionStrategy::Newest;
+            let online = match Online::new(offline, REMOTE, http_fetch.clone(), strategy) {
+                Ok(online) => online,
+                Err(err) => {
+                    assert_injected(&err, &config);
+                    continue;
+                }
+            };
+            let result = online.solve_deps(&project, false, &[], ExtraMode::default());
+            assert_eq!(
+                check_outcome(&result, &expected, &config),
+                Ok(()),
+                "{:?}",
+                config
+            );
+            if result.is_ok() {
+                solved = true;
+                break;
+            }
+        }
+        if let Some(max) = config.max_faults {
+            assert!(solved, "No recovery with {:?}", config);
+            assert!(http_fetch.injected() <= max, "{:?}", config);
+        }
+    }
+}
+
+#[test]
+fn online_solver_fetches_the_configs_failing_to_load() {
+    let sandbox = registry_sandbox(REGISTRY);
+    let project: ProjectConfig = serde_json::from_str(PROJECT).unwrap();
+    for fault in FAULTS {
+        let config = ChaosConfig {
+            rate: 1.0,
+            seed: 0,
+            faults: vec![fault],
+            max_faults: None,
+        };
+        let directory = Directory::new(sandbox.elm_home(), "0.19.1");
+        let installed = ChaosInstalled::new(directory, config.clone());
+        let offline = (sandbox.offline_solver()).with_installed_packages(installed.clone());
+        let online = Online::new(offline, REMOTE, registry_fetch, VersionStrategy::Newest).unwrap();
+        let solution = (online.solve_deps(&project, false, &[], ExtraMode::default()))
+            .map_err(Box::new)
+            .unwrap();
+        assert_solution(&solution);
+        assert!(installed.injected() > 0, "{:?}", config);
+    }
+}
+
+#[test]
+fn every_request_failing_gives_typed_errors() {
+    for fault in FAULTS {
+        let config = ChaosConfig {
+            rate: 1.0,
+            seed: 0,
+            faults: vec![fault],
+            max_faults: None,
+        };
+        let sandbox = Sandbox::new("0.19.1").unwrap();
+        let http_fetch = ChaosFetch::new(registry_fetch, config.clone());
+        let strategy = VersionStrategy::Newest;
+        match Online::new(sandbox.offline_solver(), REMOTE, http_fetch, strategy) {
+            Ok(_) => panic!("Updated the versions cache with {:?}", config),
+            Err(err) => assert_injected(&err, &config),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Resolutions of an online solver whose current time is fixed by `SOURCE_DATE_EPOCH`.
+//!
+//! The package server of the fixed registry publishes a second version of `clock/a`
+//! a day before the epoch of the test, and a minimum release age of a week
+//! must keep the first version, while the same age a month later picks the second one.
+
+mod common;
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use common::{registry_response, Entry};
+use elm_solve_deps::clock::{self, Clock, SOURCE_DATE_EPOCH};
+use elm_solve_deps::fetch::FetchError;
+use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+use elm_solve_deps::sandbox::Sandbox;
+use elm_solve_deps::semver::SemVer;
+use elm_solve_deps::solver::{ExtraMode, Online, VersionStrategy};
+
+/// Package server of the fixed registry.
+const REMOTE: &str = "http://registry.test";
+
+/// Package versions of the fixed registry, with their dependencies.
+const REGISTRY: &[Entry] = &[
+    ("elm/core", "1.0.5", &[]),
+    ("clock/a", "1.0.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+    ("clock/a", "2.0.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+];
+
+/// Release times of the package versions, in seconds since the unix epoch.
+const RELEASES: &[(&str, &str, u64)] = &[
+    ("elm/core", "1.0.5", 1_000_000_000),
+    ("clock/a", "1.0.0", 1_000_000_000),
+    ("clock/a", "2.0.0", 1_599_913_600),
+];
+
+/// The epoch of the test, a day after the release of `clock/a` 2.0.0.
+const EPOCH: u64 = 1_600_000_000;
+
+/// The package whose dependencies are solved.
+const PROJECT: &str = r#"{
+    "type": "package",
+    "name": "test/project",
+    "summary": "Project of the clock tests",
+    "license": "BSD-3-Clause",
+    "version": "1.0.0",
+    "exposed-modules": [],
+    "elm-version": "0.19.0 <= v < 0.20.0",
+    "dependencies": {
+        "elm/core": "1.0.0 <= v < 2.0.0",
+        "clock/a": "1.0.0 <= v < 3.0.0"
+    },
+    "test-dependencies": {}
+}"#;
+
+/// Serve the package list, release times and `elm.json` files of the fixed registry.
+fn registry_fetch(url: &str) -> Result<String, FetchError> {
+    for (name, _, _) in REGISTRY {
+        if url == format!("{}/packages/{}/releases.json", REMOTE, name) {
+            let releases: BTreeMap<&str, u64> = (RELEASES.iter())
+                .filter(|(pkg, _, _)| pkg == name)
+                .map(|(_, version, time)| (*version, *time))
+                .collect();
+            return Ok(serde_json::json!(releases).to_string());
+        }
+    }
+    registry_response(REMOTE, REGISTRY, url)
+}
+
+/// Solve the project online, in a fresh sandbox, with the clock of the environment,
+/// and return the version of `clock/a` in the solution.
+fn solve_at(epoch: u64) -> SemVer {
+    std::env::set_var(SOURCE_DATE_EPOCH, epoch.to_string());
+    let clock = clock::from_env().unwrap();
+    assert_eq!(clock.unix_secs(), epoch);
+    let sandbox = Sandbox::new("0.19.1").unwrap();
+    let offline = sandbox.offline_solver().with_clock(clock);
+    let online = Online::new(offline, REMOTE, registry_fetch, VersionStrategy::Newest)
+        .unwrap()
+        .with_min_release_age(Duration::from_secs(7 * 24 * 60 * 60));
+    let project: ProjectConfig = serde_json::from_str(PROJECT).unwrap();
+    let solution = (online.solve_deps(&project, false, &[], ExtraMode::default()))
+        .map_err(Box::new)
+        .unwrap();
+    let version = solution.direct.get(&Pkg::new("clock", "a")).copied();
+    version.expect("Missing clock/a in the solution")
+}
+
+#[test]
+fn source_date_epoch_decides_which_versions_are_old_enough() {
+    assert_eq!(solve_at(EPOCH), SemVer::new(1, 0, 0));
+    assert_eq!(solve_at(EPOCH + 30 * 24 * 60 * 60), SemVer::new(2, 0, 0));
+}
@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Fixed registries shared by the integration tests.
+
+// Each test crate only uses some of the fixtures.
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+
+use elm_solve_deps::fetch::FetchError;
+#[cfg(feature = "fs")]
+use elm_solve_deps::paths;
+#[cfg(feature = "fs")]
+use elm_solve_deps::sandbox::Sandbox;
+
+/// Package name and version, with the constraints of its dependencies.
+pub type Entry = (
+    &'static str,
+    &'static str,
+    &'static [(&'static str, &'static str)],
+);
+
+/// The `elm.json` of a package version of a fixed registry.
+pub fn package_config(name: &str, version: &str, deps: &[(&str, &str)]) -> serde_json::Value {
+    let deps: BTreeMap<&str, &str> = deps.iter().copied().collect();
+    serde_json::json!({
+        "type": "package",
+        "name": name,
+        "summary": "Package of a fixed registry",
+        "license": "BSD-3-Clause",
+        "version": version,
+        "exposed-modules": [],
+        "elm-version": "0.19.0 <= v < 0.20.0",
+        "dependencies": deps,
+        "test-dependencies": {},
+    })
+}
+
+/// Response of a package server at `remote` publishing a fixed registry,
+/// in the order of the registry, to a request of its package lists or of an `elm.json`.
+pub fn registry_response(
+    remote: &str,
+    registry: &[Entry],
+    url: &str,
+) -> Result<String, FetchError> {
+    let path = url.strip_prefix(remote).ok_or(FetchError::NotFound)?;
+    if path == "/all-packages" {
+        let mut all: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for (name, version, _) in registry {
+            all.entry(name).or_default().push(version);
+        }
+        return Ok(serde_json::json!(all).to_string());
+    }
+    if let Some(since) = path.strip_prefix("/all-packages/since/") {
+        let since: usize = since.parse().map_err(|_| FetchError::NotFound)?;
+        let newer: Vec<String> = (registry.iter().skip(since).rev())
+            .map(|(name, version, _)| format!("{}@{}", name, version))
+            .collect();
+        return Ok(serde_json::json!(newer).to_string());
+    }
+    (registry.iter())
+        .find(|(name, version, _)| path == format!("/packages/{}/{}/elm.json", name, version))
+        .map(|(name, version, deps)| package_config(name, version, deps).to_string())
+        .ok_or(FetchError::NotFound)
+}
+
+/// Sandbox where the packages of a fixed registry are installed.
+#[cfg(feature = "fs")]
+pub fn registry_sandbox(registry: &[Entry]) -> Sandbox {
+    let sandbox = Sandbox::new("0.19.1").unwrap();
+    let packages_dir = paths::packages_dir(sandbox.elm_home(), "0.19.1");
+    for (name, version, deps) in registry {
+        let dir = packages_dir.join(name).join(version);
+        std::fs::create_dir_all(&dir).unwrap();
+        let elm_json = package_config(name, version, deps).to_string();
+        std::fs::write(dir.join("elm.json"), elm_json).unwrap();
+    }
+    sandbox
+}
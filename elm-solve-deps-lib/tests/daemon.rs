@@ -0,0 +1,218 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Scheduling of the maintenance of a daemon, and routing of its solving requests
+//! between the offline solver, reading installed packages, and the online solvers,
+//! created on first use with a fixed registry.
+
+mod common;
+
+use std::cell::{Cell, RefCell};
+use std::convert::Infallible;
+use std::time::{Duration, Instant};
+
+use common::{registry_response, registry_sandbox, Entry};
+use elm_solve_deps::daemon::{Daemon, DaemonError, Request, Response, SolveRequest};
+use elm_solve_deps::fetch::FetchError;
+use elm_solve_deps::pkg_version::Cache;
+use elm_solve_deps::project_config::{AppDependencies, Pkg, ProjectConfig};
+use elm_solve_deps::semver::SemVer;
+use elm_solve_deps::solver::{ExtraMode, Online, VersionStrategy};
+
+/// Package server of the fixed registry.
+const REMOTE: &str = "http://registry.test";
+
+/// Package versions of the fixed registry, with their dependencies.
+const REGISTRY: &[Entry] = &[
+    ("elm/core", "1.0.5", &[]),
+    ("daemon/a", "1.0.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+    ("daemon/a", "1.1.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+    ("daemon/b", "1.0.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+];
+
+/// Installed package versions, `daemon/b` is only available online.
+const INSTALLED: &[Entry] = &[
+    ("elm/core", "1.0.5", &[]),
+    ("daemon/a", "1.0.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+];
+
+/// Duration between two updates of the list of existing packages.
+const PERIOD: Duration = Duration::from_secs(300);
+
+fn registry_fetch(url: &str) -> Result<String, FetchError> {
+    registry_response(REMOTE, REGISTRY, url)
+}
+
+/// Request solving a package depending on `elm/core` and the given package.
+fn solve_request(dependency: &str, strategy: Option<VersionStrategy>, offline: bool) -> Request {
+    let elm_json = serde_json::json!({
+        "type": "package",
+        "name": "test/project",
+        "summary": "Project of the daemon tests",
+        "license": "BSD-3-Clause",
+        "version": "1.0.0",
+        "exposed-modules": [],
+        "elm-version": "0.19.0 <= v < 0.20.0",
+        "dependencies": {
+            "elm/core": "1.0.0 <= v < 2.0.0",
+            dependency: "1.0.0 <= v < 2.0.0",
+        },
+        "test-dependencies": {},
+    });
+    let elm_json: ProjectConfig = serde_json::from_value(elm_json).unwrap();
+    Request::Solve(Box::new(SolveRequest {
+        elm_json,
+        test: false,
+        extra: Default::default(),
+        extra_mode: ExtraMode::default(),
+        strategy,
+        offline,
+    }))
+}
+
+/// Version of a package in a solution.
+fn solved_version(response: Result<Response, DaemonError>, pkg: &str) -> SemVer {
+    let solution: AppDependencies = match response {
+        Ok(Response::Solution(solution)) => solution,
+        other => panic!("Expected a solution, got {:?}", other),
+    };
+    let pkg: Pkg = pkg.parse().unwrap();
+    *solution
+        .direct
+        .get(&pkg)
+        .expect("Missing package in the solution")
+}
+
+#[test]
+fn solve_requests_only_go_online_when_needed() {
+    let sandbox = registry_sandbox(INSTALLED);
+    let created = RefCell::new(Vec::new());
+    let mk_online_solver = |strategy| {
+        created.borrow_mut().push(strategy);
+        Online::new(sandbox.offline_solver(), REMOTE, registry_fetch, strategy)
+    };
+    let update_versions = || Ok::<_, Infallible>(Cache::new());
+    let now = Instant::now();
+    let mut daemon = Daemon::new(
+        sandbox.offline_solver(),
+        Cache::new(),
+        mk_online_solver,
+        update_versions,
+        now,
+    );
+
+    // Installed packages are enough, without strategy nor online solver.
+    let offline = daemon.handle(&solve_request("daemon/a", None, false), now);
+    assert_eq!(solved_version(offline, "daemon/a"), SemVer::new(1, 0, 0));
+    assert!(created.borrow().is_empty());
+
+    // Offline requests never fall back to the online solver.
+    let missing = daemon.handle(&solve_request("daemon/b", None, true), now);
+    assert!(matches!(missing, Err(DaemonError::Solve(_))));
+    assert!(created.borrow().is_empty());
+
+    // Failing offline falls back to the newest strategy.
+    let fallback = daemon.handle(&solve_request("daemon/b", None, false), now);
+    assert_eq!(solved_version(fallback, "daemon/b"), SemVer::new(1, 0, 0));
+    assert_eq!(*created.borrow(), [VersionStrategy::Newest]);
+
+    // A strategy always solves online, reusing the online solver of that strategy.
+    let newest = solve_request("daemon/a", Some(VersionStrategy::Newest), false);
+    assert_eq!(
+        solved_version(daemon.handle(&newest, now), "daemon/a"),
+        SemVer::new(1, 1, 0)
+    );
+    let oldest = solve_request("daemon/a", Some(VersionStrategy::Oldest), false);
+    assert_eq!(
+        solved_version(daemon.handle(&oldest, now), "daemon/a"),
+        SemVer::new(1, 0, 0)
+    );
+    let expected = [VersionStrategy::Newest, VersionStrategy::Oldest];
+    assert_eq!(*created.borrow(), expected);
+
+    // Contradictory requests are rejected.
+    let contradictory = solve_request("daemon/a", Some(VersionStrategy::Newest), true);
+    let rejected = daemon.handle(&contradictory, now);
+    assert!(matches!(rejected, Err(DaemonError::OfflineStrategy)));
+}
+
+#[test]
+fn offline_daemon_rejects_online_requests() {
+    let sandbox = registry_sandbox(INSTALLED);
+    let mk_online_solver = |_| -> Result<Online<fn(&str) -> _>, _> {
+        panic!("The offline daemon must not create online solvers")
+    };
+    let update_versions = || -> Result<Cache, Infallible> {
+        panic!("The offline daemon must not update the versions cache")
+    };
+    let now = Instant::now();
+    let mut daemon = Daemon::new(
+        sandbox.offline_solver(),
+        Cache::new(),
+        mk_online_solver,
+        update_versions,
+        now,
+    )
+    .with_offline(true)
+    .with_refresh_period(PERIOD);
+    let online = daemon.handle(&solve_request("daemon/b", None, false), now);
+    assert!(matches!(online, Err(DaemonError::OfflineOnly)));
+    let offline = daemon.handle(&solve_request("daemon/a", None, true), now);
+    assert_eq!(solved_version(offline, "daemon/a"), SemVer::new(1, 0, 0));
+    // Nothing to maintain, polling again in a full period.
+    assert_eq!(daemon.poll(now + 10 * PERIOD).unwrap(), PERIOD);
+}
+
+#[test]
+fn poll_schedules_the_updates_of_the_versions_cache_and_online_solvers() {
+    let sandbox = registry_sandbox(INSTALLED);
+    let mk_online_solver =
+        |strategy| Online::new(sandbox.offline_solver(), REMOTE, registry_fetch, strategy);
+    let updates = Cell::new(0);
+    let update_versions = || {
+        updates.set(updates.get() + 1);
+        let mut cache = Cache::new();
+        cache.update(REMOTE, &registry_fetch)?;
+        Ok::<_, elm_solve_deps::pkg_version::CacheError>(cache)
+    };
+    let start = Instant::now();
+    let at = |secs: u64| start + Duration::from_secs(secs);
+    let mut daemon = Daemon::new(
+        sandbox.offline_solver(),
+        Cache::new(),
+        mk_online_solver,
+        update_versions,
+        start,
+    )
+    .with_refresh_period(PERIOD);
+    let versions = Request::Versions("daemon/a".parse().unwrap());
+    assert!(matches!(
+        daemon.handle(&versions, start),
+        Err(DaemonError::UnknownPackage(_))
+    ));
+
+    // Nothing is due before a period since the creation of the daemon.
+    assert_eq!(daemon.poll(at(100)).unwrap(), Duration::from_secs(200));
+    assert_eq!(updates.get(), 0);
+
+    // An online solver created at 200 is due at 500, after the versions cache at 300.
+    let online = solve_request("daemon/a", Some(VersionStrategy::Newest), false);
+    daemon.handle(&online, at(200)).unwrap();
+    assert_eq!(daemon.poll(at(250)).unwrap(), Duration::from_secs(50));
+
+    // The versions cache is updated at 300, and next due at 600, after the online solver.
+    assert_eq!(daemon.poll(at(300)).unwrap(), Duration::from_secs(200));
+    assert_eq!(updates.get(), 1);
+    let expected: Vec<SemVer> = vec![(1, 0, 0).into(), (1, 1, 0).into()];
+    match daemon.handle(&versions, at(300)) {
+        Ok(Response::Versions(versions)) => {
+            assert_eq!(versions.into_iter().collect::<Vec<_>>(), expected)
+        }
+        other => panic!("Expected versions, got {:?}", other),
+    }
+
+    // The online solver is refreshed at 500, late polls catch up right away.
+    assert_eq!(daemon.poll(at(550)).unwrap(), Duration::from_secs(50));
+    assert_eq!(updates.get(), 1);
+    assert_eq!(daemon.poll(at(600)).unwrap(), Duration::from_secs(250));
+    assert_eq!(updates.get(), 2);
+}
@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Checks of the `registry.dat` of the elm compiler by the doctor.
+//!
+//! The elm compiler only refreshes its `registry.dat` when it needs a version it does not know,
+//! so a healthy `ELM_HOME` routinely has a `registry.dat` knowing fewer versions
+//! than the versions cache of the dependency solver.
+//! Only a `registry.dat` knowing fewer versions than are installed is a problem,
+//! and it is never removed, the elm compiler cannot work offline without it.
+
+mod common;
+
+use std::path::PathBuf;
+
+use common::{package_config, Entry};
+use elm_solve_deps::doctor::{diagnose, Problem};
+use elm_solve_deps::paths;
+use elm_solve_deps::pkg_version::Cache;
+use elm_solve_deps::sandbox::Sandbox;
+
+/// Installed package versions, with their dependencies.
+const INSTALLED: &[Entry] = &[
+    ("elm/core", "1.0.5", &[]),
+    ("elm/json", "1.1.3", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+];
+
+/// Sandbox with installed packages, a versions cache of the whole package server,
+/// and a `registry.dat` knowing the given number of package versions.
+fn sandbox_with_registry(registry_versions: u64) -> (Sandbox, PathBuf) {
+    let sandbox = Sandbox::new("0.19.1").unwrap();
+    let packages_dir = paths::packages_dir(sandbox.elm_home(), "0.19.1");
+    let mut cache = Cache::new();
+    for (name, version, deps) in INSTALLED {
+        let dir = packages_dir.join(name).join(version);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        let elm_json = package_config(name, version, deps).to_string();
+        std::fs::write(dir.join("elm.json"), elm_json).unwrap();
+        (cache.cache.entry(name.parse().unwrap()))
+            .or_default()
+            .insert(version.parse().unwrap());
+    }
+    // Versions published since elm last refreshed registry.dat.
+    for version in ["1.0.0", "1.1.0", "1.2.0"] {
+        (cache.cache.entry("new/pkg".parse().unwrap()))
+            .or_default()
+            .insert(version.parse().unwrap());
+    }
+    cache.save(sandbox.elm_home()).unwrap();
+    let registry_path = packages_dir.join("registry.dat");
+    std::fs::write(&registry_path, registry_versions.to_be_bytes()).unwrap();
+    (sandbox, registry_path)
+}
+
+#[test]
+fn registry_behind_the_versions_cache_is_healthy() {
+    let (sandbox, _) = sandbox_with_registry(INSTALLED.len() as u64);
+    assert_eq!(diagnose(sandbox.elm_home(), "0.19.1"), Vec::new());
+}
+
+#[test]
+fn registry_behind_the_installed_packages_is_reported_but_kept() {
+    let (sandbox, registry_path) = sandbox_with_registry(1);
+    let problems = diagnose(sandbox.elm_home(), "0.19.1");
+    let expected = Problem::MismatchedRegistry {
+        path: registry_path.clone(),
+        registry_versions: Some(1),
+        installed_versions: INSTALLED.len() as u64,
+    };
+    assert_eq!(problems, vec![expected]);
+    assert!(!problems[0].fix(sandbox.elm_home()).unwrap());
+    assert!(registry_path.exists());
+}
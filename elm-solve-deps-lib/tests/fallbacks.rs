@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Package configs fetched from the GitHub fallbacks when the package server fails.
+//!
+//! The contents API of GitHub serves base64-encoded files, with line breaks,
+//! which must decode back to the original `elm.json`, whatever its padding.
+//! Configs of the fallbacks are not the ones of the package server,
+//! so they must not be cached as if they were.
+
+use std::str::FromStr;
+
+use elm_solve_deps::fetch::FetchError;
+use elm_solve_deps::pkg_version::{ConfigFallback, PkgVersion};
+use elm_solve_deps::sandbox::Sandbox;
+
+/// The package server, always failing.
+const REMOTE: &str = "http://down.test";
+
+/// Response of the contents API of GitHub for a file,
+/// base64-encoded with line breaks every 60 characters.
+fn github_contents(file: &str) -> String {
+    let encoded = base64::encode(file);
+    let lines: Vec<&str> = (encoded.as_bytes().chunks(60))
+        .map(|line| std::str::from_utf8(line).unwrap())
+        .collect();
+    serde_json::json!({ "content": lines.join("\n") + "\n" }).to_string()
+}
+
+#[test]
+fn github_api_contents_round_trip() {
+    let elm_json = r#"{"type": "package", "name": "elm/core"}"#;
+    // Lengths covering no padding, one and two padding characters.
+    for len in [0, 1, 2, 3, 4, 5, elm_json.len()] {
+        let file = &elm_json[..len];
+        let extracted = ConfigFallback::GitHubApi.extract(github_contents(file));
+        assert_eq!(extracted.unwrap(), file, "{} bytes", len);
+    }
+}
+
+#[test]
+fn github_api_contents_with_invalid_padding_fail() {
+    for content in ["YQ==YQ==", "YQ=", "Y", "YQ===", "YQ*="] {
+        let body = serde_json::json!({ "content": content }).to_string();
+        assert!(
+            ConfigFallback::GitHubApi.extract(body).is_err(),
+            "{}",
+            content
+        );
+    }
+    let body = serde_json::json!({ "content": "YQ" }).to_string();
+    assert_eq!(ConfigFallback::GitHubApi.extract(body).unwrap(), "a");
+}
+
+#[test]
+fn fallback_configs_are_not_cached() {
+    let sandbox = Sandbox::new("0.19.1").unwrap();
+    let core = PkgVersion::from_str("elm/core@1.0.5").unwrap();
+    let elm_json = serde_json::json!({
+        "type": "package",
+        "name": "elm/core",
+        "summary": "Elm's standard libraries",
+        "license": "BSD-3-Clause",
+        "version": "1.0.5",
+        "exposed-modules": [],
+        "elm-version": "0.19.0 <= v < 0.20.0",
+        "dependencies": {},
+        "test-dependencies": {},
+    })
+    .to_string();
+    let fetch = |url: &str| {
+        if url == ConfigFallback::GitHubApi.url(&core) {
+            Ok(github_contents(&elm_json))
+        } else {
+            Err(FetchError::Timeout)
+        }
+    };
+    let fallbacks = [ConfigFallback::GitHubRaw, ConfigFallback::GitHubApi];
+    let (config, fallback) = core
+        .fetch_config_with_fallbacks(sandbox.elm_home(), REMOTE, &fallbacks, &fetch)
+        .unwrap();
+    assert_eq!(fallback, Some(ConfigFallback::GitHubApi));
+    assert_eq!(config, serde_json::from_str(&elm_json).unwrap());
+    assert!(core.load_from_cache(sandbox.elm_home()).is_err());
+}
@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Package configs of a federation of package servers publishing the same package version.
+//!
+//! Both package servers publish `fed/a` 1.0.0, but only the corporate one
+//! makes it depend on `fed/b`.
+//! Each package server must get its own config cache, such that the config
+//! fetched from one of them is never used when the other one takes precedence.
+
+mod common;
+
+use std::str::FromStr;
+
+use common::{registry_response, Entry};
+use elm_solve_deps::federation::{Federation, Remote};
+use elm_solve_deps::fetch::FetchError;
+use elm_solve_deps::project_config::{AppDependencies, Pkg, ProjectConfig};
+use elm_solve_deps::sandbox::Sandbox;
+use elm_solve_deps::solver::{ExtraMode, Online, VersionStrategy};
+
+/// The main package server.
+const MAIN: &str = "http://main.test";
+
+/// The corporate package server.
+const CORP: &str = "http://corp.test";
+
+/// Package versions published on the main package server, with their dependencies.
+const MAIN_REGISTRY: &[Entry] = &[
+    ("elm/core", "1.0.5", &[]),
+    ("fed/a", "1.0.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+    ("fed/b", "1.0.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+];
+
+/// Package versions published on the corporate package server, with their dependencies.
+const CORP_REGISTRY: &[Entry] = &[(
+    "fed/a",
+    "1.0.0",
+    &[
+        ("elm/core", "1.0.0 <= v < 2.0.0"),
+        ("fed/b", "1.0.0 <= v < 2.0.0"),
+    ],
+)];
+
+/// The package whose dependencies are solved.
+const PROJECT: &str = r#"{
+    "type": "package",
+    "name": "test/project",
+    "summary": "Project of the federation tests",
+    "license": "BSD-3-Clause",
+    "version": "1.0.0",
+    "exposed-modules": [],
+    "elm-version": "0.19.0 <= v < 0.20.0",
+    "dependencies": {
+        "elm/core": "1.0.0 <= v < 2.0.0",
+        "fed/a": "1.0.0 <= v < 2.0.0"
+    },
+    "test-dependencies": {}
+}"#;
+
+/// Serve the registries of both package servers.
+fn federation_fetch(url: &str) -> Result<String, FetchError> {
+    registry_response(MAIN, MAIN_REGISTRY, url)
+        .or_else(|_| registry_response(CORP, CORP_REGISTRY, url))
+}
+
+/// Solve the project online, with `fed/a` preferably from the package server `preferred`.
+fn solve(sandbox: &Sandbox, preferred: &str) -> AppDependencies {
+    let corp = Remote::from_str(&format!("corp={}", CORP)).unwrap();
+    let federation = Federation::new()
+        .with_remote(corp)
+        .with_precedence(Pkg::new("fed", "a"), preferred);
+    let offline = sandbox.offline_solver();
+    let online = Online::new(offline, MAIN, federation_fetch, VersionStrategy::Newest)
+        .and_then(|online| online.with_federation(federation))
+        .unwrap();
+    let project: ProjectConfig = serde_json::from_str(PROJECT).unwrap();
+    (online.solve_deps(&project, false, &[], ExtraMode::default()))
+        .map_err(Box::new)
+        .unwrap()
+}
+
+#[test]
+fn each_package_server_has_its_own_configs() {
+    let sandbox = Sandbox::new("0.19.1").unwrap();
+    let fed_b = Pkg::new("fed", "b");
+    assert!(solve(&sandbox, "corp").indirect.contains_key(&fed_b));
+    assert!(!solve(&sandbox, "main").indirect.contains_key(&fed_b));
+    assert!(solve(&sandbox, "corp").indirect.contains_key(&fed_b));
+}
@@ -0,0 +1,12 @@
+{
+
+  "elm/core" = {
+    sha256 = "0jnfkbsi1gh74szci41k2ax0ywaxslmmq4grrgmnn8biw99gzipq";
+    version = "1.0.5";
+  };
+
+  "elm/json" = {
+    sha256 = "1znlk8g8cph20pqbx9fmjr7f16nn3rn72lsbdh0imi24jnk3j9j6";
+    version = "1.1.3";
+  };
+}
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Dependency paths in graphs with many paths not reaching the target.
+//!
+//! A chain of diamonds has a number of paths exponential in its length.
+//! Searching paths to a package the chain cannot reach must not explore them.
+
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::graph::{paths_between, DependencyGraph, PathLimits};
+use elm_solve_deps::project_config::Pkg;
+
+/// Number of diamonds of the chain, far too many to explore all paths.
+const DIAMONDS: usize = 40;
+
+fn any() -> Constraint {
+    "1.0.0 <= v < 2.0.0".parse().unwrap()
+}
+
+/// Graph where `test/root` depends on `elm/core` and on a chain of diamonds,
+/// whose last package depends on `test/end`.
+fn diamonds() -> DependencyGraph {
+    let pkg = |name: &str| Pkg::new("test", name);
+    let mut graph = DependencyGraph::new();
+    let root_deps = vec![(Pkg::new("elm", "core"), any()), (pkg("d0"), any())];
+    graph.add(pkg("root"), None, root_deps);
+    for i in 0..DIAMONDS {
+        let sides = [pkg(&format!("left{}", i)), pkg(&format!("right{}", i))];
+        let deps: Vec<_> = sides.iter().map(|side| (side.clone(), any())).collect();
+        graph.add(pkg(&format!("d{}", i)), None, deps);
+        for side in sides {
+            graph.add(side, None, vec![(pkg(&format!("d{}", i + 1)), any())]);
+        }
+    }
+    let end = vec![(pkg("end"), any())];
+    graph.add(pkg(&format!("d{}", DIAMONDS)), None, end);
+    graph
+}
+
+#[test]
+fn unreachable_subgraphs_are_not_explored() {
+    let limits = PathLimits {
+        max_depth: 3 * DIAMONDS,
+        max_paths: 10,
+    };
+    let root = Pkg::new("test", "root");
+    let paths = paths_between(&root, &Pkg::new("elm", "core"), &diamonds(), limits);
+    let paths: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+    assert_eq!(paths, ["test/root -> elm/core"]);
+    let unknown = paths_between(&root, &Pkg::new("elm", "json"), &diamonds(), limits);
+    assert!(unknown.is_empty());
+}
+
+#[test]
+fn paths_through_the_diamonds_are_found() {
+    let limits = PathLimits {
+        max_depth: 3 * DIAMONDS,
+        max_paths: 2,
+    };
+    let (root, end) = (Pkg::new("test", "root"), Pkg::new("test", "end"));
+    let paths = paths_between(&root, &end, &diamonds(), limits);
+    assert_eq!(paths.len(), 2);
+    for path in paths {
+        assert_eq!(path.steps.len(), 2 * DIAMONDS + 3);
+    }
+}
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Health of the packages of a solution, from the cached release times of their versions.
+//!
+//! Release times are shared with the minimum release age of online solvers,
+//! and only fetched again when the versions cache knows versions they do not list.
+
+use std::cell::Cell;
+use std::time::{Duration, SystemTime};
+
+use elm_solve_deps::clock::FixedClock;
+use elm_solve_deps::fetch::FetchError;
+use elm_solve_deps::health::{self, Health, HealthThresholds};
+use elm_solve_deps::project_config::{AppDependencies, Pkg};
+use elm_solve_deps::release_age::ReleaseTimes;
+use elm_solve_deps::sandbox::Sandbox;
+use elm_solve_deps::semver::SemVer;
+
+/// Package server of the release times.
+const REMOTE: &str = "http://registry.test";
+
+/// One year, in seconds.
+const YEAR: u64 = 365 * 24 * 60 * 60;
+
+/// Ensure the release times of `elm/core` versions, and count the requests.
+fn ensure(sandbox: &Sandbox, versions: &[SemVer], requests: &Cell<usize>) -> ReleaseTimes {
+    let fetch = |url: &str| -> Result<String, FetchError> {
+        assert_eq!(url, format!("{}/packages/elm/core/releases.json", REMOTE));
+        requests.set(requests.get() + 1);
+        Ok(format!(r#"{{ "1.0.4": {}, "1.0.5": {} }}"#, YEAR, 3 * YEAR))
+    };
+    let mut release_times = ReleaseTimes::new();
+    let core = Pkg::new("elm", "core");
+    (release_times.ensure(sandbox.elm_home(), &core, versions, &[REMOTE], &fetch)).unwrap();
+    release_times
+}
+
+#[test]
+fn release_times_are_cached_until_new_versions() {
+    let sandbox = Sandbox::new("0.19.1").unwrap();
+    let requests = Cell::new(0);
+    let versions = [SemVer::new(1, 0, 4), SemVer::new(1, 0, 5)];
+    ensure(&sandbox, &versions, &requests);
+    let release_times = ensure(&sandbox, &versions, &requests);
+    assert_eq!(requests.get(), 1);
+
+    let mut solution = AppDependencies::default();
+    solution.direct.insert(Pkg::new("elm", "core"), versions[1]);
+    let dependents = Default::default();
+    let thresholds = HealthThresholds::default();
+    let assess = |now: u64| {
+        let clock = FixedClock(SystemTime::UNIX_EPOCH + Duration::from_secs(now));
+        health::assess(&solution, &release_times, &dependents, &thresholds, &clock)
+    };
+    let assessed = assess(4 * YEAR);
+    assert_eq!(assessed[0].release_count, Some(2));
+    assert_eq!(assessed[0].last_release, Some(3 * YEAR));
+    assert_eq!(assessed[0].health, Health::Active);
+    assert_eq!(assess(6 * YEAR)[0].health, Health::Abandoned);
+
+    ensure(&sandbox, &[SemVer::new(1, 0, 6)], &requests);
+    assert_eq!(requests.get(), 2);
+}
@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Exchanges of the versions cache with an `ELM_HOME` of elm-tooling.
+//!
+//! Its index is the `registry.dat` of the elm compiler,
+//! and the packages installed there are imported too, even if missing from the index.
+
+mod common;
+
+use common::{registry_sandbox, Entry};
+use elm_solve_deps::interop::{self, CacheFormat};
+use elm_solve_deps::pkg_version::Cache;
+use elm_solve_deps::project_config::Pkg;
+use elm_solve_deps::sandbox::Sandbox;
+use elm_solve_deps::semver::SemVer;
+
+/// Package versions installed in the `ELM_HOME`.
+const INSTALLED: &[Entry] = &[("elm/core", "1.0.5", &[])];
+
+/// Versions cache with every version of `elm/core` and `elm/json`.
+fn versions_cache() -> Cache {
+    let mut cache = Cache::new();
+    let core = [SemVer::new(1, 0, 4), SemVer::new(1, 0, 5)];
+    let json = [SemVer::new(1, 1, 3), SemVer::new(256, 0, 0)];
+    cache.cache.insert(Pkg::new("elm", "core"), core.into());
+    cache.cache.insert(Pkg::new("elm", "json"), json.into());
+    cache
+}
+
+#[test]
+fn elm_tooling_home_round_trips() {
+    let sandbox = Sandbox::new("0.19.1").unwrap();
+    let cache = versions_cache();
+    interop::export(&cache, sandbox.elm_home(), CacheFormat::ElmTooling).unwrap();
+    let registry = sandbox.elm_home().join("0.19.1/packages/registry.dat");
+    let imported = interop::import(&registry, CacheFormat::ElmJson).unwrap();
+    assert_eq!(imported.cache, cache.cache);
+    let imported = interop::import(sandbox.elm_home(), CacheFormat::ElmTooling).unwrap();
+    assert_eq!(imported.cache, cache.cache);
+}
+
+#[test]
+fn elm_tooling_home_imports_installed_packages() {
+    let sandbox = registry_sandbox(INSTALLED);
+    let imported = interop::import(sandbox.elm_home(), CacheFormat::ElmTooling).unwrap();
+    let versions: Vec<_> = imported.cache[&Pkg::new("elm", "core")].iter().collect();
+    assert_eq!(versions, [&SemVer::new(1, 0, 5)]);
+
+    let mut cache = Cache::new();
+    cache
+        .cache
+        .insert(Pkg::new("elm", "json"), [SemVer::new(1, 1, 3)].into());
+    interop::export(&cache, sandbox.elm_home(), CacheFormat::ElmTooling).unwrap();
+    let imported = interop::import(sandbox.elm_home(), CacheFormat::ElmTooling).unwrap();
+    assert_eq!(imported.cache.len(), 2);
+}
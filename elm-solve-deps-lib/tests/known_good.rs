@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Known good sets restricting packages on top of the policies of the user.
+//!
+//! The known good set pins `elm/json` to its version 1.1.2.
+//! A policy of the user excluding that version, like a vulnerable one,
+//! must keep it excluded, instead of being replaced by the pin of the set.
+
+mod common;
+
+use std::collections::BTreeSet;
+
+use common::{registry_sandbox, Entry};
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::error_code::{Coded, ErrorCode};
+use elm_solve_deps::known_good::KnownGoodSet;
+use elm_solve_deps::policy::{Policies, Policy};
+use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+use elm_solve_deps::semver::SemVer;
+use elm_solve_deps::solver::ExtraMode;
+
+/// Package versions of the fixed registry, with their dependencies.
+const REGISTRY: &[Entry] = &[
+    ("elm/core", "1.0.5", &[]),
+    ("elm/json", "1.1.2", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+    ("elm/json", "1.1.3", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+];
+
+/// The known good set.
+const SET: &str = r#"{ "name": "test-set", "packages": { "elm/json": "1.1.2" } }"#;
+
+/// The package whose dependencies are solved.
+const PROJECT: &str = r#"{
+    "type": "package",
+    "name": "test/project",
+    "summary": "Project of the known good sets tests",
+    "license": "BSD-3-Clause",
+    "version": "1.0.0",
+    "exposed-modules": [],
+    "elm-version": "0.19.0 <= v < 0.20.0",
+    "dependencies": {
+        "elm/core": "1.0.0 <= v < 2.0.0",
+        "elm/json": "1.0.0 <= v < 2.0.0"
+    },
+    "test-dependencies": {}
+}"#;
+
+/// Policy of the user excluding versions of `elm/json`.
+fn exclude(constraint: &str) -> Policy {
+    Policy {
+        exclude: vec![constraint.parse::<Constraint>().unwrap()],
+        reason: Some("vulnerable".to_string()),
+        ..Policy::default()
+    }
+}
+
+/// Policies of the user, restricted by the known good set.
+fn restricted(user_policies: Policies) -> Policies {
+    let set: KnownGoodSet = serde_json::from_str(SET).unwrap();
+    let mut policies = user_policies;
+    set.restrict(&mut policies, &BTreeSet::new());
+    policies
+}
+
+#[test]
+fn user_policies_are_kept() {
+    let json = Pkg::new("elm", "json");
+    let mut user_policies = Policies::new();
+    user_policies.insert(json.clone(), exclude("1.1.2 <= v <= 1.1.2"));
+    let policies = restricted(user_policies);
+    assert!(!policies.allows(&json, &SemVer::new(1, 1, 2)));
+    assert!(!policies.allows(&json, &SemVer::new(1, 1, 3)));
+    let reason = policies.policies[&json].reason.as_deref();
+    assert_eq!(reason, Some("vulnerable, in the known good set test-set"));
+}
+
+#[test]
+fn excluded_versions_of_the_set_are_not_picked() {
+    let sandbox = registry_sandbox(REGISTRY);
+    let project: ProjectConfig = serde_json::from_str(PROJECT).unwrap();
+    let solve = |user_policies| {
+        let offline = sandbox
+            .offline_solver()
+            .with_policies(restricted(user_policies));
+        (offline.solve_deps(&project, false, &[], ExtraMode::default())).map_err(Box::new)
+    };
+
+    let solution = solve(Policies::new()).unwrap();
+    let json = solution.direct.get(&Pkg::new("elm", "json"));
+    assert_eq!(json, Some(&SemVer::new(1, 1, 2)));
+
+    let mut user_policies = Policies::new();
+    user_policies.insert(Pkg::new("elm", "json"), exclude("1.1.2 <= v <= 1.1.2"));
+    let err = solve(user_policies).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::NoSolution);
+}
@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Exports for Nix in the formats of elm2nix, against fixtures.
+//!
+//! The fixtures were generated independently of this crate:
+//! `elm-srcs.nix` with the sha256 of the unpacked tarballs served below,
+//! hashed as Nix archives like `nix-prefetch-url --unpack` does,
+//! and `registry.dat` in the binary format of the elm compiler.
+
+use std::cell::Cell;
+use std::io::Write;
+
+use elm_solve_deps::fetch::{FetchError, Frozen, HttpFetch, Response};
+use elm_solve_deps::nix::{self, elm_srcs};
+use elm_solve_deps::pkg_version::Cache;
+use elm_solve_deps::project_config::{AppDependencies, Pkg};
+use elm_solve_deps::sandbox::Sandbox;
+use elm_solve_deps::semver::SemVer;
+
+/// Expected `elm-srcs.nix` of the solution of the fixture.
+const ELM_SRCS: &str = include_str!("fixtures/nix/elm-srcs.nix");
+
+/// Expected `registry.dat` of the versions cache of the fixture.
+const REGISTRY_DAT: &[u8] = include_bytes!("fixtures/nix/registry.dat");
+
+/// Content of a tarball entry.
+enum Content {
+    Directory,
+    File(u32, &'static str),
+    Symlink(&'static str),
+}
+
+/// Entries of the GitHub tarball of `elm/core` 1.0.5, in archive order.
+/// Directories are not always listed before their files.
+const CORE_TARBALL: &[(&str, Content)] = &[
+    ("core-1.0.5/", Content::Directory),
+    (
+        "core-1.0.5/elm.json",
+        Content::File(0o644, "{\"type\": \"package\", \"name\": \"elm/core\"}\n"),
+    ),
+    (
+        "core-1.0.5/src/Basics.elm",
+        Content::File(0o644, "module Basics exposing (..)\n"),
+    ),
+    ("core-1.0.5/src/", Content::Directory),
+    (
+        "core-1.0.5/tests/run-tests.sh",
+        Content::File(0o755, "#!/bin/sh\nelm-test\n"),
+    ),
+    ("core-1.0.5/LICENSE.md", Content::Symlink("LICENSE")),
+    ("core-1.0.5/LICENSE", Content::File(0o644, "BSD-3-Clause\n")),
+];
+
+/// Entries of the GitHub tarball of `elm/json` 1.1.3, in archive order.
+const JSON_TARBALL: &[(&str, Content)] = &[
+    ("json-1.1.3/", Content::Directory),
+    (
+        "json-1.1.3/src/Json/Decode.elm",
+        Content::File(0o644, "module Json.Decode exposing (..)\n"),
+    ),
+    (
+        "json-1.1.3/elm.json",
+        Content::File(0o644, "{\"type\": \"package\", \"name\": \"elm/json\"}\n"),
+    ),
+];
+
+/// Build a gzipped tarball, with the comment GitHub adds to its tarballs.
+fn tarball(entries: &[(&str, Content)]) -> Vec<u8> {
+    let gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let mut builder = tar::Builder::new(gz);
+    let comment = "52 comment=0123456789abcdef0123456789abcdef01234567\n";
+    let mut header = tar::Header::new_ustar();
+    header.set_entry_type(tar::EntryType::XGlobalHeader);
+    header.set_size(comment.len() as u64);
+    (builder.append_data(&mut header, "pax_global_header", comment.as_bytes())).unwrap();
+    for (path, content) in entries {
+        let mut header = tar::Header::new_gnu();
+        match content {
+            Content::Directory => {
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_mode(0o755);
+                header.set_size(0);
+                builder.append_data(&mut header, path, &[][..]).unwrap();
+            }
+            Content::File(mode, text) => {
+                header.set_mode(*mode);
+                header.set_size(text.len() as u64);
+                builder
+                    .append_data(&mut header, path, text.as_bytes())
+                    .unwrap();
+            }
+            Content::Symlink(target) => {
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_mode(0o777);
+                header.set_size(0);
+                builder.append_link(&mut header, path, target).unwrap();
+            }
+        }
+    }
+    let mut gz = builder.into_inner().unwrap();
+    gz.flush().unwrap();
+    gz.finish().unwrap()
+}
+
+/// GitHub, serving the tarballs of the fixture, and counting the downloads.
+struct GitHub {
+    downloads: Cell<usize>,
+}
+
+impl HttpFetch for GitHub {
+    fn get(&self, _url: &str) -> Result<Response, FetchError> {
+        Err(FetchError::NotFound)
+    }
+
+    fn fetch_bytes(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        self.downloads.set(self.downloads.get() + 1);
+        match url {
+            "https://github.com/elm/core/archive/1.0.5.tar.gz" => Ok(tarball(CORE_TARBALL)),
+            "https://github.com/elm/json/archive/1.1.3.tar.gz" => Ok(tarball(JSON_TARBALL)),
+            _ => Err(FetchError::NotFound),
+        }
+    }
+}
+
+#[test]
+fn elm_srcs_pins_the_sha256_of_unpacked_tarballs() {
+    let sandbox = Sandbox::new("0.19.1").unwrap();
+    let mut solution = AppDependencies::default();
+    solution
+        .direct
+        .insert(Pkg::new("elm", "core"), SemVer::new(1, 0, 5));
+    solution
+        .indirect
+        .insert(Pkg::new("elm", "json"), SemVer::new(1, 1, 3));
+    let github = GitHub {
+        downloads: Cell::new(0),
+    };
+    let sources = nix::fetch_sources(&solution, sandbox.elm_home(), &github).unwrap();
+    assert_eq!(elm_srcs(&sources), ELM_SRCS);
+    assert_eq!(github.downloads.get(), 2);
+
+    // Hashed tarballs are not downloaded again.
+    let cached = nix::fetch_sources(&solution, sandbox.elm_home(), &Frozen).unwrap();
+    assert_eq!(cached, sources);
+}
+
+#[test]
+fn tarball_without_a_single_top_directory_is_rejected() {
+    let flat = tarball(&[("elm.json", Content::File(0o644, "{}"))]);
+    assert!(nix::unpacked_sha256(&flat).is_err());
+}
+
+#[test]
+fn registry_dat_lists_the_versions_cache() {
+    let mut cache = Cache::new();
+    for (pkg, version) in [
+        ("elm/core", "1.0.0"),
+        ("elm/core", "1.0.5"),
+        ("elm/json", "1.1.3"),
+        ("huge/versions", "1.0.0"),
+        ("huge/versions", "256.0.0"),
+    ] {
+        (cache.cache.entry(pkg.parse().unwrap()))
+            .or_default()
+            .insert(version.parse().unwrap());
+    }
+    assert_eq!(nix::registry_dat(&cache), REGISTRY_DAT);
+}
@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Resolutions with optional extras, skipped only when they have no solution.
+//!
+//! The fixed registry has an optional extra fitting with the project,
+//! one requiring a version of `elm/core` that is not installed,
+//! and one whose installed `elm.json` is corrupted.
+//! The second one must be skipped, while loading the third one must fail the resolution.
+
+mod common;
+
+use common::{registry_sandbox, Entry};
+use elm_solve_deps::error_code::{Coded, ErrorCode};
+use elm_solve_deps::optional::{solve_with_optional_extras, OptionalExtra, OptionalSolution};
+use elm_solve_deps::paths;
+use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+use elm_solve_deps::sandbox::Sandbox;
+use elm_solve_deps::semver::SemVer;
+use elm_solve_deps::solver::{ExtraMode, PubGrubError};
+
+/// Package versions of the fixed registry, with their dependencies.
+const REGISTRY: &[Entry] = &[
+    ("elm/core", "1.0.5", &[]),
+    ("extra/fits", "1.0.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+    (
+        "extra/conflicts",
+        "1.0.0",
+        &[("elm/core", "2.0.0 <= v < 3.0.0")],
+    ),
+    (
+        "extra/corrupted",
+        "1.0.0",
+        &[("elm/core", "1.0.0 <= v < 2.0.0")],
+    ),
+];
+
+/// The package whose dependencies are solved.
+const PROJECT: &str = r#"{
+    "type": "package",
+    "name": "test/project",
+    "summary": "Project of the optional extras tests",
+    "license": "BSD-3-Clause",
+    "version": "1.0.0",
+    "exposed-modules": [],
+    "elm-version": "0.19.0 <= v < 0.20.0",
+    "dependencies": {
+        "elm/core": "1.0.0 <= v < 2.0.0"
+    },
+    "test-dependencies": {}
+}"#;
+
+/// Sandbox with the fixed registry installed, and the `elm.json` of `extra/corrupted` truncated.
+fn sandbox() -> Sandbox {
+    let sandbox = registry_sandbox(REGISTRY);
+    let packages_dir = paths::packages_dir(sandbox.elm_home(), "0.19.1");
+    let elm_json = packages_dir.join("extra/corrupted/1.0.0/elm.json");
+    std::fs::write(elm_json, "{\"type\":").unwrap();
+    sandbox
+}
+
+/// Optional extra accepting any version 1 of a package.
+fn optional_extra(pkg: &str) -> OptionalExtra {
+    OptionalExtra {
+        pkg: pkg.parse().unwrap(),
+        constraint: "1.0.0 <= v < 2.0.0".parse().unwrap(),
+    }
+}
+
+/// Solve the project offline with these optional extras.
+fn solve(
+    sandbox: &Sandbox,
+    optional_extras: &[OptionalExtra],
+) -> Result<OptionalSolution, Box<PubGrubError<Pkg, SemVer>>> {
+    let project: ProjectConfig = serde_json::from_str(PROJECT).unwrap();
+    let offline = sandbox.offline_solver();
+    solve_with_optional_extras(
+        &project,
+        false,
+        &[],
+        ExtraMode::default(),
+        optional_extras,
+        |project, use_test, extras, extra_mode| {
+            (offline.solve_deps(project, use_test, extras, extra_mode)).map_err(Box::new)
+        },
+        |err| err.code(),
+    )
+}
+
+#[test]
+fn optional_extras_without_solution_are_skipped() {
+    let sandbox = sandbox();
+    let extras = [
+        optional_extra("extra/conflicts"),
+        optional_extra("extra/fits"),
+    ];
+    let outcome = solve(&sandbox, &extras).unwrap();
+    assert_eq!(outcome.included, vec![optional_extra("extra/fits")]);
+    assert_eq!(outcome.skipped, vec![optional_extra("extra/conflicts")]);
+    let fits = outcome.solution.direct.get(&Pkg::new("extra", "fits"));
+    assert_eq!(fits, Some(&SemVer::new(1, 0, 0)));
+}
+
+#[test]
+fn other_failures_of_optional_extras_are_errors() {
+    let sandbox = sandbox();
+    let extras = [
+        optional_extra("extra/fits"),
+        optional_extra("extra/corrupted"),
+    ];
+    let err = solve(&sandbox, &extras).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::Json);
+}
@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Overrides fetched from git repositories, and read from local paths.
+//!
+//! The repository and ref of a git override are given by the user,
+//! so they must never be read as options of git.
+
+mod common;
+
+use std::process::Command;
+use std::str::FromStr;
+
+use common::package_config;
+use elm_solve_deps::overrides::{OverrideError, PackageOverride};
+use elm_solve_deps::project_config::Pkg;
+use elm_solve_deps::sandbox::Sandbox;
+use elm_solve_deps::semver::SemVer;
+
+/// Create a git repository of the `elm/parser` package at version 1.1.1, on a `fix` branch.
+fn parser_repository(sandbox: &Sandbox, name: &str) -> std::path::PathBuf {
+    let dir = sandbox.elm_home().join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = package_config("elm/parser", "1.1.1", &[]);
+    std::fs::write(dir.join("elm.json"), config.to_string()).unwrap();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@test", "-C"])
+            .arg(&dir)
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?}", args);
+    };
+    git(&["init", "--quiet", "--initial-branch", "fix"]);
+    git(&["add", "elm.json"]);
+    git(&["commit", "--quiet", "--message", "Fix"]);
+    dir
+}
+
+#[test]
+fn git_overrides_are_fetched() {
+    let sandbox = Sandbox::new("0.19.1").unwrap();
+    let repository = parser_repository(&sandbox, "parser");
+    let spec = format!("elm/parser=git+{}#fix", repository.display());
+    let fix = PackageOverride::from_str(&spec).unwrap();
+    let config = fix.load(sandbox.elm_home()).unwrap();
+    assert_eq!(config.name, Pkg::new("elm", "parser"));
+    assert_eq!(config.version, SemVer::new(1, 1, 1));
+}
+
+#[test]
+fn local_paths_may_contain_hashes() {
+    let sandbox = Sandbox::new("0.19.1").unwrap();
+    let repository = parser_repository(&sandbox, "parser#2");
+    let spec = format!("elm/parser={}", repository.display());
+    let local = PackageOverride::from_str(&spec).unwrap();
+    let config = local.load(sandbox.elm_home()).unwrap();
+    assert_eq!(config.version, SemVer::new(1, 1, 1));
+}
+
+#[test]
+fn repositories_are_not_options() {
+    let sandbox = Sandbox::new("0.19.1").unwrap();
+    let marker = sandbox.elm_home().join("injected");
+    let spec = format!(
+        "elm/parser=git+--upload-pack=touch {}#fix",
+        marker.display()
+    );
+    let injected = PackageOverride::from_str(&spec).unwrap();
+    let err = injected.load(sandbox.elm_home()).unwrap_err();
+    assert!(matches!(err, OverrideError::GitError { .. }), "{:?}", err);
+    assert!(!marker.exists());
+}
@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Package configs packed into a single file.
+//!
+//! Configs fetched again since they were packed must win over the packed ones,
+//! a truncated pack must be reported and removed by the doctor,
+//! and concurrent appends must never interleave their entries.
+
+mod common;
+
+use std::str::FromStr;
+
+use common::{package_config, registry_response, Entry};
+use elm_solve_deps::doctor::{diagnose, Problem};
+use elm_solve_deps::federation::MAIN_REMOTE;
+use elm_solve_deps::fetch::FetchError;
+use elm_solve_deps::packed::{self, PackedStore};
+use elm_solve_deps::pkg_version::PkgVersion;
+use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+use elm_solve_deps::sandbox::Sandbox;
+use elm_solve_deps::solver::{ExtraMode, Online, VersionStrategy};
+
+/// Package server of the fixed registry.
+const REMOTE: &str = "http://registry.test";
+
+/// Package versions of the fixed registry, with their dependencies.
+const REGISTRY: &[Entry] = &[
+    ("elm/core", "1.0.5", &[]),
+    ("elm/url", "1.0.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+    ("elm/json", "1.1.3", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+];
+
+/// The package whose dependencies are solved.
+const PROJECT: &str = r#"{
+    "type": "package",
+    "name": "test/project",
+    "summary": "Project of the packed store tests",
+    "license": "BSD-3-Clause",
+    "version": "1.0.0",
+    "exposed-modules": [],
+    "elm-version": "0.19.0 <= v < 0.20.0",
+    "dependencies": {
+        "elm/json": "1.0.0 <= v < 2.0.0"
+    },
+    "test-dependencies": {}
+}"#;
+
+/// Write the cached config of a package version into its own file.
+fn cache_config(sandbox: &Sandbox, (name, version, deps): &Entry) {
+    let pkg_version = PkgVersion::from_str(&format!("{}@{}", name, version)).unwrap();
+    let path = pkg_version.remote_cache_file(sandbox.elm_home(), MAIN_REMOTE);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, package_config(name, version, deps).to_string()).unwrap();
+}
+
+/// Sandbox whose cached configs of the fixed registry are packed.
+fn packed_sandbox() -> Sandbox {
+    let sandbox = Sandbox::new("0.19.1").unwrap();
+    REGISTRY
+        .iter()
+        .for_each(|entry| cache_config(&sandbox, entry));
+    let report = packed::pack(sandbox.elm_home()).unwrap();
+    assert_eq!(report.packed, REGISTRY.len());
+    sandbox
+}
+
+#[test]
+fn refreshed_configs_win_over_packed_ones() {
+    let sandbox = packed_sandbox();
+    // A config fetched again since, with a new dependency.
+    let refreshed: Entry = (
+        "elm/json",
+        "1.1.3",
+        &[
+            ("elm/core", "1.0.0 <= v < 2.0.0"),
+            ("elm/url", "1.0.0 <= v < 2.0.0"),
+        ],
+    );
+    cache_config(&sandbox, &refreshed);
+    // Configs are never fetched, only the package lists.
+    let fetch = |url: &str| -> Result<String, FetchError> {
+        if url.ends_with("elm.json") {
+            return Err(FetchError::NotFound);
+        }
+        registry_response(REMOTE, REGISTRY, url)
+    };
+    let strategy = VersionStrategy::Newest;
+    let online = Online::new(sandbox.offline_solver(), REMOTE, fetch, strategy).unwrap();
+    let project: ProjectConfig = serde_json::from_str(PROJECT).unwrap();
+    let solution = (online.solve_deps(&project, false, &[], ExtraMode::default()))
+        .map_err(Box::new)
+        .unwrap();
+    assert!(solution.indirect.contains_key(&Pkg::new("elm", "url")));
+}
+
+#[test]
+fn truncated_pack_is_fixed_by_the_doctor() {
+    let sandbox = packed_sandbox();
+    assert_eq!(diagnose(sandbox.elm_home(), "0.19.1"), Vec::new());
+    let store = PackedStore::open(sandbox.elm_home()).unwrap();
+    let data = std::fs::read(store.data_path()).unwrap();
+    std::fs::write(store.data_path(), &data[..data.len() / 2]).unwrap();
+
+    let problems = diagnose(sandbox.elm_home(), "0.19.1");
+    assert!(
+        matches!(problems.as_slice(), [Problem::CorruptedPack { .. }]),
+        "{:?}",
+        problems
+    );
+    assert!(problems[0].fix(sandbox.elm_home()).unwrap());
+    assert!(!store.data_path().exists() && !store.index_path().exists());
+    assert_eq!(diagnose(sandbox.elm_home(), "0.19.1"), Vec::new());
+}
+
+#[test]
+fn concurrent_appends_are_not_interleaved() {
+    let sandbox = Sandbox::new("0.19.1").unwrap();
+    let elm_home = sandbox.elm_home();
+    std::thread::scope(|scope| {
+        for writer in 0..4 {
+            scope.spawn(move || {
+                let mut store = PackedStore::open(elm_home).unwrap();
+                for patch in 0..50 {
+                    let version = format!("{}.0.{}", writer + 1, patch);
+                    let config = package_config("elm/core", &version, &[]).to_string();
+                    let pkg_version = PkgVersion::from_str(&format!("elm/core@{}", version));
+                    store
+                        .append(&pkg_version.unwrap(), config.as_bytes())
+                        .unwrap();
+                }
+            });
+        }
+    });
+    let store = PackedStore::open(elm_home).unwrap();
+    assert_eq!(store.len(), 200);
+    for pkg_version in store.versions() {
+        let config = store.load_config(pkg_version).map_err(Box::new).unwrap();
+        assert_eq!(config.version, pkg_version.version);
+    }
+}
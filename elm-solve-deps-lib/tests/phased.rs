@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Splitting a solution obtained with test dependencies into the two sections of `elm.json`.
+//!
+//! Packages reachable from the direct normal dependencies are normal dependencies,
+//! even when test dependencies also need them, and the other ones are test dependencies.
+
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::graph::DependencyGraph;
+use elm_solve_deps::phased::{self, PhasedSolution};
+use elm_solve_deps::project_config::{AppDependencies, Pkg, ProjectConfig};
+
+/// An application with a test dependency.
+const PROJECT: &str = r#"{
+    "type": "application",
+    "source-directories": ["src"],
+    "elm-version": "0.19.1",
+    "dependencies": {
+        "direct": { "elm/core": "1.0.5", "elm/html": "1.0.0" },
+        "indirect": {}
+    },
+    "test-dependencies": {
+        "direct": { "elm-explorations/test": "2.1.1" },
+        "indirect": {}
+    }
+}"#;
+
+fn any() -> Constraint {
+    "1.0.0 <= v < 2.0.0".parse().unwrap()
+}
+
+fn pkg(id: &str) -> Pkg {
+    id.parse().unwrap()
+}
+
+fn deps(pins: &[(&str, &str)]) -> AppDependencies {
+    let pins = pins.iter().map(|(p, v)| (pkg(p), v.parse().unwrap()));
+    AppDependencies {
+        direct: pins.clone().filter(|(p, _)| direct(p)).collect(),
+        indirect: pins.filter(|(p, _)| !direct(p)).collect(),
+    }
+}
+
+fn direct(pkg: &Pkg) -> bool {
+    ["elm/core", "elm/html", "elm-explorations/test"].contains(&pkg.to_string().as_str())
+}
+
+#[test]
+fn packages_reached_from_normal_dependencies_stay_normal() {
+    let project: ProjectConfig = serde_json::from_str(PROJECT).unwrap();
+    let mut graph = DependencyGraph::new();
+    graph.add(
+        pkg("elm/html"),
+        None,
+        [(pkg("elm/core"), any()), (pkg("elm/json"), any())],
+    );
+    graph.add(pkg("elm/json"), None, [(pkg("elm/core"), any())]);
+    graph.add(pkg("elm/random"), None, [(pkg("elm/core"), any())]);
+    let test_deps = ["elm/core", "elm/json", "elm/random"].map(|p| (pkg(p), any()));
+    graph.add(pkg("elm-explorations/test"), None, test_deps);
+    let solution = deps(&[
+        ("elm/core", "1.0.5"),
+        ("elm/html", "1.0.0"),
+        ("elm/json", "1.1.3"),
+        ("elm/random", "1.0.0"),
+        ("elm-explorations/test", "2.1.1"),
+    ]);
+    let expected = PhasedSolution {
+        dependencies: deps(&[
+            ("elm/core", "1.0.5"),
+            ("elm/html", "1.0.0"),
+            ("elm/json", "1.1.3"),
+        ]),
+        test_dependencies: deps(&[("elm/random", "1.0.0"), ("elm-explorations/test", "2.1.1")]),
+    };
+    assert_eq!(phased::split(&project, &solution, &graph), expected);
+}
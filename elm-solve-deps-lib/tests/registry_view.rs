@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Registry views of the solvers, against installed packages and a fixed registry.
+//!
+//! Resolutions look packages up like the views of their solvers,
+//! and only add their policies and provenance recording on top.
+
+mod common;
+
+use common::{registry_response, registry_sandbox, Entry};
+use elm_solve_deps::fetch::FetchError;
+use elm_solve_deps::policy::Policies;
+use elm_solve_deps::project_config::{Pkg, ProjectConfig};
+use elm_solve_deps::provenance::Source;
+use elm_solve_deps::registry_view::RegistryView;
+use elm_solve_deps::semver::SemVer;
+use elm_solve_deps::solver::{ExtraMode, Online, VersionStrategy};
+
+/// Package server of the fixed registry.
+const REMOTE: &str = "http://registry.test";
+
+/// Package versions of the fixed registry, with their dependencies.
+const REGISTRY: &[Entry] = &[
+    ("elm/core", "1.0.5", &[]),
+    ("view/a", "1.0.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+    ("view/a", "1.1.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+];
+
+/// Installed package versions.
+const INSTALLED: &[Entry] = &[
+    ("elm/core", "1.0.5", &[]),
+    ("view/a", "1.0.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+];
+
+/// The package whose dependencies are solved.
+const PROJECT: &str = r#"{
+    "type": "package",
+    "name": "test/project",
+    "summary": "Project of the registry view tests",
+    "license": "BSD-3-Clause",
+    "version": "1.0.0",
+    "exposed-modules": [],
+    "elm-version": "0.19.0 <= v < 0.20.0",
+    "dependencies": {
+        "elm/core": "1.0.0 <= v < 2.0.0",
+        "view/a": "1.0.0 <= v < 2.0.0"
+    },
+    "test-dependencies": {}
+}"#;
+
+fn registry_fetch(url: &str) -> Result<String, FetchError> {
+    registry_response(REMOTE, REGISTRY, url)
+}
+
+#[test]
+fn offline_view_ignores_policies_applied_by_resolutions() {
+    let sandbox = registry_sandbox(INSTALLED);
+    let view_a = Pkg::new("view", "a");
+    let mut policies = Policies::new();
+    policies.exclude(view_a.clone(), SemVer::new(1, 0, 0));
+    let offline = sandbox.offline_solver().with_policies(policies);
+    assert_eq!(offline.latest(&view_a).unwrap(), Some(SemVer::new(1, 0, 0)));
+    let config = offline.config(&view_a, SemVer::new(1, 0, 0)).unwrap();
+    assert_eq!(config.map(|c| c.version), Some(SemVer::new(1, 0, 0)));
+    assert!(offline
+        .config(&view_a, SemVer::new(1, 1, 0))
+        .unwrap()
+        .is_none());
+    let project: ProjectConfig = serde_json::from_str(PROJECT).unwrap();
+    let solved = offline.solve_deps(&project, false, &[], ExtraMode::default());
+    assert!(solved.is_err(), "The excluded version must not be picked");
+}
+
+#[test]
+fn online_view_finds_the_configs_of_resolutions_without_recording_them() {
+    let sandbox = registry_sandbox(INSTALLED);
+    let view_a = Pkg::new("view", "a");
+    let offline = sandbox.offline_solver();
+    let provenance = offline.provenance().clone();
+    let online = Online::new(offline, REMOTE, registry_fetch, VersionStrategy::Newest).unwrap();
+    let versions = online.versions(&view_a).unwrap();
+    assert_eq!(
+        versions.into_iter().collect::<Vec<_>>(),
+        [(1, 0, 0).into(), (1, 1, 0).into()]
+    );
+    let latest = online.config(&view_a, SemVer::new(1, 1, 0)).unwrap();
+    assert_eq!(latest.map(|c| c.version), Some(SemVer::new(1, 1, 0)));
+    assert_eq!(provenance.source(&view_a, SemVer::new(1, 1, 0)), None);
+
+    let project: ProjectConfig = serde_json::from_str(PROJECT).unwrap();
+    let solution = (online.solve_deps(&project, false, &[], ExtraMode::default()))
+        .map_err(Box::new)
+        .unwrap();
+    assert_eq!(solution.direct.get(&view_a), Some(&SemVer::new(1, 1, 0)));
+    // Fetched by the view, then found in the cache by the resolution.
+    let source = provenance.source(&view_a, SemVer::new(1, 1, 0));
+    assert_eq!(source, Some(Source::Cache));
+}
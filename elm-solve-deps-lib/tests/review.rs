@@ -0,0 +1,135 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Resolution of a review configuration agreeing with the project solution,
+//! when pinning a shared package changes the versions of other shared packages.
+//!
+//! The review configuration depends on `pin/w`, which accepts both versions of `pin/z`.
+//! The newest `pin/z` requires the newest `pin/x`, which the project does not use.
+//! Pinning `pin/x` to the project version falls back to the oldest `pin/z`,
+//! which accepts the newest `pin/y`, which the project does not use either.
+
+mod common;
+
+use std::str::FromStr;
+
+use common::Entry;
+use elm_solve_deps::constraint::Constraint;
+use elm_solve_deps::project_config::{AppDependencies, Pkg, ProjectConfig};
+use elm_solve_deps::range::Range;
+use elm_solve_deps::review::{solve_review, ReviewError};
+use elm_solve_deps::semver::SemVer;
+use elm_solve_deps::solver::ExtraMode;
+
+/// Package versions of the fixed registry, with their dependencies.
+const REGISTRY: &[Entry] = &[
+    ("pin/x", "1.0.0", &[]),
+    ("pin/x", "2.0.0", &[]),
+    ("pin/y", "1.0.0", &[]),
+    ("pin/y", "2.0.0", &[]),
+    (
+        "pin/z",
+        "1.0.0",
+        &[
+            ("pin/x", "1.0.0 <= v < 2.0.0"),
+            ("pin/y", "1.0.0 <= v < 3.0.0"),
+        ],
+    ),
+    (
+        "pin/z",
+        "2.0.0",
+        &[
+            ("pin/x", "2.0.0 <= v < 3.0.0"),
+            ("pin/y", "1.0.0 <= v < 2.0.0"),
+        ],
+    ),
+    ("pin/w", "1.0.0", &[("pin/z", "1.0.0 <= v < 3.0.0")]),
+];
+
+/// The review configuration.
+const REVIEW: &str = r#"{
+    "type": "application",
+    "source-directories": ["src"],
+    "elm-version": "0.19.1",
+    "dependencies": {
+        "direct": { "pin/w": "1.0.0" },
+        "indirect": {}
+    },
+    "test-dependencies": { "direct": {}, "indirect": {} }
+}"#;
+
+/// Versions of a solution, as `author/package@version`.
+fn versions(solution: &AppDependencies) -> Vec<String> {
+    (solution.direct.iter())
+        .chain(solution.indirect.iter())
+        .map(|(pkg, version)| format!("{}@{}", pkg, version))
+        .collect()
+}
+
+/// Solution of the project, using the oldest versions of `pin/x` and `pin/y`.
+fn project_solution() -> AppDependencies {
+    let mut solution = AppDependencies::default();
+    for (name, version) in [("pin/x", "1.0.0"), ("pin/y", "1.0.0")] {
+        let pkg = Pkg::from_str(name).unwrap();
+        solution
+            .direct
+            .insert(pkg, SemVer::from_str(version).unwrap());
+    }
+    solution
+}
+
+#[test]
+fn pinning_one_package_changes_another_one() {
+    let sandbox = common::registry_sandbox(REGISTRY);
+    let review_config: ProjectConfig = serde_json::from_str(REVIEW).unwrap();
+    let solver = sandbox.offline_solver();
+    let pins = [(
+        Pkg::new("pin", "x"),
+        Constraint(Range::exact(SemVer::new(1, 0, 0))),
+    )];
+    let pinned = solver.solve_deps(&review_config, false, &pins, ExtraMode::Override);
+    let pinned = versions(&pinned.unwrap());
+    assert!(pinned.contains(&"pin/y@2.0.0".to_string()), "{:?}", pinned);
+}
+
+#[test]
+fn review_solution_agrees_with_the_project() {
+    let sandbox = common::registry_sandbox(REGISTRY);
+    let review_config: ProjectConfig = serde_json::from_str(REVIEW).unwrap();
+    let solver = sandbox.offline_solver();
+    let solve = |project: &ProjectConfig, use_test, extras: &[_], extra_mode| {
+        (solver.solve_deps(project, use_test, extras, extra_mode)).map_err(Box::new)
+    };
+    let review = solve_review(&review_config, &project_solution(), solve).unwrap();
+    assert_eq!(
+        versions(&review),
+        ["pin/w@1.0.0", "pin/x@1.0.0", "pin/y@1.0.0", "pin/z@1.0.0"]
+    );
+    // Pins are not direct dependencies of the review configuration.
+    assert_eq!(review.direct.len(), 1);
+}
+
+#[test]
+fn incompatible_review_configuration_fails() {
+    let sandbox = common::registry_sandbox(REGISTRY);
+    let review_config: ProjectConfig = serde_json::from_str(REVIEW).unwrap();
+    let solver = sandbox.offline_solver();
+    let solve = |project: &ProjectConfig, use_test, extras: &[_], extra_mode| {
+        (solver.solve_deps(project, use_test, extras, extra_mode)).map_err(Box::new)
+    };
+    // Pinning pin/y falls back to the oldest pin/z, which rejects pin/x 2.0.0,
+    // and no version of pin/z accepts both pin/x 2.0.0 and pin/y 2.0.0.
+    let mut project = AppDependencies::default();
+    project
+        .direct
+        .insert(Pkg::new("pin", "x"), SemVer::new(2, 0, 0));
+    project
+        .direct
+        .insert(Pkg::new("pin", "y"), SemVer::new(2, 0, 0));
+    match solve_review(&review_config, &project, solve) {
+        Err(ReviewError::Incompatible { shared, .. }) => {
+            let shared: Vec<String> = shared.iter().map(|(pkg, _, _)| pkg.to_string()).collect();
+            assert_eq!(shared, ["pin/y", "pin/x"]);
+        }
+        other => panic!("Expected an incompatible review configuration: {:?}", other),
+    }
+}
@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Golden solutions of both tie-breaking rules over a fixed registry snapshot.
+//!
+//! The registry has two packages with the same number of versions,
+//! whose newest versions each require the oldest version of the other one.
+//! The package decided first thus gets its newest version,
+//! and the tie-breaking rule alone decides which one it is.
+//! Every resolution must take the same decisions, in the same order,
+//! as the golden ones of its rule.
+
+mod common;
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use common::{registry_sandbox, Entry};
+use elm_solve_deps::progress::{Progress, ProgressEvent};
+use elm_solve_deps::project_config::{AppDependencies, Pkg, ProjectConfig};
+use elm_solve_deps::sandbox::Sandbox;
+use elm_solve_deps::solver::{ExtraMode, TieBreak};
+
+/// Package versions of the fixed registry, with their dependencies.
+const REGISTRY: &[Entry] = &[
+    ("elm/core", "1.0.5", &[]),
+    ("tie/left", "1.0.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+    (
+        "tie/left",
+        "2.0.0",
+        &[
+            ("elm/core", "1.0.0 <= v < 2.0.0"),
+            ("tie/right", "1.0.0 <= v < 2.0.0"),
+        ],
+    ),
+    ("tie/right", "1.0.0", &[("elm/core", "1.0.0 <= v < 2.0.0")]),
+    (
+        "tie/right",
+        "2.0.0",
+        &[
+            ("elm/core", "1.0.0 <= v < 2.0.0"),
+            ("tie/left", "1.0.0 <= v < 2.0.0"),
+        ],
+    ),
+];
+
+/// The package whose dependencies are solved.
+const PROJECT: &str = r#"{
+    "type": "package",
+    "name": "test/project",
+    "summary": "Project of the tie-breaking tests",
+    "license": "BSD-3-Clause",
+    "version": "1.0.0",
+    "exposed-modules": [],
+    "elm-version": "0.19.0 <= v < 0.20.0",
+    "dependencies": {
+        "elm/core": "1.0.0 <= v < 2.0.0",
+        "tie/left": "1.0.0 <= v < 3.0.0",
+        "tie/right": "1.0.0 <= v < 3.0.0"
+    },
+    "test-dependencies": {}
+}"#;
+
+/// Golden solutions and decisions of each tie-breaking rule.
+const GOLDEN: &[(TieBreak, &[(&str, &str)])] = &[
+    (
+        TieBreak::Alphabetical,
+        &[
+            ("elm/core", "1.0.5"),
+            ("tie/left", "2.0.0"),
+            ("tie/right", "1.0.0"),
+        ],
+    ),
+    (
+        TieBreak::ReverseAlphabetical,
+        &[
+            ("elm/core", "1.0.5"),
+            ("tie/right", "2.0.0"),
+            ("tie/left", "1.0.0"),
+        ],
+    ),
+];
+
+/// Number of resolutions of each tie-breaking rule, with fresh solvers.
+const RUNS: usize = 5;
+
+/// Solve the project with a fresh solver,
+/// and return the solution with the packages decided, in order.
+fn solve(sandbox: &Sandbox, tie_break: TieBreak) -> (AppDependencies, Vec<(String, String)>) {
+    let project: ProjectConfig = serde_json::from_str(PROJECT).unwrap();
+    let decisions = Arc::new(Mutex::new(Vec::new()));
+    let recorded = decisions.clone();
+    let progress = Progress::new(move |event| {
+        if let ProgressEvent::Decided { pkg, version, .. } = event {
+            if pkg != &Pkg::new("test", "project") {
+                let decision = (pkg.to_string(), version.to_string());
+                recorded.lock().unwrap().push(decision);
+            }
+        }
+    });
+    let solver = (sandbox.offline_solver())
+        .with_tie_break(tie_break)
+        .with_progress(progress);
+    let solution = (solver.solve_deps(&project, false, &[], ExtraMode::default()))
+        .unwrap_or_else(|err| panic!("Failed to solve with {}: {}", tie_break, err));
+    let decisions = decisions.lock().unwrap().clone();
+    (solution, decisions)
+}
+
+#[test]
+fn solutions_match_the_golden_ones() {
+    let sandbox = registry_sandbox(REGISTRY);
+    for (tie_break, golden) in GOLDEN {
+        let (solution, decisions) = solve(&sandbox, *tie_break);
+        let solved: BTreeMap<String, String> = (solution.direct.iter())
+            .chain(solution.indirect.iter())
+            .map(|(pkg, version)| (pkg.to_string(), version.to_string()))
+            .collect();
+        let expected: BTreeMap<String, String> = (golden.iter())
+            .map(|(pkg, version)| (pkg.to_string(), version.to_string()))
+            .collect();
+        assert_eq!(solved, expected, "{}", tie_break);
+        let expected_decisions: Vec<(String, String)> = (golden.iter())
+            .map(|(pkg, version)| (pkg.to_string(), version.to_string()))
+            .collect();
+        assert_eq!(decisions, expected_decisions, "{}", tie_break);
+    }
+}
+
+#[test]
+fn decisions_are_deterministic() {
+    let sandbox = registry_sandbox(REGISTRY);
+    for tie_break in [TieBreak::Alphabetical, TieBreak::ReverseAlphabetical] {
+        let first = solve(&sandbox, tie_break);
+        for _ in 1..RUNS {
+            assert_eq!(solve(&sandbox, tie_break), first, "{}", tie_break);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Incremental updates of the versions cache from a fixed registry.
+//!
+//! The package server numbers package versions in publication order,
+//! and the versions cache asks for the ones after the last it knows.
+//! A cache without any version, even listing packages, must be resynchronized instead.
+
+mod common;
+
+use std::cell::RefCell;
+
+use common::{registry_response, Entry};
+use elm_solve_deps::fetch::FetchError;
+use elm_solve_deps::pkg_version::Cache;
+use elm_solve_deps::project_config::Pkg;
+
+/// Package server of the fixed registry.
+const REMOTE: &str = "http://registry.test";
+
+/// Package versions of the fixed registry, in publication order.
+const REGISTRY: &[Entry] = &[
+    ("elm/core", "1.0.0", &[]),
+    ("elm/json", "1.0.0", &[]),
+    ("elm/core", "1.0.5", &[]),
+];
+
+/// Update a versions cache from the fixed registry, and return the requested urls.
+fn update(cache: &mut Cache) -> Vec<String> {
+    let requests = RefCell::new(Vec::new());
+    let fetch = |url: &str| -> Result<String, FetchError> {
+        requests.borrow_mut().push(url.to_string());
+        registry_response(REMOTE, REGISTRY, url)
+    };
+    cache.update(REMOTE, &fetch).unwrap();
+    requests.into_inner()
+}
+
+#[test]
+fn empty_cache_is_resynchronized() {
+    let mut cache = Cache::new();
+    assert_eq!(update(&mut cache), [format!("{}/all-packages", REMOTE)]);
+    assert_eq!(cache.versions_count(), REGISTRY.len());
+}
+
+#[test]
+fn cache_of_packages_without_versions_is_resynchronized() {
+    let mut cache = Cache::new();
+    cache
+        .cache
+        .insert(Pkg::new("elm", "core"), Default::default());
+    assert_eq!(update(&mut cache), [format!("{}/all-packages", REMOTE)]);
+    assert_eq!(cache.versions_count(), REGISTRY.len());
+}
+
+#[test]
+fn up_to_date_cache_asks_for_newer_versions() {
+    let mut cache = Cache::new();
+    update(&mut cache);
+    let since = format!("{}/all-packages/since/{}", REMOTE, REGISTRY.len() - 1);
+    assert_eq!(update(&mut cache), [since]);
+    assert_eq!(cache.versions_count(), REGISTRY.len());
+}
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Polling of a watchlist against a fixed registry publishing new versions.
+//!
+//! The package server publishes a patch of an older major version of a watched package,
+//! and the first release of another watched package,
+//! which are both new even though neither is newer than the newest version seen.
+
+mod common;
+
+use common::{registry_response, Entry};
+use elm_solve_deps::clock::SystemClock;
+use elm_solve_deps::fetch::FetchError;
+use elm_solve_deps::pkg_version::{Cache, PkgVersion};
+use elm_solve_deps::project_config::Pkg;
+use elm_solve_deps::sandbox::Sandbox;
+use elm_solve_deps::watchlist::Watchlist;
+
+/// Package server of the fixed registry.
+const REMOTE: &str = "http://registry.test";
+
+/// Package versions of the fixed registry, in publication order.
+const REGISTRY: &[Entry] = &[
+    ("elm/core", "1.0.5", &[]),
+    ("watched/old", "1.0.0", &[]),
+    ("watched/old", "2.0.0", &[]),
+    // Published after the watchlist started.
+    ("watched/old", "1.0.1", &[]),
+    ("watched/new", "1.0.0", &[]),
+    ("elm/core", "1.0.6", &[]),
+];
+
+/// Number of package versions published when the watchlist started.
+const PUBLISHED_BEFORE: usize = 3;
+
+#[test]
+fn poll_reports_backports_and_first_releases() {
+    let sandbox = Sandbox::new("0.19.1").unwrap();
+    let elm_home = sandbox.elm_home();
+    let before = |url: &str| -> Result<String, FetchError> {
+        registry_response(REMOTE, &REGISTRY[..PUBLISHED_BEFORE], url)
+    };
+    let mut cache = Cache::new();
+    (cache.update_with_resync(elm_home, REMOTE, &before, &SystemClock)).unwrap();
+    cache.save(elm_home).unwrap();
+
+    let mut watchlist = Watchlist::new();
+    watchlist.watch(Pkg::new("watched", "old"));
+    watchlist.watch(Pkg::new("watched", "new"));
+    watchlist.mark_seen(&cache);
+
+    let after = |url: &str| registry_response(REMOTE, REGISTRY, url);
+    let mut notified = Vec::new();
+    let mut notify = |pkg_version: &PkgVersion| notified.push(pkg_version.to_string());
+    (watchlist.poll(elm_home, REMOTE, &after, &SystemClock, &mut notify)).unwrap();
+    assert_eq!(notified, ["watched/new@1.0.0", "watched/old@1.0.1"]);
+
+    // Nothing is new at the next poll.
+    let mut notified = Vec::new();
+    let mut notify = |pkg_version: &PkgVersion| notified.push(pkg_version.to_string());
+    (watchlist.poll(elm_home, REMOTE, &after, &SystemClock, &mut notify)).unwrap();
+    assert!(notified.is_empty());
+}